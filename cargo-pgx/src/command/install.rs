@@ -15,7 +15,7 @@ use cargo_toml::Manifest;
 use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx_utils::pg_config::PgConfig;
-use pgx_utils::{get_target_dir, versioned_so_name};
+use pgx_utils::{get_target_dir, LibraryNaming};
 use std::{
     io::BufReader,
     path::{Path, PathBuf},
@@ -115,6 +115,7 @@ pub(crate) fn install_extension(
     }
 
     let versioned_so = get_property(&package_manifest_path, "module_pathname")?.is_none();
+    let library_naming = LibraryNaming::from_manifest(&package_manifest_path)?;
 
     let build_command_output = build_extension(
         user_manifest_path.as_ref(),
@@ -149,17 +150,24 @@ pub(crate) fn install_extension(
             true,
             &package_manifest_path,
         )?;
+
+        // `module_pathname` names the library as `library-name` or `module-pathname` do
+        let control = std::fs::read_to_string(&dest)
+            .wrap_err_with(|| format!("failed to read `{}`", dest.display()))?;
+        std::fs::write(&dest, library_naming.control_file(&control))
+            .wrap_err_with(|| format!("failed writing `{}`", dest.display()))?;
     }
 
     {
         let mut dest = base_directory.clone();
         dest.push(&pkgdir);
-        let so_name = if versioned_so {
-            let extver = get_version(&package_manifest_path)?;
-            versioned_so_name(&extname, &extver)
+        // only a versioned library's name needs the version
+        let extver = if versioned_so {
+            get_version(&package_manifest_path)?
         } else {
-            extname.clone()
+            String::default()
         };
+        let so_name = library_naming.library_file_name(&extname, &extver, versioned_so);
         dest.push(format!("{}.so", so_name));
 
         if cfg!(target_os = "macos") {
@@ -343,6 +351,8 @@ fn copy_sql_files(
         Option::<String>::None,
         None,
         skip_build,
+        None,
+        false,
     )?;
 
     // now copy all the version upgrade files too
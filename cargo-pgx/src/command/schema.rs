@@ -20,7 +20,7 @@ use owo_colors::OwoColorize;
 use pgx_utils::{
     pg_config::{PgConfig, Pgx},
     sql_entity_graph::{PgxSql, RustSourceOnlySqlMapping, RustSqlMapping, SqlGraphEntity},
-    PgxPgSysStub,
+    LibraryNaming, PgxPgSysStub,
 };
 use std::{
     collections::HashSet,
@@ -66,6 +66,13 @@ pub(crate) struct Schema {
     /// Skip building a fresh extension shared object.
     #[clap(long)]
     skip_build: bool,
+    /// Explain why an entity exists and what is ordered around it, instead of printing SQL
+    #[clap(long)]
+    trace: Option<String>,
+    /// Output the `ALTER ... RENAME TO` statements renaming the entities marked `renamed_from`,
+    /// for an upgrade script, instead of the schema
+    #[clap(long)]
+    renames: bool,
 }
 
 impl CommandExecute for Schema {
@@ -125,6 +132,8 @@ impl CommandExecute for Schema {
             self.dot,
             log_level,
             self.skip_build,
+            self.trace.as_deref(),
+            self.renames,
         )
     }
 }
@@ -149,9 +158,11 @@ pub(crate) fn generate_schema(
     dot: Option<impl AsRef<std::path::Path>>,
     log_level: Option<String>,
     skip_build: bool,
+    trace: Option<&str>,
+    renames: bool,
 ) -> eyre::Result<()> {
     let manifest = Manifest::from_path(&package_manifest_path)?;
-    let (control_file, _extname) = find_control_file(&package_manifest_path)?;
+    let (control_file, extname) = find_control_file(&package_manifest_path)?;
     let package_name = &manifest
         .package
         .as_ref()
@@ -166,6 +177,7 @@ pub(crate) fn generate_schema(
     }
 
     let versioned_so = get_property(&package_manifest_path, "module_pathname")?.is_none();
+    let library_naming = LibraryNaming::from_manifest(&package_manifest_path)?;
 
     let flags = std::env::var("PGX_BUILD_FLAGS").unwrap_or_default();
 
@@ -308,10 +320,13 @@ pub(crate) fn generate_schema(
     let mut num_funcs = 0_usize;
     let mut num_types = 0_usize;
     let mut num_enums = 0_usize;
+    let mut num_composites = 0_usize;
     let mut num_sqls = 0_usize;
     let mut num_ords = 0_usize;
     let mut num_hashes = 0_usize;
     let mut num_aggregates = 0_usize;
+    let mut num_triggers = 0_usize;
+    let mut num_event_triggers = 0_usize;
     for func in &fns_to_call {
         if func.starts_with("__pgx_internals_schema_") {
             let schema = func
@@ -326,6 +341,8 @@ pub(crate) fn generate_schema(
             num_types += 1;
         } else if func.starts_with("__pgx_internals_enum_") {
             num_enums += 1;
+        } else if func.starts_with("__pgx_internals_composite_") {
+            num_composites += 1;
         } else if func.starts_with("__pgx_internals_sql_") {
             num_sqls += 1;
         } else if func.starts_with("__pgx_internals_ord_") {
@@ -334,11 +351,15 @@ pub(crate) fn generate_schema(
             num_hashes += 1;
         } else if func.starts_with("__pgx_internals_aggregate_") {
             num_aggregates += 1;
+        } else if func.starts_with("__pgx_internals_trigger_") {
+            num_triggers += 1;
+        } else if func.starts_with("__pgx_internals_event_trigger_") {
+            num_event_triggers += 1;
         }
     }
 
     eprintln!(
-        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} sqls, {} ords, {} hashes, {} aggregates",
+        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} composites, {} sqls, {} ords, {} hashes, {} aggregates, {} triggers, {} event triggers",
         "  Discovered".bold().green(),
         fns_to_call.len().to_string().bold().cyan(),
         seen_schemas.iter().count().to_string().bold().cyan(),
@@ -346,10 +367,13 @@ pub(crate) fn generate_schema(
         num_funcs.to_string().bold().cyan(),
         num_types.to_string().bold().cyan(),
         num_enums.to_string().bold().cyan(),
+        num_composites.to_string().bold().cyan(),
         num_sqls.to_string().bold().cyan(),
         num_ords.to_string().bold().cyan(),
         num_hashes.to_string().bold().cyan(),
         num_aggregates.to_string().bold().cyan(),
+        num_triggers.to_string().bold().cyan(),
+        num_event_triggers.to_string().bold().cyan(),
     );
 
     tracing::debug!("Collecting {} SQL entities", fns_to_call.len());
@@ -405,10 +429,44 @@ pub(crate) fn generate_schema(
         entities.into_iter(),
         package_name.to_string(),
         versioned_so,
+        library_naming,
     )
     .wrap_err("SQL generation error")?;
 
-    if let Some(out_path) = path {
+    // a `#[pg_composite]` outside a `#[pg_schema]` finds its type in the schema of the extension
+    // named after the crate
+    let crate_name = package_name.replace("-", "_");
+    if extname != crate_name {
+        if let Some(composite) = pgx_sql.composites.keys().find(|c| c.schema.is_none()) {
+            return Err(eyre!(
+                "{}: the extension `{}` isn't named after the crate `{}`, so composite type `{}` \
+                 must be in a `#[pg_schema]` module",
+                control_file.display(),
+                extname,
+                crate_name,
+                composite.full_path,
+            ));
+        }
+    }
+
+    if renames {
+        let renames_sql = pgx_sql.renames_sql().wrap_err("SQL generation error")?;
+        if let Some(out_path) = path {
+            let out_path = out_path.as_ref();
+            eprintln!(
+                "{} renames to {}",
+                "     Writing".bold().green(),
+                format_display_path(out_path)?.cyan()
+            );
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent).wrap_err("Could not create parent directory")?
+            }
+            std::fs::write(out_path, renames_sql)
+                .wrap_err_with(|| eyre!("Could not write SQL to {}", out_path.display()))?;
+        } else {
+            print!("{}", renames_sql);
+        }
+    } else if let Some(out_path) = path {
         let out_path = out_path.as_ref();
 
         eprintln!(
@@ -423,7 +481,7 @@ pub(crate) fn generate_schema(
         pgx_sql
             .to_file(out_path)
             .wrap_err_with(|| eyre!("Could not write SQL to {}", out_path.display()))?;
-    } else {
+    } else if trace.is_none() {
         eprintln!(
             "{} SQL entities to {}",
             "     Writing".bold().green(),
@@ -439,6 +497,15 @@ pub(crate) fn generate_schema(
         tracing::info!(dot = %dot_path.display(), "Writing Graphviz DOT");
         pgx_sql.to_dot(dot_path)?;
     }
+
+    if let Some(trace) = trace {
+        eprintln!(
+            "{} SQL entity `{}`",
+            "     Tracing".bold().green(),
+            trace.cyan()
+        );
+        print!("{}", pgx_sql.trace(trace)?);
+    }
     Ok(())
 }
 
@@ -15,17 +15,19 @@ pg_module_magic!();
 #[pg_extern]
 fn sq_euclid_pgx(a: Array<f32>, b: Array<f32>) -> f32 {
     a.as_slice()
+        .expect("not a real[]")
         .iter()
-        .zip(b.as_slice().iter())
+        .zip(b.as_slice().expect("not a real[]").iter())
         .map(|(a, b)| (a - b) * (a - b))
         .sum()
 }
 
 #[pg_extern(immutable, parallel_safe)]
 fn approx_distance_pgx(compressed: Array<i64>, distances: Array<f64>) -> f64 {
-    let distances = distances.as_slice();
+    let distances = distances.as_slice().expect("not a float8[]");
     compressed
         .as_slice()
+        .expect("not a bigint[]")
         .iter()
         .map(|cc| {
             let d = distances[*cc as usize];
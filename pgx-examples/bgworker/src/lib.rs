@@ -78,6 +78,14 @@ pub extern "C" fn background_worker_main(arg: pg_sys::Datum) {
                 });
             });
         });
+
+        // queries that might run for too long, or wait on a lock forever, can be given a timeout
+        let watchdog = SpiWatchdog::new(Duration::from_millis(500))
+            .set_lock_timeout(Duration::from_millis(100));
+        match watchdog.run(|| Spi::run("SELECT pg_sleep(1)")) {
+            Ok(()) => log!("from bgworker: pg_sleep(1) finished"),
+            Err(e) => log!("from bgworker: {}", e),
+        }
     }
 
     log!(
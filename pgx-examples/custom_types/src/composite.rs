@@ -0,0 +1,29 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_composite]
+pub struct Dog {
+    name: String,
+    scritches: Option<i32>,
+}
+
+#[pg_extern]
+fn scritch(dog: Dog) -> Dog {
+    Dog {
+        scritches: Some(dog.scritches.unwrap_or_default() + 1),
+        ..dog
+    }
+}
+
+#[pg_extern]
+fn scritch_all(dogs: Vec<Dog>) -> Vec<Dog> {
+    dogs.into_iter().map(scritch).collect()
+}
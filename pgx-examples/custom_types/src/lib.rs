@@ -10,6 +10,7 @@ Use of this source code is governed by the MIT license that can be found in the
 use pgx::*;
 
 mod complex;
+mod composite;
 mod fixed_size;
 mod generic_enum;
 mod hstore_clone;
@@ -37,6 +37,18 @@ static STRUCT: PgLwLock<Pgtest> = PgLwLock::new();
 static PRIMITIVE: PgLwLock<i32> = PgLwLock::new();
 static ATOMIC: PgAtomic<std::sync::atomic::AtomicBool> = PgAtomic::new();
 
+// `#[pg_shmem]` statics are set up by `pg_module_magic!()`, without needing `pg_shmem_init!()`
+#[pg_shmem]
+static COUNTER: PgLwLock<u64> = PgLwLock::new();
+
+// a spinlock is cheaper than a `PgLwLock` for critical sections of a few instructions
+#[pg_shmem]
+static SPIN_STRUCT: PgSpinLock<Pgtest> = PgSpinLock::new();
+
+// a histogram is updated without a lock, so every backend can record into it cheaply
+#[pg_shmem]
+static VEC_PUSH_LATENCY: PgHistogram = PgHistogram::new();
+
 #[pg_guard]
 pub extern "C" fn _PG_init() {
     pg_shmem_init!(VEC);
@@ -46,6 +58,19 @@ pub extern "C" fn _PG_init() {
     pg_shmem_init!(ATOMIC);
 }
 
+#[pg_extern]
+fn counter_increment() -> i64 {
+    let mut counter = COUNTER.exclusive();
+    *counter += 1;
+    *counter as i64
+}
+
+#[pg_extern]
+fn spin_struct_swap(value1: i32, value2: i32) -> Pgtest {
+    let mut spin_struct = SPIN_STRUCT.lock();
+    std::mem::replace(&mut *spin_struct, Pgtest { value1, value2 })
+}
+
 #[pg_extern]
 fn vec_select() -> impl Iterator<Item = Pgtest> {
     VEC.share()
@@ -70,9 +95,22 @@ fn vec_drain() -> impl Iterator<Item = Pgtest> {
 
 #[pg_extern]
 fn vec_push(value: Pgtest) {
-    VEC.exclusive()
-        .push(value)
-        .unwrap_or_else(|_| warning!("Vector is full, discarding update"));
+    VEC_PUSH_LATENCY.time(|| {
+        VEC.exclusive()
+            .push(value)
+            .unwrap_or_else(|_| warning!("Vector is full, discarding update"))
+    });
+}
+
+#[pg_extern]
+fn vec_push_latency() -> impl Iterator<
+    Item = (
+        name!(lower_bound_us, i64),
+        name!(upper_bound_us, i64),
+        name!(count, i64),
+    ),
+> {
+    VEC_PUSH_LATENCY.rows()
 }
 
 #[pg_extern]
@@ -0,0 +1,159 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx_utils::sql_entity_graph::{PgxArg, PgxAttribute};
+use proc_macro2::Ident;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Expr, Lit, Meta, NestedMeta, UnOp};
+
+/// The casts asked for by `#[pgx(casts)]` or `#[pgx(casts(int4))]` on a `PostgresEnum`
+///
+/// Casts to and from `text` aren't generated, as Postgres casts every type to and from `text`
+/// with its input and output functions already.
+#[derive(Default)]
+struct EnumCasts {
+    int4: bool,
+}
+
+impl EnumCasts {
+    fn from_attributes(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut casts = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args.iter() {
+                match arg {
+                    PgxArg::Path(path) if path.is_ident("casts") => casts.int4 = true,
+                    PgxArg::List(list) if list.path.is_ident("casts") => {
+                        for target in list.nested.iter() {
+                            match target {
+                                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("int4") => {
+                                    casts.int4 = true
+                                }
+                                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("text") => {
+                                    return Err(syn::Error::new(
+                                        path.span(),
+                                        "Postgres already casts enums to and from `text` by label",
+                                    ))
+                                }
+                                other => {
+                                    return Err(syn::Error::new(other.span(), "expected `int4`"))
+                                }
+                            }
+                        }
+                    }
+                    // other `#[pgx(...)]` arguments, such as `sql`, are handled elsewhere
+                    _ => (),
+                }
+            }
+        }
+        Ok(casts)
+    }
+}
+
+/// The ordinal given to a variant by its discriminant, `Nami = 1`
+fn ordinal(variant: &syn::Variant) -> syn::Result<i32> {
+    let missing = || {
+        syn::Error::new(
+            variant.span(),
+            "`#[pgx(casts(int4))]` needs every variant to have an explicit discriminant, such as \
+             `Nami = 1`, so its ordinal doesn't change as variants are added or reordered",
+        )
+    };
+    let (negative, lit) = match &variant.discriminant {
+        Some((_, Expr::Lit(expr))) => (false, &expr.lit),
+        Some((_, Expr::Unary(unary))) if matches!(unary.op, UnOp::Neg(_)) => match &*unary.expr {
+            Expr::Lit(expr) => (true, &expr.lit),
+            _ => return Err(missing()),
+        },
+        _ => return Err(missing()),
+    };
+    let value = match lit {
+        Lit::Int(int) => int.base10_parse::<i64>()?,
+        _ => return Err(missing()),
+    };
+    let value = if negative { -value } else { value };
+    i32::try_from(value)
+        .map_err(|_| syn::Error::new(lit.span(), "an `int4` ordinal must fit in an `i32`"))
+}
+
+/// Casts between a `PostgresEnum` and `int4`, as asked for by its `#[pgx(casts)]`
+///
+/// They're the generated `{enum}_to_int4()` and `{enum}_from_int4()` functions, made casts by
+/// `#[pg_cast]` so that the `CREATE CAST`s name the enum and functions by their schemas.
+pub(crate) fn impl_enum_casts(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let casts = EnumCasts::from_attributes(&ast.attrs)?;
+    if !casts.int4 {
+        return Ok(proc_macro2::TokenStream::new());
+    }
+
+    let enum_ident = &ast.ident;
+    let enum_name = enum_ident.to_string();
+    let variants = match &ast.data {
+        Data::Enum(e) => &e.variants,
+        _ => return Err(syn::Error::new(ast.span(), "expected enum")),
+    };
+
+    let to_int4 = Ident::new(
+        &format!("{}_to_int4", enum_name).to_lowercase(),
+        enum_ident.span(),
+    );
+    let from_int4 = Ident::new(
+        &format!("{}_from_int4", enum_name).to_lowercase(),
+        enum_ident.span(),
+    );
+
+    // renamed along with the enum they're named after
+    let renamed_from = PgxAttribute::str_value(&ast.attrs, "renamed_from")?;
+    let renamed_to_int4 = renamed_from.as_ref().map(|old| {
+        let old = format!("{}_to_int4", old.value()).to_lowercase();
+        quote! { , renamed_from = #old }
+    });
+    let renamed_from_int4 = renamed_from.as_ref().map(|old| {
+        let old = format!("{}_from_int4", old.value()).to_lowercase();
+        quote! { , renamed_from = #old }
+    });
+
+    let mut to_arms = proc_macro2::TokenStream::new();
+    let mut from_arms = proc_macro2::TokenStream::new();
+    for variant in variants {
+        let label = &variant.ident;
+        let ordinal = ordinal(variant)?;
+        to_arms.extend(quote! { #enum_ident::#label => #ordinal, });
+        from_arms.extend(quote! { #ordinal => #enum_ident::#label, });
+    }
+
+    Ok(quote! {
+        #[allow(non_snake_case)]
+        #[pg_cast(explicit, immutable, parallel_safe #renamed_to_int4)]
+        fn #to_int4(value: #enum_ident) -> i32 {
+            match value {
+                #to_arms
+            }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_cast(explicit, immutable, parallel_safe #renamed_from_int4)]
+        fn #from_int4(ordinal: i32) -> #enum_ident {
+            match ordinal {
+                #from_arms
+                _ => {
+                    pgx::ereport(
+                        pgx::PgLogLevel::ERROR,
+                        pgx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                        &format!("{} is not an ordinal of enum {}", ordinal, #enum_name),
+                        file!(),
+                        line!(),
+                        column!(),
+                    );
+                    unreachable!()
+                }
+            }
+        }
+    })
+}
@@ -9,13 +9,25 @@ Use of this source code is governed by the MIT license that can be found in the
 
 extern crate proc_macro;
 
+mod enum_casts;
 mod operators;
-use operators::{impl_postgres_eq, impl_postgres_hash, impl_postgres_ord};
+mod serialization;
+mod status_view;
+mod test_matrix;
+use enum_casts::impl_enum_casts;
+use operators::{
+    impl_postgres_brin_ops, impl_postgres_eq, impl_postgres_gin_ops, impl_postgres_gist_ops,
+    impl_postgres_hash, impl_postgres_ord,
+};
+use serialization::{impl_fixed_size_datum, impl_postgres_type_trait};
+use status_view::impl_postgres_status_view;
 
 use pgx_utils::rewriter::*;
 use pgx_utils::{
     sql_entity_graph::{
-        ExtensionSql, ExtensionSqlFile, PgAggregate, PgExtern, PostgresEnum, PostgresType, Schema,
+        ExtensionSql, ExtensionSqlFile, PgAggregate, PgCastArgs, PgEventTrigger, PgExtern,
+        PgOpClass, PgOperatorArgs, PgTrigger, PgxAttribute, PostgresComposite, PostgresEnum,
+        PostgresType, Schema,
     },
     *,
 };
@@ -28,6 +40,20 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Item, ItemFn, ItemImp
 
 /// Declare a function as `#[pg_guard]` to indicate that it is called from a Postgres `extern "C"`
 /// function so that Rust `panic!()`s (and Postgres `elog(ERROR)`s) will be properly handled by `pgx`
+///
+/// Apart from `_PG_init()` and `_PG_fini()`, the function isn't exported from the extension's
+/// library unless it also has `#[no_mangle]` (or `#[export_name]`), which is needed when Postgres
+/// looks it up by name, such as the main function of a background worker:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+///     // do bgworker stuff here
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn pg_guard(_attr: TokenStream, item: TokenStream) -> TokenStream {
     // get a usable token stream
@@ -41,9 +67,12 @@ pub fn pg_guard(_attr: TokenStream, item: TokenStream) -> TokenStream {
         Item::ForeignMod(block) => rewriter.extern_block(block).into(),
 
         // process top-level functions
-        // these functions get wrapped as public extern "C" functions with #[no_mangle] so they
-        // can also be called from C code
-        Item::Fn(func) => rewriter.item_fn(func, None, false, false, false).0.into(),
+        // these functions get wrapped as extern "C" functions, which are only exported with an
+        // explicit #[no_mangle] so they don't clash with the symbols of other extensions
+        Item::Fn(func) => rewriter
+            .item_fn(func, None, false, false, false, false, false, false)
+            .0
+            .into(),
         _ => {
             panic!("#[pg_guard] can only be applied to extern \"C\" blocks and top-level functions")
         }
@@ -52,10 +81,35 @@ pub fn pg_guard(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
 /// `#[pg_test]` functions are test functions (akin to `#[test]`), but they run in-process inside
 /// Postgres during `cargo pgx test`.
+///
+/// Besides `#[pg_extern]`'s arguments, such as `error = "message"` for a test which should raise
+/// that `ERROR`, `matrix(..)` runs the test once for every combination of the values of some
+/// settings, which are applied with `SET LOCAL` before each run:
+///
+/// ```rust,ignore
+/// use pgx::*;
+///
+/// #[pg_test(matrix(jit = [on, off], max_parallel_workers_per_gather = [0, 4]))]
+/// fn test_report_query() {
+///     // runs four times, as `pg_test_report_query_jit_on_max_parallel_workers_per_gather_0`, ...
+///     let workers = Spi::get_one::<String>("SHOW max_parallel_workers_per_gather").unwrap();
+///     assert!(workers == "0" || workers == "4");
+/// }
+/// ```
+///
+/// Values are identifiers, numbers, or string literals, and settings of extensions are named like
+/// `my_extension.setting`.  Only settings a session can change can be in a matrix.  Server-wide
+/// ones, such as `huge_pages`, belong in the `postgresql_conf_options()` of the `pg_test` module,
+/// and crate features are fixed when the extension is compiled, so those need a `cargo pgx test`
+/// of their own.
 #[proc_macro_attribute]
 pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut stream = proc_macro2::TokenStream::new();
-    let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
+    let (attr, matrix) = match test_matrix::take_test_matrix(attr.into()) {
+        Ok(taken) => taken,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let args = parse_extern_attributes(attr.clone());
 
     let mut expected_error = None;
     args.into_iter().for_each(|v| {
@@ -64,9 +118,10 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     });
 
-    stream.extend(proc_macro2::TokenStream::from(pg_extern(
-        attr,
+    stream.extend(proc_macro2::TokenStream::from(pg_extern_impl(
+        attr.into(),
         item.clone(),
+        false,
     )));
 
     let expected_error = match expected_error {
@@ -78,8 +133,6 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     match ast {
         Item::Fn(func) => {
             let sql_funcname = func.sig.ident.to_string();
-            let test_func_name =
-                Ident::new(&format!("pg_{}", func.sig.ident.to_string()), func.span());
 
             let attributes = func.attrs;
             let mut att_stream = proc_macro2::TokenStream::new();
@@ -91,20 +144,52 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                 });
             }
 
-            stream.extend(quote! {
-                #[test]
-                fn #test_func_name() {
-                    let mut options = Vec::new();
-                    #att_stream
-
-                    crate::pg_test::setup(options);
-                    let res = pgx_tests::run_test(#sql_funcname, #expected_error, crate::pg_test::postgresql_conf_options());
-                    match res {
-                        Ok(()) => (),
-                        Err(e) => panic!("{:?}", e)
+            if matrix.is_empty() {
+                let test_func_name =
+                    Ident::new(&format!("pg_{}", func.sig.ident.to_string()), func.span());
+                stream.extend(quote! {
+                    #[test]
+                    fn #test_func_name() {
+                        let mut options = Vec::new();
+                        #att_stream
+
+                        crate::pg_test::setup(options);
+                        let res = pgx_tests::run_test(#sql_funcname, #expected_error, crate::pg_test::postgresql_conf_options());
+                        match res {
+                            Ok(()) => (),
+                            Err(e) => panic!("{:?}", e)
+                        }
                     }
+                });
+            } else {
+                for instance in test_matrix::instances(&matrix) {
+                    let test_func_name = Ident::new(
+                        &format!(
+                            "pg_{}_{}",
+                            func.sig.ident.to_string(),
+                            test_matrix::instance_suffix(&instance)
+                        ),
+                        func.span(),
+                    );
+                    let settings = instance
+                        .iter()
+                        .map(|(name, value)| quote! { (#name, #value) });
+                    stream.extend(quote! {
+                        #[test]
+                        fn #test_func_name() {
+                            let mut options = Vec::new();
+                            #att_stream
+
+                            crate::pg_test::setup(options);
+                            let res = pgx_tests::run_test_with_settings(#sql_funcname, #expected_error, &[#(#settings),*], crate::pg_test::postgresql_conf_options());
+                            match res {
+                                Ok(()) => (),
+                                Err(e) => panic!("{:?}", e)
+                            }
+                        }
+                    });
                 }
-            });
+            }
         }
 
         _ => panic!("#[pg_test] can only be applied to top-level functions"),
@@ -120,11 +205,41 @@ pub fn initialize(_attr: TokenStream, item: TokenStream) -> TokenStream {
     item
 }
 
-/// Declare a function as `#[pg_operator]` to indicate that it represents a Postgres operator
-/// `cargo pgx schema` will automatically generate the underlying SQL
+/**
+Declare a function as `#[pg_operator]` to indicate that it represents a Postgres operator
+`cargo pgx schema` will automatically generate the underlying SQL
+
+The operator is named, and described, either by the arguments of `#[pg_operator]`, or by the
+companion `#[opname]`, `#[commutator]`, `#[negator]`, `#[restrict]`, `#[join]`, `#[hashes]` and
+`#[merges]` attributes:
+
+```rust,ignore
+use pgx::*;
+
+#[pg_operator(immutable, parallel_safe, symbol = "<->", commutator = "<->")]
+fn point_distance(left: Point, right: Point) -> f64 {
+    ((left.x - right.x).powi(2) + (left.y - right.y).powi(2)).sqrt()
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(<->)]
+#[commutator(<->)]
+fn also_point_distance(left: Point, right: Point) -> f64 {
+    point_distance(left, right)
+}
+```
+
+`symbol`, `commutator`, `negator`, `restrict` and `join` take strings, and `hashes` and `merges` are
+flags.  Any other arguments are the same as [`#[pg_extern]`](macro@pg_extern)'s.  An operator
+class for the operators, which makes the type indexable, is created with
+[`#[pg_opclass]`](macro@pg_opclass).
+*/
 #[proc_macro_attribute]
 pub fn pg_operator(attr: TokenStream, item: TokenStream) -> TokenStream {
-    pg_extern(attr, item)
+    let args = parse_macro_input!(attr as PgOperatorArgs);
+    let mut func = parse_macro_input!(item as ItemFn);
+    func.attrs.extend(args.attrs);
+    pg_extern(args.extern_args.into(), func.into_token_stream().into())
 }
 
 /// Used with `#[pg_operator]`.  1 value which is the operator name itself
@@ -169,6 +284,52 @@ pub fn merges(_attr: TokenStream, item: TokenStream) -> TokenStream {
     item
 }
 
+/**
+Declare a function as `#[pg_cast]` to indicate that it casts the type of its argument to the type
+it returns.  `cargo pgx schema` will generate the function, as for `#[pg_extern]`, and the
+`CREATE CAST` using it:
+
+```rust,ignore
+use pgx::*;
+
+#[pg_cast(implicit, immutable, parallel_safe)]
+fn celsius_to_fahrenheit(celsius: Celsius) -> Fahrenheit {
+    Fahrenheit(celsius.0 * 9.0 / 5.0 + 32.0)
+}
+```
+
+The first argument is where Postgres may apply the cast without it being written out:
+
+* `explicit`, the default, only as `CAST(x AS type)` or `x::type`
+* `assignment`, also when the value is assigned to a column, as in an `INSERT` or `UPDATE`
+* `implicit`, also in any other expression
+
+Any other arguments are the same as [`#[pg_extern]`](macro@pg_extern)'s.  As Postgres allows, the
+function may take the `i32` type modifier of the type it casts to, and a `bool` of whether the cast
+is explicit, after the value to cast.
+*/
+#[proc_macro_attribute]
+pub fn pg_cast(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as PgCastArgs);
+    let mut func = parse_macro_input!(item as ItemFn);
+    func.attrs.extend(args.attrs);
+    pg_extern(args.extern_args.into(), func.into_token_stream().into())
+}
+
+/// Used with `#[pg_cast]`.  1 value which is `implicit`, `assignment` or `explicit`
+#[doc(hidden)]
+#[proc_macro_attribute]
+pub fn cast_context(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+/// Used by `pg_magic_func!()`.  Fails to compile if the crate's `[package.metadata.pgx]` is invalid
+#[doc(hidden)]
+#[proc_macro]
+pub fn check_library_naming(_input: TokenStream) -> TokenStream {
+    LibraryNaming::current_error().unwrap_or_default().into()
+}
+
 /**
 Declare a Rust module and its contents to be in a schema.
 
@@ -411,12 +572,30 @@ Optionally accepts the following attributes:
 * `parallel_safe`: Corresponds to [`PARALLEL SAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_unsafe`: Corresponds to [`PARALLEL UNSAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_restricted`: Corresponds to [`PARALLEL RESTRICTED`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `window`: Corresponds to [`WINDOW`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + Arguments must be `Option<T>`s, and are read through a `pgx::WindowFunctionContext` argument.
+* `cost = N`: Corresponds to [`COST`](https://www.postgresql.org/docs/current/sql-createfunction.html), the estimated cost of a call, in units of `cpu_operator_cost`.
+* `rows = N`: Corresponds to [`ROWS`](https://www.postgresql.org/docs/current/sql-createfunction.html), the estimated number of rows a set-returning function returns.
+* `support = path`: Corresponds to [`SUPPORT`](https://www.postgresql.org/docs/current/xfunc-optimization.html) (Postgres 12+), naming the `#[pg_extern]` planner support function, which takes and returns `internal`.
+  + Its requests can be answered with `pgx::planner_support`, such as to simplify calls with constant arguments, or estimate selectivity or rows.
 * `no_guard`: Do not use `#[pg_guard]` with the function.
+* `log_slow`: Log calls which take longer than the extension's threshold setting, with their arguments (see `pgx::SlowCalls`).
+* `spi_read_only`: Run every query the function makes through `Spi` read-only, including with `Spi::run` and `Spi::get_one`, until it returns, as Postgres expects of `immutable` and `stable` functions.
+* `primary_only`: Raise `cannot execute name() during recovery` when called on a standby, before the function runs, for functions which write (see `pgx::recovery`).
+  + Functions without it are assumed safe to run on a hot standby, as reading is.
+* `check_volatility`: Fail to compile an `immutable` or `stable` function whose body obviously needs more volatility.
+  + Such as reading the database through `Spi` in an `immutable` function, or reading the current time or random values in a `stable` one.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `body`: Define the function in SQL with this source, rather than calling the Rust function (see [below](#other-languages)).
+* `language`: The [`LANGUAGE`](https://www.postgresql.org/docs/current/sql-createfunction.html) of the `body`, defaults to `"sql"`.
+* `renamed_from`: The SQL name the function was created with before, which `cargo pgx schema --renames` renames it from in an upgrade script, rather than dropping it.
 
 Functions can accept and return any type which `pgx` supports. `pgx` supports many PostgreSQL types by default.
 New types can be defined via [`macro@PostgresType`] or [`macro@PostgresEnum`].
 
+A function may also return a `Result<T, E>`, which returns the `T`, or raises the `E` as an `ERROR`
+with the `SQLSTATE`, detail, and hint of the `pgx::ErrorReport` from its `pgx::IntoErrorReport`.
+
 
 Without any arguments or returns:
 ```rust,ignore
@@ -512,6 +691,26 @@ It accepts 2 arguments:
 * A name, such as `example`
 * A type
 
+When the columns aren't known until the function is called, it can return `SETOF record` as an
+iterator of `pgx::TableRow`s instead.  Its caller gives the columns with a column definition list,
+`SELECT * FROM dynamic() AS t(a integer, b text)`, and each row's values are matched up with them
+by position or by name:
+
+```rust,ignore
+use pgx::*;
+#[pg_extern]
+fn dynamic() -> impl Iterator<Item = TableRow> {
+    (1..=3).map(|i| TableRow::new().with("a", i).with("b", i.to_string()))
+}
+```
+
+Set returning functions use Postgres' "value per call" mode: the iterator is made on the first call,
+kept in the function's multi-call memory context, and advanced once for each row Postgres asks for.
+It's never collected up front, so a function called in a `SELECT` list stops as soon as Postgres
+has all the rows it needs, such as under a `LIMIT`.  The iterator is dropped when it's done, when
+the query ends early, or when the function is rescanned, in which case it's made again by a new
+first call.  Postgres itself collects all the rows of a function called in a `FROM` clause.
+
 # Special Cases
 
 `pg_sys::Oid` is a special cased type alias, in order to use it as an argument or return it must be
@@ -531,16 +730,83 @@ fn example_return() -> pg_sys::Oid {
 }
 ```
 
+# Other languages
+
+With a `body`, the function is declared in SQL (or the `language` given) instead of calling the
+Rust function, while its arguments, defaults, and return type still come from the Rust signature.
+This is useful for thin wrappers, such as overloads or adapters, which would otherwise need
+hand-written `extension_sql!`.  `requires` orders it after the functions it calls.
+
+The Rust function itself is left as it is, and can still be called from Rust.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_extern(immutable)]
+fn add_two_numbers(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[pg_extern(immutable, body = "SELECT add_two_numbers(a, 1)", requires = [add_two_numbers])]
+fn add_one(a: i32) -> i32 {
+    add_two_numbers(a, 1)
+}
+
+#[pg_extern(language = "plpgsql", body = "BEGIN RETURN add_two_numbers(a, a); END", requires = [add_two_numbers])]
+fn double(a: i32) -> i32 {
+    add_two_numbers(a, a)
+}
+```
+
+# Many arguments
+
+A function with more arguments than Postgres allows, or more than are practical to pass
+positionally, can take them packed into a single `jsonb` object with
+[`#[pg_extern_packed]`](macro@pg_extern_packed).
+
+# Smoke tests
+
+When a crate enables its `pg_smoke_test` feature (along with `pg_test`), every `#[pg_extern]` whose
+arguments are all `Option`s or have `default!()` values also gets a `#[test]`, which calls its SQL
+function with those arguments `NULL` or defaulted, and fails if that raises an `ERROR`.  This
+catches a wrapper or schema which has drifted from the Rust function, without writing a
+`#[pg_test]` for each.
+
+```toml
+[features]
+pg_test = []
+pg_smoke_test = []
+```
+
+```bash
+cargo pgx test --features pg_smoke_test
+```
 */
 #[proc_macro_attribute]
 pub fn pg_extern(attr: TokenStream, item: TokenStream) -> TokenStream {
+    pg_extern_impl(attr, item, true)
+}
+
+fn pg_extern_impl(attr: TokenStream, item: TokenStream, smoke_test: bool) -> TokenStream {
     let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
 
     let sql_graph_entity_item = PgExtern::new(attr.clone().into(), item.clone().into()).unwrap();
 
     let ast = parse_macro_input!(item as syn::Item);
     match ast {
-        Item::Fn(func) => rewrite_item_fn(func, args, &sql_graph_entity_item).into(),
+        // Postgres runs the `body` instead, so the Rust function is left as it is
+        Item::Fn(func) if sql_graph_entity_item.body().is_some() => quote! {
+            #func
+            #sql_graph_entity_item
+        }
+        .into(),
+        Item::Fn(func) => {
+            let mut stream = rewrite_item_fn(func, args, &sql_graph_entity_item);
+            if smoke_test {
+                stream.extend(sql_graph_entity_item.smoke_test());
+            }
+            stream.into()
+        }
         _ => panic!("#[pg_extern] can only be applied to top-level functions"),
     }
 }
@@ -552,11 +818,16 @@ fn rewrite_item_fn(
 ) -> proc_macro2::TokenStream {
     let is_raw = extern_args.contains(&ExternArgs::Raw);
     let no_guard = extern_args.contains(&ExternArgs::NoGuard);
+    let log_slow = extern_args.contains(&ExternArgs::LogSlow);
+    let primary_only = extern_args.contains(&ExternArgs::PrimaryOnly);
+    let read_only = extern_args.contains(&ExternArgs::SpiReadOnly);
 
     let finfo_name = syn::Ident::new(
         &format!("pg_finfo_{}_wrapper", func.sig.ident),
         Span::call_site(),
     );
+    let finfo_export =
+        LibraryNaming::current().finfo_export_attribute(&format!("{}_wrapper", func.sig.ident));
 
     // use the PgGuardRewriter to go ahead and wrap the function here, rather than applying
     // a #[pg_guard] macro to the original function.  This is necessary so that compiler
@@ -572,11 +843,14 @@ fn rewrite_item_fn(
         true,
         is_raw,
         no_guard,
+        log_slow,
+        primary_only,
+        read_only,
     );
 
     if need_wrapper {
         quote_spanned! {func_span=>
-            #[no_mangle]
+            #finfo_export
             #[doc(hidden)]
             pub extern "C" fn #finfo_name() -> &'static pg_sys::Pg_finfo_record {
                 const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
@@ -607,6 +881,34 @@ enum DogNames {
 }
 ```
 
+Casts to and from `int4` can be generated with `#[pgx(casts)]`, or `#[pgx(casts(int4))]`:
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
+use pgx::*;
+#[derive(Debug, PostgresEnum)]
+#[pgx(casts)]
+enum DogNames {
+    Nami = 1,
+    Brandy = 2,
+}
+```
+
+They're explicit, `'Nami'::DogNames::int4`, and go through the generated `dognames_to_int4()` and
+`dognames_from_int4()` functions.  There are no `text` casts to generate, as Postgres already
+casts every type to and from `text` with its input and output functions, by label for an enum.
+
+An `int4` cast is by ordinal, which is the variant's discriminant rather than its position, so
+every variant needs an explicit one.  Ordinals then don't change as variants are added or
+reordered, and values stored as them stay meaningful across versions of the extension.  Casting
+an `int4` that isn't the ordinal of a variant raises an error.
+
+Like a [`macro@PostgresType`], it can be marked `#[pgx(conflict_arbiter)]` to check that it can
+be in a unique index.
+
+When it's renamed, `#[pgx(renamed_from = "OldName")]` has `cargo pgx schema --renames` rename it
+in an upgrade script, keeping the columns of the enum, rather than dropping it.
+
 */
 #[proc_macro_derive(PostgresEnum, attributes(requires, pgx))]
 pub fn postgres_enum(input: TokenStream) -> TokenStream {
@@ -618,6 +920,7 @@ pub fn postgres_enum(input: TokenStream) -> TokenStream {
 fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
     let mut stream = proc_macro2::TokenStream::new();
     let sql_graph_entity_ast = ast.clone();
+    let casts_ast = ast.clone();
     let enum_ident = ast.ident;
     let enum_name = enum_ident.to_string();
 
@@ -672,6 +975,11 @@ fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
     let sql_graph_entity_item = PostgresEnum::from_derive_input(sql_graph_entity_ast).unwrap();
     sql_graph_entity_item.to_tokens(&mut stream);
 
+    match impl_enum_casts(&casts_ast) {
+        Ok(casts) => stream.extend(casts),
+        Err(e) => stream.extend(e.to_compile_error()),
+    }
+
     stream
 }
 
@@ -693,11 +1001,72 @@ Optionally accepts the following attributes:
 
 * `inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the type.
 * `pgvarlena_inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the `PgVarlena` of this type.
+* `sendrecvfuncs`: Give the type binary send/receive functions too, from its `pgx::SendRecvFuncs`
+  implementation, for clients using the binary format and `COPY ... (FORMAT binary)`.
+  `sendrecvfuncs(cbor)` sends and receives values as CBOR instead, with `serde`.
+  `pgx::IoContext::current()` tells any of the type's functions which format it's converting.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `conflict_arbiter`: As `#[pgx(conflict_arbiter)]`, check when the schema is generated that the
+  type can be in a unique index, so it can be the arbiter of `INSERT ... ON CONFLICT`.  That needs
+  the `=` operator from [`macro@PostgresEq`] and the btree operator class from [`macro@PostgresOrd`].
+* `serialize`: As `#[pgx(serialize = "json")]`, the format values are stored in on disk: `"cbor"`,
+  the default, `"json"`, `"bincode"` with pgx's `bincode` feature, or the path of a type
+  implementing `pgx::SerializationFormat`.
+* `version`: As `#[pgx(version = 2)]`, the version of the type's layout, which is stored before
+  each value, so values stored by older versions can still be read.
+* `migrate`: As `#[pgx(migrate = upgrade_dog)]`, a `fn(version: u16, bytes: &[u8]) -> Self` reading
+  values stored by an older `version`, or before the type had one, which are version 0.
+  Values stored as CBOR or JSON before the type had a version have nothing in front of them.
+  `"bincode"` and other formats might start a value with any byte, so they always store version 0
+  in front of values of a type without a version, and a version can be added later all the same.
+* `renamed_from`: As `#[pgx(renamed_from = "Doggo")]`, the SQL name the type was created with
+  before, which `cargo pgx schema --renames` renames it from in an upgrade script, along with its
+  input and output functions.
+* `storage`: As `#[pgx(storage = "external")]`, the type's [`STORAGE`](https://www.postgresql.org/docs/current/storage-toast.html)
+  strategy for large values: `"plain"`, `"extended"` (the default), `"main"` or `"external"`.
+  Values stored `"external"` aren't compressed, so parts of them can be read without fetching the
+  rest, with `pgx::varlena::detoast_slice`.
+* `fixed_size`: As `#[pgx(fixed_size)]`, store a `Copy` type as its own bytes, with a fixed
+  `INTERNALLENGTH` and the `ALIGNMENT` of the Rust type, rather than serializing it into a varlena.
+  It must be a `#[repr(C)]` or `#[repr(transparent)]` struct without any padding, which is checked
+  at compile time, and it needs `#[inoutfuncs]`, as it isn't converted to JSON.
+* `passedbyvalue`: As `#[pgx(passedbyvalue)]`, a `fixed_size` type of 1, 2, 4 or 8 bytes which is
+  passed in the `Datum` itself, as Postgres' own integers are, so it's never allocated.
+
+Once values are stored, changing the format or the fields of the type means giving it a new
+version, and reading the old values with `migrate`:
+
+```rust,ignore
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct DogV1 {
+    treats_recieved: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PostgresType)]
+#[pgx(serialize = "json", version = 2, migrate = upgrade_dog)]
+struct Dog {
+    treats_recieved: i64,
+    pets_gotten: i64,
+}
+
+fn upgrade_dog(version: u16, bytes: &[u8]) -> Dog {
+    match version {
+        // stored as CBOR, before `Dog` had a version
+        0 => {
+            let dog: DogV1 = pgx::Cbor::decode(bytes);
+            Dog { treats_recieved: dog.treats_recieved, pets_gotten: 0 }
+        }
+        _ => panic!("unknown version {} of Dog", version),
+    }
+}
+```
 */
 #[proc_macro_derive(
     PostgresType,
-    attributes(inoutfuncs, pgvarlena_inoutfuncs, requires, pgx)
+    attributes(inoutfuncs, pgvarlena_inoutfuncs, sendrecvfuncs, requires, pgx)
 )]
 pub fn postgres_type(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::DeriveInput);
@@ -720,6 +1089,10 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
         _ => panic!("#[derive(PostgresType)] can only be applied to structs"),
     }
 
+    // the binary functions are in addition to whichever text ones the type has
+    let send_recv = args.remove(&PostgresTypeAttribute::SendRecvFuncs);
+    let cbor_send_recv = args.remove(&PostgresTypeAttribute::CborSendRecvFuncs);
+
     if args.is_empty() {
         // assume the user wants us to implement the InOutFuncs
         args.insert(PostgresTypeAttribute::Default);
@@ -731,8 +1104,27 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
     };
 
     // all #[derive(PostgresType)] need to implement that trait
-    stream.extend(quote! {
-        impl #generics pgx::PostgresType for #name #generics { }
+    match impl_postgres_type_trait(&ast) {
+        Ok(postgres_type) => stream.extend(postgres_type),
+        Err(e) => return e.to_compile_error(),
+    }
+    match impl_fixed_size_datum(&ast) {
+        Ok(datum) => stream.extend(datum),
+        Err(e) => return e.to_compile_error(),
+    }
+
+    // the _in and _out functions are named after the type, so they're renamed along with it
+    let renamed_from = match PgxAttribute::str_value(&ast.attrs, "renamed_from") {
+        Ok(renamed_from) => renamed_from,
+        Err(e) => return e.to_compile_error(),
+    };
+    let renamed_in = renamed_from.as_ref().map(|old| {
+        let old = format!("{}_in", old.value()).to_lowercase();
+        quote! { , renamed_from = #old }
+    });
+    let renamed_out = renamed_from.as_ref().map(|old| {
+        let old = format!("{}_out", old.value()).to_lowercase();
+        quote! { , renamed_from = #old }
     });
 
     // and if we don't have custom inout/funcs, we use the JsonInOutFuncs trait
@@ -748,14 +1140,16 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
             impl #generics JsonInOutFuncs #inout_generics for #name #generics {}
 
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_in)]
             pub fn #funcname_in #generics(input: &#lifetime pgx::cstr_core::CStr) -> #name #generics {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Input);
                 #name::input(input)
             }
 
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_out)]
             pub fn #funcname_out #generics(input: #name #generics) -> &#lifetime pgx::cstr_core::CStr {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Output);
                 let mut buffer = StringInfo::new();
                 input.output(&mut buffer);
                 buffer.into()
@@ -766,14 +1160,16 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
         // otherwise if it's InOutFuncs our _in/_out functions use an owned type instance
         stream.extend(quote! {
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_in)]
             pub fn #funcname_in #generics(input: &#lifetime pgx::cstr_core::CStr) -> #name #generics {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Input);
                 #name::input(input)
             }
 
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_out)]
             pub fn #funcname_out #generics(input: #name #generics) -> &#lifetime pgx::cstr_core::CStr {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Output);
                 let mut buffer = StringInfo::new();
                 input.output(&mut buffer);
                 buffer.into()
@@ -783,14 +1179,16 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
         // otherwise if it's PgVarlenaInOutFuncs our _in/_out functions use a PgVarlena
         stream.extend(quote! {
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_in)]
             pub fn #funcname_in #generics(input: &#lifetime pgx::cstr_core::CStr) -> pgx::PgVarlena<#name #generics> {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Input);
                 #name::input(input)
             }
 
             #[doc(hidden)]
-            #[pg_extern(immutable,parallel_safe)]
+            #[pg_extern(immutable,parallel_safe #renamed_out)]
             pub fn #funcname_out #generics(input: pgx::PgVarlena<#name #generics>) -> &#lifetime pgx::cstr_core::CStr {
+                let _context = pgx::IoContext::enter(pgx::IoFormat::Text, pgx::IoDirection::Output);
                 let mut buffer = StringInfo::new();
                 input.output(&mut buffer);
                 buffer.into()
@@ -798,6 +1196,63 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
         });
     }
 
+    if send_recv || cbor_send_recv {
+        let send_recv_funcs = if cbor_send_recv {
+            stream.extend(quote! {
+                impl #generics pgx::CborSendRecvFuncs for #name #generics {}
+            });
+            quote! { pgx::CborSendRecvFuncs }
+        } else {
+            quote! { pgx::SendRecvFuncs }
+        };
+        let funcname_send = Ident::new(&format!("{}_send", name).to_lowercase(), name.span());
+        let funcname_recv = Ident::new(&format!("{}_recv", name).to_lowercase(), name.span());
+        let renamed_send = renamed_from.as_ref().map(|old| {
+            let old = format!("{}_send", old.value()).to_lowercase();
+            quote! { , renamed_from = #old }
+        });
+        let renamed_recv = renamed_from.as_ref().map(|old| {
+            let old = format!("{}_recv", old.value()).to_lowercase();
+            quote! { , renamed_from = #old }
+        });
+
+        if args.contains(&PostgresTypeAttribute::PgVarlenaInOutFuncs) {
+            stream.extend(quote! {
+                #[doc(hidden)]
+                #[pg_extern(immutable,parallel_safe #renamed_send)]
+                pub fn #funcname_send #generics(input: pgx::PgVarlena<#name #generics>) -> Vec<u8> {
+                    let _context = pgx::IoContext::enter(pgx::IoFormat::Binary, pgx::IoDirection::Output);
+                    <#name #generics as #send_recv_funcs>::send(&*input)
+                }
+
+                #[doc(hidden)]
+                #[pg_extern(immutable,parallel_safe #renamed_recv)]
+                pub fn #funcname_recv #generics(input: pgx::Internal) -> pgx::PgVarlena<#name #generics> {
+                    let _context = pgx::IoContext::enter(pgx::IoFormat::Binary, pgx::IoDirection::Input);
+                    let mut result = pgx::PgVarlena::<#name #generics>::new();
+                    *result = <#name #generics as #send_recv_funcs>::recv(pgx::recv_message(&input));
+                    result
+                }
+            });
+        } else {
+            stream.extend(quote! {
+                #[doc(hidden)]
+                #[pg_extern(immutable,parallel_safe #renamed_send)]
+                pub fn #funcname_send #generics(input: #name #generics) -> Vec<u8> {
+                    let _context = pgx::IoContext::enter(pgx::IoFormat::Binary, pgx::IoDirection::Output);
+                    <#name #generics as #send_recv_funcs>::send(&input)
+                }
+
+                #[doc(hidden)]
+                #[pg_extern(immutable,parallel_safe #renamed_recv)]
+                pub fn #funcname_recv #generics(input: pgx::Internal) -> #name #generics {
+                    let _context = pgx::IoContext::enter(pgx::IoFormat::Binary, pgx::IoDirection::Input);
+                    <#name #generics as #send_recv_funcs>::recv(pgx::recv_message(&input))
+                }
+            });
+        }
+    }
+
     let sql_graph_entity_item = PostgresType::from_derive_input(ast).unwrap();
     sql_graph_entity_item.to_tokens(&mut stream);
 
@@ -890,6 +1345,8 @@ fn impl_guc_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
 enum PostgresTypeAttribute {
     InOutFuncs,
     PgVarlenaInOutFuncs,
+    SendRecvFuncs,
+    CborSendRecvFuncs,
     Default,
 }
 
@@ -908,6 +1365,15 @@ fn parse_postgres_type_args(attributes: &[Attribute]) -> HashSet<PostgresTypeAtt
                 categorized_attributes.insert(PostgresTypeAttribute::PgVarlenaInOutFuncs);
             }
 
+            "sendrecvfuncs" => {
+                let codec = a.tokens.to_string().replace(' ', "");
+                if codec == "(cbor)" {
+                    categorized_attributes.insert(PostgresTypeAttribute::CborSendRecvFuncs);
+                } else {
+                    categorized_attributes.insert(PostgresTypeAttribute::SendRecvFuncs);
+                }
+            }
+
             _ => {
                 // we can just ignore attributes we don't understand
             }
@@ -930,9 +1396,18 @@ enum DogNames {
     Brandy,
 }
 ```
+The `=` operator is declared `HASHES` when the type also derives [`macro@PostgresHash`], and
+`MERGES` when it also derives [`macro@PostgresOrd`], which is what lets Postgres plan hash and merge
+joins on it.  Without the operator class those need, the flags are left off.  Deriving
+[`macro@PostgresOrd`] also makes the type usable in a unique index, and so as the arbiter of
+`INSERT ... ON CONFLICT`, which `#[pgx(conflict_arbiter)]` on the type checks for.
+
 Optionally accepts the following attributes:
 
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `distinct_from`: Also generate `<typename>_is_distinct_from()` and
+  `<typename>_is_not_distinct_from()`, which compare like `IS DISTINCT FROM`, so that NULLs are
+  equal to each other and unequal to anything else.
 */
 #[proc_macro_derive(PostgresEq, attributes(pgx))]
 pub fn postgres_eq(input: TokenStream) -> TokenStream {
@@ -995,6 +1470,126 @@ pub fn postgres_hash(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/**
+Generate the support functions and operator class which make a type implementing
+[`pgx::GistSupport`](trait@pgx::GistSupport) indexable with `USING gist` indexes.
+
+```rust,ignore
+use pgx::*;
+use serde::{Deserialize, Serialize};
+#[derive(PartialEq, Serialize, Deserialize, PostgresType, PostgresGistOps)]
+#[gist(default, operators(3 = "&&"), requires = [span_overlaps])]
+pub struct Span {
+    lo: i32,
+    hi: i32,
+}
+```
+
+The functions are named `<typename>_gist_consistent()`, `<typename>_gist_union()`, and so on.
+The operators the class supports are given in the `#[gist(..)]` attribute, which accepts the same
+arguments as [`#[pg_opclass]`](macro@pg_opclass) but `using` and `functions`.
+*/
+#[proc_macro_derive(PostgresGistOps, attributes(gist))]
+pub fn postgres_gist_ops(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_gist_ops(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+Generate the support functions and operator class which make a type implementing
+[`pgx::GinSupport`](trait@pgx::GinSupport) indexable with `USING gin` indexes.
+
+```rust,ignore
+use pgx::*;
+use serde::{Deserialize, Serialize};
+#[derive(Serialize, Deserialize, PostgresType, PostgresGinOps)]
+#[gin(default, storage = "text", operators(1 = "&&"), requires = [tags_overlap])]
+pub struct Tags(Vec<String>);
+```
+
+The functions are named `<typename>_gin_compare()`, `<typename>_gin_extract_value()`, and so on.
+The operators the class supports are given in the `#[gin(..)]` attribute, which accepts the same
+arguments as [`#[pg_opclass]`](macro@pg_opclass) but `using` and `functions`.  Unless the type's
+`GinSupport::Key` is the type itself, the SQL type of the keys has to be given as the `storage`.
+*/
+#[proc_macro_derive(PostgresGinOps, attributes(gin))]
+pub fn postgres_gin_ops(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_gin_ops(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+Generate the support functions and operator class which make a type implementing
+[`pgx::BrinSupport`](trait@pgx::BrinSupport) indexable with `USING brin` indexes.
+
+```rust,ignore
+use pgx::*;
+use serde::{Deserialize, Serialize};
+#[derive(Serialize, Deserialize, PostgresType, PostgresBrinOps)]
+#[brin(default, storage = "Bounds", operators(3 = "="), requires = [Bounds, reading_eq])]
+pub struct Reading(i64);
+```
+
+The functions are named `<typename>_brin_opcinfo()`, `<typename>_brin_add_value()`, and so on.
+The operators the class supports are given in the `#[brin(..)]` attribute, which accepts the same
+arguments as [`#[pg_opclass]`](macro@pg_opclass) but `using` and `functions`.  Unless the type's
+`BrinSupport::Summary` is the type itself, the SQL type of the summaries has to be given as the
+`storage`, and required if it's declared in the extension.
+*/
+#[proc_macro_derive(PostgresBrinOps, attributes(brin))]
+pub fn postgres_brin_ops(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_brin_ops(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+Generate a function and a view of the same name showing the struct's fields as a row, for the
+`SELECT * FROM myext_status` an extension offers to show what its shared memory and background
+workers are up to.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(Copy, Clone, Default, PostgresStatusView)]
+#[pgx(source = WORKER_STATUS, name = "myext_status")]
+pub struct WorkerStatus {
+    jobs_run: i64,
+    jobs_failed: i64,
+    paused: bool,
+    #[pgx(skip)]
+    scratch: [u8; 64],
+}
+
+unsafe impl PGXSharedMemory for WorkerStatus {}
+
+static WORKER_STATUS: PgLwLock<WorkerStatus> = PgLwLock::new();
+```
+
+The struct is copied out of its `source` with `pgx::StatusViewSource::snapshot()`, which for a
+`PgLwLock` or `PgSpinLock` holds the lock only while copying, and each field becomes a column of
+the same name and type.
+
+Accepts the following attributes:
+
+* `source`: The static the struct is kept in, whose type implements `StatusViewSource`.
+* `name`: The name of the function and the view, which is the struct's name in lowercase if it's
+  not given.
+* `skip`, on a field: Leave the field out, such as when it's not a type Postgres has.
+*/
+#[proc_macro_derive(PostgresStatusView, attributes(pgx))]
+pub fn postgres_status_view(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_status_view(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 /**
 Declare a `pgx::Aggregate` implentation on a type as able to used by Postgres as an aggregate.
 
@@ -1021,6 +1616,544 @@ pub fn pg_aggregate(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare an operator class for a type, so it can be indexed with `btree`, `hash`, `gist` or `gin`
+(or `spgist` or `brin`) indexes.
+
+The operators are named by their strategy numbers, and the support functions, which are usually
+`#[pg_extern]` functions, by their support numbers:
+
+```rust,ignore
+use pgx::*;
+
+#[derive(PostgresType, PostgresEq, Serialize, Deserialize)]
+#[pg_opclass(
+    using = "btree",
+    default,
+    operators(1 = "<", 2 = "<=", 3 = "=", 4 = ">=", 5 = ">"),
+    functions(1 = version_cmp),
+    requires = [version_lt, version_le, version_ge, version_gt]
+)]
+pub struct Version {
+    major: i32,
+    minor: i32,
+}
+
+#[pg_extern(immutable, parallel_safe)]
+fn version_cmp(left: Version, right: Version) -> i32 {
+    (left.major, left.minor).cmp(&(right.major, right.minor)) as i32
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "<", negator = ">=")]
+fn version_lt(left: Version, right: Version) -> bool {
+    version_cmp(left, right) < 0
+}
+
+// ... and the same for `<=`, `>=` and `>`
+```
+
+This generates:
+
+```sql
+CREATE OPERATOR CLASS Version_btree_ops DEFAULT FOR TYPE Version USING btree AS
+    OPERATOR 1 <,
+    OPERATOR 2 <=,
+    OPERATOR 3 =,
+    OPERATOR 4 >=,
+    OPERATOR 5 >,
+    FUNCTION 1 version_cmp;
+```
+
+Accepts the following attributes:
+
+* `using`: The index access method, such as `"btree"` or `"gist"`.  Required.
+* `name`: The name of the class, which defaults to `{type}_{using}_ops`.  An operator family of the
+  same name is created along with it.
+* `default`: Make it the default class for the type, which is used unless an index names another.
+* `operators(..)`: The operators, by strategy number.  Each is written as it is in
+  `CREATE OPERATOR CLASS`, so an ordering operator is `15 = "<-> (Point, Point) FOR ORDER BY float_ops"`.
+* `functions(..)`: The support functions, by support number.  Each is the path to the Rust
+  function, whose name must be unique in SQL.
+* `storage`: The type stored in the index, for `gist`, `gin` and `brin` classes which store
+  something else.
+* `requires`: Anything else which must be created first, such as the functions behind the
+  operators, as in [`extension_sql!`](macro@extension_sql).
+
+The class is created after the type and the support functions.
+*/
+#[proc_macro_attribute]
+pub fn pg_opclass(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match PgOpClass::new(attr.into(), item.into()) {
+        Ok(opclass) => opclass.to_token_stream().into(),
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as a Postgres trigger function.
+
+The function takes a `&pgx::PgTrigger` and returns an `Option<pgx::PgHeapTuple>` (or a `Result`
+of one).  Returning `None` from a `BEFORE ... FOR EACH ROW` trigger skips the row, otherwise the
+returned tuple is the one Postgres uses.  The `CREATE FUNCTION ... RETURNS trigger` is generated,
+but the `CREATE TRIGGER` statement is left to the extension, usually in an
+[`extension_sql!`](macro@extension_sql) which `requires` the trigger function.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_trigger]
+fn uppercase_name(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_, AllocatedByRust>>, PgHeapTupleError> {
+    let mut new = match trigger.new() {
+        Some(new) => new.into_owned(),
+        None => return Ok(None),
+    };
+    let name: Option<String> = new.get_by_name("name")?;
+    new.set_by_name("name", name.map(|name| name.to_uppercase()))?;
+    Ok(Some(new))
+}
+
+extension_sql!(
+    r#"
+    CREATE TABLE people (name text);
+    CREATE TRIGGER uppercase_name BEFORE INSERT ON people FOR EACH ROW EXECUTE PROCEDURE uppercase_name();
+    "#,
+    name = "create_people",
+    requires = [uppercase_name]
+);
+```
+
+Optionally accepts the following attributes:
+
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_attribute]
+pub fn pg_trigger(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match PgTrigger::new(attr.into(), item.into()) {
+        Ok(trigger) => trigger.to_token_stream().into(),
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as a Postgres event trigger, and create the event trigger for it.
+
+The function takes a `&pgx::PgEventTrigger` and returns nothing.  Both the
+`CREATE FUNCTION ... RETURNS event_trigger` and the `CREATE EVENT TRIGGER` statements are generated.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_event_trigger(event = "ddl_command_start", tags = ["DROP TABLE"])]
+fn forbid_drop_table(trigger: &PgEventTrigger) {
+    error!("`{}` is not allowed", trigger.tag());
+}
+```
+
+Accepts the following attributes:
+
+* `event`: Required. One of `"ddl_command_start"`, `"ddl_command_end"`, `"sql_drop"`, or `"table_rewrite"`.
+* `tags`: The command tags to fire for, as in `WHEN TAG IN (...)`, eg `tags = ["CREATE TABLE", "ALTER TABLE"]`.
+* `name`: The name of the event trigger, otherwise the name of the function.
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_attribute]
+pub fn pg_event_trigger(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match PgEventTrigger::new(attr.into(), item.into()) {
+        Ok(trigger) => trigger.to_token_stream().into(),
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a Postgres composite type from a Rust struct with named fields.
+
+Generates the `CREATE TYPE ... AS (...)` for the struct, mapping each field's Rust type to its SQL
+type, along with `FromDatum` and `IntoDatum` implementations so the struct can be used as an
+argument or return type of a `#[pg_extern]`.  Fields which can be `NULL` must be an `Option`;
+a `NULL` in any other field panics when the composite is converted from a datum.
+
+The type's oid is looked up the first time it's needed in each transaction, by its name qualified
+with the schema the extension created it in, so the `search_path` of the caller doesn't matter.
+That's the `#[pg_schema]` module it's in, or else the schema of the extension named after the crate.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_composite]
+struct Dog {
+    name: String,
+    scritches: Option<i32>,
+}
+
+#[pg_extern]
+fn scritch(dog: Dog) -> Dog {
+    Dog {
+        scritches: Some(dog.scritches.unwrap_or_default() + 1),
+        ..dog
+    }
+}
+```
+
+Optionally accepts the following attributes:
+
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_attribute]
+pub fn pg_composite(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match PostgresComposite::new(attr.into(), item.into()) {
+        Ok(composite) => composite.to_token_stream().into(),
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a `static` `PgLwLock`, `PgSpinLock`, `PgAtomic`, or `PgHistogram` that lives in Postgres
+shared memory.
+
+The shared memory is requested, and created when Postgres starts, by `pg_module_magic!()`,
+so unlike with `pg_shmem_init!()` nothing needs to be done in `_PG_init()`.  As with any shared memory, the
+extension must be loaded through `shared_preload_libraries`.
+
+The statics are registered by the shared library's constructors, which are only supported on Linux,
+FreeBSD and macOS.  Other targets fail to compile, and should use `pg_shmem_init!()`.
+
+```rust,ignore
+use pgx::*;
+
+pg_module_magic!();
+
+#[pg_shmem]
+static COUNTER: PgLwLock<u64> = PgLwLock::new();
+
+#[pg_extern]
+fn increment() -> i64 {
+    let mut counter = COUNTER.exclusive();
+    *counter += 1;
+    *counter as i64
+}
+```
+*/
+#[proc_macro_attribute]
+pub fn pg_shmem(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = proc_macro2::TokenStream::from(attr);
+    if !attr.is_empty() {
+        return syn::Error::new_spanned(attr, "`#[pg_shmem]` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let item = parse_macro_input!(item as syn::ItemStatic);
+    if let Some(mutability) = &item.mutability {
+        return syn::Error::new_spanned(mutability, "`#[pg_shmem]` statics cannot be `mut`")
+            .to_compile_error()
+            .into();
+    }
+
+    let ident = &item.ident;
+    let register_ident = Ident::new(
+        &format!("__pgx_shmem_register_{}", ident),
+        Span::call_site(),
+    );
+
+    // registration runs from the shared library's constructors, so that the statics are known
+    // by the time Postgres calls `Pg_magic_func()`
+    TokenStream::from(quote! {
+        #item
+
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "macos")))]
+        compile_error!("`#[pg_shmem]` is only supported on Linux, FreeBSD and macOS; use `pg_shmem_init!()` in `_PG_init()` instead");
+
+        #[doc(hidden)]
+        #[used]
+        #[allow(non_upper_case_globals)]
+        #[cfg_attr(any(target_os = "linux", target_os = "freebsd"), link_section = ".init_array")]
+        #[cfg_attr(target_os = "macos", link_section = "__DATA,__mod_init_func")]
+        static #register_ident: extern "C" fn() = {
+            extern "C" fn register() {
+                ::pgx::PgSharedMem::register(&#ident);
+            }
+            register
+        };
+    })
+}
+
+/**
+Expose the methods of a type which holds backend-local state as SQL functions.
+
+Each method taking `&self` or `&mut self` becomes a [`#[pg_extern]`](macro@pg_extern) function of
+the same name, with the remaining arguments, which calls the method on the backend's instance of
+the type (see [`pgx::with_backend_state`](../pgx/backend_state/fn.with_backend_state.html)).  The
+type must implement `Default`, which creates the instance the first time it's used.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(Default)]
+struct Counter {
+    count: i64,
+}
+
+#[pg_extern_methods]
+impl Counter {
+    fn increment(&mut self, by: default!(i64, 1)) -> i64 {
+        self.count += by;
+        self.count
+    }
+
+    #[pgx(name = "current_count")]
+    fn current(&self) -> i64 {
+        self.count
+    }
+}
+```
+
+```sql
+SELECT increment(); -- 1
+SELECT increment(10); -- 11
+SELECT current_count(); -- 11
+```
+
+Methods may use the [`#[pgx]`](macro@pgx) attribute, which takes the same args as
+[`#[pg_extern]`](macro@pg_extern).  Associated functions without a `self` are left alone, so they
+can be used as helpers.  A method which calls, through SPI, a function using the same type's state
+mutably raises an `ERROR`.
+*/
+#[proc_macro_attribute]
+pub fn pg_extern_methods(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = proc_macro2::TokenStream::from(attr);
+    if !attr.is_empty() {
+        return syn::Error::new_spanned(attr, "`#[pg_extern_methods]` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let item_impl = parse_macro_input!(item as ItemImpl);
+    impl_pg_extern_methods(item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn impl_pg_extern_methods(mut item_impl: ItemImpl) -> Result<proc_macro2::TokenStream, syn::Error> {
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "`#[pg_extern_methods]` can't be used on trait implementations",
+        ));
+    }
+    if !item_impl.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_impl.generics,
+            "`#[pg_extern_methods]` can't be used on generic implementations",
+        ));
+    }
+
+    let self_ty = &item_impl.self_ty;
+    let mut externs = Vec::new();
+    for impl_item in item_impl.items.iter_mut() {
+        let method = match impl_item {
+            syn::ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let receiver = match method.sig.inputs.first() {
+            Some(syn::FnArg::Receiver(receiver)) => receiver.clone(),
+            _ => continue,
+        };
+        if receiver.reference.is_none() {
+            return Err(syn::Error::new_spanned(
+                receiver,
+                "`#[pg_extern_methods]` methods must take `&self` or `&mut self`",
+            ));
+        }
+        if !method.sig.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &method.sig.generics,
+                "`#[pg_extern_methods]` methods can't be generic",
+            ));
+        }
+
+        // `#[pgx(...)]` is for the function, not the method
+        let mut extern_args = proc_macro2::TokenStream::new();
+        let mut i = 0;
+        while i < method.attrs.len() {
+            if method.attrs[i].path.is_ident("pgx") {
+                let attr = method.attrs.remove(i);
+                extern_args = attr.parse_args()?;
+            } else {
+                i += 1;
+            }
+        }
+
+        let mut args = Vec::new();
+        let mut arg_names = Vec::new();
+        for input in method.sig.inputs.iter().skip(1) {
+            if let syn::FnArg::Typed(pat_type) = input {
+                match &*pat_type.pat {
+                    syn::Pat::Ident(pat) => {
+                        let (name, ty) = (&pat.ident, &pat_type.ty);
+                        args.push(quote! { #name: #ty });
+                        arg_names.push(name.clone());
+                    }
+                    pat => {
+                        return Err(syn::Error::new_spanned(
+                            pat,
+                            "`#[pg_extern_methods]` arguments must be plain names",
+                        ))
+                    }
+                }
+            }
+        }
+
+        let vis = &method.vis;
+        let name = &method.sig.ident;
+        let output = &method.sig.output;
+        let with_state = match receiver.mutability {
+            Some(_) => quote! { with_backend_state_mut },
+            None => quote! { with_backend_state },
+        };
+        externs.push(quote! {
+            #[::pgx::pg_extern(#extern_args)]
+            #vis fn #name(#(#args),*) #output {
+                ::pgx::#with_state::<#self_ty, _>(|state| state.#name(#(#arg_names),*))
+            }
+        });
+    }
+
+    Ok(quote! {
+        #item_impl
+        #(#externs)*
+    })
+}
+
+/**
+Declare a function whose arguments are packed into a single `jsonb` object, keyed by the names of
+the Rust function's parameters.
+
+Postgres caps functions at 100 arguments (`FUNC_MAX_ARGS`), and calling one with dozens of
+positional arguments is error prone anyway.  This takes the same arguments as
+[`#[pg_extern]`](macro@pg_extern), and declares a SQL function of the same name which takes one
+`jsonb` argument, unpacks it, and calls the Rust function, which is left as it is:
+
+```rust,ignore
+use pgx::*;
+
+#[pg_extern_packed(immutable)]
+fn make_report(title: String, width: i32, height: Option<i32>, tags: Vec<String>) -> String {
+    format!("{} ({}x{}) {:?}", title, width, height.unwrap_or(width), tags)
+}
+```
+
+```sql
+SELECT make_report('{"title": "sales", "width": 80, "tags": ["q1"]}');
+```
+
+Each argument is deserialized from its key with `serde`, so parameters must be owned types which
+implement `Deserialize`, such as numbers, `String`, `Vec`s, `serde_json::Value`, and
+`#[derive(PostgresType)]` structs.  An `Option` parameter may be left out or `null`.  A missing
+argument, one of the wrong type, or a key that isn't a parameter raises an `ERROR` naming it.
+*/
+#[proc_macro_attribute]
+pub fn pg_extern_packed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let func = parse_macro_input!(item as ItemFn);
+    impl_pg_extern_packed(attr.into(), func)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn impl_pg_extern_packed(
+    attr: proc_macro2::TokenStream,
+    func: ItemFn,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    if !func.sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &func.sig.generics,
+            "`#[pg_extern_packed]` functions can't be generic",
+        ));
+    }
+
+    let mut arg_names = Vec::new();
+    let mut arg_types = Vec::new();
+    for input in func.sig.inputs.iter() {
+        let pat_type = match input {
+            syn::FnArg::Typed(pat_type) => pat_type,
+            syn::FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "`#[pg_extern_packed]` can only be applied to top-level functions",
+                ))
+            }
+        };
+        let name = match &*pat_type.pat {
+            syn::Pat::Ident(pat) => &pat.ident,
+            pat => {
+                return Err(syn::Error::new_spanned(
+                    pat,
+                    "`#[pg_extern_packed]` arguments must be plain names",
+                ))
+            }
+        };
+        if let syn::Type::Reference(reference) = &*pat_type.ty {
+            return Err(syn::Error::new_spanned(
+                reference,
+                "`#[pg_extern_packed]` arguments must be owned, as they're deserialized from jsonb",
+            ));
+        }
+        arg_names.push(name.clone());
+        arg_types.push(pat_type.ty.clone());
+    }
+
+    let name = &func.sig.ident;
+    let packed_name = Ident::new(&format!("{}_packed", name), name.span());
+    let keys = arg_names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let function = name.to_string();
+
+    // the SQL function is named after the Rust one, unless it's given a `name`
+    let extern_args = parse_extern_attributes(attr.clone());
+    let mut attr = attr;
+    if !extern_args
+        .iter()
+        .any(|arg| matches!(arg, ExternArgs::Name(_)))
+    {
+        if !attr.is_empty() {
+            attr.extend(quote! { , });
+        }
+        attr.extend(quote! { name = #function });
+    }
+
+    let vis = &func.vis;
+    let output = &func.sig.output;
+    Ok(quote! {
+        #func
+
+        #[::pgx::pg_extern(#attr)]
+        #vis fn #packed_name(args: ::pgx::JsonB) #output {
+            let mut __pgx_packed_args = ::pgx::PackedArgs::unpack(#function, args, &[#(#keys),*]);
+            #( let #arg_names: #arg_types = __pgx_packed_args.take(#keys); )*
+            #name(#(#arg_names),*)
+        }
+    })
+}
+
 /**
 A helper attribute for various contexts.
 
@@ -8,10 +8,10 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 use pgx_utils::{
     operator_common::*,
-    sql_entity_graph::{PostgresHash, PostgresOrd},
+    sql_entity_graph::{PgOpClass, PgxAttribute, PostgresHash, PostgresOrd},
 };
 
-use quote::ToTokens;
+use quote::{quote, ToTokens};
 use syn::DeriveInput;
 
 pub(crate) fn impl_postgres_eq(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
@@ -19,6 +19,9 @@ pub(crate) fn impl_postgres_eq(ast: DeriveInput) -> syn::Result<proc_macro2::Tok
 
     stream.extend(eq(&ast.ident));
     stream.extend(ne(&ast.ident));
+    if PgxAttribute::has_flag(&ast.attrs, "distinct_from")? {
+        stream.extend(distinct_from(&ast.ident));
+    }
 
     Ok(stream)
 }
@@ -48,3 +51,101 @@ pub(crate) fn impl_postgres_hash(ast: DeriveInput) -> syn::Result<proc_macro2::T
 
     Ok(stream)
 }
+
+pub(crate) fn impl_postgres_gist_ops(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut stream = proc_macro2::TokenStream::new();
+
+    stream.extend(gist_support(&ast.ident));
+
+    let mut args = proc_macro2::TokenStream::new();
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("gist")) {
+        let tokens = attr.parse_args::<proc_macro2::TokenStream>()?;
+        if !tokens.is_empty() {
+            args.extend(quote! { , #tokens });
+        }
+    }
+    // in order of their support numbers
+    let functions = [
+        "consistent",
+        "union",
+        "compress",
+        "decompress",
+        "penalty",
+        "picksplit",
+        "same",
+    ];
+    let functions = functions.iter().enumerate().map(|(i, function)| {
+        let number = syn::LitInt::new(&(i + 1).to_string(), ast.ident.span());
+        let name = gist_function_name(&ast.ident, function);
+        quote! { #number = #name }
+    });
+    let opclass = PgOpClass::for_type(
+        ast.ident.clone(),
+        quote! { using = "gist", functions(#(#functions),*) #args },
+    )?;
+    opclass.to_tokens(&mut stream);
+
+    Ok(stream)
+}
+
+pub(crate) fn impl_postgres_gin_ops(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut stream = proc_macro2::TokenStream::new();
+
+    stream.extend(gin_support(&ast.ident));
+
+    let mut args = proc_macro2::TokenStream::new();
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("gin")) {
+        let tokens = attr.parse_args::<proc_macro2::TokenStream>()?;
+        if !tokens.is_empty() {
+            args.extend(quote! { , #tokens });
+        }
+    }
+    // in order of their support numbers
+    let functions = [
+        "compare",
+        "extract_value",
+        "extract_query",
+        "consistent",
+        "compare_partial",
+    ];
+    let functions = functions.iter().enumerate().map(|(i, function)| {
+        let number = syn::LitInt::new(&(i + 1).to_string(), ast.ident.span());
+        let name = gin_function_name(&ast.ident, function);
+        quote! { #number = #name }
+    });
+    let opclass = PgOpClass::for_type(
+        ast.ident.clone(),
+        quote! { using = "gin", functions(#(#functions),*) #args },
+    )?;
+    opclass.to_tokens(&mut stream);
+
+    Ok(stream)
+}
+
+pub(crate) fn impl_postgres_brin_ops(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut stream = proc_macro2::TokenStream::new();
+
+    stream.extend(brin_support(&ast.ident));
+
+    let mut args = proc_macro2::TokenStream::new();
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("brin")) {
+        let tokens = attr.parse_args::<proc_macro2::TokenStream>()?;
+        if !tokens.is_empty() {
+            args.extend(quote! { , #tokens });
+        }
+    }
+    // in order of their support numbers
+    let functions = ["opcinfo", "add_value", "consistent", "union"];
+    let functions = functions.iter().enumerate().map(|(i, function)| {
+        let number = syn::LitInt::new(&(i + 1).to_string(), ast.ident.span());
+        let name = brin_function_name(&ast.ident, function);
+        quote! { #number = #name }
+    });
+    let opclass = PgOpClass::for_type(
+        ast.ident.clone(),
+        quote! { using = "brin", functions(#(#functions),*) #args },
+    )?;
+    opclass.to_tokens(&mut stream);
+
+    Ok(stream)
+}
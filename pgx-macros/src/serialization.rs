@@ -0,0 +1,219 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx_utils::sql_entity_graph::{ArgValue, PgxArg, PgxAttribute};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Lit, Meta, NestedMeta};
+
+/// The `impl pgx::PostgresType`, with the format, version and migration from the type's
+/// `#[pgx(serialize = .., version = .., migrate = ..)]`
+pub(crate) fn impl_postgres_type_trait(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &ast.ident;
+    let generics = &ast.generics;
+
+    let mut format = quote! { pgx::Cbor };
+    let mut version = None;
+    let mut migrate = None;
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+        let attr = attr.parse_args::<PgxAttribute>()?;
+        for arg in attr.args {
+            match arg {
+                PgxArg::NameValue(nv) if nv.path.is_ident("serialize") => {
+                    format = match nv.value {
+                        ArgValue::Path(path) => quote! { #path },
+                        ArgValue::Lit(Lit::Str(lit)) => match lit.value().as_str() {
+                            "cbor" => quote! { pgx::Cbor },
+                            "json" => quote! { pgx::JsonFormat },
+                            "bincode" => quote! { pgx::Bincode },
+                            _ => return Err(syn::Error::new(
+                                lit.span(),
+                                "expected \"cbor\", \"json\", \"bincode\", or the path of a pgx::SerializationFormat",
+                            )),
+                        },
+                        ArgValue::Lit(lit) => {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                "expected a format, such as `serialize = \"json\"`",
+                            ))
+                        }
+                    }
+                }
+                PgxArg::NameValue(nv) if nv.path.is_ident("version") => match nv.value {
+                    ArgValue::Lit(Lit::Int(lit)) => {
+                        let value = lit.base10_parse::<u16>()?;
+                        if value == 0 {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                "versions start at 1, as values stored without one are version 0",
+                            ));
+                        }
+                        version = Some(value);
+                    }
+                    ArgValue::Lit(lit) => {
+                        return Err(syn::Error::new(lit.span(), "expected an integer"))
+                    }
+                    ArgValue::Path(path) => {
+                        return Err(syn::Error::new(path.span(), "expected an integer"))
+                    }
+                },
+                PgxArg::NameValue(nv) if nv.path.is_ident("migrate") => match nv.value {
+                    ArgValue::Path(path) => migrate = Some(path),
+                    ArgValue::Lit(lit) => return Err(syn::Error::new(
+                        lit.span(),
+                        "expected the function which reads older versions, such as `migrate = upgrade_point`",
+                    )),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    let version = match (version, &migrate) {
+        (Some(version), _) => quote! { const VERSION: u16 = #version; },
+        (None, Some(migrate)) => {
+            return Err(syn::Error::new(
+                migrate.span(),
+                "`migrate` needs a `#[pgx(version = ..)]` for the values it doesn't read",
+            ))
+        }
+        (None, None) => quote! {},
+    };
+    let migrate = migrate.map(|migrate| {
+        quote! {
+            fn migrate(version: u16, bytes: &[u8]) -> Self {
+                #migrate(version, bytes)
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #generics pgx::PostgresType for #name #generics {
+            type Format = #format;
+            #version
+            #migrate
+        }
+    })
+}
+
+/// For `#[pgx(fixed_size)]` and `#[pgx(passedbyvalue)]` types, the `impl pgx::IntoDatum` and
+/// `impl pgx::FromDatum` which copy the bytes of the value, rather than serializing it
+pub(crate) fn impl_fixed_size_datum(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &ast.ident;
+    let passed_by_value = PgxAttribute::has_flag(&ast.attrs, "passedbyvalue")?;
+    let fixed_size = passed_by_value || PgxAttribute::has_flag(&ast.attrs, "fixed_size")?;
+    if !fixed_size {
+        return Ok(quote! {});
+    }
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            ast.generics.span(),
+            "fixed-size types can't be generic",
+        ));
+    }
+    if !ast
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("inoutfuncs"))
+    {
+        return Err(syn::Error::new(
+            name.span(),
+            "fixed-size types need `#[inoutfuncs]`, as they aren't converted to and from JSON",
+        ));
+    }
+    if !has_c_layout(&ast.attrs)? {
+        return Err(syn::Error::new(
+            name.span(),
+            "fixed-size types need `#[repr(C)]` or `#[repr(transparent)]`, so that their bytes are \
+                laid out the same by every build of the extension",
+        ));
+    }
+    let fields = match &ast.data {
+        Data::Struct(data) => data.fields.iter().map(|field| &field.ty),
+        _ => {
+            return Err(syn::Error::new(
+                name.span(),
+                "fixed-size types must be structs",
+            ))
+        }
+    };
+    let padding_message = format!(
+        "{} has padding between or after its fields, whose bytes Postgres would store and compare",
+        name
+    );
+    let padding_check = quote! {
+        const _: () = assert!(
+            core::mem::size_of::<#name>() == 0 #(+ core::mem::size_of::<#fields>())*,
+            #padding_message
+        );
+    };
+
+    let (into_datum, from_datum, size_check) = if passed_by_value {
+        let message = format!("{} must be 1, 2, 4 or 8 bytes to be passed by value", name);
+        (
+            quote! { pgx::by_value_into_datum },
+            quote! { pgx::by_value_from_datum },
+            quote! {
+                const _: () = assert!(
+                    matches!(core::mem::size_of::<#name>(), 1 | 2 | 4 | 8),
+                    #message
+                );
+            },
+        )
+    } else {
+        (
+            quote! { pgx::fixed_size_into_datum },
+            quote! { pgx::fixed_size_from_datum },
+            quote! {},
+        )
+    };
+
+    Ok(quote! {
+        #padding_check
+        #size_check
+
+        impl pgx::IntoDatum for #name {
+            fn into_datum(self) -> Option<pgx::pg_sys::Datum> {
+                Some(unsafe { #into_datum(self) })
+            }
+
+            fn type_oid() -> pgx::pg_sys::Oid {
+                pgx::rust_regtypein::<Self>()
+            }
+        }
+
+        impl pgx::FromDatum for #name {
+            unsafe fn from_datum(
+                datum: pgx::pg_sys::Datum,
+                is_null: bool,
+                _typoid: pgx::pg_sys::Oid,
+            ) -> Option<Self> {
+                if is_null {
+                    None
+                } else {
+                    Some(#from_datum(datum))
+                }
+            }
+        }
+    })
+}
+
+/// Is there a `#[repr(C)]` or `#[repr(transparent)]` among `attrs`?
+fn has_c_layout(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        if let Meta::List(list) = attr.parse_meta()? {
+            if list.nested.iter().any(|repr| {
+                matches!(repr, NestedMeta::Meta(Meta::Path(path))
+                    if path.is_ident("C") || path.is_ident("transparent"))
+            }) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
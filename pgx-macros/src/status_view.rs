@@ -0,0 +1,99 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx_utils::sql_entity_graph::{ArgValue, PgxArg, PgxAttribute};
+use proc_macro2::Ident;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Lit};
+
+/// A `#[pg_extern]` returning one row, made of the fields of a snapshot of the struct taken from
+/// its `#[pgx(source = ..)]`, and a view of the same name selecting from it
+pub(crate) fn impl_postgres_status_view(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let struct_name = &ast.ident;
+    let fields =
+        match &ast.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => &fields.named,
+                _ => return Err(syn::Error::new(
+                    data.fields.span(),
+                    "#[derive(PostgresStatusView)] needs named fields, which are the column names",
+                )),
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    struct_name.span(),
+                    "#[derive(PostgresStatusView)] can only be applied to structs",
+                ))
+            }
+        };
+
+    let mut source = None;
+    let mut name = struct_name.to_string().to_lowercase();
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+        let attr = attr.parse_args::<PgxAttribute>()?;
+        for arg in attr.args {
+            match arg {
+                PgxArg::NameValue(nv) if nv.path.is_ident("source") => match nv.value {
+                    ArgValue::Path(path) => source = Some(path),
+                    ArgValue::Lit(lit) => return Err(syn::Error::new(
+                        lit.span(),
+                        "expected the static the struct is kept in, such as `source = MY_STATUS`",
+                    )),
+                },
+                PgxArg::NameValue(nv) if nv.path.is_ident("name") => match nv.value {
+                    ArgValue::Lit(Lit::Str(lit)) => name = lit.value(),
+                    ArgValue::Lit(lit) => {
+                        return Err(syn::Error::new(lit.span(), "expected a string"))
+                    }
+                    ArgValue::Path(path) => {
+                        return Err(syn::Error::new(path.span(), "expected a string"))
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+    let source = source.ok_or_else(|| {
+        syn::Error::new(
+            struct_name.span(),
+            "#[derive(PostgresStatusView)] needs `#[pgx(source = ..)]`, the static the struct is kept in",
+        )
+    })?;
+
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        if PgxAttribute::has_flag(&field.attrs, "skip")? {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        columns.push(quote! { name!(#ident, #ty) });
+        values.push(quote! { snapshot.#ident });
+    }
+    if columns.is_empty() {
+        return Err(syn::Error::new(
+            struct_name.span(),
+            "#[derive(PostgresStatusView)] needs at least one field that isn't `#[pgx(skip)]`",
+        ));
+    }
+
+    let fn_name = Ident::new(&name, struct_name.span());
+    let view_sql = format!("CREATE VIEW {name} AS SELECT * FROM {name}();", name = name);
+    let view_sql_name = format!("{}_view", name);
+    Ok(quote! {
+        #[pg_extern]
+        fn #fn_name() -> impl std::iter::Iterator<Item = ( #( #columns, )* )> {
+            let snapshot: #struct_name = pgx::StatusViewSource::snapshot(&#source);
+            std::iter::once(( #( #values, )* ))
+        }
+
+        extension_sql!(#view_sql, name = #view_sql_name, requires = [#fn_name]);
+    })
+}
@@ -0,0 +1,143 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Ident, Lit, Token};
+
+/// One setting of a `#[pg_test(matrix(..))]`, and the values each instance of the test has for it
+pub(crate) struct MatrixSetting {
+    pub(crate) name: String,
+    pub(crate) values: Vec<String>,
+}
+
+impl Parse for MatrixSetting {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // either `name`, `extension.name`, or "extension.name"
+        let name = if input.peek(syn::LitStr) {
+            input.parse::<syn::LitStr>()?.value()
+        } else {
+            let parts = Punctuated::<Ident, Token![.]>::parse_separated_nonempty(input)?;
+            parts
+                .iter()
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>()
+                .join(".")
+        };
+        let _eq: Token![=] = input.parse()?;
+
+        let content;
+        bracketed!(content in input);
+        let values = Punctuated::<MatrixValue, Token![,]>::parse_terminated(&content)?;
+        if values.is_empty() {
+            return Err(content.error(format!("`{}` has no values", name)));
+        }
+
+        Ok(MatrixSetting {
+            name,
+            values: values.into_iter().map(|value| value.0).collect(),
+        })
+    }
+}
+
+/// A value of a setting, as `SET` would take it: `on`, `4`, `-1`, `1.5`, or `'some string'`
+struct MatrixValue(String);
+
+impl Parse for MatrixValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let negative = input.parse::<Option<Token![-]>>()?.is_some();
+        let value = if input.peek(Ident) {
+            input.parse::<Ident>()?.to_string()
+        } else {
+            match input.parse::<Lit>()? {
+                Lit::Str(s) => s.value(),
+                Lit::Int(i) => i.base10_digits().to_string(),
+                Lit::Float(f) => f.base10_digits().to_string(),
+                Lit::Bool(b) => b.value.to_string(),
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        "expected an identifier, number, or string literal",
+                    ))
+                }
+            }
+        };
+        Ok(MatrixValue(if negative {
+            format!("-{}", value)
+        } else {
+            value
+        }))
+    }
+}
+
+/// Take the `matrix(..)` out of the arguments of `#[pg_test]`, returning the rest of them, which
+/// are `#[pg_extern]`'s
+pub(crate) fn take_test_matrix(
+    attr: TokenStream,
+) -> syn::Result<(TokenStream, Vec<MatrixSetting>)> {
+    let mut rest = Vec::new();
+    let mut matrix = Vec::new();
+    let mut tokens = attr.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match (&token, tokens.peek()) {
+            (TokenTree::Ident(ident), Some(TokenTree::Group(group)))
+                if ident == "matrix" && group.delimiter() == Delimiter::Parenthesis =>
+            {
+                let settings = Punctuated::<MatrixSetting, Token![,]>::parse_terminated
+                    .parse2(group.stream())?;
+                matrix.extend(settings);
+                tokens.next();
+                // and the comma after it, if any
+                if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ',') {
+                    tokens.next();
+                }
+            }
+            _ => rest.push(token),
+        }
+    }
+    Ok((rest.into_iter().collect(), matrix))
+}
+
+/// Every combination of the values of the `matrix`'s settings, in order
+pub(crate) fn instances(matrix: &[MatrixSetting]) -> Vec<Vec<(&str, &str)>> {
+    let mut instances = vec![Vec::new()];
+    for setting in matrix {
+        instances = instances
+            .into_iter()
+            .flat_map(|instance| {
+                setting.values.iter().map(move |value| {
+                    let mut instance = instance.clone();
+                    instance.push((setting.name.as_str(), value.as_str()));
+                    instance
+                })
+            })
+            .collect();
+    }
+    instances
+}
+
+/// The suffix of the `#[test]` function for an instance, such as `jit_off_work_mem_64kb`
+pub(crate) fn instance_suffix(instance: &[(&str, &str)]) -> String {
+    instance
+        .iter()
+        .flat_map(|(name, value)| [*name, *value])
+        .map(|part| {
+            part.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
@@ -16,6 +16,14 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "access/htup.h"
 #include "access/htup_details.h"
 #include "catalog/pg_type.h"
+#include "executor/executor.h"
+#include "executor/tuptable.h"
+#if IS_PG_10 || IS_PG_11
+#include "libpq/ip.h"
+#else
+#include "common/ip.h"
+#endif
+#include "libpq/libpq-be.h"
 #if IS_PG_10 || IS_PG_11
 #include "nodes/relation.h"
 #else
@@ -23,6 +31,9 @@ Use of this source code is governed by the MIT license that can be found in the
 #endif
 #include "nodes/pg_list.h"
 #include "parser/parsetree.h"
+#include "storage/bufmgr.h"
+#include "storage/spin.h"
+#include "utils/acl.h"
 #include "utils/memutils.h"
 #include "utils/builtins.h"
 
@@ -49,6 +60,30 @@ void pgx_ereport(int level, int code, char *message, char *file, int lineno, int
                     errmsg("%s", message), errcontext_msg("%s:%d:%d", file, lineno, colno)));
 }
 
+PGDLLEXPORT void pgx_ereport_with_backtrace(int level, int code, char *message, char *backtrace, char *file, int lineno, int colno);
+void pgx_ereport_with_backtrace(int level, int code, char *message, char *backtrace, char *file, int lineno, int colno) {
+    ereport(level,
+            (errcode(code),
+                    errmsg("%s", message),
+                    errdetail_log("Rust backtrace:\n%s", backtrace),
+                    errcontext_msg("%s:%d:%d", file, lineno, colno)));
+}
+
+PGDLLEXPORT void pgx_ereport_detailed(int level, int code, char *message, char *detail, char *hint, char *schema, char *table, char *column, char *datatype, char *constraint, char *file, int lineno, int colno);
+void pgx_ereport_detailed(int level, int code, char *message, char *detail, char *hint, char *schema, char *table, char *column, char *datatype, char *constraint, char *file, int lineno, int colno) {
+    ereport(level,
+            (errcode(code),
+                    errmsg("%s", message),
+                    detail ? errdetail("%s", detail) : 0,
+                    hint ? errhint("%s", hint) : 0,
+                    schema ? err_generic_string(PG_DIAG_SCHEMA_NAME, schema) : 0,
+                    table ? err_generic_string(PG_DIAG_TABLE_NAME, table) : 0,
+                    column ? err_generic_string(PG_DIAG_COLUMN_NAME, column) : 0,
+                    datatype ? err_generic_string(PG_DIAG_DATATYPE_NAME, datatype) : 0,
+                    constraint ? err_generic_string(PG_DIAG_CONSTRAINT_NAME, constraint) : 0,
+                    errcontext_msg("%s:%d:%d", file, lineno, colno)));
+}
+
 PGDLLEXPORT void pgx_SET_VARSIZE(struct varlena *ptr, int size);
 void pgx_SET_VARSIZE(struct varlena *ptr, int size) {
     SET_VARSIZE(ptr, size);
@@ -110,3 +145,79 @@ PGDLLEXPORT char *pgx_GETSTRUCT(HeapTuple tuple);
 char *pgx_GETSTRUCT(HeapTuple tuple) {
     return GETSTRUCT(tuple);
 }
+
+PGDLLEXPORT void pgx_SpinLockInit(volatile slock_t *lock);
+void pgx_SpinLockInit(volatile slock_t *lock) {
+    SpinLockInit(lock);
+}
+
+PGDLLEXPORT void pgx_SpinLockAcquire(volatile slock_t *lock);
+void pgx_SpinLockAcquire(volatile slock_t *lock) {
+    SpinLockAcquire(lock);
+}
+
+PGDLLEXPORT void pgx_SpinLockRelease(volatile slock_t *lock);
+void pgx_SpinLockRelease(volatile slock_t *lock) {
+    SpinLockRelease(lock);
+}
+
+PGDLLEXPORT bool pgx_SpinLockFree(slock_t *lock);
+bool pgx_SpinLockFree(slock_t *lock) {
+    return SpinLockFree(lock);
+}
+
+PGDLLEXPORT TupleTableSlot *pgx_ExecClearTuple(TupleTableSlot *slot);
+TupleTableSlot *pgx_ExecClearTuple(TupleTableSlot *slot) {
+    return ExecClearTuple(slot);
+}
+
+PGDLLEXPORT HeapTuple pgx_ExecCopySlotHeapTuple(TupleTableSlot *slot);
+HeapTuple pgx_ExecCopySlotHeapTuple(TupleTableSlot *slot) {
+#if IS_PG_10 || IS_PG_11
+    return ExecCopySlotTuple(slot);
+#else
+    return ExecCopySlotHeapTuple(slot);
+#endif
+}
+
+PGDLLEXPORT Datum pgx_ExecGetJunkAttribute(TupleTableSlot *slot, AttrNumber attno, bool *isNull);
+Datum pgx_ExecGetJunkAttribute(TupleTableSlot *slot, AttrNumber attno, bool *isNull) {
+    return ExecGetJunkAttribute(slot, attno, isNull);
+}
+
+PGDLLEXPORT Page pgx_BufferGetPage(Buffer buffer);
+Page pgx_BufferGetPage(Buffer buffer) {
+    return BufferGetPage(buffer);
+}
+
+PGDLLEXPORT Oid pgx_get_role_oid(const char *rolname);
+Oid pgx_get_role_oid(const char *rolname) {
+    return get_role_oid(rolname, true);
+}
+
+PGDLLEXPORT bool pgx_has_privs_of_role(Oid member, Oid role);
+bool pgx_has_privs_of_role(Oid member, Oid role) {
+    return has_privs_of_role(member, role);
+}
+
+PGDLLEXPORT bool pgx_client_addr(char *host, int hostlen, int *port);
+bool pgx_client_addr(char *host, int hostlen, int *port) {
+    char service[NI_MAXSERV];
+
+    /* background workers have no client, and Unix-domain socket clients have no address */
+    if (MyProcPort == NULL)
+        return false;
+    if (MyProcPort->raddr.addr.ss_family != AF_INET
+#ifdef HAVE_IPV6
+        && MyProcPort->raddr.addr.ss_family != AF_INET6
+#endif
+        )
+        return false;
+
+    if (pg_getnameinfo_all(&MyProcPort->raddr.addr, MyProcPort->raddr.salen,
+                           host, hostlen, service, sizeof(service),
+                           NI_NUMERICHOST | NI_NUMERICSERV) != 0)
+        return false;
+    *port = atoi(service);
+    return true;
+}
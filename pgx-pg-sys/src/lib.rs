@@ -223,6 +223,7 @@ mod all_versions {
     pub const MaxOffsetNumber: super::OffsetNumber =
         (super::BLCKSZ as usize / std::mem::size_of::<super::ItemIdData>()) as super::OffsetNumber;
     pub const InvalidBlockNumber: u32 = 0xFFFF_FFFF as crate::BlockNumber;
+    pub const MaxBlockNumber: u32 = 0xFFFF_FFFE as crate::BlockNumber;
     pub const VARHDRSZ: usize = std::mem::size_of::<super::int32>();
     pub const InvalidTransactionId: super::TransactionId = 0 as super::TransactionId;
     pub const InvalidCommandId: super::CommandId = (!(0 as super::CommandId)) as super::CommandId;
@@ -231,6 +232,7 @@ mod all_versions {
     pub const FrozenTransactionId: super::TransactionId = 2 as super::TransactionId;
     pub const FirstNormalTransactionId: super::TransactionId = 3 as super::TransactionId;
     pub const MaxTransactionId: super::TransactionId = 0xFFFF_FFFF as super::TransactionId;
+    pub const InvalidMultiXactId: super::MultiXactId = 0 as super::MultiXactId;
 
     #[pgx_macros::pg_guard]
     extern "C" {
@@ -239,6 +241,26 @@ mod all_versions {
         pub fn pgx_list_nth_oid(list: *mut super::List, nth: i32) -> super::Oid;
         pub fn pgx_list_nth_cell(list: *mut super::List, nth: i32) -> *mut super::ListCell;
         pub fn pgx_GETSTRUCT(tuple: pg_sys::HeapTuple) -> *mut std::os::raw::c_char;
+        pub fn pgx_SpinLockInit(lock: *mut pg_sys::slock_t);
+        pub fn pgx_SpinLockAcquire(lock: *mut pg_sys::slock_t);
+        pub fn pgx_SpinLockRelease(lock: *mut pg_sys::slock_t);
+        pub fn pgx_SpinLockFree(lock: *mut pg_sys::slock_t) -> bool;
+        pub fn pgx_ExecClearTuple(slot: *mut pg_sys::TupleTableSlot)
+            -> *mut pg_sys::TupleTableSlot;
+        pub fn pgx_ExecCopySlotHeapTuple(slot: *mut pg_sys::TupleTableSlot) -> pg_sys::HeapTuple;
+        pub fn pgx_ExecGetJunkAttribute(
+            slot: *mut pg_sys::TupleTableSlot,
+            attno: pg_sys::AttrNumber,
+            is_null: *mut bool,
+        ) -> pg_sys::Datum;
+        pub fn pgx_BufferGetPage(buffer: pg_sys::Buffer) -> pg_sys::Page;
+        pub fn pgx_get_role_oid(rolname: *const std::os::raw::c_char) -> pg_sys::Oid;
+        pub fn pgx_has_privs_of_role(member: pg_sys::Oid, role: pg_sys::Oid) -> bool;
+        pub fn pgx_client_addr(
+            host: *mut std::os::raw::c_char,
+            hostlen: i32,
+            port: *mut i32,
+        ) -> bool;
     }
 
     #[inline]
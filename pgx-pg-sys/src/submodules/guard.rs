@@ -10,10 +10,11 @@ Use of this source code is governed by the MIT license that can be found in the
 #![allow(non_snake_case)]
 
 use crate::FlushErrorState;
+use once_cell::sync::OnceCell;
 use std::any::Any;
 use std::cell::Cell;
-use std::panic::catch_unwind;
 use std::mem;
+use std::panic::catch_unwind;
 
 extern "C" {
     fn pg_re_throw();
@@ -25,7 +26,15 @@ extern "C" {
         lineno: i32,
         colno: i32,
     );
-
+    fn pgx_ereport_with_backtrace(
+        level: i32,
+        code: i32,
+        message: *const std::os::raw::c_char,
+        backtrace: *const std::os::raw::c_char,
+        file: *const std::os::raw::c_char,
+        lineno: i32,
+        colno: i32,
+    );
 }
 
 #[derive(Clone, Debug)]
@@ -54,10 +63,24 @@ struct PanicLocation {
     file: String,
     line: u32,
     col: u32,
+    backtrace: Option<String>,
 }
 
 thread_local! { static PANIC_LOCATION: Cell<Option<PanicLocation>> = Cell::new(None) }
 
+/// Asked at each panic whether to capture a backtrace, once something has said how to decide
+static CAPTURE_BACKTRACE: OnceCell<fn() -> bool> = OnceCell::new();
+
+/// Have the panic hook capture a Rust backtrace whenever `enabled()` says so, which is logged with
+/// the ERROR the panic becomes.
+///
+/// `pgx::PanicBacktraces::define_guc()` calls this with a check of its setting.
+#[doc(hidden)]
+pub fn capture_panic_backtraces(enabled: fn() -> bool) {
+    // the first setting to be defined decides
+    let _ = CAPTURE_BACKTRACE.set(enabled);
+}
+
 fn take_panic_location() -> PanicLocation {
     PANIC_LOCATION.with(|p| match p.take() {
         Some(location) => location,
@@ -67,6 +90,7 @@ fn take_panic_location() -> PanicLocation {
             file: "<unknown>".to_string(),
             line: 0,
             col: 0,
+            backtrace: None,
         },
     })
 }
@@ -77,14 +101,17 @@ pub fn register_pg_guard_panic_handler() {
             let existing = p.take();
 
             p.replace(if existing.is_none() {
-                match info.location() {
-                    Some(location) => Some(PanicLocation {
-                        file: location.file().to_string(),
-                        line: location.line(),
-                        col: location.column(),
-                    }),
-                    None => None,
-                }
+                info.location().map(|location| PanicLocation {
+                    file: location.file().to_string(),
+                    line: location.line(),
+                    col: location.column(),
+                    backtrace: match CAPTURE_BACKTRACE.get() {
+                        Some(enabled) if enabled() => {
+                            Some(format!("{:?}", backtrace::Backtrace::new()))
+                        }
+                        _ => None,
+                    },
+                })
             } else {
                 existing
             })
@@ -235,14 +262,29 @@ where
             let c_file = std::ffi::CString::new(location.file).unwrap();
 
             unsafe {
-                pgx_ereport(
-                    crate::ERROR as i32,
-                    2600, // ERRCODE_INTERNAL_ERROR
-                    c_message.as_ptr(),
-                    c_file.as_ptr(),
-                    location.line as i32,
-                    location.col as i32,
-                );
+                match location.backtrace {
+                    Some(backtrace) => {
+                        let c_backtrace =
+                            std::ffi::CString::new(backtrace.replace('\0', "")).unwrap();
+                        pgx_ereport_with_backtrace(
+                            crate::ERROR as i32,
+                            2600, // ERRCODE_INTERNAL_ERROR
+                            c_message.as_ptr(),
+                            c_backtrace.as_ptr(),
+                            c_file.as_ptr(),
+                            location.line as i32,
+                            location.col as i32,
+                        );
+                    }
+                    None => pgx_ereport(
+                        crate::ERROR as i32,
+                        2600, // ERRCODE_INTERNAL_ERROR
+                        c_message.as_ptr(),
+                        c_file.as_ptr(),
+                        location.line as i32,
+                        location.col as i32,
+                    ),
+                }
             }
             unreachable!("ereport() failed at depth==0");
         }
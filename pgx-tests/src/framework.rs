@@ -22,7 +22,7 @@ use postgres::Client;
 use std::collections::HashMap;
 use std::fmt::Write as _;
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 type LogLines = Arc<Mutex<HashMap<String, Vec<String>>>>;
 
@@ -62,16 +62,81 @@ pub fn run_test(
     sql_funcname: &str,
     expected_error: Option<&str>,
     postgresql_conf: Vec<&'static str>,
+) -> eyre::Result<()> {
+    let schema = "tests"; // get_extension_schema();
+    run_test_query(
+        &format!("SELECT \"{schema}\".\"{sql_funcname}\"();"),
+        expected_error,
+        postgresql_conf,
+    )
+}
+
+/// Run the `#[pg_test]` function `sql_funcname` with each of the `settings`, a GUC's name and
+/// value, applied with `SET LOCAL` first.  Used by the instances of a `#[pg_test(matrix(..))]`.
+pub fn run_test_with_settings(
+    sql_funcname: &str,
+    expected_error: Option<&str>,
+    settings: &[(&str, &str)],
+    postgresql_conf: Vec<&'static str>,
+) -> eyre::Result<()> {
+    let schema = "tests"; // get_extension_schema();
+    let mut query = String::new();
+    for (name, value) in settings {
+        let _ = write!(
+            query,
+            "SELECT set_config({}, {}, true);",
+            quote_literal(name),
+            quote_literal(value)
+        );
+    }
+    let _ = write!(query, "SELECT \"{schema}\".\"{sql_funcname}\"();");
+    run_test_query(&query, expected_error, postgresql_conf)
+}
+
+fn quote_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Call the SQL function whose C symbol is `wrapper_name` with `NULL` for each argument that has
+/// no default, and fail if that raises an `ERROR`.  Used by the `pg_smoke_test` tests which
+/// `#[pg_extern]` generates.
+pub fn run_smoke_test(wrapper_name: &str, postgresql_conf: Vec<&'static str>) -> eyre::Result<()> {
+    initialize_test_framework(postgresql_conf.clone())?;
+
+    let (mut client, _) = client();
+    let row = client
+        .query_opt(
+            "SELECT format('SELECT %I.%I(%s);', n.nspname, p.proname,
+                    (SELECT coalesce(string_agg(format('NULL::%s', format_type(t.oid, NULL)), ', ' ORDER BY t.ord), '')
+                       FROM unnest(p.proargtypes::oid[]) WITH ORDINALITY AS t(oid, ord)
+                      WHERE t.ord <= p.pronargs - p.pronargdefaults))
+               FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+              WHERE p.prosrc = $1
+              LIMIT 1",
+            &[&wrapper_name],
+        )
+        .wrap_err("failed to look up smoke test function")?;
+    let query: String = match row {
+        Some(row) => row.get(0),
+        None => return Err(eyre!("No SQL function found for `{wrapper_name}`")),
+    };
+
+    run_test_query(&query, None, postgresql_conf)
+}
+
+fn run_test_query(
+    query: &str,
+    expected_error: Option<&str>,
+    postgresql_conf: Vec<&'static str>,
 ) -> eyre::Result<()> {
     let (loglines, system_session_id) = initialize_test_framework(postgresql_conf)?;
 
     let (mut client, session_id) = client();
 
-    let schema = "tests"; // get_extension_schema();
     let result = match client.transaction() {
         // run the test function in a transaction
         Ok(mut tx) => {
-            let result = tx.simple_query(&format!("SELECT \"{schema}\".\"{sql_funcname}\"();"));
+            let result = tx.simple_query(query);
 
             if result.is_ok() {
                 // and abort the transaction when complete
@@ -236,8 +301,25 @@ pub fn client() -> (postgres::Client, String) {
 
 fn install_extension() -> eyre::Result<()> {
     eprintln!("installing extension");
-    let is_release = std::env::var("PGX_BUILD_PROFILE").unwrap_or("debug".into()) == "release";
     let no_schema = std::env::var("PGX_NO_SCHEMA").unwrap_or("false".into()) == "true";
+
+    let mut command = cargo_pgx_command("install")?;
+    command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+    if no_schema {
+        command.arg("--no-schema");
+    }
+
+    let mut child = command.spawn().unwrap();
+    let status = child.wait().unwrap();
+    if !status.success() {
+        return Err(eyre!("failed to install extension"));
+    }
+    Ok(())
+}
+
+/// A `cargo pgx` command building the extension the same way `cargo pgx test` asked for
+fn cargo_pgx_command(subcommand: &str) -> eyre::Result<Command> {
+    let is_release = std::env::var("PGX_BUILD_PROFILE").unwrap_or("debug".into()) == "release";
     let mut features = std::env::var("PGX_FEATURES").unwrap_or("".to_string());
     if !features.contains("pg_test") {
         features += " pg_test";
@@ -253,12 +335,10 @@ fn install_extension() -> eyre::Result<()> {
     let mut command = Command::new("cargo");
     command
         .arg("pgx")
-        .arg("install")
+        .arg(subcommand)
         .arg("--test")
         .arg("--pg-config")
         .arg(pg_config.path().ok_or(eyre!("No pg_config found"))?)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
         .env("CARGO_TARGET_DIR", get_target_dir()?);
 
     if let Ok(manifest_path) = std::env::var("PGX_MANIFEST_PATH") {
@@ -286,16 +366,184 @@ fn install_extension() -> eyre::Result<()> {
     if is_release {
         command.arg("--release");
     }
-    if no_schema {
-        command.arg("--no-schema");
+    Ok(command)
+}
+
+/// Compare the SQL schema of the extension being tested with the snapshot checked in at
+/// `snapshot_path`, relative to the crate's `Cargo.toml`, panicking with a diff if they differ.
+///
+/// This catches a change to the extension's SQL, such as a function changing its signature or a
+/// type being renamed, when running `cargo pgx test` rather than when packaging a release:
+///
+/// ```rust,no_run
+/// #[cfg(any(test, feature = "pg_test"))]
+/// #[pgx::pg_schema]
+/// mod tests {
+///     #[test]
+///     fn schema_is_unchanged() {
+///         pgx_tests::assert_schema_matches!("sql/schema.snapshot.sql");
+///     }
+/// }
+/// ```
+///
+/// When the change is intended, run the tests with `PGX_UPDATE_SNAPSHOTS=true` to write the
+/// new schema to the snapshot, and review it along with the rest of the change.  The line
+/// numbers in the comments saying where each item is defined are left out of the comparison, so
+/// moving code around doesn't change the snapshot.
+#[macro_export]
+macro_rules! assert_schema_matches {
+    ($snapshot_path:expr) => {
+        $crate::assert_schema_matches_snapshot(
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($snapshot_path),
+        )
+    };
+}
+
+/// The function behind [`assert_schema_matches!`], taking the snapshot's full path
+pub fn assert_schema_matches_snapshot(snapshot_path: impl AsRef<Path>) {
+    let snapshot_path = snapshot_path.as_ref();
+    let update = std::env::var("PGX_UPDATE_SNAPSHOTS").unwrap_or("false".into()) == "true";
+    let schema = generate_schema().expect("failed to generate the schema");
+
+    if update {
+        if let Some(parent) = snapshot_path.parent() {
+            std::fs::create_dir_all(parent).expect("failed to create the snapshot's directory");
+        }
+        std::fs::write(snapshot_path, &schema).expect("failed to write the snapshot");
+        eprintln!("updated schema snapshot {}", snapshot_path.display());
+        return;
     }
 
-    let mut child = command.spawn().unwrap();
-    let status = child.wait().unwrap();
+    let snapshot = match std::fs::read_to_string(snapshot_path) {
+        Ok(snapshot) => snapshot,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => panic!(
+            "schema snapshot {} doesn't exist, run the tests with PGX_UPDATE_SNAPSHOTS=true to create it",
+            snapshot_path.display()
+        ),
+        Err(e) => panic!(
+            "failed to read schema snapshot {}: {}",
+            snapshot_path.display(),
+            e
+        ),
+    };
+
+    let expected = normalize_schema(&snapshot);
+    let actual = normalize_schema(&schema);
+    if expected != actual {
+        panic!(
+            "the schema doesn't match snapshot {}, run the tests with PGX_UPDATE_SNAPSHOTS=true to update it if this is intended\n\n{}",
+            snapshot_path.display(),
+            diff_lines(&expected, &actual)
+        );
+    }
+}
+
+/// Run `cargo pgx schema`, returning the SQL it generates
+fn generate_schema() -> eyre::Result<String> {
+    let mut out = get_target_dir()?;
+    out.push(format!("pgx-schema-snapshot-{}.sql", get_extension_name()));
+
+    let mut command = cargo_pgx_command("schema")?;
+    command
+        .arg("--out")
+        .arg(&out)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    let status = command
+        .status()
+        .wrap_err("failed to run `cargo pgx schema`")?;
     if !status.success() {
-        return Err(eyre!("failed to install extension"));
+        return Err(eyre!("failed to generate the schema"));
     }
-    Ok(())
+    std::fs::read_to_string(&out).wrap_err("failed to read the generated schema")
+}
+
+/// The lines of `schema` to compare, without the line numbers of the `-- src/lib.rs:42`
+/// comments, or trailing whitespace
+fn normalize_schema(schema: &str) -> Vec<String> {
+    let location = regex::Regex::new(r"^(-- .+\.rs):\d+$").unwrap();
+    schema
+        .lines()
+        .map(|line| location.replace(line.trim_end(), "$1").into_owned())
+        .collect()
+}
+
+/// A diff of the lines that changed from `expected` to `actual`, with a few lines of context
+fn diff_lines(expected: &[String], actual: &[String]) -> String {
+    const CONTEXT: usize = 3;
+
+    let prefix = expected
+        .iter()
+        .zip(actual)
+        .take_while(|(e, a)| e == a)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+    let removed = &expected[prefix..expected.len() - suffix];
+    let added = &actual[prefix..actual.len() - suffix];
+
+    let mut diff = String::new();
+    writeln!(
+        diff,
+        "@@ -{},{} +{},{} @@",
+        prefix + 1,
+        removed.len(),
+        prefix + 1,
+        added.len()
+    )
+    .unwrap();
+    for line in &expected[prefix.saturating_sub(CONTEXT)..prefix] {
+        writeln!(diff, " {}", line).unwrap();
+    }
+
+    // Match up the lines in between, unless there are too many to compare every pair
+    if removed.len() * added.len() <= 1_000_000 {
+        let mut common = vec![vec![0u32; added.len() + 1]; removed.len() + 1];
+        for i in (0..removed.len()).rev() {
+            for j in (0..added.len()).rev() {
+                common[i][j] = if removed[i] == added[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < removed.len() || j < added.len() {
+            if i < removed.len() && j < added.len() && removed[i] == added[j] {
+                writeln!(diff, " {}", removed[i]).unwrap();
+                i += 1;
+                j += 1;
+            } else if j == added.len()
+                || (i < removed.len() && common[i + 1][j] >= common[i][j + 1])
+            {
+                writeln!(diff, "{}", format!("-{}", removed[i]).red()).unwrap();
+                i += 1;
+            } else {
+                writeln!(diff, "{}", format!("+{}", added[j]).green()).unwrap();
+                j += 1;
+            }
+        }
+    } else {
+        for line in removed {
+            writeln!(diff, "{}", format!("-{}", line).red()).unwrap();
+        }
+        for line in added {
+            writeln!(diff, "{}", format!("+{}", line).green()).unwrap();
+        }
+    }
+
+    let end = expected.len() - suffix;
+    for line in &expected[end..(end + CONTEXT).min(expected.len())] {
+        writeln!(diff, " {}", line).unwrap();
+    }
+    diff
 }
 
 fn initdb(postgresql_conf: Vec<&'static str>) -> eyre::Result<()> {
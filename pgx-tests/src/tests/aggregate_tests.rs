@@ -105,6 +105,62 @@ impl Aggregate for DemoUnique {
     }
 }
 
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoParallelSum;
+
+#[pg_aggregate]
+impl Aggregate for DemoParallelSum {
+    const PARALLEL: Option<ParallelOption> = Some(pgx::aggregate::ParallelOption::Safe);
+
+    type Args = i64;
+    type State = Internal;
+    type Finalize = i64;
+
+    #[pgx(parallel_safe)]
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        *unsafe { current.get_or_insert_default::<i64>() } += arg;
+        current
+    }
+
+    #[pgx(parallel_safe)]
+    fn combine(
+        mut first: Self::State,
+        mut second: Self::State,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let second = *unsafe { second.get_or_insert_default::<i64>() };
+        *unsafe { first.get_or_insert_default::<i64>() } += second;
+        first
+    }
+
+    #[pgx(parallel_safe, strict)]
+    fn serial(mut current: Self::State, _fcinfo: pg_sys::FunctionCallInfo) -> Vec<u8> {
+        unsafe { current.get_or_insert_default::<i64>() }
+            .to_le_bytes()
+            .to_vec()
+    }
+
+    #[pgx(parallel_safe, strict)]
+    fn deserial(buf: Vec<u8>, _fcinfo: pg_sys::FunctionCallInfo) -> Self::State {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf);
+        Internal::new(i64::from_le_bytes(bytes))
+    }
+
+    #[pgx(parallel_safe)]
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        *unsafe { current.get_or_insert_default::<i64>() }
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug, PostgresType, Serialize, Deserialize)]
 pub struct DemoPercentileDisc;
 
@@ -178,6 +234,20 @@ mod tests {
         assert_eq!(retval, 2);
     }
 
+    #[pg_test]
+    fn aggregate_demo_parallel_sum() {
+        Spi::run("SET LOCAL parallel_setup_cost = 0");
+        Spi::run("SET LOCAL parallel_tuple_cost = 0");
+        Spi::run("SET LOCAL min_parallel_table_scan_size = 0");
+        Spi::run("SET LOCAL max_parallel_workers_per_gather = 2");
+        Spi::run("CREATE TABLE demo_parallel_sum_values AS SELECT generate_series(1, 10000)::bigint AS value");
+
+        let retval =
+            Spi::get_one::<i64>("SELECT DemoParallelSum(value) FROM demo_parallel_sum_values;")
+                .expect("SQL select failed");
+        assert_eq!(retval, 50005000);
+    }
+
     #[pg_test]
     fn aggregate_demo_percentile_disc() {
         // Example from https://www.postgresql.org/docs/current/xaggr.html#XAGGR-ORDERED-SET-AGGREGATES
@@ -0,0 +1,94 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+fn type_name(oid: pg_sys::Oid) -> String {
+    unsafe { std::ffi::CStr::from_ptr(pg_sys::format_type_be(oid)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[pg_extern]
+fn anyelement_type_name(element: AnyElement) -> String {
+    type_name(element.oid())
+}
+
+/// Whichever of the two is larger, for any integer type
+#[pg_extern]
+fn anyelement_greater(left: AnyElement, right: AnyElement) -> AnyElement {
+    match left.oid() {
+        pg_sys::INT4OID => {
+            let (l, r) = (left.into::<i32>().unwrap(), right.into::<i32>().unwrap());
+            AnyElement::from_value(l.max(r)).unwrap()
+        }
+        pg_sys::INT8OID => {
+            let (l, r) = (left.into::<i64>().unwrap(), right.into::<i64>().unwrap());
+            AnyElement::from_value(l.max(r)).unwrap()
+        }
+        _ => panic!("only integers can be compared"),
+    }
+}
+
+#[pg_extern]
+fn anynonarray_identity(value: AnyNonArray) -> AnyNonArray {
+    value
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+#[pg_extern]
+fn anycompatible_type_name(left: AnyCompatible, _right: AnyCompatible) -> String {
+    type_name(left.oid())
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_anyelement_oid() {
+        let name = Spi::get_one::<String>("SELECT anyelement_type_name(now())")
+            .expect("anyelement_type_name() returned null");
+        assert_eq!(name, "timestamp with time zone");
+    }
+
+    #[pg_test]
+    fn test_anyelement_return() {
+        let greater = Spi::get_one::<i32>("SELECT anyelement_greater(4, 2)")
+            .expect("anyelement_greater() returned null");
+        assert_eq!(greater, 4);
+        let greater = Spi::get_one::<i64>("SELECT anyelement_greater(4::bigint, 12)")
+            .expect("anyelement_greater() returned null");
+        assert_eq!(greater, 12);
+    }
+
+    #[pg_test]
+    fn test_anynonarray() {
+        let text = Spi::get_one::<String>("SELECT anynonarray_identity('hello'::text)")
+            .expect("anynonarray_identity() returned null");
+        assert_eq!(text, "hello");
+    }
+
+    #[pg_test(error = "function anynonarray_identity(integer[]) does not exist")]
+    fn test_anynonarray_rejects_arrays() {
+        Spi::get_one::<Vec<i32>>("SELECT anynonarray_identity(ARRAY[1, 2])");
+    }
+
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    fn test_anycompatible() {
+        let name = Spi::get_one::<String>("SELECT anycompatible_type_name(1, 2.5)")
+            .expect("anycompatible_type_name() returned null");
+        assert_eq!(name, "numeric");
+    }
+}
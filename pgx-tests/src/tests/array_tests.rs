@@ -34,12 +34,17 @@ fn sum_array_i64(values: Array<i64>) -> i64 {
 
 #[pg_extern(name = "sum_array_siced")]
 fn sum_array_i32_sliced(values: Array<i32>) -> i32 {
-    values.as_slice().iter().sum()
+    values.as_slice().expect("not an integer[]").iter().sum()
 }
 
 #[pg_extern(name = "sum_array_sliced")]
 fn sum_array_i64_sliced(values: Array<i64>) -> i64 {
-    values.as_slice().iter().sum()
+    values.as_slice().expect("not a bigint[]").iter().sum()
+}
+
+#[pg_extern]
+fn sum_array_f64_sliced(values: Array<f64>) -> f64 {
+    values.as_slice().expect("not a float8[]").iter().sum()
 }
 
 #[pg_extern]
@@ -49,7 +54,12 @@ fn count_true(values: Array<bool>) -> i32 {
 
 #[pg_extern]
 fn count_true_sliced(values: Array<bool>) -> i32 {
-    values.as_slice().iter().filter(|b| **b).count() as i32
+    values
+        .as_slice()
+        .expect("not a boolean[]")
+        .iter()
+        .filter(|b| **b)
+        .count() as i32
 }
 
 #[pg_extern]
@@ -57,6 +67,19 @@ fn count_nulls(values: Array<i32>) -> i32 {
     values.iter().map(|v| v.is_none()).filter(|v| *v).count() as i32
 }
 
+#[pg_extern]
+fn array_null_positions(values: Array<i32>) -> Vec<i32> {
+    (0..values.len())
+        .filter(|i| values.is_null(*i))
+        .map(|i| i as i32)
+        .collect()
+}
+
+#[pg_extern]
+fn get_array_element(values: Array<&str>, index: i32) -> Option<String> {
+    values.get(index as usize).flatten().map(str::to_string)
+}
+
 #[pg_extern]
 fn optional_array_arg(values: Option<Array<f32>>) -> f32 {
     values.unwrap().iter().map(|v| v.unwrap_or(0f32)).sum()
@@ -99,6 +122,29 @@ fn return_zero_length_vec() -> Vec<i32> {
     Vec::new()
 }
 
+#[pg_extern]
+fn array_shape(values: Array<i32>) -> Vec<i32> {
+    let mut shape = values.dims().to_vec();
+    shape.extend_from_slice(values.lower_bounds());
+    shape
+}
+
+#[pg_extern]
+fn transpose_matrix(matrix: Vec<Vec<i32>>) -> Vec<Vec<i32>> {
+    let columns = matrix.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|column| matrix.iter().map(|row| row[column]).collect())
+        .collect()
+}
+
+#[pg_extern]
+fn zero_based_identity_matrix(n: i32) -> ArrayBuilder<i32> {
+    let n = n as usize;
+    let mut matrix = ArrayBuilder::new(&[n, n]).lower_bounds(&[0, 0]);
+    matrix.extend((0..n * n).map(|i| Some((i % (n + 1) == 0) as i32)));
+    matrix
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -136,6 +182,31 @@ mod tests {
         assert_eq!(sum.unwrap(), 6);
     }
 
+    #[pg_test]
+    fn test_sum_array_f64_sliced() {
+        let sum = Spi::get_one::<f64>("SELECT sum_array_f64_sliced(ARRAY[1.5, 2.5, -1]::float8[])");
+        assert_eq!(sum, Some(3.0));
+    }
+
+    #[pg_test]
+    fn test_sliced_array_of_other_type() {
+        let oids = vec![1u32, 2].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(oids, false, pg_sys::OIDARRAYOID) }.unwrap();
+        assert_eq!(array.as_slice(), None);
+        let array = unsafe { Array::<u32>::from_datum(oids, false, pg_sys::OIDARRAYOID) }.unwrap();
+        assert_eq!(array.as_slice(), Some(&[1u32, 2][..]));
+
+        let chars = vec![b'a' as i8, 0].into_datum().unwrap();
+        let array =
+            unsafe { Array::<bool>::from_datum(chars, false, pg_sys::CHARARRAYOID) }.unwrap();
+        assert_eq!(array.as_slice(), None);
+    }
+
+    #[pg_test(error = "array contains NULL")]
+    fn test_sliced_array_with_nulls() {
+        Spi::get_one::<f64>("SELECT sum_array_f64_sliced(ARRAY[1, NULL]::float8[])");
+    }
+
     #[pg_test(error = "attempt to add with overflow")]
     fn test_sum_array_i32_overflow() {
         Spi::get_one::<i64>(
@@ -164,6 +235,31 @@ mod tests {
         assert_eq!(cnt.unwrap(), 2);
     }
 
+    #[pg_test]
+    fn test_array_null_positions() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT array_null_positions(ARRAY[NULL, 1, NULL, 2]::integer[]) = ARRAY[0, 2]",
+        );
+        assert_eq!(rc, Some(true));
+        let rc = Spi::get_one::<bool>(
+            "SELECT array_null_positions(ARRAY[1, 2]::integer[]) = ARRAY[]::integer[]",
+        );
+        assert_eq!(rc, Some(true));
+    }
+
+    #[pg_test]
+    fn test_get_array_element() {
+        let element =
+            Spi::get_one::<String>("SELECT get_array_element(ARRAY['one', NULL, 'three'], 2)");
+        assert_eq!(element.as_deref(), Some("three"));
+        let element =
+            Spi::get_one::<String>("SELECT get_array_element(ARRAY['one', NULL, 'three'], 1)");
+        assert_eq!(element, None);
+        let element =
+            Spi::get_one::<String>("SELECT get_array_element(ARRAY['one', NULL, 'three'], 9)");
+        assert_eq!(element, None);
+    }
+
     #[pg_test]
     fn test_optional_array() {
         let sum = Spi::get_one::<f32>("SELECT optional_array_arg(ARRAY[1,2,3]::real[])");
@@ -219,6 +315,41 @@ mod tests {
         assert!(rc)
     }
 
+    #[pg_test]
+    fn test_array_shape() {
+        let shape =
+            Spi::get_one::<Vec<i32>>("SELECT array_shape('[0:1][2:4]={{1,2,3},{4,5,6}}'::int[])")
+                .expect("failed to get SPI result");
+        assert_eq!(shape, vec![2, 3, 0, 2]);
+
+        let shape = Spi::get_one::<Vec<i32>>("SELECT array_shape('{}'::int[])")
+            .expect("failed to get SPI result");
+        assert_eq!(shape, Vec::<i32>::new());
+    }
+
+    #[pg_test]
+    fn test_transpose_matrix() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT transpose_matrix(ARRAY[[1,2,3],[4,5,6]]) = ARRAY[[1,4],[2,5],[3,6]]",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_read_nested_vec() {
+        let matrix = Spi::get_one::<Vec<Vec<Option<i32>>>>("SELECT ARRAY[[1,NULL],[3,4]]")
+            .expect("failed to get SPI result");
+        assert_eq!(matrix, vec![vec![Some(1), None], vec![Some(3), Some(4)]]);
+    }
+
+    #[pg_test]
+    fn test_array_builder() {
+        let matrix = Spi::get_one::<String>("SELECT zero_based_identity_matrix(2)::text")
+            .expect("failed to get SPI result");
+        assert_eq!(matrix, "[0:1][0:1]={{1,0},{0,1}}");
+    }
+
     #[pg_test]
     fn test_slice_to_array() {
         let owned_vec = vec![Some(1), Some(2), Some(3), None, Some(4)];
@@ -0,0 +1,189 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::bgworkers::*;
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE async_worker_results (
+    id serial PRIMARY KEY,
+    worker text NOT NULL,
+    result text NOT NULL
+);
+"#,
+    name = "create_async_worker_results"
+);
+
+fn insert_result(worker: &str, result: &str) {
+    Spi::run(&format!(
+        "INSERT INTO async_worker_results (worker, result) VALUES ('{}', '{}')",
+        worker, result
+    ));
+}
+
+/// Every worker connects to the database of the test which started it, given as its `extra`
+fn connect_worker() {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(Some(BackgroundWorker::get_extra()), None);
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn async_transaction_worker_main(_arg: pg_sys::Datum) {
+    connect_worker();
+    let count = run_async(|pg| async move {
+        pg.transaction(|| insert_result("transaction", "inserted"))
+            .await;
+        // committed, so a new transaction sees it
+        pg.transaction(|| {
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM async_worker_results WHERE worker = 'transaction'",
+            )
+        })
+        .await
+    });
+    BackgroundWorker::transaction(move || insert_result("transaction", &format!("{:?}", count)));
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn async_shutdown_worker_main(_arg: pg_sys::Datum) {
+    connect_worker();
+    let finished = run_async(|pg| async move {
+        pg.transaction(|| insert_result("shutdown", "started"))
+            .await;
+        pg.shutdown().await;
+        let shutting_down = pg.is_shutting_down();
+        pg.transaction(move || insert_result("shutdown", &format!("{}", shutting_down)))
+            .await;
+        // never finishes on its own, so it's cancelled once the grace period is up
+        std::future::pending::<()>().await
+    });
+    let result = if finished.is_some() {
+        "finished"
+    } else {
+        "cancelled"
+    };
+    BackgroundWorker::transaction(move || insert_result("shutdown", result));
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn async_panic_worker_main(_arg: pg_sys::Datum) {
+    connect_worker();
+    let panic = std::panic::catch_unwind(|| {
+        run_async(|_pg| async {
+            panic!("the future panicked");
+        })
+    });
+    let result = match panic {
+        Ok(_) => String::from("no panic"),
+        Err(e) => e
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .unwrap_or_else(|| String::from("an unknown panic")),
+    };
+    BackgroundWorker::transaction(move || insert_result("panic", &result));
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::os::raw::c_char;
+    use std::time::Duration;
+
+    fn set_name(dest: &mut [c_char], name: &str) {
+        for (dest, src) in dest.iter_mut().zip(name.as_bytes()) {
+            *dest = *src as c_char;
+        }
+    }
+
+    /// Start a dynamic background worker running `function`, and wait until it's running
+    fn start_worker(function: &str) -> *mut pg_sys::BackgroundWorkerHandle {
+        let database =
+            Spi::get_one::<String>("SELECT current_database()::text").expect("no database");
+        let mut worker = pg_sys::BackgroundWorker::default();
+        set_name(&mut worker.bgw_name, function);
+        set_name(&mut worker.bgw_library_name, "pgx_tests");
+        set_name(&mut worker.bgw_function_name, function);
+        set_name(&mut worker.bgw_extra, &database);
+        worker.bgw_flags =
+            (pg_sys::BGWORKER_SHMEM_ACCESS | pg_sys::BGWORKER_BACKEND_DATABASE_CONNECTION) as i32;
+        worker.bgw_start_time = pg_sys::BgWorkerStartTime_BgWorkerStart_RecoveryFinished;
+        worker.bgw_restart_time = pg_sys::BGW_NEVER_RESTART;
+        worker.bgw_notify_pid = unsafe { pg_sys::MyProcPid };
+
+        let mut handle = std::ptr::null_mut();
+        unsafe {
+            assert!(
+                pg_sys::RegisterDynamicBackgroundWorker(&mut worker, &mut handle),
+                "no background worker slot is free"
+            );
+            let mut pid = 0;
+            assert_eq!(
+                pg_sys::WaitForBackgroundWorkerStartup(handle, &mut pid),
+                pg_sys::BgwHandleStatus_BGWH_STARTED
+            );
+        }
+        handle
+    }
+
+    fn wait_for_worker(handle: *mut pg_sys::BackgroundWorkerHandle) {
+        assert_eq!(
+            unsafe { pg_sys::WaitForBackgroundWorkerShutdown(handle) },
+            pg_sys::BgwHandleStatus_BGWH_STOPPED
+        );
+    }
+
+    /// What the worker recorded, in order, each committed in a transaction of its own
+    fn results(worker: &str) -> String {
+        Spi::get_one::<String>(&format!(
+            "SELECT string_agg(result, ', ' ORDER BY id) FROM async_worker_results \
+             WHERE worker = '{}'",
+            worker
+        ))
+        .unwrap_or_default()
+    }
+
+    #[pg_test]
+    fn test_async_transaction() {
+        let worker = start_worker("async_transaction_worker_main");
+        wait_for_worker(worker);
+        assert_eq!(results("transaction"), "inserted, Some(Some(1))");
+    }
+
+    #[pg_test]
+    fn test_async_shutdown_cancels_after_grace_period() {
+        let worker = start_worker("async_shutdown_worker_main");
+        // the worker's own SIGTERM handler must be attached before it's sent one
+        for _ in 0..1000 {
+            if !results("shutdown").is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(results("shutdown"), "started");
+
+        unsafe { pg_sys::TerminateBackgroundWorker(worker) };
+        wait_for_worker(worker);
+        assert_eq!(results("shutdown"), "started, true, cancelled");
+    }
+
+    #[pg_test]
+    fn test_async_panic() {
+        let worker = start_worker("async_panic_worker_main");
+        wait_for_worker(worker);
+        assert_eq!(results("panic"), "the future panicked");
+    }
+}
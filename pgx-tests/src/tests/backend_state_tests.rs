@@ -0,0 +1,55 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[derive(Default)]
+struct Counter {
+    count: i64,
+}
+
+#[pg_extern_methods]
+impl Counter {
+    fn increment(&mut self, by: default!(i64, 1)) -> i64 {
+        self.count += by;
+        self.count
+    }
+
+    #[pgx(name = "current_count")]
+    fn current(&self) -> i64 {
+        self.count
+    }
+
+    fn increment_through_spi(&mut self) -> i64 {
+        Spi::get_one::<i64>("SELECT increment()").unwrap()
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_pg_extern_methods_share_state() {
+        assert_eq!(Spi::get_one::<i64>("SELECT increment()"), Some(1));
+        assert_eq!(Spi::get_one::<i64>("SELECT increment(10)"), Some(11));
+        assert_eq!(Spi::get_one::<i64>("SELECT current_count()"), Some(11));
+    }
+
+    #[pg_test(
+        error = "pgx_tests::tests::backend_state_tests::Counter is in use by another function"
+    )]
+    fn test_pg_extern_methods_reentrant() {
+        Spi::get_one::<i64>("SELECT increment_through_spi()");
+    }
+}
@@ -0,0 +1,61 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::backend_status::{self, BackendState};
+    use pgx::*;
+
+    #[pg_test]
+    fn test_this_backend() {
+        let pid = unsafe { pg_sys::MyProcPid };
+        let this = backend_status::backend(pid).expect("this backend isn't listed");
+        assert_eq!(this.pid, pid);
+        assert_eq!(this.database_oid, unsafe { pg_sys::MyDatabaseId });
+        assert_eq!(this.user_oid, unsafe { pg_sys::GetSessionUserId() });
+        assert_eq!(this.backend_type, "client backend");
+        assert_eq!(this.state, Some(BackendState::Active));
+        assert_eq!(this.wait_event, None);
+        assert!(this.xact_start.is_some());
+        assert!(this.backend_start.is_some());
+    }
+
+    #[pg_test]
+    fn test_backends_match_pg_stat_activity() {
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM pg_stat_activity")
+            .expect("count() returned null");
+        assert_eq!(backend_status::backends().len() as i64, count);
+    }
+
+    #[pg_test]
+    fn test_refresh() {
+        Spi::run("SET application_name = 'before'");
+        let pid = unsafe { pg_sys::MyProcPid };
+        backend_status::refresh();
+        let before = backend_status::backend(pid).and_then(|this| this.application_name);
+        assert_eq!(before, Some("before".to_string()));
+
+        Spi::run("SET application_name = 'after'");
+        // the snapshot stays the same until it's refreshed
+        let unchanged = backend_status::backend(pid).and_then(|this| this.application_name);
+        assert_eq!(unchanged, Some("before".to_string()));
+        backend_status::refresh();
+        let after = backend_status::backend(pid).and_then(|this| this.application_name);
+        assert_eq!(after, Some("after".to_string()));
+    }
+
+    #[pg_test]
+    fn test_unknown_pid() {
+        assert_eq!(backend_status::backend(-1), None);
+    }
+}
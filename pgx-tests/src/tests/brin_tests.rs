@@ -0,0 +1,132 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+/// A timestamp, indexed by the earliest and latest in each range of pages
+#[derive(Debug, Serialize, Deserialize, PostgresType, PostgresBrinOps)]
+#[brin(
+    default,
+    storage = "StampRange",
+    operators(1 = "<", 3 = "=", 5 = ">"),
+    requires = [StampRange, stamp_lt, stamp_eq, stamp_gt]
+)]
+pub struct Stamp(i64);
+
+/// The earliest and latest stamps in a range of pages
+#[derive(Debug, Serialize, Deserialize, PostgresType)]
+pub struct StampRange {
+    lo: i64,
+    hi: i64,
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "<")]
+fn stamp_lt(left: Stamp, right: Stamp) -> bool {
+    left.0 < right.0
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "=")]
+fn stamp_eq(left: Stamp, right: Stamp) -> bool {
+    left.0 == right.0
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = ">")]
+fn stamp_gt(left: Stamp, right: Stamp) -> bool {
+    left.0 > right.0
+}
+
+impl BrinSupport for Stamp {
+    type Summary = StampRange;
+
+    fn summarize(value: Stamp) -> StampRange {
+        StampRange {
+            lo: value.0,
+            hi: value.0,
+        }
+    }
+
+    fn add_value(summary: &mut StampRange, value: Stamp) -> bool {
+        if summary.lo <= value.0 && value.0 <= summary.hi {
+            return false;
+        }
+        summary.lo = summary.lo.min(value.0);
+        summary.hi = summary.hi.max(value.0);
+        true
+    }
+
+    fn consistent(summary: &StampRange, query: &Stamp, strategy: u16) -> bool {
+        match strategy {
+            1 => summary.lo < query.0,
+            3 => summary.lo <= query.0 && query.0 <= summary.hi,
+            5 => summary.hi > query.0,
+            _ => panic!("unknown strategy {}", strategy),
+        }
+    }
+
+    fn union(summary: &mut StampRange, other: StampRange) {
+        summary.lo = summary.lo.min(other.lo);
+        summary.hi = summary.hi.max(other.hi);
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_stamped() {
+        Spi::run(
+            "CREATE TABLE stamped AS
+                SELECT x::text::Stamp AS stamp FROM generate_series(1, 10000) x;
+             INSERT INTO stamped SELECT NULL FROM generate_series(1, 100);
+             CREATE INDEX stamped_brin ON stamped USING brin (stamp);
+             ANALYZE stamped;
+             SET LOCAL enable_seqscan = off;",
+        );
+    }
+
+    #[pg_test]
+    fn test_brin_equal() {
+        create_stamped();
+        let query = "SELECT count(*) FROM stamped WHERE stamp = '5000'";
+        assert!(Spi::explain(query).0.to_string().contains("stamped_brin"));
+        assert_eq!(Spi::get_one::<i64>(query), Some(1));
+    }
+
+    #[pg_test]
+    fn test_brin_range() {
+        create_stamped();
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM stamped WHERE stamp < '100'");
+        assert_eq!(count, Some(99));
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM stamped WHERE stamp > '9000'");
+        assert_eq!(count, Some(1000));
+    }
+
+    #[pg_test]
+    fn test_brin_nulls() {
+        create_stamped();
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM stamped WHERE stamp IS NULL");
+        assert_eq!(count, Some(100));
+    }
+
+    #[pg_test]
+    fn test_brin_opclass() {
+        let functions = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_amproc
+               JOIN pg_opfamily ON amprocfamily = pg_opfamily.oid
+              WHERE opfname = 'stamp_brin_ops'",
+        );
+        assert_eq!(functions, Some(4));
+    }
+}
@@ -62,4 +62,36 @@ mod tests {
             .expect("SPI result was null");
         assert_eq!(vec.as_slice(), b"bcd")
     }
+
+    #[pg_test]
+    fn test_detoast_slice() {
+        let bytes = rust_byte_slice_to_bytea(b"abcdefg").into_pg();
+        unsafe {
+            assert_eq!(detoast_packed(bytes), bytes);
+            assert_eq!(varlena_slice_to_byte_slice(bytes, 1, 3), b"bcd");
+            assert_eq!(varlena_slice_to_byte_slice(bytes, 5, 100), b"fg");
+        }
+    }
+
+    #[pg_test]
+    fn test_detoast_slice_external() {
+        Spi::run("CREATE TABLE toasted (bytes bytea)");
+        Spi::run("ALTER TABLE toasted ALTER COLUMN bytes SET STORAGE EXTERNAL");
+        Spi::run("INSERT INTO toasted SELECT decode(repeat('0123456789', 10000), 'escape')");
+        let tail = Spi::connect(|client| {
+            let datum = client
+                .select("SELECT bytes FROM toasted", None, None)
+                .first()
+                .get_datum::<pg_sys::Datum>(1)
+                .expect("bytes was null");
+            let varlena = datum as *mut pg_sys::varlena;
+            unsafe {
+                assert!(varatt_is_external(varlena));
+                Ok(Some(
+                    varlena_slice_to_byte_slice(varlena, 99_990, 10).to_vec(),
+                ))
+            }
+        });
+        assert_eq!(tail.as_deref(), Some(&b"0123456789"[..]));
+    }
 }
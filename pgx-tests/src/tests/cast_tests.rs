@@ -0,0 +1,86 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, PostgresType)]
+pub struct Celsius(f64);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, PostgresType)]
+pub struct Fahrenheit(f64);
+
+#[pg_cast(implicit, immutable, parallel_safe)]
+fn celsius_to_float8(celsius: Celsius) -> f64 {
+    celsius.0
+}
+
+#[pg_cast(assignment, immutable, parallel_safe)]
+fn float8_to_celsius(degrees: f64) -> Celsius {
+    Celsius(degrees)
+}
+
+#[pg_cast(immutable, parallel_safe)]
+fn celsius_to_fahrenheit(celsius: Celsius) -> Fahrenheit {
+    Fahrenheit(celsius.0 * 9.0 / 5.0 + 32.0)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{Celsius, Fahrenheit};
+    use pgx::*;
+
+    #[pg_test]
+    fn test_cast_contexts() {
+        let contexts = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(castcontext::text ORDER BY castcontext)
+               FROM pg_cast
+              WHERE castsource IN ('Celsius'::regtype, 'Fahrenheit'::regtype)
+                 OR casttarget IN ('Celsius'::regtype, 'Fahrenheit'::regtype)",
+        );
+        assert_eq!(
+            contexts,
+            Some(vec!["a".to_string(), "e".to_string(), "i".to_string()])
+        );
+    }
+
+    #[pg_test]
+    fn test_explicit_cast() {
+        let fahrenheit = Spi::get_one::<Fahrenheit>("SELECT '100'::Celsius::Fahrenheit");
+        assert_eq!(fahrenheit, Some(Fahrenheit(212.0)));
+    }
+
+    #[pg_test]
+    fn test_implicit_cast() {
+        let root = Spi::get_one::<f64>("SELECT sqrt('16'::Celsius)");
+        assert_eq!(root, Some(4.0));
+    }
+
+    #[pg_test]
+    fn test_assignment_cast() {
+        Spi::run(
+            "CREATE TABLE temperatures (reading Celsius);
+             INSERT INTO temperatures VALUES (21.5::float8);",
+        );
+        let reading = Spi::get_one::<Celsius>("SELECT reading FROM temperatures");
+        assert_eq!(reading, Some(Celsius(21.5)));
+    }
+
+    #[pg_test(error = "column \"reading\" is of type fahrenheit but expression is of type celsius")]
+    fn test_explicit_cast_is_not_assignment() {
+        Spi::run(
+            "CREATE TABLE readings (reading Fahrenheit);
+             INSERT INTO readings VALUES ('100'::Celsius);",
+        );
+    }
+}
@@ -0,0 +1,127 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use pgx::*;
+
+#[pg_extern]
+fn accept_chrono_types(
+    d: NaiveDate,
+    t: NaiveTime,
+    ts: NaiveDateTime,
+    tstz: DateTime<Utc>,
+) -> NaiveDateTime {
+    assert_eq!(ts.date(), d);
+    assert_eq!(ts.time(), t);
+    assert_eq!(tstz.naive_utc(), ts);
+    ts
+}
+
+#[pg_extern]
+fn return_chrono_nanosecond_time() -> NaiveTime {
+    NaiveTime::from_hms_nano_opt(12, 34, 56, 789_012_999).unwrap()
+}
+
+#[pg_extern]
+fn return_chrono_nanosecond_timestamptz() -> DateTime<Utc> {
+    let naive = NaiveDate::from_ymd_opt(2022, 3, 12)
+        .unwrap()
+        .and_hms_nano_opt(12, 34, 56, 789_012_999)
+        .unwrap();
+    Utc.from_utc_datetime(&naive)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+    use pgx::*;
+    use std::convert::TryFrom;
+
+    #[pg_test]
+    fn test_chrono_round_trip() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_chrono_types('2022-03-12', '12:34:56.789012', \
+                '2022-03-12 12:34:56.789012', '2022-03-12 14:34:56.789012+02') \
+                = '2022-03-12 12:34:56.789012'::timestamp",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let tstz =
+            Spi::get_one::<DateTime<Utc>>("SELECT '2000-01-01 00:00:00.000001+00'::timestamptz")
+                .expect("failed to get SPI result");
+        let expected = NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_micro_opt(0, 0, 0, 1)
+            .unwrap();
+        assert_eq!(tstz, Utc.from_utc_datetime(&expected));
+    }
+
+    #[pg_test]
+    fn test_chrono_truncates_to_microseconds() {
+        let result =
+            Spi::get_one::<bool>("SELECT return_chrono_nanosecond_time() = '12:34:56.789012'")
+                .expect("failed to get SPI result");
+        assert!(result);
+
+        let result = Spi::get_one::<bool>(
+            "SELECT return_chrono_nanosecond_timestamptz() = '2022-03-12 12:34:56.789012+00'",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let time = Spi::get_one::<NaiveTime>("SELECT return_chrono_nanosecond_time()")
+            .expect("failed to get SPI result");
+        assert_eq!(time.nanosecond(), 789_012_000);
+    }
+
+    #[pg_test]
+    fn test_chrono_infinity() {
+        let date = Spi::get_one::<Date>("SELECT 'infinity'::date").expect("SPI result was null");
+        assert_eq!(
+            NaiveDate::try_from(date),
+            Err(DateTimeConversionError::Infinite)
+        );
+
+        let timestamp = Spi::get_one::<Timestamp>("SELECT '-infinity'::timestamp")
+            .expect("SPI result was null");
+        assert_eq!(
+            NaiveDateTime::try_from(timestamp),
+            Err(DateTimeConversionError::Infinite)
+        );
+
+        let timestamp = Spi::get_one::<TimestampWithTimeZone>("SELECT 'infinity'::timestamptz")
+            .expect("SPI result was null");
+        assert_eq!(
+            DateTime::<Utc>::try_from(timestamp),
+            Err(DateTimeConversionError::Infinite)
+        );
+    }
+
+    #[pg_test]
+    fn test_chrono_duration() {
+        let interval = Spi::get_one::<Interval>("SELECT '1 day 2 seconds'::interval")
+            .expect("SPI result was null");
+        assert_eq!(
+            chrono::Duration::try_from(interval),
+            Ok(chrono::Duration::days(1) + chrono::Duration::seconds(2))
+        );
+
+        let interval =
+            Spi::get_one::<Interval>("SELECT '1 month'::interval").expect("SPI result was null");
+        assert_eq!(
+            chrono::Duration::try_from(interval),
+            Err(DateTimeConversionError::IntervalHasMonths)
+        );
+    }
+}
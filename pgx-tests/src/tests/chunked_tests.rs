@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::io::Read;
+
+    /// `len` bytes counting up from zero
+    fn counting(len: i64) -> impl Read {
+        std::io::Cursor::new((0..len).map(|i| i as u8).collect::<Vec<_>>())
+    }
+
+    #[pg_extern]
+    fn counting_chunks(len: i64, chunk_size: i32) -> impl Iterator<Item = Vec<u8>> {
+        ByteaChunks::new(counting(len)).chunk_size(chunk_size as usize)
+    }
+
+    #[pg_extern]
+    fn counting_large_object(len: i64, chunk_size: i32) -> pg_sys::Oid {
+        ByteaChunks::new(counting(len))
+            .chunk_size(chunk_size as usize)
+            .into_large_object()
+    }
+
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "disk on fire",
+            ))
+        }
+    }
+
+    #[pg_test]
+    fn test_chunks() {
+        let lengths = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(length(chunk)) FROM tests.counting_chunks(10, 4) AS chunk",
+        )
+        .expect("no chunks");
+        assert_eq!(lengths, vec![4, 4, 2]);
+
+        let whole = Spi::get_one::<Vec<u8>>(
+            "SELECT string_agg(chunk, ''::bytea) FROM tests.counting_chunks(300, 7) AS chunk",
+        )
+        .expect("no chunks");
+        assert_eq!(whole, (0..300).map(|i| i as u8).collect::<Vec<_>>());
+    }
+
+    #[pg_test]
+    fn test_no_chunks() {
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM tests.counting_chunks(0, 4)");
+        assert_eq!(count, Some(0));
+    }
+
+    #[pg_test]
+    fn test_chunks_into_large_object() {
+        let whole = Spi::get_one::<Vec<u8>>("SELECT lo_get(tests.counting_large_object(300, 7))")
+            .expect("large object was null");
+        assert_eq!(whole, (0..300).map(|i| i as u8).collect::<Vec<_>>());
+
+        let empty = Spi::get_one::<Vec<u8>>("SELECT lo_get(tests.counting_large_object(0, 7))")
+            .expect("large object was null");
+        assert!(empty.is_empty());
+    }
+
+    #[pg_test(error = "could not read the next chunk: disk on fire")]
+    fn test_chunks_read_error() {
+        ByteaChunks::new(Broken).for_each(drop);
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk size must be between 1 and 1073741819 bytes, but was 0")]
+    fn test_zero_chunk_size() {
+        ByteaChunks::new(Broken).chunk_size(0);
+    }
+}
@@ -0,0 +1,130 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_composite]
+#[derive(Debug, PartialEq)]
+struct Cat {
+    name: String,
+    lives: i32,
+    toy: Option<Toy>,
+}
+
+#[pg_composite]
+#[derive(Debug, PartialEq)]
+struct Toy {
+    name: String,
+    squeaks: Option<bool>,
+}
+
+#[pgx::pg_schema]
+mod pets {
+    use pgx::*;
+
+    #[pg_composite]
+    pub struct Hamster {
+        name: String,
+    }
+}
+
+#[pg_extern]
+fn cat_lose_life(cat: Cat) -> Cat {
+    Cat {
+        lives: cat.lives - 1,
+        ..cat
+    }
+}
+
+#[pg_extern]
+fn cat_toy_name(cat: Cat) -> Option<String> {
+    cat.toy.map(|toy| toy.name)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{Cat, Toy};
+    use pgx::*;
+
+    #[pg_test]
+    fn test_composite_argument_and_return() {
+        let lives = Spi::get_one::<i32>("SELECT (cat_lose_life(ROW('Tom', 9, NULL)::Cat)).lives")
+            .expect("SQL select failed");
+        assert_eq!(lives, 8);
+    }
+
+    #[pg_test]
+    fn test_nested_composite() {
+        let name = Spi::get_one::<String>(
+            "SELECT cat_toy_name(ROW('Tom', 9, ROW('mouse', true)::Toy)::Cat)",
+        );
+        assert_eq!(name.as_deref(), Some("mouse"));
+
+        let name = Spi::get_one::<String>("SELECT cat_toy_name(ROW('Tom', 9, NULL)::Cat)");
+        assert_eq!(name, None);
+    }
+
+    #[pg_test]
+    fn test_composite_round_trip() {
+        let cat = Cat {
+            name: "Tom".to_string(),
+            lives: 9,
+            toy: Some(Toy {
+                name: "mouse".to_string(),
+                squeaks: None,
+            }),
+        };
+        let datum = cat.into_datum();
+        let cat = unsafe { Cat::from_datum(datum.unwrap(), datum.is_none(), Cat::type_oid()) };
+        assert_eq!(
+            cat,
+            Some(Cat {
+                name: "Tom".to_string(),
+                lives: 9,
+                toy: Some(Toy {
+                    name: "mouse".to_string(),
+                    squeaks: None,
+                }),
+            })
+        );
+    }
+
+    #[pg_test]
+    fn test_composite_type_oid_ignores_search_path() {
+        let schema = Spi::get_one::<String>(
+            "SELECT extnamespace::regnamespace::text FROM pg_extension WHERE extname = 'pgx_tests'",
+        )
+        .expect("pgx_tests isn't installed");
+        Spi::run("CREATE SCHEMA shadow");
+        Spi::run("CREATE TYPE shadow.cat AS (name text)");
+        Spi::run("SET LOCAL search_path TO shadow, pg_catalog");
+
+        let expected =
+            Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{}.cat'::regtype::oid", schema));
+        assert_eq!(Some(Cat::type_oid()), expected);
+        assert_ne!(Cat::type_oid(), regtypein("cat"));
+    }
+
+    #[pg_test]
+    fn test_composite_type_oid_in_pg_schema() {
+        Spi::run("CREATE TYPE hamster AS (name text)");
+        let expected = Spi::get_one::<pg_sys::Oid>("SELECT 'pets.hamster'::regtype::oid");
+        assert_eq!(Some(super::pets::Hamster::type_oid()), expected);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "`Cat.name` is NULL")]
+    fn test_composite_null_field() {
+        Spi::get_one::<String>("SELECT cat_toy_name(ROW(NULL, 9, NULL)::Cat)");
+    }
+}
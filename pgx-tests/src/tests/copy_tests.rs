@@ -0,0 +1,60 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_copy_from_text() {
+        Spi::run("CREATE TABLE copied (id int, name text)");
+        let copied = PgRelation::open_with_name_and_share_lock("copied").unwrap();
+
+        let count = PgCopyFrom::new(&copied, &b"1\tNami\n2\tBrandy\n3\t\\N\n"[..]).insert();
+        assert_eq!(count, 3);
+
+        let names = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(name, '?'), ',' ORDER BY id) FROM copied",
+        )
+        .unwrap();
+        assert_eq!(names, "Nami,Brandy,?");
+    }
+
+    #[pg_test]
+    fn test_copy_from_csv_columns() {
+        Spi::run("CREATE TABLE copied (id serial, name text, age int)");
+        let copied = PgRelation::open_with_name_and_share_lock("copied").unwrap();
+
+        let csv = "age,name\n12,\"Sally, Jr\"\n3,Nami\n";
+        let count = PgCopyFrom::new(&copied, csv.as_bytes())
+            .option("format", "csv")
+            .option("header", "true")
+            .columns(&["age", "name"])
+            .insert();
+        assert_eq!(count, 2);
+
+        let name = Spi::get_one::<String>("SELECT name FROM copied WHERE id = 1").unwrap();
+        assert_eq!(name, "Sally, Jr");
+        let age = Spi::get_one::<i32>("SELECT age FROM copied WHERE name = 'Nami'").unwrap();
+        assert_eq!(age, 3);
+    }
+
+    #[pg_test(
+        error = "new row for relation \"copied\" violates check constraint \"copied_id_check\""
+    )]
+    fn test_copy_from_checks_constraints() {
+        Spi::run("CREATE TABLE copied (id int CHECK (id > 0))");
+        let copied = PgRelation::open_with_name_and_share_lock("copied").unwrap();
+        PgCopyFrom::new(&copied, &b"1\n0\n"[..]).insert();
+    }
+}
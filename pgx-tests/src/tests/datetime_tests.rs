@@ -36,6 +36,24 @@ fn accept_timestamp_with_time_zone(t: TimestampWithTimeZone) -> TimestampWithTim
     t
 }
 
+#[pg_extern]
+fn accept_interval(i: Interval) -> Interval {
+    i
+}
+
+#[pg_extern]
+fn accept_time_crate_types(
+    d: time::Date,
+    t: time::Time,
+    ts: time::PrimitiveDateTime,
+    tstz: time::OffsetDateTime,
+) -> time::PrimitiveDateTime {
+    assert_eq!(ts.date(), d);
+    assert_eq!(ts.time(), t);
+    assert_eq!(tstz.offset(), UtcOffset::UTC);
+    ts
+}
+
 #[pg_extern]
 fn return_3pm_mountain_time() -> TimestampWithTimeZone {
     let three_pm = TimestampWithTimeZone::new(
@@ -131,6 +149,60 @@ mod serialization_tests {
             json
         );
     }
+
+    #[test]
+    fn test_infinity_serialization() {
+        let json = json!({
+            "date": Date::INFINITY,
+            "timestamp": Timestamp::NEG_INFINITY,
+            "timestamptz": TimestampWithTimeZone::INFINITY,
+        });
+
+        assert_eq!(
+            json!({"date":"infinity","timestamp":"-infinity","timestamptz":"infinity"}),
+            json
+        );
+    }
+
+    #[test]
+    fn test_interval_serialization() {
+        let interval = Interval::new(14, 3, 4 * 3_600_000_000 + 5 * 60_000_000 + 6_500_000);
+        assert_eq!(
+            json!({ "interval": interval }),
+            json!({"interval":"P1Y2M3DT4H5M6.5S"})
+        );
+        assert_eq!(Interval::default().to_string(), "PT0S");
+        assert_eq!(
+            Interval::from_micros(-90 * 60_000_000).to_string(),
+            "PT-1H-30M"
+        );
+        assert_eq!(Interval::from_micros(-500_000).to_string(), "PT-0.5S");
+    }
+
+    #[test]
+    fn test_interval_arithmetic() {
+        let interval = Interval::new(1, 2, 3) + Interval::new(1, -2, 3);
+        assert_eq!(interval, Interval::new(2, 0, 6));
+        assert_eq!(
+            interval - Interval::from_months(2),
+            Interval::from_micros(6)
+        );
+        assert_eq!(-interval, Interval::new(-2, 0, -6));
+
+        let duration = time::Duration::try_from(Interval::new(0, 1, 1)).unwrap();
+        assert_eq!(
+            duration,
+            time::Duration::days(1) + time::Duration::microseconds(1)
+        );
+        assert_eq!(
+            time::Duration::try_from(Interval::from_months(1)),
+            Err(DateTimeConversionError::IntervalHasMonths)
+        );
+        assert_eq!(
+            Interval::try_from(time::Duration::seconds(2)),
+            Ok(Interval::from_micros(2_000_000))
+        );
+    }
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -265,6 +337,99 @@ mod tests {
         assert_eq!(ts.hour(), 14);
     }
 
+    #[pg_test]
+    fn test_accept_interval() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_interval('1 year 2 mons 3 days 04:05:06.5'::interval) \
+             = '1 year 2 mons 3 days 04:05:06.5'::interval",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let interval = Spi::get_one::<Interval>("SELECT '-1 mon 2 days -00:00:01'::interval")
+            .expect("failed to get SPI result");
+        assert_eq!(interval, Interval::new(-1, 2, -1_000_000));
+    }
+
+    #[pg_test]
+    fn test_timestamp_arithmetic() {
+        let ts = Spi::get_one::<Timestamp>("SELECT '2020-01-31 12:00'::timestamp")
+            .expect("failed to get SPI result");
+
+        // months keep the day of the month, as far as the next month has it
+        let next_month = ts + Interval::from_months(1);
+        assert_eq!((next_month.month() as u8, next_month.day()), (2, 29));
+
+        let earlier = ts - Interval::new(0, 1, 3_600_000_000);
+        assert_eq!((earlier.day(), earlier.hour()), (30, 11));
+        assert_eq!(ts - earlier, Interval::new(0, 1, 3_600_000_000));
+
+        let date =
+            Spi::get_one::<Date>("SELECT '2020-01-31'::date").expect("failed to get SPI result");
+        let ts = date + Interval::from_days(1);
+        assert_eq!((ts.month() as u8, ts.day(), ts.hour()), (2, 1, 0));
+    }
+
+    #[pg_test]
+    fn test_timestamp_with_time_zone_arithmetic() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_timestamp_with_time_zone(now()) + '1 day'::interval = now() + '1 day'::interval",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let tstz =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-03-12 12:00+00'::timestamptz")
+                .expect("failed to get SPI result");
+        let later = tstz + Interval::from_micros(36 * 3_600_000_000);
+        // the difference is justified into days of 24 hours
+        assert_eq!(later - tstz, Interval::new(0, 1, 12 * 3_600_000_000));
+        assert_eq!((later - Interval::from_days(1)).day(), 13);
+    }
+
+    #[pg_test]
+    fn test_infinity() {
+        let date =
+            Spi::get_one::<Date>("SELECT 'infinity'::date").expect("failed to get SPI result");
+        assert!(date.is_infinity());
+        let ts = Spi::get_one::<Timestamp>("SELECT '-infinity'::timestamp")
+            .expect("failed to get SPI result");
+        assert!(ts.is_neg_infinity());
+        let tstz = Spi::get_one::<TimestampWithTimeZone>("SELECT 'infinity'::timestamptz")
+            .expect("failed to get SPI result");
+        assert!(tstz.is_infinity());
+        assert!((tstz + Interval::from_days(1)).is_infinity());
+
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_date('-infinity'::date) = '-infinity'::date \
+                AND accept_timestamp('infinity'::timestamp) = 'infinity'::timestamp \
+                AND accept_timestamp_with_time_zone('-infinity') = '-infinity'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+    }
+
+    #[pg_test]
+    fn test_time_crate_types() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_time_crate_types('2022-03-12', '12:34:56.789012', \
+                '2022-03-12 12:34:56.789012', '2022-03-12 12:34:56.789012+00') \
+                = '2022-03-12 12:34:56.789012'::timestamp",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let tstz = Spi::get_one::<time::OffsetDateTime>(
+            "SELECT '2000-01-01 00:00:00.000001+00'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(tstz, time::macros::datetime!(2000-01-01 0:00:00.000001 UTC));
+
+        let date = Spi::get_one::<time::Date>("SELECT 'infinity'::date")
+            .expect("failed to get SPI result");
+        assert_eq!(date, time::Date::MAX);
+    }
+
     #[pg_test]
     fn test_timestamptz() {
         let result = Spi::get_one::<i64>(
@@ -0,0 +1,52 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn test_primitive_datum_slice_round_trip() {
+        let (datums, nulls) = i32::into_datums(vec![1, -2, i32::MAX]);
+        assert_eq!(nulls, vec![false, false, false]);
+        let values = unsafe { i32::from_datum_slice(&datums, &nulls, pg_sys::INT4OID) };
+        assert_eq!(values, vec![Some(1), Some(-2), Some(i32::MAX)]);
+
+        let (datums, nulls) = f64::into_datums([0.5, -1.25].iter().copied());
+        let values = unsafe { f64::from_datum_slice(&datums, &nulls, pg_sys::FLOAT8OID) };
+        assert_eq!(values, vec![Some(0.5), Some(-1.25)]);
+    }
+
+    #[test]
+    fn test_datum_slice_nulls() {
+        let (datums, nulls) = Option::<i64>::into_datums(vec![Some(7), None, Some(0)]);
+        assert_eq!(nulls, vec![false, true, false]);
+        let values = unsafe { i64::from_datum_slice(&datums, &nulls, pg_sys::INT8OID) };
+        assert_eq!(values, vec![Some(7), None, Some(0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "2 datums were given with 1 null flags")]
+    fn test_datum_slice_length_mismatch() {
+        unsafe { bool::from_datum_slice(&[0, 1], &[false], pg_sys::BOOLOID) };
+    }
+
+    #[pg_test]
+    fn test_varlena_datum_slice_round_trip() {
+        let strings = vec![Some("one".to_string()), None, Some("three".to_string())];
+        let (datums, nulls) = Option::<String>::into_datums(strings.clone());
+        assert_eq!(nulls, vec![false, true, false]);
+        let values = unsafe { String::from_datum_slice(&datums, &nulls, pg_sys::TEXTOID) };
+        assert_eq!(values, strings);
+    }
+}
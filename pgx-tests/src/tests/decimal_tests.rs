@@ -0,0 +1,79 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use rust_decimal::Decimal;
+
+#[pg_extern]
+fn accept_decimal(decimal: Decimal) -> Decimal {
+    decimal
+}
+
+#[pg_extern]
+fn return_max_decimal() -> Decimal {
+    Decimal::MAX
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use rust_decimal::Decimal;
+    use std::convert::TryFrom;
+
+    #[pg_test]
+    fn test_decimal_round_trip() {
+        let result = Spi::get_one::<&str>("SELECT accept_decimal(123.4500)::text")
+            .expect("SPI result was null");
+        assert_eq!(result, "123.4500");
+
+        let decimal =
+            Spi::get_one::<Decimal>("SELECT 123.4500::numeric").expect("SPI result was null");
+        assert_eq!(decimal, Decimal::new(1234500, 4));
+        assert_eq!(decimal.scale(), 4);
+
+        let result =
+            Spi::get_one::<&str>("SELECT return_max_decimal()::text").expect("SPI result was null");
+        assert_eq!(result, "79228162514264337593543950335");
+    }
+
+    #[pg_test]
+    fn test_decimal_negative() {
+        let decimal =
+            Spi::get_one::<Decimal>("SELECT -0.000001::numeric").expect("SPI result was null");
+        assert_eq!(decimal, Decimal::new(-1, 6));
+
+        let result = Spi::get_one::<bool>("SELECT accept_decimal(-42.5) = -42.5")
+            .expect("SPI result was null");
+        assert!(result);
+    }
+
+    #[pg_test(error = "numeric can't be converted to a Decimal: value is NaN")]
+    fn test_decimal_rejects_nan() {
+        Spi::get_one::<Decimal>("SELECT accept_decimal('NaN')");
+    }
+
+    #[pg_test(error = "numeric can't be converted to a Decimal: value is out of range")]
+    fn test_decimal_rejects_out_of_range() {
+        Spi::get_one::<Decimal>("SELECT accept_decimal(1e30)");
+    }
+
+    #[pg_test]
+    fn test_decimal_rejects_excess_scale() {
+        let numeric = Spi::get_one::<Numeric>("SELECT 0.00000000000000000000000000001::numeric")
+            .expect("SPI result was null");
+        assert_eq!(
+            Decimal::try_from(numeric),
+            Err(NumericConversionError::OutOfRange)
+        );
+    }
+}
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::dsm::*;
+    use pgx::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Default)]
+    struct Counter {
+        count: AtomicU64,
+    }
+    unsafe impl PGXSharedMemory for Counter {}
+
+    #[pg_test]
+    fn test_dsm_segment() {
+        let segment = DsmSegment::<Counter>::create();
+        assert_eq!(segment.count.load(Ordering::Relaxed), 0);
+        segment.count.fetch_add(2, Ordering::Relaxed);
+        assert_eq!(segment.count.load(Ordering::Relaxed), 2);
+        assert_ne!(segment.handle(), 0);
+    }
+
+    #[pg_test]
+    fn test_dsa_area() {
+        let area = DsaArea::create("dsm_tests");
+        assert_eq!(area.handle(), area.handle());
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    fn test_dshash() {
+        let area = DsaArea::create("dsm_tests");
+        let mut table = DsHash::<i64, i64>::create(&area);
+        assert!(table.get(&1).is_none());
+
+        *table.get_or_insert_with(1, || 10) += 1;
+        *table.get_or_insert_with(1, || 10) += 1;
+        assert_eq!(table.get(&1).map(|value| *value), Some(12));
+
+        if let Some(mut value) = table.get_mut(&1) {
+            *value = 0;
+        }
+        assert_eq!(table.get(&1).map(|value| *value), Some(0));
+
+        assert!(table.remove(&1));
+        assert!(!table.remove(&1));
+        assert!(table.get(&1).is_none());
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    fn test_dshash_default_panics() {
+        let area = DsaArea::create("dsm_tests");
+        let mut table = DsHash::<i64, i64>::create(&area);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            table.get_or_insert_with(1, || panic!("no default"));
+        }));
+        assert!(result.is_err());
+        assert!(table.get(&1).is_none());
+        assert_eq!(*table.get_or_insert_with(1, || 10), 10);
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_test(error = "this backend is already attached to this dshash table")]
+    fn test_dshash_attach_twice() {
+        let area = DsaArea::create("dsm_tests");
+        let table = DsHash::<i64, i64>::create(&area);
+        DsHash::<i64, i64>::attach(&area, table.handle());
+    }
+}
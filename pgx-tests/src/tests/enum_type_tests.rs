@@ -23,13 +23,21 @@ fn take_foo_enum(value: Foo) -> Foo {
     Foo::Three
 }
 
+#[derive(PostgresEnum, PartialEq, Debug)]
+#[pgx(casts)]
+pub enum Ordinal {
+    First = 1,
+    Third = 3,
+    Second = 2,
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
-    use crate::tests::enum_type_tests::Foo;
+    use crate::tests::enum_type_tests::{Foo, Ordinal};
     use pgx::*;
 
     #[test]
@@ -41,4 +49,32 @@ mod tests {
             Spi::get_one::<Foo>("SELECT take_foo_enum('One');").expect("failed to get SPI result");
         assert_eq!(Foo::Three, result);
     }
+
+    #[pg_test]
+    fn test_enum_io_casts() {
+        // Postgres's own casts through the enum's input and output functions
+        let label = Spi::get_one::<String>("SELECT 'Third'::Ordinal::text;").unwrap();
+        assert_eq!(label, "Third");
+        let value = Spi::get_one::<Ordinal>("SELECT 'Second'::text::Ordinal;").unwrap();
+        assert_eq!(value, Ordinal::Second);
+    }
+
+    #[pg_test]
+    fn test_enum_int4_casts() {
+        let ordinal = Spi::get_one::<i32>("SELECT 'Third'::Ordinal::int4;").unwrap();
+        assert_eq!(ordinal, 3);
+        let value = Spi::get_one::<Ordinal>("SELECT 2::Ordinal;").unwrap();
+        assert_eq!(value, Ordinal::Second);
+        let ordinals = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(value::int4 ORDER BY value) \
+             FROM unnest(enum_range(NULL::Ordinal)) value;",
+        )
+        .unwrap();
+        assert_eq!(ordinals, vec![1, 3, 2]);
+    }
+
+    #[pg_test(error = "4 is not an ordinal of enum Ordinal")]
+    fn test_enum_int4_cast_validates() {
+        Spi::get_one::<Ordinal>("SELECT 4::Ordinal;");
+    }
 }
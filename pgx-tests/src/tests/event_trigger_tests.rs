@@ -0,0 +1,112 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_event_trigger(event = "ddl_command_end", tags = ["COMMENT"], name = "event_trigger_test_log")]
+fn event_trigger_log_comment(trigger: &PgEventTrigger) {
+    let has_log = Spi::get_one::<bool>("SELECT to_regclass('event_trigger_log') IS NOT NULL")
+        .unwrap_or(false);
+    if has_log {
+        Spi::run(&format!(
+            "INSERT INTO event_trigger_log VALUES ('{}', '{}')",
+            trigger.event_name(),
+            trigger.tag()
+        ));
+    }
+}
+
+#[pg_event_trigger(event = "sql_drop", tags = ["DROP TABLE"])]
+fn event_trigger_protect_table(trigger: &PgEventTrigger) {
+    for dropped in trigger.dropped_objects() {
+        if dropped.object_type == "table"
+            && dropped.object_name.as_deref() == Some("event_trigger_protected")
+        {
+            panic!("{} is protected", dropped.object_identity);
+        }
+    }
+}
+
+// Event triggers fire for the whole database, so these are only enabled by the tests using them
+extension_sql!(
+    r#"
+ALTER EVENT TRIGGER "event_trigger_test_log" DISABLE;
+ALTER EVENT TRIGGER "event_trigger_protect_table" DISABLE;
+"#,
+    name = "disable_test_event_triggers",
+    finalize
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    // Each test's transaction is rolled back, which disables the trigger again even if it fails
+    fn enable(name: &str) {
+        Spi::run(&format!("ALTER EVENT TRIGGER \"{}\" ENABLE", name));
+    }
+
+    fn disable(name: &str) {
+        Spi::run(&format!("ALTER EVENT TRIGGER \"{}\" DISABLE", name));
+    }
+
+    #[pg_test]
+    fn test_event_trigger_fires() {
+        enable("event_trigger_test_log");
+        Spi::run("CREATE TEMPORARY TABLE event_trigger_log (event text, tag text)");
+        Spi::run("COMMENT ON TABLE event_trigger_log IS 'logged'");
+
+        let (event, tag) =
+            Spi::get_two::<String, String>("SELECT event, tag FROM event_trigger_log");
+        assert_eq!(event, Some("ddl_command_end".to_string()));
+        assert_eq!(tag, Some("COMMENT".to_string()));
+        disable("event_trigger_test_log");
+    }
+
+    #[pg_test]
+    fn test_event_trigger_ignores_other_tags() {
+        enable("event_trigger_test_log");
+        Spi::run("CREATE TEMPORARY TABLE event_trigger_log (event text, tag text)");
+        Spi::run("CREATE TEMPORARY TABLE event_trigger_other (id int)");
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM event_trigger_log");
+        assert_eq!(count, Some(0));
+        disable("event_trigger_test_log");
+    }
+
+    #[pg_test]
+    fn test_event_triggers_disabled_outside_tests() {
+        Spi::run("CREATE TEMPORARY TABLE event_trigger_log (event text, tag text)");
+        Spi::run("COMMENT ON TABLE event_trigger_log IS 'not logged'");
+        Spi::run("CREATE TABLE tests.event_trigger_protected (id int)");
+        Spi::run("DROP TABLE tests.event_trigger_protected");
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM event_trigger_log");
+        assert_eq!(count, Some(0));
+    }
+
+    #[pg_test(error = "tests.event_trigger_protected is protected")]
+    fn test_event_trigger_dropped_objects() {
+        enable("event_trigger_protect_table");
+        Spi::run("CREATE TABLE tests.event_trigger_protected (id int)");
+        Spi::run("DROP TABLE tests.event_trigger_protected");
+    }
+
+    #[pg_test]
+    fn test_event_trigger_allows_other_drops() {
+        enable("event_trigger_protect_table");
+        Spi::run("CREATE TABLE tests.event_trigger_unprotected (id int)");
+        Spi::run("DROP TABLE tests.event_trigger_unprotected");
+        disable("event_trigger_protect_table");
+    }
+}
@@ -0,0 +1,156 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PostgresType)]
+pub struct Samples(Vec<f64>);
+
+#[pg_extern]
+fn samples_new() -> PgExpanded<Samples> {
+    PgExpanded::new(Samples(Vec::new()))
+}
+
+#[pg_extern]
+fn samples_push(mut samples: PgExpanded<Samples>, value: f64) -> PgExpanded<Samples> {
+    samples.0.push(value);
+    samples
+}
+
+#[pg_extern]
+fn samples_len(samples: PgExpanded<Samples>) -> i64 {
+    samples.0.len() as i64
+}
+
+/// Takes the flat form, so expanded values have to be flattened for it
+#[pg_extern]
+fn samples_sum(samples: Samples) -> f64 {
+    samples.0.iter().sum()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use crate::tests::expanded_tests::Samples;
+    use pgx::*;
+
+    #[pg_test]
+    fn test_expanded_in_place() {
+        let datum = PgExpanded::new(Samples(vec![1.0])).into_datum().unwrap();
+        assert!(unsafe { varatt_is_external_expanded_rw(datum as *const pg_sys::varlena) });
+
+        let mut samples =
+            unsafe { PgExpanded::<Samples>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert!(!samples.is_read_only());
+        samples.0.push(2.0);
+
+        // the same object was changed, rather than a copy of it
+        let samples =
+            unsafe { PgExpanded::<Samples>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert_eq!(samples.0, vec![1.0, 2.0]);
+    }
+
+    #[pg_test]
+    fn test_expanded_read_only() {
+        let datum = PgExpanded::new(Samples(vec![1.0])).into_datum().unwrap();
+        let read_only = unsafe { pg_sys::MakeExpandedObjectReadOnlyInternal(datum) };
+
+        let mut samples =
+            unsafe { PgExpanded::<Samples>::from_datum(read_only, false, pg_sys::InvalidOid) }
+                .unwrap();
+        assert!(samples.is_read_only());
+        samples.0.push(2.0);
+        assert!(!samples.is_read_only());
+
+        let original =
+            unsafe { PgExpanded::<Samples>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert_eq!(original.0, vec![1.0]);
+        assert_eq!(samples.0, vec![1.0, 2.0]);
+    }
+
+    #[pg_test]
+    fn test_expanded_memory_context() {
+        let samples = PgExpanded::new(Samples(vec![1.0]));
+        let context = samples.memory_context().unwrap().value();
+        assert_ne!(context, unsafe { pg_sys::CurrentMemoryContext });
+
+        let ptr = samples
+            .memory_context()
+            .unwrap()
+            .switch_to(|_| unsafe { pg_sys::palloc(16) });
+        assert!(unsafe { pg_sys::MemoryContextContains(context, ptr) });
+    }
+
+    #[pg_test]
+    fn test_expanded_memory_context_read_only() {
+        let datum = PgExpanded::new(Samples(vec![1.0])).into_datum().unwrap();
+        let read_only = unsafe { pg_sys::MakeExpandedObjectReadOnlyInternal(datum) };
+
+        let mut samples =
+            unsafe { PgExpanded::<Samples>::from_datum(read_only, false, pg_sys::InvalidOid) }
+                .unwrap();
+        assert!(samples.memory_context().is_none());
+
+        // its own copy has a context of its own
+        samples.0.push(2.0);
+        let original =
+            unsafe { PgExpanded::<Samples>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert_ne!(
+            samples.memory_context().unwrap().value(),
+            original.memory_context().unwrap().value()
+        );
+    }
+
+    #[pg_test]
+    fn test_expanded_calls() {
+        let sum = Spi::get_one::<f64>(
+            "SELECT samples_sum(samples_push(samples_push(samples_new(), 1), 2))",
+        );
+        assert_eq!(sum, Some(3.0));
+
+        let len = Spi::get_one::<i64>("SELECT samples_len(samples_push('[1, 2]'::Samples, 3))");
+        assert_eq!(len, Some(3));
+    }
+
+    #[pg_test]
+    fn test_expanded_flattened() {
+        Spi::run(
+            "CREATE TABLE samples_table AS SELECT samples_push(samples_new(), 4) AS samples;
+             INSERT INTO samples_table VALUES (samples_push(samples_new(), 5));",
+        );
+        let sum = Spi::get_one::<f64>("SELECT sum(samples_sum(samples)) FROM samples_table");
+        assert_eq!(sum, Some(9.0));
+        let text = Spi::get_one::<&str>("SELECT samples_push(samples_new(), 6)::text");
+        assert_eq!(text, Some("[6.0]"));
+    }
+
+    #[pg_test]
+    fn test_expanded_plpgsql() {
+        Spi::run(
+            "CREATE FUNCTION samples_loop(n int) RETURNS float8 LANGUAGE plpgsql AS $$
+             DECLARE
+                 samples Samples := samples_new();
+             BEGIN
+                 FOR i IN 1..n LOOP
+                     samples := samples_push(samples, i);
+                 END LOOP;
+                 RETURN samples_sum(samples);
+             END;
+             $$;",
+        );
+        assert_eq!(
+            Spi::get_one::<f64>("SELECT samples_loop(1000)"),
+            Some(500500.0)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_guard]
+extern "C" fn export_tests_callback(_arg: pg_sys::Datum) {}
+
+/// Looked up by name, like the main function of a background worker
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn export_tests_by_name(_arg: pg_sys::Datum) {}
+
+#[pg_extern]
+fn export_tests_function() {
+    export_tests_callback(0);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::ffi::CString;
+
+    /// Postgres loads the library with its symbols visible to everything in the backend
+    fn is_exported(symbol: &str) -> bool {
+        let symbol = CString::new(symbol).unwrap();
+        unsafe { !libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()).is_null() }
+    }
+
+    #[pg_test]
+    fn test_wrappers_are_exported() {
+        assert!(is_exported("export_tests_function_wrapper"));
+        assert!(is_exported("pg_finfo_export_tests_function_wrapper"));
+    }
+
+    #[pg_test]
+    fn test_guarded_functions_are_hidden() {
+        assert!(!is_exported("export_tests_callback"));
+        assert!(!is_exported("export_tests_function"));
+        assert!(is_exported("export_tests_by_name"));
+    }
+}
@@ -0,0 +1,167 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+struct Countdown {
+    remaining: i32,
+}
+
+impl ForeignDataWrapper for Countdown {
+    type Row = (i32, String);
+
+    fn begin(table: &PgForeignTable) -> Self {
+        Countdown {
+            remaining: table.option("from").unwrap_or("3").parse().unwrap(),
+        }
+    }
+
+    fn iterate(&mut self) -> Option<Self::Row> {
+        self.remaining -= 1;
+        (self.remaining >= 0).then(|| (self.remaining, format!("T-{}", self.remaining)))
+    }
+
+    fn re_scan(&mut self) {
+        panic!("Countdown can't be rescanned");
+    }
+}
+
+#[pg_extern]
+fn countdown_fdw_handler() -> PgBox<pg_sys::FdwRoutine> {
+    fdw_routine::<Countdown>()
+}
+
+extension_sql!(
+    "CREATE FOREIGN DATA WRAPPER countdown_fdw HANDLER countdown_fdw_handler;",
+    name = "create_countdown_fdw",
+    requires = [countdown_fdw_handler]
+);
+
+/// Each test runs in its own backend, so this starts out empty
+static mut PETS: Vec<(i32, String)> = Vec::new();
+
+struct Pets {
+    position: usize,
+}
+
+impl ForeignDataWrapper for Pets {
+    type Row = (i32, String);
+    const MODIFY: ForeignModify = ForeignModify::all();
+
+    fn begin(_table: &PgForeignTable) -> Self {
+        Pets { position: 0 }
+    }
+
+    fn iterate(&mut self) -> Option<Self::Row> {
+        let row = unsafe { PETS.get(self.position).cloned() };
+        self.position += 1;
+        row
+    }
+
+    fn re_scan(&mut self) {
+        self.position = 0;
+    }
+
+    fn begin_modify(_table: &PgForeignTable) -> Self {
+        Pets { position: 0 }
+    }
+
+    fn insert(&mut self, row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        let id = row.get_by_name("id").unwrap().unwrap();
+        let name = row.get_by_name("name").unwrap().unwrap();
+        unsafe { PETS.push((id, name)) };
+    }
+
+    fn update(
+        &mut self,
+        old: PgHeapTuple<'static, AllocatedByRust>,
+        new: PgHeapTuple<'_, AllocatedByPostgres>,
+    ) {
+        let old_id: i32 = old.get_by_name("id").unwrap().unwrap();
+        let id = new.get_by_name("id").unwrap().unwrap();
+        let name = new.get_by_name("name").unwrap().unwrap();
+        for pet in unsafe { PETS.iter_mut() } {
+            if pet.0 == old_id {
+                *pet = (id, name);
+                break;
+            }
+        }
+    }
+
+    fn delete(&mut self, old: PgHeapTuple<'static, AllocatedByRust>) {
+        let old_id: i32 = old.get_by_name("id").unwrap().unwrap();
+        unsafe { PETS.retain(|pet| pet.0 != old_id) };
+    }
+}
+
+#[pg_extern]
+fn pets_fdw_handler() -> PgBox<pg_sys::FdwRoutine> {
+    fdw_routine::<Pets>()
+}
+
+extension_sql!(
+    "CREATE FOREIGN DATA WRAPPER pets_fdw HANDLER pets_fdw_handler;",
+    name = "create_pets_fdw",
+    requires = [pets_fdw_handler]
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_fdw_scan() {
+        Spi::run("CREATE SERVER countdown FOREIGN DATA WRAPPER countdown_fdw OPTIONS (from '5')");
+        Spi::run("CREATE FOREIGN TABLE liftoff (n int, label text) SERVER countdown");
+        Spi::run(
+            "CREATE FOREIGN TABLE short_liftoff (n int, label text) SERVER countdown OPTIONS (from '2')",
+        );
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM liftoff").unwrap();
+        assert_eq!(count, 5);
+        let label = Spi::get_one::<String>("SELECT label FROM liftoff WHERE n = 3").unwrap();
+        assert_eq!(label, "T-3");
+
+        // the table's options override its server's
+        let total = Spi::get_one::<i64>("SELECT sum(n) FROM short_liftoff").unwrap();
+        assert_eq!(total, 1);
+    }
+
+    #[pg_test(error = "column `label` has type oid 23, but the row's value has type oid 25")]
+    fn test_fdw_scan_wrong_type() {
+        Spi::run("CREATE SERVER countdown FOREIGN DATA WRAPPER countdown_fdw");
+        Spi::run("CREATE FOREIGN TABLE liftoff (n int, label int) SERVER countdown");
+        Spi::run("SELECT * FROM liftoff");
+    }
+
+    #[pg_test]
+    fn test_fdw_modify() {
+        Spi::run("CREATE SERVER pets FOREIGN DATA WRAPPER pets_fdw");
+        Spi::run("CREATE FOREIGN TABLE pets (id int, name text) SERVER pets");
+
+        Spi::run("INSERT INTO pets VALUES (1, 'Nami'), (2, 'Brandy'), (3, 'Sally')");
+        Spi::run("UPDATE pets SET name = 'Brandy Jr' WHERE id = 2");
+        Spi::run("DELETE FROM pets WHERE id = 1");
+
+        let names =
+            Spi::get_one::<String>("SELECT string_agg(name, ',' ORDER BY id) FROM pets").unwrap();
+        assert_eq!(names, "Brandy Jr,Sally");
+    }
+
+    #[pg_test(error = "cannot insert into foreign table \"liftoff\"")]
+    fn test_fdw_not_updatable() {
+        Spi::run("CREATE SERVER countdown FOREIGN DATA WRAPPER countdown_fdw");
+        Spi::run("CREATE FOREIGN TABLE liftoff (n int, label text) SERVER countdown");
+        Spi::run("INSERT INTO liftoff VALUES (1, 'one')");
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use time::macros::datetime;
+
+    #[pg_test]
+    fn test_to_char_integers() {
+        assert_eq!(42i16.to_char("FM000").unwrap(), "042");
+        assert_eq!(42i32.to_char("FM000").unwrap(), "042");
+        assert_eq!(
+            1234567i64.to_char("9G999G999").unwrap(),
+            Spi::get_one::<String>("SELECT to_char(1234567::bigint, '9G999G999')").unwrap()
+        );
+        assert_eq!(
+            (-5i32).to_char("S999").unwrap(),
+            Spi::get_one::<String>("SELECT to_char(-5, 'S999')").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn test_to_char_floats() {
+        assert_eq!(
+            1.5f32.to_char("9D99").unwrap(),
+            Spi::get_one::<String>("SELECT to_char(1.5::real, '9D99')").unwrap()
+        );
+        assert_eq!(
+            1234.5678f64.to_char("9G999D99").unwrap(),
+            Spi::get_one::<String>("SELECT to_char(1234.5678::float8, '9G999D99')").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn test_to_char_numeric() {
+        let total = Numeric("1234567.891".to_string());
+        assert_eq!(
+            total.to_char("L9G999G999D99").unwrap(),
+            Spi::get_one::<String>("SELECT to_char(1234567.891::numeric, 'L9G999G999D99')")
+                .unwrap()
+        );
+        assert_eq!(total.to_char("FM9999999.99").unwrap(), "1234567.89");
+    }
+
+    #[pg_test]
+    fn test_to_char_timestamps() {
+        let ts = Timestamp::new(datetime!(2021-07-04 13:05:00));
+        assert_eq!(
+            ts.to_char("YYYY-MM-DD HH24:MI").unwrap(),
+            "2021-07-04 13:05"
+        );
+        assert_eq!(
+            ts.to_char("TMMonth").unwrap(),
+            Spi::get_one::<String>("SELECT to_char('2021-07-04'::timestamp, 'TMMonth')").unwrap()
+        );
+        assert_eq!(Timestamp::INFINITY.to_char("YYYY"), None);
+
+        Spi::run("SET TIME ZONE 'UTC'");
+        let tstz = TimestampWithTimeZone::from(datetime!(2021-07-04 13:05 UTC));
+        assert_eq!(tstz.to_char("HH24:MI TZ").unwrap(), "13:05 UTC");
+    }
+
+    #[pg_test]
+    fn test_to_char_interval() {
+        let interval = Interval::new(0, 2, 3_600_000_000);
+        assert_eq!(interval.to_char("DD HH24").unwrap(), "02 01");
+    }
+
+    #[pg_test]
+    fn test_locale_conventions() {
+        let conventions = LocaleConventions::current();
+        assert_eq!(
+            format!("0{}5", conventions.decimal_point),
+            Spi::get_one::<String>("SELECT to_char(0.5, 'FM0D0')").unwrap()
+        );
+        assert_eq!(conventions, LocaleConventions::current());
+    }
+}
@@ -0,0 +1,93 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn take_and_return_point(point: pg_sys::Point) -> pg_sys::Point {
+        point
+    }
+
+    #[pg_extern]
+    fn take_and_return_box(the_box: pg_sys::BOX) -> pg_sys::BOX {
+        the_box
+    }
+
+    #[pg_extern]
+    fn take_and_return_lseg(lseg: pg_sys::LSEG) -> pg_sys::LSEG {
+        lseg
+    }
+
+    #[pg_extern]
+    fn take_and_return_circle(circle: pg_sys::CIRCLE) -> pg_sys::CIRCLE {
+        circle
+    }
+
+    #[pg_extern]
+    fn reverse_path(path: Path) -> Path {
+        Path {
+            points: path.points.into_iter().rev().collect(),
+            closed: path.closed,
+        }
+    }
+
+    #[pg_extern]
+    fn polygon_point_count(polygon: Polygon) -> i32 {
+        polygon.points.len() as i32
+    }
+
+    #[pg_extern]
+    fn triangle() -> Polygon {
+        Polygon::new(vec![
+            pg_sys::Point { x: 0.0, y: 0.0 },
+            pg_sys::Point { x: 4.0, y: 0.0 },
+            pg_sys::Point { x: 0.0, y: 3.0 },
+        ])
+    }
+
+    #[pg_test]
+    fn test_take_and_return_fixed_size_shapes() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.take_and_return_point('(1,2)') ~= '(1,2)'::point
+                AND tests.take_and_return_box('(3,4),(1,2)') ~= '(3,4),(1,2)'::box
+                AND tests.take_and_return_lseg('[(1,2),(3,4)]') = '[(1,2),(3,4)]'::lseg
+                AND tests.take_and_return_circle('<(1,2),3>') ~= '<(1,2),3>'::circle;",
+        );
+        assert_eq!(rc, Some(true));
+    }
+
+    #[pg_test]
+    fn test_path() {
+        let rc = Spi::get_one::<String>("SELECT tests.reverse_path('[(1,2),(3,4),(5,6)]')::text;");
+        assert_eq!(rc.as_deref(), Some("[(5,6),(3,4),(1,2)]"));
+
+        let rc = Spi::get_one::<String>("SELECT tests.reverse_path('((1,2),(3,4))')::text;");
+        assert_eq!(rc.as_deref(), Some("((3,4),(1,2))"));
+    }
+
+    #[pg_test]
+    fn test_polygon() {
+        let rc =
+            Spi::get_one::<i32>("SELECT tests.polygon_point_count('((0,0),(1,1),(1,0),(0,1))');");
+        assert_eq!(rc, Some(4));
+
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.triangle() ~= '((0,0),(4,0),(0,3))'::polygon
+                AND box(tests.triangle()) ~= '(4,3),(0,0)'::box
+                AND tests.triangle() @> '(1,1)'::point;",
+        );
+        assert_eq!(rc, Some(true));
+    }
+}
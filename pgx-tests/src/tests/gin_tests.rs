@@ -0,0 +1,153 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+/// A set of tags, indexed by each of them
+#[derive(Debug, Serialize, Deserialize, PostgresType, PostgresGinOps)]
+#[gin(
+    default,
+    storage = "text",
+    operators(1 = "&&", 2 = "@>", 3 = "^@"),
+    requires = [tags_overlap, tags_contain, tags_prefix]
+)]
+pub struct Tags(Vec<String>);
+
+#[pg_operator(immutable, parallel_safe, symbol = "&&", commutator = "&&")]
+fn tags_overlap(left: Tags, right: Tags) -> bool {
+    left.0.iter().any(|tag| right.0.contains(tag))
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "@>")]
+fn tags_contain(left: Tags, right: Tags) -> bool {
+    right.0.iter().all(|tag| left.0.contains(tag))
+}
+
+/// Does any tag start with any of the prefixes?
+#[pg_operator(immutable, parallel_safe, symbol = "^@")]
+fn tags_prefix(left: Tags, prefixes: Tags) -> bool {
+    left.0
+        .iter()
+        .any(|tag| prefixes.0.iter().any(|prefix| tag.starts_with(prefix)))
+}
+
+impl GinSupport for Tags {
+    type Key = String;
+
+    fn extract_value(value: Tags) -> Vec<Option<String>> {
+        value.0.into_iter().map(Some).collect()
+    }
+
+    fn extract_query(query: Tags, strategy: u16) -> GinQuery<String> {
+        let keys = query.0.into_iter().map(Some).collect::<Vec<_>>();
+        match strategy {
+            1 => GinQuery::new(keys),
+            // everything contains no tags at all
+            2 if keys.is_empty() => GinQuery {
+                search_mode: GinSearchMode::All,
+                ..GinQuery::new(keys)
+            },
+            2 => GinQuery::new(keys),
+            3 => GinQuery::partial(keys),
+            _ => panic!("unknown strategy {}", strategy),
+        }
+    }
+
+    fn consistent(check: &[bool], _query: &Tags, strategy: u16) -> GinMatch {
+        let found = match strategy {
+            1 | 3 => check.contains(&true),
+            2 => !check.contains(&false),
+            _ => panic!("unknown strategy {}", strategy),
+        };
+        if found {
+            GinMatch::Yes
+        } else {
+            GinMatch::No
+        }
+    }
+
+    fn compare_partial(partial: &String, key: &String, _strategy: u16) -> GinPartialMatch {
+        // keys starting with the prefix come right after it, and before any which don't
+        if key.starts_with(partial.as_str()) {
+            GinPartialMatch::Match
+        } else if key > partial {
+            GinPartialMatch::Stop
+        } else {
+            GinPartialMatch::NoMatch
+        }
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_tagged() {
+        Spi::run(
+            "CREATE TABLE tagged AS
+                SELECT format('[\"t%s\", \"u%s\"]', x % 10, x % 7)::Tags AS tags
+                  FROM generate_series(1, 1000) x;
+             CREATE INDEX tagged_gin ON tagged USING gin (tags);
+             ANALYZE tagged;
+             SET LOCAL enable_seqscan = off;",
+        );
+    }
+
+    /// How many of the rows in the table satisfy `f`, of their `x % 10` and `x % 7`
+    fn expected_tagged(f: impl Fn(i32, i32) -> bool) -> i64 {
+        (1..=1000).filter(|x| f(x % 10, x % 7)).count() as i64
+    }
+
+    #[pg_test]
+    fn test_gin_overlap() {
+        create_tagged();
+        let query = r#"SELECT count(*) FROM tagged WHERE tags && '["t3", "u20"]'"#;
+        assert!(Spi::explain(query).0.to_string().contains("tagged_gin"));
+        let expected = expected_tagged(|t, _| t == 3);
+        assert_eq!(Spi::get_one::<i64>(query), Some(expected));
+    }
+
+    #[pg_test]
+    fn test_gin_contains() {
+        create_tagged();
+        let count =
+            Spi::get_one::<i64>(r#"SELECT count(*) FROM tagged WHERE tags @> '["t3", "u4"]'"#);
+        let expected = expected_tagged(|t, u| t == 3 && u == 4);
+        assert_eq!(count, Some(expected));
+
+        let count = Spi::get_one::<i64>(r#"SELECT count(*) FROM tagged WHERE tags @> '[]'"#);
+        assert_eq!(count, Some(1000));
+    }
+
+    #[pg_test]
+    fn test_gin_partial_match() {
+        create_tagged();
+        let count = Spi::get_one::<i64>(r#"SELECT count(*) FROM tagged WHERE tags ^@ '["u"]'"#);
+        assert_eq!(count, Some(1000));
+
+        let count = Spi::get_one::<i64>(r#"SELECT count(*) FROM tagged WHERE tags ^@ '["t1"]'"#);
+        let expected = expected_tagged(|t, _| t == 1);
+        assert_eq!(count, Some(expected));
+    }
+
+    #[pg_test]
+    fn test_gin_opclass() {
+        let functions = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_amproc
+               JOIN pg_opfamily ON amprocfamily = pg_opfamily.oid
+              WHERE opfname = 'tags_gin_ops'",
+        );
+        assert_eq!(functions, Some(5));
+    }
+}
@@ -0,0 +1,127 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+/// A range of integers, indexed by the smallest span covering those under each key
+#[derive(Debug, PartialEq, Serialize, Deserialize, PostgresType, PostgresGistOps)]
+#[gist(
+    default,
+    operators(3 = "&&", 7 = "@>"),
+    requires = [span_overlaps, span_contains]
+)]
+pub struct Span {
+    lo: i32,
+    hi: i32,
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "&&", commutator = "&&")]
+fn span_overlaps(left: Span, right: Span) -> bool {
+    left.lo <= right.hi && right.lo <= left.hi
+}
+
+#[pg_operator(immutable, parallel_safe, symbol = "@>")]
+fn span_contains(left: Span, right: Span) -> bool {
+    left.lo <= right.lo && right.hi <= left.hi
+}
+
+impl GistSupport for Span {
+    type Key = Span;
+
+    fn compress(value: Span) -> Span {
+        value
+    }
+
+    fn consistent(key: &Span, query: &Span, strategy: u16, _is_leaf: bool) -> GistMatch {
+        // a key covers its values, so it overlaps, or contains, whatever they do
+        let found = match strategy {
+            3 => key.lo <= query.hi && query.lo <= key.hi,
+            7 => key.lo <= query.lo && query.hi <= key.hi,
+            _ => panic!("unknown strategy {}", strategy),
+        };
+        if found {
+            GistMatch::Yes
+        } else {
+            GistMatch::No
+        }
+    }
+
+    fn union(keys: Vec<Span>) -> Span {
+        Span {
+            lo: keys.iter().map(|key| key.lo).min().unwrap(),
+            hi: keys.iter().map(|key| key.hi).max().unwrap(),
+        }
+    }
+
+    fn penalty(original: &Span, new: &Span) -> f32 {
+        ((original.lo - new.lo).max(0) + (new.hi - original.hi).max(0)) as f32
+    }
+
+    fn picksplit(keys: &[Span]) -> (Vec<usize>, Vec<usize>) {
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| keys[*i].lo as i64 + keys[*i].hi as i64);
+        let right = order.split_off(order.len() / 2);
+        (order, right)
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_spans() {
+        Spi::run(
+            "CREATE TABLE spans AS
+                SELECT format('{\"lo\": %s, \"hi\": %s}', x, x + x % 7)::Span AS span
+                  FROM generate_series(1, 10000) x;
+             CREATE INDEX spans_gist ON spans USING gist (span);
+             ANALYZE spans;
+             SET LOCAL enable_seqscan = off;",
+        );
+    }
+
+    /// How many of the spans in the table satisfy `f`
+    fn expected_spans(f: impl Fn(i32, i32) -> bool) -> i64 {
+        (1..=10000).filter(|x| f(*x, x + x % 7)).count() as i64
+    }
+
+    #[pg_test]
+    fn test_gist_overlaps() {
+        create_spans();
+        let query = r#"SELECT count(*) FROM spans WHERE span && '{"lo": 100, "hi": 110}'"#;
+        assert!(Spi::explain(query).0.to_string().contains("spans_gist"));
+        let expected = expected_spans(|lo, hi| lo <= 110 && 100 <= hi);
+        assert_eq!(Spi::get_one::<i64>(query), Some(expected));
+    }
+
+    #[pg_test]
+    fn test_gist_contains() {
+        create_spans();
+        let count = Spi::get_one::<i64>(
+            r#"SELECT count(*) FROM spans WHERE span @> '{"lo": 5000, "hi": 5003}'"#,
+        );
+        let expected = expected_spans(|lo, hi| lo <= 5000 && 5003 <= hi);
+        assert_eq!(count, Some(expected));
+    }
+
+    #[pg_test]
+    fn test_gist_opclass() {
+        let functions = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_amproc
+               JOIN pg_opfamily ON amprocfamily = pg_opfamily.oid
+              WHERE opfname = 'span_gist_ops'",
+        );
+        assert_eq!(functions, Some(7));
+    }
+}
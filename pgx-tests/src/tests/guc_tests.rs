@@ -55,6 +55,28 @@ mod tests {
         assert_eq!(GUC.get(), 12);
     }
 
+    #[pg_test]
+    fn test_int_guc_with_unit() {
+        static GUC: GucSetting<i32> = GucSetting::new(250);
+        GucRegistry::define_int_guc_with_flags(
+            "test.int_ms",
+            "test int guc with a unit",
+            "test int guc with a unit",
+            &GUC,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::UNIT_MS | GucFlags::NOT_IN_SAMPLE,
+        );
+        assert_eq!(GUC.get(), 250);
+
+        Spi::run("SET test.int_ms = '2s'");
+        assert_eq!(GUC.get(), 2000);
+
+        let shown = Spi::get_one::<String>("SHOW test.int_ms").expect("SHOW failed");
+        assert_eq!(shown, "2s");
+    }
+
     #[pg_test]
     fn test_float_guc() {
         static GUC: GucSetting<f64> = GucSetting::new(42.42);
@@ -142,4 +164,33 @@ mod tests {
         Spi::run("SET test.enum = 'three'");
         assert_eq!(GUC.get(), TestEnum::Three);
     }
+
+    #[pg_test]
+    fn test_get_builtin_guc() {
+        Spi::run("SET work_mem = '8MB'");
+        assert_eq!(Guc::get::<i32>("work_mem"), Some(8192));
+        assert_eq!(Guc::get_bytes("work_mem"), Some(8 * 1024 * 1024));
+        assert_eq!(Guc::get::<String>("work_mem").as_deref(), Some("8192"));
+
+        Spi::run("SET statement_timeout = '2min'");
+        assert_eq!(
+            Guc::get_duration("statement_timeout"),
+            Some(std::time::Duration::from_secs(120))
+        );
+        assert_eq!(Guc::get_bytes("statement_timeout"), None);
+
+        Spi::run("SET enable_seqscan = off");
+        assert_eq!(Guc::get::<bool>("enable_seqscan"), Some(false));
+
+        assert_eq!(Guc::get::<i32>("test.no_such_setting"), None);
+        assert_eq!(Guc::flags("test.no_such_setting"), None);
+    }
+
+    #[pg_test]
+    fn test_subscriber_error() {
+        // the test's transaction is aborted when it's done, which would restart the cluster if
+        // this ERROR weren't reported as a WARNING
+        Guc::subscribe::<String, _>("application_name", |_| error!("ERROR in a subscriber"));
+        Spi::run("SET application_name = 'test_subscriber_error'");
+    }
 }
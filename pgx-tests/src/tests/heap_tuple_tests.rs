@@ -0,0 +1,483 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Dog {
+    name: String,
+    scritches: i32,
+    weight: Option<f64>,
+    tricks: Vec<String>,
+    owner: Option<Owner>,
+    details: serde_json::Value,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Owner {
+    name: String,
+    since: i16,
+}
+
+/// What JSON can't hold: non-finite floats, bytes, and an array of a composite type
+#[derive(Debug, Serialize, Deserialize)]
+struct Measurement {
+    reading: f64,
+    low: f32,
+    payload: Vec<u8>,
+    owners: Vec<Owner>,
+}
+
+/// A `pet` as it was first declared, before users altered the type
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Pet {
+    name: String,
+    age: Option<i32>,
+}
+
+#[pg_trigger]
+fn trigger_scritch_dog(
+    trigger: &PgTrigger,
+) -> Result<Option<PgHeapTuple<'_, AllocatedByRust>>, PgHeapTupleError> {
+    let new = match trigger.new() {
+        Some(new) => new,
+        None => return Ok(None),
+    };
+    let mut dog: Dog = new.into_struct()?;
+    dog.scritches += 1;
+    dog.tricks.push("sit".to_string());
+    PgHeapTuple::from_struct(trigger.relation().tuple_desc(), &dog).map(Some)
+}
+
+/// Any row as a JSON object, as `to_json()` does
+#[pg_extern]
+fn record_to_json(row: PgHeapTuple<'static, AllocatedByRust>) -> JsonB {
+    JsonB(
+        row.into_struct()
+            .expect("the row can't be converted to json"),
+    )
+}
+
+#[pg_extern]
+fn record_attribute_count(row: PgHeapTuple<'static, AllocatedByRust>) -> i64 {
+    row.len() as i64
+}
+
+#[pg_extern]
+fn record_has_attribute(row: PgHeapTuple<'static, AllocatedByRust>, name: &str) -> bool {
+    row.has_attribute(name)
+}
+
+#[pg_extern]
+fn pet_age(row: PgHeapTuple<'static, AllocatedByRust>) -> Option<i32> {
+    row.into_struct::<Pet>().expect("the row isn't a pet").age
+}
+
+#[pg_extern]
+fn describe_measurement(row: PgHeapTuple<'static, AllocatedByRust>) -> String {
+    let measurement: Measurement = row.into_struct().expect("the row isn't a measurement");
+    format!("{:?}", measurement)
+}
+
+/// The rows as an array of their composite type
+#[pg_extern]
+fn pair_records(
+    first: PgHeapTuple<'static, AllocatedByRust>,
+    second: PgHeapTuple<'static, AllocatedByRust>,
+) -> Vec<PgHeapTuple<'static, AllocatedByRust>> {
+    vec![first, second]
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{Dog, Measurement, Owner, Pet};
+    use pgx::*;
+    use serde_json::json;
+
+    fn create_dogs_table() {
+        Spi::run("CREATE TYPE dog_owner AS (name text, since int2)");
+        Spi::run(
+            "CREATE TABLE dogs (
+                name text,
+                scritches int4,
+                weight float8,
+                tricks text[],
+                owner dog_owner,
+                details jsonb
+            )",
+        );
+    }
+
+    #[pg_test]
+    fn test_struct_round_trip() {
+        create_dogs_table();
+        let relation = PgRelation::open_with_name_and_share_lock("dogs").unwrap();
+        let dog = Dog {
+            name: "Nami".to_string(),
+            scritches: 10,
+            weight: None,
+            tricks: vec!["roll \"over\"".to_string(), "shake, paw".to_string()],
+            owner: Some(Owner {
+                name: "Brandy".to_string(),
+                since: 2019,
+            }),
+            details: json!({ "good": true }),
+        };
+
+        let tuple = PgHeapTuple::from_struct(relation.tuple_desc(), &dog).unwrap();
+        assert_eq!(tuple.get_by_name::<i32>("scritches").unwrap(), Some(10));
+        assert_eq!(tuple.get_by_name::<f64>("weight").unwrap(), None);
+        assert_eq!(tuple.into_struct::<Dog>().unwrap(), dog);
+    }
+
+    #[pg_test]
+    fn test_from_struct_unknown_field() {
+        create_dogs_table();
+        let relation = PgRelation::open_with_name_and_share_lock("dogs").unwrap();
+        let result = PgHeapTuple::from_struct(relation.tuple_desc(), &json!({ "breed": "lab" }));
+        assert_eq!(
+            result.err(),
+            Some(PgHeapTupleError::NoSuchAttributeName("breed".to_string()))
+        );
+    }
+
+    #[pg_test]
+    fn test_trigger_with_structs() {
+        create_dogs_table();
+        Spi::run(
+            "CREATE TRIGGER scritch BEFORE INSERT ON dogs
+                FOR EACH ROW EXECUTE PROCEDURE trigger_scritch_dog()",
+        );
+        Spi::run(
+            "INSERT INTO dogs VALUES
+                ('Brandy', 1, 30.5, ARRAY['stay'], ROW('Nami', 2020), '{}')",
+        );
+
+        let scritches =
+            Spi::get_one::<i32>("SELECT scritches FROM dogs").expect("SQL select failed");
+        assert_eq!(scritches, 2);
+        let tricks =
+            Spi::get_one::<Vec<String>>("SELECT tricks FROM dogs").expect("SQL select failed");
+        assert_eq!(tricks, vec!["stay".to_string(), "sit".to_string()]);
+        let owner =
+            Spi::get_one::<String>("SELECT (owner).name FROM dogs").expect("SQL select failed");
+        assert_eq!(owner, "Nami");
+    }
+
+    #[pg_test]
+    fn test_record_argument() {
+        let json = Spi::get_one::<JsonB>("SELECT record_to_json(ROW(1, 'two', ARRAY[3]))")
+            .expect("record_to_json() returned null");
+        assert_eq!(json.0, json!({"f1": 1, "f2": "two", "f3": [3]}));
+
+        let json = Spi::get_one::<JsonB>(
+            "SELECT record_to_json(t) FROM (VALUES (42, 'Nami')) AS t(id, name)",
+        )
+        .expect("record_to_json() returned null");
+        assert_eq!(json.0, json!({"id": 42, "name": "Nami"}));
+
+        create_dogs_table();
+        Spi::run("INSERT INTO dogs (name, scritches) VALUES ('Brandy', 3)");
+        let count = Spi::get_one::<i64>("SELECT record_attribute_count(dogs) FROM dogs")
+            .expect("record_attribute_count() returned null");
+        assert_eq!(count, 6);
+    }
+
+    fn dog_tuple(name: &str) -> PgHeapTuple<'static, AllocatedByRust> {
+        let relation = PgRelation::open_with_name_and_share_lock("dogs").unwrap();
+        let dog = json!({ "name": name, "scritches": 1 });
+        PgHeapTuple::from_struct(relation.tuple_desc(), &dog).unwrap()
+    }
+
+    #[pg_test]
+    fn test_array_of_records() {
+        create_dogs_table();
+        let datum = vec![dog_tuple("Nami"), dog_tuple("Brandy")]
+            .into_datum()
+            .expect("the array is null");
+        let array = unsafe { &*(datum as *mut pg_sys::ArrayType) };
+        assert_eq!(array.ndim, 1);
+        assert_eq!(array.elemtype, regtypein("dogs"));
+
+        let names = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(dog.name) FROM unnest(pair_records(
+                ROW('Nami', 1, NULL, NULL, NULL, NULL)::dogs,
+                ROW('Brandy', 2, NULL, NULL, NULL, NULL)::dogs
+            )::dogs[]) dog",
+        );
+        assert_eq!(names, Some(vec!["Nami".to_string(), "Brandy".to_string()]));
+    }
+
+    #[pg_test]
+    fn test_nested_array_of_records() {
+        create_dogs_table();
+        let datum = vec![
+            vec![dog_tuple("Nami"), dog_tuple("Brandy")],
+            vec![dog_tuple("Rex"), dog_tuple("Fido")],
+        ]
+        .into_datum()
+        .expect("the array is null");
+        let array = unsafe { &*(datum as *mut pg_sys::ArrayType) };
+        assert_eq!(array.ndim, 2);
+        assert_eq!(array.elemtype, regtypein("dogs"));
+    }
+
+    fn create_measurement_type() {
+        Spi::run("CREATE TYPE dog_owner AS (name text, since int2)");
+        Spi::run(
+            "CREATE TYPE measurement AS (
+                reading float8,
+                low float4,
+                payload bytea,
+                owners dog_owner[]
+            )",
+        );
+    }
+
+    fn measurement_tupdesc() -> PgTupleDesc<'static> {
+        unsafe {
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(
+                regtypein("measurement"),
+                -1,
+            ))
+        }
+    }
+
+    fn measurement(reading: f64, low: f32) -> Measurement {
+        Measurement {
+            reading,
+            low,
+            payload: vec![0, 1, 255],
+            owners: vec![
+                Owner {
+                    name: "Nami".to_string(),
+                    since: 2019,
+                },
+                Owner {
+                    name: "Brandy".to_string(),
+                    since: 2020,
+                },
+            ],
+        }
+    }
+
+    /// Query `sql` about the composite datum of `tuple`, which is `$1`
+    fn select_from_tuple<T: FromDatum + IntoDatum>(
+        tuple: PgHeapTuple<'static, AllocatedByRust>,
+        sql: &str,
+    ) -> Option<T> {
+        let datum = tuple.into_composite_datum();
+        Spi::get_one_with_args(sql, vec![(PgOid::from(regtypein("measurement")), datum)])
+    }
+
+    #[pg_test]
+    fn test_struct_non_finite_floats() {
+        create_measurement_type();
+        for (reading, low) in [
+            (f64::INFINITY, f32::NEG_INFINITY),
+            (f64::NEG_INFINITY, f32::INFINITY),
+            (f64::NAN, f32::NAN),
+        ] {
+            let tuple = PgHeapTuple::from_struct(measurement_tupdesc(), &measurement(reading, low))
+                .unwrap();
+            let read = tuple.get_by_name::<f64>("reading").unwrap().unwrap();
+            assert!(read == reading || (read.is_nan() && reading.is_nan()));
+            let read = tuple.get_by_name::<f32>("low").unwrap().unwrap();
+            assert!(read == low || (read.is_nan() && low.is_nan()));
+
+            let round_trip: Measurement = tuple.into_struct().unwrap();
+            assert_eq!(
+                format!("{:?}", round_trip),
+                format!("{:?}", measurement(reading, low))
+            );
+        }
+
+        let described = Spi::get_one::<String>(
+            "SELECT describe_measurement(ROW('NaN', '-Infinity', '', '{}')::measurement)",
+        );
+        assert_eq!(
+            described.as_deref(),
+            Some("Measurement { reading: NaN, low: -inf, payload: [], owners: [] }")
+        );
+    }
+
+    #[pg_test]
+    fn test_struct_bytea() {
+        create_measurement_type();
+        let tuple =
+            PgHeapTuple::from_struct(measurement_tupdesc(), &measurement(1.0, 2.0)).unwrap();
+        assert_eq!(
+            tuple.get_by_name::<Vec<u8>>("payload").unwrap(),
+            Some(vec![0, 1, 255])
+        );
+        let hex =
+            select_from_tuple::<String>(tuple, "SELECT encode(($1::measurement).payload, 'hex')");
+        assert_eq!(hex.as_deref(), Some("0001ff"));
+
+        let described = Spi::get_one::<String>(
+            "SELECT describe_measurement(ROW(0, 0, '\\x00ff', '{}')::measurement)",
+        );
+        assert_eq!(
+            described.as_deref(),
+            Some("Measurement { reading: 0.0, low: 0.0, payload: [0, 255], owners: [] }")
+        );
+    }
+
+    #[pg_test]
+    fn test_struct_array_of_composites() {
+        create_measurement_type();
+        let tuple =
+            PgHeapTuple::from_struct(measurement_tupdesc(), &measurement(1.0, 2.0)).unwrap();
+        let owner = select_from_tuple::<String>(
+            tuple,
+            "SELECT (($1::measurement).owners[2]).name || ' ' || (($1::measurement).owners[2]).since",
+        );
+        assert_eq!(owner.as_deref(), Some("Brandy 2020"));
+
+        let described = Spi::get_one::<String>(
+            "SELECT describe_measurement(ROW(0, 0, '', ARRAY[
+                ROW('Nami', 2019)::dog_owner,
+                ROW('Brandy, \"the dog\"', 2020)::dog_owner
+            ])::measurement)",
+        );
+        assert_eq!(
+            described.as_deref(),
+            Some(
+                "Measurement { reading: 0.0, low: 0.0, payload: [], owners: [\
+                 Owner { name: \"Nami\", since: 2019 }, \
+                 Owner { name: \"Brandy, \\\"the dog\\\"\", since: 2020 }] }"
+            )
+        );
+    }
+
+    fn create_altered_pets_table() {
+        Spi::run("CREATE TYPE pet AS (name text, age int4)");
+        Spi::run("CREATE TABLE pets (pet pet)");
+        Spi::run("INSERT INTO pets VALUES (ROW('Nami', 3))");
+        Spi::run("ALTER TYPE pet ADD ATTRIBUTE toy text");
+    }
+
+    #[pg_test]
+    fn test_added_attribute() {
+        create_altered_pets_table();
+        let json = Spi::get_one::<JsonB>("SELECT record_to_json(pet) FROM pets")
+            .expect("record_to_json() returned null");
+        assert_eq!(json.0, json!({"name": "Nami", "age": 3, "toy": null}));
+
+        let has_toy = Spi::get_one::<bool>("SELECT record_has_attribute(pet, 'toy') FROM pets");
+        assert_eq!(has_toy, Some(true));
+        let age = Spi::get_one::<i32>("SELECT pet_age(pet) FROM pets");
+        assert_eq!(age, Some(3));
+    }
+
+    #[pg_test]
+    fn test_dropped_attribute() {
+        create_altered_pets_table();
+        Spi::run("ALTER TYPE pet DROP ATTRIBUTE age");
+        let json = Spi::get_one::<JsonB>("SELECT record_to_json(pet) FROM pets")
+            .expect("record_to_json() returned null");
+        assert_eq!(json.0, json!({"name": "Nami", "toy": null}));
+
+        let has_age = Spi::get_one::<bool>("SELECT record_has_attribute(pet, 'age') FROM pets");
+        assert_eq!(has_age, Some(false));
+        let age = Spi::get_one::<i32>("SELECT pet_age(pet) FROM pets");
+        assert_eq!(age, None);
+        // dropped attributes keep their numbers
+        let count = Spi::get_one::<i64>("SELECT record_attribute_count(pet) FROM pets");
+        assert_eq!(count, Some(3));
+    }
+
+    #[pg_test]
+    fn test_from_struct_ignoring_unknown() {
+        create_altered_pets_table();
+        Spi::run("ALTER TYPE pet DROP ATTRIBUTE age");
+        let pet = Pet {
+            name: "Brandy".to_string(),
+            age: Some(5),
+        };
+        let tupdesc = || unsafe {
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(regtypein("pet"), -1))
+        };
+
+        let result = PgHeapTuple::from_struct(tupdesc(), &pet);
+        assert_eq!(
+            result.err(),
+            Some(PgHeapTupleError::NoSuchAttributeName("age".to_string()))
+        );
+
+        let tuple = PgHeapTuple::from_struct_ignoring_unknown(tupdesc(), &pet).unwrap();
+        assert_eq!(tuple.attnum("toy"), Some(AttributeNumber::new(3)));
+        assert_eq!(tuple.attnum("age"), None);
+        assert_eq!(
+            tuple.get_by_name::<String>("name").unwrap(),
+            Some("Brandy".to_string())
+        );
+        assert_eq!(tuple.get_by_name::<String>("toy").unwrap(), None);
+    }
+
+    #[pg_test]
+    fn test_by_index() {
+        create_altered_pets_table();
+        let attnos = AttributeNumbers::new(["name", "toy"]);
+        let mut tuple = PgHeapTuple::new_composite_type(regtypein("pet"));
+        let [name, toy] = attnos.resolve(&tuple).unwrap();
+        assert_eq!((name.get(), toy.get()), (1, 3));
+
+        tuple.set_by_index(name, "Nami").unwrap();
+        tuple.set_by_index(toy, "ball").unwrap();
+        assert_eq!(
+            tuple.get_by_index::<String>(toy).unwrap(),
+            Some("ball".to_string())
+        );
+        assert_eq!(
+            tuple.set_by_index(name, 5),
+            Err(PgHeapTupleError::IncompatibleTypes {
+                attribute: "name".to_string(),
+                expected: pg_sys::TEXTOID,
+                found: pg_sys::INT4OID,
+            })
+        );
+        assert_eq!(
+            tuple.get_by_index::<i32>(toy),
+            Err(PgHeapTupleError::IncompatibleTypes {
+                attribute: "toy".to_string(),
+                expected: pg_sys::TEXTOID,
+                found: pg_sys::INT4OID,
+            })
+        );
+        assert_eq!(
+            tuple.get_by_name::<f64>("name"),
+            Err(PgHeapTupleError::IncompatibleTypes {
+                attribute: "name".to_string(),
+                expected: pg_sys::TEXTOID,
+                found: pg_sys::FLOAT8OID,
+            })
+        );
+        assert_eq!(
+            tuple.get_by_index::<String>(AttributeNumber::new(4)),
+            Err(PgHeapTupleError::NoSuchAttributeNumber(4))
+        );
+        assert_eq!(
+            AttributeNumber::try_from(0),
+            Err(PgHeapTupleError::NoSuchAttributeNumber(0))
+        );
+
+        // once the type's been altered, the attributes are looked up again
+        Spi::run("ALTER TYPE pet DROP ATTRIBUTE toy");
+        Spi::run("ALTER TYPE pet ADD ATTRIBUTE toy text");
+        let tuple = PgHeapTuple::new_composite_type(regtypein("pet"));
+        let [name, toy] = attnos.resolve(&tuple).unwrap();
+        assert_eq!((name.get(), toy.get()), (1, 4));
+    }
+}
@@ -0,0 +1,84 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    #[pg_test]
+    fn test_histogram_buckets() {
+        let mut inner = PgHistogramInner::default();
+        let histogram = PgHistogram::new();
+        histogram.attach(&mut inner);
+
+        for value in [0, 1, 2, 3, 4, 7, 8, 1000, u64::MAX] {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.count(), 9);
+
+        let buckets = histogram.buckets();
+        assert_eq!(buckets.len(), 65);
+        let counts = buckets[..5]
+            .iter()
+            .map(|bucket| (bucket.lower_bound, bucket.upper_bound, bucket.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![(0, 0, 1), (1, 1, 1), (2, 3, 2), (4, 7, 2), (8, 15, 1)]
+        );
+        assert_eq!(buckets[10].lower_bound, 512);
+        assert_eq!(buckets[10].upper_bound, 1023);
+        assert_eq!(buckets[10].count, 1);
+        assert_eq!(buckets[64].upper_bound, u64::MAX);
+        assert_eq!(buckets[64].count, 1);
+    }
+
+    #[pg_test]
+    fn test_histogram_rows() {
+        let mut inner = PgHistogramInner::default();
+        let histogram = PgHistogram::new();
+        histogram.attach(&mut inner);
+
+        histogram.record(5);
+        histogram.record(6);
+        histogram.record_duration(Duration::from_millis(2));
+        histogram.record(u64::MAX);
+
+        let rows = histogram.rows().collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![(4, 7, 2), (1024, 2047, 1), (i64::MAX, i64::MAX, 1)]
+        );
+    }
+
+    #[pg_test]
+    fn test_histogram_time_and_reset() {
+        let mut inner = PgHistogramInner::default();
+        let histogram = PgHistogram::new();
+        histogram.attach(&mut inner);
+
+        let result = histogram.time(|| {
+            std::thread::sleep(Duration::from_millis(1));
+            42
+        });
+        assert_eq!(result, 42);
+        assert_eq!(histogram.count(), 1);
+        assert!(histogram.sum() >= 1000);
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.sum(), 0);
+        assert_eq!(histogram.rows().count(), 0);
+    }
+}
@@ -42,4 +42,39 @@ mod tests {
         .expect("failed to get SPI result");
         assert!(rc)
     }
+
+    #[pg_extern]
+    fn take_and_return_cidr(cidr: Cidr) -> Cidr {
+        cidr
+    }
+
+    #[pg_test]
+    fn test_take_and_return_cidr() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.take_and_return_cidr('192.168.0.0/16') = '192.168.0.0/16'::cidr;",
+        );
+        assert_eq!(rc, Some(true));
+    }
+
+    #[pg_test(error = "invalid cidr value: \"192.168.0.1/16\"")]
+    fn test_cidr_with_host_bits() {
+        Cidr("192.168.0.1/16".to_string()).into_datum();
+    }
+
+    #[pg_extern]
+    fn macaddr_to_macaddr8(addr: MacAddr) -> MacAddr8 {
+        addr.into()
+    }
+
+    #[pg_test]
+    fn test_macaddr() {
+        let rc = Spi::get_one::<MacAddr>("SELECT '08:00:2b:01:02:03'::macaddr;");
+        assert_eq!(rc, Some(MacAddr([0x08, 0x00, 0x2b, 0x01, 0x02, 0x03])));
+        assert_eq!(rc.unwrap().to_string(), "08:00:2b:01:02:03");
+
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.macaddr_to_macaddr8('08:00:2b:01:02:03') = '08:00:2b:01:02:03'::macaddr::macaddr8;",
+        );
+        assert_eq!(rc, Some(true));
+    }
 }
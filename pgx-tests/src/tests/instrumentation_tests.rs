@@ -0,0 +1,58 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    #[pg_extern]
+    fn instrumented_filter(value: i32) -> bool {
+        let keep = Instrumentation::time("filter", || {
+            std::thread::sleep(Duration::from_millis(1));
+            value % 2 == 0
+        });
+        if !keep {
+            Instrumentation::count("filtered", 1);
+        }
+        keep
+    }
+
+    #[pg_test]
+    fn test_instrumentation_counters() {
+        Instrumentation::reset();
+        let kept = Spi::get_one::<i64>(
+            "SELECT count(*) FROM generate_series(1, 5) AS n WHERE tests.instrumented_filter(n)",
+        );
+        assert_eq!(kept, Some(2));
+
+        let counters = Instrumentation::counters();
+        assert_eq!(counters.len(), 2);
+        assert_eq!(counters[0].name, "filter");
+        assert_eq!(counters[0].count, 5);
+        assert!(counters[0].elapsed >= Duration::from_millis(5));
+        assert_eq!(counters[1].name, "filtered");
+        assert_eq!(counters[1].count, 3);
+        assert_eq!(counters[1].elapsed, Duration::ZERO);
+    }
+
+    #[pg_test]
+    fn test_instrumentation_reset() {
+        Instrumentation::count("things", 3);
+        Instrumentation::count("things", 4);
+        assert_eq!(Instrumentation::counters()[0].count, 7);
+
+        Instrumentation::reset();
+        assert!(Instrumentation::counters().is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    /// Cancel our own query, as `SELECT pg_cancel_backend(pg_backend_pid())` would, without
+    /// running a query that would notice it
+    fn cancel_self() {
+        let pid = unsafe { pg_sys::MyProcPid };
+        direct_function_call::<bool>(pg_sys::pg_cancel_backend, vec![pid.into_datum()]);
+    }
+
+    /// Loop for up to a few seconds, which is plenty for a signal to ourselves to arrive
+    fn long_loop() -> usize {
+        (0..5000)
+            .check_for_interrupts()
+            .inspect(|_| std::thread::sleep(Duration::from_millis(1)))
+            .count()
+    }
+
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_cancel_long_loop() {
+        cancel_self();
+        long_loop();
+    }
+
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_interrupt_guard() {
+        let holdoff = unsafe { pg_sys::InterruptHoldoffCount };
+        {
+            let _outer = InterruptGuard::hold();
+            {
+                let _inner = InterruptGuard::hold();
+                assert_eq!(unsafe { pg_sys::InterruptHoldoffCount }, holdoff + 2);
+            }
+            assert_eq!(unsafe { pg_sys::InterruptHoldoffCount }, holdoff + 1);
+
+            // the cancel waits for the guard
+            cancel_self();
+            assert_eq!(long_loop(), 5000);
+            assert!(interrupt_pending());
+        }
+        assert_eq!(unsafe { pg_sys::InterruptHoldoffCount }, holdoff);
+
+        check_for_interrupts!();
+    }
+
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_interrupt_guard_cancel_only() {
+        let holdoff = unsafe { pg_sys::QueryCancelHoldoffCount };
+        {
+            let _guard = InterruptGuard::hold_cancel();
+            assert_eq!(unsafe { pg_sys::QueryCancelHoldoffCount }, holdoff + 1);
+
+            cancel_self();
+            assert_eq!(long_loop(), 5000);
+        }
+        assert_eq!(unsafe { pg_sys::QueryCancelHoldoffCount }, holdoff);
+
+        check_for_interrupts!();
+    }
+
+    /// Wait up to a few seconds for a signal to ourselves to arrive, without processing it
+    fn wait_for_interrupt() {
+        for _ in 0..5000 {
+            if interrupt_pending() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[pg_test]
+    fn test_cancellation_token_cancel() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        assert_eq!(clone.check(), Ok(()));
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(Cancelled));
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_cancellation_token_query_cancel() {
+        let token = CancellationToken::new();
+        let worker = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                let mut checks = 0u64;
+                while token.check().is_ok() {
+                    checks += 1;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                checks
+            })
+        };
+
+        cancel_self();
+        worker.join().unwrap();
+        assert!(token.is_cancelled());
+        token.check_for_interrupts();
+    }
+
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_cancellation_token_interrupt_guard() {
+        let token = CancellationToken::new();
+        {
+            let _guard = InterruptGuard::hold_cancel();
+            cancel_self();
+            wait_for_interrupt();
+            assert!(!token.is_cancelled());
+        }
+        assert!(token.is_cancelled());
+        token.check_for_interrupts();
+    }
+}
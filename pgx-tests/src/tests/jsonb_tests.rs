@@ -0,0 +1,167 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn jsonb_path_text(document: JsonbRef, path: Vec<String>) -> Option<String> {
+        let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+        match document.get_path(&path)? {
+            JsonbValueRef::Null => Some("null".into()),
+            JsonbValueRef::Bool(value) => Some(value.to_string()),
+            JsonbValueRef::Number(value) => Some(value.to_string()),
+            JsonbValueRef::String(value) => Some(value.to_string()),
+            JsonbValueRef::Container(container) => Some(container.to_string()),
+        }
+    }
+
+    #[pg_extern]
+    fn jsonb_subdocument(document: JsonbRef<'static>, key: &str) -> Option<JsonbRef<'static>> {
+        document.get(key)?.as_container()
+    }
+
+    #[pg_extern]
+    fn jsonb_reversed(document: JsonbRef) -> JsonbRef<'static> {
+        let mut builder = JsonbBuilder::new();
+        builder.begin_array();
+        let elements = document.elements().collect::<Vec<_>>();
+        for element in elements.iter().rev() {
+            builder.value(element);
+        }
+        builder.end_array();
+        builder.finish()
+    }
+
+    #[pg_test]
+    fn test_jsonb_get_path() {
+        let document = r#"'{"a": {"b": [1, "two", {"c": null}], "d": true}, "e": 1.50}'::jsonb"#;
+        for (path, expected) in [
+            ("{a,b,1}", Some("two")),
+            ("{a,b,0}", Some("1")),
+            ("{a,b,2,c}", Some("null")),
+            ("{a,d}", Some("true")),
+            ("{e}", Some("1.50")),
+            ("{a,b,2}", Some(r#"{"c": null}"#)),
+            (
+                "{}",
+                Some(r#"{"a": {"b": [1, "two", {"c": null}], "d": true}, "e": 1.50}"#),
+            ),
+            ("{a,b,3}", None),
+            ("{a,b,x}", None),
+            ("{a,x}", None),
+            ("{e,x}", None),
+        ] {
+            let result = Spi::get_one::<String>(&format!(
+                "SELECT tests.jsonb_path_text({}, '{}')",
+                document, path
+            ));
+            assert_eq!(result.as_deref(), expected, "path {}", path);
+        }
+
+        let scalar = Spi::get_one::<String>(r#"SELECT tests.jsonb_path_text('"hi"', '{}')"#);
+        assert_eq!(scalar.as_deref(), Some("hi"));
+    }
+
+    #[pg_test]
+    fn test_jsonb_subdocument() {
+        let result = Spi::get_one::<bool>(
+            r#"SELECT tests.jsonb_subdocument('{"a": {"b": [1, 2]}, "c": 3}', 'a') = '{"b": [1, 2]}'"#,
+        );
+        assert_eq!(result, Some(true));
+
+        let result =
+            Spi::get_one::<bool>(r#"SELECT tests.jsonb_subdocument('{"c": 3}', 'c') IS NULL"#);
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test]
+    fn test_jsonb_iteration() {
+        let document = Spi::get_one::<JsonbRef>(
+            r#"SELECT '{"bb": [1, 2, 3], "a": "x", "ccc": {"d": 4}}'::jsonb"#,
+        )
+        .expect("jsonb was null");
+        assert!(document.is_object());
+        assert_eq!(document.len(), 3);
+        // jsonb keeps keys shortest first
+        assert_eq!(document.keys().collect::<Vec<_>>(), vec!["a", "bb", "ccc"]);
+
+        let entries = document.entries().collect::<Vec<_>>();
+        assert_eq!(entries[0].0, "a");
+        assert_eq!(entries[0].1.as_str(), Some("x"));
+        assert_eq!(
+            entries[2].1.as_container().unwrap().to_string(),
+            r#"{"d": 4}"#
+        );
+
+        let array = document.get("bb").unwrap().as_container().unwrap();
+        assert!(array.is_array());
+        let numbers = array
+            .elements()
+            .map(|element| element.as_number().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec!["1", "2", "3"]);
+        assert_eq!(array.keys().count(), 0);
+        assert_eq!(document.elements().count(), 0);
+    }
+
+    #[pg_test]
+    fn test_jsonb_builder() {
+        let mut builder = JsonbBuilder::new();
+        builder
+            .begin_object()
+            .key("name")
+            .string("Nami")
+            .key("age")
+            .number(3.into())
+            .key("toys")
+            .begin_array()
+            .string("ball")
+            .null()
+            .bool(false)
+            .end_array()
+            .key("name")
+            .string("Nami the dog")
+            .end_object();
+        let jsonb = builder.finish();
+        assert_eq!(
+            jsonb.to_string(),
+            r#"{"age": 3, "name": "Nami the dog", "toys": ["ball", null, false]}"#
+        );
+
+        let mut builder = JsonbBuilder::new();
+        builder.string("just me");
+        assert_eq!(builder.finish().to_string(), r#""just me""#);
+    }
+
+    #[pg_test]
+    fn test_jsonb_builder_copies_values() {
+        let result = Spi::get_one::<bool>(
+            r#"SELECT tests.jsonb_reversed('[1, "two", {"three": [3]}, null]') = '[null, {"three": [3]}, "two", 1]'"#,
+        );
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test(error = "a value in an object needs a key() first")]
+    fn test_jsonb_builder_value_without_key() {
+        JsonbBuilder::new().begin_object().string("oops");
+    }
+
+    #[pg_test(error = "JsonbBuilder finished with 1 objects or arrays still open")]
+    fn test_jsonb_builder_unfinished() {
+        let mut builder = JsonbBuilder::new();
+        builder.begin_array();
+        builder.finish();
+    }
+}
@@ -0,0 +1,129 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn held_locks(relation: &PgRelation, mode: LockMode) -> i64 {
+        Spi::get_one::<i64>(&format!(
+            "SELECT count(*) FROM pg_locks
+              WHERE relation = {} AND mode = '{}' AND pid = pg_backend_pid() AND granted",
+            relation.oid(),
+            mode.name()
+        ))
+        .expect("SQL select failed")
+    }
+
+    #[pg_test]
+    fn test_lock_is_released_on_drop() {
+        Spi::run("CREATE TABLE lock_test (id int)");
+        let relation = PgRelation::open_with_name_and_share_lock("lock_test").unwrap();
+
+        let guard = relation.lock(LockMode::ShareRowExclusive);
+        assert_eq!(guard.mode(), LockMode::ShareRowExclusive);
+        assert_eq!(held_locks(&relation, LockMode::ShareRowExclusive), 1);
+
+        drop(guard);
+        assert_eq!(held_locks(&relation, LockMode::ShareRowExclusive), 0);
+    }
+
+    #[pg_test]
+    fn test_try_lock() {
+        Spi::run("CREATE TABLE try_lock_test (id int)");
+        let relation = PgRelation::open_with_name_and_share_lock("try_lock_test").unwrap();
+
+        let guard = relation
+            .try_lock(LockMode::Exclusive)
+            .expect("lock should be available");
+        guard.hold_until_end_of_transaction();
+        assert_eq!(held_locks(&relation, LockMode::Exclusive), 1);
+    }
+
+    #[pg_test]
+    fn test_lock_conflicts() {
+        Spi::run("CREATE TABLE lock_conflicts_test (id int)");
+        let relation = PgRelation::open_with_name_and_share_lock("lock_conflicts_test").unwrap();
+
+        // our own locks never conflict with us
+        assert!(relation
+            .lock_conflicts(LockMode::AccessExclusive)
+            .is_empty());
+    }
+
+    #[pg_test]
+    fn test_lock_mode_conflicts() {
+        assert!(LockMode::AccessExclusive.conflicts_with(LockMode::AccessShare));
+        assert!(LockMode::Share.conflicts_with(LockMode::RowExclusive));
+        assert!(!LockMode::RowShare.conflicts_with(LockMode::RowExclusive));
+        assert!(!LockMode::Share.conflicts_with(LockMode::Share));
+        assert_eq!(
+            LockMode::AccessShare.conflicting_modes(),
+            vec![LockMode::AccessExclusive]
+        );
+    }
+
+    #[pg_test]
+    fn test_lock_mode_conflicts_are_symmetric() {
+        // writers block `CREATE INDEX` and `CREATE INDEX` blocks writers
+        assert!(LockMode::RowExclusive.conflicts_with(LockMode::Share));
+        assert!(LockMode::Share.conflicts_with(LockMode::RowExclusive));
+        assert!(LockMode::RowExclusive
+            .conflicting_modes()
+            .contains(&LockMode::Share));
+        assert!(LockMode::Share
+            .conflicting_modes()
+            .contains(&LockMode::RowExclusive));
+
+        for mode in LockMode::ALL.iter().copied() {
+            for other in LockMode::ALL.iter().copied() {
+                assert_eq!(
+                    mode.conflicts_with(other),
+                    other.conflicts_with(mode),
+                    "{} and {}",
+                    mode,
+                    other
+                );
+            }
+        }
+    }
+
+    #[pg_test]
+    fn test_lock_set() {
+        Spi::run("CREATE TABLE lock_set_a (id int)");
+        Spi::run("CREATE TABLE lock_set_b (id int)");
+        let a = PgRelation::open_with_name_and_share_lock("lock_set_a").unwrap();
+        let b = PgRelation::open_with_name_and_share_lock("lock_set_b").unwrap();
+
+        let locks = PgRelationLockSet::new()
+            .add(b.oid(), LockMode::Exclusive)
+            .add_all(vec![a.oid(), b.oid()], LockMode::RowExclusive)
+            .acquire()
+            .expect("locks should be available");
+
+        // locked in oid order, and `b` in the strongest of its modes
+        let mut expected = vec![
+            (a.oid(), LockMode::RowExclusive),
+            (b.oid(), LockMode::Exclusive),
+        ];
+        expected.sort();
+        assert_eq!(locks.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(held_locks(&a, LockMode::RowExclusive), 1);
+        assert_eq!(held_locks(&b, LockMode::Exclusive), 1);
+        assert_eq!(held_locks(&b, LockMode::RowExclusive), 0);
+
+        drop(locks);
+        assert_eq!(held_locks(&a, LockMode::RowExclusive), 0);
+        assert_eq!(held_locks(&b, LockMode::Exclusive), 0);
+    }
+}
@@ -86,4 +86,182 @@ mod tests {
     fn test_panic() {
         panic!("panic message")
     }
+
+    /// Call `call`, raising an `ERROR` of the fields of the `ERROR` it raises, separated by `|`
+    fn error_fields(call: &str) {
+        Spi::run(&format!(
+            "DO $$
+             DECLARE
+                code text; detail text; hint text; tbl text; col text;
+             BEGIN
+                PERFORM {};
+             EXCEPTION WHEN OTHERS THEN
+                GET STACKED DIAGNOSTICS code = RETURNED_SQLSTATE, detail = PG_EXCEPTION_DETAIL,
+                    hint = PG_EXCEPTION_HINT, tbl = TABLE_NAME, col = COLUMN_NAME;
+                RAISE EXCEPTION '%|%|%|%|%', code, detail, hint, tbl, col;
+             END $$",
+            call
+        ));
+    }
+
+    #[pg_extern]
+    fn checked_price(price: i64) -> Result<i64, ErrorReport> {
+        if price < 0 {
+            return Err(ErrorReport::new(
+                PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+                "negative price",
+            )
+            .detail(format!("The price was {}.", price))
+            .hint("Refunds are recorded separately.")
+            .table("products")
+            .column("price"));
+        }
+        Ok(price)
+    }
+
+    enum AccountError {
+        NoSuchAccount(i64),
+    }
+
+    impl IntoErrorReport for AccountError {
+        fn into_error_report(self) -> ErrorReport {
+            match self {
+                AccountError::NoSuchAccount(id) => ErrorReport::new(
+                    PgSqlErrorCode::ERRCODE_NO_DATA_FOUND,
+                    format!("account {} does not exist", id),
+                ),
+            }
+        }
+    }
+
+    #[pg_extern]
+    fn account_name(id: i64) -> Result<Option<String>, AccountError> {
+        match id {
+            1 => Ok(Some("checking".to_string())),
+            2 => Ok(None),
+            _ => Err(AccountError::NoSuchAccount(id)),
+        }
+    }
+
+    #[pg_extern]
+    fn check_positive(n: i32) -> Result<(), String> {
+        if n > 0 {
+            Ok(())
+        } else {
+            Err(format!("{} isn't positive", n))
+        }
+    }
+
+    #[pg_test]
+    fn test_result_ok() {
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT tests.checked_price(5)"),
+            Some(5)
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.account_name(1)"),
+            Some("checking".to_string())
+        );
+        assert_eq!(Spi::get_one::<String>("SELECT tests.account_name(2)"), None);
+        Spi::run("SELECT tests.check_positive(1)");
+    }
+
+    #[pg_test(error = "23514|The price was -5.|Refunds are recorded separately.|products|price")]
+    fn test_result_error_report() {
+        error_fields("tests.checked_price(-5)");
+    }
+
+    #[pg_test(error = "P0002||||")]
+    fn test_result_into_error_report() {
+        error_fields("tests.account_name(7)");
+    }
+
+    #[pg_test(error = "-1 isn't positive")]
+    fn test_result_string() {
+        Spi::run("SELECT tests.check_positive(-1)");
+    }
+
+    #[pg_test(error = "22000||||")]
+    fn test_result_string_code() {
+        error_fields("tests.check_positive(-1)");
+    }
+
+    #[pg_test]
+    fn test_ereport_macro_notice() {
+        ereport!(
+            NOTICE,
+            PgSqlErrorCode::ERRCODE_WARNING_DEPRECATED_FEATURE,
+            "deprecated",
+            hint = "Use something else.",
+        );
+    }
+
+    #[pg_test(error = "42P01||Create it first.|widgets|")]
+    fn test_ereport_macro_error() {
+        error_fields("tests.ereport_undefined_table()");
+    }
+
+    #[pg_extern]
+    fn ereport_undefined_table() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+            "widgets doesn't exist",
+            hint = "Create it first.",
+            table = "widgets",
+        );
+    }
+
+    fn parse_sku(sku: &str) -> Result<i64, impl IntoErrorReport + '_> {
+        sku.strip_prefix("SKU-")
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| {
+                ErrorReport::lazy(
+                    PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                    move || format!("invalid SKU: \"{}\"", sku),
+                )
+            })
+    }
+
+    #[pg_extern]
+    fn lazy_sku(sku: &str) -> i64 {
+        parse_sku(sku).unwrap_or_else(|e| {
+            e.into_error_report()
+                .hint("SKUs look like SKU-1234.")
+                .raise()
+        })
+    }
+
+    #[pg_test]
+    fn test_lazy_error_report_unreported() {
+        let formatted = std::cell::Cell::new(0);
+        let report = ErrorReport::lazy(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, || {
+            formatted.set(formatted.get() + 1);
+            "never reported"
+        });
+        assert_eq!(report.sqlerrcode(), PgSqlErrorCode::ERRCODE_DATA_EXCEPTION);
+        drop(report);
+        assert_eq!(formatted.get(), 0);
+
+        let report = ErrorReport::lazy(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, || {
+            formatted.set(formatted.get() + 1);
+            "reported"
+        });
+        assert_eq!(report.into_error_report().message(), "reported");
+        assert_eq!(formatted.get(), 1);
+    }
+
+    #[pg_test(error = "invalid SKU: \"12\"")]
+    fn test_lazy_error_report_raise() {
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT tests.lazy_sku('SKU-12')"),
+            Some(12)
+        );
+        Spi::run("SELECT tests.lazy_sku('12')");
+    }
+
+    #[pg_test(error = "22P02||SKUs look like SKU-1234.||")]
+    fn test_lazy_error_report_fields() {
+        error_fields("tests.lazy_sku('12')");
+    }
 }
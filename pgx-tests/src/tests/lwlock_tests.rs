@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn addin_shmem_init_lock() -> *mut pg_sys::LWLock {
+        unsafe { &mut (*pg_sys::MainLWLockArray.add(21)).lock }
+    }
+
+    #[pg_test]
+    fn test_lwlock_guards() {
+        let lock = addin_shmem_init_lock();
+        unsafe {
+            let shared = LwLockShared::acquire(lock);
+            assert!(LwLockShared::try_acquire(lock).is_some());
+            assert!(LwLockExclusive::try_acquire(lock).is_none());
+            drop(shared);
+            assert!(!pg_sys::LWLockHeldByMe(lock));
+
+            let exclusive = LwLockExclusive::try_acquire(lock).expect("lock should be available");
+            assert!(pg_sys::LWLockHeldByMe(lock));
+            drop(exclusive);
+            assert!(!pg_sys::LWLockHeldByMe(lock));
+        }
+    }
+
+    #[pg_test]
+    fn test_lwlock_poisoned_by_panic() {
+        let lock = addin_shmem_init_lock();
+        let poisoned = AtomicBool::new(false);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+            let _guard = LwLockExclusive::acquire(lock).poison_on_panic(&poisoned);
+            panic!("panicking while holding the lock");
+        }));
+        assert!(result.is_err());
+        assert!(poisoned.load(Ordering::SeqCst));
+        assert!(!unsafe { pg_sys::LWLockHeldByMe(lock) });
+    }
+
+    #[pg_test]
+    fn test_spinlock() {
+        let mut inner = PgSpinLockInner::<i32>::default();
+        let lock = PgSpinLock::new();
+        lock.attach(&mut inner);
+
+        *lock.lock() += 42;
+        assert_eq!(*lock.lock(), 42);
+        assert!(!lock.is_poisoned());
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "PgSpinLock is poisoned")]
+    fn test_spinlock_poisoned_by_panic() {
+        let mut inner = PgSpinLockInner::<i32>::default();
+        let lock = PgSpinLock::new();
+        lock.attach(&mut inner);
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut value = lock.lock();
+            *value = 1;
+            panic!("panicking while holding the lock");
+        }));
+        assert!(result.is_err());
+        assert!(lock.is_poisoned());
+
+        lock.clear_poison();
+        assert_eq!(*lock.lock(), 1);
+
+        let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let _value = lock.lock();
+            panic!("panicking while holding the lock");
+        }));
+        lock.lock();
+    }
+}
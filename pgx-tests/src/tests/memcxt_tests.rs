@@ -13,6 +13,7 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
+    use pgx::pg_sys::AsPgCStr;
     use pgx::*;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
@@ -47,4 +48,141 @@ mod tests {
 
         assert!(did_drop.load(Ordering::SeqCst))
     }
+
+    #[pg_extern]
+    fn memory_contexts() -> impl Iterator<
+        Item = (
+            name!(name, String),
+            name!(ident, Option<String>),
+            name!(level, i32),
+            name!(total_bytes, i64),
+            name!(used_bytes, i64),
+        ),
+    > {
+        PgMemoryContexts::TopMemoryContext
+            .statistics_tree()
+            .into_iter()
+            .map(|stats| {
+                let used_bytes = stats.used_bytes() as i64;
+                (
+                    stats.name,
+                    stats.ident,
+                    stats.level as i32,
+                    stats.total_bytes as i64,
+                    used_bytes,
+                )
+            })
+    }
+
+    #[pg_test]
+    fn test_statistics_tree() {
+        let mut parent = PgMemoryContexts::new("test parent");
+        let child = unsafe {
+            pg_sys::AllocSetContextCreateExtended(
+                parent.value(),
+                "test child".as_pg_cstr(),
+                pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
+                pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
+                pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+            )
+        };
+        let mut child = PgMemoryContexts::For(child);
+        let empty = child.statistics();
+        child.palloc(64 * 1024);
+
+        let tree = parent.statistics_tree();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].name, "test parent");
+        assert_eq!(tree[0].level, 0);
+        assert_eq!(tree[1].name, "test child");
+        assert_eq!(tree[1].level, 1);
+        assert!(tree[1].total_bytes >= empty.total_bytes + 64 * 1024);
+        assert!(tree[1].used_bytes() >= 64 * 1024);
+        assert!(tree[1].blocks > empty.blocks);
+
+        parent.reset();
+        assert_eq!(parent.statistics_tree().len(), 1);
+    }
+
+    #[pg_test]
+    fn test_memory_contexts_function() {
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM tests.memory_contexts() \
+             WHERE name = 'TopMemoryContext' AND level = 0",
+        );
+        assert_eq!(count, Some(1));
+        let nested = Spi::get_one::<bool>(
+            "SELECT bool_and(total_bytes >= used_bytes) AND max(level) > 0 \
+             FROM tests.memory_contexts()",
+        );
+        assert_eq!(nested, Some(true));
+    }
+
+    #[pg_test]
+    fn test_budgeted_memory_context() {
+        let mut budget = BudgetedMemoryContext::new(PgMemoryContexts::new("test budget"), 1024);
+        budget.palloc(512);
+        budget.palloc0_slice::<i64>(32);
+        assert_eq!(budget.allocated(), 768);
+        assert_eq!(budget.remaining(), 256);
+
+        budget.reset();
+        assert_eq!(budget.allocated(), 0);
+        budget.pstrdup("within budget");
+        assert_eq!(budget.allocated(), 14);
+    }
+
+    #[pg_test(
+        error = "memory budget of 1024 bytes exceeded: 1000 bytes are already allocated, and 32 more were requested"
+    )]
+    fn test_budgeted_memory_context_exceeded() {
+        let mut budget = BudgetedMemoryContext::new(PgMemoryContexts::new("test budget"), 1024);
+        budget.palloc(1000);
+        budget.palloc_slice::<i32>(8);
+    }
+
+    #[pg_test(
+        error = "memory budget of 1024 bytes exceeded: 0 bytes are already allocated, and 18446744073709551615 more were requested"
+    )]
+    fn test_budgeted_memory_context_overflow() {
+        let mut budget = BudgetedMemoryContext::new(PgMemoryContexts::new("test budget"), 1024);
+        budget.palloc_slice::<i64>(usize::MAX / 2);
+    }
+
+    #[pg_test]
+    fn test_weak_pctx() {
+        let did_drop = Arc::new(AtomicBool::new(false));
+        let mut context = PgMemoryContexts::new("test weak");
+        let mut weak = context.leak_weak((
+            42,
+            TestObject {
+                did_drop: did_drop.clone(),
+            },
+        ));
+        assert!(weak.is_valid());
+        unsafe { weak.get_mut() }.unwrap().0 += 1;
+        assert_eq!(unsafe { weak.get() }.map(|value| value.0), Some(43));
+
+        context.reset();
+        assert!(did_drop.load(Ordering::SeqCst));
+        assert!(!weak.is_valid());
+        assert!(unsafe { weak.get() }.is_none());
+        assert!(weak.as_ptr().is_none());
+
+        // the context is still usable, but what was in it is gone for good
+        context.palloc(64);
+        assert!(!weak.is_valid());
+    }
+
+    #[pg_test]
+    fn test_weak_pctx_context_deleted() {
+        let weak = {
+            let mut context = PgMemoryContexts::new("test weak deleted");
+            let weak = context.leak_weak(String::from("temporary"));
+            assert_eq!(unsafe { weak.get() }.map(String::as_str), Some("temporary"));
+            drop(context);
+            weak
+        };
+        assert!(!weak.is_valid());
+    }
 }
@@ -9,34 +9,92 @@ Use of this source code is governed by the MIT license that can be found in the
 
 mod aggregate_tests;
 mod anyarray_tests;
+mod anyelement_tests;
 mod array_tests;
+#[cfg(feature = "async")]
+mod async_bgworker_tests;
+mod backend_state_tests;
+mod backend_status_tests;
+mod brin_tests;
 mod bytea_tests;
+mod cast_tests;
 mod cfg_tests;
+#[cfg(feature = "chrono")]
+mod chrono_tests;
+mod chunked_tests;
+mod composite_tests;
+mod copy_tests;
 mod datetime_tests;
+mod datum_slice_tests;
+#[cfg(feature = "rust_decimal")]
+mod decimal_tests;
 mod default_arg_value_tests;
 mod derive_pgtype_lifetimes;
+mod dsm_tests;
 mod enum_type_tests;
+mod event_trigger_tests;
+mod expanded_tests;
+mod export_tests;
 mod fcinfo_tests;
+mod fdw_tests;
+mod formatting_tests;
+mod geo_tests;
+mod gin_tests;
+mod gist_tests;
 mod guc_tests;
+mod heap_tuple_tests;
+mod histogram_tests;
 mod hooks_tests;
 mod inet_tests;
+mod instrumentation_tests;
 mod internal_tests;
+mod interrupts_tests;
 mod json_tests;
+mod jsonb_tests;
 mod lifetime_tests;
+mod lock_tests;
 mod log_tests;
+mod lwlock_tests;
 mod memcxt_tests;
 mod name_tests;
 mod numeric_tests;
+mod operator_tests;
+mod packed_args_tests;
+mod page_inspect_tests;
+mod panic_backtrace_tests;
+mod parallel_tests;
 mod pg_extern_tests;
 mod pg_try_tests;
 mod pgbox_tests;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+mod planner_support_tests;
+#[cfg(feature = "plugins")]
+mod plugin_tests;
 mod postgres_type_tests;
+mod recovery_tests;
+mod retry_tests;
+mod roles_tests;
 mod schema_tests;
+mod server_features_tests;
+mod session_tests;
+mod slow_calls_tests;
 mod spi_tests;
+mod spi_watchdog_tests;
 mod srf_tests;
+mod status_view_tests;
 mod struct_type_tests;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+mod tableam_tests;
+mod test_matrix_tests;
+mod text_search_tests;
+mod timezone_tests;
+mod trigger_tests;
 mod uuid_tests;
 mod variadic_tests;
+mod wait_event_tests;
+#[cfg(feature = "wasm")]
+mod wasm_tests;
+mod window_tests;
 mod xact_callback_tests;
 mod xid64_tests;
 
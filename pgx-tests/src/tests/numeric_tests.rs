@@ -55,6 +55,89 @@ mod tests {
         assert!(result);
     }
 
+    #[pg_extern]
+    fn numeric_average(values: Vec<Numeric>) -> Numeric {
+        let count: Numeric = (values.len() as u64).into();
+        let sum = values
+            .into_iter()
+            .fold(0.into(), |sum: Numeric, value| sum + value);
+        sum / count
+    }
+
+    #[pg_test]
+    fn test_numeric_average() {
+        let result = Spi::get_one::<bool>(
+            "SELECT tests.numeric_average(ARRAY[1.5, 2.25, 99999999999999999999]) = (1.5 + 2.25 + 99999999999999999999) / 3;",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+    }
+
+    #[pg_test]
+    fn test_numeric_arithmetic() {
+        let a = Numeric("12.5".into());
+        let b = Numeric("-0.25".into());
+        assert_eq!((a.clone() + b.clone()).0, "12.25");
+        assert_eq!((a.clone() - b.clone()).0, "12.75");
+        assert_eq!((a.clone() * b.clone()).0, "-3.125");
+        assert_eq!(a.clone() / b.clone(), (-50).into());
+        assert_eq!((a.clone() % Numeric("5".into())).0, "2.5");
+        assert_eq!((-a).0, "-12.5");
+    }
+
+    #[pg_test(error = "division by zero")]
+    fn test_numeric_division_by_zero() {
+        let _ = Numeric::from(1i128) / 0.into();
+    }
+
+    #[pg_test]
+    fn test_numeric_comparison() {
+        let one = Numeric("1.000".into());
+        assert_eq!(one, 1.into());
+        assert!(one < Numeric("1.001".into()));
+        assert!(Numeric("-2".into()) < one);
+        assert!(Numeric("NaN".into()) > Numeric(i128::MAX.to_string()));
+        assert!(Numeric("nan".into()).is_nan());
+        assert!(!one.is_nan());
+        assert_eq!(one.normalize().0, "1.000");
+        assert_eq!(Numeric("+15e-1".into()).normalize().0, "1.5");
+    }
+
+    #[pg_test]
+    fn test_numeric_rounding() {
+        let value = Numeric("-1234.5678".into());
+        assert_eq!(value.round(2).0, "-1234.57");
+        assert_eq!(value.round(-2).0, "-1200");
+        assert_eq!(value.trunc(2).0, "-1234.56");
+        assert_eq!(value.trunc(0).0, "-1234");
+        assert_eq!(value.abs().0, "1234.5678");
+    }
+
+    #[pg_test]
+    fn test_numeric_i128() {
+        use std::convert::TryFrom;
+
+        assert_eq!(i128::try_from(Numeric::from(i128::MIN)), Ok(i128::MIN));
+        assert_eq!(i128::try_from(Numeric::from(i128::MAX)), Ok(i128::MAX));
+        assert_eq!(i128::try_from(Numeric("42.000".into())), Ok(42));
+        assert_eq!(
+            i128::try_from(Numeric("42.5".into())),
+            Err(NumericConversionError::NotAnInteger)
+        );
+        assert_eq!(
+            i128::try_from(Numeric::from(u128::MAX)),
+            Err(NumericConversionError::OutOfRange)
+        );
+        assert_eq!(
+            i128::try_from(Numeric("NaN".into())),
+            Err(NumericConversionError::NaN)
+        );
+        assert_eq!(
+            i64::try_from(Numeric::from(i64::MAX as i128 + 1)),
+            Err(NumericConversionError::OutOfRange)
+        );
+    }
+
     #[pg_test]
     fn test_deserialize_numeric() {
         use serde_json::json;
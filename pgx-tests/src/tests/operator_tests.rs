@@ -0,0 +1,194 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+/// Has a hash operator class, so its `=` can be used by hash joins
+#[derive(
+    Debug, Eq, PartialEq, Hash, Serialize, Deserialize, PostgresType, PostgresEq, PostgresHash,
+)]
+#[pgx(distinct_from)]
+pub struct HashedThing(i32);
+
+/// Only has `=`, which can't be used by hash joins
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, PostgresType, PostgresEq)]
+pub struct UnhashedThing(i32);
+
+/// Can be in a unique index, so it can be the arbiter of `INSERT ... ON CONFLICT`
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Serialize,
+    Deserialize,
+    PostgresType,
+    PostgresEq,
+    PostgresOrd,
+    PostgresHash,
+)]
+#[pgx(conflict_arbiter)]
+pub struct ArbiterThing(i32);
+
+/// Ordered by operators and an operator class of its own, rather than `#[derive(PostgresOrd)]`
+#[derive(Debug, Serialize, Deserialize, PostgresType)]
+#[pg_opclass(
+    using = "btree",
+    default,
+    operators(1 = "<", 2 = "<=", 3 = "=", 4 = ">=", 5 = ">"),
+    functions(1 = rankedthing_cmp),
+    requires = [rankedthing_lt, rankedthing_le, rankedthing_eq, rankedthing_ge, rankedthing_gt]
+)]
+pub struct RankedThing(i32);
+
+#[pg_extern(immutable, parallel_safe)]
+fn rankedthing_cmp(left: RankedThing, right: RankedThing) -> i32 {
+    left.0.cmp(&right.0) as i32
+}
+
+#[pg_operator(immutable, symbol = "<", commutator = ">", negator = ">=")]
+fn rankedthing_lt(left: RankedThing, right: RankedThing) -> bool {
+    left.0 < right.0
+}
+
+#[pg_operator(immutable, symbol = "<=", commutator = ">=", negator = ">")]
+fn rankedthing_le(left: RankedThing, right: RankedThing) -> bool {
+    left.0 <= right.0
+}
+
+#[pg_operator(immutable, symbol = "=", commutator = "=")]
+fn rankedthing_eq(left: RankedThing, right: RankedThing) -> bool {
+    left.0 == right.0
+}
+
+#[pg_operator(immutable, symbol = ">=", commutator = "<=", negator = "<")]
+fn rankedthing_ge(left: RankedThing, right: RankedThing) -> bool {
+    left.0 >= right.0
+}
+
+#[pg_operator(immutable, symbol = ">", commutator = "<", negator = "<=")]
+fn rankedthing_gt(left: RankedThing, right: RankedThing) -> bool {
+    left.0 > right.0
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn setup_join(type_name: &str) -> &'static str {
+        Spi::run(&format!(
+            "CREATE TABLE lefts AS SELECT x::text::{ty} AS thing FROM generate_series(1, 100) x;
+             CREATE TABLE rights AS SELECT x::text::{ty} AS thing FROM generate_series(50, 150) x;
+             ANALYZE lefts;
+             ANALYZE rights;
+             SET LOCAL enable_nestloop = off;
+             SET LOCAL enable_mergejoin = off;",
+            ty = type_name
+        ));
+        "SELECT count(*) FROM lefts JOIN rights USING (thing)"
+    }
+
+    #[pg_test]
+    fn test_hash_join() {
+        let query = setup_join("HashedThing");
+        assert!(Spi::explain(query).0.to_string().contains("\"Hash Join\""));
+        assert_eq!(Spi::get_one::<i64>(query), Some(51));
+    }
+
+    #[pg_test]
+    fn test_no_hash_join_without_hash_opclass() {
+        let query = setup_join("UnhashedThing");
+        assert!(!Spi::explain(query).0.to_string().contains("\"Hash Join\""));
+        assert_eq!(Spi::get_one::<i64>(query), Some(51));
+    }
+
+    #[pg_test]
+    fn test_merge_join() {
+        let query = setup_join("ArbiterThing");
+        Spi::run("SET LOCAL enable_mergejoin = on; SET LOCAL enable_hashjoin = off;");
+        assert!(Spi::explain(query).0.to_string().contains("\"Merge Join\""));
+        assert_eq!(Spi::get_one::<i64>(query), Some(51));
+    }
+
+    #[pg_test]
+    fn test_on_conflict_arbiter() {
+        Spi::run(
+            "CREATE TABLE arbitrated (thing ArbiterThing PRIMARY KEY, hits int NOT NULL);
+             INSERT INTO arbitrated VALUES ('1', 1), ('2', 1);
+             INSERT INTO arbitrated VALUES ('2', 1), ('3', 1)
+                ON CONFLICT (thing) DO UPDATE SET hits = arbitrated.hits + excluded.hits;",
+        );
+        let hits = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(hits ORDER BY thing) FROM arbitrated WHERE thing = ANY(ARRAY['2', '3']::ArbiterThing[])",
+        );
+        assert_eq!(hits, Some(vec![2, 1]));
+    }
+
+    #[pg_test]
+    fn test_operator_symbol() {
+        let compared = Spi::get_one::<Vec<bool>>(
+            "SELECT ARRAY[
+                '1'::RankedThing < '2'::RankedThing,
+                '1'::RankedThing >= '2'::RankedThing,
+                '2'::RankedThing = '2'::RankedThing
+            ]",
+        );
+        assert_eq!(compared, Some(vec![true, false, true]));
+
+        let commutator = Spi::get_one::<bool>(
+            "SELECT lt.oprcom = gt.oid AND lt.oprnegate = ge.oid
+               FROM pg_operator lt, pg_operator gt, pg_operator ge
+              WHERE lt.oprname = '<' AND lt.oprleft = 'RankedThing'::regtype
+                AND gt.oprname = '>' AND gt.oprleft = 'RankedThing'::regtype
+                AND ge.oprname = '>=' AND ge.oprleft = 'RankedThing'::regtype",
+        );
+        assert_eq!(commutator, Some(true));
+    }
+
+    #[pg_test]
+    fn test_opclass() {
+        let sorted = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(thing::text::int ORDER BY thing)
+               FROM unnest(ARRAY['10', '9', '2']::RankedThing[]) thing",
+        );
+        assert_eq!(sorted, Some(vec![2, 9, 10]));
+
+        Spi::run(
+            "CREATE TABLE ranked AS SELECT x::text::RankedThing AS thing FROM generate_series(1, 1000) x;
+             CREATE INDEX ranked_thing ON ranked (thing);
+             ANALYZE ranked;
+             SET LOCAL enable_seqscan = off;",
+        );
+        let query = "SELECT count(*) FROM ranked WHERE thing < '10'";
+        assert!(Spi::explain(query).0.to_string().contains("ranked_thing"));
+        assert_eq!(Spi::get_one::<i64>(query), Some(9));
+    }
+
+    #[pg_test]
+    fn test_is_distinct_from() {
+        let distinct = Spi::get_one::<Vec<bool>>(
+            "SELECT ARRAY[
+                hashedthing_is_distinct_from('1', '1'),
+                hashedthing_is_distinct_from('1', '2'),
+                hashedthing_is_distinct_from('1', NULL),
+                hashedthing_is_distinct_from(NULL, NULL),
+                hashedthing_is_not_distinct_from(NULL, NULL),
+                hashedthing_is_not_distinct_from('1', NULL)
+            ]",
+        );
+        assert_eq!(distinct, Some(vec![false, true, true, false, true, false]));
+    }
+}
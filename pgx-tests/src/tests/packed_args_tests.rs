@@ -0,0 +1,84 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern_packed(immutable)]
+fn packed_report(title: String, width: i32, height: Option<i32>, tags: Vec<String>) -> String {
+    format!(
+        "{} {}x{} {}",
+        title,
+        width,
+        height.unwrap_or(width),
+        tags.join(",")
+    )
+}
+
+#[pg_extern_packed(name = "packed_sum")]
+fn sum_of_packed(args: Vec<i64>, offset: Option<i64>) -> i64 {
+    args.iter().sum::<i64>() + offset.unwrap_or(0)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_packed_args() {
+        let report = Spi::get_one::<String>(
+            r#"SELECT packed_report('{"title": "sales", "width": 80, "tags": ["q1", "q2"]}')"#,
+        );
+        assert_eq!(report, Some("sales 80x80 q1,q2".to_string()));
+
+        let report = Spi::get_one::<String>(
+            r#"SELECT packed_report('{"title": "sales", "width": 80, "height": 24, "tags": []}')"#,
+        );
+        assert_eq!(report, Some("sales 80x24 ".to_string()));
+    }
+
+    #[pg_test]
+    fn test_packed_args_named() {
+        let sum = Spi::get_one::<i64>(r#"SELECT packed_sum('{"args": [1, 2, 3], "offset": 10}')"#);
+        assert_eq!(sum, Some(16));
+        // the Rust function is still callable
+        assert_eq!(super::sum_of_packed(vec![1, 2], None), 3);
+    }
+
+    #[pg_test(error = "packed_report() is missing its argument `width`")]
+    fn test_packed_args_missing() {
+        Spi::get_one::<String>(r#"SELECT packed_report('{"title": "sales", "tags": []}')"#);
+    }
+
+    #[pg_test(
+        error = "packed_report() has no argument `depth`, its arguments are: title, width, height, tags"
+    )]
+    fn test_packed_args_unknown() {
+        Spi::get_one::<String>(
+            r#"SELECT packed_report('{"title": "sales", "width": 80, "tags": [], "depth": 3}')"#,
+        );
+    }
+
+    #[pg_test(
+        error = "packed_report() argument `width` is invalid: invalid type: string \"wide\", expected i32"
+    )]
+    fn test_packed_args_invalid() {
+        Spi::get_one::<String>(
+            r#"SELECT packed_report('{"title": "sales", "width": "wide", "tags": []}')"#,
+        );
+    }
+
+    #[pg_test(error = "packed_sum() takes its arguments as a jsonb object, not `[1,2]`")]
+    fn test_packed_args_not_an_object() {
+        Spi::get_one::<i64>("SELECT packed_sum('[1, 2]')");
+    }
+}
@@ -0,0 +1,110 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    /// Heap tuples start with their `t_xmin`
+    struct HeapItems;
+
+    impl PageFormatter for HeapItems {
+        fn item(_page: &RawPage, item: &PageItem) -> Vec<(&'static str, String)> {
+            let xmin = u32::from_ne_bytes([item.data[0], item.data[1], item.data[2], item.data[3]]);
+            vec![("t_xmin", xmin.to_string())]
+        }
+    }
+
+    page_inspect_srf!(heap_items, HeapItems);
+    page_inspect_srf!(raw_items, RawPageFormatter);
+
+    fn field(function: &str, itemoffset: Option<i32>, field: &str) -> Option<String> {
+        let itemoffset = match itemoffset {
+            Some(itemoffset) => format!("= {}", itemoffset),
+            None => "IS NULL".to_string(),
+        };
+        Spi::get_one(&format!(
+            "SELECT value FROM tests.{}('page_inspect_t', 0) WHERE itemoffset {} AND field = '{}'",
+            function, itemoffset, field
+        ))
+    }
+
+    #[pg_test]
+    fn test_page_inspect_srf() {
+        Spi::run("CREATE TABLE page_inspect_t (id int)");
+        Spi::run("INSERT INTO page_inspect_t VALUES (1), (2), (3)");
+        Spi::run("DELETE FROM page_inspect_t WHERE id = 3");
+        let xmin = Spi::get_one::<String>("SELECT xmin::text FROM page_inspect_t WHERE id = 2");
+
+        let items = Spi::get_one::<i64>(
+            "SELECT count(*) FROM tests.heap_items('page_inspect_t', 0) WHERE field = 'lp_flags'",
+        );
+        assert_eq!(items, Some(3));
+        assert_eq!(field("heap_items", Some(2), "t_xmin"), xmin);
+        assert_eq!(
+            field("heap_items", Some(1), "lp_flags"),
+            Some(pg_sys::LP_NORMAL.to_string())
+        );
+        assert_eq!(
+            field("heap_items", None, "pagesize"),
+            Some(pg_sys::BLCKSZ.to_string())
+        );
+        assert_eq!(
+            field("heap_items", None, "special"),
+            field("heap_items", None, "pagesize")
+        );
+
+        // without a formatter, only what every page has is described
+        assert_eq!(field("raw_items", Some(2), "t_xmin"), None);
+        assert_eq!(
+            field("raw_items", Some(2), "lp_len"),
+            field("heap_items", Some(2), "lp_len")
+        );
+    }
+
+    #[pg_test]
+    fn test_raw_page() {
+        Spi::run("CREATE TABLE raw_page_t AS SELECT generate_series(1, 10) AS id");
+        let relation = PgRelation::open_with_name_and_share_lock("raw_page_t").unwrap();
+        let page = RawPage::read(&relation, 0);
+
+        assert!(!page.is_new());
+        assert_eq!(page.bytes().len(), pg_sys::BLCKSZ as usize);
+        assert_eq!(page.header().layout_version, 4);
+        assert!(page.header().lower < page.header().upper);
+        assert!(page.special().is_empty());
+
+        let items = page.items().collect::<Vec<_>>();
+        assert_eq!(items.len(), 10);
+        assert!(items
+            .iter()
+            .all(|item| item.lp_flags == pg_sys::LP_NORMAL
+                && item.data.len() == item.lp_len as usize));
+
+        let copy = RawPage::from_bytes(page.bytes()).expect("the copy isn't a page");
+        assert_eq!(copy.header(), page.header());
+        assert!(RawPage::from_bytes(&page.bytes()[1..]).is_none());
+    }
+
+    #[pg_test(error = "block number 1 is out of range for relation \"page_inspect_empty\"")]
+    fn test_page_inspect_out_of_range() {
+        Spi::run("CREATE TABLE page_inspect_empty (id int)");
+        Spi::run("INSERT INTO page_inspect_empty VALUES (1)");
+        Spi::run("SELECT * FROM tests.raw_items('page_inspect_empty', 1)");
+    }
+
+    #[pg_test(error = "invalid block number")]
+    fn test_page_inspect_invalid_block() {
+        Spi::run("SELECT * FROM tests.raw_items('pg_class', -1)");
+    }
+}
@@ -0,0 +1,69 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::ffi::CStr;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[inline(never)]
+    fn look_up_the_seventh(values: &[i32]) -> i32 {
+        values[7]
+    }
+
+    #[pg_extern]
+    fn panic_for_a_backtrace() -> i32 {
+        look_up_the_seventh(&[1, 2, 3])
+    }
+
+    /// The server log's `DETAIL` of the error calling `panic_for_a_backtrace()` raises
+    fn detail_log() -> Option<String> {
+        let memory_context = unsafe { pg_sys::CurrentMemoryContext };
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            Spi::get_one::<i32>("SELECT tests.panic_for_a_backtrace()")
+        }));
+        let error = result.expect_err("panic_for_a_backtrace() didn't raise an error");
+        assert!(error.downcast_ref::<pg_sys::JumpContext>().is_some());
+        unsafe {
+            pg_sys::CurrentMemoryContext = memory_context;
+            let edata = pg_sys::CopyErrorData();
+            pg_sys::FlushErrorState();
+
+            let message = CStr::from_ptr((*edata).message).to_string_lossy();
+            assert!(message.contains("the len is 3 but the index is 7"));
+            if (*edata).detail_log.is_null() {
+                None
+            } else {
+                Some(
+                    CStr::from_ptr((*edata).detail_log)
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            }
+        }
+    }
+
+    #[pg_test]
+    fn test_panic_backtraces() {
+        PanicBacktraces::define_guc("pgx_tests.panic_backtraces");
+        let shown = Spi::get_one::<String>("SHOW pgx_tests.panic_backtraces").expect("SHOW failed");
+        assert_eq!(shown, "off");
+        assert_eq!(detail_log(), None);
+
+        Spi::run("SET pgx_tests.panic_backtraces = on");
+        let backtrace = detail_log().expect("no backtrace was logged");
+        assert!(backtrace.starts_with("Rust backtrace:"));
+        assert!(backtrace.contains("look_up_the_seventh"));
+    }
+}
@@ -0,0 +1,79 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::parallel::ParallelWorker;
+use pgx::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct Squares {
+    next: AtomicU64,
+    end: u64,
+}
+unsafe impl PGXSharedMemory for Squares {}
+
+fn claim_squares(squares: &Squares, mut found: impl FnMut(u64) -> bool) {
+    loop {
+        let i = squares.next.fetch_add(1, Ordering::Relaxed);
+        if i >= squares.end || !found(i * i) {
+            break;
+        }
+    }
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn pgx_tests_squares_worker(
+    seg: *mut pg_sys::dsm_segment,
+    toc: *mut pg_sys::shm_toc,
+) {
+    let worker = unsafe { ParallelWorker::<Squares>::attach(seg, toc) };
+    claim_squares(worker.shared(), |square| worker.send(&square));
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{claim_squares, Squares};
+    use pgx::parallel::ParallelQuery;
+    use pgx::*;
+    use std::sync::atomic::AtomicU64;
+
+    fn sum_squares(workers: usize, end: u64) -> (usize, u64) {
+        let squares = Squares {
+            next: AtomicU64::new(0),
+            end,
+        };
+        let mut results = ParallelQuery::new("pgx_tests", "pgx_tests_squares_worker")
+            .set_workers(workers)
+            .launch::<_, u64>(squares);
+        let mut sum = results.by_ref().sum::<u64>();
+        claim_squares(results.shared(), |square| {
+            sum += square;
+            true
+        });
+        (results.workers_launched(), sum)
+    }
+
+    #[pg_test]
+    fn test_parallel_workers() {
+        let (launched, sum) = sum_squares(2, 1000);
+        assert!(launched <= 2);
+        assert_eq!(sum, (0..1000u64).map(|i| i * i).sum::<u64>());
+    }
+
+    #[pg_test]
+    fn test_parallel_without_workers() {
+        let (launched, sum) = sum_squares(0, 10);
+        assert_eq!(launched, 0);
+        assert_eq!(sum, 285);
+    }
+}
@@ -51,4 +51,92 @@ mod tests {
             .expect("failed to get SPI result");
         assert!(result)
     }
+
+    #[pg_extern(immutable)]
+    fn add_integers(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[pg_extern(
+        immutable,
+        body = "SELECT tests.add_integers(a, 1)",
+        requires = [add_integers]
+    )]
+    fn add_one_in_sql(a: i32) -> i32 {
+        add_integers(a, 1)
+    }
+
+    #[pg_extern(
+        language = "plpgsql",
+        body = "BEGIN RETURN tests.add_integers(a, a); END",
+        requires = [add_integers]
+    )]
+    fn double_in_plpgsql(a: i32) -> i32 {
+        add_integers(a, a)
+    }
+
+    #[pg_test]
+    fn test_sql_body() {
+        let result = Spi::get_one::<i32>("SELECT tests.add_one_in_sql(41)")
+            .expect("failed to get SPI result");
+        assert_eq!(result, 42);
+        assert_eq!(add_one_in_sql(41), 42);
+
+        let language = Spi::get_one::<String>(
+            "SELECT lanname::text FROM pg_proc JOIN pg_language ON prolang = pg_language.oid
+              WHERE proname = 'add_one_in_sql'",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(language, "sql");
+    }
+
+    #[pg_test]
+    fn test_plpgsql_body() {
+        let result = Spi::get_one::<i32>("SELECT tests.double_in_plpgsql(21)")
+            .expect("failed to get SPI result");
+        assert_eq!(result, 42);
+    }
+
+    #[pg_extern(immutable, parallel_safe, cost = 50, rows = 3)]
+    fn three_evens() -> impl Iterator<Item = i32> {
+        vec![0, 2, 4].into_iter()
+    }
+
+    #[pg_test]
+    fn test_cost_and_rows() {
+        let (parallel, cost, rows) = Spi::get_three::<String, f32, f32>(
+            "SELECT proparallel::text, procost, prorows FROM pg_proc WHERE proname = 'three_evens'",
+        );
+        assert_eq!(parallel, Some("s".to_string()));
+        assert_eq!(cost, Some(50.0));
+        assert_eq!(rows, Some(3.0));
+    }
+
+    /// A planner support function which never helps, by returning a null pointer
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_extern(immutable)]
+    fn unhelpful_support(_request: Internal) -> Internal {
+        Internal::from(Some(0))
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_extern(immutable, support = unhelpful_support)]
+    fn supported_add_one(a: i32) -> i32 {
+        a + 1
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    fn test_support() {
+        let support = Spi::get_one::<String>(
+            "SELECT support.proname::text FROM pg_proc
+               JOIN pg_proc support ON support.oid = pg_proc.prosupport
+              WHERE pg_proc.proname = 'supported_add_one'",
+        );
+        assert_eq!(support, Some("unhelpful_support".to_string()));
+
+        // the planner calls the support function, which declines to help
+        let result = Spi::get_one::<i32>("SELECT tests.supported_add_one(41)");
+        assert_eq!(result, Some(42));
+    }
 }
@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern(immutable)]
+    fn add_support(request: Internal) -> Internal {
+        planner_support(request, |request| match request {
+            SupportRequest::Simplify(call) => {
+                let args = call.args();
+                match (args.const_value::<i32>(0), args.const_value::<i32>(1)) {
+                    (Some(a), Some(b)) => call.replace_with_const(Some(a + b + 1000)),
+                    (_, Some(0)) => call.replace_with_arg(0),
+                    _ => SupportResponse::declined(),
+                }
+            }
+            _ => SupportResponse::declined(),
+        })
+    }
+
+    #[pg_extern(immutable, support = add_support)]
+    fn supported_add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[pg_extern(immutable)]
+    fn series_support(request: Internal) -> Internal {
+        planner_support(request, |request| match request {
+            SupportRequest::Rows(call) => match call.args().const_value::<i32>(0) {
+                Some(n) => call.rows(n as f64),
+                None => SupportResponse::declined(),
+            },
+            _ => SupportResponse::declined(),
+        })
+    }
+
+    #[pg_extern(immutable, support = series_support)]
+    fn supported_series(n: i32) -> impl std::iter::Iterator<Item = i32> {
+        1..=n
+    }
+
+    #[pg_extern(immutable)]
+    fn rare_support(request: Internal) -> Internal {
+        planner_support(request, |request| match request {
+            SupportRequest::Selectivity(call) if !call.is_join() => call.selectivity(0.01),
+            _ => SupportResponse::declined(),
+        })
+    }
+
+    #[pg_extern(immutable, support = rare_support)]
+    fn is_rare(n: i32) -> bool {
+        n % 100 == 0
+    }
+
+    fn explain(query: &str) -> serde_json::Value {
+        let plan = Spi::get_one::<Json>(&format!("EXPLAIN (VERBOSE, FORMAT JSON) {}", query))
+            .expect("EXPLAIN returned nothing");
+        plan.0[0]["Plan"].clone()
+    }
+
+    #[pg_test]
+    fn test_simplify() {
+        // folded by the support function, which is how we can tell it's not the function itself
+        let result = Spi::get_one::<i32>("SELECT tests.supported_add(1, 2)");
+        assert_eq!(result, Some(1003));
+
+        let plan = explain("SELECT tests.supported_add(x, 0) FROM generate_series(1, 3) x");
+        assert_eq!(plan["Output"][0], "x");
+
+        // the support function declines, so the call is left as it is
+        let plan = explain("SELECT tests.supported_add(x, 1) FROM generate_series(1, 3) x");
+        let output = plan["Output"][0].as_str().unwrap();
+        assert!(output.ends_with("supported_add(x, 1)"));
+    }
+
+    #[pg_test]
+    fn test_rows() {
+        let plan = explain("SELECT * FROM tests.supported_series(42)");
+        assert_eq!(plan["Plan Rows"], 42);
+
+        // without a constant, the support function declines, and the function's ROWS is used
+        let plan = explain("SELECT * FROM tests.supported_series((random() * 10)::int)");
+        assert_eq!(plan["Plan Rows"], 1000);
+    }
+
+    #[pg_test]
+    fn test_selectivity() {
+        let plan = explain("SELECT * FROM generate_series(1, 1000) x WHERE tests.is_rare(x)");
+        assert_eq!(plan["Plan Rows"], 10);
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::plugins::{PluginCall, PluginRegistrar};
+use pgx::*;
+
+// pgx_tests is its own plugin, so it can load itself
+pgx::declare_plugin!(register_plugin_functions);
+
+fn register_plugin_functions(registrar: &mut PluginRegistrar) {
+    registrar.function("scale", plugin_scale);
+    registrar.function("greet", plugin_greet);
+}
+
+#[pg_guard]
+unsafe extern "C" fn plugin_scale(call: &mut PluginCall) -> pg_sys::Datum {
+    let value = call.arg::<f64>(0);
+    let factor = call.arg::<f64>(1).unwrap_or(1.0);
+    call.result(value.map(|value| value * factor))
+}
+
+#[pg_guard]
+unsafe extern "C" fn plugin_greet(call: &mut PluginCall) -> pg_sys::Datum {
+    let name = call.arg::<&str>(0).expect("name is NULL");
+    call.result(Some(format!("Hello, {}", name)))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::plugins::{Plugin, PluginError};
+    use pgx::*;
+    use std::ffi::CStr;
+
+    /// The path of the library these tests are in
+    fn own_library_path() -> String {
+        unsafe {
+            let mut info = std::mem::zeroed::<libc::Dl_info>();
+            libc::dladdr(own_library_path as *const libc::c_void, &mut info);
+            CStr::from_ptr(info.dli_fname)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    #[pg_test]
+    fn test_plugin_functions() {
+        let plugin = Plugin::load(own_library_path()).unwrap();
+        assert!(plugin.has_function("scale"));
+        assert!(!plugin.has_function("missing"));
+
+        let scaled = plugin
+            .call::<f64>("scale", &[2.5f64.into_datum(), 4.0f64.into_datum()])
+            .unwrap();
+        assert_eq!(scaled, Some(10.0));
+
+        let scaled = plugin
+            .call::<f64>("scale", &[None, 4.0f64.into_datum()])
+            .unwrap();
+        assert_eq!(scaled, None);
+
+        let greeting = plugin
+            .call::<String>("greet", &["Nami".into_datum()])
+            .unwrap();
+        assert_eq!(greeting.as_deref(), Some("Hello, Nami"));
+    }
+
+    #[pg_test]
+    fn test_missing_plugin_function() {
+        let plugin = Plugin::load(own_library_path()).unwrap();
+        match plugin.call::<f64>("missing", &[]) {
+            Err(PluginError::NoSuchFunction(name)) => assert_eq!(name, "missing"),
+            _ => panic!("called a missing function"),
+        }
+    }
+
+    #[pg_test(error = "name is NULL")]
+    fn test_plugin_function_error() {
+        let plugin = Plugin::load(own_library_path()).unwrap();
+        plugin.call::<String>("greet", &[None]).unwrap();
+    }
+
+    #[pg_test]
+    fn test_not_a_plugin() {
+        match Plugin::load("$libdir/plpgsql") {
+            Err(PluginError::NotAPlugin { .. }) => (),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("plpgsql loaded as a plugin"),
+        }
+        assert!(matches!(
+            Plugin::load("$libdir/no_such_plugin"),
+            Err(PluginError::Load(_))
+        ));
+    }
+}
@@ -61,6 +61,109 @@ impl InOutFuncs for CustomTextFormatSerializedType {
     }
 }
 
+#[derive(Serialize, Deserialize, PostgresType)]
+#[inoutfuncs]
+#[sendrecvfuncs]
+pub struct BinaryType {
+    a: i32,
+}
+
+impl InOutFuncs for BinaryType {
+    fn input(input: &CStr) -> Self {
+        assert_eq!(
+            IoContext::current().map(|context| (context.format(), context.direction())),
+            Some((IoFormat::Text, IoDirection::Input))
+        );
+        BinaryType {
+            a: i32::from_str(input.to_str().unwrap()).expect("a is not a valid i32"),
+        }
+    }
+
+    fn output(&self, buffer: &mut StringInfo) {
+        assert_eq!(
+            IoContext::current().map(|context| (context.format(), context.direction())),
+            Some((IoFormat::Text, IoDirection::Output))
+        );
+        buffer.push_str(&self.a.to_string())
+    }
+}
+
+impl SendRecvFuncs for BinaryType {
+    fn recv(input: &[u8]) -> Self {
+        assert_eq!(
+            IoContext::current().map(|context| (context.format(), context.direction())),
+            Some((IoFormat::Binary, IoDirection::Input))
+        );
+        BinaryType {
+            a: i32::from_be_bytes(input.try_into().expect("a is not 4 bytes")),
+        }
+    }
+
+    fn send(&self) -> Vec<u8> {
+        assert_eq!(
+            IoContext::current().map(|context| (context.format(), context.direction())),
+            Some((IoFormat::Binary, IoDirection::Output))
+        );
+        self.a.to_be_bytes().to_vec()
+    }
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+#[sendrecvfuncs(cbor)]
+pub struct CborBinaryType {
+    a: i32,
+    b: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PostgresType)]
+#[repr(transparent)]
+#[pgx(passedbyvalue)]
+#[inoutfuncs]
+pub struct ByValueId(i64);
+
+impl InOutFuncs for ByValueId {
+    fn input(input: &CStr) -> Self {
+        let id = input.to_str().unwrap().trim_start_matches("id:");
+        ByValueId(i64::from_str(id).expect("not a valid id"))
+    }
+
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&format!("id:{}", self.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PostgresType)]
+#[repr(C)]
+#[pgx(fixed_size)]
+#[inoutfuncs]
+pub struct FixedSizePair {
+    a: i64,
+    b: i64,
+}
+
+impl InOutFuncs for FixedSizePair {
+    fn input(input: &CStr) -> Self {
+        let (a, b) = input
+            .to_str()
+            .unwrap()
+            .split_once(',')
+            .expect("expected a,b");
+        FixedSizePair {
+            a: i64::from_str(a).expect("a is not a valid i64"),
+            b: i64::from_str(b).expect("b is not a valid i64"),
+        }
+    }
+
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&format!("{},{}", self.a, self.b))
+    }
+}
+
+#[pg_extern]
+fn next_id(id: ByValueId) -> ByValueId {
+    ByValueId(id.0 + 1)
+}
+
 #[derive(Serialize, Deserialize, PostgresType)]
 pub struct JsonType {
     a: f32,
@@ -68,6 +171,70 @@ pub struct JsonType {
     c: i64,
 }
 
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(serialize = "json")]
+pub struct JsonStoredType {
+    a: i32,
+    b: String,
+}
+
+/// The layout of `VersionedType` before it had a version
+#[derive(Serialize, Deserialize)]
+pub struct UnversionedType {
+    a: i32,
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(version = 2, migrate = migrate_versioned_type)]
+pub struct VersionedType {
+    a: i32,
+    b: i32,
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(storage = "external")]
+pub struct ExternalStorageType {
+    samples: Vec<i64>,
+}
+
+/// JSON, as far as pgx knows a format whose values might start with any byte
+pub struct OpaqueJson;
+
+impl SerializationFormat for OpaqueJson {
+    fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo) {
+        JsonFormat::encode(value, buffer)
+    }
+
+    fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T {
+        JsonFormat::decode(bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(serialize = OpaqueJson)]
+pub struct OpaqueStoredType {
+    a: i32,
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(serialize = OpaqueJson, version = 1, migrate = migrate_opaque_versioned_type)]
+pub struct OpaqueVersionedType {
+    a: i32,
+    b: i32,
+}
+
+fn migrate_opaque_versioned_type(version: u16, bytes: &[u8]) -> OpaqueVersionedType {
+    assert_eq!(version, 0);
+    let old: OpaqueStoredType = JsonFormat::decode(bytes);
+    OpaqueVersionedType { a: old.a, b: -1 }
+}
+
+fn migrate_versioned_type(version: u16, bytes: &[u8]) -> VersionedType {
+    assert_eq!(version, 0);
+    let old: UnversionedType = Cbor::decode(bytes);
+    VersionedType { a: old.a, b: -1 }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -75,7 +242,9 @@ mod tests {
     use crate as pgx_tests;
 
     use crate::tests::postgres_type_tests::{
-        CustomTextFormatSerializedType, JsonType, VarlenaType,
+        binarytype_recv, cborbinarytype_recv, BinaryType, ByValueId, CborBinaryType,
+        CustomTextFormatSerializedType, FixedSizePair, JsonStoredType, JsonType, OpaqueStoredType,
+        OpaqueVersionedType, UnversionedType, VarlenaType, VersionedType,
     };
     use pgx::*;
 
@@ -107,4 +276,166 @@ mod tests {
         assert_eq!(result.b, 2.0);
         assert_eq!(result.c, 3);
     }
+
+    #[pg_test]
+    fn test_serialize_json() {
+        let datum = JsonStoredType {
+            a: 1,
+            b: "two".into(),
+        }
+        .into_datum()
+        .unwrap();
+        let stored = unsafe { <&[u8]>::from_datum(datum, false, pg_sys::BYTEAOID) }.unwrap();
+        assert_eq!(stored, br#"{"a":1,"b":"two"}"#);
+
+        let result =
+            Spi::get_one::<JsonStoredType>(r#"SELECT '{"a": 3, "b": "four"}'::JsonStoredType"#)
+                .expect("SPI returned NULL");
+        assert_eq!(result.a, 3);
+        assert_eq!(result.b, "four");
+    }
+
+    #[pg_test]
+    fn test_versioned_type() {
+        let datum = VersionedType { a: 1, b: 2 }.into_datum().unwrap();
+        let stored = unsafe { <&[u8]>::from_datum(datum, false, pg_sys::BYTEAOID) }.unwrap();
+        assert_eq!(&stored[..3], &[0xFF, 2, 0]);
+
+        let result =
+            unsafe { VersionedType::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert_eq!((result.a, result.b), (1, 2));
+    }
+
+    #[pg_test]
+    fn test_versioned_type_migrate() {
+        let mut buffer = StringInfo::new();
+        buffer.push_bytes(&[0u8; pg_sys::VARHDRSZ]);
+        Cbor::encode(&UnversionedType { a: 7 }, &mut buffer);
+        let size = buffer.len();
+        let varlena = buffer.into_char_ptr() as *mut pg_sys::varlena;
+        unsafe { set_varsize(varlena, size as i32) };
+
+        let result = unsafe {
+            VersionedType::from_datum(varlena as pg_sys::Datum, false, pg_sys::InvalidOid)
+        }
+        .unwrap();
+        assert_eq!((result.a, result.b), (7, -1));
+    }
+
+    #[pg_test]
+    fn test_unversioned_type_in_opaque_format() {
+        let datum = OpaqueStoredType { a: 5 }.into_datum().unwrap();
+        let stored = unsafe { <&[u8]>::from_datum(datum, false, pg_sys::BYTEAOID) }.unwrap();
+        assert_eq!(&stored[..3], &[0xFF, 0, 0]);
+        assert_eq!(&stored[3..], br#"{"a":5}"#);
+
+        // stored before `OpaqueVersionedType` had a version, but after version 0
+        let result =
+            unsafe { OpaqueVersionedType::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        assert_eq!((result.a, result.b), (5, -1));
+    }
+
+    #[pg_test]
+    fn test_io_context_text() {
+        assert_eq!(IoContext::current(), None);
+        let result = Spi::get_one::<String>("SELECT '42'::BinaryType::text");
+        assert_eq!(result, Some("42".to_string()));
+        assert_eq!(IoContext::current(), None);
+    }
+
+    #[pg_test]
+    fn test_send_recv() {
+        let sent = Spi::get_one::<Vec<u8>>("SELECT binarytype_send('42'::BinaryType)");
+        assert_eq!(sent, Some(vec![0, 0, 0, 42]));
+
+        let mut message = StringInfo::new();
+        message.push_bytes(&[0, 0, 1, 0]);
+        let message = message.into_pg();
+        let received = binarytype_recv(Internal::from(Some(message as pg_sys::Datum)));
+        assert_eq!(received.a, 256);
+        // the whole message was read
+        unsafe { assert_eq!((*message).cursor, (*message).len) };
+        assert_eq!(IoContext::current(), None);
+    }
+
+    #[pg_test]
+    fn test_send_recv_registered() {
+        let functions = Spi::get_one::<String>(
+            "SELECT typsend::text || ',' || typreceive::text FROM pg_type WHERE typname = 'binarytype'",
+        );
+        assert_eq!(
+            functions,
+            Some("binarytype_send,binarytype_recv".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_cbor_send_recv() {
+        let sent = Spi::get_one::<Vec<u8>>(
+            r#"SELECT cborbinarytype_send('{"a": 7, "b": "seven"}'::CborBinaryType)"#,
+        )
+        .expect("SPI returned NULL");
+        let decoded: CborBinaryType = Cbor::decode(&sent);
+        assert_eq!((decoded.a, decoded.b.as_str()), (7, "seven"));
+
+        let mut message = StringInfo::new();
+        message.push_bytes(&sent);
+        let message = message.into_pg();
+        let received = cborbinarytype_recv(Internal::from(Some(message as pg_sys::Datum)));
+        assert_eq!((received.a, received.b.as_str()), (7, "seven"));
+
+        let functions = Spi::get_one::<String>(
+            "SELECT typsend::text || ',' || typreceive::text FROM pg_type WHERE typname = 'cborbinarytype'",
+        );
+        assert_eq!(
+            functions,
+            Some("cborbinarytype_send,cborbinarytype_recv".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_passed_by_value() {
+        let id = Spi::get_one::<ByValueId>("SELECT next_id('id:41'::ByValueId)");
+        assert_eq!(id, Some(ByValueId(42)));
+        assert_eq!(ByValueId(7).into_datum(), Some(7));
+
+        let layout = Spi::get_one::<String>(
+            "SELECT typlen || ',' || typbyval || ',' || typalign || ',' || typstorage FROM pg_type WHERE typname = 'byvalueid'",
+        );
+        assert_eq!(layout, Some("8,true,d,p".to_string()));
+    }
+
+    #[pg_test]
+    fn test_fixed_size() {
+        Spi::run(
+            "CREATE TABLE fixed_size_pairs (pair FixedSizePair);
+             INSERT INTO fixed_size_pairs VALUES ('1,2'), ('-3,4');",
+        );
+        let pairs =
+            Spi::get_one::<Vec<FixedSizePair>>("SELECT array_agg(pair) FROM fixed_size_pairs");
+        assert_eq!(
+            pairs,
+            Some(vec![
+                FixedSizePair { a: 1, b: 2 },
+                FixedSizePair { a: -3, b: 4 }
+            ])
+        );
+
+        let layout = Spi::get_one::<String>(
+            "SELECT typlen || ',' || typbyval || ',' || typalign FROM pg_type WHERE typname = 'fixedsizepair'",
+        );
+        assert_eq!(layout, Some("16,false,d".to_string()));
+    }
+
+    #[pg_test]
+    fn test_storage() {
+        let storage = Spi::get_one::<String>(
+            "SELECT typstorage::text FROM pg_type WHERE typname = 'externalstoragetype'",
+        );
+        assert_eq!(storage, Some("e".to_string()));
+        let storage = Spi::get_one::<String>(
+            "SELECT typstorage::text FROM pg_type WHERE typname = 'jsontype'",
+        );
+        assert_eq!(storage, Some("x".to_string()));
+    }
 }
@@ -0,0 +1,46 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern(primary_only)]
+fn primary_only_increment(i: i32) -> i32 {
+    i + 1
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_not_in_recovery() {
+        assert_eq!(
+            Some(recovery::in_recovery()),
+            Spi::get_one::<bool>("SELECT pg_is_in_recovery()")
+        );
+        assert!(!recovery::is_hot_standby());
+    }
+
+    #[pg_test]
+    fn test_prevent_during_recovery_on_primary() {
+        recovery::prevent_during_recovery("test_prevent_during_recovery_on_primary()");
+    }
+
+    #[pg_test]
+    fn test_primary_only_function_on_primary() {
+        assert_eq!(
+            Some(2),
+            Spi::get_one::<i32>("SELECT primary_only_increment(1)")
+        );
+    }
+}
@@ -0,0 +1,100 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    fn fail(code: PgSqlErrorCode, message: &str) {
+        ereport(
+            PgLogLevel::ERROR,
+            code,
+            message,
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+
+    fn quick_retry(max_attempts: u32) -> PgRetry {
+        PgRetry::new()
+            .set_max_attempts(max_attempts)
+            .set_backoff(Duration::from_millis(1), Duration::from_millis(2))
+    }
+
+    #[pg_test]
+    fn test_retry_until_success() {
+        Spi::run("CREATE TABLE retry_attempts (attempt int)");
+
+        let mut attempts = 0;
+        let result = quick_retry(3).run(|| {
+            attempts += 1;
+            Spi::run(&format!("INSERT INTO retry_attempts VALUES ({})", attempts));
+            match attempts {
+                1 => fail(
+                    PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE,
+                    "conflict",
+                ),
+                2 => fail(PgSqlErrorCode::ERRCODE_T_R_DEADLOCK_DETECTED, "deadlock"),
+                _ => {}
+            }
+            attempts * 10
+        });
+        assert_eq!(result, 30);
+        assert_eq!(attempts, 3);
+
+        // the failed attempts' subtransactions were rolled back
+        let rows =
+            Spi::get_one::<String>("SELECT string_agg(attempt::text, ',') FROM retry_attempts");
+        assert_eq!(rows, Some("3".to_string()));
+    }
+
+    #[pg_test]
+    fn test_retry_first_attempt() {
+        let mut attempts = 0;
+        let result = PgRetry::new().run(|| {
+            attempts += 1;
+            Spi::get_one::<i32>("SELECT 42")
+        });
+        assert_eq!(result, Some(42));
+        assert_eq!(attempts, 1);
+    }
+
+    #[pg_test(error = "still conflicting")]
+    fn test_retry_gives_up() {
+        quick_retry(2).run(|| {
+            fail(
+                PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE,
+                "still conflicting",
+            )
+        });
+    }
+
+    #[pg_test(error = "division by zero")]
+    fn test_retry_other_errors() {
+        let mut attempts = 0;
+        let _ = pg_try(std::panic::AssertUnwindSafe(|| {
+            quick_retry(5).run(|| {
+                attempts += 1;
+                Spi::get_one::<i32>("SELECT 1 / 0")
+            })
+        }))
+        .unwrap_or_rethrow(|| assert_eq!(attempts, 1));
+    }
+
+    #[pg_test(error = "not a database error")]
+    fn test_retry_panics() {
+        quick_retry(5).run(|| panic!("not a database error"));
+    }
+}
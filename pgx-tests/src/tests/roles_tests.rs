@@ -0,0 +1,67 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn reset_cache() -> bool {
+        require_role!("roles_test_admin");
+        true
+    }
+
+    fn create_roles() {
+        Spi::run("CREATE ROLE roles_test_admin NOLOGIN");
+        Spi::run("CREATE ROLE roles_test_user NOLOGIN");
+    }
+
+    #[pg_test]
+    fn test_superuser_has_every_role() {
+        create_roles();
+        assert!(is_superuser());
+        assert!(has_role("roles_test_admin"));
+        assert!(reset_cache());
+        require_superuser!();
+    }
+
+    #[pg_test]
+    fn test_role_member() {
+        create_roles();
+        Spi::run("GRANT roles_test_admin TO roles_test_user");
+        Spi::run("SET ROLE roles_test_user");
+        assert!(!is_superuser());
+        assert!(has_role("roles_test_admin"));
+        assert!(reset_cache());
+    }
+
+    #[pg_test(error = "permission denied")]
+    fn test_require_role_denied() {
+        create_roles();
+        Spi::run("SET ROLE roles_test_user");
+        assert!(!has_role("roles_test_admin"));
+        reset_cache();
+    }
+
+    #[pg_test(error = "role \"roles_test_admin\" does not exist")]
+    fn test_require_missing_role() {
+        assert_eq!(role_oid("roles_test_admin"), None);
+        reset_cache();
+    }
+
+    #[pg_test(error = "permission denied")]
+    fn test_require_superuser_denied() {
+        create_roles();
+        Spi::run("SET ROLE roles_test_user");
+        require_superuser!();
+    }
+}
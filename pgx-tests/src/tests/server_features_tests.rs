@@ -0,0 +1,72 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[pg_test]
+    fn test_server_version_num() {
+        let version_num = Spi::get_one::<i32>("SELECT current_setting('server_version_num')::int")
+            .expect("SPI result was null");
+        assert_eq!(server_version_num(), version_num);
+        assert_eq!(
+            server_version_num() / 10000,
+            pg_sys::PG_VERSION_NUM as i32 / 10000
+        );
+    }
+
+    #[pg_test]
+    fn test_is_available() {
+        assert!(ServerFeature::Version(100000).is_available());
+        assert!(ServerFeature::Version(server_version_num()).is_available());
+        assert!(!ServerFeature::Version(server_version_num() + 1).is_available());
+        assert_eq!(
+            ServerFeature::Multiranges.is_available(),
+            cfg!(feature = "pg14")
+        );
+    }
+
+    #[pg_test]
+    fn test_display() {
+        assert_eq!(
+            ServerFeature::Multiranges.to_string(),
+            "Postgres 14 or newer"
+        );
+        assert_eq!(
+            ServerFeature::Version(140002).to_string(),
+            "Postgres 14.2 or newer"
+        );
+    }
+
+    #[pg_test]
+    fn test_require_feature_available() {
+        require_feature!(Version(100000), "Postgres 10 is needed");
+    }
+
+    #[pg_test(error = "the future is needed (requires Postgres 99 or newer)")]
+    fn test_require_feature_unavailable() {
+        require_feature!(Version(990000), "the future is needed");
+    }
+
+    #[pg_test]
+    fn test_if_feature() {
+        let chosen = if_feature!(Version(990000), { "future" } else { "present" });
+        assert_eq!(chosen, "present");
+
+        let mut ran = false;
+        if_feature!(Version(100000), {
+            ran = true;
+        });
+        assert!(ran);
+    }
+}
@@ -0,0 +1,49 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_backend_pid() {
+        assert_eq!(
+            Some(session::backend_pid()),
+            Spi::get_one::<i32>("SELECT pg_backend_pid()")
+        );
+    }
+
+    #[pg_test]
+    fn test_query_string() {
+        let query = session::query_string().expect("no query string");
+        assert!(query.contains("test_query_string"), "{}", query);
+    }
+
+    #[pg_test]
+    fn test_application_name() {
+        Spi::run("SET application_name = ''");
+        assert_eq!(session::application_name(), None);
+        Spi::run("SET application_name = 'auditor'");
+        assert_eq!(session::application_name(), Some("auditor".to_string()));
+    }
+
+    #[pg_test]
+    fn test_client_addr() {
+        let addr = Spi::get_one::<String>("SELECT host(inet_client_addr())");
+        let port = Spi::get_one::<i32>("SELECT inet_client_port()");
+        assert_eq!(
+            session::client_addr().map(|addr| (addr.ip().to_string(), addr.port() as i32)),
+            addr.zip(port)
+        );
+    }
+}
@@ -0,0 +1,46 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern(log_slow)]
+fn slow_word_count(document: &str, _note: Option<&str>) -> i64 {
+    document.split_whitespace().count() as i64
+}
+
+#[pg_extern(log_slow)]
+fn slow_words<'a>(document: &'a str) -> impl std::iter::Iterator<Item = &'a str> {
+    document.split_whitespace()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[pg_test]
+    fn test_log_slow() {
+        SlowCalls::define_guc("pgx_tests.slow_ms");
+        let shown = Spi::get_one::<String>("SHOW pgx_tests.slow_ms").expect("SHOW failed");
+        assert_eq!(shown, "-1");
+
+        // every call is logged, with the long document cut short and the NULL shown as such
+        Spi::run("SET pgx_tests.slow_ms = 0");
+        let count =
+            Spi::get_one::<i64>("SELECT slow_word_count(repeat('it''s a word ', 100), NULL)")
+                .expect("slow_word_count() returned null");
+        assert_eq!(count, 300);
+
+        let words = Spi::get_one::<i64>("SELECT count(*) FROM slow_words('one two three')")
+            .expect("count() returned null");
+        assert_eq!(words, 3);
+    }
+}
@@ -35,6 +35,97 @@ mod tests {
         });
     }
 
+    #[pg_test]
+    fn test_spi_depth() {
+        assert!(!Spi::is_connected());
+        assert_eq!(Spi::depth(), 0);
+        assert!(Spi::check_connection().is_err());
+
+        Spi::execute(|_| {
+            assert!(Spi::is_connected());
+            assert_eq!(Spi::depth(), 1);
+            assert!(Spi::check_connection().is_ok());
+
+            Spi::execute(|_| {
+                assert_eq!(Spi::depth(), 2);
+            });
+
+            assert_eq!(Spi::depth(), 1);
+        });
+
+        assert_eq!(Spi::depth(), 0);
+    }
+
+    #[pg_test]
+    fn test_spi_read_only() {
+        let rc = Spi::connect_read_only(|client| {
+            Ok(client
+                .select("SELECT 42", None, None)
+                .first()
+                .get_datum::<i32>(1))
+        });
+
+        assert_eq!(42, rc.expect("SPI failed to return proper value"))
+    }
+
+    #[pg_test(error = "CREATE TABLE is not allowed in a non-volatile function")]
+    fn test_spi_read_only_rejects_writes() {
+        Spi::execute_read_only(|client| {
+            client.select("CREATE TABLE tests.spi_read_only (id int)", None, None);
+        });
+    }
+
+    #[pg_extern(stable, spi_read_only)]
+    fn spi_insert_stable() -> bool {
+        Spi::run("INSERT INTO tests.spi_writes VALUES (1)");
+        Spi::in_read_only_function()
+    }
+
+    #[pg_extern(stable)]
+    fn spi_insert_stable_read_write() -> bool {
+        Spi::run("INSERT INTO tests.spi_writes VALUES (3)");
+        Spi::in_read_only_function()
+    }
+
+    #[pg_extern(volatile)]
+    fn spi_insert_volatile() -> bool {
+        Spi::run("INSERT INTO tests.spi_writes VALUES (2)");
+        Spi::in_read_only_function()
+    }
+
+    #[pg_test(error = "INSERT is not allowed in a non-volatile function")]
+    fn test_spi_read_only_in_stable_function() {
+        Spi::run("CREATE TABLE tests.spi_writes (id int)");
+        Spi::get_one::<bool>("SELECT tests.spi_insert_stable()");
+    }
+
+    #[pg_test]
+    fn test_spi_read_write_without_spi_read_only() {
+        Spi::run("CREATE TABLE tests.spi_writes (id int)");
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT tests.spi_insert_stable_read_write()"),
+            Some(false)
+        );
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.spi_writes WHERE id = 3"),
+            Some(1)
+        );
+    }
+
+    #[pg_test]
+    fn test_spi_read_write_in_volatile_function() {
+        Spi::run("CREATE TABLE tests.spi_writes (id int)");
+        assert!(!Spi::in_read_only_function());
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT tests.spi_insert_volatile()"),
+            Some(false)
+        );
+        assert_eq!(
+            Spi::get_one::<i32>("SELECT id FROM tests.spi_writes"),
+            Some(2)
+        );
+    }
+
     #[pg_test]
     fn test_spi_returns_primitive() {
         let rc = Spi::connect(|client| {
@@ -168,4 +259,123 @@ mod tests {
         );
         assert_eq!(result, Some(1));
     }
+
+    #[pg_test]
+    fn test_query_bind() {
+        let sum = Spi::query("SELECT $1 + $2::int8")
+            .bind(40i64)
+            .bind(2i32)
+            .get_one::<i64>();
+        assert_eq!(sum, Some(42));
+    }
+
+    #[pg_test]
+    fn test_query_bind_null() {
+        let is_null = Spi::query("SELECT $1 IS NULL AND pg_typeof($1) = 'integer'::regtype")
+            .bind(Option::<i32>::None)
+            .get_one::<bool>();
+        assert_eq!(is_null, Some(true));
+    }
+
+    #[pg_test]
+    fn test_query_map() {
+        Spi::run("CREATE TABLE tests.dogs (name text, age int)");
+        for (name, age) in [("Nami", Some(3)), ("Brandy", Some(12)), ("Sally", None)] {
+            Spi::query("INSERT INTO tests.dogs VALUES ($1, $2)")
+                .bind(name)
+                .bind(age)
+                .run();
+        }
+
+        let dogs = Spi::query("SELECT name, age FROM tests.dogs WHERE name <> $1 ORDER BY name")
+            .bind("Nami")
+            .read_only()
+            .map(|row| (row["name"].value::<String>(), row["age"].value::<i32>()));
+        assert_eq!(
+            dogs,
+            vec![
+                (Some("Brandy".to_string()), Some(12)),
+                (Some("Sally".to_string()), None)
+            ]
+        );
+
+        let limited = Spi::query("SELECT name FROM tests.dogs")
+            .limit(2)
+            .map(|_| ());
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[cfg(feature = "pg14")]
+    #[pg_test]
+    fn test_query_cursor_options() {
+        use pgx::spi::CursorOptions;
+
+        Spi::run("CREATE TABLE tests.plans (id int)");
+        let explain = |options| {
+            Spi::query("EXPLAIN (COSTS OFF) SELECT * FROM tests.plans WHERE id = $1")
+                .bind(7)
+                .cursor_options(options)
+                .map(|row| row[1].value::<String>().unwrap())
+                .join("\n")
+        };
+        assert!(explain(CursorOptions::GENERIC_PLAN).contains("(id = $1)"));
+        assert!(explain(CursorOptions::CUSTOM_PLAN).contains("(id = 7)"));
+
+        let count = Spi::query("SELECT count(*) FROM tests.plans WHERE id <> $1")
+            .bind(7)
+            .cursor_options(CursorOptions::GENERIC_PLAN | CursorOptions::PARALLEL_OK)
+            .read_only()
+            .get_one::<i64>();
+        assert_eq!(count, Some(0));
+    }
+
+    #[cfg(feature = "pg14")]
+    #[pg_test]
+    fn test_query_must_return_tuples() {
+        Spi::run("CREATE TABLE tests.returned (id int)");
+        let id = Spi::query("INSERT INTO tests.returned VALUES ($1) RETURNING id")
+            .bind(3)
+            .must_return_tuples()
+            .get_one::<i32>();
+        assert_eq!(id, Some(3));
+    }
+
+    #[cfg(feature = "pg14")]
+    #[pg_test(error = "INSERT query does not return tuples")]
+    fn test_query_must_return_tuples_error() {
+        Spi::run("CREATE TABLE tests.returned (id int)");
+        Spi::query("INSERT INTO tests.returned VALUES ($1)")
+            .bind(3)
+            .must_return_tuples()
+            .run();
+    }
+
+    #[pg_test]
+    fn test_spi_get_borrowed() {
+        let lengths = Spi::connect(|client| {
+            let table = client
+                .select(
+                    "SELECT repeat('x', 100000), decode(repeat('ff', 100000), 'hex'), NULL::text",
+                    None,
+                    None,
+                )
+                .first();
+            let text = table.get_borrowed::<&str>(1).expect("text was null");
+            let bytes = table.get_borrowed::<&[u8]>(2).expect("bytea was null");
+            assert_eq!(table.get_one_borrowed::<&str>(), Some(text));
+            assert!(text.bytes().all(|b| b == b'x'));
+            assert!(bytes.iter().all(|b| *b == 0xff));
+            assert_eq!(table.get_borrowed::<&str>(3), None);
+            assert_eq!(table.get_borrowed::<&str>(4), None);
+            Ok(Some(text.len() as i64 + bytes.len() as i64))
+        });
+        assert_eq!(lengths, Some(200000));
+    }
+
+    #[pg_test(
+        error = "parameter $1 is a `(core::option::Option<i32>, core::option::Option<i32>)`, which has no SQL type"
+    )]
+    fn test_query_bind_untyped() {
+        Spi::query("SELECT $1").bind((Some(1), Some(2))).run();
+    }
 }
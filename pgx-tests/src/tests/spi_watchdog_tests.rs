@@ -0,0 +1,140 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::bgworkers::*;
+use pgx::*;
+use std::time::Duration;
+
+extension_sql!(
+    r#"
+CREATE TABLE spi_watchdog_results (
+    id serial PRIMARY KEY,
+    worker text NOT NULL,
+    result text NOT NULL
+);
+"#,
+    name = "create_spi_watchdog_results"
+);
+
+fn insert_result(worker: &str, result: &str) {
+    Spi::run(&format!(
+        "INSERT INTO spi_watchdog_results (worker, result) VALUES ('{}', '{}')",
+        worker,
+        result.replace('\'', "''")
+    ));
+}
+
+/// Every worker connects to the database of the test which started it, given as its `extra`
+fn connect_worker() {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(Some(BackgroundWorker::get_extra()), None);
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn spi_watchdog_timeout_worker_main(_arg: pg_sys::Datum) {
+    connect_worker();
+    let watchdog = SpiWatchdog::new(Duration::from_millis(100));
+    let result = match watchdog.run(|| Spi::run("SELECT pg_sleep(10)")) {
+        Ok(()) => String::from("finished"),
+        Err(e @ SpiWatchdogError::Timeout { elapsed, .. }) => format!(
+            "timeout {}, within a second: {}",
+            e.sqlstate(),
+            elapsed < Duration::from_secs(1)
+        ),
+        Err(e) => format!("{:?}", e),
+    };
+    BackgroundWorker::transaction(move || insert_result("timeout", &result));
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn spi_watchdog_error_worker_main(_arg: pg_sys::Datum) {
+    connect_worker();
+    let watchdog = SpiWatchdog::new(Duration::from_secs(60));
+    let result = match watchdog.run(|| Spi::run("SELECT 1 / 0")) {
+        Ok(()) => String::from("finished"),
+        Err(e) => format!("{} {}", e.sqlstate(), e),
+    };
+    BackgroundWorker::transaction(move || insert_result("error", &result));
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::os::raw::c_char;
+
+    fn set_name(dest: &mut [c_char], name: &str) {
+        for (dest, src) in dest.iter_mut().zip(name.as_bytes()) {
+            *dest = *src as c_char;
+        }
+    }
+
+    /// Run `function` in a dynamic background worker, and wait for it to exit
+    fn run_worker(function: &str) {
+        let database =
+            Spi::get_one::<String>("SELECT current_database()::text").expect("no database");
+        let mut worker = pg_sys::BackgroundWorker::default();
+        set_name(&mut worker.bgw_name, function);
+        set_name(&mut worker.bgw_library_name, "pgx_tests");
+        set_name(&mut worker.bgw_function_name, function);
+        set_name(&mut worker.bgw_extra, &database);
+        worker.bgw_flags =
+            (pg_sys::BGWORKER_SHMEM_ACCESS | pg_sys::BGWORKER_BACKEND_DATABASE_CONNECTION) as i32;
+        worker.bgw_start_time = pg_sys::BgWorkerStartTime_BgWorkerStart_RecoveryFinished;
+        worker.bgw_restart_time = pg_sys::BGW_NEVER_RESTART;
+        worker.bgw_notify_pid = unsafe { pg_sys::MyProcPid };
+
+        let mut handle = std::ptr::null_mut();
+        unsafe {
+            assert!(
+                pg_sys::RegisterDynamicBackgroundWorker(&mut worker, &mut handle),
+                "no background worker slot is free"
+            );
+            let mut pid = 0;
+            assert_eq!(
+                pg_sys::WaitForBackgroundWorkerStartup(handle, &mut pid),
+                pg_sys::BgwHandleStatus_BGWH_STARTED
+            );
+            assert_eq!(
+                pg_sys::WaitForBackgroundWorkerShutdown(handle),
+                pg_sys::BgwHandleStatus_BGWH_STOPPED
+            );
+        }
+    }
+
+    fn result(worker: &str) -> Option<String> {
+        Spi::get_one::<String>(&format!(
+            "SELECT result FROM spi_watchdog_results WHERE worker = '{}'",
+            worker
+        ))
+    }
+
+    #[pg_test]
+    fn test_spi_watchdog_timeout() {
+        run_worker("spi_watchdog_timeout_worker_main");
+        assert_eq!(
+            result("timeout").as_deref(),
+            Some("timeout 57014, within a second: true")
+        );
+    }
+
+    #[pg_test]
+    fn test_spi_watchdog_error() {
+        run_worker("spi_watchdog_error_worker_main");
+        assert_eq!(
+            result("error").as_deref(),
+            Some("22012 query failed with SQLSTATE 22012: division by zero")
+        );
+    }
+}
@@ -8,6 +8,32 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use pgx::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTERS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts up from zero, for as long as it's asked to
+struct Counter(i64);
+
+impl Iterator for Counter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        self.0 += 1;
+        Some(self.0 - 1)
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        COUNTERS_DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[pg_extern]
+fn count_forever() -> impl std::iter::Iterator<Item = i64> {
+    Counter(0)
+}
 
 #[pg_extern]
 fn example_generate_series(
@@ -67,6 +93,35 @@ fn return_none_setof_iterator() -> Option<impl std::iter::Iterator<Item = i32>>
     }
 }
 
+/// A row for each of `1..=rows`, with that number in each column the caller asked for, as text
+/// if the column isn't an integer
+#[pg_extern]
+fn example_dynamic_set(
+    rows: i32,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> impl std::iter::Iterator<Item = TableRow> {
+    let columns = TableRow::expected_columns(fcinfo)
+        .expect("no column definition list")
+        .iter()
+        .map(|column| column.atttypid)
+        .collect::<Vec<_>>();
+    (1..=rows).map(move |row| {
+        columns
+            .iter()
+            .fold(TableRow::new(), |table_row, &typid| match typid {
+                pg_sys::INT4OID => table_row.with_value(row),
+                _ => table_row.with_value(row.to_string()),
+            })
+    })
+}
+
+#[pg_extern]
+fn example_named_set() -> impl std::iter::Iterator<Item = TableRow> {
+    vec![("Nami", 3), ("Brandy", 12)]
+        .into_iter()
+        .map(|(name, age)| TableRow::new().with("age", age).with("name", name))
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -74,6 +129,7 @@ mod tests {
     use crate as pgx_tests;
 
     use pgx::*;
+    use std::sync::atomic::Ordering;
 
     #[pg_test]
     fn test_generate_series() {
@@ -95,6 +151,24 @@ mod tests {
         assert_eq!(cnt.unwrap(), 10)
     }
 
+    #[pg_test]
+    fn test_srf_is_not_collected() {
+        let total =
+            Spi::get_one::<i64>("SELECT max(n) FROM (SELECT count_forever() AS n LIMIT 4) s");
+        assert_eq!(total, Some(3));
+    }
+
+    #[pg_test]
+    fn test_srf_rescan() {
+        let dropped = super::COUNTERS_DROPPED.load(Ordering::SeqCst);
+        let maxes = Spi::get_one::<Vec<i64>>(
+            "SELECT array_agg((SELECT max(n) FROM (SELECT count_forever() AS n LIMIT x) s) ORDER BY x)
+             FROM generate_series(1, 3) x",
+        );
+        assert_eq!(maxes, Some(vec![0, 1, 2]));
+        assert_eq!(super::COUNTERS_DROPPED.load(Ordering::SeqCst) - dropped, 3);
+    }
+
     #[pg_test]
     fn test_composite_set() {
         let cnt = Spi::connect(|client| {
@@ -165,4 +239,47 @@ mod tests {
 
         assert_eq!(cnt, Some(0))
     }
+
+    #[pg_test]
+    fn test_dynamic_set() {
+        let row = Spi::get_two::<i32, String>(
+            "SELECT a, b FROM example_dynamic_set(3) AS t(a integer, b text) ORDER BY a DESC LIMIT 1",
+        );
+        assert_eq!(row, (Some(3), Some("3".to_string())));
+    }
+
+    #[pg_test]
+    fn test_dynamic_set_has_no_column_limit() {
+        let columns = (1..=40)
+            .map(|i| format!("c{} integer", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sum = Spi::get_one::<i64>(&format!(
+            "SELECT sum(c1 + c40) FROM example_dynamic_set(2) AS t({})",
+            columns
+        ));
+        assert_eq!(sum, Some(6));
+    }
+
+    #[pg_test]
+    fn test_named_set() {
+        let names = Spi::get_one::<String>(
+            "SELECT string_agg(name, ',' ORDER BY age) FROM example_named_set() AS t(age integer, name text)",
+        );
+        assert_eq!(names.as_deref(), Some("Nami,Brandy"));
+    }
+
+    #[pg_test(
+        error = "returned row does not match the column definition list: no such attribute: `age`"
+    )]
+    fn test_named_set_missing_column() {
+        Spi::run("SELECT * FROM example_named_set() AS t(name text)");
+    }
+
+    #[pg_test(
+        error = "returned row does not match the column definition list: attribute `age` has type oid 20, but was given a value of type oid 23"
+    )]
+    fn test_named_set_wrong_type() {
+        Spi::run("SELECT * FROM example_named_set() AS t(name text, age bigint)");
+    }
 }
@@ -0,0 +1,74 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[derive(Clone, PostgresStatusView)]
+#[pgx(source = TEST_STATUS, name = "test_worker_status")]
+pub struct WorkerStatus {
+    jobs_run: i64,
+    paused: bool,
+    last_job: String,
+    #[pgx(skip)]
+    #[allow(dead_code)]
+    scratch: [u8; 4],
+}
+
+/// Stands in for the shared memory, which pgx_tests isn't preloaded to have
+pub struct TestStatus;
+
+impl StatusViewSource<WorkerStatus> for TestStatus {
+    fn snapshot(&self) -> WorkerStatus {
+        WorkerStatus {
+            jobs_run: 42,
+            paused: false,
+            last_job: String::from("vacuum"),
+            scratch: [0; 4],
+        }
+    }
+}
+
+static TEST_STATUS: TestStatus = TestStatus;
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_status_view() {
+        let row = Spi::get_three::<i64, bool, String>(
+            "SELECT jobs_run, paused, last_job FROM test_worker_status",
+        );
+        assert_eq!(row, (Some(42), Some(false), Some(String::from("vacuum"))));
+    }
+
+    #[pg_test]
+    fn test_status_function_columns() {
+        let columns = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(attname::text ORDER BY attnum)
+               FROM pg_attribute
+              WHERE attrelid = 'test_worker_status'::regclass AND attnum > 0",
+        );
+        assert_eq!(
+            columns,
+            Some(vec![
+                String::from("jobs_run"),
+                String::from("paused"),
+                String::from("last_job")
+            ])
+        );
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_worker_status()");
+        assert_eq!(count, Some(1));
+    }
+}
@@ -0,0 +1,139 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use std::collections::HashMap;
+
+/// The rows of each table, which start out empty as each test runs in its own backend
+static mut TABLES: Option<HashMap<pg_sys::Oid, Vec<(i32, String)>>> = None;
+
+fn rows(relation: &PgRelation) -> &'static mut Vec<(i32, String)> {
+    unsafe {
+        TABLES
+            .get_or_insert_with(HashMap::new)
+            .entry(relation.oid())
+            .or_default()
+    }
+}
+
+/// Rows are numbered from 1, in the first block
+fn ctid(index: usize) -> pg_sys::ItemPointerData {
+    let mut ctid = pg_sys::ItemPointerData::default();
+    item_pointer_set_all(&mut ctid, 0, index as pg_sys::OffsetNumber + 1);
+    ctid
+}
+
+struct Memory {
+    rows: Vec<(i32, String)>,
+    position: usize,
+}
+
+impl TableAccessMethod for Memory {
+    type Row = (i32, String);
+
+    fn estimate_rows(relation: &PgRelation) -> f64 {
+        rows(relation).len() as f64
+    }
+
+    fn create_storage(relation: &PgRelation) {
+        rows(relation).clear();
+    }
+
+    fn begin_scan(relation: &PgRelation) -> Self {
+        Memory {
+            rows: rows(relation).clone(),
+            position: 0,
+        }
+    }
+
+    fn next(&mut self) -> Option<(pg_sys::ItemPointerData, Self::Row)> {
+        let row = self.rows.get(self.position).cloned()?;
+        self.position += 1;
+        Some((ctid(self.position - 1), row))
+    }
+
+    fn rescan(&mut self) {
+        self.position = 0;
+    }
+
+    fn fetch(relation: &PgRelation, ctid: pg_sys::ItemPointerData) -> Option<Self::Row> {
+        let (_, offset) = item_pointer_get_both(ctid);
+        rows(relation).get(offset as usize - 1).cloned()
+    }
+
+    fn insert(
+        relation: &PgRelation,
+        row: PgHeapTuple<'_, AllocatedByPostgres>,
+    ) -> pg_sys::ItemPointerData {
+        let id = row.get_by_name("id").unwrap().unwrap();
+        let name = row.get_by_name("name").unwrap().unwrap();
+        let rows = rows(relation);
+        rows.push((id, name));
+        ctid(rows.len() - 1)
+    }
+}
+
+#[pg_extern(sql = r#"
+    CREATE FUNCTION memory_am_handler(internal) RETURNS table_am_handler
+    LANGUAGE c AS '@MODULE_PATHNAME@', '@FUNCTION_NAME@';
+    CREATE ACCESS METHOD memory TYPE TABLE HANDLER memory_am_handler;
+"#)]
+fn memory_am_handler(_fcinfo: pg_sys::FunctionCallInfo) -> PgBox<pg_sys::TableAmRoutine> {
+    table_am_routine::<Memory>()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_tableam_insert_and_scan() {
+        Spi::run("CREATE TABLE pets (id int, name text) USING memory");
+        Spi::run("INSERT INTO pets VALUES (1, 'Nami'), (2, 'Brandy'), (3, 'Sally')");
+
+        let names =
+            Spi::get_one::<String>("SELECT string_agg(name, ',' ORDER BY id) FROM pets").unwrap();
+        assert_eq!(names, "Nami,Brandy,Sally");
+        let name = Spi::get_one::<String>("SELECT name FROM pets WHERE id = 3").unwrap();
+        assert_eq!(name, "Sally");
+    }
+
+    #[pg_test]
+    fn test_tableam_fetch_by_ctid() {
+        Spi::run("CREATE TABLE pets (id int, name text) USING memory");
+        Spi::run("INSERT INTO pets VALUES (1, 'Nami'), (2, 'Brandy')");
+
+        let name = Spi::get_one::<String>("SELECT name FROM pets WHERE ctid = '(0,2)'").unwrap();
+        assert_eq!(name, "Brandy");
+        let missing =
+            Spi::get_one::<i64>("SELECT count(*) FROM pets WHERE ctid = '(0,9)'").unwrap();
+        assert_eq!(missing, 0);
+    }
+
+    #[pg_test]
+    fn test_tableam_truncate() {
+        Spi::run("CREATE TABLE pets (id int, name text) USING memory");
+        Spi::run("INSERT INTO pets VALUES (1, 'Nami')");
+        Spi::run("TRUNCATE pets");
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM pets").unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[pg_test(error = "pgx_tests::tests::tableam_tests::Memory does not support UPDATE")]
+    fn test_tableam_update_unsupported() {
+        Spi::run("CREATE TABLE pets (id int, name text) USING memory");
+        Spi::run("INSERT INTO pets VALUES (1, 'Nami')");
+        Spi::run("UPDATE pets SET name = 'Brandy'");
+    }
+}
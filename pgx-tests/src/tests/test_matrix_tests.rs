@@ -0,0 +1,36 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test(matrix(enable_seqscan = [on, off], work_mem = ["64kB", "1MB"]))]
+    fn test_matrix_settings() {
+        let seqscan = Spi::get_one::<String>("SHOW enable_seqscan").unwrap();
+        assert!(seqscan == "on" || seqscan == "off");
+        let work_mem = Spi::get_one::<String>("SHOW work_mem").unwrap();
+        assert!(work_mem == "64kB" || work_mem == "1MB");
+    }
+
+    #[pg_test(matrix(pgx_tests.matrix_value = [-1, 2.5, "three"]))]
+    fn test_matrix_extension_setting() {
+        let value = Spi::get_one::<String>("SELECT current_setting('pgx_tests.matrix_value')");
+        assert!(matches!(value.as_deref(), Some("-1" | "2.5" | "three")));
+    }
+
+    #[pg_test(error = "matrix failure", matrix(statement_timeout = [0, "1h"]))]
+    fn test_matrix_error() {
+        error!("matrix failure");
+    }
+}
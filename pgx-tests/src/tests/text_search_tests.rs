@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn tsvector_words(vector: TsVector) -> Vec<String> {
+        vector
+            .lexemes()
+            .map(|lexeme| lexeme.word().to_string())
+            .collect()
+    }
+
+    #[pg_extern]
+    fn english_tsvector(document: &str) -> TsVector {
+        TsVector::from_document_with_config("english", document)
+    }
+
+    #[pg_test]
+    fn test_tsvector_lexemes() {
+        let vector = TsVector::parse("'rat':3C 'fat':2,4A 'cat'");
+        assert_eq!(vector.len(), 3);
+
+        let lexemes = vector.lexemes().collect::<Vec<_>>();
+        assert_eq!(lexemes[0].word(), "cat");
+        assert_eq!(lexemes[0].positions().count(), 0);
+        assert_eq!(lexemes[1].word(), "fat");
+        assert_eq!(
+            lexemes[1].positions().collect::<Vec<_>>(),
+            vec![(2, TsWeight::D), (4, TsWeight::A)]
+        );
+        assert_eq!(lexemes[2].word(), "rat");
+        assert_eq!(
+            lexemes[2].positions().collect::<Vec<_>>(),
+            vec![(3, TsWeight::C)]
+        );
+
+        assert_eq!(vector.strip().to_string(), "'cat' 'fat' 'rat'");
+    }
+
+    #[pg_test]
+    fn test_tsvector_from_document() {
+        let vector = TsVector::from_document_with_config("english", "The fat rats");
+        assert_eq!(vector.to_string(), "'fat':2 'rat':3");
+        assert!(TsVector::from_document_with_config("english", "the").is_empty());
+    }
+
+    #[pg_test]
+    fn test_tsvector_round_trip() {
+        let words = Spi::get_one::<Vec<String>>(
+            "SELECT tests.tsvector_words(to_tsvector('english', 'a fat cat sat on a mat'));",
+        )
+        .expect("SPI result was null");
+        assert_eq!(words, vec!["cat", "fat", "mat", "sat"]);
+
+        let matches = Spi::get_one::<bool>(
+            "SELECT tests.english_tsvector('The fat rats') = to_tsvector('english', 'The fat rats');",
+        );
+        assert_eq!(matches, Some(true));
+    }
+
+    #[pg_test]
+    fn test_tsquery_matches() {
+        let vector = TsVector::from_document_with_config("english", "a fat cat sat on a mat");
+        assert!(vector.matches(&TsQuery::from_query_with_config("english", "cats & mat")));
+        assert!(!vector.matches(&TsQuery::from_query_with_config("english", "cats & rat")));
+        assert!(TsQuery::parse("fat <-> cat").matches(&vector));
+        assert!(!TsQuery::parse("cat <-> fat").matches(&vector));
+        assert!(TsQuery::from_plain_text_with_config("english", "the mats").matches(&vector));
+    }
+
+    #[pg_test]
+    fn test_tsquery_lexemes() {
+        let query = TsQuery::from_query_with_config("english", "fat & (rats | cats)");
+        assert_eq!(query.to_string(), "'fat' & ( 'rat' | 'cat' )");
+
+        let mut lexemes = query.lexemes().collect::<Vec<_>>();
+        lexemes.sort_unstable();
+        assert_eq!(lexemes, vec!["cat", "fat", "rat"]);
+    }
+
+    #[pg_test(error = "syntax error in tsquery: \"fat &\"")]
+    fn test_tsquery_invalid() {
+        TsQuery::parse("fat &");
+    }
+}
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use time::macros::datetime;
+
+    #[pg_test]
+    fn test_timezone_by_name() {
+        let tz = PgTimeZone::by_name("America/New_York").expect("no America/New_York");
+        assert_eq!(tz.name(), "America/New_York");
+        assert!(tz.is_acceptable());
+        assert!(PgTimeZone::by_name("Nowhere/Special").is_none());
+    }
+
+    #[pg_test]
+    fn test_timezone_names() {
+        let names = PgTimeZone::names();
+        assert!(names.iter().any(|name| name == "Europe/Paris"));
+        assert!(!names.iter().any(|name| name == "Factory"));
+
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_timezone_names WHERE name = 'Europe/Paris'",
+        );
+        assert_eq!(count, Some(1));
+    }
+
+    #[pg_test]
+    fn test_timezone_session() {
+        Spi::run("SET TIME ZONE 'Asia/Tokyo'");
+        assert_eq!(PgTimeZone::session().name(), "Asia/Tokyo");
+    }
+
+    #[pg_test]
+    fn test_timezone_local_time() {
+        let tz = PgTimeZone::by_name("America/New_York").unwrap();
+
+        let summer = TimestampWithTimeZone::from(datetime!(2021-07-01 16:00 UTC));
+        let local = tz.local_time(summer).unwrap();
+        assert_eq!(local.time.hour(), 12);
+        assert_eq!(local.time.offset().whole_hours(), -4);
+        assert_eq!(local.abbreviation, "EDT");
+        assert!(local.is_dst);
+
+        let winter = TimestampWithTimeZone::from(datetime!(2021-01-01 17:00 UTC));
+        let local = tz.local_time(winter).unwrap();
+        assert_eq!(local.time.hour(), 12);
+        assert_eq!(local.abbreviation, "EST");
+        assert!(!local.is_dst);
+
+        assert!(tz.local_time(TimestampWithTimeZone::INFINITY).is_none());
+    }
+
+    #[pg_test]
+    fn test_timezone_to_instant() {
+        let tz = PgTimeZone::by_name("America/New_York").unwrap();
+
+        let instant = tz.to_instant(Timestamp::new(datetime!(2021-07-01 12:00:00.5)));
+        assert_eq!(*instant, datetime!(2021-07-01 16:00:00.5 UTC));
+
+        // skipped by the change to daylight saving time, as `AT TIME ZONE` does
+        let instant = tz.to_instant(Timestamp::new(datetime!(2018-03-11 02:30)));
+        let expected = Spi::get_one::<TimestampWithTimeZone>(
+            "SELECT '2018-03-11 02:30'::timestamp AT TIME ZONE 'America/New_York'",
+        )
+        .unwrap();
+        assert_eq!(*instant, *expected);
+
+        assert!(tz.to_instant(Timestamp::INFINITY).is_infinity());
+    }
+}
@@ -0,0 +1,134 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_trigger]
+fn trigger_uppercase_title(
+    trigger: &PgTrigger,
+) -> Result<Option<PgHeapTuple<'_, AllocatedByRust>>, PgHeapTupleError> {
+    let mut new = match trigger.new() {
+        Some(new) => new.into_owned(),
+        None => return Ok(None),
+    };
+    let title: Option<String> = new.get_by_name("title")?;
+    new.set_by_name("title", title.map(|title| title.to_uppercase()))?;
+    Ok(Some(new))
+}
+
+#[pg_trigger]
+fn trigger_skip_negative(trigger: &PgTrigger) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+    let new = trigger.new()?;
+    match new.get_by_name::<i32>("value") {
+        Ok(Some(value)) if value < 0 => None,
+        _ => Some(new),
+    }
+}
+
+#[pg_trigger]
+fn trigger_read_as_int8(
+    trigger: &PgTrigger,
+) -> Result<Option<PgHeapTuple<'_, AllocatedByPostgres>>, PgHeapTupleError> {
+    let new = match trigger.new() {
+        Some(new) => new,
+        None => return Ok(None),
+    };
+    new.get_by_name::<i64>("value")?;
+    Ok(Some(new))
+}
+
+#[pg_trigger]
+fn trigger_describe(
+    trigger: &PgTrigger,
+) -> Result<Option<PgHeapTuple<'_, AllocatedByRust>>, PgHeapTupleError> {
+    let mut new = match trigger.new() {
+        Some(new) => new.into_owned(),
+        None => return Ok(None),
+    };
+    let description = format!(
+        "{:?} {:?} {:?} {} on {} {:?}",
+        trigger.event(),
+        trigger.when(),
+        trigger.level(),
+        trigger.name(),
+        trigger.table_name(),
+        trigger.extra_args(),
+    );
+    new.set_by_name("description", description)?;
+    Ok(Some(new))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_trigger_modifies_row() {
+        Spi::run("CREATE TABLE trigger_test_titles (title text)");
+        Spi::run(
+            "CREATE TRIGGER uppercase BEFORE INSERT ON trigger_test_titles
+                FOR EACH ROW EXECUTE PROCEDURE trigger_uppercase_title()",
+        );
+        Spi::run("INSERT INTO trigger_test_titles VALUES ('a title'), (NULL)");
+
+        let titles = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(title ORDER BY title) FROM trigger_test_titles",
+        )
+        .expect("SQL select failed");
+        assert_eq!(titles, vec![Some("A TITLE".to_string()), None]);
+    }
+
+    #[pg_test]
+    fn test_trigger_skips_row() {
+        Spi::run("CREATE TABLE trigger_test_values (value int4)");
+        Spi::run(
+            "CREATE TRIGGER skip_negative BEFORE INSERT ON trigger_test_values
+                FOR EACH ROW EXECUTE PROCEDURE trigger_skip_negative()",
+        );
+        Spi::run("INSERT INTO trigger_test_values VALUES (1), (-2), (3)");
+
+        let values = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(value ORDER BY value) FROM trigger_test_values",
+        )
+        .expect("SQL select failed");
+        assert_eq!(values, vec![1, 3]);
+    }
+
+    #[pg_test(error = "attribute `value` has type oid 23, but was given a value of type oid 20")]
+    fn test_trigger_read_wrong_type() {
+        Spi::run("CREATE TABLE trigger_test_ints (value int4)");
+        Spi::run(
+            "CREATE TRIGGER read_as_int8 BEFORE INSERT ON trigger_test_ints
+                FOR EACH ROW EXECUTE PROCEDURE trigger_read_as_int8()",
+        );
+        Spi::run("INSERT INTO trigger_test_ints VALUES (1)");
+    }
+
+    #[pg_test]
+    fn test_trigger_metadata() {
+        Spi::run("CREATE TABLE trigger_test_described (id int4, description text)");
+        Spi::run(
+            "CREATE TRIGGER describe BEFORE UPDATE ON trigger_test_described
+                FOR EACH ROW EXECUTE PROCEDURE trigger_describe('an', 'argument')",
+        );
+        Spi::run("INSERT INTO trigger_test_described VALUES (1, NULL)");
+        Spi::run("UPDATE trigger_test_described SET id = 2");
+
+        let description = Spi::get_one::<String>("SELECT description FROM trigger_test_described")
+            .expect("SQL select failed");
+        assert_eq!(
+            description,
+            r#"Update Before Row describe on trigger_test_described ["an", "argument"]"#
+        );
+    }
+}
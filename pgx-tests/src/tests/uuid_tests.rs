@@ -27,6 +27,12 @@ fn display_uuid(uuid: Uuid) -> String {
     format!("{}", uuid)
 }
 
+#[cfg(feature = "uuid")]
+#[pg_extern]
+fn accept_uuid_crate_uuid(uuid: uuid::Uuid) -> uuid::Uuid {
+    uuid
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pg_schema]
 mod tests {
@@ -80,4 +86,16 @@ mod tests {
             .expect("SPI result was null");
         assert_eq!(uuid, Uuid::from_bytes(super::TEST_UUID_V4))
     }
+
+    #[cfg(feature = "uuid")]
+    #[pg_test]
+    fn test_uuid_crate_round_trip() {
+        let uuid = Spi::get_one::<uuid::Uuid>(
+            "SELECT accept_uuid_crate_uuid('123e4567-e89b-12d3-a456-426614174000'::uuid);",
+        )
+        .expect("SPI result was null");
+        assert_eq!(uuid, uuid::Uuid::from_bytes(super::TEST_UUID_V4));
+        assert_eq!(Uuid::from(uuid), Uuid::from_bytes(super::TEST_UUID_V4));
+        assert_eq!(uuid.to_string(), "123e4567-e89b-12d3-a456-426614174000");
+    }
 }
@@ -0,0 +1,46 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_wait_event_guard() {
+        let event = WaitEvent::extension(7);
+        assert_eq!(WaitEvent::current(), None);
+        {
+            let _waiting = event.start();
+            assert_eq!(WaitEvent::current(), Some(event));
+        }
+        assert_eq!(WaitEvent::current(), None);
+    }
+
+    #[pg_test]
+    fn test_wait_event_info() {
+        assert_eq!(WaitEvent::EXTENSION.info(), pg_sys::PG_WAIT_EXTENSION);
+        assert_eq!(
+            WaitEvent::extension(7).info(),
+            pg_sys::PG_WAIT_EXTENSION | 7
+        );
+        assert_eq!(WaitEvent::extension(7).name(), "Extension");
+    }
+
+    #[pg_test]
+    fn test_report_wait_start_and_end() {
+        pgstat_report_wait_start(WaitEvent::EXTENSION.info());
+        assert_eq!(WaitEvent::current(), Some(WaitEvent::EXTENSION));
+        pgstat_report_wait_end();
+        assert_eq!(WaitEvent::current(), None);
+    }
+}
@@ -0,0 +1,114 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::wasm::{WasmError, WasmLimits, WasmSandbox, WasmType, WasmValue};
+    use pgx::*;
+
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len))))
+          (func (export "spin") (result i32)
+            (loop $forever (br $forever))
+            (i32.const 0))
+          (func (export "grow") (result i32)
+            (memory.grow (i32.const 1000)))
+          (func (export "huge") (result i64)
+            (i64.const 0x400ffffffff)))
+    "#;
+
+    fn sandbox() -> WasmSandbox {
+        WasmSandbox::new(MODULE.as_bytes(), WasmLimits::default()).unwrap()
+    }
+
+    #[pg_test]
+    fn test_wasm_scalars() {
+        let sum = sandbox().call(
+            "add",
+            &[WasmValue::Int4(2), WasmValue::Int4(3)],
+            WasmType::Int4,
+        );
+        assert_eq!(sum, Ok(WasmValue::Int4(5)));
+    }
+
+    #[pg_test]
+    fn test_wasm_text() {
+        let text = Spi::get_one::<&str>("SELECT 'hello'::text").unwrap();
+        let arg = unsafe {
+            WasmValue::from_datum(text.into_datum().unwrap(), false, pg_sys::TEXTOID).unwrap()
+        };
+        let echoed = sandbox().call("echo", &[arg], WasmType::Text);
+        assert_eq!(echoed, Ok(WasmValue::Text("hello".to_string())));
+    }
+
+    #[pg_test]
+    fn test_wasm_fuel_limit() {
+        let result = sandbox().call("spin", &[], WasmType::Int4);
+        assert_eq!(
+            result,
+            Err(WasmError::OutOfFuel {
+                fuel: WasmLimits::default().fuel
+            })
+        );
+    }
+
+    #[pg_test]
+    fn test_wasm_memory_limit() {
+        // memory.grow returns -1 rather than grow past the limit
+        let result = sandbox().call("grow", &[], WasmType::Int4);
+        assert_eq!(result, Ok(WasmValue::Int4(-1)));
+    }
+
+    #[pg_test]
+    fn test_wasm_result_outside_memory() {
+        // 4GiB claimed at 1024, which is checked against the 64KiB of memory before allocating
+        let result = sandbox().call("huge", &[], WasmType::Bytea);
+        assert_eq!(
+            result,
+            Err(WasmError::Trap(
+                "returned 4294967295 bytes at 1024, outside of its memory".to_string()
+            ))
+        );
+    }
+
+    #[pg_test]
+    fn test_wasm_errors() {
+        let result = sandbox().call("missing", &[], WasmType::Int4);
+        assert_eq!(result, Err(WasmError::MissingExport("missing".to_string())));
+
+        let result = sandbox().call(
+            "add",
+            &[WasmValue::Int4(2), WasmValue::Int4(3)],
+            WasmType::Int8,
+        );
+        assert!(matches!(result, Err(WasmError::Type(_))));
+
+        assert!(matches!(
+            WasmSandbox::new(b"(module (func", WasmLimits::default()),
+            Err(WasmError::Compile(_))
+        ));
+    }
+}
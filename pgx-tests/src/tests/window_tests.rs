@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern(window)]
+fn window_row_number(mut window: WindowFunctionContext) -> i64 {
+    let counter = unsafe { window.partition_local_memory::<i64>() };
+    *counter += 1;
+    *counter
+}
+
+#[pg_extern(window)]
+fn window_partition_size(window: WindowFunctionContext) -> i64 {
+    window.partition_row_count()
+}
+
+#[pg_extern(window)]
+fn window_previous(_value: Option<i32>, window: WindowFunctionContext) -> Option<i32> {
+    window
+        .get_func_arg_in_partition(0, -1, WindowSeek::Current, false)
+        .flatten()
+}
+
+#[pg_extern(window)]
+fn window_last_in_frame(_value: Option<i32>, window: WindowFunctionContext) -> Option<i32> {
+    window
+        .get_func_arg_in_frame(0, 0, WindowSeek::Tail, false)
+        .flatten()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_window_row_number() {
+        let retval = Spi::get_one::<Vec<i64>>(
+            "SELECT array_agg(n ORDER BY x, n)
+               FROM (SELECT x, window_row_number() OVER (PARTITION BY x % 2 ORDER BY x) AS n
+                       FROM generate_series(1, 5) x) s;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, vec![1, 1, 2, 2, 3]);
+    }
+
+    #[pg_test]
+    fn test_window_partition_size() {
+        let retval = Spi::get_one::<Vec<i64>>(
+            "SELECT array_agg(n ORDER BY x)
+               FROM (SELECT x, window_partition_size() OVER (PARTITION BY x % 2) AS n
+                       FROM generate_series(1, 5) x) s;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, vec![3, 2, 3, 2, 3]);
+    }
+
+    #[pg_test]
+    fn test_window_previous() {
+        let retval = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT array_agg(n ORDER BY x)
+               FROM (SELECT x, window_previous(x) OVER (ORDER BY x) AS n
+                       FROM generate_series(1, 4) x) s;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, vec![None, Some(1), Some(2), Some(3)]);
+    }
+
+    #[pg_test]
+    fn test_window_last_in_frame() {
+        let retval = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(n ORDER BY x)
+               FROM (SELECT x, window_last_in_frame(x) OVER (ORDER BY x) AS n
+                       FROM generate_series(1, 4) x) s;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, vec![1, 2, 3, 4]);
+    }
+}
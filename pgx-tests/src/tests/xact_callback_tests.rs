@@ -14,6 +14,7 @@ mod tests {
     use crate as pgx_tests;
 
     use pgx::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn make_idea_happy() {}
@@ -24,4 +25,38 @@ mod tests {
             info!("TESTMSG: Called on abort")
         });
     }
+
+    #[pg_test]
+    fn test_xact_callback_panic_on_abort() {
+        // the test's transaction is aborted when it's done, which would restart the cluster if
+        // this panic weren't reported as a WARNING
+        register_xact_callback(PgXactCallbackEvent::Abort, || panic!("panic during abort"));
+        register_xact_callback(PgXactCallbackEvent::Abort, || error!("ERROR during abort"));
+    }
+
+    #[pg_test]
+    fn test_subxact_callback() {
+        static ABORTED: AtomicUsize = AtomicUsize::new(0);
+        static COMMITTED: AtomicUsize = AtomicUsize::new(0);
+
+        register_subxact_callback(PgSubXactCallbackEvent::AbortSub, |_, _| {
+            ABORTED.fetch_add(1, Ordering::SeqCst);
+            panic!("panic during subtransaction abort")
+        });
+        register_subxact_callback(PgSubXactCallbackEvent::CommitSub, |_, _| {
+            COMMITTED.fetch_add(1, Ordering::SeqCst);
+        });
+
+        unsafe {
+            let memory_context = pg_sys::CurrentMemoryContext;
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            pg_sys::ReleaseCurrentSubTransaction();
+            pg_sys::CurrentMemoryContext = memory_context;
+        }
+
+        assert_eq!(ABORTED.load(Ordering::SeqCst), 1);
+        assert_eq!(COMMITTED.load(Ordering::SeqCst), 1);
+    }
 }
@@ -23,11 +23,13 @@ use syn::{GenericArgument, ItemFn, PathArguments, ReturnType, Type, TypeParamBou
 
 mod pgx_pg_sys_stub;
 
+pub mod library_naming;
 pub mod operator_common;
 pub mod pg_config;
 pub mod rewriter;
 pub mod sql_entity_graph;
 
+pub use library_naming::LibraryNaming;
 pub use pgx_pg_sys_stub::PgxPgSysStub;
 
 #[doc(hidden)]
@@ -183,13 +185,20 @@ pub enum ExternArgs {
     Volatile,
     Raw,
     NoGuard,
+    LogSlow,
+    PrimaryOnly,
+    SpiReadOnly,
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
     Error(String),
     Schema(String),
     Name(String),
     Cost(String),
+    Rows(String),
+    /// The planner support function, which is rendered with its SQL name, as it's resolved
+    Support(PositioningRef),
     Requires(Vec<PositioningRef>),
 }
 
@@ -204,11 +213,17 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::ParallelSafe => write!(f, "PARALLEL SAFE"),
             ExternArgs::ParallelUnsafe => write!(f, "PARALLEL UNSAFE"),
             ExternArgs::ParallelRestricted => write!(f, "PARALLEL RESTRICTED"),
+            ExternArgs::Window => write!(f, "WINDOW"),
             ExternArgs::Error(_) => Ok(()),
             ExternArgs::NoGuard => Ok(()),
+            ExternArgs::LogSlow => Ok(()),
+            ExternArgs::PrimaryOnly => Ok(()),
+            ExternArgs::SpiReadOnly => Ok(()),
             ExternArgs::Schema(_) => Ok(()),
             ExternArgs::Name(_) => Ok(()),
             ExternArgs::Cost(cost) => write!(f, "COST {}", cost),
+            ExternArgs::Rows(rows) => write!(f, "ROWS {}", rows),
+            ExternArgs::Support(_) => Ok(()),
             ExternArgs::Requires(_) => Ok(()),
         }
     }
@@ -223,9 +238,13 @@ impl ToTokens for ExternArgs {
             ExternArgs::Volatile => tokens.append(format_ident!("Volatile")),
             ExternArgs::Raw => tokens.append(format_ident!("Raw")),
             ExternArgs::NoGuard => tokens.append(format_ident!("NoGuard")),
+            ExternArgs::LogSlow => tokens.append(format_ident!("LogSlow")),
+            ExternArgs::PrimaryOnly => tokens.append(format_ident!("PrimaryOnly")),
+            ExternArgs::SpiReadOnly => tokens.append(format_ident!("SpiReadOnly")),
             ExternArgs::ParallelSafe => tokens.append(format_ident!("ParallelSafe")),
             ExternArgs::ParallelUnsafe => tokens.append(format_ident!("ParallelUnsafe")),
             ExternArgs::ParallelRestricted => tokens.append(format_ident!("ParallelRestricted")),
+            ExternArgs::Window => tokens.append(format_ident!("Window")),
             ExternArgs::Error(_s) => {
                 tokens.append_all(
                     quote! {
@@ -258,6 +277,22 @@ impl ToTokens for ExternArgs {
                     .to_token_stream(),
                 );
             }
+            ExternArgs::Rows(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Rows(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Support(item) => {
+                tokens.append_all(
+                    quote! {
+                        Support(#item)
+                    }
+                    .to_token_stream(),
+                );
+            }
             ExternArgs::Requires(items) => {
                 tokens.append_all(
                     quote! {
@@ -302,9 +337,13 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "volatile" => args.insert(ExternArgs::Volatile),
                     "raw" => args.insert(ExternArgs::Raw),
                     "no_guard" => args.insert(ExternArgs::NoGuard),
+                    "log_slow" => args.insert(ExternArgs::LogSlow),
+                    "primary_only" => args.insert(ExternArgs::PrimaryOnly),
+                    "spi_read_only" => args.insert(ExternArgs::SpiReadOnly),
                     "parallel_safe" => args.insert(ExternArgs::ParallelSafe),
                     "parallel_unsafe" => args.insert(ExternArgs::ParallelUnsafe),
                     "parallel_restricted" => args.insert(ExternArgs::ParallelRestricted),
+                    "window" => args.insert(ExternArgs::Window),
                     "error" => {
                         let _punc = itr.next().unwrap();
                         let literal = itr.next().unwrap();
@@ -0,0 +1,327 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! How an extension's shared library, and the functions Postgres looks up in it, are named and
+//! exported
+use eyre::{eyre, WrapErr};
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Naming and exports of an extension's shared library, from the `[package.metadata.pgx]` table
+/// of its `Cargo.toml`
+///
+/// ```toml
+/// [package.metadata.pgx]
+/// # installed as `$libdir/spi_utils.so`, rather than after the extension and its version
+/// library-name = "spi_utils"
+/// # functions are exported as `spi_utils_hello_wrapper` rather than `hello_wrapper`
+/// symbol-prefix = "spi_utils_"
+/// # used for `@MODULE_PATHNAME@` and the generated `CREATE FUNCTION`s, as is
+/// module-pathname = "$libdir/spi_utils"
+/// ```
+///
+/// Postgres loads libraries with their symbols visible to each other, so two extensions built
+/// from crates with functions of the same name can conflict when they're loaded by the same
+/// backend.  A `symbol-prefix` keeps their names apart.
+///
+/// For the same reason, only the functions Postgres looks up by name are exported from the
+/// library: the wrappers of `#[pg_extern]`s and triggers, and `_PG_init()`.  Other `#[pg_guard]`
+/// functions, such as a background worker's main function, need `#[no_mangle]`, unless
+/// `export-guarded-functions = true` exports all of them.
+///
+/// These are read as the extension is compiled, so a `cargo clean` may be needed for a change to
+/// them to take effect.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct LibraryNaming {
+    /// File name the library is installed with, without the `.so`
+    pub library_name: Option<String>,
+    /// Prefixed to the name of each function Postgres looks up in the library
+    pub symbol_prefix: Option<String>,
+    /// Replaces `@MODULE_PATHNAME@`, and names the library in generated SQL
+    pub module_pathname: Option<String>,
+    /// Export every `#[pg_guard]` function, as if it had `#[no_mangle]`
+    pub export_guarded_functions: bool,
+}
+
+thread_local! {
+    static CURRENT: Result<LibraryNaming, String> = {
+        match std::env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => LibraryNaming::from_manifest(Path::new(&dir).join("Cargo.toml"))
+                .map_err(|e| format!("{:#}", e)),
+            None => Ok(LibraryNaming::default()),
+        }
+    };
+}
+
+impl LibraryNaming {
+    /// The naming of the crate being compiled, for use by the proc macros
+    ///
+    /// An invalid `[package.metadata.pgx]` gives the default naming here, so that the macros
+    /// still expand, and is reported by [`LibraryNaming::current_error`] instead.
+    pub fn current() -> Self {
+        CURRENT.with(|current| current.clone().unwrap_or_default())
+    }
+
+    /// A `compile_error!` describing what's wrong with the `[package.metadata.pgx]` of the crate
+    /// being compiled, if anything is
+    pub fn current_error() -> Option<TokenStream> {
+        CURRENT.with(|current| match current {
+            Ok(_) => None,
+            Err(message) => Some(quote! { compile_error!(#message); }),
+        })
+    }
+
+    /// Read `[package.metadata.pgx]` from the `Cargo.toml` at `path`
+    pub fn from_manifest(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let manifest = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Could not read `{}`", path.display()))?;
+        Self::from_manifest_str(&manifest)
+            .wrap_err_with(|| format!("Invalid `[package.metadata.pgx]` in `{}`", path.display()))
+    }
+
+    /// Read `[package.metadata.pgx]` from the contents of a `Cargo.toml`
+    pub fn from_manifest_str(manifest: &str) -> eyre::Result<Self> {
+        let manifest = manifest.parse::<toml::Value>()?;
+        let naming: Self = match manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("pgx"))
+        {
+            Some(pgx) => pgx.clone().try_into()?,
+            None => return Ok(Self::default()),
+        };
+
+        if let Some(prefix) = &naming.symbol_prefix {
+            if !prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                || prefix.starts_with(|c: char| c.is_ascii_digit())
+            {
+                return Err(eyre!(
+                    "`symbol-prefix` must start a valid C identifier, but is `{}`",
+                    prefix
+                ));
+            }
+        }
+        if let Some(name) = &naming.library_name {
+            if name.is_empty() || name.contains('/') {
+                return Err(eyre!(
+                    "`library-name` must be a file name, without a directory, but is `{}`",
+                    name
+                ));
+            }
+        }
+        Ok(naming)
+    }
+
+    /// The name `name` is exported from the library as
+    pub fn symbol(&self, name: &str) -> String {
+        match &self.symbol_prefix {
+            Some(prefix) => format!("{}{}", prefix, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// The attribute to export a function Postgres looks up as `name`
+    pub fn export_attribute(&self, name: &str) -> TokenStream {
+        match &self.symbol_prefix {
+            Some(_) => {
+                let symbol = self.symbol(name);
+                quote! { #[export_name = #symbol] }
+            }
+            None => quote! { #[no_mangle] },
+        }
+    }
+
+    /// The attribute to export the `pg_finfo_` function of `wrapper`, which Postgres looks up by
+    /// the name `wrapper` is exported as
+    pub fn finfo_export_attribute(&self, wrapper: &str) -> TokenStream {
+        match &self.symbol_prefix {
+            Some(_) => {
+                let symbol = format!("pg_finfo_{}", self.symbol(wrapper));
+                quote! { #[export_name = #symbol] }
+            }
+            None => quote! { #[no_mangle] },
+        }
+    }
+
+    /// File name of the installed library, without the `.so`
+    ///
+    /// Unless `library-name` says otherwise, a library is named after its extension, and also its
+    /// version when `versioned_so`, which is when the control file has no `module_pathname`.
+    pub fn library_file_name(
+        &self,
+        extension_name: &str,
+        extension_version: &str,
+        versioned_so: bool,
+    ) -> String {
+        match &self.library_name {
+            Some(name) => name.clone(),
+            None if versioned_so => crate::versioned_so_name(extension_name, extension_version),
+            None => extension_name.to_string(),
+        }
+    }
+
+    /// The contents of an extension's `.control` file, with its `module_pathname` set from
+    /// `module-pathname`, or from `library-name` under `$libdir`, when either is given
+    ///
+    /// A control file without a `module_pathname` is left as it is, as its library is versioned.
+    pub fn control_file(&self, control: &str) -> String {
+        let pathname = match (&self.module_pathname, &self.library_name) {
+            (Some(pathname), _) => pathname.clone(),
+            (None, Some(name)) => format!("$libdir/{}", name),
+            (None, None) => return control.to_string(),
+        };
+        control
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, _)) if key.trim() == "module_pathname" => {
+                    format!("module_pathname = '{}'\n", pathname)
+                }
+                _ => format!("{}\n", line),
+            })
+            .collect()
+    }
+
+    /// What generated SQL names the library as
+    ///
+    /// This is `module-pathname` if it's given, or `MODULE_PATHNAME` for Postgres to substitute
+    /// when the control file has one, otherwise the library's path under `$libdir`.
+    pub fn module_pathname(
+        &self,
+        extension_name: &str,
+        extension_version: &str,
+        versioned_so: bool,
+    ) -> String {
+        match &self.module_pathname {
+            Some(pathname) => pathname.clone(),
+            None if !versioned_so => String::from("MODULE_PATHNAME"),
+            None => format!(
+                "$libdir/{}",
+                self.library_file_name(extension_name, extension_version, versioned_so)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LibraryNaming;
+
+    #[test]
+    fn defaults_without_metadata() {
+        let naming = LibraryNaming::from_manifest_str("[package]\nname = \"spi\"\n").unwrap();
+        assert_eq!(naming, LibraryNaming::default());
+        assert_eq!(naming.symbol("hello_wrapper"), "hello_wrapper");
+        assert_eq!(naming.library_file_name("spi", "1.0", true), "spi-1.0");
+        assert_eq!(naming.library_file_name("spi", "1.0", false), "spi");
+        assert_eq!(
+            naming.module_pathname("spi", "1.0", true),
+            "$libdir/spi-1.0"
+        );
+        assert_eq!(
+            naming.module_pathname("spi", "1.0", false),
+            "MODULE_PATHNAME"
+        );
+    }
+
+    #[test]
+    fn reads_metadata() {
+        let naming = LibraryNaming::from_manifest_str(
+            "[package]\n\
+             name = \"spi\"\n\
+             [package.metadata.pgx]\n\
+             library-name = \"spi_utils\"\n\
+             symbol-prefix = \"spi_utils_\"\n\
+             export-guarded-functions = true\n",
+        )
+        .unwrap();
+        assert!(naming.export_guarded_functions);
+        assert_eq!(naming.symbol("hello_wrapper"), "spi_utils_hello_wrapper");
+        assert_eq!(
+            naming.finfo_export_attribute("hello_wrapper").to_string(),
+            "# [export_name = \"pg_finfo_spi_utils_hello_wrapper\"]"
+        );
+        assert_eq!(naming.library_file_name("spi", "1.0", true), "spi_utils");
+        assert_eq!(
+            naming.module_pathname("spi", "1.0", true),
+            "$libdir/spi_utils"
+        );
+        assert_eq!(
+            naming.module_pathname("spi", "1.0", false),
+            "MODULE_PATHNAME"
+        );
+    }
+
+    #[test]
+    fn module_pathname_is_used_as_is() {
+        let naming = LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\nmodule-pathname = \"$libdir/elsewhere/spi\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            naming.module_pathname("spi", "1.0", false),
+            "$libdir/elsewhere/spi"
+        );
+    }
+
+    #[test]
+    fn control_file_module_pathname() {
+        let control = "comment = 'spi'\nmodule_pathname = '$libdir/spi'\nrelocatable = false\n";
+
+        let naming = LibraryNaming::from_manifest_str("[package]\nname = \"spi\"\n").unwrap();
+        assert_eq!(naming.control_file(control), control);
+
+        let naming = LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\nlibrary-name = \"spi_utils\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            naming.control_file(control),
+            "comment = 'spi'\nmodule_pathname = '$libdir/spi_utils'\nrelocatable = false\n"
+        );
+
+        let naming = LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\n\
+             library-name = \"spi_utils\"\n\
+             module-pathname = \"$libdir/elsewhere/spi\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            naming.control_file(control),
+            "comment = 'spi'\nmodule_pathname = '$libdir/elsewhere/spi'\nrelocatable = false\n"
+        );
+
+        // a versioned library's control file has no `module_pathname` to set
+        assert_eq!(
+            naming.control_file("comment = 'spi'\n"),
+            "comment = 'spi'\n"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\nsymbol-prefix = \"spi-\"\n"
+        )
+        .is_err());
+        assert!(LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\nsymbol-prefix = \"1spi\"\n"
+        )
+        .is_err());
+        assert!(LibraryNaming::from_manifest_str(
+            "[package.metadata.pgx]\nlibrary-name = \"lib/spi\"\n"
+        )
+        .is_err());
+    }
+}
@@ -19,6 +19,7 @@ pub fn eq(type_name: &Ident) -> proc_macro2::TokenStream {
         #[pg_operator(immutable, parallel_safe)]
         #[opname(=)]
         #[negator(<>)]
+        #[commutator(=)]
         #[restrict(eqsel)]
         #[join(eqjoinsel)]
         #[merges]
@@ -39,6 +40,7 @@ pub fn ne(type_name: &Ident) -> proc_macro2::TokenStream {
         #[pg_operator(immutable, parallel_safe)]
         #[opname(<>)]
         #[negator(=)]
+        #[commutator(<>)]
         #[restrict(neqsel)]
         #[join(neqjoinsel)]
         fn #pg_name(left: #type_name, right: #type_name) -> bool {
@@ -151,3 +153,229 @@ pub fn hash(type_name: &Ident) -> proc_macro2::TokenStream {
         }
     }
 }
+
+/// Null-safe comparisons, which are `IS DISTINCT FROM` and `IS NOT DISTINCT FROM` as functions
+pub fn distinct_from(type_name: &Ident) -> proc_macro2::TokenStream {
+    let distinct_name = Ident::new(
+        &format!("{}_is_distinct_from", type_name).to_lowercase(),
+        type_name.span(),
+    );
+    let not_distinct_name = Ident::new(
+        &format!("{}_is_not_distinct_from", type_name).to_lowercase(),
+        type_name.span(),
+    );
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #distinct_name(left: Option<#type_name>, right: Option<#type_name>) -> bool {
+            left != right
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #not_distinct_name(left: Option<#type_name>, right: Option<#type_name>) -> bool {
+            left == right
+        }
+    }
+}
+
+/// The name of a GiST support function of the type, such as `<typename>_gist_consistent()`
+pub fn gist_function_name(type_name: &Ident, function: &str) -> Ident {
+    Ident::new(
+        &format!("{}_gist_{}", type_name, function).to_lowercase(),
+        type_name.span(),
+    )
+}
+
+/// The GiST support functions, which call those of the type's `pgx::GistSupport` implementation
+pub fn gist_support(type_name: &Ident) -> proc_macro2::TokenStream {
+    let consistent = gist_function_name(type_name, "consistent");
+    let union = gist_function_name(type_name, "union");
+    let compress = gist_function_name(type_name, "compress");
+    let decompress = gist_function_name(type_name, "decompress");
+    let penalty = gist_function_name(type_name, "penalty");
+    let picksplit = gist_function_name(type_name, "picksplit");
+    let same = gist_function_name(type_name, "same");
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #consistent(
+            entry: pgx::Internal,
+            query: #type_name,
+            strategy: i16,
+            _subtype: pgx::Internal,
+            recheck: pgx::Internal,
+        ) -> bool {
+            unsafe { pgx::gist::gist_consistent::<#type_name>(entry, query, strategy, recheck) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #union(entryvec: pgx::Internal, _size: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::gist::gist_union::<#type_name>(entryvec) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #compress(entry: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::gist::gist_compress::<#type_name>(entry) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #decompress(entry: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::gist::gist_decompress::<#type_name>(entry) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #penalty(
+            original: pgx::Internal,
+            new: pgx::Internal,
+            penalty: pgx::Internal,
+        ) -> pgx::Internal {
+            unsafe { pgx::gist::gist_penalty::<#type_name>(original, new, penalty) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #picksplit(entryvec: pgx::Internal, splitvec: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::gist::gist_picksplit::<#type_name>(entryvec, splitvec) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #same(left: pgx::Internal, right: pgx::Internal, result: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::gist::gist_same::<#type_name>(left, right, result) }
+        }
+    }
+}
+
+/// The name of a GIN support function of the type, such as `<typename>_gin_extract_value()`
+pub fn gin_function_name(type_name: &Ident, function: &str) -> Ident {
+    Ident::new(
+        &format!("{}_gin_{}", type_name, function).to_lowercase(),
+        type_name.span(),
+    )
+}
+
+/// The GIN support functions, which call those of the type's `pgx::GinSupport` implementation
+pub fn gin_support(type_name: &Ident) -> proc_macro2::TokenStream {
+    let compare = gin_function_name(type_name, "compare");
+    let extract_value = gin_function_name(type_name, "extract_value");
+    let extract_query = gin_function_name(type_name, "extract_query");
+    let consistent = gin_function_name(type_name, "consistent");
+    let compare_partial = gin_function_name(type_name, "compare_partial");
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #compare(left: pgx::Internal, right: pgx::Internal) -> i32 {
+            unsafe { pgx::gin::gin_compare::<#type_name>(left, right) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #extract_value(
+            value: #type_name,
+            nkeys: pgx::Internal,
+            null_flags: pgx::Internal,
+        ) -> pgx::Internal {
+            unsafe { pgx::gin::gin_extract_value::<#type_name>(value, nkeys, null_flags) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #extract_query(
+            query: #type_name,
+            nkeys: pgx::Internal,
+            strategy: i16,
+            partial_matches: pgx::Internal,
+            _extra_data: pgx::Internal,
+            null_flags: pgx::Internal,
+            search_mode: pgx::Internal,
+        ) -> pgx::Internal {
+            unsafe {
+                pgx::gin::gin_extract_query::<#type_name>(
+                    query,
+                    nkeys,
+                    strategy,
+                    partial_matches,
+                    null_flags,
+                    search_mode,
+                )
+            }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #consistent(
+            check: pgx::Internal,
+            strategy: i16,
+            query: #type_name,
+            nkeys: i32,
+            _extra_data: pgx::Internal,
+            recheck: pgx::Internal,
+            _query_keys: pgx::Internal,
+            _null_flags: pgx::Internal,
+        ) -> bool {
+            unsafe { pgx::gin::gin_consistent::<#type_name>(check, strategy, query, nkeys, recheck) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #compare_partial(
+            partial: pgx::Internal,
+            key: pgx::Internal,
+            strategy: i16,
+            _extra_data: pgx::Internal,
+        ) -> i32 {
+            unsafe { pgx::gin::gin_compare_partial::<#type_name>(partial, key, strategy) }
+        }
+    }
+}
+
+/// The name of a BRIN support function of the type, such as `<typename>_brin_add_value()`
+pub fn brin_function_name(type_name: &Ident, function: &str) -> Ident {
+    Ident::new(
+        &format!("{}_brin_{}", type_name, function).to_lowercase(),
+        type_name.span(),
+    )
+}
+
+/// The BRIN support functions, which call those of the type's `pgx::BrinSupport` implementation
+pub fn brin_support(type_name: &Ident) -> proc_macro2::TokenStream {
+    let opcinfo = brin_function_name(type_name, "opcinfo");
+    let add_value = brin_function_name(type_name, "add_value");
+    let consistent = brin_function_name(type_name, "consistent");
+    let union = brin_function_name(type_name, "union");
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #opcinfo(_type_oid: pgx::Internal) -> pgx::Internal {
+            unsafe { pgx::brin::brin_opcinfo::<#type_name>() }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #add_value(
+            _desc: pgx::Internal,
+            column: pgx::Internal,
+            new_value: pgx::Internal,
+            is_null: pgx::Internal,
+        ) -> bool {
+            unsafe { pgx::brin::brin_add_value::<#type_name>(column, new_value, is_null) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #consistent(_desc: pgx::Internal, column: pgx::Internal, key: pgx::Internal) -> bool {
+            unsafe { pgx::brin::brin_consistent::<#type_name>(column, key) }
+        }
+
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #union(_desc: pgx::Internal, column: pgx::Internal, other: pgx::Internal) -> bool {
+            unsafe { pgx::brin::brin_union::<#type_name>(column, other) }
+        }
+    }
+}
@@ -10,7 +10,7 @@ Use of this source code is governed by the MIT license that can be found in the
 extern crate proc_macro;
 
 use crate::sql_entity_graph::PgExtern;
-use crate::{categorize_return_type, CategorizedType};
+use crate::{categorize_return_type, CategorizedType, LibraryNaming};
 use proc_macro2::{Ident, Span};
 use quote::{quote, quote_spanned, ToTokens};
 use std::ops::Deref;
@@ -46,9 +46,20 @@ impl PgGuardRewriter {
         rewrite_args: bool,
         is_raw: bool,
         no_guard: bool,
+        log_slow: bool,
+        primary_only: bool,
+        read_only: bool,
     ) -> (proc_macro2::TokenStream, bool) {
         if rewrite_args {
-            self.item_fn_with_rewrite(func, entity_submission, is_raw, no_guard)
+            self.item_fn_with_rewrite(
+                func,
+                entity_submission,
+                is_raw,
+                no_guard,
+                log_slow,
+                primary_only,
+                read_only,
+            )
         } else {
             (
                 self.item_fn_without_rewrite(func, entity_submission, no_guard),
@@ -63,6 +74,9 @@ impl PgGuardRewriter {
         entity_submission: Option<&PgExtern>,
         is_raw: bool,
         no_guard: bool,
+        log_slow: bool,
+        primary_only: bool,
+        read_only: bool,
     ) -> (proc_macro2::TokenStream, bool) {
         // remember the original visibility and signature classifications as we want
         // to use those for the outer function
@@ -92,12 +106,54 @@ impl PgGuardRewriter {
             Ident::new("result", Span::call_site())
         };
 
-        let func_call = quote! {
-            let #result_var_name = {
-                #rewritten_args
+        let func_call = if log_slow {
+            quote! {
+                let #result_var_name = {
+                    let slow_call_start = std::time::Instant::now();
+                    let result = {
+                        #rewritten_args
+
+                        #func_name(#arg_list)
+                    };
+                    pgx::SlowCalls::check(fcinfo, slow_call_start.elapsed());
+                    result
+                };
+            }
+        } else {
+            quote! {
+                let #result_var_name = {
+                    #rewritten_args
+
+                    #func_name(#arg_list)
+                };
+            }
+        };
 
-                #func_name(#arg_list)
-            };
+        // checked before the arguments are even read, as a standby can't run it at all
+        let func_call = if primary_only {
+            let sql_name = format!(
+                "{}()",
+                entity_submission
+                    .map(|entity| entity.name())
+                    .unwrap_or_else(|| func_name.to_string())
+            );
+            quote! {
+                pgx::recovery::prevent_during_recovery(#sql_name);
+                #func_call
+            }
+        } else {
+            func_call
+        };
+
+        // SPI runs read-only for as long as a `spi_read_only` function does, and any other
+        // function called from one gets its writes back
+        let func_call = if entity_submission.is_some() {
+            quote! {
+                let _spi_function_guard = pgx::Spi::enter_function(#read_only);
+                #func_call
+            }
+        } else {
+            func_call
         };
 
         let prolog = quote! {
@@ -127,6 +183,37 @@ impl PgGuardRewriter {
                 false,
             ),
 
+            // `TableRow`s are returned as a table, though there's just the one type
+            CategorizedType::Iterator(types) if is_table_row(&types) => (
+                PgGuardRewriter::impl_table_srf(
+                    types,
+                    func_span,
+                    prolog,
+                    vis,
+                    func_name_wrapper,
+                    generics,
+                    func_call,
+                    entity_submission,
+                    false,
+                ),
+                true,
+            ),
+
+            CategorizedType::OptionalIterator(types) if is_table_row(&types) => (
+                PgGuardRewriter::impl_table_srf(
+                    types,
+                    func_span,
+                    prolog,
+                    vis,
+                    func_name_wrapper,
+                    generics,
+                    func_call,
+                    entity_submission,
+                    true,
+                ),
+                true,
+            ),
+
             CategorizedType::Iterator(types) if types.len() == 1 => (
                 PgGuardRewriter::impl_setof_srf(
                     types,
@@ -189,6 +276,16 @@ impl PgGuardRewriter {
         }
     }
 
+    /// Postgres looks up the wrapper by name, so it's exported, with the library's symbol prefix
+    fn guard_attributes(func_name_wrapper: &Ident, no_guard: bool) -> proc_macro2::TokenStream {
+        let export = LibraryNaming::current().export_attribute(&func_name_wrapper.to_string());
+        if no_guard {
+            export
+        } else {
+            quote! {#[pg_guard] #export}
+        }
+    }
+
     fn impl_standard_udf(
         func_span: Span,
         prolog: proc_macro2::TokenStream,
@@ -200,11 +297,7 @@ impl PgGuardRewriter {
         sql_graph_entity_submission: Option<&PgExtern>,
         no_guard: bool,
     ) -> proc_macro2::TokenStream {
-        let guard = if no_guard {
-            quote! {#[no_mangle]}
-        } else {
-            quote! {#[pg_guard]}
-        };
+        let guard = PgGuardRewriter::guard_attributes(&func_name_wrapper, no_guard);
         let sql_graph_entity_submission = sql_graph_entity_submission.cloned().into_iter();
         quote_spanned! {func_span=>
             #prolog
@@ -286,11 +379,12 @@ impl PgGuardRewriter {
             }
         };
 
+        let guard = PgGuardRewriter::guard_attributes(&func_name_wrapper, false);
         let sql_graph_entity_submission = sql_graph_entity_submission.cloned().into_iter();
 
         quote_spanned! {func_span=>
             #prolog
-            #[pg_guard]
+            #guard
             #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
 
                 struct IteratorHolder<T> {
@@ -314,13 +408,12 @@ impl PgGuardRewriter {
                 funcctx = pgx::srf_per_call_setup(fcinfo);
                 iterator_holder = pgx::PgBox::from_pg(funcctx.user_fctx as *mut IteratorHolder<#generic_type>);
 
-                let mut iter = Box::from_raw(iterator_holder.iter);
+                // the iterator is only borrowed, as it's dropped when the multi-call memory context
+                // is deleted, whether that's because it's done, or the scan ended early or is
+                // being rescanned
+                let iter = &mut *iterator_holder.iter;
                 match iter.next() {
                     Some(result) => {
-                        // we need to leak the boxed iterator so that it's not freed by rust and we can
-                        // continue to use it
-                        Box::leak(iter);
-
                         pgx::srf_return_next(fcinfo, &mut funcctx);
                         match result.into_datum() {
                             Some(datum) => datum,
@@ -328,10 +421,6 @@ impl PgGuardRewriter {
                         }
                     },
                     None => {
-                        // leak the iterator here too, even tho we're done, b/c our MemoryContextCallback
-                        // function is going to properly drop it for us
-                        Box::leak(iter);
-
                         pgx::srf_return_done(fcinfo, &mut funcctx);
                         pgx::pg_return_null(fcinfo)
                     },
@@ -353,29 +442,41 @@ impl PgGuardRewriter {
         entity_submission: Option<&PgExtern>,
         optional: bool,
     ) -> proc_macro2::TokenStream {
-        let numtypes = types.len();
-        let i = (0..numtypes).map(syn::Index::from);
-        let create_heap_tuple = quote! {
-            let mut datums: [usize; #numtypes] = [0; #numtypes];
-            let mut nulls: [bool; #numtypes] = [false; #numtypes];
-
-            // TODO:  how to detect that 'result.i' is an Option, and if it's none
-            //        set nulls[i] to true?
-            #(
-                let datum = result.#i.into_datum();
-                match datum {
-                    Some(datum) => { datums[#i] = datum as usize; },
-                    None => { nulls[#i] = true; }
-                }
-            )*
+        // a `TableRow` is matched up with the caller's column definition list as it's returned,
+        // rather than being a tuple of the columns
+        let (generic_type, create_heap_tuple) = if is_table_row(&types) {
+            let generic_type = proc_macro2::TokenStream::from_str(&types[0]).unwrap();
+            let generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
+            let create_heap_tuple = quote! {
+                let heap_tuple = result.into_heap_tuple(funcctx.tuple_desc);
+            };
+            (generic_type, create_heap_tuple)
+        } else {
+            let numtypes = types.len();
+            let i = (0..numtypes).map(syn::Index::from);
+            let create_heap_tuple = quote! {
+                let mut datums: [usize; #numtypes] = [0; #numtypes];
+                let mut nulls: [bool; #numtypes] = [false; #numtypes];
+
+                // TODO:  how to detect that 'result.i' is an Option, and if it's none
+                //        set nulls[i] to true?
+                #(
+                    let datum = result.#i.into_datum();
+                    match datum {
+                        Some(datum) => { datums[#i] = datum as usize; },
+                        None => { nulls[#i] = true; }
+                    }
+                )*
 
-            let heap_tuple = pgx::pg_sys::heap_form_tuple(funcctx.tuple_desc, datums.as_mut_ptr(), nulls.as_mut_ptr());
-        };
+                let heap_tuple = pgx::pg_sys::heap_form_tuple(funcctx.tuple_desc, datums.as_mut_ptr(), nulls.as_mut_ptr());
+            };
 
-        let composite_type = format!("({})", types.join(","));
-        let generic_type = proc_macro2::TokenStream::from_str(&composite_type).unwrap();
-        let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
-        crate::anonymonize_lifetimes(&mut generic_type);
+            let composite_type = format!("({})", types.join(","));
+            let generic_type = proc_macro2::TokenStream::from_str(&composite_type).unwrap();
+            let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
+            crate::anonymonize_lifetimes(&mut generic_type);
+            (generic_type, create_heap_tuple)
+        };
 
         let result_handler = if optional {
             quote! {
@@ -392,11 +493,12 @@ impl PgGuardRewriter {
                 let result = pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| { #func_call result });
             }
         };
+        let guard = PgGuardRewriter::guard_attributes(&func_name_wrapper, false);
         let sql_graph_entity_submission = entity_submission.cloned().into_iter();
 
         quote_spanned! {func_span=>
             #prolog
-            #[pg_guard]
+            #guard
             #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
 
                 struct IteratorHolder<T> {
@@ -429,13 +531,12 @@ impl PgGuardRewriter {
                 funcctx = pgx::srf_per_call_setup(fcinfo);
                 iterator_holder = pgx::PgBox::from_pg(funcctx.user_fctx as *mut IteratorHolder<#generic_type>);
 
-                let mut iter = Box::from_raw(iterator_holder.iter);
+                // the iterator is only borrowed, as it's dropped when the multi-call memory context
+                // is deleted, whether that's because it's done, or the scan ended early or is
+                // being rescanned
+                let iter = &mut *iterator_holder.iter;
                 match iter.next() {
                     Some(result) => {
-                        // we need to leak the boxed iterator so that it's not freed by rust and we can
-                        // continue to use it
-                        Box::leak(iter);
-
                         #create_heap_tuple
 
                         let datum = pgx::heap_tuple_get_datum(heap_tuple);
@@ -443,10 +544,6 @@ impl PgGuardRewriter {
                         datum as pgx::pg_sys::Datum
                     },
                     None => {
-                        // leak the iterator here too, even tho we're done, b/c our MemoryContextCallback
-                        // function is going to properly drop it for us
-                        Box::leak(iter);
-
                         pgx::srf_return_done(fcinfo, &mut funcctx);
                         pgx::pg_return_null(fcinfo)
                     },
@@ -490,18 +587,32 @@ impl PgGuardRewriter {
             quote! { pg_sys::guard::guard( || #func_name(#arg_list) ) }
         };
 
-        let prolog = if input_func_name == "__pgx_private_shmem_hook" {
-            // we do not want "no_mangle" on this function
-            quote! {}
-        } else if input_func_name == "_PG_init" || input_func_name == "_PG_fini" {
+        // an explicit `#[no_mangle]` or `#[export_name]` is for the outer function, not the inner one
+        let export = func
+            .attrs
+            .iter()
+            .position(|attr| attr.path.is_ident("no_mangle") || attr.path.is_ident("export_name"))
+            .map(|i| func.attrs.remove(i));
+
+        // only functions Postgres looks up by name need exporting from the library, and other
+        // symbols could clash with those of other extensions loaded into the same backend
+        let prolog = if input_func_name == "_PG_init" || input_func_name == "_PG_fini" {
             quote! {
                 #[allow(non_snake_case)]
                 #[no_mangle]
             }
-        } else {
+        } else if let Some(export) = export {
+            quote! {
+                #export
+            }
+        } else if input_func_name != "__pgx_private_shmem_hook"
+            && LibraryNaming::current().export_guarded_functions
+        {
             quote! {
                 #[no_mangle]
             }
+        } else {
+            quote! {}
         };
 
         let sql_graph_entity_submission = entity_submission.cloned().into_iter();
@@ -673,6 +784,27 @@ impl FunctionSignatureRewriter {
                             None => pgx::pg_return_null(fcinfo)
                         }
                     });
+                } else if type_matches(type_, "Result < ()")
+                    || type_matches(type_, "std :: result :: Result < ()")
+                {
+                    stream.extend(quote! {
+                        match result {
+                            Ok(()) => pgx::pg_return_void(),
+                            Err(e) => pgx::IntoErrorReport::into_error_report(e).raise(),
+                        }
+                    });
+                } else if type_matches(type_, "Result <")
+                    || type_matches(type_, "std :: result :: Result <")
+                {
+                    stream.extend(quote! {
+                        match result {
+                            Ok(result) => match result.into_datum() {
+                                Some(datum) => datum,
+                                None => pgx::pg_return_null(fcinfo)
+                            },
+                            Err(e) => pgx::IntoErrorReport::into_error_report(e).raise(),
+                        }
+                    });
                 } else if type_matches(type_, "pg_sys :: Datum") {
                     stream.extend(quote! {
                         result
@@ -716,6 +848,16 @@ impl FunctionSignatureRewriter {
                         let mut type_ = ty.ty.clone();
                         let is_option = type_matches(&type_, "Option");
 
+                        if type_matches(&type_, "WindowFunctionContext")
+                            || type_matches(&type_, "pgx :: WindowFunctionContext")
+                        {
+                            // not a SQL argument, so it doesn't take up an argument position
+                            stream.extend(quote_spanned! {ident.span()=>
+                                let #name = pgx::WindowFunctionContext::from_fcinfo(#fcinfo_ident);
+                            });
+                            continue;
+                        }
+
                         let ts = if is_option {
                             let option_type = extract_option_type(&type_);
                             let mut option_type = syn::parse2::<syn::Type>(option_type).unwrap();
@@ -788,3 +930,11 @@ fn extract_option_type(ty: &Type) -> proc_macro2::TokenStream {
         _ => panic!("No type found inside Option"),
     }
 }
+
+/// Is an iterator of these types an iterator of `pgx::TableRow`s?
+fn is_table_row(types: &[String]) -> bool {
+    match types {
+        [ty] => ty.rsplit("::").next().map(str::trim) == Some("TableRow"),
+        _ => false,
+    }
+}
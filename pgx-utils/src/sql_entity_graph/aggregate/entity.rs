@@ -213,7 +213,7 @@ impl ToSql for PgAggregateEntity {
         }
         if let Some(value) = self.deserialfunc {
             optional_attributes.push((
-                format!("\tDESERIALFUNC ={} \"{}\"", schema, value),
+                format!("\tDESERIALFUNC = {}\"{}\"", schema, value),
                 format!("/* {}::deserial */", self.full_path),
             ));
         }
@@ -388,23 +388,49 @@ impl PgAggregate {
             pg_externs.push(parse_quote! {
                 #[allow(non_snake_case, clippy::too_many_arguments)]
                 #pg_extern_attr
-                fn #fn_name(this: #type_state_without_self, buf: Vec<u8>, internal: pgx::PgBox<#type_state_without_self>, fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::PgBox<#type_state_without_self> {
+                fn #fn_name(buf: Vec<u8>, _internal: pgx::Internal, fcinfo: pgx::pg_sys::FunctionCallInfo) -> #type_state_without_self {
                     <#target_path as pgx::Aggregate>::in_memory_context(
                         fcinfo,
-                        move |_context| <#target_path as pgx::Aggregate>::deserial(this, buf, internal, fcinfo)
+                        move |_context| <#target_path as pgx::Aggregate>::deserial(buf, fcinfo)
                     )
                 }
             });
             Some(fn_name)
         } else {
             item_impl.items.push(parse_quote! {
-                fn deserial(current: #type_state_without_self, _buf: Vec<u8>, _internal: pgx::PgBox<#type_state_without_self>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::PgBox<#type_state_without_self> {
+                fn deserial(_buf: Vec<u8>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> #type_state_without_self {
                     unimplemented!("Call to deserial on an aggregate which does not support it.")
                 }
             });
             None
         };
 
+        // Postgres only moves partial states between parallel workers through these functions, so
+        // they only make sense together, and only alongside `combine`.
+        match (fn_serial, fn_deserial) {
+            (Some(found), None) | (None, Some(found)) => {
+                return Err(syn::Error::new(
+                    found.sig.ident.span(),
+                    "`#[pg_aggregate]` requires both `serial` and `deserial` be implemented, or neither.",
+                ))
+            }
+            (Some(found), Some(_)) => {
+                if fn_combine.is_none() {
+                    return Err(syn::Error::new(
+                        found.sig.ident.span(),
+                        "`#[pg_aggregate]` requires `combine` be implemented when using `serial` and `deserial`.",
+                    ));
+                }
+                if !is_internal_type(&type_state_without_self) {
+                    return Err(syn::Error::new(
+                        found.sig.ident.span(),
+                        "`#[pg_aggregate]` only supports `serial` and `deserial` when `State` is `Internal`.",
+                    ));
+                }
+            }
+            (None, None) => (),
+        }
+
         let fn_moving_state = get_impl_func_by_name(&item_impl_snapshot, "moving_state");
         let fn_moving_state_name = if let Some(found) = fn_moving_state {
             let fn_name = Ident::new(
@@ -874,6 +900,19 @@ fn remap_self_to_target(ty: &mut syn::Type, target: &syn::Ident) {
     }
 }
 
+fn is_internal_type(ty: &syn::Type) -> bool {
+    // We don't actually have type resolution here, this is a "Best guess".
+    match ty {
+        Type::Path(ty_path) => ty_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string() == "Internal")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
 fn get_pgx_attr_macro(attr_name: impl AsRef<str>, ty: &syn::Type) -> Option<TokenStream2> {
     match &ty {
         syn::Type::Macro(ty_macro) => {
@@ -936,7 +975,7 @@ mod tests {
         let tokens: ItemImpl = parse_quote! {
             #[pg_aggregate]
             impl Aggregate for DemoAgg {
-                type State = PgVarlena<Self>;
+                type State = Internal;
                 type Args = i32;
                 type OrderBy = i32;
                 type MovingState = i32;
@@ -966,7 +1005,7 @@ mod tests {
                     todo!()
                 }
 
-                fn deserial(current: Self::State, _buf: Vec<u8>, _internal: PgBox<Self>) -> PgBox<Self> {
+                fn deserial(_buf: Vec<u8>) -> Self::State {
                     todo!()
                 }
 
@@ -997,6 +1036,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn agg_serial_without_deserial() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl Aggregate for DemoAgg {
+                type State = Internal;
+                type Args = i32;
+
+                fn state(current: Self::State, v: Self::Args) -> Self::State {
+                    todo!()
+                }
+
+                fn combine(current: Self::State, _other: Self::State) -> Self::State {
+                    todo!()
+                }
+
+                fn serial(current: Self::State) -> Vec<u8> {
+                    todo!()
+                }
+            }
+        };
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn agg_serial_without_combine() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl Aggregate for DemoAgg {
+                type State = Internal;
+                type Args = i32;
+
+                fn state(current: Self::State, v: Self::Args) -> Self::State {
+                    todo!()
+                }
+
+                fn serial(current: Self::State) -> Vec<u8> {
+                    todo!()
+                }
+
+                fn deserial(_buf: Vec<u8>) -> Self::State {
+                    todo!()
+                }
+            }
+        };
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn agg_serial_without_internal_state() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl Aggregate for DemoAgg {
+                type State = PgVarlena<Self>;
+                type Args = i32;
+
+                fn state(current: Self::State, v: Self::Args) -> Self::State {
+                    todo!()
+                }
+
+                fn combine(current: Self::State, _other: Self::State) -> Self::State {
+                    todo!()
+                }
+
+                fn serial(current: Self::State) -> Vec<u8> {
+                    todo!()
+                }
+
+                fn deserial(_buf: Vec<u8>) -> Self::State {
+                    todo!()
+                }
+            }
+        };
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_err());
+        Ok(())
+    }
+
     #[test]
     fn agg_missing_required() -> Result<()> {
         // This is not valid as it is missing required types/consts.
@@ -64,15 +64,14 @@ impl ToSql for ExtensionSqlEntity {
     fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
         let sql = format!(
             "\n\
-                -- {file}:{line}\n\
+                {sql_anchor_comment}\n\
                 {bootstrap}\
                 {creates}\
                 {requires}\
                 {finalize}\
                 {sql}\
                 ",
-            file = self.file,
-            line = self.line,
+            sql_anchor_comment = self.sql_anchor_comment(),
             bootstrap = if self.bootstrap { "-- bootstrap\n" } else { "" },
             creates = if !self.creates.is_empty() {
                 format!(
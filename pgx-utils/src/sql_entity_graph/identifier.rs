@@ -0,0 +1,152 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+//! Checks for the names of generated SQL objects.
+
+/// Postgres' default `NAMEDATALEN`, identifiers are at most one byte shorter than this.
+pub const NAMEDATALEN: usize = 64;
+
+/// Keywords which Postgres reserves outright, so they can't be used as an unquoted name of
+/// anything.
+///
+/// See [the PostgreSQL keyword appendix](https://www.postgresql.org/docs/current/sql-keywords-appendix.html).
+pub const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "column",
+    "constraint",
+    "create",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "from",
+    "grant",
+    "group",
+    "having",
+    "in",
+    "initially",
+    "intersect",
+    "into",
+    "lateral",
+    "leading",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "not",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "select",
+    "session_user",
+    "some",
+    "symmetric",
+    "table",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// Is `name` a keyword Postgres reserves?  Unquoted names are case insensitive, so is this one.
+pub fn is_reserved_keyword(name: &str) -> bool {
+    RESERVED_KEYWORDS.contains(&name.to_lowercase().as_str())
+}
+
+/// Would `name` have to be quoted to be used as it is?
+///
+/// Unquoted identifiers are folded to lowercase, so any uppercase letter has to be quoted too.
+pub fn needs_quoting(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_ok = matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_lowercase());
+    !starts_ok
+        || !chars.all(|c| c == '_' || c == '$' || c.is_ascii_lowercase() || c.is_ascii_digit())
+        || is_reserved_keyword(name)
+}
+
+/// The name Postgres actually uses for `name`, which it silently truncates to fit in
+/// `NAMEDATALEN - 1` bytes, without splitting a character.
+pub fn truncate(name: &str) -> &str {
+    if name.len() < NAMEDATALEN {
+        return name;
+    }
+    let mut end = NAMEDATALEN - 1;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation() {
+        let long = "a".repeat(70);
+        assert_eq!(truncate(&long).len(), NAMEDATALEN - 1);
+        assert_eq!(truncate("short_name"), "short_name");
+        // a 2 byte character straddling the limit is dropped entirely
+        let multibyte = format!("{}é", "a".repeat(62));
+        assert_eq!(truncate(&multibyte), "a".repeat(62));
+    }
+
+    #[test]
+    fn quoting() {
+        assert!(!needs_quoting("my_function"));
+        assert!(!needs_quoting("_private$2"));
+        assert!(needs_quoting("MyFunction"));
+        assert!(needs_quoting("2fast"));
+        assert!(needs_quoting("has-dash"));
+        assert!(needs_quoting("select"));
+        assert!(is_reserved_keyword("User"));
+        assert!(!is_reserved_keyword("name"));
+    }
+}
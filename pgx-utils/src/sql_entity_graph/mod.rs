@@ -9,11 +9,16 @@ Use of this source code is governed by the MIT license that can be found in the
 pub(crate) mod aggregate;
 pub(crate) mod control_file;
 pub(crate) mod extension_sql;
+pub(crate) mod identifier;
 pub(crate) mod mapping;
+pub(crate) mod pg_event_trigger;
 pub(crate) mod pg_extern;
+pub(crate) mod pg_opclass;
+pub(crate) mod pg_trigger;
 pub(crate) mod pgx_attribute;
 pub(crate) mod pgx_sql;
 pub(crate) mod positioning_ref;
+pub(crate) mod postgres_composite;
 pub(crate) mod postgres_enum;
 pub(crate) mod postgres_hash;
 pub(crate) mod postgres_ord;
@@ -31,12 +36,23 @@ pub use extension_sql::{
     ExtensionSql, ExtensionSqlFile, SqlDeclared,
 };
 pub use mapping::{RustSourceOnlySqlMapping, RustSqlMapping};
+pub use pg_event_trigger::{entity::PgEventTriggerEntity, PgEventTrigger};
 pub use pg_extern::{
-    entity::{PgExternArgumentEntity, PgExternEntity, PgExternReturnEntity, PgOperatorEntity},
-    NameMacro, PgExtern, PgExternArgument, PgOperator,
+    entity::{
+        PgCastEntity, PgExternAccessCheck, PgExternArgumentEntity, PgExternEntity,
+        PgExternReturnEntity, PgOperatorEntity,
+    },
+    NameMacro, PgCast, PgCastArgs, PgExtern, PgExternArgument, PgOperator, PgOperatorArgs,
 };
+pub use pg_opclass::PgOpClass;
+pub use pg_trigger::{entity::PgTriggerEntity, PgTrigger};
+pub use pgx_attribute::{ArgValue, NameValueArg, PgxArg, PgxAttribute};
 pub use pgx_sql::PgxSql;
 pub use positioning_ref::PositioningRef;
+pub use postgres_composite::{
+    entity::{PostgresCompositeEntity, PostgresCompositeFieldEntity},
+    PostgresComposite,
+};
 pub use postgres_enum::{entity::PostgresEnumEntity, PostgresEnum};
 pub use postgres_hash::{entity::PostgresHashEntity, PostgresHash};
 pub use postgres_ord::{entity::PostgresOrdEntity, PostgresOrd};
@@ -64,25 +80,22 @@ pub trait SqlGraphIdentifier {
     fn file(&self) -> Option<&'static str>;
 
     fn line(&self) -> Option<u32>;
-}
 
-/// An entity corresponding to some SQL required by the extension.
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub enum SqlGraphEntity {
-    ExtensionRoot(ControlFile),
-    Schema(SchemaEntity),
-    CustomSql(ExtensionSqlEntity),
-    Function(PgExternEntity),
-    Type(PostgresTypeEntity),
-    BuiltinType(String),
-    Enum(PostgresEnumEntity),
-    Ord(PostgresOrdEntity),
-    Hash(PostgresHashEntity),
-    Aggregate(PgAggregateEntity),
-}
+    /// A stable identifier for the entity, derived from its [`SqlGraphIdentifier::dot_identifier`].
+    ///
+    /// This does not change between runs unless the entity is renamed or moved to another module.
+    fn entity_id(&self) -> String {
+        // FNV-1a, since the `std` hashers make no promises about stability between releases.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in self.dot_identifier().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
 
-impl SqlGraphEntity {
-    pub fn sql_anchor_comment(&self) -> String {
+    /// A SQL comment block identifying where the entity came from.
+    fn sql_anchor_comment(&self) -> String {
         let maybe_file_and_line = if let (Some(file), Some(line)) = (self.file(), self.line()) {
             format!("-- {file}:{line}\n", file = file, line = line)
         } else {
@@ -91,14 +104,34 @@ impl SqlGraphEntity {
         format!(
             "\
             {maybe_file_and_line}\
-            -- {rust_identifier}\
+            -- {rust_identifier}\n\
+            -- entity: {entity_id}\
         ",
             maybe_file_and_line = maybe_file_and_line,
             rust_identifier = self.rust_identifier(),
+            entity_id = self.entity_id(),
         )
     }
 }
 
+/// An entity corresponding to some SQL required by the extension.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SqlGraphEntity {
+    ExtensionRoot(ControlFile),
+    Schema(SchemaEntity),
+    CustomSql(ExtensionSqlEntity),
+    Function(PgExternEntity),
+    Type(PostgresTypeEntity),
+    BuiltinType(String),
+    Enum(PostgresEnumEntity),
+    Composite(PostgresCompositeEntity),
+    Ord(PostgresOrdEntity),
+    Hash(PostgresHashEntity),
+    Aggregate(PgAggregateEntity),
+    Trigger(PgTriggerEntity),
+    EventTrigger(PgEventTriggerEntity),
+}
+
 impl SqlGraphIdentifier for SqlGraphEntity {
     fn dot_identifier(&self) -> String {
         match self {
@@ -108,9 +141,12 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.dot_identifier(),
             SqlGraphEntity::BuiltinType(item) => format!("preexisting type {}", item),
             SqlGraphEntity::Enum(item) => item.dot_identifier(),
+            SqlGraphEntity::Composite(item) => item.dot_identifier(),
             SqlGraphEntity::Ord(item) => item.dot_identifier(),
             SqlGraphEntity::Hash(item) => item.dot_identifier(),
             SqlGraphEntity::Aggregate(item) => item.dot_identifier(),
+            SqlGraphEntity::Trigger(item) => item.dot_identifier(),
+            SqlGraphEntity::EventTrigger(item) => item.dot_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.dot_identifier(),
         }
     }
@@ -123,9 +159,12 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.rust_identifier(),
             SqlGraphEntity::BuiltinType(item) => item.to_string(),
             SqlGraphEntity::Enum(item) => item.rust_identifier(),
+            SqlGraphEntity::Composite(item) => item.rust_identifier(),
             SqlGraphEntity::Ord(item) => item.rust_identifier(),
             SqlGraphEntity::Hash(item) => item.rust_identifier(),
             SqlGraphEntity::Aggregate(item) => item.rust_identifier(),
+            SqlGraphEntity::Trigger(item) => item.rust_identifier(),
+            SqlGraphEntity::EventTrigger(item) => item.rust_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.rust_identifier(),
         }
     }
@@ -138,9 +177,12 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.file(),
             SqlGraphEntity::BuiltinType(_item) => None,
             SqlGraphEntity::Enum(item) => item.file(),
+            SqlGraphEntity::Composite(item) => item.file(),
             SqlGraphEntity::Ord(item) => item.file(),
             SqlGraphEntity::Hash(item) => item.file(),
             SqlGraphEntity::Aggregate(item) => item.file(),
+            SqlGraphEntity::Trigger(item) => item.file(),
+            SqlGraphEntity::EventTrigger(item) => item.file(),
             SqlGraphEntity::ExtensionRoot(item) => item.file(),
         }
     }
@@ -153,9 +195,12 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.line(),
             SqlGraphEntity::BuiltinType(_item) => None,
             SqlGraphEntity::Enum(item) => item.line(),
+            SqlGraphEntity::Composite(item) => item.line(),
             SqlGraphEntity::Ord(item) => item.line(),
             SqlGraphEntity::Hash(item) => item.line(),
             SqlGraphEntity::Aggregate(item) => item.line(),
+            SqlGraphEntity::Trigger(item) => item.line(),
+            SqlGraphEntity::EventTrigger(item) => item.line(),
             SqlGraphEntity::ExtensionRoot(item) => item.line(),
         }
     }
@@ -180,7 +225,7 @@ impl ToSql for SqlGraphEntity {
                 if context.graph.neighbors_undirected(context.externs.get(item).unwrap().clone()).any(|neighbor| {
                     let neighbor_item = &context.graph[neighbor];
                     match neighbor_item {
-                        SqlGraphEntity::Type(PostgresTypeEntity { in_fn, in_fn_module_path, out_fn, out_fn_module_path, .. }) => {
+                        SqlGraphEntity::Type(PostgresTypeEntity { in_fn, in_fn_module_path, out_fn, out_fn_module_path, send_fn, recv_fn, module_path, .. }) => {
                             let is_in_fn = item.full_path.starts_with(in_fn_module_path) && item.full_path.ends_with(in_fn);
                             if is_in_fn {
                                 tracing::trace!(r#type = %neighbor_item.dot_identifier(), "Skipping, is an in_fn.");
@@ -189,7 +234,14 @@ impl ToSql for SqlGraphEntity {
                             if is_out_fn {
                                 tracing::trace!(r#type = %neighbor_item.dot_identifier(), "Skipping, is an out_fn.");
                             }
-                            is_in_fn || is_out_fn
+                            let is_send_recv_fn = [send_fn, recv_fn].iter().any(|func| match func {
+                                Some(func) => item.full_path == format!("{}::{}", module_path, func),
+                                None => false,
+                            });
+                            if is_send_recv_fn {
+                                tracing::trace!(r#type = %neighbor_item.dot_identifier(), "Skipping, is a send or receive fn.");
+                            }
+                            is_in_fn || is_out_fn || is_send_recv_fn
                         },
                         _ => false,
                     }
@@ -208,6 +260,10 @@ impl ToSql for SqlGraphEntity {
                 .to_sql_config
                 .to_sql(self, context)
                 .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::Composite(item) => item
+                .to_sql_config
+                .to_sql(self, context)
+                .unwrap_or_else(|| item.to_sql(context)),
             SqlGraphEntity::Ord(item) => item
                 .to_sql_config
                 .to_sql(self, context)
@@ -220,6 +276,14 @@ impl ToSql for SqlGraphEntity {
                 .to_sql_config
                 .to_sql(self, context)
                 .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::Trigger(item) => item
+                .to_sql_config
+                .to_sql(self, context)
+                .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::EventTrigger(item) => item
+                .to_sql_config
+                .to_sql(self, context)
+                .unwrap_or_else(|| item.to_sql(context)),
             SqlGraphEntity::ExtensionRoot(item) => item.to_sql(context),
         }
     }
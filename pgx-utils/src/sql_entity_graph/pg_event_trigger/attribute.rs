@@ -0,0 +1,81 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{pgx_attribute::ArgValue, to_sql::ToSqlConfig};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Lit, LitStr, Token,
+};
+
+/// The events an event trigger can fire on
+pub(crate) const EVENTS: &[&str] = &[
+    "ddl_command_start",
+    "ddl_command_end",
+    "sql_drop",
+    "table_rewrite",
+];
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum PgEventTriggerAttribute {
+    Event(LitStr),
+    Tags(Vec<LitStr>),
+    Name(LitStr),
+    Sql(ToSqlConfig),
+}
+
+impl Parse for PgEventTriggerAttribute {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let ident: syn::Ident = input.parse()?;
+        let found = match ident.to_string().as_str() {
+            "event" => {
+                let _eq: Token![=] = input.parse()?;
+                let event: LitStr = input.parse()?;
+                if !EVENTS.contains(&event.value().as_str()) {
+                    return Err(syn::Error::new(
+                        event.span(),
+                        format!("`event` must be one of {}", EVENTS.join(", ")),
+                    ));
+                }
+                Self::Event(event)
+            }
+            "tags" => {
+                let _eq: Token![=] = input.parse()?;
+                let content;
+                let _bracket = syn::bracketed!(content in input);
+                let tags = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                Self::Tags(tags.into_iter().collect())
+            }
+            "name" => {
+                let _eq: Token![=] = input.parse()?;
+                Self::Name(input.parse()?)
+            }
+            "sql" => {
+                let _eq: Token![=] = input.parse()?;
+                match input.parse::<ArgValue>()? {
+                    ArgValue::Path(p) => Self::Sql(ToSqlConfig::from(p)),
+                    ArgValue::Lit(Lit::Bool(b)) => Self::Sql(ToSqlConfig::from(b.value)),
+                    ArgValue::Lit(Lit::Str(s)) => Self::Sql(ToSqlConfig::from(s)),
+                    ArgValue::Lit(other) => {
+                        return Err(syn::Error::new(
+                            other.span(),
+                            "expected boolean, path, or string literal",
+                        ))
+                    }
+                }
+            }
+            e => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("Invalid option `{}` inside `#[pg_event_trigger]`", e),
+                ))
+            }
+        };
+        Ok(found)
+    }
+}
@@ -0,0 +1,106 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    pgx_sql::PgxSql,
+    to_sql::{entity::ToSqlConfigEntity, ToSql},
+    SqlGraphEntity, SqlGraphIdentifier,
+};
+use std::cmp::Ordering;
+
+/// The output of a [`PgEventTrigger`](crate::sql_entity_graph::pg_event_trigger::PgEventTrigger) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PgEventTriggerEntity {
+    pub fn_name: &'static str,
+    pub name: &'static str,
+    pub event: &'static str,
+    pub tags: Vec<&'static str>,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl Ord for PgEventTriggerEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+    }
+}
+
+impl PartialOrd for PgEventTriggerEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Into<SqlGraphEntity> for PgEventTriggerEntity {
+    fn into(self) -> SqlGraphEntity {
+        SqlGraphEntity::EventTrigger(self)
+    }
+}
+
+impl SqlGraphIdentifier for PgEventTriggerEntity {
+    fn dot_identifier(&self) -> String {
+        format!("event trigger {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PgEventTriggerEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.event_triggers[self];
+        let schema_prefix = context.schema_prefix_for(&self_index);
+        let sql = format!(
+            "\n\
+            {sql_anchor_comment}\n\
+            CREATE FUNCTION {schema_prefix}\"{fn_name}\"()\n\
+                \tRETURNS event_trigger\n\
+                \tLANGUAGE c /* Rust */\n\
+                \tAS '{module_pathname}', '{symbol}';\n\
+            CREATE EVENT TRIGGER \"{name}\" ON {event}\n\
+                {when_tag}\
+                \tEXECUTE PROCEDURE {schema_prefix}\"{fn_name}\"();\
+            ",
+            sql_anchor_comment = self.sql_anchor_comment(),
+            schema_prefix = schema_prefix,
+            fn_name = self.fn_name,
+            module_pathname = context.get_module_pathname(),
+            symbol = context.get_symbol_name(&format!("{}_wrapper", self.fn_name)),
+            name = self.name,
+            event = self.event,
+            when_tag = if self.tags.is_empty() {
+                String::default()
+            } else {
+                format!(
+                    "\tWHEN TAG IN ({})\n",
+                    self.tags
+                        .iter()
+                        .map(|tag| format!("'{}'", tag.replace('\'', "''")))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            },
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
@@ -0,0 +1,261 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+mod attribute;
+pub mod entity;
+
+use crate::sql_entity_graph::ToSqlConfig;
+use crate::LibraryNaming;
+use attribute::{PgEventTriggerAttribute, EVENTS};
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{parse::Parser, punctuated::Punctuated, LitStr, Token};
+
+/// A parsed `#[pg_event_trigger]` item.
+///
+/// It should be created with [`PgEventTrigger::new`], as the attribute arguments are required.
+///
+/// Using [`quote::ToTokens`] will output the function, its `extern "C"` wrapper, and the
+/// declaration for a [`PgEventTriggerEntity`][crate::sql_entity_graph::PgEventTriggerEntity].
+///
+/// ```rust
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgEventTrigger;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed = PgEventTrigger::new(
+///     quote! { event = "ddl_command_end", tags = ["CREATE TABLE"] },
+///     quote! {
+///         fn example(trigger: &PgEventTrigger) {
+///             unimplemented!()
+///         }
+///     },
+/// )?;
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgEventTrigger {
+    func: syn::ItemFn,
+    event: LitStr,
+    tags: Vec<LitStr>,
+    name: Option<LitStr>,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgEventTrigger {
+    pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<Self, syn::Error> {
+        let parser = Punctuated::<PgEventTriggerAttribute, Token![,]>::parse_terminated;
+        let attrs = parser.parse2(attr)?;
+        let func = syn::parse2::<syn::ItemFn>(item)?;
+
+        let mut event = None;
+        let mut tags = Vec::new();
+        let mut name = None;
+        let mut to_sql_config = None;
+        for attr in attrs {
+            match attr {
+                PgEventTriggerAttribute::Event(value) => {
+                    event.get_or_insert(value);
+                }
+                PgEventTriggerAttribute::Tags(value) => tags.extend(value),
+                PgEventTriggerAttribute::Name(value) => {
+                    name.get_or_insert(value);
+                }
+                PgEventTriggerAttribute::Sql(config) => {
+                    to_sql_config.get_or_insert(config);
+                }
+            }
+        }
+
+        let event = event.ok_or_else(|| {
+            syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "`#[pg_event_trigger]` requires an `event`, one of {}",
+                    EVENTS.join(", ")
+                ),
+            )
+        })?;
+
+        if func.sig.inputs.len() != 1 {
+            return Err(syn::Error::new_spanned(
+                &func.sig.inputs,
+                "`#[pg_event_trigger]` functions take exactly one argument, a `&pgx::PgEventTrigger`",
+            ));
+        }
+        match &func.sig.output {
+            syn::ReturnType::Default => (),
+            syn::ReturnType::Type(_, ty) => match ty.as_ref() {
+                syn::Type::Tuple(tuple) if tuple.elems.is_empty() => (),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "`#[pg_event_trigger]` functions cannot return a value",
+                    ))
+                }
+            },
+        }
+
+        Ok(Self {
+            func,
+            event,
+            tags,
+            name,
+            to_sql_config: to_sql_config.unwrap_or_default(),
+        })
+    }
+
+    fn wrapper_tokens(&self) -> TokenStream2 {
+        let ident = &self.func.sig.ident;
+        let wrapper_ident = syn::Ident::new(&format!("{}_wrapper", ident), ident.span());
+        let finfo_ident = syn::Ident::new(&format!("pg_finfo_{}_wrapper", ident), ident.span());
+        let naming = LibraryNaming::current();
+        let wrapper_export = naming.export_attribute(&wrapper_ident.to_string());
+        let finfo_export = naming.finfo_export_attribute(&wrapper_ident.to_string());
+        quote! {
+            #[pgx::pg_guard]
+            #wrapper_export
+            pub unsafe extern "C" fn #wrapper_ident(fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::pg_sys::Datum {
+                let trigger = pgx::PgEventTrigger::from_fcinfo(fcinfo);
+                #ident(&trigger);
+                0
+            }
+
+            #finfo_export
+            #[doc(hidden)]
+            pub extern "C" fn #finfo_ident() -> &'static pgx::pg_sys::Pg_finfo_record {
+                const V1_API: pgx::pg_sys::Pg_finfo_record = pgx::pg_sys::Pg_finfo_record { api_version: 1 };
+                &V1_API
+            }
+        }
+    }
+
+    fn entity_tokens(&self) -> TokenStream2 {
+        let ident = &self.func.sig.ident;
+        let event = &self.event;
+        let tags = &self.tags;
+        let name = match &self.name {
+            Some(name) => quote! { #name },
+            None => quote! { stringify!(#ident) },
+        };
+        let to_sql_config = &self.to_sql_config;
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_event_trigger_{}", ident),
+            Span::call_site(),
+        );
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                use core::any::TypeId;
+                extern crate alloc;
+                use alloc::vec::Vec;
+                use alloc::vec;
+                let submission = ::pgx::utils::sql_entity_graph::PgEventTriggerEntity {
+                    fn_name: stringify!(#ident),
+                    name: #name,
+                    event: #event,
+                    tags: vec![#(#tags),*],
+                    file: file!(),
+                    line: line!(),
+                    module_path: core::module_path!(),
+                    full_path: concat!(core::module_path!(), "::", stringify!(#ident)),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::EventTrigger(submission)
+            }
+        }
+    }
+}
+
+impl ToTokens for PgEventTrigger {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let func = &self.func;
+        let wrapper = self.wrapper_tokens();
+        let entity = self.entity_tokens();
+        tokens.append_all(quote! {
+            #func
+            #wrapper
+            #entity
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgEventTrigger;
+    use quote::quote;
+
+    #[test]
+    fn event_trigger_with_tags() {
+        let parsed = PgEventTrigger::new(
+            quote! { event = "ddl_command_end", tags = ["CREATE TABLE", "ALTER TABLE"], name = "on_ddl" },
+            quote! {
+                fn example(trigger: &PgEventTrigger) {
+                    unimplemented!()
+                }
+            },
+        )
+        .expect("event trigger should parse");
+        assert_eq!(parsed.event.value(), "ddl_command_end");
+        assert_eq!(
+            parsed
+                .tags
+                .iter()
+                .map(|tag| tag.value())
+                .collect::<Vec<_>>(),
+            vec!["CREATE TABLE", "ALTER TABLE"]
+        );
+        assert_eq!(
+            parsed.name.map(|name| name.value()),
+            Some("on_ddl".to_string())
+        );
+    }
+
+    #[test]
+    fn event_trigger_without_event() {
+        let parsed = PgEventTrigger::new(
+            quote! {},
+            quote! {
+                fn example(trigger: &PgEventTrigger) {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn event_trigger_with_unknown_event() {
+        let parsed = PgEventTrigger::new(
+            quote! { event = "ddl_command_middle" },
+            quote! {
+                fn example(trigger: &PgEventTrigger) {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn event_trigger_with_return_value() {
+        let parsed = PgEventTrigger::new(
+            quote! { event = "sql_drop" },
+            quote! {
+                fn example(trigger: &PgEventTrigger) -> i32 {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+}
@@ -0,0 +1,48 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens, TokenStreamExt};
+
+/// The `require_role!("role")` or `require_superuser!()` a function starts with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AccessCheck {
+    Role(syn::LitStr),
+    Superuser,
+}
+
+impl AccessCheck {
+    /// The check the first statement of `func` makes, if it's one of the macros.  Only a literal
+    /// role name is known, and a check after the function has done something else doesn't count.
+    pub(crate) fn of(func: &syn::ItemFn) -> Option<AccessCheck> {
+        let mac = match func.block.stmts.first()? {
+            syn::Stmt::Item(syn::Item::Macro(item)) => &item.mac,
+            syn::Stmt::Semi(syn::Expr::Macro(expr), _)
+            | syn::Stmt::Expr(syn::Expr::Macro(expr)) => &expr.mac,
+            _ => return None,
+        };
+        match mac.path.segments.last()?.ident.to_string().as_str() {
+            "require_role" => mac.parse_body::<syn::LitStr>().ok().map(AccessCheck::Role),
+            "require_superuser" if mac.tokens.is_empty() => Some(AccessCheck::Superuser),
+            _ => None,
+        }
+    }
+}
+
+impl ToTokens for AccessCheck {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.append_all(match self {
+            AccessCheck::Role(role) => quote! {
+                ::pgx::utils::sql_entity_graph::PgExternAccessCheck::Role(#role)
+            },
+            AccessCheck::Superuser => quote! {
+                ::pgx::utils::sql_entity_graph::PgExternAccessCheck::Superuser
+            },
+        })
+    }
+}
@@ -27,6 +27,24 @@ pub struct PgExternArgument {
 }
 
 impl PgExternArgument {
+    /// Can this argument be `NULL`, because it is an `Option`?
+    pub fn is_optional(&self) -> bool {
+        match &self.ty {
+            syn::Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident == "Option")
+                .unwrap_or_default(),
+            _ => false,
+        }
+    }
+
+    /// Does this argument have a `default!()` value?
+    pub fn has_default(&self) -> bool {
+        self.default.is_some()
+    }
+
     pub fn build(value: FnArg) -> Result<Option<Self>, syn::Error> {
         match value {
             syn::FnArg::Typed(pat) => Self::build_from_pat_type(pat),
@@ -86,7 +104,7 @@ impl PgExternArgument {
             _ => None,
         };
 
-        // We special case ignore `*mut pg_sys::FunctionCallInfoData`
+        // We special case ignore `*mut pg_sys::FunctionCallInfoData` and `WindowFunctionContext`
         match true_ty {
             syn::Type::Reference(ref mut ty_ref) => {
                 if let Some(ref mut lifetime) = &mut ty_ref.lifetime {
@@ -110,6 +128,14 @@ impl PgExternArgument {
                     || (saw_functioncallinfobasedata && segments.segments.len() == 1)
                 {
                     return Ok(None);
+                } else if segments
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident == "WindowFunctionContext")
+                    .unwrap_or_default()
+                {
+                    // It's built from the `fcinfo` of a window function, skipping
+                    return Ok(None);
                 } else {
                     for segment in &mut path.path.segments {
                         match &mut segment.arguments {
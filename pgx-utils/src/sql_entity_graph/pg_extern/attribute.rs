@@ -23,15 +23,25 @@ pub enum Attribute {
     Volatile,
     Raw,
     NoGuard,
+    LogSlow,
+    PrimaryOnly,
+    SpiReadOnly,
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
+    CheckVolatility,
     Error(syn::LitStr),
     Schema(syn::LitStr),
     Name(syn::LitStr),
     Cost(syn::Expr),
+    Rows(syn::Expr),
+    Support(syn::Path),
     Requires(Punctuated<PositioningRef, Token![,]>),
     Sql(ToSqlConfig),
+    Language(syn::LitStr),
+    Body(syn::LitStr),
+    RenamedFrom(syn::LitStr),
 }
 
 impl Attribute {
@@ -43,6 +53,9 @@ impl Attribute {
             Attribute::Volatile => quote! { ::pgx::utils::ExternArgs::Volatile },
             Attribute::Raw => quote! { ::pgx::utils::ExternArgs::Raw },
             Attribute::NoGuard => quote! { ::pgx::utils::ExternArgs::NoGuard },
+            Attribute::LogSlow => quote! { ::pgx::utils::ExternArgs::LogSlow },
+            Attribute::PrimaryOnly => quote! { ::pgx::utils::ExternArgs::PrimaryOnly },
+            Attribute::SpiReadOnly => quote! { ::pgx::utils::ExternArgs::SpiReadOnly },
             Attribute::ParallelSafe => {
                 quote! { ::pgx::utils::ExternArgs::ParallelSafe }
             }
@@ -52,6 +65,9 @@ impl Attribute {
             Attribute::ParallelRestricted => {
                 quote! { ::pgx::utils::ExternArgs::ParallelRestricted }
             }
+            Attribute::Window => quote! { ::pgx::utils::ExternArgs::Window },
+            // Only checked when the function is parsed
+            Attribute::CheckVolatility => quote! {},
             Attribute::Error(s) => {
                 quote! { ::pgx::utils::ExternArgs::Error(String::from(#s)) }
             }
@@ -64,6 +80,14 @@ impl Attribute {
             Attribute::Cost(s) => {
                 quote! { ::pgx::utils::ExternArgs::Cost(format!("{}", #s)) }
             }
+            Attribute::Rows(s) => {
+                quote! { ::pgx::utils::ExternArgs::Rows(format!("{}", #s)) }
+            }
+            Attribute::Support(path) => {
+                let support =
+                    PositioningRef::FullPath(path.to_token_stream().to_string().replace(" ", ""));
+                quote! { ::pgx::utils::ExternArgs::Support(#support) }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -71,8 +95,11 @@ impl Attribute {
                     .collect::<Vec<_>>();
                 quote! { ::pgx::utils::ExternArgs::Requires(vec![#(#items_iter),*],) }
             }
-            // This attribute is handled separately
-            Attribute::Sql(_) => {
+            // These attributes are handled separately
+            Attribute::Sql(_)
+            | Attribute::Language(_)
+            | Attribute::Body(_)
+            | Attribute::RenamedFrom(_) => {
                 quote! {}
             }
         }
@@ -88,6 +115,9 @@ impl ToTokens for Attribute {
             Attribute::Volatile => quote! { volatile },
             Attribute::Raw => quote! { raw },
             Attribute::NoGuard => quote! { no_guard },
+            Attribute::LogSlow => quote! { log_slow },
+            Attribute::PrimaryOnly => quote! { primary_only },
+            Attribute::SpiReadOnly => quote! { spi_read_only },
             Attribute::ParallelSafe => {
                 quote! { parallel_safe }
             }
@@ -97,6 +127,8 @@ impl ToTokens for Attribute {
             Attribute::ParallelRestricted => {
                 quote! { parallel_restricted }
             }
+            Attribute::Window => quote! { window },
+            Attribute::CheckVolatility => quote! { check_volatility },
             Attribute::Error(s) => {
                 quote! { error = #s }
             }
@@ -109,6 +141,12 @@ impl ToTokens for Attribute {
             Attribute::Cost(s) => {
                 quote! { cost = #s }
             }
+            Attribute::Rows(s) => {
+                quote! { rows = #s }
+            }
+            Attribute::Support(path) => {
+                quote! { support = #path }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -120,6 +158,15 @@ impl ToTokens for Attribute {
             Attribute::Sql(to_sql_config) => {
                 quote! { sql = #to_sql_config }
             }
+            Attribute::Language(s) => {
+                quote! { language = #s }
+            }
+            Attribute::Body(s) => {
+                quote! { body = #s }
+            }
+            Attribute::RenamedFrom(s) => {
+                quote! { renamed_from = #s }
+            }
         };
         tokens.append_all(quoted);
     }
@@ -135,9 +182,14 @@ impl Parse for Attribute {
             "volatile" => Self::Volatile,
             "raw" => Self::Raw,
             "no_guard" => Self::NoGuard,
+            "log_slow" => Self::LogSlow,
+            "primary_only" => Self::PrimaryOnly,
+            "spi_read_only" => Self::SpiReadOnly,
             "parallel_safe" => Self::ParallelSafe,
             "parallel_unsafe" => Self::ParallelUnsafe,
             "parallel_restricted" => Self::ParallelRestricted,
+            "window" => Self::Window,
+            "check_volatility" => Self::CheckVolatility,
             "error" => {
                 let _eq: Token![=] = input.parse()?;
                 let literal: syn::LitStr = input.parse()?;
@@ -158,12 +210,37 @@ impl Parse for Attribute {
                 let literal: syn::Expr = input.parse()?;
                 Self::Cost(literal)
             }
+            "rows" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::Expr = input.parse()?;
+                Self::Rows(literal)
+            }
+            "support" => {
+                let _eq: Token![=] = input.parse()?;
+                let path: syn::Path = input.parse()?;
+                Self::Support(path)
+            }
             "requires" => {
                 let _eq: syn::token::Eq = input.parse()?;
                 let content;
                 let _bracket = syn::bracketed!(content in input);
                 Self::Requires(content.parse_terminated(PositioningRef::parse)?)
             }
+            "language" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::LitStr = input.parse()?;
+                Self::Language(literal)
+            }
+            "body" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::LitStr = input.parse()?;
+                Self::Body(literal)
+            }
+            "renamed_from" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::LitStr = input.parse()?;
+                Self::RenamedFrom(literal)
+            }
             "sql" => {
                 use crate::sql_entity_graph::pgx_attribute::ArgValue;
                 use syn::Lit;
@@ -0,0 +1,141 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::returning::Returning;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens, TokenStreamExt};
+use std::convert::TryFrom;
+use syn::parse::{Parse, ParseBuffer, Parser};
+
+/// Where Postgres applies a `#[pg_cast]` cast without it being written out, from
+/// `#[pg_cast(implicit)]`, `#[pg_cast(assignment)]` or `#[pg_cast(explicit)]`.
+///
+/// It is created during [`PgExtern`](crate::sql_entity_graph::PgExtern) parsing, from the
+/// `#[cast_context(..)]` attribute `#[pg_cast]` leaves on the function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgCast {
+    Explicit,
+    Assignment,
+    Implicit,
+}
+
+impl PgCast {
+    fn from_ident(ident: &syn::Ident) -> Option<Self> {
+        match ident.to_string().as_str() {
+            "explicit" => Some(PgCast::Explicit),
+            "assignment" => Some(PgCast::Assignment),
+            "implicit" => Some(PgCast::Implicit),
+            _ => None,
+        }
+    }
+
+    /// The context of a function's `#[cast_context(..)]`, if it has one
+    pub(crate) fn of(func: &syn::ItemFn) -> Result<Option<Self>, syn::Error> {
+        let attr = match func.attrs.iter().find(|attr| {
+            attr.path
+                .segments
+                .last()
+                .map(|segment| segment.ident == "cast_context")
+                .unwrap_or_default()
+        }) {
+            Some(attr) => attr,
+            None => return Ok(None),
+        };
+        let ident = attr.parse_args::<syn::Ident>()?;
+        match PgCast::from_ident(&ident) {
+            Some(cast) => Ok(Some(cast)),
+            None => Err(syn::Error::new_spanned(
+                ident,
+                "expected `implicit`, `assignment` or `explicit`",
+            )),
+        }
+    }
+}
+
+impl ToTokens for PgCast {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.append_all(match self {
+            PgCast::Explicit => quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Explicit },
+            PgCast::Assignment => {
+                quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Assignment }
+            }
+            PgCast::Implicit => quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Implicit },
+        })
+    }
+}
+
+/// The arguments of `#[pg_cast(implicit, immutable, parallel_safe)]`.
+///
+/// The cast's context is turned into the `#[cast_context]` attribute
+/// [`PgExtern`](crate::sql_entity_graph::PgExtern) reads it from, and everything else is left for
+/// `#[pg_extern]`.
+#[derive(Debug, Clone, Default)]
+pub struct PgCastArgs {
+    pub extern_args: TokenStream2,
+    pub attrs: Vec<syn::Attribute>,
+}
+
+impl Parse for PgCastArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::Error> {
+        let mut args = PgCastArgs::default();
+        let mut context = None;
+        let tokens = input
+            .parse::<TokenStream2>()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        for segment in
+            tokens.split(|tt| matches!(tt, TokenTree::Punct(punct) if punct.as_char() == ','))
+        {
+            match segment {
+                [] => (),
+                [TokenTree::Ident(ident)] if PgCast::from_ident(ident).is_some() => {
+                    if context.is_some() {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            "a cast is one of `implicit`, `assignment` or `explicit`",
+                        ));
+                    }
+                    context = Some(ident.clone());
+                }
+                _ => {
+                    if !args.extern_args.is_empty() {
+                        args.extern_args.append_all(quote! { , });
+                    }
+                    args.extern_args.extend(segment.iter().cloned());
+                }
+            }
+        }
+        let context = context.unwrap_or_else(|| syn::Ident::new("explicit", input.span()));
+        args.attrs = syn::Attribute::parse_outer.parse2(quote! {
+            #[::pgx::cast_context(#context)]
+        })?;
+        Ok(args)
+    }
+}
+
+/// `CREATE CAST` takes a function of the value to cast, optionally followed by the type modifier
+/// of the type it's cast to and whether the cast is explicit, which returns the type it's cast to.
+pub(crate) fn check_cast(func: &syn::ItemFn) -> Result<(), syn::Error> {
+    if PgCast::of(func)?.is_none() {
+        return Ok(());
+    }
+    if func.sig.inputs.is_empty() || func.sig.inputs.len() > 3 {
+        return Err(syn::Error::new_spanned(
+            &func.sig.inputs,
+            "a `#[pg_cast]` function takes the value to cast, \
+                and optionally the `i32` type modifier and `bool` explicit flag",
+        ));
+    }
+    match Returning::try_from(&func.sig.output) {
+        Ok(Returning::Type(_)) | Err(_) => Ok(()),
+        Ok(_) => Err(syn::Error::new_spanned(
+            &func.sig.output,
+            "a `#[pg_cast]` function returns the value it casts to",
+        )),
+    }
+}
@@ -17,7 +17,7 @@ pub use returning::PgExternReturnEntity;
 use crate::{
     sql_entity_graph::{
         extension_sql::SqlDeclared,
-        pgx_sql::PgxSql,
+        pgx_sql::{find_positioning_ref_target, PgxSql},
         to_sql::{entity::ToSqlConfigEntity, ToSql},
         SqlGraphEntity, SqlGraphIdentifier,
     },
@@ -42,9 +42,49 @@ pub struct PgExternEntity {
     pub fn_args: Vec<PgExternArgumentEntity>,
     pub fn_return: PgExternReturnEntity,
     pub operator: Option<PgOperatorEntity>,
+    /// The context of the `CREATE CAST` using the function, from `#[pg_cast]`
+    pub cast: Option<PgCastEntity>,
+    /// The `LANGUAGE` of the `body`, if the function isn't the Rust one.
+    pub language: Option<&'static str>,
+    /// Source of the function in `language`, which Postgres calls instead of the Rust function.
+    pub body: Option<&'static str>,
+    /// The SQL name the function had before, which an upgrade script renames it from
+    pub renamed_from: Option<&'static str>,
+    /// The `require_role!()` or `require_superuser!()` the function starts with
+    pub access_check: Option<PgExternAccessCheck>,
     pub to_sql_config: ToSqlConfigEntity,
 }
 
+/// Who a function checks it's called by before it does anything, so it's safe for `PUBLIC` to be
+/// able to execute it, as Postgres lets them by default
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PgExternAccessCheck {
+    /// A role with the privileges of this one, from `require_role!("role")`
+    Role(&'static str),
+    /// A superuser, from `require_superuser!()`
+    Superuser,
+}
+
+impl core::fmt::Display for PgExternAccessCheck {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PgExternAccessCheck::Role(role) => write!(f, "the privileges of role {}", role),
+            PgExternAccessCheck::Superuser => write!(f, "a superuser"),
+        }
+    }
+}
+
+/// Where Postgres applies a cast without it being written out as `CAST(x AS type)` or `x::type`
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PgCastEntity {
+    /// Nowhere, the default
+    Explicit,
+    /// `AS ASSIGNMENT`, when the value is assigned to a column, as in an `INSERT` or `UPDATE`
+    Assignment,
+    /// `AS IMPLICIT`, in any expression, as well as on assignment
+    Implicit,
+}
+
 impl Ord for PgExternEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
@@ -82,13 +122,9 @@ impl SqlGraphIdentifier for PgExternEntity {
     }
 }
 
-impl ToSql for PgExternEntity {
-    #[tracing::instrument(
-        level = "error",
-        skip(self, context),
-        fields(identifier = %self.rust_identifier()),
-    )]
-    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+impl PgExternEntity {
+    /// The `CREATE FUNCTION`, without the operator of an operator function
+    fn function_sql(&self, context: &PgxSql) -> eyre::Result<String> {
         let self_index = context.externs[self];
         let mut extern_attrs = self.extern_attrs.clone();
         // if we already have a STRICT marker we do not need to add it
@@ -106,29 +142,44 @@ impl ToSql for PgExternEntity {
         }
 
         let module_pathname = &context.get_module_pathname();
+        let language_and_body = match (self.language, self.body) {
+            (Some(language), Some(body)) => format!("LANGUAGE {}\nAS $pgx${}$pgx$", language, body),
+            _ => format!(
+                "LANGUAGE c /* Rust */\nAS '{}', '{}'",
+                module_pathname,
+                context.get_symbol_name(&format!("{}_wrapper", self.unaliased_name))
+            ),
+        };
 
-        let fn_sql = format!("\
+        let fn_sql = format!(
+            "\
                                 CREATE FUNCTION {schema}\"{name}\"({arguments}) {returns}\n\
                                 {extern_attrs}\
+                                {support}\
                                 {search_path}\
-                                LANGUAGE c /* Rust */\n\
-                                AS '{module_pathname}', '{unaliased_name}_wrapper';\
+                                {language_and_body};\
                             ",
-                             schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
-                             name = self.name,
-                             unaliased_name = self.unaliased_name,
-                             module_pathname = module_pathname,
-                             arguments = if !self.fn_args.is_empty() {
-                                 let mut args = Vec::new();
-                                 for (idx, arg) in self.fn_args.iter().enumerate() {
-                                     let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
-                                         SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
-                                         SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
-                                         SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
-                                         _ => false,
-                                     }).ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
-                                     let needs_comma = idx < (self.fn_args.len() - 1);
-                                     let buf = format!("\
+            schema = self
+                .schema
+                .map(|schema| format!("{}.", schema))
+                .unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+            name = self.name,
+            language_and_body = language_and_body,
+            arguments = if !self.fn_args.is_empty() {
+                let mut args = Vec::new();
+                for (idx, arg) in self.fn_args.iter().enumerate() {
+                    let graph_index = context
+                        .graph
+                        .neighbors_undirected(self_index)
+                        .find(|neighbor| match &context.graph[*neighbor] {
+                            SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
+                            SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
+                            SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
+                            _ => false,
+                        })
+                        .ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
+                    let needs_comma = idx < (self.fn_args.len() - 1);
+                    let buf = format!("\
                                             \t\"{pattern}\" {variadic}{schema_prefix}{sql_type}{default}{maybe_comma}/* {full_path} */\
                                         ",
                                             pattern = arg.pattern,
@@ -145,20 +196,31 @@ impl ToSql for PgExternEntity {
                                             maybe_comma = if needs_comma { ", " } else { " " },
                                             full_path = arg.full_path,
                                      );
-                                     args.push(buf);
-                                 };
-                                 String::from("\n") + &args.join("\n") + "\n"
-                             } else { Default::default() },
-                             returns = match &self.fn_return {
-                                 PgExternReturnEntity::None => String::from("RETURNS void"),
-                                 PgExternReturnEntity::Type { id, source, full_path, .. } => {
-                                     let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
-                                         SqlGraphEntity::Type(ty) => ty.id_matches(&id),
-                                         SqlGraphEntity::Enum(en) => en.id_matches(&id),
-                                         SqlGraphEntity::BuiltinType(defined) => &*defined == full_path,
-                                         _ => false,
-                                     }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
-                                     format!("RETURNS {schema_prefix}{sql_type} /* {full_path} */",
+                    args.push(buf);
+                }
+                String::from("\n") + &args.join("\n") + "\n"
+            } else {
+                Default::default()
+            },
+            returns = match &self.fn_return {
+                PgExternReturnEntity::None => String::from("RETURNS void"),
+                PgExternReturnEntity::Type {
+                    id,
+                    source,
+                    full_path,
+                    ..
+                } => {
+                    let graph_index = context
+                        .graph
+                        .neighbors_undirected(self_index)
+                        .find(|neighbor| match &context.graph[*neighbor] {
+                            SqlGraphEntity::Type(ty) => ty.id_matches(&id),
+                            SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                            SqlGraphEntity::BuiltinType(defined) => &*defined == full_path,
+                            _ => false,
+                        })
+                        .ok_or_else(|| eyre!("Could not find return type in graph."))?;
+                    format!("RETURNS {schema_prefix}{sql_type} /* {full_path} */",
                                              sql_type = context.source_only_to_sql_type(source).or_else(|| {
                                                  context.type_id_to_sql_type(*id)
                                              }).or_else(|| {
@@ -174,15 +236,24 @@ impl ToSql for PgExternEntity {
                                              schema_prefix = context.schema_prefix_for(&graph_index),
                                              full_path = full_path
                                      )
-                                 },
-                                 PgExternReturnEntity::SetOf { id, source, full_path, .. } => {
-                                     let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
-                                         SqlGraphEntity::Type(ty) => ty.id_matches(&id),
-                                         SqlGraphEntity::Enum(en) => en.id_matches(&id),
-                                         SqlGraphEntity::BuiltinType(defined) => defined == full_path,
-                                         _ => false,
-                                     }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
-                                     format!("RETURNS SETOF {schema_prefix}{sql_type} /* {full_path} */",
+                }
+                PgExternReturnEntity::SetOf {
+                    id,
+                    source,
+                    full_path,
+                    ..
+                } => {
+                    let graph_index = context
+                        .graph
+                        .neighbors_undirected(self_index)
+                        .find(|neighbor| match &context.graph[*neighbor] {
+                            SqlGraphEntity::Type(ty) => ty.id_matches(&id),
+                            SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                            SqlGraphEntity::BuiltinType(defined) => defined == full_path,
+                            _ => false,
+                        })
+                        .ok_or_else(|| eyre!("Could not find return type in graph."))?;
+                    format!("RETURNS SETOF {schema_prefix}{sql_type} /* {full_path} */",
                                              sql_type = context.source_only_to_sql_type(source).or_else(|| {
                                                  context.type_id_to_sql_type(*id)
                                              }).or_else(|| {
@@ -198,18 +269,24 @@ impl ToSql for PgExternEntity {
                                              schema_prefix = context.schema_prefix_for(&graph_index),
                                              full_path = full_path
                                      )
-                                 },
-                                 PgExternReturnEntity::Iterated(table_items) => {
-                                     let mut items = String::new();
-                                     for (idx, (id, source, ty_name, _module_path, col_name)) in table_items.iter().enumerate() {
-                                         let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
-                                             SqlGraphEntity::Type(ty) => ty.id_matches(&id),
-                                             SqlGraphEntity::Enum(en) => en.id_matches(&id),
-                                             SqlGraphEntity::BuiltinType(defined) => defined == ty_name,
-                                             _ => false,
-                                         });
-                                         let needs_comma = idx < (table_items.len() - 1);
-                                         let item = format!("\n\t{col_name} {schema_prefix}{ty_resolved}{needs_comma} /* {ty_name} */",
+                }
+                PgExternReturnEntity::Iterated(table_items) => {
+                    let mut items = String::new();
+                    for (idx, (id, source, ty_name, _module_path, col_name)) in
+                        table_items.iter().enumerate()
+                    {
+                        let graph_index =
+                            context
+                                .graph
+                                .neighbors_undirected(self_index)
+                                .find(|neighbor| match &context.graph[*neighbor] {
+                                    SqlGraphEntity::Type(ty) => ty.id_matches(&id),
+                                    SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                                    SqlGraphEntity::BuiltinType(defined) => defined == ty_name,
+                                    _ => false,
+                                });
+                        let needs_comma = idx < (table_items.len() - 1);
+                        let item = format!("\n\t{col_name} {schema_prefix}{ty_resolved}{needs_comma} /* {ty_name} */",
                                                             col_name = col_name.expect("An iterator of tuples should have `named!()` macro declarations."),
                                                             schema_prefix = if let Some(graph_index) = graph_index {
                                                                 context.schema_prefix_for(&graph_index)
@@ -229,23 +306,30 @@ impl ToSql for PgExternEntity {
                                                             needs_comma = if needs_comma { ", " } else { " " },
                                                             ty_name = ty_name
                                          );
-                                         items.push_str(&item);
-                                     }
-                                     format!("RETURNS TABLE ({}\n)", items)
-                                 },
-                                 PgExternReturnEntity::Trigger => String::from("RETURNS trigger"),
-                             },
-                             search_path = if let Some(search_path) = &self.search_path {
-                                 let retval = format!("SET search_path TO {}", search_path.join(", "));
-                                 retval + "\n"
-                             } else { Default::default() },
-                             extern_attrs = if extern_attrs.is_empty() {
-                                 String::default()
-                             } else {
-                                 let mut retval = extern_attrs.iter().map(|attr| format!("{}", attr).to_uppercase()).collect::<Vec<_>>().join(" ");
-                                 retval.push('\n');
-                                 retval
-                             },
+                        items.push_str(&item);
+                    }
+                    format!("RETURNS TABLE ({}\n)", items)
+                }
+                PgExternReturnEntity::Trigger => String::from("RETURNS trigger"),
+            },
+            support = self.support_sql(context)?,
+            search_path = if let Some(search_path) = &self.search_path {
+                let retval = format!("SET search_path TO {}", search_path.join(", "));
+                retval + "\n"
+            } else {
+                Default::default()
+            },
+            extern_attrs = if extern_attrs.is_empty() {
+                String::default()
+            } else {
+                let mut retval = extern_attrs
+                    .iter()
+                    .map(|attr| format!("{}", attr).to_uppercase())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                retval.push('\n');
+                retval
+            },
         );
 
         let ext_sql = format!(
@@ -253,8 +337,13 @@ impl ToSql for PgExternEntity {
                                 -- {file}:{line}\n\
                                 -- {module_path}::{name}\n\
                                 {requires}\
+                                {access_check}\
                                 {fn_sql}\
                             ",
+            access_check = match &self.access_check {
+                Some(check) => format!("-- only callable by {}\n", check),
+                None => String::new(),
+            },
             name = self.name,
             module_path = self.module_path,
             file = self.file,
@@ -288,6 +377,204 @@ impl ToSql for PgExternEntity {
             },
         );
         tracing::trace!(sql = %ext_sql);
+        Ok(ext_sql)
+    }
+
+    /// The `SUPPORT` clause naming the planner support function, if the function has one
+    fn support_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let support = match self.extern_attrs.iter().find_map(|attr| match attr {
+            ExternArgs::Support(support) => Some(support),
+            _ => None,
+        }) {
+            Some(support) => support,
+            None => return Ok(String::new()),
+        };
+        let support_index = find_positioning_ref_target(
+            support,
+            &context.types,
+            &context.enums,
+            &context.composites,
+            &context.externs,
+            &context.schemas,
+            &context.extension_sqls,
+            &context.triggers,
+        )
+        .ok_or_else(|| eyre!("Could not find `support` function: {}", support))?;
+        match &context.graph[*support_index] {
+            SqlGraphEntity::Function(function) => Ok(format!(
+                "SUPPORT {schema}\"{name}\"\n",
+                schema = function
+                    .schema
+                    .map(|schema| format!("{}.", schema))
+                    .unwrap_or_else(|| context.schema_prefix_for(support_index)),
+                name = function.name,
+            )),
+            _ => Err(eyre!(
+                "`support` of `{}` is not a `#[pg_extern]` function: {}",
+                self.name,
+                support
+            )),
+        }
+    }
+
+    /// The SQL types of the arguments, which name the function along with its name
+    fn argument_types_sql(&self, context: &PgxSql) -> eyre::Result<Vec<String>> {
+        let self_index = context.externs[self];
+        let mut types = Vec::new();
+        for arg in &self.fn_args {
+            let graph_index = context
+                .graph
+                .neighbors_undirected(self_index)
+                .find(|neighbor| match &context.graph[*neighbor] {
+                    SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
+                    SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
+                    SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
+                    _ => false,
+                })
+                .ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
+            let sql_type = context
+                .rust_to_sql(arg.ty_id, arg.ty_source, arg.full_path)
+                .ok_or_else(|| {
+                    eyre!(
+                        "Failed to map argument `{}` type `{}` to SQL type while naming function `{}`.",
+                        arg.pattern,
+                        arg.full_path,
+                        self.name
+                    )
+                })?;
+            types.push(format!(
+                "{variadic}{schema_prefix}{sql_type}",
+                variadic = if arg.is_variadic { "VARIADIC " } else { "" },
+                schema_prefix = context.schema_prefix_for(&graph_index),
+                sql_type = sql_type,
+            ));
+        }
+        Ok(types)
+    }
+
+    /// The `CREATE CAST` from the type of the first argument to the return type of a `#[pg_cast]`
+    /// function
+    fn cast_sql(&self, context: &PgxSql, cast: PgCastEntity) -> eyre::Result<String> {
+        let self_index = context.externs[self];
+        let arguments = self.argument_types_sql(context)?;
+        let source = arguments.first().ok_or_else(|| {
+            eyre!(
+                "Did not find the argument to cast for cast `{}`.",
+                self.name
+            )
+        })?;
+        let (id, source_only, full_path) = match &self.fn_return {
+            PgExternReturnEntity::Type {
+                id,
+                source,
+                full_path,
+                ..
+            } => (id, source, full_path),
+            _ => return Err(eyre!("Cast `{}` does not return a type.", self.name)),
+        };
+        let target_graph_index = context
+            .graph
+            .neighbors_undirected(self_index)
+            .find(|neighbor| match &context.graph[*neighbor] {
+                SqlGraphEntity::Type(ty) => ty.id_matches(id),
+                SqlGraphEntity::Enum(en) => en.id_matches(id),
+                SqlGraphEntity::BuiltinType(defined) => defined == full_path,
+                _ => false,
+            })
+            .ok_or_else(|| eyre!("Could not find return type in graph."))?;
+        let target = context
+            .rust_to_sql(*id, source_only, full_path)
+            .ok_or_else(|| {
+                eyre!(
+                    "Failed to map return type `{}` to SQL type while building cast `{}`.",
+                    full_path,
+                    self.name
+                )
+            })?;
+        let cast_sql = format!(
+            "\n\n\
+                -- {file}:{line}\n\
+                -- {module_path}::{unaliased_name}\n\
+                CREATE CAST ({source} AS {target_schema}{target}) /* {full_path} */\n\
+                \tWITH FUNCTION {schema}\"{name}\"({arguments}){context};\
+            ",
+            file = self.file,
+            line = self.line,
+            module_path = self.module_path,
+            unaliased_name = self.unaliased_name,
+            source = source,
+            target_schema = context.schema_prefix_for(&target_graph_index),
+            target = target,
+            full_path = full_path,
+            schema = self
+                .schema
+                .map(|schema| format!("{}.", schema))
+                .unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+            name = self.name,
+            arguments = arguments.join(", "),
+            context = match cast {
+                PgCastEntity::Explicit => "",
+                PgCastEntity::Assignment => "\n\tAS ASSIGNMENT",
+                PgCastEntity::Implicit => "\n\tAS IMPLICIT",
+            },
+        );
+        tracing::trace!(sql = %cast_sql);
+        Ok(cast_sql)
+    }
+
+    /// For an upgrade script, the `ALTER FUNCTION .. RENAME TO` of a function marked
+    /// `#[pg_extern(renamed_from = ..)]`, and the `CREATE OR REPLACE FUNCTION` pointing it at the
+    /// renamed Rust function.  Renaming keeps the function's OID, so what depends on it, such as
+    /// operators, views and indexes, isn't dropped along with it.
+    pub fn rename_sql(&self, context: &PgxSql) -> eyre::Result<Option<String>> {
+        let renamed_from = match self.renamed_from {
+            Some(renamed_from) if renamed_from != self.name => renamed_from,
+            _ => return Ok(None),
+        };
+        let self_index = context.externs[self];
+        let alter = format!(
+            "\n\
+                -- {file}:{line}\n\
+                -- {module_path}::{name}, renamed from `{renamed_from}`\n\
+                ALTER FUNCTION {schema}\"{renamed_from}\"({arguments}) RENAME TO \"{name}\";\n\
+            ",
+            file = self.file,
+            line = self.line,
+            module_path = self.module_path,
+            name = self.name,
+            renamed_from = renamed_from,
+            schema = self
+                .schema
+                .map(|schema| format!("{}.", schema))
+                .unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+            arguments = self.argument_types_sql(context)?.join(", "),
+        );
+
+        // Functions with hand-written SQL are left to it
+        if !self.to_sql_config.enabled
+            || self.to_sql_config.content.is_some()
+            || self.to_sql_config.callback.is_some()
+        {
+            return Ok(Some(alter));
+        }
+        let create = self.function_sql(context)?.replacen(
+            "CREATE FUNCTION",
+            "CREATE OR REPLACE FUNCTION",
+            1,
+        );
+        Ok(Some(alter + &create))
+    }
+}
+
+impl ToSql for PgExternEntity {
+    #[tracing::instrument(
+        level = "error",
+        skip(self, context),
+        fields(identifier = %self.rust_identifier()),
+    )]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.externs[self];
+        let ext_sql = self.function_sql(context)?;
 
         let rendered = if let Some(op) = &self.operator {
             let mut optionals = vec![];
@@ -303,13 +590,6 @@ impl ToSql for PgExternEntity {
             if let Some(it) = op.join {
                 optionals.push(format!("\tJOIN = {}", it));
             };
-            if op.hashes {
-                optionals.push(String::from("\tHASHES"));
-            };
-            if op.merges {
-                optionals.push(String::from("\tMERGES"));
-            };
-
             let left_arg = self
                 .fn_args
                 .get(0)
@@ -335,6 +615,41 @@ impl ToSql for PgExternEntity {
                 })
                 .ok_or_else(|| eyre!("Could not find right arg function in graph."))?;
 
+            // HASHES and MERGES promise a hash or btree operator class for the operator to be found
+            // in.  Without a hash one, Postgres would plan hash joins that fail as they're run, so
+            // when the operands are of a type that's missing the `#[derive(PostgresHash)]` or
+            // `#[derive(PostgresOrd)]` for it, the flag is left off.
+            let operand_type = match &context.graph[left_arg_graph_index] {
+                SqlGraphEntity::Type(ty) if left_arg_graph_index == right_arg_graph_index => {
+                    Some(ty)
+                }
+                _ => None,
+            };
+            if op.hashes {
+                match operand_type {
+                    Some(ty) if !context.hashes.keys().any(|hash| ty.id_matches(&hash.id)) => {
+                        tracing::warn!(
+                            operator = %self.name,
+                            "Not declaring `HASHES`, as `{}` has no hash operator class",
+                            left_arg.full_path
+                        )
+                    }
+                    _ => optionals.push(String::from("\tHASHES")),
+                }
+            };
+            if op.merges {
+                match operand_type {
+                    Some(ty) if !context.ords.keys().any(|ord| ty.id_matches(&ord.id)) => {
+                        tracing::warn!(
+                            operator = %self.name,
+                            "Not declaring `MERGES`, as `{}` has no btree operator class",
+                            left_arg.full_path
+                        )
+                    }
+                    _ => optionals.push(String::from("\tMERGES")),
+                }
+            };
+
             let operator_sql = format!("\n\n\
                                         -- {file}:{line}\n\
                                         -- {module_path}::{unaliased_name}\n\
@@ -365,6 +680,10 @@ impl ToSql for PgExternEntity {
         } else {
             ext_sql
         };
+        let rendered = match self.cast {
+            Some(cast) => rendered + &self.cast_sql(context, cast)?,
+            None => rendered,
+        };
         Ok(rendered)
     }
 }
@@ -6,22 +6,30 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+mod access_check;
 mod argument;
 mod attribute;
+mod cast;
 pub mod entity;
 mod operator;
 mod returning;
 mod search_path;
+mod volatility;
 
 pub use argument::PgExternArgument;
-pub use operator::PgOperator;
+pub use cast::{PgCast, PgCastArgs};
+pub use operator::{PgOperator, PgOperatorArgs};
 pub use returning::NameMacro;
 
 use crate::sql_entity_graph::ToSqlConfig;
+use crate::LibraryNaming;
+use access_check::AccessCheck;
 use attribute::Attribute;
+use cast::check_cast;
 use operator::{PgxOperatorAttributeWithIdent, PgxOperatorOpName};
 use returning::Returning;
 use search_path::SearchPathList;
+use volatility::check_volatility;
 
 use eyre::WrapErr;
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
@@ -62,7 +70,7 @@ pub struct PgExtern {
 }
 
 impl PgExtern {
-    fn name(&self) -> String {
+    pub(crate) fn name(&self) -> String {
         self.attrs
             .iter()
             .find_map(|a| match a {
@@ -79,6 +87,32 @@ impl PgExtern {
         })
     }
 
+    /// The `LANGUAGE` and source of a function declared with `#[pg_extern(body = ..)]`, which
+    /// Postgres runs in place of the Rust function.
+    pub fn body(&self) -> Option<(String, syn::LitStr)> {
+        let body = self.attrs.iter().find_map(|a| match a {
+            Attribute::Body(body) => Some(body.clone()),
+            _ => None,
+        })?;
+        let language = self
+            .attrs
+            .iter()
+            .find_map(|a| match a {
+                Attribute::Language(language) => Some(language.value()),
+                _ => None,
+            })
+            .unwrap_or_else(|| String::from("sql"));
+        Some((language, body))
+    }
+
+    /// The SQL name the function had before, from `#[pg_extern(renamed_from = ..)]`
+    fn renamed_from(&self) -> Option<String> {
+        self.attrs.iter().find_map(|a| match a {
+            Attribute::RenamedFrom(name) => Some(name.value()),
+            _ => None,
+        })
+    }
+
     pub fn extern_attrs(&self) -> &[Attribute] {
         self.attrs.as_slice()
     }
@@ -105,7 +139,8 @@ impl PgExtern {
                             let sql = retval.get_or_insert_with(String::default);
                             let line = inner.value().trim_start().replace(
                                 "@FUNCTION_NAME@",
-                                &*(self.func.sig.ident.to_string() + "_wrapper"),
+                                &LibraryNaming::current()
+                                    .symbol(&format!("{}_wrapper", self.func.sig.ident)),
                             ) + "\n";
                             sql.push_str(&*line);
                         }
@@ -198,6 +233,47 @@ impl PgExtern {
         Returning::try_from(&self.func.sig.output)
     }
 
+    /// A `#[test]` which calls this function from SQL with every argument `NULL` or defaulted, and
+    /// fails if that raises an `ERROR`.  It only exists when the crate enables its `pg_smoke_test`
+    /// feature along with `pg_test`.
+    ///
+    /// `None` if some argument can be neither `NULL` nor defaulted, or the function can't be called
+    /// on its own, such as a window function.
+    pub fn smoke_test(&self) -> Option<TokenStream2> {
+        if !self.to_sql_config.enabled
+            || self.attrs.contains(&Attribute::Window)
+            || self.body().is_some()
+        {
+            return None;
+        }
+        if let Ok(Returning::Trigger) = self.returns() {
+            return None;
+        }
+        let inputs = self.inputs().ok()?;
+        if !inputs
+            .iter()
+            .all(|arg| arg.is_optional() || arg.has_default())
+        {
+            return None;
+        }
+
+        let ident = &self.func.sig.ident;
+        let wrapper_name = LibraryNaming::current().symbol(&format!("{}_wrapper", ident));
+        let test_func_name = Ident::new(&format!("pg_smoke_test_{}", ident), ident.span());
+        Some(quote! {
+            #[cfg(all(any(test, feature = "pg_test"), feature = "pg_smoke_test"))]
+            #[test]
+            fn #test_func_name() {
+                crate::pg_test::setup(Vec::new());
+                let res = pgx_tests::run_smoke_test(#wrapper_name, crate::pg_test::postgresql_conf_options());
+                match res {
+                    Ok(()) => (),
+                    Err(e) => panic!("{:?}", e)
+                }
+            }
+        })
+    }
+
     pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<Self, syn::Error> {
         let mut attrs = Vec::new();
         let mut to_sql_config: Option<ToSqlConfig> = None;
@@ -216,13 +292,20 @@ impl PgExtern {
         }
 
         let func = syn::parse2::<syn::ItemFn>(item)?;
+        if attrs.contains(&Attribute::Window) {
+            check_window_arguments(&func)?;
+        }
+        check_body(&attrs, &func)?;
+        check_rows(&attrs, &func)?;
+        check_volatility(&attrs, &func)?;
+        check_cast(&func)?;
 
         if let Some(ref mut to_sql_config) = to_sql_config {
             if let Some(ref mut content) = to_sql_config.content {
                 let value = content.value();
                 let updated_value = value.replace(
                     "@FUNCTION_NAME@",
-                    &*(func.sig.ident.to_string() + "_wrapper"),
+                    &LibraryNaming::current().symbol(&format!("{}_wrapper", func.sig.ident)),
                 ) + "\n";
                 *content = syn::LitStr::new(&updated_value, Span::call_site());
             }
@@ -245,6 +328,15 @@ impl ToTokens for PgExtern {
         let extern_attrs = self
             .attrs
             .iter()
+            .filter(|attr| {
+                !matches!(
+                    attr,
+                    Attribute::Language(_)
+                        | Attribute::Body(_)
+                        | Attribute::CheckVolatility
+                        | Attribute::RenamedFrom(_)
+                )
+            })
             .map(|attr| attr.to_sql_entity_graph_tokens())
             .collect::<Punctuated<_, Token![,]>>();
         let search_path = self.search_path().into_iter();
@@ -260,6 +352,18 @@ impl ToTokens for PgExtern {
             }
         };
         let operator = self.operator().into_iter();
+        // `check_cast` has already rejected a context that doesn't parse
+        let cast = PgCast::of(&self.func).ok().flatten().into_iter();
+        let (language, body) = match self.body() {
+            Some((language, body)) => (Some(language), Some(body)),
+            None => (None, None),
+        };
+        let language_iter = language.iter();
+        let renamed_from = self.renamed_from();
+        let renamed_from_iter = renamed_from.iter();
+        let access_check = AccessCheck::of(&self.func);
+        let access_check_iter = access_check.iter();
+        let body_iter = body.iter();
         let to_sql_config = match self.overridden() {
             None => self.to_sql_config.clone(),
             Some(content) => {
@@ -292,6 +396,11 @@ impl ToTokens for PgExtern {
                     fn_args: vec![#(#inputs),*],
                     fn_return: #returns,
                     operator: None #( .unwrap_or(Some(#operator)) )*,
+                    cast: None #( .unwrap_or(Some(#cast)) )*,
+                    language: None #( .unwrap_or(Some(#language_iter)) )*,
+                    body: None #( .unwrap_or(Some(#body_iter)) )*,
+                    renamed_from: None #( .unwrap_or(Some(#renamed_from_iter)) )*,
+                    access_check: None #( .unwrap_or(Some(#access_check_iter)) )*,
                     to_sql_config: #to_sql_config,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Function(submission)
@@ -320,6 +429,13 @@ impl Parse for PgExtern {
         }
 
         let func: syn::ItemFn = input.parse()?;
+        if attrs.contains(&Attribute::Window) {
+            check_window_arguments(&func)?;
+        }
+        check_body(&attrs, &func)?;
+        check_rows(&attrs, &func)?;
+        check_volatility(&attrs, &func)?;
+        check_cast(&func)?;
         Ok(Self {
             attrs,
             func,
@@ -327,3 +443,298 @@ impl Parse for PgExtern {
         })
     }
 }
+
+/// Postgres passes every argument of a window function as NULL, their values are read through a
+/// `WindowFunctionContext` instead.  Any argument that isn't an `Option<T>` would always panic.
+fn check_window_arguments(func: &syn::ItemFn) -> Result<(), syn::Error> {
+    for input in &func.sig.inputs {
+        if let syn::FnArg::Typed(pat_ty) = input {
+            let last_segment = match pat_ty.ty.as_ref() {
+                syn::Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+                _ => None,
+            };
+            match last_segment.as_deref() {
+                Some("Option") | Some("WindowFunctionContext") | Some("FunctionCallInfo") => (),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &pat_ty.ty,
+                        "arguments of a `#[pg_extern(window)]` function are always NULL, \
+                            declare them as `Option<T>` and read them through `WindowFunctionContext`",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A `body` replaces the Rust function in SQL, so a `language` needs one, and a window function
+/// can't have one, as only C functions can be window functions.
+fn check_body(attrs: &[Attribute], func: &syn::ItemFn) -> Result<(), syn::Error> {
+    let has_body = attrs.iter().any(|a| matches!(a, Attribute::Body(_)));
+    for attr in attrs {
+        match attr {
+            Attribute::Language(language) if !has_body => {
+                return Err(syn::Error::new_spanned(
+                    language,
+                    "`language` requires a `body` for the function, written in that language",
+                ))
+            }
+            Attribute::Window if has_body => {
+                return Err(syn::Error::new_spanned(
+                    &func.sig.ident,
+                    "a `#[pg_extern(window)]` function can't have a `body`",
+                ))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Postgres only accepts `ROWS` for a function which returns a set.
+fn check_rows(attrs: &[Attribute], func: &syn::ItemFn) -> Result<(), syn::Error> {
+    let rows = attrs.iter().find_map(|attr| match attr {
+        Attribute::Rows(rows) => Some(rows),
+        _ => None,
+    });
+    if let Some(rows) = rows {
+        match Returning::try_from(&func.sig.output) {
+            Ok(Returning::SetOf(_)) | Ok(Returning::Iterated(_)) | Err(_) => (),
+            Ok(_) => {
+                return Err(syn::Error::new_spanned(
+                    rows,
+                    "`rows` is the estimated number of rows of a set-returning function, \
+                        which this function isn't",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessCheck, PgCast, PgCastArgs, PgExtern};
+    use quote::{quote, ToTokens};
+
+    #[test]
+    fn window_function() {
+        let parsed = PgExtern::new(
+            quote! { window },
+            quote! {
+                fn first_value(value: Option<i32>, window: WindowFunctionContext) -> Option<i32> {
+                    unimplemented!()
+                }
+            },
+        )
+        .expect("window function should parse");
+        assert_eq!(parsed.inputs().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn window_function_non_option_argument() {
+        let parsed = PgExtern::new(
+            quote! { window },
+            quote! {
+                fn first_value(value: i32, window: WindowFunctionContext) -> Option<i32> {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn sql_body() {
+        let parsed = PgExtern::new(
+            quote! { immutable, body = "SELECT add_two_numbers(a, 1)" },
+            quote! {
+                fn add_one(a: i32) -> i32 {
+                    a + 1
+                }
+            },
+        )
+        .expect("function with a body should parse");
+        let (language, body) = parsed.body().expect("function should have a body");
+        assert_eq!(language, "sql");
+        assert_eq!(body.value(), "SELECT add_two_numbers(a, 1)");
+        assert!(parsed.smoke_test().is_none());
+    }
+
+    #[test]
+    fn rows_and_support() {
+        PgExtern::new(
+            quote! { parallel_safe, cost = 10, rows = 5, support = crate::support::evens_support },
+            quote! {
+                fn evens(n: i32) -> impl Iterator<Item = i32> {
+                    (0..n).step_by(2)
+                }
+            },
+        )
+        .expect("set-returning function with `rows` and `support` should parse");
+    }
+
+    #[test]
+    fn rows_without_set() {
+        let parsed = PgExtern::new(
+            quote! { rows = 5 },
+            quote! {
+                fn add_one(a: i32) -> i32 {
+                    a + 1
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn language_without_body() {
+        let parsed = PgExtern::new(
+            quote! { language = "plpgsql" },
+            quote! {
+                fn add_one(a: i32) -> i32 {
+                    a + 1
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn check_volatility_immutable_spi() {
+        let parsed = PgExtern::new(
+            quote! { immutable, check_volatility },
+            quote! {
+                fn count_rows() -> i64 {
+                    Spi::get_one("SELECT count(*) FROM t").unwrap()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn check_volatility_stable() {
+        let parsed = PgExtern::new(
+            quote! { stable, check_volatility },
+            quote! {
+                fn count_rows() -> i64 {
+                    Spi::get_one("SELECT count(*) FROM t").unwrap()
+                }
+            },
+        );
+        assert!(parsed.is_ok());
+
+        let parsed = PgExtern::new(
+            quote! { stable, check_volatility },
+            quote! {
+                fn roll() -> i32 {
+                    let roll: u8 = rand::random();
+                    format!("{:?}", std::time::SystemTime::now());
+                    roll as i32
+                }
+            },
+        );
+        let error = parsed.expect_err("random values and the time aren't stable");
+        assert_eq!(error.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn check_volatility_opt_in() {
+        let parsed = PgExtern::new(
+            quote! { immutable },
+            quote! {
+                fn count_rows() -> i64 {
+                    Spi::get_one("SELECT count(*) FROM t").unwrap()
+                }
+            },
+        );
+        assert!(parsed.is_ok());
+    }
+
+    #[test]
+    fn renamed_from() {
+        let parsed = PgExtern::new(
+            quote! { immutable, renamed_from = "add_numbers" },
+            quote! {
+                fn sum(a: i32, b: i32) -> i32 {
+                    a + b
+                }
+            },
+        )
+        .expect("renamed function should parse");
+        assert_eq!(parsed.renamed_from().as_deref(), Some("add_numbers"));
+        assert_eq!(parsed.name(), "sum");
+    }
+
+    #[test]
+    fn access_check() {
+        let parsed = PgExtern::new(
+            quote! {},
+            quote! {
+                fn reset_cache() {
+                    require_role!("myext_admin");
+                    Spi::run("TRUNCATE cache");
+                }
+            },
+        )
+        .expect("function should parse");
+        assert_eq!(
+            AccessCheck::of(&parsed.func),
+            Some(AccessCheck::Role(syn::parse_quote!("myext_admin")))
+        );
+
+        let parsed = PgExtern::new(
+            quote! {},
+            quote! {
+                fn drop_caches() {
+                    pgx::require_superuser!();
+                }
+            },
+        )
+        .expect("function should parse");
+        assert_eq!(AccessCheck::of(&parsed.func), Some(AccessCheck::Superuser));
+
+        // only a check before anything else is done counts
+        let parsed = PgExtern::new(
+            quote! {},
+            quote! {
+                fn reset_cache() {
+                    Spi::run("TRUNCATE cache");
+                    require_role!("myext_admin");
+                }
+            },
+        )
+        .expect("function should parse");
+        assert_eq!(AccessCheck::of(&parsed.func), None);
+    }
+
+    #[test]
+    fn cast() {
+        let args = syn::parse2::<PgCastArgs>(quote! { implicit, immutable, parallel_safe })
+            .expect("cast arguments should parse");
+        assert_eq!(args.extern_args.to_string(), "immutable , parallel_safe");
+
+        let mut func: syn::ItemFn = syn::parse_quote! {
+            fn celsius_to_float8(celsius: Celsius) -> f64 {
+                celsius.0
+            }
+        };
+        func.attrs.extend(args.attrs);
+        let parsed = PgExtern::new(args.extern_args, func.to_token_stream())
+            .expect("cast function should parse");
+        assert_eq!(PgCast::of(&parsed.func).unwrap(), Some(PgCast::Implicit));
+
+        let args = syn::parse2::<PgCastArgs>(quote! {}).expect("cast arguments should parse");
+        let mut func: syn::ItemFn = syn::parse_quote! {
+            fn log_celsius(celsius: Celsius) {
+                pgx::info!("{}", celsius.0);
+            }
+        };
+        func.attrs.extend(args.attrs);
+        assert!(PgExtern::new(args.extern_args, func.to_token_stream()).is_err());
+
+        assert!(syn::parse2::<PgCastArgs>(quote! { implicit, assignment }).is_err());
+    }
+}
@@ -6,9 +6,9 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
 use quote::{quote, ToTokens, TokenStreamExt};
-use syn::parse::{Parse, ParseBuffer};
+use syn::parse::{Parse, ParseBuffer, Parser};
 use syn::{parenthesized, token::Paren};
 
 /// A parsed `#[pg_operator]` operator.
@@ -49,6 +49,82 @@ impl ToTokens for PgOperator {
     }
 }
 
+/// The arguments of `#[pg_operator(symbol = "<->", commutator = "<->", immutable)]`.
+///
+/// The operator's own settings are turned into the `#[opname]`, `#[commutator]`, etc, attributes
+/// [`PgExtern`](crate::sql_entity_graph::PgExtern) reads them from, and everything else is left
+/// for `#[pg_extern]`.
+#[derive(Debug, Clone, Default)]
+pub struct PgOperatorArgs {
+    pub extern_args: TokenStream2,
+    pub attrs: Vec<syn::Attribute>,
+}
+
+impl Parse for PgOperatorArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::Error> {
+        let mut args = PgOperatorArgs::default();
+        let tokens = input
+            .parse::<TokenStream2>()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        for segment in
+            tokens.split(|tt| matches!(tt, TokenTree::Punct(punct) if punct.as_char() == ','))
+        {
+            if !segment.is_empty() {
+                args.push_segment(segment)?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+impl PgOperatorArgs {
+    fn push_segment(&mut self, segment: &[TokenTree]) -> Result<(), syn::Error> {
+        let name = match &segment[0] {
+            TokenTree::Ident(ident) => ident.to_string(),
+            _ => String::new(),
+        };
+        let attr_name = match name.as_str() {
+            "symbol" => "opname",
+            "commutator" | "negator" | "restrict" | "join" | "hashes" | "merges" => name.as_str(),
+            _ => {
+                if !self.extern_args.is_empty() {
+                    self.extern_args.append_all(quote! { , });
+                }
+                self.extern_args.extend(segment.iter().cloned());
+                return Ok(());
+            }
+        };
+        let attr_ident = syn::Ident::new(attr_name, segment[0].span());
+        let is_flag = matches!(attr_name, "hashes" | "merges");
+        let attr = match &segment[1..] {
+            [] if is_flag => quote! { #[::pgx::#attr_ident] },
+            [TokenTree::Punct(eq), value] if eq.as_char() == '=' && !is_flag => {
+                let value: syn::LitStr = syn::parse2(value.to_token_stream())?;
+                quote! { #[::pgx::#attr_ident(#value)] }
+            }
+            _ if is_flag => {
+                let message = format!("`{}` takes no value", name);
+                return Err(syn::Error::new(segment[0].span(), message));
+            }
+            _ => {
+                let message = format!("expected `{} = \"...\"`", name);
+                return Err(syn::Error::new(segment[0].span(), message));
+            }
+        };
+        self.attrs.extend(syn::Attribute::parse_outer.parse2(attr)?);
+        Ok(())
+    }
+}
+
+/// The operator, or function, named by an attribute, as in `#[opname(<->)]` or `#[opname("<->")]`
+fn operator_attribute_value(tokens: &TokenStream2) -> String {
+    match syn::parse2::<syn::LitStr>(tokens.clone()) {
+        Ok(value) => value.value(),
+        Err(_) => tokens.to_string().replace(" ", ""),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PgxOperatorAttributeWithIdent {
     pub paren_token: Paren,
@@ -67,8 +143,7 @@ impl Parse for PgxOperatorAttributeWithIdent {
 
 impl ToTokens for PgxOperatorAttributeWithIdent {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        let fn_name = &self.fn_name;
-        let operator = fn_name.to_string().replace(" ", "");
+        let operator = operator_attribute_value(&self.fn_name);
         let quoted = quote! {
             #operator
         };
@@ -94,8 +169,7 @@ impl Parse for PgxOperatorOpName {
 
 impl ToTokens for PgxOperatorOpName {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        let op_name = &self.op_name;
-        let op_string = op_name.to_string().replacen(" ", "", 256);
+        let op_string = operator_attribute_value(&self.op_name);
         let quoted = quote! {
             #op_string
         };
@@ -107,6 +107,18 @@ impl TryFrom<&syn::ReturnType> for Returning {
                 let mut ty = *ty.clone();
                 anonymonize_lifetimes(&mut ty);
 
+                // a `Result<T, E>` returns a `T`, and raises an `ERROR` for an `E`
+                if let Some(ok_ty) = result_ok_type(&ty) {
+                    let arrow = Default::default();
+                    return match Returning::try_from(&syn::ReturnType::Type(arrow, ok_ty))? {
+                        returning @ Returning::Type(_) => Ok(returning),
+                        _ => Err(eyre!(
+                            "`Result` can only be returned around a single value, not `{}`",
+                            ty.to_token_stream()
+                        )),
+                    };
+                }
+
                 match ty {
                     syn::Type::ImplTrait(mut impl_trait) => {
                         Returning::parse_impl_trait(&mut impl_trait)
@@ -202,6 +214,24 @@ impl TryFrom<&syn::ReturnType> for Returning {
     }
 }
 
+/// The `T` of a `Result<T, E>`
+fn result_ok_type(ty: &syn::Type) -> Option<Box<syn::Type>> {
+    let segment = match ty {
+        syn::Type::Path(typepath) => typepath.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ok_ty) => Some(Box::new(ok_ty.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 impl ToTokens for Returning {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let quoted = match self {
@@ -0,0 +1,144 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::attribute::Attribute;
+use proc_macro2::{Ident, Spacing, TokenStream as TokenStream2, TokenTree};
+
+/// How much a function's result may change between calls with the same arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Volatility {
+    Immutable,
+    Stable,
+    Volatile,
+}
+
+impl std::fmt::Display for Volatility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Volatility::Immutable => "IMMUTABLE",
+            Volatility::Stable => "STABLE",
+            Volatility::Volatile => "VOLATILE",
+        })
+    }
+}
+
+/// The volatility a function calling `path` needs at least, and why.
+///
+/// This only knows the obvious cases, it can't see what the functions called in turn do.
+fn required_volatility(path: &[String]) -> Option<(Volatility, &'static str)> {
+    let segments = path.iter().map(String::as_str).collect::<Vec<_>>();
+    if let Some(spi) = segments.iter().position(|s| *s == "Spi") {
+        return match segments.get(spi + 1) {
+            Some(&"run") => Some((Volatility::Volatile, "can modify the database")),
+            _ => Some((Volatility::Stable, "reads the database")),
+        };
+    }
+    match segments.as_slice() {
+        [.., "SystemTime" | "Instant" | "Utc" | "Local", "now"] | [.., "GetCurrentTimestamp"] => {
+            Some((Volatility::Volatile, "reads the current time"))
+        }
+        [.., "GetCurrentTransactionStartTimestamp"] => {
+            Some((Volatility::Stable, "reads the transaction's start time"))
+        }
+        ["rand", ..] | [.., "thread_rng"] | [_, .., "random"] | [.., "Uuid", "new_v4"] => {
+            Some((Volatility::Volatile, "returns random values"))
+        }
+        _ => None,
+    }
+}
+
+/// Every path, such as `Spi::get_one` or `rand::random`, in `stream`
+fn collect_paths(stream: TokenStream2, paths: &mut Vec<Vec<Ident>>) {
+    let mut path = Vec::new();
+    let mut after_colons = false;
+    let mut colon = false;
+    for token in stream {
+        match token {
+            TokenTree::Ident(ident) => {
+                if !after_colons && !path.is_empty() {
+                    paths.push(std::mem::take(&mut path));
+                }
+                path.push(ident);
+                after_colons = false;
+                colon = false;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == ':' => {
+                if colon {
+                    after_colons = true;
+                    colon = false;
+                } else if punct.spacing() == Spacing::Joint {
+                    colon = true;
+                } else if !path.is_empty() {
+                    paths.push(std::mem::take(&mut path));
+                }
+            }
+            other => {
+                if !path.is_empty() {
+                    paths.push(std::mem::take(&mut path));
+                }
+                after_colons = false;
+                colon = false;
+                if let TokenTree::Group(group) = other {
+                    collect_paths(group.stream(), paths);
+                }
+            }
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+}
+
+/// With `check_volatility`, a function declared `immutable` or `stable` can't call anything that
+/// obviously needs more volatility, such as reading the database with `Spi` from an `immutable`
+/// function.  Postgres trusts the declared volatility, so getting it wrong gives wrong results.
+pub(crate) fn check_volatility(attrs: &[Attribute], func: &syn::ItemFn) -> Result<(), syn::Error> {
+    if !attrs.contains(&Attribute::CheckVolatility) {
+        return Ok(());
+    }
+    let declared = if attrs.contains(&Attribute::Immutable) {
+        Volatility::Immutable
+    } else if attrs.contains(&Attribute::Stable) {
+        Volatility::Stable
+    } else {
+        return Ok(());
+    };
+
+    let mut paths = Vec::new();
+    collect_paths(quote::ToTokens::to_token_stream(&func.block), &mut paths);
+
+    let mut seen = Vec::new();
+    let mut error: Option<syn::Error> = None;
+    for path in paths {
+        let segments = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let (required, reason) = match required_volatility(&segments) {
+            Some((required, reason)) if required > declared => (required, reason),
+            _ => continue,
+        };
+        let display = segments.join("::");
+        if seen.contains(&display) {
+            continue;
+        }
+        let e = syn::Error::new(
+            path[0].span(),
+            format!(
+                "`{}` is declared {}, but `{}` {}, so it must be {}",
+                func.sig.ident, declared, display, reason, required
+            ),
+        );
+        seen.push(display);
+        match error.as_mut() {
+            Some(error) => error.combine(e),
+            None => error = Some(e),
+        }
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,95 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::positioning_ref::PositioningRef;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitInt, LitStr, Token,
+};
+
+#[derive(Debug, Clone)]
+pub enum PgOpClassAttribute {
+    Using(LitStr),
+    Name(LitStr),
+    Default,
+    Storage(LitStr),
+    Operators(Punctuated<PgOpClassMember<LitStr>, Token![,]>),
+    Functions(Punctuated<PgOpClassMember<syn::Path>, Token![,]>),
+    Requires(Punctuated<PositioningRef, Token![,]>),
+}
+
+impl Parse for PgOpClassAttribute {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let ident: syn::Ident = input.parse()?;
+        let found = match ident.to_string().as_str() {
+            "using" => {
+                let _eq: Token![=] = input.parse()?;
+                let using: LitStr = input.parse()?;
+                match using.value().as_str() {
+                    "btree" | "hash" | "gist" | "gin" | "spgist" | "brin" => Self::Using(using),
+                    other => {
+                        return Err(syn::Error::new(
+                            using.span(),
+                            format!("Unknown index access method `{}`", other),
+                        ))
+                    }
+                }
+            }
+            "name" => {
+                let _eq: Token![=] = input.parse()?;
+                Self::Name(input.parse()?)
+            }
+            "default" => Self::Default,
+            "storage" => {
+                let _eq: Token![=] = input.parse()?;
+                Self::Storage(input.parse()?)
+            }
+            "operators" => {
+                let content;
+                let _paren = syn::parenthesized!(content in input);
+                Self::Operators(content.parse_terminated(PgOpClassMember::parse)?)
+            }
+            "functions" => {
+                let content;
+                let _paren = syn::parenthesized!(content in input);
+                Self::Functions(content.parse_terminated(PgOpClassMember::parse)?)
+            }
+            "requires" => {
+                let _eq: Token![=] = input.parse()?;
+                let content;
+                let _bracket = syn::bracketed!(content in input);
+                Self::Requires(content.parse_terminated(PositioningRef::parse)?)
+            }
+            e => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("Invalid option `{}` inside `#[pg_opclass]`", e),
+                ))
+            }
+        };
+        Ok(found)
+    }
+}
+
+/// An operator or support function of the class, with its strategy or support number, as in
+/// `1 = "<"` or `1 = my_type_cmp`
+#[derive(Debug, Clone)]
+pub struct PgOpClassMember<T> {
+    pub number: LitInt,
+    pub value: T,
+}
+
+impl<T: Parse> Parse for PgOpClassMember<T> {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let number = input.parse()?;
+        let _eq: Token![=] = input.parse()?;
+        let value = input.parse()?;
+        Ok(Self { number, value })
+    }
+}
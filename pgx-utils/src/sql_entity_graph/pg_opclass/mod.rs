@@ -0,0 +1,202 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+mod attribute;
+
+use crate::sql_entity_graph::{
+    extension_sql::{ExtensionSql, ExtensionSqlAttribute},
+    positioning_ref::PositioningRef,
+};
+use attribute::PgOpClassAttribute;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{ToTokens, TokenStreamExt};
+use syn::{parse::Parser, punctuated::Punctuated, Ident, LitStr, Token};
+
+/// A parsed `#[pg_opclass]` item.
+///
+/// It is created with [`PgOpClass::new`] from the attribute's arguments and the item.
+///
+/// Using [`quote::ToTokens`] will output the type, and the declaration for an
+/// [`ExtensionSqlEntity`][crate::sql_entity_graph::ExtensionSqlEntity] which creates the operator
+/// class after the type and its support functions.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgOpClass;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let opclass = PgOpClass::new(
+///     quote! { using = "btree", default, operators(1 = "<", 3 = "="), functions(1 = example_cmp) },
+///     quote! { struct Example { demo: i32 } },
+/// )?;
+/// let sql_graph_entity_tokens = opclass.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgOpClass {
+    item: Option<syn::Item>,
+    ty: Ident,
+    attrs: Vec<PgOpClassAttribute>,
+}
+
+impl PgOpClass {
+    pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<Self, syn::Error> {
+        let parser = Punctuated::<PgOpClassAttribute, Token![,]>::parse_terminated;
+        let attrs = parser.parse2(attr)?.into_iter().collect::<Vec<_>>();
+        let item = syn::parse2::<syn::Item>(item)?;
+        let ty = match &item {
+            syn::Item::Struct(item) => item.ident.clone(),
+            syn::Item::Enum(item) => item.ident.clone(),
+            _ => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "#[pg_opclass] can only be applied to structs and enums",
+                ))
+            }
+        };
+        Self::from_parts(Some(item), ty, attrs)
+    }
+
+    /// The operator class of a type being derived, such as by `#[derive(PostgresGistOps)]`, which
+    /// leaves the type as it is
+    pub fn for_type(ty: Ident, attr: TokenStream2) -> Result<Self, syn::Error> {
+        let parser = Punctuated::<PgOpClassAttribute, Token![,]>::parse_terminated;
+        let attrs = parser.parse2(attr)?.into_iter().collect::<Vec<_>>();
+        Self::from_parts(None, ty, attrs)
+    }
+
+    fn from_parts(
+        item: Option<syn::Item>,
+        ty: Ident,
+        attrs: Vec<PgOpClassAttribute>,
+    ) -> Result<Self, syn::Error> {
+        let this = Self { item, ty, attrs };
+        this.using()?;
+        if this.members().is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "#[pg_opclass] needs at least one of `operators(..)` or `functions(..)`",
+            ));
+        }
+        Ok(this)
+    }
+
+    fn using(&self) -> Result<String, syn::Error> {
+        self.attrs
+            .iter()
+            .find_map(|attr| match attr {
+                PgOpClassAttribute::Using(using) => Some(using.value()),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                syn::Error::new(
+                    Span::call_site(),
+                    "#[pg_opclass] needs the index access method, as in `using = \"btree\"`",
+                )
+            })
+    }
+
+    /// The name of the class, which defaults to `{type}_{using}_ops` as with `#[derive(PostgresOrd)]`
+    fn name(&self) -> String {
+        self.attrs
+            .iter()
+            .find_map(|attr| match attr {
+                PgOpClassAttribute::Name(name) => Some(name.value()),
+                _ => None,
+            })
+            .unwrap_or_else(|| format!("{}_{}_ops", self.ty, self.using().unwrap()))
+    }
+
+    /// The `OPERATOR`, `FUNCTION` and `STORAGE` items of the class
+    fn members(&self) -> Vec<String> {
+        let mut members = Vec::new();
+        for attr in &self.attrs {
+            if let PgOpClassAttribute::Operators(operators) = attr {
+                for operator in operators {
+                    members.push(format!(
+                        "OPERATOR {} {}",
+                        operator.number,
+                        operator.value.value()
+                    ));
+                }
+            }
+        }
+        for attr in &self.attrs {
+            if let PgOpClassAttribute::Functions(functions) = attr {
+                for function in functions {
+                    let name = &function.value.segments.last().unwrap().ident;
+                    members.push(format!("FUNCTION {} {}", function.number, name));
+                }
+            }
+        }
+        for attr in &self.attrs {
+            if let PgOpClassAttribute::Storage(storage) = attr {
+                members.push(format!("STORAGE {}", storage.value()));
+            }
+        }
+        members
+    }
+
+    fn requires(&self) -> Punctuated<PositioningRef, Token![,]> {
+        let mut requires = Punctuated::new();
+        requires.push(PositioningRef::FullPath(self.ty.to_string()));
+        for attr in &self.attrs {
+            match attr {
+                PgOpClassAttribute::Functions(functions) => {
+                    for function in functions {
+                        let path = function.value.to_token_stream().to_string();
+                        requires.push(PositioningRef::FullPath(path.replace(" ", "")));
+                    }
+                }
+                PgOpClassAttribute::Requires(items) => requires.extend(items.iter().cloned()),
+                _ => (),
+            }
+        }
+        requires
+    }
+}
+
+impl ToTokens for PgOpClass {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let name = self.name();
+        let default = if self
+            .attrs
+            .iter()
+            .any(|attr| matches!(attr, PgOpClassAttribute::Default))
+        {
+            " DEFAULT"
+        } else {
+            ""
+        };
+        let sql = format!(
+            "\n\
+            CREATE OPERATOR CLASS {name}{default} FOR TYPE {ty} USING {using} AS\n\
+                \t{members};\n\
+            ",
+            name = name,
+            default = default,
+            ty = self.ty,
+            using = self.using().unwrap(),
+            members = self.members().join(",\n\t"),
+        );
+        let mut attrs = Punctuated::new();
+        attrs.push(ExtensionSqlAttribute::Requires(self.requires()));
+        let extension_sql = ExtensionSql {
+            sql: LitStr::new(&sql, Span::call_site()),
+            name: LitStr::new(&name, Span::call_site()),
+            attrs,
+        };
+        if let Some(item) = &self.item {
+            item.to_tokens(tokens);
+        }
+        tokens.append_all(extension_sql.to_token_stream());
+    }
+}
@@ -0,0 +1,47 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{pgx_attribute::ArgValue, to_sql::ToSqlConfig};
+use syn::{
+    parse::{Parse, ParseStream},
+    Lit, Token,
+};
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum PgTriggerAttribute {
+    Sql(ToSqlConfig),
+}
+
+impl Parse for PgTriggerAttribute {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let ident: syn::Ident = input.parse()?;
+        let found = match ident.to_string().as_str() {
+            "sql" => {
+                let _eq: Token![=] = input.parse()?;
+                match input.parse::<ArgValue>()? {
+                    ArgValue::Path(p) => Self::Sql(ToSqlConfig::from(p)),
+                    ArgValue::Lit(Lit::Bool(b)) => Self::Sql(ToSqlConfig::from(b.value)),
+                    ArgValue::Lit(Lit::Str(s)) => Self::Sql(ToSqlConfig::from(s)),
+                    ArgValue::Lit(other) => {
+                        return Err(syn::Error::new(
+                            other.span(),
+                            "expected boolean, path, or string literal",
+                        ))
+                    }
+                }
+            }
+            e => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("Invalid option `{}` inside `#[pg_trigger]`", e),
+                ))
+            }
+        };
+        Ok(found)
+    }
+}
@@ -0,0 +1,85 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    pgx_sql::PgxSql,
+    to_sql::{entity::ToSqlConfigEntity, ToSql},
+    SqlGraphEntity, SqlGraphIdentifier,
+};
+use std::cmp::Ordering;
+
+/// The output of a [`PgTrigger`](crate::sql_entity_graph::pg_trigger::PgTrigger) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PgTriggerEntity {
+    pub fn_name: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl Ord for PgTriggerEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+    }
+}
+
+impl PartialOrd for PgTriggerEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Into<SqlGraphEntity> for PgTriggerEntity {
+    fn into(self) -> SqlGraphEntity {
+        SqlGraphEntity::Trigger(self)
+    }
+}
+
+impl SqlGraphIdentifier for PgTriggerEntity {
+    fn dot_identifier(&self) -> String {
+        format!("trigger fn {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PgTriggerEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.triggers[self];
+        let sql = format!(
+            "\n\
+            {sql_anchor_comment}\n\
+            CREATE FUNCTION {schema_prefix}\"{fn_name}\"()\n\
+                \tRETURNS TRIGGER\n\
+                \tLANGUAGE c /* Rust */\n\
+                \tAS '{module_pathname}', '{symbol}';\
+            ",
+            sql_anchor_comment = self.sql_anchor_comment(),
+            schema_prefix = context.schema_prefix_for(&self_index),
+            fn_name = self.fn_name,
+            module_pathname = context.get_module_pathname(),
+            symbol = context.get_symbol_name(&format!("{}_wrapper", self.fn_name)),
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
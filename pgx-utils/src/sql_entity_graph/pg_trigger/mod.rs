@@ -0,0 +1,210 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+mod attribute;
+pub mod entity;
+
+use crate::sql_entity_graph::ToSqlConfig;
+use crate::LibraryNaming;
+use attribute::PgTriggerAttribute;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{
+    parse::{Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    Token,
+};
+
+/// A parsed `#[pg_trigger]` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the function, its `extern "C"` wrapper, and the
+/// declaration for a [`PgTriggerEntity`][crate::sql_entity_graph::PgTriggerEntity].
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgTrigger;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: PgTrigger = parse_quote! {
+///     fn example(trigger: &PgTrigger) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+///         unimplemented!()
+///     }
+/// };
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgTrigger {
+    func: syn::ItemFn,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgTrigger {
+    pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<Self, syn::Error> {
+        let parser = Punctuated::<PgTriggerAttribute, Token![,]>::parse_terminated;
+        let attrs = parser.parse2(attr)?;
+        let func = syn::parse2::<syn::ItemFn>(item)?;
+        Self::from_parts(attrs, func)
+    }
+
+    fn from_parts(
+        attrs: Punctuated<PgTriggerAttribute, Token![,]>,
+        func: syn::ItemFn,
+    ) -> Result<Self, syn::Error> {
+        let mut to_sql_config = None;
+        for attr in attrs {
+            match attr {
+                PgTriggerAttribute::Sql(config) => {
+                    to_sql_config.get_or_insert(config);
+                }
+            }
+        }
+
+        if func.sig.inputs.len() != 1 {
+            return Err(syn::Error::new_spanned(
+                &func.sig.inputs,
+                "`#[pg_trigger]` functions take exactly one argument, a `&pgx::PgTrigger`",
+            ));
+        }
+        if let syn::ReturnType::Default = func.sig.output {
+            return Err(syn::Error::new_spanned(
+                &func.sig,
+                "`#[pg_trigger]` functions must return an `Option<pgx::PgHeapTuple>`, or a `Result` of one",
+            ));
+        }
+
+        Ok(Self {
+            func,
+            to_sql_config: to_sql_config.unwrap_or_default(),
+        })
+    }
+
+    fn wrapper_tokens(&self) -> TokenStream2 {
+        let ident = &self.func.sig.ident;
+        let wrapper_ident = syn::Ident::new(&format!("{}_wrapper", ident), ident.span());
+        let finfo_ident = syn::Ident::new(&format!("pg_finfo_{}_wrapper", ident), ident.span());
+        let naming = LibraryNaming::current();
+        let wrapper_export = naming.export_attribute(&wrapper_ident.to_string());
+        let finfo_export = naming.finfo_export_attribute(&wrapper_ident.to_string());
+        quote! {
+            #[pgx::pg_guard]
+            #wrapper_export
+            pub unsafe extern "C" fn #wrapper_ident(fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::pg_sys::Datum {
+                let trigger = pgx::PgTrigger::from_fcinfo(fcinfo);
+                let result = #ident(&trigger);
+                pgx::IntoTriggerDatum::into_trigger_datum(result)
+            }
+
+            #finfo_export
+            #[doc(hidden)]
+            pub extern "C" fn #finfo_ident() -> &'static pgx::pg_sys::Pg_finfo_record {
+                const V1_API: pgx::pg_sys::Pg_finfo_record = pgx::pg_sys::Pg_finfo_record { api_version: 1 };
+                &V1_API
+            }
+        }
+    }
+
+    fn entity_tokens(&self) -> TokenStream2 {
+        let ident = &self.func.sig.ident;
+        let to_sql_config = &self.to_sql_config;
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_trigger_{}", ident),
+            Span::call_site(),
+        );
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                use core::any::TypeId;
+                extern crate alloc;
+                use alloc::vec::Vec;
+                use alloc::vec;
+                let submission = ::pgx::utils::sql_entity_graph::PgTriggerEntity {
+                    fn_name: stringify!(#ident),
+                    file: file!(),
+                    line: line!(),
+                    module_path: core::module_path!(),
+                    full_path: concat!(core::module_path!(), "::", stringify!(#ident)),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::Trigger(submission)
+            }
+        }
+    }
+}
+
+impl ToTokens for PgTrigger {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let func = &self.func;
+        let wrapper = self.wrapper_tokens();
+        let entity = self.entity_tokens();
+        tokens.append_all(quote! {
+            #func
+            #wrapper
+            #entity
+        });
+    }
+}
+
+impl Parse for PgTrigger {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let func: syn::ItemFn = input.parse()?;
+        Self::from_parts(Punctuated::new(), func)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgTrigger;
+    use quote::quote;
+
+    #[test]
+    fn trigger_with_sql_option() {
+        let parsed = PgTrigger::new(
+            quote! { sql = false },
+            quote! {
+                fn example(trigger: &PgTrigger) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+                    unimplemented!()
+                }
+            },
+        )
+        .expect("trigger should parse");
+        assert!(!parsed.to_sql_config.enabled);
+    }
+
+    #[test]
+    fn trigger_without_argument() {
+        let parsed = PgTrigger::new(
+            quote! {},
+            quote! {
+                fn example() -> Option<PgHeapTuple<'static, AllocatedByPostgres>> {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn trigger_without_return() {
+        let parsed = PgTrigger::new(
+            quote! {},
+            quote! {
+                fn example(trigger: &PgTrigger) {
+                    unimplemented!()
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+}
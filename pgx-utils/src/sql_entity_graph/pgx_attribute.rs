@@ -22,6 +22,46 @@ pub struct PgxAttribute {
     pub args: Vec<PgxArg>,
 }
 
+impl PgxAttribute {
+    /// Is there a bare `flag`, as in `#[pgx(flag)]`, among the `#[pgx]` attributes in `attrs`?
+    pub fn has_flag(attrs: &[syn::Attribute], flag: &str) -> syn::Result<bool> {
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            if attr
+                .args
+                .iter()
+                .any(|arg| matches!(arg, PgxArg::Path(path) if path.is_ident(flag)))
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The string `value` of a `name = "value"`, as in `#[pgx(name = "value")]`, among the `#[pgx]`
+    /// attributes in `attrs`
+    pub fn str_value(attrs: &[syn::Attribute], name: &str) -> syn::Result<Option<syn::LitStr>> {
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args {
+                match arg {
+                    PgxArg::NameValue(nv) if nv.path.is_ident(name) => match nv.value {
+                        ArgValue::Lit(syn::Lit::Str(value)) => return Ok(Some(value)),
+                        ArgValue::Lit(lit) => {
+                            return Err(syn::Error::new(lit.span(), "expected a string"))
+                        }
+                        ArgValue::Path(path) => {
+                            return Err(syn::Error::new_spanned(path, "expected a string"))
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
 impl Parse for PgxAttribute {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
         let parser = Punctuated::<PgxArg, Token![,]>::parse_terminated;
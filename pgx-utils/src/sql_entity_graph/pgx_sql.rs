@@ -10,7 +10,7 @@ use eyre::{eyre, WrapErr};
 use std::{any::TypeId, collections::HashMap, fmt::Debug, path::Path};
 
 use owo_colors::{OwoColorize, XtermColors};
-use petgraph::{dot::Dot, graph::NodeIndex, stable_graph::StableGraph};
+use petgraph::{dot::Dot, graph::NodeIndex, stable_graph::StableGraph, visit::EdgeRef};
 use tracing::instrument;
 
 use crate::sql_entity_graph::{
@@ -20,9 +20,13 @@ use crate::sql_entity_graph::{
         entity::{ExtensionSqlEntity, SqlDeclaredEntity},
         SqlDeclared,
     },
+    identifier,
     mapping::{RustSourceOnlySqlMapping, RustSqlMapping},
+    pg_event_trigger::entity::PgEventTriggerEntity,
     pg_extern::entity::{PgExternEntity, PgExternReturnEntity},
+    pg_trigger::entity::PgTriggerEntity,
     positioning_ref::PositioningRef,
+    postgres_composite::entity::PostgresCompositeEntity,
     postgres_enum::entity::PostgresEnumEntity,
     postgres_hash::entity::PostgresHashEntity,
     postgres_ord::entity::PostgresOrdEntity,
@@ -31,7 +35,7 @@ use crate::sql_entity_graph::{
     to_sql::ToSql,
     SqlGraphEntity, SqlGraphIdentifier,
 };
-use crate::versioned_so_name;
+use crate::LibraryNaming;
 
 /// A generator for SQL.
 ///
@@ -62,11 +66,15 @@ pub struct PgxSql {
     pub types: HashMap<PostgresTypeEntity, NodeIndex>,
     pub builtin_types: HashMap<String, NodeIndex>,
     pub enums: HashMap<PostgresEnumEntity, NodeIndex>,
+    pub composites: HashMap<PostgresCompositeEntity, NodeIndex>,
     pub ords: HashMap<PostgresOrdEntity, NodeIndex>,
     pub hashes: HashMap<PostgresHashEntity, NodeIndex>,
     pub aggregates: HashMap<PgAggregateEntity, NodeIndex>,
+    pub triggers: HashMap<PgTriggerEntity, NodeIndex>,
+    pub event_triggers: HashMap<PgEventTriggerEntity, NodeIndex>,
     pub extension_name: String,
     pub versioned_so: bool,
+    pub library_naming: LibraryNaming,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
@@ -84,6 +92,7 @@ impl PgxSql {
         entities: impl Iterator<Item = SqlGraphEntity>,
         extension_name: String,
         versioned_so: bool,
+        library_naming: LibraryNaming,
     ) -> eyre::Result<Self> {
         let mut graph = StableGraph::new();
 
@@ -96,9 +105,12 @@ impl PgxSql {
         let mut externs: Vec<PgExternEntity> = Vec::default();
         let mut types: Vec<PostgresTypeEntity> = Vec::default();
         let mut enums: Vec<PostgresEnumEntity> = Vec::default();
+        let mut composites: Vec<PostgresCompositeEntity> = Vec::default();
         let mut ords: Vec<PostgresOrdEntity> = Vec::default();
         let mut hashes: Vec<PostgresHashEntity> = Vec::default();
         let mut aggregates: Vec<PgAggregateEntity> = Vec::default();
+        let mut triggers: Vec<PgTriggerEntity> = Vec::default();
+        let mut event_triggers: Vec<PgEventTriggerEntity> = Vec::default();
         for entity in entities {
             match entity {
                 SqlGraphEntity::ExtensionRoot(input_control) => {
@@ -120,6 +132,9 @@ impl PgxSql {
                 SqlGraphEntity::Enum(input_enum) => {
                     enums.push(input_enum);
                 }
+                SqlGraphEntity::Composite(input_composite) => {
+                    composites.push(input_composite);
+                }
                 SqlGraphEntity::Ord(input_ord) => {
                     ords.push(input_ord);
                 }
@@ -129,6 +144,12 @@ impl PgxSql {
                 SqlGraphEntity::Aggregate(input_hash) => {
                     aggregates.push(input_hash);
                 }
+                SqlGraphEntity::Trigger(input_trigger) => {
+                    triggers.push(input_trigger);
+                }
+                SqlGraphEntity::EventTrigger(input_event_trigger) => {
+                    event_triggers.push(input_event_trigger);
+                }
             }
         }
 
@@ -148,6 +169,8 @@ impl PgxSql {
         let mapped_schemas = initialize_schemas(&mut graph, bootstrap, finalize, schemas)?;
         let mapped_enums = initialize_enums(&mut graph, root, bootstrap, finalize, enums)?;
         let mapped_types = initialize_types(&mut graph, root, bootstrap, finalize, types)?;
+        let mapped_composites =
+            initialize_composites(&mut graph, root, bootstrap, finalize, composites)?;
         let (mapped_externs, mut mapped_builtin_types) = initialize_externs(
             &mut graph,
             root,
@@ -156,6 +179,7 @@ impl PgxSql {
             externs,
             &mapped_types,
             &mapped_enums,
+            &mapped_composites,
         )?;
         let mapped_ords = initialize_ords(&mut graph, root, bootstrap, finalize, ords)?;
         let mapped_hashes = initialize_hashes(&mut graph, root, bootstrap, finalize, hashes)?;
@@ -169,6 +193,9 @@ impl PgxSql {
             &mapped_enums,
             &mapped_types,
         )?;
+        let mapped_triggers = initialize_triggers(&mut graph, root, bootstrap, finalize, triggers)?;
+        let mapped_event_triggers =
+            initialize_event_triggers(&mut graph, root, bootstrap, finalize, event_triggers)?;
 
         // Now we can circle back and build up the edge sets.
         connect_schemas(&mut graph, &mapped_schemas, root);
@@ -178,18 +205,29 @@ impl PgxSql {
             &mapped_schemas,
             &mapped_types,
             &mapped_enums,
+            &mapped_composites,
             &mapped_externs,
+            &mapped_triggers,
         )?;
         connect_enums(&mut graph, &mapped_enums, &mapped_schemas);
         connect_types(&mut graph, &mapped_types, &mapped_schemas);
+        connect_composites(
+            &mut graph,
+            &mapped_composites,
+            &mapped_schemas,
+            &mapped_types,
+            &mapped_enums,
+        );
         connect_externs(
             &mut graph,
             &mapped_externs,
             &mapped_schemas,
             &mapped_types,
             &mapped_enums,
+            &mapped_composites,
             &mapped_builtin_types,
             &mapped_extension_sqls,
+            &mapped_triggers,
         )?;
         connect_ords(
             &mut graph,
@@ -216,6 +254,8 @@ impl PgxSql {
             &mapped_builtin_types,
             &mapped_externs,
         );
+        connect_triggers(&mut graph, &mapped_triggers, &mapped_schemas);
+        connect_event_triggers(&mut graph, &mapped_event_triggers, &mapped_schemas);
 
         let mut this = Self {
             type_mappings: type_mappings.map(|x| (x.id.clone(), x)).collect(),
@@ -227,17 +267,25 @@ impl PgxSql {
             types: mapped_types,
             builtin_types: mapped_builtin_types,
             enums: mapped_enums,
+            composites: mapped_composites,
             ords: mapped_ords,
             hashes: mapped_hashes,
             aggregates: mapped_aggregates,
+            triggers: mapped_triggers,
+            event_triggers: mapped_event_triggers,
             graph: graph,
             graph_root: root,
             graph_bootstrap: bootstrap,
             graph_finalize: finalize,
             extension_name: extension_name,
             versioned_so,
+            library_naming,
         };
         this.register_types();
+        this.check_identifiers()?;
+        this.check_overloads()?;
+        this.check_conflict_arbiters()?;
+        this.check_renames()?;
         Ok(this)
     }
 
@@ -348,6 +396,10 @@ impl PgxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#C9A7C8\", weight = 5, shape = \"oval\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::Composite(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#B8A6C9\", weight = 5, shape = \"oval\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::Ord(_item) => format!(
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFCFD3\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
@@ -360,6 +412,14 @@ impl PgxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::Trigger(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#ADC7C6\", weight = 4, shape = \"box\"",
+                        node.dot_identifier()
+                    ),
+                    SqlGraphEntity::EventTrigger(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#ADC7C6\", weight = 4, shape = \"box\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::CustomSql(_item) => format!(
                         "label = \"{}\", weight = 3, shape = \"signature\"",
                         node.dot_identifier()
@@ -426,6 +486,124 @@ impl PgxSql {
         Ok(full_sql)
     }
 
+    /// The statements an upgrade script needs to rename the types and functions marked
+    /// `renamed_from`, instead of dropping them and creating them again, which would drop
+    /// everything depending on them too.
+    ///
+    /// Types come first, so the functions taking them are renamed by their new names.
+    #[instrument(level = "error", skip(self))]
+    pub fn renames_sql(&self) -> eyre::Result<String> {
+        let mut types = self
+            .types
+            .keys()
+            .filter_map(|item| Some((item.file, item.line, item.rename_sql(self)?)))
+            .chain(
+                self.enums
+                    .keys()
+                    .filter_map(|item| Some((item.file, item.line, item.rename_sql(self)?))),
+            )
+            .collect::<Vec<_>>();
+        types.sort();
+
+        let mut functions = Vec::new();
+        for item in self.externs.keys() {
+            if let Some(sql) = item.rename_sql(self)? {
+                functions.push((item.file, item.line, sql));
+            }
+        }
+        functions.sort();
+
+        let mut renames = String::new();
+        for (_, _, sql) in types.into_iter().chain(functions) {
+            renames.push_str(&sql);
+            renames.push('\n');
+        }
+        Ok(renames)
+    }
+
+    /// Explain why the entities matching `needle` exist, and where they are ordered in the output.
+    ///
+    /// `needle` may be a Rust identifier (or a `::` suffix of one), a DOT identifier, or an entity ID
+    /// as found in the SQL anchor comments.
+    #[instrument(level = "error", skip(self))]
+    pub fn trace(&self, needle: &str) -> eyre::Result<String> {
+        let ordered = petgraph::algo::toposort(&self.graph, None).map_err(|e| {
+            eyre!(
+                "Failed to toposort SQL entities, node with cycle: {:?}",
+                self.graph[e.node_id()]
+            )
+        })?;
+        let describe = |index: NodeIndex| {
+            let entity = &self.graph[index];
+            match (entity.file(), entity.line()) {
+                (Some(file), Some(line)) => {
+                    format!("{} ({}:{})", entity.dot_identifier(), file, line)
+                }
+                _ => entity.dot_identifier(),
+            }
+        };
+
+        let mut traced = String::new();
+        for (position, index) in ordered.iter().enumerate() {
+            let entity = &self.graph[*index];
+            let rust_identifier = entity.rust_identifier();
+            let matches = rust_identifier == needle
+                || rust_identifier.ends_with(&format!("::{}", needle))
+                || entity.dot_identifier() == needle
+                || entity.entity_id() == needle;
+            if !matches {
+                continue;
+            }
+
+            traced.push_str(&format!("{}\n", entity.dot_identifier()));
+            traced.push_str(&format!("  entity: {}\n", entity.entity_id()));
+            if let (Some(file), Some(line)) = (entity.file(), entity.line()) {
+                traced.push_str(&format!("  defined at: {}:{}\n", file, line));
+            }
+            traced.push_str(&format!(
+                "  position: {} of {}\n",
+                position + 1,
+                ordered.len()
+            ));
+
+            traced.push_str("  requires:\n");
+            for edge in self
+                .graph
+                .edges_directed(*index, petgraph::Direction::Incoming)
+            {
+                traced.push_str(&format!(
+                    "    {} ({:?})\n",
+                    describe(edge.source()),
+                    edge.weight()
+                ));
+            }
+
+            traced.push_str("  required by:\n");
+            for edge in self
+                .graph
+                .edges_directed(*index, petgraph::Direction::Outgoing)
+            {
+                traced.push_str(&format!(
+                    "    {} ({:?})\n",
+                    describe(edge.target()),
+                    edge.weight()
+                ));
+            }
+
+            if let Some(before) = position.checked_sub(1).map(|before| ordered[before]) {
+                traced.push_str(&format!("  ordered after: {}\n", describe(before)));
+            }
+            if let Some(after) = ordered.get(position + 1) {
+                traced.push_str(&format!("  ordered before: {}\n", describe(*after)));
+            }
+        }
+
+        if traced.is_empty() {
+            return Err(eyre!("No SQL entity matching `{}` was found", needle));
+        }
+        Ok(traced)
+    }
+
     #[instrument(level = "error", skip(self))]
     pub fn register_types(&mut self) {
         for (item, _index) in self.enums.clone() {
@@ -439,6 +617,17 @@ impl PgxSql {
                 );
             }
         }
+        for (item, _index) in self.composites.clone() {
+            for mapping in &item.mappings {
+                assert_eq!(
+                    self.type_mappings
+                        .insert(mapping.id.clone(), mapping.clone()),
+                    None,
+                    "Cannot map `{}` twice.",
+                    item.full_path,
+                );
+            }
+        }
         for (item, _index) in self.types.clone() {
             for mapping in &item.mappings {
                 assert_eq!(
@@ -452,6 +641,285 @@ impl PgxSql {
         }
     }
 
+    /// Warn about names which Postgres will truncate, or which callers will have to quote, and
+    /// fail on names which can't be created unquoted, or which clash once Postgres truncates them
+    /// or folds their case.
+    #[instrument(level = "error", skip(self))]
+    pub fn check_identifiers(&self) -> eyre::Result<()> {
+        // Functions are created with quoted names, so any name works, but may be awkward to call
+        for item in self.externs.keys() {
+            check_identifier_length("function", item.name, &item.rust_identifier());
+            if identifier::needs_quoting(item.name) {
+                tracing::warn!(
+                    "`{}` is created as SQL function `\"{}\"`, which has to be quoted when it's called",
+                    item.rust_identifier(),
+                    item.name,
+                );
+            }
+        }
+
+        // Everything else is created with unquoted names
+        let mut unquoted = Vec::new();
+        for item in self.schemas.keys() {
+            unquoted.push(("schema", item.name, item.rust_identifier(), None));
+        }
+        for (item, index) in &self.types {
+            unquoted.push(("type", item.name, item.rust_identifier(), Some(*index)));
+        }
+        for (item, index) in &self.enums {
+            unquoted.push(("type", item.name, item.rust_identifier(), Some(*index)));
+        }
+        for (item, index) in &self.composites {
+            unquoted.push(("type", item.name, item.rust_identifier(), Some(*index)));
+        }
+        for (item, index) in &self.aggregates {
+            unquoted.push(("aggregate", item.name, item.rust_identifier(), Some(*index)));
+        }
+        unquoted.sort_by(|left, right| left.2.cmp(&right.2));
+
+        let mut seen: HashMap<(&str, String, String), (&str, String)> = HashMap::default();
+        for (kind, name, rust_identifier, index) in unquoted {
+            check_identifier_length(kind, name, &rust_identifier);
+            if identifier::is_reserved_keyword(name) {
+                return Err(eyre!(
+                    "`{}` would be created as SQL {} `{}`, which is a reserved keyword in Postgres. \
+                    Rename it.",
+                    rust_identifier,
+                    kind,
+                    name,
+                ));
+            }
+            // Aggregates are functions, and may be overloaded
+            if kind == "aggregate" {
+                continue;
+            }
+            // Every `#[pg_schema]` module with the same name shares one schema
+            let schema = index
+                .and_then(|index| self.schema_alias_of(&index))
+                .unwrap_or_default();
+            let folded = identifier::truncate(&name.to_lowercase()).to_string();
+            if let Some((other_name, other_rust_identifier)) = seen.insert(
+                (kind, schema, folded.clone()),
+                (name, rust_identifier.clone()),
+            ) {
+                if kind != "schema" || other_name != name {
+                    return Err(eyre!(
+                        "`{}` (SQL {kind} `{}`) and `{}` (SQL {kind} `{}`) would both be created as \
+                        SQL {kind} `{}`, as Postgres folds unquoted names to lowercase and \
+                        truncates them to {} bytes.",
+                        other_rust_identifier,
+                        other_name,
+                        rust_identifier,
+                        name,
+                        folded,
+                        identifier::NAMEDATALEN - 1,
+                        kind = kind,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find `#[pg_extern]`s which would create the same function, or overloads Postgres couldn't
+    /// tell apart once defaults are filled in, which otherwise only fails at `CREATE EXTENSION`.
+    #[instrument(level = "error", skip(self))]
+    pub fn check_overloads(&self) -> eyre::Result<()> {
+        let mut signatures: HashMap<(String, &str), Vec<(&PgExternEntity, Vec<String>, usize)>> =
+            HashMap::default();
+        let mut externs = self.externs.iter().collect::<Vec<_>>();
+        externs.sort_by_key(|(item, _)| (item.file, item.line));
+        'externs: for (item, index) in externs {
+            // Hand-written SQL can call the function anything
+            if !item.to_sql_config.enabled
+                || item.to_sql_config.content.is_some()
+                || item.to_sql_config.callback.is_some()
+            {
+                continue;
+            }
+            let mut arg_types = Vec::with_capacity(item.fn_args.len());
+            for arg in &item.fn_args {
+                let sql_type = match self.rust_to_sql(arg.ty_id, arg.ty_source, arg.full_path) {
+                    Some(sql_type) => sql_type,
+                    // Reported when the function is rendered
+                    None => continue 'externs,
+                };
+                let schema_prefix = self
+                    .graph
+                    .neighbors_undirected(*index)
+                    .find(|neighbor| match &self.graph[*neighbor] {
+                        SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
+                        SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
+                        SqlGraphEntity::Composite(composite) => composite.id_matches(&arg.ty_id),
+                        _ => false,
+                    })
+                    .map(|neighbor| self.schema_prefix_for(&neighbor))
+                    .unwrap_or_default();
+                arg_types.push((schema_prefix + &sql_type).to_lowercase());
+            }
+            let required = item
+                .fn_args
+                .iter()
+                .take_while(|arg| arg.default.is_none())
+                .count();
+            let schema = item
+                .schema
+                .map(String::from)
+                .or_else(|| self.schema_alias_of(index))
+                .unwrap_or_default();
+
+            // Names which only differ after Postgres truncates them are overloads too
+            let overloads = signatures
+                .entry((schema, identifier::truncate(item.name)))
+                .or_default();
+            for (other, other_arg_types, other_required) in overloads.iter() {
+                if let Some(arg_count) =
+                    overload_conflict(&arg_types, required, other_arg_types, *other_required)
+                {
+                    let problem = if arg_types == *other_arg_types {
+                        String::from("have the same SQL signature")
+                    } else {
+                        format!("are ambiguous when called with {} arguments", arg_count)
+                    };
+                    return Err(eyre!(
+                        "`{}` ({}:{}) and `{}` ({}:{}) {} as SQL function `{}({})`. \
+                        Rename one with `#[pg_extern(name = \"...\")]`.",
+                        other.full_path,
+                        other.file,
+                        other.line,
+                        item.full_path,
+                        item.file,
+                        item.line,
+                        problem,
+                        item.name,
+                        arg_types[..arg_count].join(", "),
+                    ));
+                }
+            }
+            overloads.push((item, arg_types, required));
+        }
+        Ok(())
+    }
+
+    /// Make sure the types marked `#[pgx(conflict_arbiter)]` have what a unique index on them
+    /// needs, so they can be used by `INSERT ... ON CONFLICT`: an `=` operator, and a default btree
+    /// operator class for it to be in.
+    pub fn check_conflict_arbiters(&self) -> eyre::Result<()> {
+        let types = self
+            .types
+            .keys()
+            .filter(|ty| ty.conflict_arbiter)
+            .map(|ty| (ty.full_path, ty.file, ty.line, &ty.mappings));
+        let enums = self
+            .enums
+            .keys()
+            .filter(|en| en.conflict_arbiter)
+            .map(|en| (en.full_path, en.file, en.line, &en.mappings));
+        for (full_path, file, line, mappings) in types.chain(enums) {
+            let id_matches = |id: &TypeId| mappings.iter().any(|mapping| mapping.id == *id);
+            let has_eq = self.externs.keys().any(|item| {
+                matches!(&item.operator, Some(op) if op.opname == Some("="))
+                    && item.fn_args.len() == 2
+                    && item.fn_args.iter().all(|arg| id_matches(&arg.ty_id))
+            });
+            if !has_eq {
+                return Err(eyre!(
+                    "`{}` ({}:{}) is marked `#[pgx(conflict_arbiter)]`, but has no `=` operator. \
+                    Add `#[derive(PostgresEq)]` to it.",
+                    full_path,
+                    file,
+                    line,
+                ));
+            }
+            if !self.ords.keys().any(|ord| id_matches(&ord.id)) {
+                return Err(eyre!(
+                    "`{}` ({}:{}) is marked `#[pgx(conflict_arbiter)]`, but has no btree operator \
+                    class for a unique index to use. Add `#[derive(PostgresOrd)]` to it.",
+                    full_path,
+                    file,
+                    line,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure nothing is still declared under the name something else is `renamed_from`, which
+    /// would have to be created while the old one is renamed away from under it.
+    pub fn check_renames(&self) -> eyre::Result<()> {
+        let types = self
+            .types
+            .keys()
+            .map(|ty| (ty.name, ty.full_path, ty.renamed_from, self.types[ty]))
+            .chain(
+                self.enums
+                    .keys()
+                    .map(|en| (en.name, en.full_path, en.renamed_from, self.enums[en])),
+            )
+            .collect::<Vec<_>>();
+        for (_, full_path, renamed_from, index) in &types {
+            let renamed_from: &str = match renamed_from {
+                Some(renamed_from) => renamed_from,
+                None => continue,
+            };
+            let schema = self.schema_alias_of(index);
+            if let Some((_, other, _, _)) = types.iter().find(|(other, _, _, other_index)| {
+                other.eq_ignore_ascii_case(renamed_from)
+                    && self.schema_alias_of(other_index) == schema
+            }) {
+                return Err(eyre!(
+                    "`{}` is renamed from SQL type `{}`, but `{}` is still declared as `{}`.",
+                    full_path,
+                    renamed_from,
+                    other,
+                    renamed_from,
+                ));
+            }
+            if types
+                .iter()
+                .any(|(_, other, other_renamed_from, other_index)| {
+                    other != full_path
+                        && *other_renamed_from == Some(renamed_from)
+                        && self.schema_alias_of(other_index) == schema
+                })
+            {
+                return Err(eyre!(
+                    "`{}` and another type are both renamed from SQL type `{}`.",
+                    full_path,
+                    renamed_from,
+                ));
+            }
+        }
+
+        for item in self.externs.keys() {
+            let renamed_from = match item.renamed_from {
+                Some(renamed_from) => renamed_from,
+                None => continue,
+            };
+            // A function is named by its arguments too, so only one with the same ones clashes
+            if let Some(other) = self.externs.keys().find(|other| {
+                other.name == renamed_from
+                    && other.schema == item.schema
+                    && other.fn_args.len() == item.fn_args.len()
+                    && other
+                        .fn_args
+                        .iter()
+                        .zip(&item.fn_args)
+                        .all(|(left, right)| left.ty_id == right.ty_id)
+            }) {
+                return Err(eyre!(
+                    "`{}` is renamed from SQL function `{}`, but `{}` is still declared as `{}` \
+                    with the same arguments.",
+                    item.full_path,
+                    renamed_from,
+                    other.full_path,
+                    renamed_from,
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn has_sql_declared_entity(&self, identifier: &SqlDeclared) -> Option<&SqlDeclaredEntity> {
         self.extension_sqls.iter().find_map(|(item, _index)| {
             let retval = item.creates.iter().find_map(|create_entity| {
@@ -504,16 +972,50 @@ impl PgxSql {
     }
 
     pub fn get_module_pathname(&self) -> String {
-        return if self.versioned_so {
-            let extname = &self.extension_name;
-            let extver = &self.control.default_version;
-            format!("$libdir/{}", versioned_so_name(extname, extver))
-        } else {
-            String::from("MODULE_PATHNAME")
-        };
+        self.library_naming.module_pathname(
+            &self.extension_name,
+            &self.control.default_version,
+            self.versioned_so,
+        )
+    }
+
+    /// The name `symbol` is exported from the extension's library as
+    pub fn get_symbol_name(&self, symbol: &str) -> String {
+        self.library_naming.symbol(symbol)
+    }
+}
+
+/// Postgres silently truncates long names, so point it out when it will happen.
+fn check_identifier_length(kind: &str, name: &str, rust_identifier: &str) {
+    let truncated = identifier::truncate(name);
+    if truncated != name {
+        tracing::warn!(
+            "`{}` is created as SQL {} `{}`, which is longer than {} bytes (`NAMEDATALEN` - 1), \
+            so Postgres will silently truncate it to `{}`",
+            rust_identifier,
+            kind,
+            name,
+            identifier::NAMEDATALEN - 1,
+            truncated,
+        );
     }
 }
 
+/// Can a call with some number of arguments match both signatures?  Postgres refuses such a call
+/// as ambiguous, so returns the number of arguments if so.
+///
+/// Each signature is its argument types, and how many leading arguments don't have a default.
+fn overload_conflict(
+    left: &[String],
+    left_required: usize,
+    right: &[String],
+    right_required: usize,
+) -> Option<usize> {
+    let shortest = left_required.max(right_required);
+    let longest = left.len().min(right.len());
+    (shortest..=longest).find(|&arg_count| left[..arg_count] == right[..arg_count])
+}
+
 #[tracing::instrument(level = "error", skip_all)]
 fn build_base_edges(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
@@ -594,9 +1096,11 @@ pub fn find_positioning_ref_target<'a>(
     positioning_ref: &'a PositioningRef,
     types: &'a HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &'a HashMap<PostgresEnumEntity, NodeIndex>,
+    composites: &'a HashMap<PostgresCompositeEntity, NodeIndex>,
     externs: &'a HashMap<PgExternEntity, NodeIndex>,
     schemas: &'a HashMap<SchemaEntity, NodeIndex>,
     extension_sqls: &'a HashMap<ExtensionSqlEntity, NodeIndex>,
+    triggers: &'a HashMap<PgTriggerEntity, NodeIndex>,
 ) -> Option<&'a NodeIndex> {
     match positioning_ref {
         PositioningRef::FullPath(path) => {
@@ -616,6 +1120,11 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+            for (other, other_index) in composites {
+                if last_segment == &other.name && other.module_path.ends_with(&module_path) {
+                    return Some(&other_index);
+                }
+            }
             for (other, other_index) in externs {
                 if *last_segment == other.unaliased_name
                     && other.module_path.ends_with(&module_path)
@@ -623,6 +1132,11 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+            for (other, other_index) in triggers {
+                if *last_segment == other.fn_name && other.module_path.ends_with(&module_path) {
+                    return Some(&other_index);
+                }
+            }
             for (other, other_index) in schemas {
                 if other.module_path.ends_with(path) {
                     return Some(&other_index);
@@ -647,7 +1161,9 @@ fn connect_extension_sqls(
     schemas: &HashMap<SchemaEntity, NodeIndex>,
     types: &HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    composites: &HashMap<PostgresCompositeEntity, NodeIndex>,
     externs: &HashMap<PgExternEntity, NodeIndex>,
+    triggers: &HashMap<PgTriggerEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in extension_sqls {
         make_schema_connection(
@@ -664,9 +1180,11 @@ fn connect_extension_sqls(
                 requires,
                 types,
                 enums,
+                composites,
                 externs,
                 schemas,
                 extension_sqls,
+                triggers,
             ) {
                 tracing::debug!(from = %item.rust_identifier(), to = ?graph[*target].rust_identifier(), "Adding ExtensionSQL after positioning ref target");
                 graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -795,6 +1313,66 @@ fn connect_types(
     }
 }
 
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_composites(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    composites: Vec<PostgresCompositeEntity>,
+) -> eyre::Result<HashMap<PostgresCompositeEntity, NodeIndex>> {
+    let mut mapped_composites = HashMap::default();
+    for item in composites {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_composites.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_composites)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_composites(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    composites: &HashMap<PostgresCompositeEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+    types: &HashMap<PostgresTypeEntity, NodeIndex>,
+    enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+) {
+    for (item, &index) in composites {
+        make_schema_connection(
+            graph,
+            "Composite",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+
+        for field in &item.fields {
+            let found = make_type_or_enum_connection(
+                graph,
+                "Composite",
+                index,
+                &item.rust_identifier(),
+                &field.ty_id,
+                types,
+                enums,
+            );
+            if found {
+                continue;
+            }
+            for (other, &other_index) in composites {
+                if other.id_matches(&field.ty_id) {
+                    tracing::debug!(from = %item.rust_identifier(), to = %other.rust_identifier(), "Adding Composite after Composite (due to field) edge");
+                    graph.add_edge(other_index, index, SqlGraphRelationship::RequiredBy);
+                    break;
+                }
+            }
+        }
+    }
+}
+
 #[tracing::instrument(level = "error", skip_all)]
 fn initialize_externs(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
@@ -804,6 +1382,7 @@ fn initialize_externs(
     externs: Vec<PgExternEntity>,
     mapped_types: &HashMap<PostgresTypeEntity, NodeIndex>,
     mapped_enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    mapped_composites: &HashMap<PostgresCompositeEntity, NodeIndex>,
 ) -> eyre::Result<(
     HashMap<PgExternEntity, NodeIndex>,
     HashMap<String, NodeIndex>,
@@ -830,6 +1409,12 @@ fn initialize_externs(
                     break;
                 }
             }
+            for (ty_item, &_ty_index) in mapped_composites {
+                if ty_item.id_matches(&arg.ty_id) {
+                    found = true;
+                    break;
+                }
+            }
             if !found {
                 mapped_builtin_types
                     .entry(arg.full_path.to_string())
@@ -856,6 +1441,12 @@ fn initialize_externs(
                         break;
                     }
                 }
+                for (ty_item, &_ty_index) in mapped_composites {
+                    if ty_item.id_matches(id) {
+                        found = true;
+                        break;
+                    }
+                }
                 if !found {
                     mapped_builtin_types
                         .entry(full_path.to_string())
@@ -879,6 +1470,12 @@ fn initialize_externs(
                             break;
                         }
                     }
+                    for (ty_item, &_ty_index) in mapped_composites {
+                        if ty_item.id_matches(&iterated_return.0) {
+                            found = true;
+                            break;
+                        }
+                    }
                     if !found {
                         mapped_builtin_types
                             .entry(iterated_return.1.to_string())
@@ -902,8 +1499,10 @@ fn connect_externs(
     schemas: &HashMap<SchemaEntity, NodeIndex>,
     types: &HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    composites: &HashMap<PostgresCompositeEntity, NodeIndex>,
     builtin_types: &HashMap<String, NodeIndex>,
     extension_sqls: &HashMap<ExtensionSqlEntity, NodeIndex>,
+    triggers: &HashMap<PgTriggerEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in externs {
         make_schema_connection(
@@ -923,9 +1522,11 @@ fn connect_externs(
                             requires,
                             types,
                             enums,
+                            composites,
                             externs,
                             schemas,
                             extension_sqls,
+                            triggers,
                         ) {
                             tracing::debug!(from = %item.rust_identifier(), to = %graph[*target].rust_identifier(), "Adding Extern after positioning ref target");
                             graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -934,6 +1535,23 @@ fn connect_externs(
                         }
                     }
                 }
+                crate::ExternArgs::Support(support) => {
+                    if let Some(target) = find_positioning_ref_target(
+                        support,
+                        types,
+                        enums,
+                        composites,
+                        externs,
+                        schemas,
+                        extension_sqls,
+                        triggers,
+                    ) {
+                        tracing::debug!(from = %item.rust_identifier(), to = %graph[*target].rust_identifier(), "Adding Extern after support function edge");
+                        graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
+                    } else {
+                        return Err(eyre!("Could not find `support` function: {:?}", support));
+                    }
+                }
                 _ => (),
             }
         }
@@ -958,6 +1576,16 @@ fn connect_externs(
                     }
                 }
             }
+            if !found {
+                for (composite_item, &composite_index) in composites {
+                    if composite_item.id_matches(&arg.ty_id) {
+                        tracing::debug!(from = %item.rust_identifier(), to = %composite_item.rust_identifier(), "Adding Extern after Composite (due to argument) edge");
+                        graph.add_edge(composite_index, index, SqlGraphRelationship::RequiredByArg);
+                        found = true;
+                        break;
+                    }
+                }
+            }
             if !found {
                 let builtin_index = builtin_types
                     .get(arg.full_path)
@@ -1004,6 +1632,16 @@ fn connect_externs(
                         }
                     }
                 }
+                if !found {
+                    for (ty_item, &ty_index) in composites {
+                        if ty_item.id_matches(id) {
+                            tracing::debug!(from = %item.rust_identifier(), to = %ty_item.rust_identifier(), "Adding Extern after Composite (due to return) edge");
+                            graph.add_edge(ty_index, index, SqlGraphRelationship::RequiredByReturn);
+                            found = true;
+                            break;
+                        }
+                    }
+                }
                 if !found {
                     let builtin_index = builtin_types
                         .get(&full_path.to_string())
@@ -1056,6 +1694,20 @@ fn connect_externs(
                             }
                         }
                     }
+                    if !found {
+                        for (ty_item, &ty_index) in composites {
+                            if ty_item.id_matches(&iterated_return.0) {
+                                tracing::debug!(from = %item.rust_identifier(), to = %ty_item.rust_identifier(), "Adding Extern after Composite (due to return) edge");
+                                graph.add_edge(
+                                    ty_index,
+                                    index,
+                                    SqlGraphRelationship::RequiredByReturn,
+                                );
+                                found = true;
+                                break;
+                            }
+                        }
+                    }
                     if !found {
                         let builtin_index = builtin_types
                             .get(&iterated_return.1.to_string())
@@ -1232,6 +1884,78 @@ fn connect_hashes(
     }
 }
 
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_triggers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    triggers: Vec<PgTriggerEntity>,
+) -> eyre::Result<HashMap<PgTriggerEntity, NodeIndex>> {
+    let mut mapped_triggers = HashMap::default();
+    for item in triggers {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_triggers.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_triggers)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_triggers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    triggers: &HashMap<PgTriggerEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+) {
+    for (item, &index) in triggers {
+        make_schema_connection(
+            graph,
+            "Trigger",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+    }
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_event_triggers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    event_triggers: Vec<PgEventTriggerEntity>,
+) -> eyre::Result<HashMap<PgEventTriggerEntity, NodeIndex>> {
+    let mut mapped_event_triggers = HashMap::default();
+    for item in event_triggers {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_event_triggers.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_event_triggers)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_event_triggers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    event_triggers: &HashMap<PgEventTriggerEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+) {
+    for (item, &index) in event_triggers {
+        make_schema_connection(
+            graph,
+            "Event trigger",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+    }
+}
+
 fn initialize_aggregates(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
     root: NodeIndex,
@@ -1545,3 +2269,161 @@ fn make_type_or_enum_connection(
 
     found
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{overload_conflict, PgxSql};
+    use crate::sql_entity_graph::{
+        ControlFile, ExtensionSqlEntity, PositioningRef, SqlGraphEntity, SqlGraphIdentifier,
+    };
+    use crate::LibraryNaming;
+
+    fn types(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn extension_sql(name: &'static str, line: u32, requires: &[&str]) -> SqlGraphEntity {
+        SqlGraphEntity::CustomSql(ExtensionSqlEntity {
+            module_path: "tests",
+            full_path: "tests::extension_sql",
+            sql: "",
+            file: "src/lib.rs",
+            line,
+            name,
+            bootstrap: false,
+            finalize: false,
+            requires: requires
+                .iter()
+                .map(|name| PositioningRef::Name(name.to_string()))
+                .collect(),
+            creates: Vec::new(),
+        })
+    }
+
+    /// An extension with `second` requiring `first`
+    fn pgx_sql() -> PgxSql {
+        let root = SqlGraphEntity::ExtensionRoot(ControlFile {
+            comment: String::from("tests"),
+            default_version: String::from("1.0"),
+            module_pathname: None,
+            relocatable: false,
+            superuser: true,
+            schema: None,
+        });
+        let entities = vec![
+            root,
+            extension_sql("second", 20, &["first"]),
+            extension_sql("first", 10, &[]),
+        ];
+        PgxSql::build(
+            std::iter::empty(),
+            std::iter::empty(),
+            entities.into_iter(),
+            String::from("tests"),
+            false,
+            LibraryNaming::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn trace_explains_order() {
+        let pgx_sql = pgx_sql();
+        assert_eq!(
+            pgx_sql.trace("first").unwrap(),
+            "\
+            sql first\n  \
+              entity: 409326bc60ae6329\n  \
+              defined at: src/lib.rs:10\n  \
+              position: 2 of 3\n  \
+              requires:\n    \
+                extension root (RequiredBy)\n  \
+              required by:\n    \
+                sql second (src/lib.rs:20) (RequiredBy)\n  \
+              ordered after: extension root\n  \
+              ordered before: sql second (src/lib.rs:20)\n\
+            "
+        );
+
+        let second = pgx_sql.trace("second").unwrap();
+        assert!(second.contains("  position: 3 of 3\n"), "{}", second);
+        assert!(
+            second.contains("  ordered after: sql first (src/lib.rs:10)\n"),
+            "{}",
+            second
+        );
+        assert!(!second.contains("ordered before"), "{}", second);
+    }
+
+    #[test]
+    fn trace_finds_entity_ids() {
+        let pgx_sql = pgx_sql();
+        assert_eq!(
+            pgx_sql.trace("0f5bf72869a74d25").unwrap(),
+            pgx_sql.trace("second").unwrap()
+        );
+        assert_eq!(
+            pgx_sql.trace("sql second").unwrap(),
+            pgx_sql.trace("second").unwrap()
+        );
+        assert!(pgx_sql.trace("third").is_err());
+    }
+
+    struct Named(&'static str);
+
+    impl SqlGraphIdentifier for Named {
+        fn dot_identifier(&self) -> String {
+            self.0.to_string()
+        }
+        fn rust_identifier(&self) -> String {
+            self.0.to_string()
+        }
+        fn file(&self) -> Option<&'static str> {
+            None
+        }
+        fn line(&self) -> Option<u32> {
+            None
+        }
+    }
+
+    #[test]
+    fn entity_ids_are_stable() {
+        // the FNV-1a reference values, which these must never drift from, or every anchor comment
+        // in already generated SQL would stop matching
+        assert_eq!(Named("").entity_id(), "cbf29ce484222325");
+        assert_eq!(Named("a").entity_id(), "af63dc4c8601ec8c");
+        assert_eq!(
+            extension_sql("first", 10, &[]).entity_id(),
+            "409326bc60ae6329"
+        );
+        assert_eq!(
+            extension_sql("first", 99, &["second"]).entity_id(),
+            "409326bc60ae6329"
+        );
+    }
+
+    #[test]
+    fn same_signature_conflicts() {
+        let args = types(&["integer", "text"]);
+        assert_eq!(overload_conflict(&args, 2, &args, 2), Some(2));
+    }
+
+    #[test]
+    fn different_signatures_dont_conflict() {
+        let left = types(&["integer", "text"]);
+        let right = types(&["bigint", "text"]);
+        assert_eq!(overload_conflict(&left, 2, &right, 2), None);
+        assert_eq!(overload_conflict(&left, 2, &left[..1], 1), None);
+    }
+
+    #[test]
+    fn defaults_conflict() {
+        let left = types(&["integer"]);
+        let right = types(&["integer", "text"]);
+        // `f(integer, text DEFAULT ..)` can be called as `f(integer)`
+        assert_eq!(overload_conflict(&left, 1, &right, 1), Some(1));
+        assert_eq!(overload_conflict(&left, 1, &right, 2), None);
+        // all defaulted, so `f()` would be ambiguous
+        assert_eq!(overload_conflict(&left, 0, &types(&["text"]), 0), Some(0));
+    }
+}
@@ -0,0 +1,151 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    mapping::RustSqlMapping,
+    pgx_sql::PgxSql,
+    to_sql::{entity::ToSqlConfigEntity, ToSql},
+    SqlGraphEntity, SqlGraphIdentifier,
+};
+
+use eyre::eyre;
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// The output of a [`PostgresComposite`](crate::sql_entity_graph::postgres_composite::PostgresComposite) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresCompositeEntity {
+    pub name: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    /// The `#[pg_schema]` the composite is in, which `type_oid()` looks it up in
+    pub schema: Option<&'static str>,
+    pub mappings: std::collections::HashSet<RustSqlMapping>,
+    pub fields: Vec<PostgresCompositeFieldEntity>,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+/// A field of a [`PostgresCompositeEntity`], which becomes an attribute of the composite type.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PostgresCompositeFieldEntity {
+    pub name: &'static str,
+    pub ty_source: &'static str,
+    pub ty_id: core::any::TypeId,
+    pub full_path: &'static str,
+}
+
+impl Hash for PostgresCompositeEntity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.full_path.hash(state);
+    }
+}
+
+impl Ord for PostgresCompositeEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+    }
+}
+
+impl PartialOrd for PostgresCompositeEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PostgresCompositeEntity {
+    pub fn id_matches(&self, candidate: &core::any::TypeId) -> bool {
+        self.mappings.iter().any(|tester| *candidate == tester.id)
+    }
+}
+
+impl Into<SqlGraphEntity> for PostgresCompositeEntity {
+    fn into(self) -> SqlGraphEntity {
+        SqlGraphEntity::Composite(self)
+    }
+}
+
+impl SqlGraphIdentifier for PostgresCompositeEntity {
+    fn dot_identifier(&self) -> String {
+        format!("composite {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PostgresCompositeEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.composites[self];
+        // the library finds the type in the schema it was compiled with, so it must be this one
+        let schema = context
+            .graph
+            .neighbors_undirected(self_index)
+            .find_map(|neighbor| match &context.graph[neighbor] {
+                SqlGraphEntity::Schema(schema) => Some(schema.name),
+                _ => None,
+            });
+        if schema != self.schema {
+            return Err(eyre!(
+                "Composite type `{}` is created in schema {:?}, but its `type_oid()` looks in {:?}.",
+                self.full_path,
+                schema,
+                self.schema,
+            ));
+        }
+
+        let mut fields = Vec::new();
+        for field in &self.fields {
+            let sql_type = context
+                .rust_to_sql(field.ty_id, field.ty_source, field.full_path)
+                .ok_or_else(|| {
+                    eyre!(
+                        "Failed to map field `{}` type `{}` to SQL type while building composite type `{}`.",
+                        field.name,
+                        field.full_path,
+                        self.full_path
+                    )
+                })?;
+            fields.push(format!(
+                "\t\"{name}\" {sql_type} /* {full_path} */",
+                name = field.name,
+                sql_type = sql_type,
+                full_path = field.full_path,
+            ));
+        }
+
+        let sql = format!(
+            "\n\
+            {sql_anchor_comment}\n\
+            CREATE TYPE {schema}{name} AS (\n\
+                {fields}\n\
+            );\
+            ",
+            sql_anchor_comment = self.sql_anchor_comment(),
+            schema = context.schema_prefix_for(&self_index),
+            name = self.name,
+            fields = fields.join(",\n"),
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
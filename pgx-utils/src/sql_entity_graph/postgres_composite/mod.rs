@@ -0,0 +1,306 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+mod attribute;
+pub mod entity;
+
+use crate::sql_entity_graph::ToSqlConfig;
+use attribute::PostgresCompositeAttribute;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{parse::Parser, punctuated::Punctuated, Token};
+
+/// A parsed `#[pg_composite]` item.
+///
+/// It should be created with [`PostgresComposite::new`].
+///
+/// Using [`quote::ToTokens`] will output the struct, its `FromDatum`/`IntoDatum` implementations,
+/// and the declaration for a [`PostgresCompositeEntity`][crate::sql_entity_graph::PostgresCompositeEntity].
+///
+/// ```rust
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PostgresComposite;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed = PostgresComposite::new(
+///     quote! {},
+///     quote! {
+///         struct Dog {
+///             name: String,
+///             scritches: Option<i32>,
+///         }
+///     },
+/// )?;
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostgresComposite {
+    item: syn::ItemStruct,
+    to_sql_config: ToSqlConfig,
+    schema: Option<String>,
+}
+
+impl PostgresComposite {
+    pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<Self, syn::Error> {
+        let parser = Punctuated::<PostgresCompositeAttribute, Token![,]>::parse_terminated;
+        let attrs = parser.parse2(attr)?;
+        let item = syn::parse2::<syn::ItemStruct>(item)?;
+
+        let mut to_sql_config = None;
+        let mut schema = None;
+        for attr in attrs {
+            match attr {
+                PostgresCompositeAttribute::Sql(config) => {
+                    to_sql_config.get_or_insert(config);
+                }
+                PostgresCompositeAttribute::Schema(name) => {
+                    schema.get_or_insert(name);
+                }
+            }
+        }
+
+        if !item.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item.generics,
+                "`#[pg_composite]` structs cannot be generic",
+            ));
+        }
+        match &item.fields {
+            syn::Fields::Named(fields) if !fields.named.is_empty() => (),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &item,
+                    "`#[pg_composite]` structs must have named fields",
+                ))
+            }
+        }
+
+        Ok(Self {
+            item,
+            to_sql_config: to_sql_config.unwrap_or_default(),
+            schema,
+        })
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &syn::Field> {
+        self.item.fields.iter()
+    }
+
+    fn datum_tokens(&self) -> TokenStream2 {
+        let ident = &self.item.ident;
+        let name = ident.to_string();
+        // `CREATE TYPE` leaves the name unquoted, so Postgres folds it to lowercase
+        let sql_name = name.to_lowercase();
+        // as is `CREATE SCHEMA`'s
+        let schema = match &self.schema {
+            Some(schema) => {
+                let schema = schema.to_lowercase();
+                quote! { Some(#schema) }
+            }
+            None => quote! { None },
+        };
+
+        let from_fields = self.fields().map(|field| {
+            let field_ident = field.ident.as_ref().expect("fields are named");
+            let field_name = field_ident.to_string();
+            let null_message = format!("`{}.{}` is NULL", name, field_name);
+            if is_option(&field.ty) {
+                quote! {
+                    #field_ident: tuple.get_by_name(#field_name).unwrap_or_else(|e| panic!("{}", e))
+                }
+            } else {
+                quote! {
+                    #field_ident: tuple
+                        .get_by_name(#field_name)
+                        .unwrap_or_else(|e| panic!("{}", e))
+                        .unwrap_or_else(|| panic!(#null_message))
+                }
+            }
+        });
+        let into_fields = self.fields().map(|field| {
+            let field_ident = field.ident.as_ref().expect("fields are named");
+            let field_name = field_ident.to_string();
+            quote! {
+                tuple
+                    .set_by_name(#field_name, self.#field_ident)
+                    .unwrap_or_else(|e| panic!("{}", e));
+            }
+        });
+
+        quote! {
+            impl ::pgx::FromDatum for #ident {
+                unsafe fn from_datum(
+                    datum: ::pgx::pg_sys::Datum,
+                    is_null: bool,
+                    _typoid: ::pgx::pg_sys::Oid,
+                ) -> Option<Self> {
+                    if is_null {
+                        return None;
+                    }
+                    let tuple = ::pgx::PgHeapTuple::from_composite_datum(datum);
+                    Some(#ident {
+                        #( #from_fields, )*
+                    })
+                }
+            }
+
+            impl ::pgx::IntoDatum for #ident {
+                fn into_datum(self) -> Option<::pgx::pg_sys::Datum> {
+                    let mut tuple = ::pgx::PgHeapTuple::new_composite_type(Self::type_oid());
+                    #( #into_fields )*
+                    tuple.into_composite_datum()
+                }
+
+                fn type_oid() -> ::pgx::pg_sys::Oid {
+                    // in the schema the SQL creates it in, rather than wherever the `search_path` says
+                    static TYPE_OID: ::pgx::ExtensionTypeOid =
+                        ::pgx::ExtensionTypeOid::new(env!("CARGO_CRATE_NAME"), #schema, #sql_name);
+                    TYPE_OID.get()
+                }
+            }
+        }
+    }
+
+    fn entity_tokens(&self) -> TokenStream2 {
+        let ident = &self.item.ident;
+        let to_sql_config = &self.to_sql_config;
+        let schema = match &self.schema {
+            Some(schema) => quote! { Some(#schema) },
+            None => quote! { None },
+        };
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_composite_{}", ident),
+            Span::call_site(),
+        );
+        let fields = self.fields().map(|field| {
+            let field_ident = field.ident.as_ref().expect("fields are named");
+            let ty = &field.ty;
+            let ty_string = ty.to_token_stream().to_string().replace(" ", "");
+            quote! {
+                ::pgx::utils::sql_entity_graph::PostgresCompositeFieldEntity {
+                    name: stringify!(#field_ident),
+                    ty_source: #ty_string,
+                    ty_id: TypeId::of::<#ty>(),
+                    full_path: core::any::type_name::<#ty>(),
+                }
+            }
+        });
+
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                use core::any::TypeId;
+                extern crate alloc;
+                use alloc::vec::Vec;
+                use alloc::vec;
+                let mut mappings = Default::default();
+                <#ident as ::pgx::datum::WithTypeIds>::register_with_refs(&mut mappings, stringify!(#ident).to_string());
+                ::pgx::datum::WithSizedTypeIds::<#ident>::register_sized_with_refs(&mut mappings, stringify!(#ident).to_string());
+                ::pgx::datum::WithArrayTypeIds::<#ident>::register_array_with_refs(&mut mappings, stringify!(#ident).to_string());
+                ::pgx::datum::WithVarlenaTypeIds::<#ident>::register_varlena_with_refs(&mut mappings, stringify!(#ident).to_string());
+
+                let submission = ::pgx::utils::sql_entity_graph::PostgresCompositeEntity {
+                    name: stringify!(#ident),
+                    file: file!(),
+                    line: line!(),
+                    module_path: module_path!(),
+                    full_path: core::any::type_name::<#ident>(),
+                    schema: #schema,
+                    mappings,
+                    fields: vec![ #( #fields ),* ],
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::Composite(submission)
+            }
+        }
+    }
+}
+
+/// Is `ty` written as an `Option<...>`?
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+impl ToTokens for PostgresComposite {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let item = &self.item;
+        let datum = self.datum_tokens();
+        let entity = self.entity_tokens();
+        tokens.append_all(quote! {
+            #item
+            #datum
+            #entity
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostgresComposite;
+    use quote::quote;
+
+    #[test]
+    fn composite_with_sql_option() {
+        let parsed = PostgresComposite::new(
+            quote! { sql = false },
+            quote! {
+                struct Dog {
+                    name: String,
+                }
+            },
+        )
+        .expect("composite should parse");
+        assert!(!parsed.to_sql_config.enabled);
+    }
+
+    #[test]
+    fn composite_with_schema() {
+        let parsed = PostgresComposite::new(
+            quote! { schema = "pets", sql = false },
+            quote! {
+                struct Dog {
+                    name: String,
+                }
+            },
+        )
+        .expect("composite should parse");
+        assert_eq!(parsed.schema.as_deref(), Some("pets"));
+        assert!(!parsed.to_sql_config.enabled);
+    }
+
+    #[test]
+    fn composite_tuple_struct() {
+        let parsed = PostgresComposite::new(quote! {}, quote! { struct Dog(String); });
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn composite_generic_struct() {
+        let parsed = PostgresComposite::new(
+            quote! {},
+            quote! {
+                struct Dog<'a> {
+                    name: &'a str,
+                }
+            },
+        );
+        assert!(parsed.is_err());
+    }
+}
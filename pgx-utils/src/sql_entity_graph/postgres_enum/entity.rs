@@ -29,6 +29,11 @@ pub struct PostgresEnumEntity {
     pub mappings: std::collections::HashSet<RustSqlMapping>,
     pub variants: Vec<&'static str>,
     pub to_sql_config: ToSqlConfigEntity,
+    /// Marked `#[pgx(conflict_arbiter)]`, so it must be usable in a unique index
+    pub conflict_arbiter: bool,
+    /// The SQL name the type had before, from `#[pgx(renamed_from = ..)]`, which an upgrade script
+    /// renames it from
+    pub renamed_from: Option<&'static str>,
 }
 
 impl Hash for PostgresEnumEntity {
@@ -55,6 +60,27 @@ impl PostgresEnumEntity {
     pub fn id_matches(&self, candidate: &core::any::TypeId) -> bool {
         self.mappings.iter().any(|tester| *candidate == tester.id)
     }
+    /// For an upgrade script, the `ALTER TYPE .. RENAME TO` of a type marked
+    /// `#[pgx(renamed_from = ..)]`, which keeps the columns and functions using it
+    pub fn rename_sql(&self, context: &PgxSql) -> Option<String> {
+        let renamed_from = self
+            .renamed_from
+            .filter(|renamed_from| *renamed_from != self.name)?;
+        let self_index = context.enums[self];
+        Some(format!(
+            "\n\
+                -- {file}:{line}\n\
+                -- {full_path}, renamed from `{renamed_from}`\n\
+                ALTER TYPE {schema}{renamed_from} RENAME TO {name};\
+            ",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            renamed_from = renamed_from,
+            schema = context.schema_prefix_for(&self_index),
+            name = self.name,
+        ))
+    }
 }
 
 impl Into<SqlGraphEntity> for PostgresEnumEntity {
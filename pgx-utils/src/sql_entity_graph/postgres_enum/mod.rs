@@ -8,7 +8,7 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 pub mod entity;
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{PgxAttribute, ToSqlConfig};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens, TokenStreamExt};
 use syn::{
@@ -45,6 +45,8 @@ pub struct PostgresEnum {
     generics: Generics,
     variants: Punctuated<syn::Variant, Token![,]>,
     to_sql_config: ToSqlConfig,
+    conflict_arbiter: bool,
+    renamed_from: Option<syn::LitStr>,
 }
 
 impl PostgresEnum {
@@ -59,24 +61,32 @@ impl PostgresEnum {
             generics,
             variants,
             to_sql_config,
+            conflict_arbiter: false,
+            renamed_from: None,
         }
     }
 
     pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let conflict_arbiter = PgxAttribute::has_flag(&derive_input.attrs, "conflict_arbiter")?;
+        let renamed_from = PgxAttribute::str_value(&derive_input.attrs, "renamed_from")?;
         let data_enum = match derive_input.data {
             syn::Data::Enum(data_enum) => data_enum,
             syn::Data::Union(_) | syn::Data::Struct(_) => {
                 return Err(syn::Error::new(derive_input.ident.span(), "expected enum"))
             }
         };
-        Ok(Self::new(
-            derive_input.ident,
-            derive_input.generics,
-            data_enum.variants,
-            to_sql_config,
-        ))
+        Ok(Self {
+            conflict_arbiter,
+            renamed_from,
+            ..Self::new(
+                derive_input.ident,
+                derive_input.generics,
+                data_enum.variants,
+                to_sql_config,
+            )
+        })
     }
 }
 
@@ -85,12 +95,18 @@ impl Parse for PostgresEnum {
         let parsed: ItemEnum = input.parse()?;
         let to_sql_config =
             ToSqlConfig::from_attributes(parsed.attrs.as_slice())?.unwrap_or_default();
-        Ok(Self::new(
-            parsed.ident,
-            parsed.generics,
-            parsed.variants,
-            to_sql_config,
-        ))
+        let conflict_arbiter = PgxAttribute::has_flag(&parsed.attrs, "conflict_arbiter")?;
+        let renamed_from = PgxAttribute::str_value(&parsed.attrs, "renamed_from")?;
+        Ok(Self {
+            conflict_arbiter,
+            renamed_from,
+            ..Self::new(
+                parsed.ident,
+                parsed.generics,
+                parsed.variants,
+                to_sql_config,
+            )
+        })
     }
 }
 
@@ -104,11 +120,13 @@ impl ToTokens for PostgresEnum {
         }
         let (_impl_generics, ty_generics, _where_clauses) = static_generics.split_for_impl();
 
-        let variants = self.variants.iter();
+        let variants = self.variants.iter().map(|variant| &variant.ident);
         let sql_graph_entity_fn_name =
             syn::Ident::new(&format!("__pgx_internals_enum_{}", name), Span::call_site());
 
         let to_sql_config = &self.to_sql_config;
+        let conflict_arbiter = self.conflict_arbiter;
+        let renamed_from = self.renamed_from.iter();
 
         let inv = quote! {
             #[no_mangle]
@@ -132,6 +150,8 @@ impl ToTokens for PostgresEnum {
                     mappings,
                     variants: vec![ #(  stringify!(#variants)  ),* ],
                     to_sql_config: #to_sql_config,
+                    conflict_arbiter: #conflict_arbiter,
+                    renamed_from: None #( .unwrap_or(Some(#renamed_from)) )*,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Enum(submission)
             }
@@ -32,7 +32,26 @@ pub struct PostgresTypeEntity {
     pub in_fn_module_path: String,
     pub out_fn: &'static str,
     pub out_fn_module_path: String,
+    /// The binary send function, from `#[sendrecvfuncs]`, which is in the type's module
+    pub send_fn: Option<&'static str>,
+    /// The binary receive function, from `#[sendrecvfuncs]`, which is in the type's module
+    pub recv_fn: Option<&'static str>,
     pub to_sql_config: ToSqlConfigEntity,
+    /// Marked `#[pgx(conflict_arbiter)]`, so it must be usable in a unique index
+    pub conflict_arbiter: bool,
+    /// The SQL name the type had before, from `#[pgx(renamed_from = ..)]`, which an upgrade script
+    /// renames it from
+    pub renamed_from: Option<&'static str>,
+    /// How Postgres TOASTs values, from `#[pgx(storage = ..)]`: `plain`, `extended` (the
+    /// default), `main` or `external`
+    pub storage: &'static str,
+    /// The size of values of a type marked `#[pgx(fixed_size)]` or `#[pgx(passedbyvalue)]`, or
+    /// `None` for the usual varlena types
+    pub internal_length: Option<usize>,
+    /// The alignment of values of a fixed-size type, in bytes
+    pub alignment: Option<usize>,
+    /// Marked `#[pgx(passedbyvalue)]`, so values are passed in the `Datum` itself
+    pub passed_by_value: bool,
 }
 
 impl Hash for PostgresTypeEntity {
@@ -59,6 +78,49 @@ impl PostgresTypeEntity {
     pub fn id_matches(&self, candidate: &core::any::TypeId) -> bool {
         self.mappings.iter().any(|tester| *candidate == tester.id)
     }
+    /// For an upgrade script, the `ALTER TYPE .. RENAME TO` of a type marked
+    /// `#[pgx(renamed_from = ..)]`, which keeps the columns and functions using it
+    pub fn rename_sql(&self, context: &PgxSql) -> Option<String> {
+        let renamed_from = self
+            .renamed_from
+            .filter(|renamed_from| *renamed_from != self.name)?;
+        let self_index = context.types[self];
+        Some(format!(
+            "\n\
+                -- {file}:{line}\n\
+                -- {full_path}, renamed from `{renamed_from}`\n\
+                ALTER TYPE {schema}{renamed_from} RENAME TO {name};\
+            ",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            renamed_from = renamed_from,
+            schema = context.schema_prefix_for(&self_index),
+            name = self.name,
+        ))
+    }
+
+    /// The `INTERNALLENGTH`, `PASSEDBYVALUE` and `ALIGNMENT` options of the type's `CREATE TYPE`
+    fn layout_options(&self) -> String {
+        let internal_length = match self.internal_length {
+            Some(length) => length.to_string(),
+            None => String::from("variable"),
+        };
+        let mut options = format!("\tINTERNALLENGTH = {},\n", internal_length);
+        if self.passed_by_value {
+            options += "\tPASSEDBYVALUE,\n";
+        }
+        if let Some(alignment) = self.alignment {
+            let alignment = match alignment {
+                1 => "char",
+                2 => "int2",
+                4 => "int4",
+                _ => "double",
+            };
+            options += &format!("\tALIGNMENT = {},\n", alignment);
+        }
+        options
+    }
 }
 
 impl Into<SqlGraphEntity> for PostgresTypeEntity {
@@ -168,6 +230,35 @@ impl ToSql for PostgresTypeEntity {
         let out_fn_sql = out_fn.to_sql(context)?;
         tracing::trace!(%out_fn_sql);
 
+        // and `SEND`/`RECEIVE`, which are optional, along with them
+        let mut send_recv_sql = String::new();
+        let mut send_recv_options = String::new();
+        for (option, func) in [("SEND", item.send_fn), ("RECEIVE", item.recv_fn)] {
+            let func = match func {
+                Some(func) => func,
+                None => continue,
+            };
+            let func_path = format!("{}::{}", item.module_path, func);
+            let (func_graph_index, func_entity) = context
+                .graph
+                .neighbors_undirected(self_index)
+                .find_map(|neighbor| match &context.graph[neighbor] {
+                    SqlGraphEntity::Function(entity) if entity.full_path == func_path => {
+                        Some((neighbor, entity))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| eyre!("Could not find `{}` graph entity.", func_path))?;
+            send_recv_sql += &format!("\n{}", func_entity.to_sql(context)?);
+            send_recv_options += &format!(
+                "\t{option} = {schema_prefix}{func}, /* {func_path} */\n",
+                option = option,
+                schema_prefix = context.schema_prefix_for(&func_graph_index),
+                func = func,
+                func_path = func_path,
+            );
+        }
+
         let shell_type = format!(
             "\n\
                                 -- {file}:{line}\n\
@@ -186,10 +277,11 @@ impl ToSql for PostgresTypeEntity {
                                 -- {file}:{line}\n\
                                 -- {full_path}\n\
                                 CREATE TYPE {schema}{name} (\n\
-                                    \tINTERNALLENGTH = variable,\n\
+                                    {layout_options}\
                                     \tINPUT = {schema_prefix_in_fn}{in_fn}, /* {in_fn_path} */\n\
                                     \tOUTPUT = {schema_prefix_out_fn}{out_fn}, /* {out_fn_path} */\n\
-                                    \tSTORAGE = extended\n\
+                                    {send_recv_options}\
+                                    \tSTORAGE = {storage}\n\
                                 );\
                             ",
                                         full_path = item.full_path,
@@ -203,9 +295,19 @@ impl ToSql for PostgresTypeEntity {
                                         schema_prefix_out_fn = context.schema_prefix_for(&out_fn_graph_index),
                                         out_fn = item.out_fn,
                                         out_fn_path = out_fn_path,
+                                        layout_options = item.layout_options(),
+                                        send_recv_options = send_recv_options,
+                                        storage = item.storage,
         );
         tracing::trace!(sql = %materialized_type);
 
-        Ok(shell_type + "\n" + &in_fn_sql + "\n" + &out_fn_sql + "\n" + &materialized_type)
+        Ok(shell_type
+            + "\n"
+            + &in_fn_sql
+            + "\n"
+            + &out_fn_sql
+            + &send_recv_sql
+            + "\n"
+            + &materialized_type)
     }
 }
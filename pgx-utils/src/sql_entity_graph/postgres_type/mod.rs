@@ -19,7 +19,7 @@ use syn::{
     DeriveInput, Generics, ItemStruct,
 };
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{PgxAttribute, ToSqlConfig};
 
 /// A parsed `#[derive(PostgresType)]` item.
 ///
@@ -49,7 +49,17 @@ pub struct PostgresType {
     generics: Generics,
     in_fn: Ident,
     out_fn: Ident,
+    /// The binary send and receive functions, for `#[sendrecvfuncs]` types
+    send_recv_fns: Option<(Ident, Ident)>,
     to_sql_config: ToSqlConfig,
+    conflict_arbiter: bool,
+    renamed_from: Option<syn::LitStr>,
+    /// How Postgres TOASTs values, from `#[pgx(storage = ..)]`
+    storage: Option<syn::LitStr>,
+    /// Stored as the bytes of the Rust value, from `#[pgx(fixed_size)]` or `#[pgx(passedbyvalue)]`
+    fixed_size: bool,
+    /// Passed in the `Datum` itself, from `#[pgx(passedbyvalue)]`
+    passed_by_value: bool,
 }
 
 impl PostgresType {
@@ -65,10 +75,59 @@ impl PostgresType {
             name,
             in_fn,
             out_fn,
+            send_recv_fns: None,
             to_sql_config,
+            conflict_arbiter: false,
+            renamed_from: None,
+            storage: None,
+            fixed_size: false,
+            passed_by_value: false,
         }
     }
 
+    /// Whether a type is `#[pgx(fixed_size)]`, or `#[pgx(passedbyvalue)]`, which is fixed-size too
+    fn layout(attrs: &[syn::Attribute]) -> Result<(bool, bool), syn::Error> {
+        let passed_by_value = PgxAttribute::has_flag(attrs, "passedbyvalue")?;
+        let fixed_size = passed_by_value || PgxAttribute::has_flag(attrs, "fixed_size")?;
+        Ok((fixed_size, passed_by_value))
+    }
+
+    /// The `#[pgx(storage = ..)]` of a type, which must be one of Postgres' storage strategies,
+    /// and `plain` for fixed-size types, which aren't TOASTed
+    fn storage(
+        attrs: &[syn::Attribute],
+        fixed_size: bool,
+    ) -> Result<Option<syn::LitStr>, syn::Error> {
+        let storage = match PgxAttribute::str_value(attrs, "storage")? {
+            Some(storage) => storage,
+            None => return Ok(None),
+        };
+        match storage.value().to_lowercase().as_str() {
+            "plain" => Ok(Some(syn::LitStr::new("plain", storage.span()))),
+            _ if fixed_size => Err(syn::Error::new(
+                storage.span(),
+                "fixed-size types can only be `storage = \"plain\"`",
+            )),
+            value @ ("extended" | "main" | "external") => {
+                Ok(Some(syn::LitStr::new(value, storage.span())))
+            }
+            _ => Err(syn::Error::new(
+                storage.span(),
+                "expected \"plain\", \"extended\", \"main\" or \"external\"",
+            )),
+        }
+    }
+
+    /// The `_send` and `_recv` functions of a type with the `#[sendrecvfuncs]` attribute
+    fn send_recv_fns(ident: &Ident, attrs: &[syn::Attribute]) -> Option<(Ident, Ident)> {
+        if !attrs.iter().any(|attr| attr.path.is_ident("sendrecvfuncs")) {
+            return None;
+        }
+        let send = Ident::new(&format!("{}_send", ident).to_lowercase(), ident.span());
+        let recv = Ident::new(&format!("{}_recv", ident).to_lowercase(), ident.span());
+        Some((send, recv))
+    }
+
     pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
         let _data_struct = match derive_input.data {
             syn::Data::Struct(data_struct) => data_struct,
@@ -81,6 +140,10 @@ impl PostgresType {
         };
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let conflict_arbiter = PgxAttribute::has_flag(&derive_input.attrs, "conflict_arbiter")?;
+        let renamed_from = PgxAttribute::str_value(&derive_input.attrs, "renamed_from")?;
+        let (fixed_size, passed_by_value) = Self::layout(&derive_input.attrs)?;
+        let storage = Self::storage(&derive_input.attrs, fixed_size)?;
         let funcname_in = Ident::new(
             &format!("{}_in", derive_input.ident).to_lowercase(),
             derive_input.ident.span(),
@@ -89,13 +152,22 @@ impl PostgresType {
             &format!("{}_out", derive_input.ident).to_lowercase(),
             derive_input.ident.span(),
         );
-        Ok(Self::new(
-            derive_input.ident,
-            derive_input.generics,
-            funcname_in,
-            funcname_out,
-            to_sql_config,
-        ))
+        let send_recv_fns = Self::send_recv_fns(&derive_input.ident, &derive_input.attrs);
+        Ok(Self {
+            conflict_arbiter,
+            renamed_from,
+            send_recv_fns,
+            storage,
+            fixed_size,
+            passed_by_value,
+            ..Self::new(
+                derive_input.ident,
+                derive_input.generics,
+                funcname_in,
+                funcname_out,
+                to_sql_config,
+            )
+        })
     }
 
     pub fn inventory_fn_name(&self) -> String {
@@ -118,6 +190,10 @@ impl Parse for PostgresType {
         let parsed: ItemStruct = input.parse()?;
         let to_sql_config =
             ToSqlConfig::from_attributes(parsed.attrs.as_slice())?.unwrap_or_default();
+        let conflict_arbiter = PgxAttribute::has_flag(&parsed.attrs, "conflict_arbiter")?;
+        let renamed_from = PgxAttribute::str_value(&parsed.attrs, "renamed_from")?;
+        let (fixed_size, passed_by_value) = Self::layout(&parsed.attrs)?;
+        let storage = Self::storage(&parsed.attrs, fixed_size)?;
         let funcname_in = Ident::new(
             &format!("{}_in", parsed.ident).to_lowercase(),
             parsed.ident.span(),
@@ -126,13 +202,22 @@ impl Parse for PostgresType {
             &format!("{}_out", parsed.ident).to_lowercase(),
             parsed.ident.span(),
         );
-        Ok(Self::new(
-            parsed.ident,
-            parsed.generics,
-            funcname_in,
-            funcname_out,
-            to_sql_config,
-        ))
+        let send_recv_fns = Self::send_recv_fns(&parsed.ident, &parsed.attrs);
+        Ok(Self {
+            conflict_arbiter,
+            renamed_from,
+            send_recv_fns,
+            storage,
+            fixed_size,
+            passed_by_value,
+            ..Self::new(
+                parsed.ident,
+                parsed.generics,
+                funcname_in,
+                funcname_out,
+                to_sql_config,
+            )
+        })
     }
 }
 
@@ -147,6 +232,13 @@ impl ToTokens for PostgresType {
 
         let in_fn = &self.in_fn;
         let out_fn = &self.out_fn;
+        let (send_fn, recv_fn) = match &self.send_recv_fns {
+            Some((send, recv)) => (
+                quote! { Some(stringify!(#send)) },
+                quote! { Some(stringify!(#recv)) },
+            ),
+            None => (quote! { None }, quote! { None }),
+        };
 
         let sql_graph_entity_fn_name = syn::Ident::new(
             &format!("__pgx_internals_type_{}", self.name),
@@ -154,6 +246,27 @@ impl ToTokens for PostgresType {
         );
 
         let to_sql_config = &self.to_sql_config;
+        let conflict_arbiter = self.conflict_arbiter;
+        let renamed_from = self.renamed_from.iter();
+        let storage = match &self.storage {
+            Some(storage) => storage.value(),
+            None if self.fixed_size => String::from("plain"),
+            None => String::from("extended"),
+        };
+        let internal_length = if self.fixed_size {
+            quote! { Some(core::mem::size_of::<#name #ty_generics>()) }
+        } else {
+            quote! { None }
+        };
+        // values passed by value are stored as an integer of their size, and aligned as one
+        let alignment = if self.passed_by_value {
+            quote! { Some(core::mem::size_of::<#name #ty_generics>()) }
+        } else if self.fixed_size {
+            quote! { Some(core::mem::align_of::<#name #ty_generics>()) }
+        } else {
+            quote! { None }
+        };
+        let passed_by_value = self.passed_by_value;
 
         let inv = quote! {
             #[no_mangle]
@@ -181,6 +294,10 @@ impl ToTokens for PostgresType {
                     &mut mappings,
                     stringify!(#name).to_string()
                 );
+                pgx::datum::WithExpandedTypeIds::<#name #ty_generics>::register_expanded_with_refs(
+                    &mut mappings,
+                    stringify!(#name).to_string()
+                );
                 let submission = ::pgx::utils::sql_entity_graph::PostgresTypeEntity {
                     name: stringify!(#name),
                     file: file!(),
@@ -202,7 +319,15 @@ impl ToTokens for PostgresType {
                         let _ = path_items.pop(); // Drop the one we don't want.
                         path_items.join("::")
                     },
+                    send_fn: #send_fn,
+                    recv_fn: #recv_fn,
                     to_sql_config: #to_sql_config,
+                    conflict_arbiter: #conflict_arbiter,
+                    renamed_from: None #( .unwrap_or(Some(#renamed_from)) )*,
+                    storage: #storage,
+                    internal_length: #internal_length,
+                    alignment: #alignment,
+                    passed_by_value: #passed_by_value,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Type(submission)
             }
@@ -67,6 +67,29 @@ impl ToTokens for Schema {
         // End of hack
 
         let mut updated_content = content_items.clone();
+        // a composite's `type_oid()` looks it up in the schema it's created in
+        for item in updated_content.iter_mut() {
+            if let syn::Item::Struct(item) = item {
+                for attr in item.attrs.iter_mut() {
+                    let is_composite = attr
+                        .path
+                        .segments
+                        .last()
+                        .map(|segment| segment.ident == "pg_composite")
+                        .unwrap_or(false);
+                    if is_composite {
+                        let schema = ident.to_string();
+                        attr.tokens = match syn::parse2::<proc_macro2::Group>(attr.tokens.clone()) {
+                            Ok(args) => {
+                                let args = args.stream();
+                                quote! { (schema = #schema, #args) }
+                            }
+                            Err(_) => quote! { (schema = #schema) },
+                        };
+                    }
+                }
+            }
+        }
         let sql_graph_entity_fn_name = syn::Ident::new(
             &format!("__pgx_internals_schema_{}_{}", ident, postfix),
             Span::call_site(),
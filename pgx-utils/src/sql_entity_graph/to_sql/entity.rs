@@ -6,7 +6,9 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
-use crate::sql_entity_graph::{pgx_sql::PgxSql, to_sql::ToSqlFn, SqlGraphEntity};
+use crate::sql_entity_graph::{
+    pgx_sql::PgxSql, to_sql::ToSqlFn, SqlGraphEntity, SqlGraphIdentifier,
+};
 
 /// Represents configuration options for tuning the SQL generator.
 ///
@@ -266,13 +266,74 @@ CREATE AGGREGATE DemoSum (
 );
 ```
 
+## Parallel Partial Aggregation
+
+Setting `PARALLEL` to `Some(ParallelOption::Safe)` and providing a `combine` function allows
+Postgres to run the aggregate in parallel workers and merge their partial states.
+
+When the state is [`Internal`](crate::datum::Internal), Postgres also needs to move those partial
+states between processes, so `serial` and `deserial` must be provided as well:
+
+```rust
+# use pgx::*;
+#
+pub struct DemoParallelSum;
+
+#[pg_aggregate]
+impl Aggregate for DemoParallelSum {
+    const PARALLEL: Option<ParallelOption> = Some(ParallelOption::Safe);
+    type Args = i64;
+    type State = Internal;
+    type Finalize = i64;
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::State {
+        *unsafe { current.get_or_insert_default::<i64>() } += arg;
+        current
+    }
+
+    fn combine(
+        mut first: Self::State,
+        mut second: Self::State,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::State {
+        let second = *unsafe { second.get_or_insert_default::<i64>() };
+        *unsafe { first.get_or_insert_default::<i64>() } += second;
+        first
+    }
+
+    fn serial(mut current: Self::State, _fcinfo: pg_sys::FunctionCallInfo) -> Vec<u8> {
+        unsafe { current.get_or_insert_default::<i64>() }.to_le_bytes().to_vec()
+    }
+
+    fn deserial(buf: Vec<u8>, _fcinfo: pg_sys::FunctionCallInfo) -> Self::State {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf);
+        Internal::new(i64::from_le_bytes(bytes))
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::Finalize {
+        *unsafe { current.get_or_insert_default::<i64>() }
+    }
+}
+```
+
+`#[pg_aggregate]` will reject implementations which provide only one of `serial`/`deserial`, or
+provide them without `combine`.
+
 */
 
 use crate::{
     error,
     memcxt::PgMemoryContexts,
     pg_sys::{AggCheckCallContext, CurrentMemoryContext, FunctionCallInfo, MemoryContext},
-    pgbox::PgBox,
 };
 
 pub use pgx_utils::sql_entity_graph::{FinalizeModify, ParallelOption};
@@ -349,6 +410,11 @@ where
     /// **Optional:** This const can be skipped, `#[pg_aggregate]` will create a stub.
     const ORDERED_SET: bool = false;
 
+    /// Set to `Some(ParallelOption::Safe)` to allow the aggregate to run in parallel workers.
+    ///
+    /// Partial aggregation additionally requires `combine`, and `serial`/`deserial` when `State` is
+    /// [`Internal`](crate::datum::Internal).
+    ///
     /// **Optional:** This const can be skipped, `#[pg_aggregate]` will create a stub.
     const PARALLEL: Option<ParallelOption> = None;
 
@@ -384,16 +450,21 @@ where
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn combine(current: Self::State, _other: Self::State, fcinfo: FunctionCallInfo) -> Self::State;
 
+    /// Serialize the state into a `bytea` so it can be passed between parallel workers.
+    ///
+    /// Only valid when `State` is [`Internal`](crate::datum::Internal), and requires `combine`
+    /// and `deserial` also be implemented.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn serial(current: Self::State, fcinfo: FunctionCallInfo) -> Vec<u8>;
 
+    /// Deserialize a state previously produced by `serial`.
+    ///
+    /// Only valid when `State` is [`Internal`](crate::datum::Internal), and requires `combine`
+    /// and `serial` also be implemented.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
-    fn deserial(
-        current: Self::State,
-        _buf: Vec<u8>,
-        _internal: PgBox<Self::State>,
-        fcinfo: FunctionCallInfo,
-    ) -> PgBox<Self::State>;
+    fn deserial(buf: Vec<u8>, fcinfo: FunctionCallInfo) -> Self::State;
 
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn moving_state(
@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! State an extension keeps for the lifetime of a backend, such as caches or counters
+//!
+//! Each backend has its own instance of a type, created with [`Default`] the first time it's
+//! used.  [`#[pg_extern_methods]`](macro@crate::pg_extern_methods) exposes methods of such a
+//! type as SQL functions, which use this instance.
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Backends are single threaded, and the instances are boxed, so they don't move as more types
+/// are added
+static mut STATES: Option<HashMap<TypeId, Box<dyn Any>>> = None;
+
+fn backend_state<T: Default + 'static>() -> &'static RefCell<T> {
+    unsafe {
+        STATES
+            .get_or_insert_with(HashMap::new)
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(RefCell::new(T::default())))
+            .downcast_ref()
+            .unwrap()
+    }
+}
+
+/// Call `f` with this backend's instance of `T`
+///
+/// ## Panics
+///
+/// If `f` is called while the instance is borrowed mutably, such as from a function `f` runs
+/// through SPI.
+pub fn with_backend_state<T: Default + 'static, R>(f: impl FnOnce(&T) -> R) -> R {
+    let state = backend_state::<T>().try_borrow().unwrap_or_else(|_| {
+        panic!(
+            "{} is in use by another function",
+            std::any::type_name::<T>()
+        )
+    });
+    f(&state)
+}
+
+/// Call `f` with this backend's instance of `T`, which it can modify
+///
+/// ## Panics
+///
+/// If the instance is already borrowed, such as when `f` runs a function through SPI which uses it
+/// too.
+pub fn with_backend_state_mut<T: Default + 'static, R>(f: impl FnOnce(&mut T) -> R) -> R {
+    let mut state = backend_state::<T>().try_borrow_mut().unwrap_or_else(|_| {
+        panic!(
+            "{} is in use by another function",
+            std::any::type_name::<T>()
+        )
+    });
+    f(&mut state)
+}
@@ -0,0 +1,181 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The live backends and what they're doing, as `pg_stat_activity` shows them, without querying
+//! it through SPI
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use pgx::backend_status::{self, BackendState};
+//! use std::time::Duration;
+//!
+//! /// How many transactions have been idle for longer than `limit`
+//! fn idle_in_transaction(limit: Duration) -> usize {
+//!     let now = unsafe { pg_sys::GetCurrentTimestamp() };
+//!     backend_status::backends()
+//!         .iter()
+//!         .filter(|backend| backend.state == Some(BackendState::IdleInTransaction))
+//!         .filter(|backend| match backend.state_change {
+//!             Some(since) => now - since > limit.as_micros() as i64,
+//!             None => false,
+//!         })
+//!         .count()
+//! }
+//! ```
+//!
+//! As with `pg_stat_activity`, this is a snapshot taken the first time it's read in a transaction,
+//! which stays the same until the transaction ends, or it's [refreshed](refresh).
+use crate::{pg_sys, WaitEvent};
+use std::ffi::CStr;
+
+/// What a backend is doing, as `pg_stat_activity.state` shows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendState {
+    /// `idle`, waiting for a command from its client
+    Idle,
+    /// `active`, running a query
+    Active,
+    /// `idle in transaction`
+    IdleInTransaction,
+    /// `fastpath function call`
+    FastPath,
+    /// `idle in transaction (aborted)`, where the transaction has failed
+    IdleInTransactionAborted,
+    /// `disabled`, as `track_activities` is off for the backend
+    Disabled,
+}
+
+impl BackendState {
+    fn from_pg(state: pg_sys::BackendState) -> Option<Self> {
+        match state {
+            pg_sys::BackendState_STATE_IDLE => Some(BackendState::Idle),
+            pg_sys::BackendState_STATE_RUNNING => Some(BackendState::Active),
+            pg_sys::BackendState_STATE_IDLEINTRANSACTION => Some(BackendState::IdleInTransaction),
+            pg_sys::BackendState_STATE_FASTPATH => Some(BackendState::FastPath),
+            pg_sys::BackendState_STATE_IDLEINTRANSACTION_ABORTED => {
+                Some(BackendState::IdleInTransactionAborted)
+            }
+            pg_sys::BackendState_STATE_DISABLED => Some(BackendState::Disabled),
+            _ => None,
+        }
+    }
+}
+
+/// A backend, or other server process, as a row of `pg_stat_activity` shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendStatus {
+    /// `pid`
+    pub pid: i32,
+    /// `datid`, which is `InvalidOid` for processes not connected to a database
+    pub database_oid: pg_sys::Oid,
+    /// `usesysid`, which is `InvalidOid` for processes not connected as a role
+    pub user_oid: pg_sys::Oid,
+    /// `application_name`, or `None` if the client didn't set one
+    pub application_name: Option<String>,
+    /// `backend_type`, such as `client backend` or `autovacuum worker`
+    pub backend_type: String,
+    /// `state`, which is only known for client backends
+    pub state: Option<BackendState>,
+    /// `wait_event_type` and `wait_event`, while the process is waiting
+    pub wait_event: Option<WaitEvent>,
+    /// `backend_start`
+    pub backend_start: Option<pg_sys::TimestampTz>,
+    /// `xact_start`, while the process is in a transaction
+    pub xact_start: Option<pg_sys::TimestampTz>,
+    /// `query_start`, of the query it's running, or last ran if it's idle
+    pub query_start: Option<pg_sys::TimestampTz>,
+    /// `state_change`, when its `state` last changed
+    pub state_change: Option<pg_sys::TimestampTz>,
+}
+
+/// A timestamp Postgres leaves zero when it's not set
+fn timestamp(timestamp: pg_sys::TimestampTz) -> Option<pg_sys::TimestampTz> {
+    (timestamp != 0).then(|| timestamp)
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+unsafe fn backend_type(status: &pg_sys::PgBackendStatus) -> String {
+    CStr::from_ptr(pg_sys::pgstat_get_backend_desc(status.st_backendType))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+unsafe fn backend_type(status: &pg_sys::PgBackendStatus) -> String {
+    CStr::from_ptr(pg_sys::GetBackendTypeDesc(status.st_backendType))
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl BackendStatus {
+    unsafe fn from_pg(status: &pg_sys::PgBackendStatus) -> Self {
+        let application_name = if status.st_appname.is_null() {
+            None
+        } else {
+            Some(
+                CStr::from_ptr(status.st_appname)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+            .filter(|name| !name.is_empty())
+        };
+        // `pg_stat_activity` reads it from the process's PGPROC, which auxiliary processes such
+        // as the checkpointer don't have one of that can be found this way
+        let proc = pg_sys::BackendPidGetProc(status.st_procpid);
+        let wait_event = if proc.is_null() {
+            None
+        } else {
+            match std::ptr::read_volatile(&(*proc).wait_event_info) {
+                0 => None,
+                info => Some(WaitEvent::from_info(info)),
+            }
+        };
+
+        BackendStatus {
+            pid: status.st_procpid,
+            database_oid: status.st_databaseid,
+            user_oid: status.st_userid,
+            application_name,
+            backend_type: backend_type(status),
+            state: BackendState::from_pg(status.st_state),
+            wait_event,
+            backend_start: timestamp(status.st_proc_start_timestamp),
+            xact_start: timestamp(status.st_xact_start_timestamp),
+            query_start: timestamp(status.st_activity_start_timestamp),
+            state_change: timestamp(status.st_state_start_timestamp),
+        }
+    }
+}
+
+/// Every live backend and other server process, as `pg_stat_activity` lists them
+pub fn backends() -> Vec<BackendStatus> {
+    unsafe {
+        // backends are numbered from 1
+        (1..=pg_sys::pgstat_fetch_stat_numbackends())
+            .filter_map(|i| pg_sys::pgstat_fetch_stat_beentry(i).as_ref())
+            .map(|status| BackendStatus::from_pg(status))
+            .collect()
+    }
+}
+
+/// The backend or other server process with the process ID `pid`, if it's live
+pub fn backend(pid: i32) -> Option<BackendStatus> {
+    unsafe {
+        (1..=pg_sys::pgstat_fetch_stat_numbackends())
+            .filter_map(|i| pg_sys::pgstat_fetch_stat_beentry(i).as_ref())
+            .find(|status| status.st_procpid == pid)
+            .map(|status| BackendStatus::from_pg(status))
+    }
+}
+
+/// Take a new snapshot the next time the backends are read, rather than keeping the one taken
+/// earlier in the transaction, as `pg_stat_clear_snapshot()` does
+pub fn refresh() {
+    unsafe { pg_sys::pgstat_clear_snapshot() }
+}
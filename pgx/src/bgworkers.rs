@@ -10,17 +10,25 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Safely create Postgres Background Workers, including with full SPI support
 //!
 //! See: [https://www.postgresql.org/docs/12/bgworker.html](https://www.postgresql.org/docs/12/bgworker.html)
-use crate::pg_sys;
+use crate::log::{error_data_message, take_error_data};
+use crate::{pg_sys, PgSqlErrorCode, WaitEvent};
 use std::convert::TryInto;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+mod run_async;
+#[cfg(feature = "async")]
+pub use self::run_async::{run_async, run_async_on, PgThread};
 
 pub static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
 static GOT_SIGHUP: AtomicBool = AtomicBool::new(false);
 static GOT_SIGTERM: AtomicBool = AtomicBool::new(false);
+static WATCHDOG_ACTIVE: AtomicBool = AtomicBool::new(false);
+static WATCHDOG_CANCELLED: AtomicBool = AtomicBool::new(false);
 
 bitflags! {
     struct BGWflags: i32 {
@@ -110,12 +118,23 @@ impl BackgroundWorker {
     ///
     /// Returns true if we're still supposed to be alive and haven't received a SIGTERM
     pub fn wait_latch(timeout: Option<Duration>) -> bool {
+        BackgroundWorker::wait_latch_as(timeout, WaitEvent::EXTENSION)
+    }
+
+    /// Like [`wait_latch`](BackgroundWorker::wait_latch), but `pg_stat_activity` shows the
+    /// worker waiting on `wait_event`
+    pub fn wait_latch_as(timeout: Option<Duration>, wait_event: WaitEvent) -> bool {
         match timeout {
             Some(t) => wait_latch(
                 t.as_millis().try_into().unwrap(),
                 WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
+                wait_event,
+            ),
+            None => wait_latch(
+                0,
+                WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH,
+                wait_event,
             ),
-            None => wait_latch(0, WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH),
         };
         !BackgroundWorker::sigterm_received()
     }
@@ -181,6 +200,264 @@ impl BackgroundWorker {
     }
 }
 
+/// Why a [`SpiWatchdog`] did not return the result of its closure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpiWatchdogError {
+    /// The closure was still running when its timeout expired
+    Timeout {
+        timeout: Duration,
+        elapsed: Duration,
+    },
+    /// The closure waited for a lock for longer than its lock timeout
+    LockTimeout {
+        lock_timeout: Duration,
+        elapsed: Duration,
+    },
+    /// The background worker received a SIGTERM while the closure was running
+    Cancelled { elapsed: Duration },
+    /// The closure raised some other ERROR, or panicked
+    Error { sqlstate: String, message: String },
+}
+
+impl SpiWatchdogError {
+    /// The five-character SQLSTATE the closure was stopped with, such as `57014`
+    /// (`query_canceled`) for a [`Timeout`](SpiWatchdogError::Timeout).  A panic is `XX000`.
+    pub fn sqlstate(&self) -> &str {
+        match self {
+            SpiWatchdogError::Timeout { .. } | SpiWatchdogError::Cancelled { .. } => "57014",
+            SpiWatchdogError::LockTimeout { .. } => "55P03",
+            SpiWatchdogError::Error { sqlstate, .. } => sqlstate,
+        }
+    }
+}
+
+impl std::fmt::Display for SpiWatchdogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpiWatchdogError::Timeout { timeout, elapsed } => write!(
+                f,
+                "query timed out after {:?} (timeout is {:?})",
+                elapsed, timeout
+            ),
+            SpiWatchdogError::LockTimeout {
+                lock_timeout,
+                elapsed,
+            } => write!(
+                f,
+                "query could not acquire a lock within {:?}, after running for {:?}",
+                lock_timeout, elapsed
+            ),
+            SpiWatchdogError::Cancelled { elapsed } => {
+                write!(f, "query was cancelled after {:?}", elapsed)
+            }
+            SpiWatchdogError::Error { sqlstate, message } => {
+                write!(f, "query failed with SQLSTATE {}: {}", sqlstate, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpiWatchdogError {}
+
+/// Runs SPI work from a background worker in its own transaction, cancelling it if it takes too long
+///
+/// The timeout is enforced the same way as `statement_timeout`, and an optional lock timeout with
+/// `lock_timeout`, so a query blocked on a lock is woken up and cancelled too.  If the worker has
+/// attached a `SIGTERM` handler with [`BackgroundWorker::attach_signal_handlers`], a `SIGTERM`
+/// also cancels the running query.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::SpiWatchdog;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+///     // attach signal handlers and connect to SPI first
+///     let watchdog = SpiWatchdog::new(Duration::from_secs(60))
+///         .set_lock_timeout(Duration::from_secs(5));
+///     match watchdog.run(|| Spi::run("DELETE FROM my_queue WHERE processed")) {
+///         Ok(()) => log!("cleanup complete"),
+///         Err(e) => warning!("cleanup skipped: {}", e),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpiWatchdog {
+    timeout: Duration,
+    lock_timeout: Option<Duration>,
+}
+
+impl SpiWatchdog {
+    /// Cancel the work if it runs for longer than `timeout`
+    pub fn new(timeout: Duration) -> SpiWatchdog {
+        SpiWatchdog {
+            timeout,
+            lock_timeout: None,
+        }
+    }
+
+    /// Cancel the work if it waits for any one lock for longer than `input`
+    pub fn set_lock_timeout(mut self, input: Duration) -> Self {
+        self.lock_timeout = Some(input);
+        self
+    }
+
+    /// Run `f` in a new transaction, committing it if `f` returns.
+    ///
+    /// If `f` raises an ERROR, panics, times out, or is cancelled, the transaction is aborted and
+    /// the reason is returned.
+    ///
+    /// ## Panics
+    ///
+    /// If called while a transaction is already in progress.
+    pub fn run<R, F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe>(
+        &self,
+        f: F,
+    ) -> Result<R, SpiWatchdogError> {
+        if unsafe { pg_sys::IsTransactionState() } {
+            panic!("SpiWatchdog::run() starts its own transaction, but was called inside one");
+        }
+
+        let outer_memory_context = unsafe { pg_sys::CurrentMemoryContext };
+        let lock_timeout = self.lock_timeout;
+        let started = Instant::now();
+
+        unsafe {
+            pg_sys::SetCurrentStatementStartTimestamp();
+            pg_sys::StartTransactionCommand();
+            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+        }
+
+        WATCHDOG_CANCELLED.store(false, Ordering::SeqCst);
+        WATCHDOG_ACTIVE.store(true, Ordering::SeqCst);
+        unsafe {
+            pg_sys::enable_timeout_after(
+                pg_sys::TimeoutId_STATEMENT_TIMEOUT,
+                as_timeout_millis(self.timeout),
+            );
+        }
+        let result = std::panic::catch_unwind(move || {
+            if let Some(lock_timeout) = lock_timeout {
+                crate::Spi::run(&format!(
+                    "SET LOCAL lock_timeout = {}",
+                    as_timeout_millis(lock_timeout)
+                ));
+            }
+            f()
+        });
+        WATCHDOG_ACTIVE.store(false, Ordering::SeqCst);
+        unsafe {
+            pg_sys::disable_timeout(pg_sys::TimeoutId_STATEMENT_TIMEOUT, false);
+        }
+        let elapsed = started.elapsed();
+
+        match result {
+            Ok(result) => {
+                // the timeout or a SIGTERM may have requested a cancel after `f` finished
+                if WATCHDOG_CANCELLED.load(Ordering::SeqCst)
+                    || unsafe {
+                        pg_sys::get_timeout_indicator(pg_sys::TimeoutId_STATEMENT_TIMEOUT, true)
+                    }
+                {
+                    unsafe { clear_query_cancel() };
+                }
+                unsafe {
+                    pg_sys::PopActiveSnapshot();
+                    pg_sys::CommitTransactionCommand();
+                }
+                Ok(result)
+            }
+            Err(e) => {
+                let (sqlerrcode, message) = match e.downcast_ref::<pg_sys::JumpContext>() {
+                    Some(_) => unsafe {
+                        let edata = take_error_data(outer_memory_context);
+                        let message = error_data_message(edata);
+                        let sqlerrcode = (*edata).sqlerrcode;
+                        pg_sys::FreeErrorData(edata);
+                        (sqlerrcode, message)
+                    },
+                    None => match e.downcast::<String>() {
+                        Ok(message) => (PgSqlErrorCode::ERRCODE_INTERNAL_ERROR as i32, *message),
+                        Err(e) => match e.downcast::<&str>() {
+                            Ok(message) => (
+                                PgSqlErrorCode::ERRCODE_INTERNAL_ERROR as i32,
+                                message.to_string(),
+                            ),
+                            Err(_) => (
+                                PgSqlErrorCode::ERRCODE_INTERNAL_ERROR as i32,
+                                String::from("Box<Any>"),
+                            ),
+                        },
+                    },
+                };
+                unsafe {
+                    pg_sys::AbortCurrentTransaction();
+                    pg_sys::CurrentMemoryContext = outer_memory_context;
+                }
+
+                let cancelled = sqlerrcode == PgSqlErrorCode::ERRCODE_QUERY_CANCELED as i32;
+                let lock_not_available =
+                    sqlerrcode == PgSqlErrorCode::ERRCODE_LOCK_NOT_AVAILABLE as i32;
+                Err(match lock_timeout {
+                    _ if cancelled && WATCHDOG_CANCELLED.load(Ordering::SeqCst) => {
+                        SpiWatchdogError::Cancelled { elapsed }
+                    }
+                    _ if cancelled => SpiWatchdogError::Timeout {
+                        timeout: self.timeout,
+                        elapsed,
+                    },
+                    Some(lock_timeout) if lock_not_available => SpiWatchdogError::LockTimeout {
+                        lock_timeout,
+                        elapsed,
+                    },
+                    _ => SpiWatchdogError::Error {
+                        sqlstate: unsafe {
+                            CStr::from_ptr(pg_sys::unpack_sql_state(sqlerrcode))
+                                .to_string_lossy()
+                                .into_owned()
+                        },
+                        message,
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// Postgres timeouts are an `int` of milliseconds, where zero disables them
+fn as_timeout_millis(duration: Duration) -> i32 {
+    duration.as_millis().clamp(1, i32::MAX as u128) as i32
+}
+
+/// Ask the running query to cancel itself at its next `CHECK_FOR_INTERRUPTS()`
+unsafe fn request_query_cancel() {
+    #[cfg(any(feature = "pg10", feature = "pg11"))]
+    {
+        pg_sys::QueryCancelPending = true;
+        pg_sys::InterruptPending = true;
+    }
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    {
+        pg_sys::QueryCancelPending = 1;
+        pg_sys::InterruptPending = 1;
+    }
+}
+
+/// Forget a cancel requested by [`request_query_cancel`] that arrived too late to cancel anything
+unsafe fn clear_query_cancel() {
+    #[cfg(any(feature = "pg10", feature = "pg11"))]
+    {
+        pg_sys::QueryCancelPending = false;
+    }
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    {
+        pg_sys::QueryCancelPending = 0;
+    }
+}
+
 unsafe extern "C" fn worker_spi_sighup(_signal_args: i32) {
     GOT_SIGHUP.store(true, Ordering::SeqCst);
     pg_sys::ProcessConfigFile(pg_sys::GucContext_PGC_SIGHUP);
@@ -189,6 +466,11 @@ unsafe extern "C" fn worker_spi_sighup(_signal_args: i32) {
 
 unsafe extern "C" fn worker_spi_sigterm(_signal_args: i32) {
     GOT_SIGTERM.store(true, Ordering::SeqCst);
+    crate::interrupts::request_termination();
+    if WATCHDOG_ACTIVE.load(Ordering::SeqCst) {
+        WATCHDOG_CANCELLED.store(true, Ordering::SeqCst);
+        request_query_cancel();
+    }
     pg_sys::SetLatch(pg_sys::MyLatch);
 }
 
@@ -213,6 +495,7 @@ unsafe extern "C" fn worker_spi_sigterm(_signal_args: i32) {
 /// }
 ///
 /// #[pg_guard]
+/// #[no_mangle]
 /// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
 ///     // do bgworker stuff here
 /// }
@@ -307,6 +590,7 @@ impl BackgroundWorkerBuilder {
     /// The specified function **must** be:
     ///     - `extern "C"`,
     ///     - guarded with `#[pg_guard]`,
+    ///     - exported with `#[no_mangle]`, so Postgres can find it,
     ///     - take 1 argument of type `pgx::pg_sys::Datum`, and
     ///     - return "void"
     ///
@@ -316,6 +600,7 @@ impl BackgroundWorkerBuilder {
     /// use pgx::*;
     ///
     /// #[pg_guard]
+    /// #[no_mangle]
     /// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
     /// }
     /// ```
@@ -424,13 +709,13 @@ impl BackgroundWorkerBuilder {
     }
 }
 
-fn wait_latch(timeout: i64, wakeup_flags: WLflags) -> i32 {
+fn wait_latch(timeout: i64, wakeup_flags: WLflags, wait_event: WaitEvent) -> i32 {
     unsafe {
         let latch = pg_sys::WaitLatch(
             pg_sys::MyLatch,
             wakeup_flags.bits(),
             timeout,
-            pg_sys::PG_WAIT_EXTENSION,
+            wait_event.info(),
         );
         pg_sys::ResetLatch(pg_sys::MyLatch);
         check_for_interrupts!();
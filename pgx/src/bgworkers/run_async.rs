@@ -0,0 +1,263 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Running an async runtime in a background worker, with the Postgres calls on the worker's own
+//! thread.  This needs pgx's `async` feature.
+use super::BackgroundWorker;
+use crate::pg_sys;
+use std::future::Future;
+use std::sync::mpsc::TryRecvError;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// How long the future has to finish after a `SIGTERM` before it's cancelled
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+extern "C" {
+    // Not `pg_sys::SetLatch`, which is guarded for calling from the Postgres thread.  Setting a
+    // latch only wakes the process up, so any thread can.
+    #[link_name = "SetLatch"]
+    fn set_latch_from_any_thread(latch: *mut pg_sys::Latch);
+}
+
+/// The background worker's `MyLatch`, which the async side sets to wake the worker's thread
+#[derive(Clone, Copy)]
+struct Latch(*mut pg_sys::Latch);
+
+unsafe impl Send for Latch {}
+unsafe impl Sync for Latch {}
+
+impl Latch {
+    fn set(&self) {
+        unsafe { set_latch_from_any_thread(self.0) }
+    }
+}
+
+/// Sets the latch when the future is done with, however it ends
+struct SetLatchOnDrop(Latch);
+
+impl Drop for SetLatchOnDrop {
+    fn drop(&mut self) {
+        self.0.set()
+    }
+}
+
+/// The way back onto the background worker's own thread, which is the only one that may call
+/// into Postgres, for the future run by [`run_async`]
+#[derive(Clone)]
+pub struct PgThread {
+    jobs: mpsc::UnboundedSender<Job>,
+    latch: Latch,
+    shutdown: watch::Receiver<bool>,
+    reload: watch::Receiver<u64>,
+}
+
+impl PgThread {
+    /// Run `f` on the background worker's thread, where it can use `pg_sys` and the rest of pgx,
+    /// and wait for its result.
+    ///
+    /// `f` runs between the worker's waits on its latch, so it holds up everything else the worker
+    /// would do on its thread, but not the async runtime.  An `ERROR` or panic in `f` stops the
+    /// worker, as it would in a worker without an async runtime.
+    pub async fn run<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move || {
+                let _ = result_tx.send(f());
+            }))
+            .unwrap_or_else(|_| panic!("the background worker has stopped"));
+        self.latch.set();
+        result_rx.await.expect("the background worker has stopped")
+    }
+
+    /// Run `f` in a transaction on the background worker's thread, so it can use `Spi`, and wait
+    /// for its result.  The transaction is committed if `f` returns.
+    ///
+    /// The worker must have connected with [`BackgroundWorker::connect_worker_to_spi`] first.
+    pub async fn transaction<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.run(move || unsafe {
+            pg_sys::SetCurrentStatementStartTimestamp();
+            pg_sys::StartTransactionCommand();
+            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+            let result = f();
+            pg_sys::PopActiveSnapshot();
+            pg_sys::CommitTransactionCommand();
+            result
+        })
+        .await
+    }
+
+    /// Has the background worker been asked to stop, with a `SIGTERM`?
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Wait until the background worker is asked to stop, with a `SIGTERM`.
+    ///
+    /// The future given to [`run_async`] then has a few seconds to finish on its own, before it's
+    /// cancelled at whatever `.await` it's waiting at.
+    pub async fn shutdown(&self) {
+        let mut shutdown = self.shutdown.clone();
+        while !*shutdown.borrow() {
+            if shutdown.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Wait until the configuration file is reloaded after a `SIGHUP`, for workers which read
+    /// settings once and keep them, such as the address they listen on
+    pub async fn reloaded(&mut self) {
+        let _ = self.reload.changed().await;
+    }
+}
+
+/// Run a future on a new single-threaded tokio runtime, from a background worker, returning its
+/// output, or `None` if it was cancelled because the worker was asked to stop.
+///
+/// Postgres must only be called from the background worker's own thread, and the runtime runs on
+/// another, so the future is given a [`PgThread`] to run anything that uses `pg_sys`, `Spi` or
+/// the rest of pgx on the worker's thread instead.  Meanwhile, the worker's thread waits on its
+/// latch like any other background worker, so it notices the postmaster dying, and
+/// `pg_stat_activity` shows it idle.
+///
+/// The worker's signal handlers must be attached with
+/// [`BackgroundWorker::attach_signal_handlers`] first.  A `SIGTERM` resolves
+/// [`PgThread::shutdown`], and a `SIGHUP` resolves [`PgThread::reloaded`], once the configuration
+/// has been reloaded.
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn queue_consumer_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+///
+///     run_async(|pg| async move {
+///         while !pg.is_shutting_down() {
+///             // receive a message without blocking Postgres, such as from a socket
+///             let message = String::from("hello");
+///             pg.transaction(move || {
+///                 Spi::run(&format!("INSERT INTO messages VALUES ('{}')", message))
+///             })
+///             .await;
+///         }
+///     });
+/// }
+/// ```
+///
+/// ## Panics
+///
+/// If the future panics, which is then an `ERROR` in the worker.
+pub fn run_async<F, Fut, R>(f: F) -> Option<R>
+where
+    F: FnOnce(PgThread) -> Fut,
+    Fut: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build the async runtime");
+    run_async_on(runtime, f)
+}
+
+/// Like [`run_async`], but on a runtime of the worker's own, such as a multi-threaded one
+pub fn run_async_on<F, Fut, R>(runtime: tokio::runtime::Runtime, f: F) -> Option<R>
+where
+    F: FnOnce(PgThread) -> Fut,
+    Fut: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    let latch = Latch(unsafe { pg_sys::MyLatch });
+    let (jobs_tx, mut jobs) = mpsc::unbounded_channel::<Job>();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (reload_tx, reload_rx) = watch::channel(0u64);
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let future = f(PgThread {
+        jobs: jobs_tx,
+        latch,
+        shutdown: shutdown_rx,
+        reload: reload_rx,
+    });
+
+    let handle = runtime.handle().clone();
+    let task = handle.spawn(async move {
+        let _wake = SetLatchOnDrop(latch);
+        let _ = done_tx.send(future.await);
+    });
+
+    // a current-thread runtime only makes progress while something blocks on it
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let driver = std::thread::Builder::new()
+        .name(String::from("pgx async runtime"))
+        .spawn(move || {
+            runtime.block_on(async {
+                let _ = stop_rx.await;
+            });
+            runtime.shutdown_timeout(SHUTDOWN_GRACE);
+        })
+        .expect("failed to start the async runtime's thread");
+
+    let mut deadline = None;
+    let result = loop {
+        while let Ok(job) = jobs.try_recv() {
+            job();
+        }
+        match done_rx.try_recv() {
+            Ok(result) => break Some(result),
+            Err(TryRecvError::Disconnected) => break None,
+            Err(TryRecvError::Empty) => {}
+        }
+        if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+            task.abort();
+            break None;
+        }
+
+        if BackgroundWorker::sighup_received() {
+            // the signal handler has reloaded the configuration already
+            let reloads = *reload_tx.borrow() + 1;
+            let _ = reload_tx.send(reloads);
+        }
+        let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if !BackgroundWorker::wait_latch(timeout) && deadline.is_none() {
+            let _ = shutdown_tx.send(true);
+            deadline = Some(Instant::now() + SHUTDOWN_GRACE);
+        }
+    };
+
+    // the future finished without a result only if it panicked, or it was cancelled
+    let panic = match result {
+        None if deadline.is_none() => match handle.block_on(task) {
+            Err(e) if e.is_panic() => Some(e.into_panic()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let _ = stop_tx.send(());
+    driver.join().expect("the async runtime's thread panicked");
+    if let Some(panic) = panic {
+        std::panic::resume_unwind(panic);
+    }
+    result
+}
@@ -0,0 +1,201 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! BRIN index support for custom types, through the [`BrinSupport`] trait and
+//! `#[derive(PostgresBrinOps)]`
+use crate::{pg_sys, FromDatum, Internal, IntoDatum};
+
+/// The support functions of a BRIN operator class for a type.
+///
+/// BRIN indexes keep a summary of each range of a table's pages, such as the smallest and largest
+/// values in it, and skip the ranges whose summary can't match a query.  They're tiny, and suit
+/// large tables which are appended to in order, so nearby rows have similar values.
+///
+/// `#[derive(PostgresBrinOps)]` generates the functions Postgres calls, which take and return
+/// `internal` pointers to its `BrinDesc` and `BrinValues`, from the trait's, as well as the
+/// operator class.  The functions handle nulls, so the trait only ever sees values:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PostgresType, PostgresBrinOps)]
+/// #[brin(default, storage = "Bounds", operators(3 = "="), requires = [Bounds, reading_eq])]
+/// pub struct Reading(i64);
+///
+/// /// The smallest and largest readings in a range of pages
+/// #[derive(Serialize, Deserialize, PostgresType)]
+/// pub struct Bounds {
+///     lo: i64,
+///     hi: i64,
+/// }
+///
+/// #[pg_operator(immutable, parallel_safe, symbol = "=")]
+/// fn reading_eq(left: Reading, right: Reading) -> bool {
+///     left.0 == right.0
+/// }
+///
+/// impl BrinSupport for Reading {
+///     type Summary = Bounds;
+///
+///     fn summarize(value: Reading) -> Bounds {
+///         Bounds { lo: value.0, hi: value.0 }
+///     }
+///
+///     fn add_value(summary: &mut Bounds, value: Reading) -> bool {
+///         if summary.lo <= value.0 && value.0 <= summary.hi {
+///             return false;
+///         }
+///         summary.lo = summary.lo.min(value.0);
+///         summary.hi = summary.hi.max(value.0);
+///         true
+///     }
+///
+///     fn consistent(summary: &Bounds, query: &Reading, _strategy: u16) -> bool {
+///         summary.lo <= query.0 && query.0 <= summary.hi
+///     }
+///
+///     fn union(summary: &mut Bounds, other: Bounds) {
+///         summary.lo = summary.lo.min(other.lo);
+///         summary.hi = summary.hi.max(other.hi);
+///     }
+/// }
+/// ```
+pub trait BrinSupport: FromDatum + IntoDatum {
+    /// The summary of a range of pages, which is often a different type than `Self`, and then has
+    /// to be given as the class's `storage`
+    type Summary: FromDatum + IntoDatum;
+
+    /// The summary of a range with only `value` in it
+    fn summarize(value: Self) -> Self::Summary;
+
+    /// Add `value` to the `summary`, returning whether it had to change
+    fn add_value(summary: &mut Self::Summary, value: Self) -> bool;
+
+    /// Can any value in a range with the `summary` match `query`, for the operator of the class
+    /// with the `strategy` number?
+    fn consistent(summary: &Self::Summary, query: &Self, strategy: u16) -> bool;
+
+    /// Combine `other` into `summary`, so it covers the values of both ranges
+    fn union(summary: &mut Self::Summary, other: Self::Summary);
+}
+
+unsafe fn values_of<'a>(column: &Internal) -> &'a mut pg_sys::BrinValues {
+    column
+        .get_mut::<pg_sys::BrinValues>()
+        .expect("the BRIN values are null")
+}
+
+unsafe fn summary_of<T: BrinSupport>(values: &pg_sys::BrinValues) -> T::Summary {
+    T::Summary::from_datum(*values.bv_values, false, pg_sys::InvalidOid)
+        .expect("the BRIN summary is null")
+}
+
+unsafe fn set_summary<T: BrinSupport>(values: &mut pg_sys::BrinValues, summary: T::Summary) {
+    *values.bv_values = summary.into_datum().expect("a BRIN summary can't be null");
+    values.bv_allnulls = false;
+}
+
+/// The `opcInfo` support function, number 1, which describes the summary the class stores
+#[doc(hidden)]
+pub unsafe fn brin_opcinfo<T: BrinSupport>() -> Internal {
+    let info = pg_sys::palloc0(
+        std::mem::size_of::<pg_sys::BrinOpcInfo>()
+            + std::mem::size_of::<*mut pg_sys::TypeCacheEntry>(),
+    ) as *mut pg_sys::BrinOpcInfo;
+    (*info).oi_nstored = 1;
+    #[cfg(feature = "pg14")]
+    {
+        (*info).oi_regular_nulls = true;
+    }
+    (*info).oi_typcache.as_mut_slice(1)[0] = pg_sys::lookup_type_cache(T::Summary::type_oid(), 0);
+    Internal::from(Some(info as pg_sys::Datum))
+}
+
+/// The `addValue` support function, number 2
+#[doc(hidden)]
+pub unsafe fn brin_add_value<T: BrinSupport>(
+    column: Internal,
+    new_value: Internal,
+    is_null: Internal,
+) -> bool {
+    let values = values_of(&column);
+    // `is_null` is passed by value
+    if is_null.unwrap().unwrap_or_default() != 0 {
+        let changed = !values.bv_hasnulls;
+        values.bv_hasnulls = true;
+        return changed;
+    }
+
+    let datum = new_value.unwrap().unwrap_or_default();
+    let value = T::from_datum(datum, false, pg_sys::InvalidOid).expect("the BRIN value is null");
+    if values.bv_allnulls {
+        set_summary::<T>(values, T::summarize(value));
+        return true;
+    }
+
+    let mut summary = summary_of::<T>(values);
+    let changed = T::add_value(&mut summary, value);
+    if changed {
+        set_summary::<T>(values, summary);
+    }
+    changed
+}
+
+/// The `consistent` support function, number 3
+#[doc(hidden)]
+pub unsafe fn brin_consistent<T: BrinSupport>(column: Internal, key: Internal) -> bool {
+    let values = values_of(&column);
+    let key = key
+        .get::<pg_sys::ScanKeyData>()
+        .expect("the BRIN scan key is null");
+
+    let flags = key.sk_flags as u32;
+    if flags & pg_sys::SK_ISNULL != 0 {
+        return if flags & pg_sys::SK_SEARCHNULL != 0 {
+            values.bv_allnulls || values.bv_hasnulls
+        } else if flags & pg_sys::SK_SEARCHNOTNULL != 0 {
+            !values.bv_allnulls
+        } else {
+            // comparisons with null are never true
+            false
+        };
+    }
+    if values.bv_allnulls {
+        return false;
+    }
+
+    let query =
+        T::from_datum(key.sk_argument, false, pg_sys::InvalidOid).expect("the BRIN query is null");
+    T::consistent(&summary_of::<T>(values), &query, key.sk_strategy)
+}
+
+/// The `union` support function, number 4
+#[doc(hidden)]
+pub unsafe fn brin_union<T: BrinSupport>(column: Internal, other: Internal) -> bool {
+    let values = values_of(&column);
+    let other = values_of(&other);
+
+    if other.bv_hasnulls || other.bv_allnulls {
+        values.bv_hasnulls = true;
+    }
+    if other.bv_allnulls {
+        return true;
+    }
+
+    let other = summary_of::<T>(other);
+    if values.bv_allnulls {
+        set_summary::<T>(values, other);
+    } else {
+        let mut summary = summary_of::<T>(values);
+        T::union(&mut summary, other);
+        set_summary::<T>(values, summary);
+    }
+    true
+}
@@ -9,7 +9,8 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system
 
-use crate::pg_sys;
+use crate::log::{error_data_message, take_error_data};
+use crate::{pg_sys, warning};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -19,18 +20,14 @@ use std::rc::Rc;
 pub enum PgXactCallbackEvent {
     /// Fired when a transaction is aborted.  It is mutually exclusive with `PgXactCallbackEvent::Commit`
     ///
-    /// ## Safety
-    ///
-    /// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while this event is firing will
-    /// cause the Postgres backend to abort.
+    /// The transaction can't fail anymore, so a Rust `panic!()` or Postgres `ereport(ERROR)` while
+    /// this event is firing is reported as a WARNING.
     Abort,
 
     /// Fired when a transcation is committed.  It is mutually exclusive with `PgXactCallbackEvent::Abort`
     ///
-    /// ## Safety
-    ///
-    /// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while this event is firing will
-    /// cause the Postgres backend to abort.
+    /// The transaction can't fail anymore, so a Rust `panic!()` or Postgres `ereport(ERROR)` while
+    /// this event is firing is reported as a WARNING.
     Commit,
 
     /// Fired immediately before the transaction is committed.  This is your last chance to cleanly
@@ -54,6 +51,18 @@ pub enum PgXactCallbackEvent {
 }
 
 impl PgXactCallbackEvent {
+    /// Is the transaction already over when this event fires, so that it can't raise an ERROR?
+    fn is_transaction_over(&self) -> bool {
+        matches!(
+            self,
+            PgXactCallbackEvent::Abort
+                | PgXactCallbackEvent::Commit
+                | PgXactCallbackEvent::ParallelAbort
+                | PgXactCallbackEvent::ParallelCommit
+                | PgXactCallbackEvent::Prepare
+        )
+    }
+
     fn translate_pg_event(pg_event: pg_sys::XactEvent) -> Self {
         match pg_event {
             pg_sys::XactEvent_XACT_EVENT_ABORT => PgXactCallbackEvent::Abort,
@@ -97,6 +106,55 @@ impl XactCallbackReceipt {
     }
 }
 
+/// Run a callback for an event that fires after the (sub)transaction is already over, where Postgres
+/// would turn an ERROR into a PANIC and restart the cluster.  A Rust `panic!()` or Postgres
+/// `ereport(ERROR)` from `f` is reported as a WARNING instead.
+fn run_reporting_errors<F>(event: &dyn std::fmt::Debug, f: F)
+where
+    F: FnOnce() + std::panic::UnwindSafe,
+{
+    if let Err(message) = catch_error_message(f) {
+        warning!("{:?} callback failed: {}", event, message);
+    }
+}
+
+/// Run `f`, catching a Rust `panic!()` or Postgres `ereport(ERROR)` from it.  An ERROR's state is
+/// copied and flushed, so Postgres can carry on, and its message returned.
+pub(crate) fn catch_error_message<F>(f: F) -> Result<(), String>
+where
+    F: FnOnce() + std::panic::UnwindSafe,
+{
+    let (memory_context, interrupt_holdoff) =
+        unsafe { (pg_sys::CurrentMemoryContext, pg_sys::InterruptHoldoffCount) };
+
+    let e = match std::panic::catch_unwind(f) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    let message = match e.downcast_ref::<pg_sys::JumpContext>() {
+        Some(_) => unsafe {
+            let edata = take_error_data(memory_context);
+            let message = error_data_message(edata);
+            pg_sys::FreeErrorData(edata);
+            message
+        },
+        None => match e.downcast::<String>() {
+            Ok(message) => *message,
+            Err(e) => match e.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => String::from("Box<Any>"),
+            },
+        },
+    };
+
+    // the ERROR reset these, but our caller still expects them
+    unsafe {
+        pg_sys::CurrentMemoryContext = memory_context;
+        pg_sys::InterruptHoldoffCount = interrupt_holdoff;
+    }
+    Err(message)
+}
+
 /// An internal wrapper for a callback closure
 struct XactCallbackWrapper(
     Box<dyn FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
@@ -109,8 +167,9 @@ type CallbackMap = HashMap<PgXactCallbackEvent, Vec<Rc<RefCell<Option<XactCallba
 /// closures can be registered per event (one at a time), and they are called in the order in which
 /// they were registered.
 ///
-/// Registered callbacks only remain registered for the life of a single transaction.  Registration
-/// of permanet callbacks should be done through the unsafe `pg_sys::RegisterXactCallback()` function.
+/// Registered callbacks only remain registered for the life of a single transaction:  they're
+/// unregistered once the transaction commits or aborts, whether or not their event fired.
+/// Registration of permanet callbacks should be done through the unsafe `pg_sys::RegisterXactCallback()` function.
 ///
 ///
 /// ## Examples
@@ -141,11 +200,14 @@ type CallbackMap = HashMap<PgXactCallbackEvent, Vec<Rc<RefCell<Option<XactCallba
 /// receipt.unregister_callback();
 /// ```
 ///
-/// ## Safety
+/// ## Errors
 ///
-/// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while executing a `PgXactCallbackEvent::Commit`
-/// or `PgXactCallbackEvent::Abort` event will immediately cause the Postgres backend to abort and
-/// the entire cluster to restart.
+/// A Rust `panic!()` or Postgres `ereport(ERROR)` while executing a `PgXactCallbackEvent::PreCommit`
+/// (or `::ParallelPreCommit` or `::PrePrepare`) event aborts the transaction.
+///
+/// The transaction is already over by a `PgXactCallbackEvent::Commit`, `::Abort`, `::ParallelCommit`,
+/// `::ParallelAbort` or `::Prepare` event, and Postgres would restart the entire cluster for an
+/// ERROR then, so one is reported as a WARNING instead and the remaining callbacks still run.
 ///
 /// As the Postgres internal documentation says:  
 ///
@@ -188,13 +250,14 @@ where
         // in the order they were registered
         if let Some(hooks) = hooks {
             for hook in hooks.into_iter() {
-                // TODO:  do we need to catch panics and do something with them?  They'll cause
-                //  the Postgres backend to Abort() if we're handling XactEvent::Commit/Abort events
-
                 // effectively 'take' the hook from the internal RefCell
                 if let Some(hook) = hook.replace(None) {
-                    // and execute it under guard for proper panic/elog(ERROR) handling
-                    crate::guard::guard(hook.0);
+                    if which_event.is_transaction_over() {
+                        run_reporting_errors(&which_event, hook.0);
+                    } else {
+                        // execute it under guard for proper panic/elog(ERROR) handling
+                        crate::guard::guard(hook.0);
+                    }
                 }
             }
         }
@@ -234,8 +297,8 @@ where
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub enum PgSubXactCallbackEvent {
-    /// Fired when a subtransaction is aborted.  While Rust `panic!()`s and Postgres `ereport(ERROR)`s
-    /// can occur here, it's not recommended
+    /// Fired when a subtransaction is aborted.  A Rust `panic!()` or Postgres `ereport(ERROR)` here
+    /// is reported as a WARNING, as the subtransaction is already being aborted
     AbortSub,
 
     /// Fired when a subtransaction is committed.  While Rust `panic!()`s and Postgres `ereport(ERROR)`s
@@ -302,6 +365,25 @@ struct SubXactCallbackWrapper(
 type SubCallbackMap =
     HashMap<PgSubXactCallbackEvent, Vec<Rc<RefCell<Option<SubXactCallbackWrapper>>>>>;
 
+/// Register a closure to be called during one of the `PgSubXactCallbackEvent` events, with the
+/// `SubTransactionId` of the subtransaction and of its parent.  Multiple closures can be registered
+/// per event, and they are called in the order in which they were registered, every time the event
+/// fires.
+///
+/// Like [`register_xact_callback`], registered callbacks only remain registered until the top-level
+/// transaction commits or aborts.
+///
+/// ## Examples
+///
+/// Clean up after work done in a subtransaction that's rolled back:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// register_subxact_callback(PgSubXactCallbackEvent::AbortSub, |my_subid, _parent_subid| {
+///     info!("subtransaction {} rolled back", my_subid)
+/// });
+/// ```
 pub fn register_subxact_callback<F>(
     which_event: PgSubXactCallbackEvent,
     f: F,
@@ -332,7 +414,12 @@ where
                 for hook in hooks.iter() {
                     let hook = hook.borrow();
                     if let Some(hook) = hook.as_ref() {
-                        crate::guard::guard(|| (hook.0)(my_subid, parent_subid));
+                        let f = || (hook.0)(my_subid, parent_subid);
+                        if which_event == PgSubXactCallbackEvent::AbortSub {
+                            run_reporting_errors(&which_event, f);
+                        } else {
+                            crate::guard::guard(f);
+                        }
                     }
                 }
             }
@@ -0,0 +1,164 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Returning data too big for a single `bytea`, as a set of chunks
+use crate::{
+    check_for_interrupts, direct_function_call, direct_function_call_as_datum, ereport, pg_sys,
+    IntoDatum, PgLogLevel, PgSqlErrorCode,
+};
+use std::io::{ErrorKind, Read};
+
+/// The size of each chunk, unless [`ByteaChunks::chunk_size`] says otherwise
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The biggest chunk a `bytea` can hold, which is just under 1GB
+pub const MAX_CHUNK_SIZE: usize = 0x3fff_ffff - pg_sys::VARHDRSZ;
+
+/// Reads anything which can be `Read` as a series of `bytea` chunks, so it can be returned from a
+/// function no matter how big it is.
+///
+/// A `bytea`, like any other value, can't be bigger than 1GB, but a `#[pg_extern]` returning the
+/// chunks as `SETOF bytea` can return any amount of data, which the client puts back together by
+/// appending the chunks in the order they're returned.  Or, if the client would rather read a
+/// single value, [`into_large_object`](ByteaChunks::into_large_object) writes the chunks to a
+/// large object, which can be up to 4TB, and which clients can read with `lo_export` or the
+/// large object functions of their driver.
+///
+/// Both are best made from the same source, so they return the same data:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn report(id: i32) -> impl std::io::Read {
+///     std::fs::File::open(format!("/var/reports/{}.csv", id)).expect("no such report")
+/// }
+///
+/// /// `SELECT chunk FROM export_report(42) AS chunk`, which the client appends in order
+/// #[pg_extern]
+/// fn export_report(id: i32) -> impl Iterator<Item = Vec<u8>> {
+///     ByteaChunks::new(report(id))
+/// }
+///
+/// /// `SELECT lo_export(export_report_to_large_object(42), '/tmp/42.csv')`, or read the large
+/// /// object from the client
+/// #[pg_extern]
+/// fn export_report_to_large_object(id: i32) -> pg_sys::Oid {
+///     ByteaChunks::new(report(id)).into_large_object()
+/// }
+/// ```
+///
+/// An error reading the source raises an ERROR.
+pub struct ByteaChunks<R: Read> {
+    source: R,
+    chunk_size: usize,
+    finished: bool,
+}
+
+impl<R: Read> ByteaChunks<R> {
+    /// Read `source` in chunks of [`DEFAULT_CHUNK_SIZE`] bytes
+    pub fn new(source: R) -> Self {
+        ByteaChunks {
+            source,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            finished: false,
+        }
+    }
+
+    /// Read chunks of `chunk_size` bytes instead, which must be at least 1 and at most
+    /// [`MAX_CHUNK_SIZE`]
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            panic!(
+                "chunk size must be between 1 and {} bytes, but was {}",
+                MAX_CHUNK_SIZE, chunk_size
+            );
+        }
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Write every chunk to a new large object, returning its oid.
+    ///
+    /// The large object belongs to the current user, who needs permission to create it, like
+    /// `lo_create()`.
+    pub fn into_large_object(mut self) -> pg_sys::Oid {
+        let first = self.next().unwrap_or_default();
+        let oid = unsafe {
+            direct_function_call::<pg_sys::Oid>(
+                pg_sys::be_lo_from_bytea,
+                vec![
+                    pg_sys::InvalidOid.into_datum(),
+                    first.as_slice().into_datum(),
+                ],
+            )
+            .expect("lo_from_bytea returned null")
+        };
+
+        let mut offset = first.len() as i64;
+        for chunk in self {
+            unsafe {
+                direct_function_call_as_datum(
+                    pg_sys::be_lo_put,
+                    vec![
+                        oid.into_datum(),
+                        offset.into_datum(),
+                        chunk.as_slice().into_datum(),
+                    ],
+                );
+            }
+            offset += chunk.len() as i64;
+        }
+        oid
+    }
+
+    /// Fill `chunk` from the source, stopping early only at its end
+    fn fill(&mut self, chunk: &mut Vec<u8>) {
+        let mut filled = 0;
+        while filled < chunk.len() {
+            check_for_interrupts!();
+            match self.source.read(&mut chunk[filled..]) {
+                Ok(0) => {
+                    self.finished = true;
+                    break;
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    ereport(
+                        PgLogLevel::ERROR,
+                        PgSqlErrorCode::ERRCODE_IO_ERROR,
+                        &format!("could not read the next chunk: {}", e),
+                        file!(),
+                        line!(),
+                        column!(),
+                    );
+                    unreachable!()
+                }
+            }
+        }
+        chunk.truncate(filled);
+    }
+}
+
+impl<R: Read> Iterator for ByteaChunks<R> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        if self.finished {
+            return None;
+        }
+        let mut chunk = vec![0; self.chunk_size];
+        self.fill(&mut chunk);
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
@@ -0,0 +1,199 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Bulk loading with `COPY FROM`, streamed from Rust rather than a file or the client
+use crate::{
+    ereport, pg_guard, pg_sys, LockMode, PgBox, PgList, PgLogLevel, PgMemoryContexts, PgRelation,
+    PgRelationLockGuard, PgSqlErrorCode,
+};
+use std::io::{ErrorKind, Read};
+use std::os::raw::{c_char, c_int, c_void};
+
+/// Load rows into a table from anything which can be `Read`, in any format `COPY` understands
+///
+/// This runs the same code as `COPY ... FROM STDIN`, so the data is parsed as it's read, and
+/// triggers, constraints, and indexes are applied just as they are for `COPY`.  The calling user
+/// needs `INSERT` privilege on the table, which is locked in `ROW EXCLUSIVE` mode until the end
+/// of the transaction.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn load_pets(csv: &str) -> i64 {
+///     let pets = PgRelation::open_with_name_and_share_lock("pets").unwrap();
+///     PgCopyFrom::new(&pets, csv.as_bytes())
+///         .option("format", "csv")
+///         .option("header", "true")
+///         .columns(&["id", "name"])
+///         .insert() as i64
+/// }
+/// ```
+///
+/// Outside of a function called from SQL, such as in a background worker, there must be an
+/// active snapshot, as with [`BackgroundWorker::transaction`](crate::bgworkers::BackgroundWorker::transaction).
+pub struct PgCopyFrom<'a, R: Read> {
+    relation: &'a PgRelation,
+    source: R,
+    options: Vec<(String, String)>,
+    columns: Vec<String>,
+}
+
+impl<'a, R: Read> PgCopyFrom<'a, R> {
+    /// Copy into `relation` from `source`, which is in `COPY`'s text format unless an `option`
+    /// says otherwise
+    pub fn new(relation: &'a PgRelation, source: R) -> Self {
+        PgCopyFrom {
+            relation,
+            source,
+            options: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Set one of the [`COPY` options](https://www.postgresql.org/docs/current/sql-copy.html),
+    /// eg `.option("format", "csv")`
+    pub fn option(mut self, name: &str, value: &str) -> Self {
+        self.options.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Which columns the data has, in order, otherwise it has all of them
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
+
+    /// Insert every row of the data, returning how many there were
+    pub fn insert(mut self) -> u64 {
+        unsafe {
+            pg_sys::PreventCommandIfReadOnly(b"COPY FROM\0".as_ptr() as *const c_char);
+            let relid = self.relation.oid();
+            PgRelationLockGuard::acquire(relid, LockMode::RowExclusive)
+                .hold_until_end_of_transaction();
+
+            if pg_sys::check_enable_rls(relid, pg_sys::InvalidOid, false)
+                == pg_sys::CheckEnableRlsResult_RLS_ENABLED as c_int
+            {
+                ereport(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                    "COPY FROM not supported with row-level security",
+                    file!(),
+                    line!(),
+                    column!(),
+                );
+            }
+
+            // checked the way `COPY` checks them, including column privileges
+            let mut rte =
+                PgBox::<pg_sys::RangeTblEntry>::alloc_node(pg_sys::NodeTag_T_RangeTblEntry);
+            rte.rtekind = pg_sys::RTEKind_RTE_RELATION;
+            rte.relid = relid;
+            rte.relkind = (*self.relation.rd_rel).relkind;
+            #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+            {
+                rte.rellockmode = pg_sys::RowExclusiveLock as c_int;
+            }
+            rte.requiredPerms = pg_sys::ACL_INSERT;
+            for attribute in self.relation.tuple_desc().iter() {
+                let copied = self.columns.is_empty()
+                    || self.columns.iter().any(|column| column == attribute.name());
+                if !attribute.attisdropped && copied {
+                    rte.insertedCols = pg_sys::bms_add_member(
+                        rte.insertedCols,
+                        attribute.attnum as c_int - pg_sys::FirstLowInvalidHeapAttributeNumber,
+                    );
+                }
+            }
+            let mut range_table = PgList::new();
+            range_table.push(rte.into_pg());
+            let range_table = range_table.into_pg();
+            pg_sys::ExecCheckRTPerms(range_table, true);
+
+            let pstate = pg_sys::make_parsestate(std::ptr::null_mut());
+            (*pstate).p_rtable = range_table;
+
+            let context = PgMemoryContexts::CurrentMemoryContext;
+            let mut options = PgList::<pg_sys::DefElem>::new();
+            for (name, value) in &self.options {
+                let value = pg_sys::makeString(context.pstrdup(value));
+                options.push(pg_sys::makeDefElem(
+                    context.pstrdup(name),
+                    value as *mut pg_sys::Node,
+                    -1,
+                ));
+            }
+            let mut columns = PgList::<pg_sys::Value>::new();
+            for column in &self.columns {
+                columns.push(pg_sys::makeString(context.pstrdup(column)));
+            }
+
+            let previous =
+                SOURCE.replace((&mut self.source as *mut R as *mut c_void, read_from::<R>));
+
+            #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+            let cstate = pg_sys::BeginCopyFrom(
+                pstate,
+                self.relation.as_ptr(),
+                std::ptr::null(),
+                false,
+                Some(read_source),
+                columns.into_pg(),
+                options.into_pg(),
+            );
+            #[cfg(feature = "pg14")]
+            let cstate = pg_sys::BeginCopyFrom(
+                pstate,
+                self.relation.as_ptr(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                false,
+                Some(read_source),
+                columns.into_pg(),
+                options.into_pg(),
+            );
+            let count = pg_sys::CopyFrom(cstate);
+            pg_sys::EndCopyFrom(cstate);
+            pg_sys::free_parsestate(pstate);
+
+            SOURCE = previous;
+            count
+        }
+    }
+}
+
+/// The source of the `COPY FROM` in progress, as Postgres doesn't pass its callback any state
+static mut SOURCE: Option<(*mut c_void, unsafe fn(*mut c_void, &mut [u8]) -> usize)> = None;
+
+unsafe fn read_from<R: Read>(source: *mut c_void, buf: &mut [u8]) -> usize {
+    let source = &mut *(source as *mut R);
+    loop {
+        match source.read(buf) {
+            Ok(len) => return len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => panic!("could not read COPY data: {}", e),
+        }
+    }
+}
+
+/// Fill `outbuf` with at least `minread` bytes, unless the data ends first
+#[pg_guard]
+unsafe extern "C" fn read_source(outbuf: *mut c_void, minread: c_int, maxread: c_int) -> c_int {
+    let (source, read) = SOURCE.expect("there is no COPY FROM in progress");
+    let buf = std::slice::from_raw_parts_mut(outbuf as *mut u8, maxread as usize);
+    let mut len = 0;
+    while len < minread as usize {
+        match read(source, &mut buf[len..]) {
+            0 => break,
+            read => len += read,
+        }
+    }
+    len as c_int
+}
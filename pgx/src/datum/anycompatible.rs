@@ -0,0 +1,130 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres 13's `anycompatible` family of pseudo-types
+use crate::{pg_sys, FromDatum, IntoDatum};
+
+/// A value of any type, for Postgres' `anycompatible` pseudo-type.
+///
+/// Unlike `anyelement`, the arguments of the same call don't need to be of exactly the same type,
+/// as they're all cast to a common type, such as `numeric` for an `integer` and a `numeric`.
+#[derive(Debug, Clone, Copy)]
+pub struct AnyCompatible {
+    datum: pg_sys::Datum,
+    typoid: pg_sys::Oid,
+}
+
+impl AnyCompatible {
+    pub fn datum(&self) -> pg_sys::Datum {
+        self.datum
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.typoid
+    }
+
+    #[inline]
+    pub fn into<T: FromDatum>(&self) -> Option<T> {
+        unsafe { T::from_datum(self.datum(), false, self.oid()) }
+    }
+
+    /// Wrap `value` to be returned, which must be of the type the function's `anycompatible`
+    /// arguments were resolved to, as given by their [`oid()`](AnyCompatible::oid)
+    pub fn from_value<T: IntoDatum>(value: T) -> Option<AnyCompatible> {
+        Some(AnyCompatible {
+            datum: value.into_datum()?,
+            typoid: T::type_oid(),
+        })
+    }
+}
+
+impl FromDatum for AnyCompatible {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<AnyCompatible> {
+        if is_null {
+            None
+        } else {
+            Some(AnyCompatible { datum, typoid })
+        }
+    }
+}
+
+impl IntoDatum for AnyCompatible {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::ANYCOMPATIBLEOID
+    }
+}
+
+/// An array of any type, for Postgres' `anycompatiblearray` pseudo-type, whose elements are of
+/// the common type of the call's [`AnyCompatible`] arguments
+#[derive(Debug, Clone, Copy)]
+pub struct AnyCompatibleArray {
+    datum: pg_sys::Datum,
+    typoid: pg_sys::Oid,
+}
+
+impl AnyCompatibleArray {
+    pub fn datum(&self) -> pg_sys::Datum {
+        self.datum
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.typoid
+    }
+
+    #[inline]
+    pub fn into<T: FromDatum>(&self) -> Option<T> {
+        unsafe { T::from_datum(self.datum(), false, self.oid()) }
+    }
+
+    /// Wrap `value` to be returned, which must be of the type the function's
+    /// `anycompatiblearray` arguments were resolved to, as given by their
+    /// [`oid()`](AnyCompatibleArray::oid)
+    pub fn from_value<T: IntoDatum>(value: T) -> Option<AnyCompatibleArray> {
+        Some(AnyCompatibleArray {
+            datum: value.into_datum()?,
+            typoid: T::type_oid(),
+        })
+    }
+}
+
+impl FromDatum for AnyCompatibleArray {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<AnyCompatibleArray> {
+        if is_null {
+            None
+        } else {
+            Some(AnyCompatibleArray { datum, typoid })
+        }
+    }
+}
+
+impl IntoDatum for AnyCompatibleArray {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::ANYCOMPATIBLEARRAYOID
+    }
+}
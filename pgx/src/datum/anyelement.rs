@@ -28,6 +28,15 @@ impl AnyElement {
     pub fn into<T: FromDatum>(&self) -> Option<T> {
         unsafe { T::from_datum(self.datum(), false, self.oid()) }
     }
+
+    /// Wrap `value` to be returned, which must be of the type the function's polymorphic
+    /// arguments were resolved to, as given by their [`oid()`](AnyElement::oid)
+    pub fn from_value<T: IntoDatum>(value: T) -> Option<AnyElement> {
+        Some(AnyElement {
+            datum: value.into_datum()?,
+            typoid: T::type_oid(),
+        })
+    }
 }
 
 impl FromDatum for AnyElement {
@@ -0,0 +1,70 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, FromDatum, IntoDatum};
+
+/// A value of any type but an array, for Postgres' `anynonarray` pseudo-type.
+///
+/// It resolves to the same type as `anyelement` arguments and results of the same call, but
+/// the function won't match arrays.
+#[derive(Debug, Clone, Copy)]
+pub struct AnyNonArray {
+    datum: pg_sys::Datum,
+    typoid: pg_sys::Oid,
+}
+
+impl AnyNonArray {
+    pub fn datum(&self) -> pg_sys::Datum {
+        self.datum
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.typoid
+    }
+
+    #[inline]
+    pub fn into<T: FromDatum>(&self) -> Option<T> {
+        unsafe { T::from_datum(self.datum(), false, self.oid()) }
+    }
+
+    /// Wrap `value` to be returned, which must be of the type the function's polymorphic
+    /// arguments were resolved to, as given by their [`oid()`](AnyNonArray::oid)
+    pub fn from_value<T: IntoDatum>(value: T) -> Option<AnyNonArray> {
+        Some(AnyNonArray {
+            datum: value.into_datum()?,
+            typoid: T::type_oid(),
+        })
+    }
+}
+
+impl FromDatum for AnyNonArray {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<AnyNonArray> {
+        if is_null {
+            None
+        } else {
+            Some(AnyNonArray { datum, typoid })
+        }
+    }
+}
+
+impl IntoDatum for AnyNonArray {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::ANYNONARRAYOID
+    }
+}
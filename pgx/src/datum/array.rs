@@ -7,24 +7,134 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{pg_sys, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts};
+use crate::{
+    heap_tuple_header_get_type_id, pg_sys, varsize_any, void_mut_ptr, FromDatum, IntoDatum,
+    PgMemoryContexts,
+};
+use once_cell::unsync::OnceCell;
 use serde::Serializer;
 use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int};
 
 pub type VariadicArray<'a, T> = Array<'a, T>;
 
+/// A Postgres array, whose elements are converted to `T` as they're accessed
+///
+/// The array is read where it lies, without first copying its elements out, so iterating over it
+/// walks its data just once.  Arrays of fixed-width types without NULLs can also be borrowed
+/// directly as a slice with [`Array::as_slice()`].
+///
+/// Arrays of any number of dimensions are read as a flat sequence of their elements, in row-major
+/// order.  Their shape is given by [`Array::dims()`] and [`Array::lower_bounds()`].
 pub struct Array<'a, T: FromDatum> {
     ptr: *mut pg_sys::varlena,
     array_type: *mut pg_sys::ArrayType,
-    elements: *mut pg_sys::Datum,
-    nulls: *mut bool,
     typoid: pg_sys::Oid,
+    elemtype: pg_sys::Oid,
     nelems: usize,
-    elem_slice: &'a [pg_sys::Datum],
-    null_slice: &'a [bool],
-    _marker: PhantomData<T>,
+    ndim: usize,
+    /// The dimensions followed by the lower bounds, in the array's header
+    dims: *const c_int,
+    /// The dimension and lower bound of an array made by [`Array::over()`]
+    flat_shape: [c_int; 2],
+    layout: ElementLayout,
+    data: *const u8,
+    null_bitmap: *const u8,
+    /// The bitmap of an array made by [`Array::over()`]
+    owned_null_bitmap: Option<Vec<u8>>,
+    /// Whether NULL elements have a slot in `data`, which they only do for [`Array::over()`]
+    null_slots: bool,
+    /// Where each element starts in `data`, found the first time an element is accessed by index
+    /// when it can't be calculated
+    offsets: OnceCell<Vec<usize>>,
+    _marker: PhantomData<(&'a (), T)>,
 }
 
+/// How an array's elements are stored, from `get_typlenbyvalalign()`
+#[derive(Debug, Clone, Copy)]
+struct ElementLayout {
+    len: i16,
+    by_val: bool,
+    align: usize,
+    typalign: c_char,
+}
+
+impl ElementLayout {
+    unsafe fn of(elemtype: pg_sys::Oid) -> Self {
+        let mut len = 0;
+        let mut by_val = false;
+        let mut typalign = 0;
+        pg_sys::get_typlenbyvalalign(elemtype, &mut len, &mut by_val, &mut typalign);
+
+        let align = match typalign as u8 {
+            b'c' => 1,
+            b's' => 2,
+            b'i' => 4,
+            b'd' => 8,
+            other => panic!("unrecognized type alignment `{}`", other as char),
+        };
+        ElementLayout {
+            len,
+            by_val,
+            align,
+            typalign,
+        }
+    }
+
+    /// Round `offset` up to where an element can start, like `att_align_nominal()`
+    #[inline]
+    fn align(&self, offset: usize) -> usize {
+        (offset + self.align - 1) & !(self.align - 1)
+    }
+
+    /// The number of bytes the element at `ptr` takes, like `att_addlength_pointer()`
+    #[inline]
+    unsafe fn size_of(&self, ptr: *const u8) -> usize {
+        match self.len {
+            -1 => varsize_any(ptr as *const pg_sys::varlena),
+            -2 => std::ffi::CStr::from_ptr(ptr as *const std::os::raw::c_char)
+                .to_bytes_with_nul()
+                .len(),
+            len => len as usize,
+        }
+    }
+
+    /// The Datum for the element at `ptr`, like `fetch_att()`
+    #[inline]
+    unsafe fn fetch(&self, ptr: *const u8) -> pg_sys::Datum {
+        if !self.by_val {
+            return ptr as pg_sys::Datum;
+        }
+        match self.len {
+            1 => *(ptr as *const i8) as pg_sys::Datum,
+            2 => *(ptr as *const i16) as pg_sys::Datum,
+            4 => *(ptr as *const i32) as pg_sys::Datum,
+            8 => *(ptr as *const i64) as pg_sys::Datum,
+            len => panic!("unsupported length for a by-value type: {}", len),
+        }
+    }
+}
+
+/// Types which Postgres stores in arrays just as Rust lays them out in slices
+///
+/// [`Array::as_slice()`] only borrows the elements of an array of the type's
+/// [`type_oid()`](IntoDatum::type_oid).
+///
+/// # Safety
+///
+/// The type must be the same size as its Postgres type, which is fixed width, and any value of
+/// that type must be a valid value of this one.
+pub unsafe trait ArraySliceable: FromDatum + IntoDatum + Copy {}
+
+unsafe impl ArraySliceable for bool {}
+unsafe impl ArraySliceable for i8 {}
+unsafe impl ArraySliceable for i16 {}
+unsafe impl ArraySliceable for i32 {}
+unsafe impl ArraySliceable for u32 {}
+unsafe impl ArraySliceable for i64 {}
+unsafe impl ArraySliceable for f32 {}
+unsafe impl ArraySliceable for f64 {}
+
 impl<'a, T: FromDatum + serde::Serialize> serde::Serialize for Array<'a, T> {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
     where
@@ -51,22 +161,47 @@ impl<'a, T: FromDatum> Array<'a, T> {
     ///
     /// # Safety
     ///
-    /// This function is unsafe as it can't validate the provided pointer are valid or that
-    ///
+    /// This function is unsafe as it can't validate the provided pointers are valid, or that
+    /// they point to `nelems` values, for as long as the `Array` is used.  They aren't freed when
+    /// the `Array` is dropped.
     pub unsafe fn over(
         elements: *mut pg_sys::Datum,
         nulls: *mut bool,
         nelems: usize,
     ) -> Array<'a, T> {
+        let nulls = std::slice::from_raw_parts(nulls, nelems);
+        let owned_null_bitmap = if nulls.iter().any(|is_null| *is_null) {
+            let mut bitmap = vec![0u8; (nelems + 7) / 8];
+            for (i, _) in nulls.iter().enumerate().filter(|(_, is_null)| !**is_null) {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+            Some(bitmap)
+        } else {
+            None
+        };
+
         Array::<T> {
             ptr: std::ptr::null_mut(),
             array_type: std::ptr::null_mut(),
-            elements,
-            nulls,
             typoid: pg_sys::InvalidOid,
+            elemtype: pg_sys::InvalidOid,
             nelems,
-            elem_slice: std::slice::from_raw_parts(elements, nelems),
-            null_slice: std::slice::from_raw_parts(nulls, nelems),
+            ndim: 1,
+            dims: std::ptr::null(),
+            flat_shape: [nelems as c_int, 1],
+            layout: ElementLayout {
+                len: std::mem::size_of::<pg_sys::Datum>() as i16,
+                by_val: true,
+                align: std::mem::align_of::<pg_sys::Datum>(),
+                typalign: b'd' as c_char,
+            },
+            data: elements as *const u8,
+            null_bitmap: owned_null_bitmap
+                .as_ref()
+                .map_or(std::ptr::null(), |bitmap| bitmap.as_ptr()),
+            owned_null_bitmap,
+            null_slots: true,
+            offsets: OnceCell::new(),
             _marker: PhantomData,
         }
     }
@@ -74,20 +209,44 @@ impl<'a, T: FromDatum> Array<'a, T> {
     unsafe fn from_pg(
         ptr: *mut pg_sys::varlena,
         array_type: *mut pg_sys::ArrayType,
-        elements: *mut pg_sys::Datum,
-        nulls: *mut bool,
         typoid: pg_sys::Oid,
-        nelems: usize,
     ) -> Self {
+        let array_ref = array_type.as_ref().expect("ArrayType * was NULL");
+
+        // the header is followed by the dimensions, lower bounds, and the null bitmap if there
+        // are any NULLs, then the data is aligned after those, like `ARR_DATA_PTR()`
+        let ndim = array_ref.ndim as usize;
+        let dims =
+            (array_type as *const u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *const c_int;
+        let nelems = pg_sys::ArrayGetNItems(array_ref.ndim, dims) as usize;
+        let (data, null_bitmap) = if array_ref.dataoffset != 0 {
+            (
+                (array_type as *const u8).add(array_ref.dataoffset as usize),
+                dims.add(2 * ndim) as *const u8,
+            )
+        } else {
+            let overhead =
+                std::mem::size_of::<pg_sys::ArrayType>() + 2 * ndim * std::mem::size_of::<c_int>();
+            let maxalign = pg_sys::MAXIMUM_ALIGNOF as usize;
+            let overhead = (overhead + maxalign - 1) & !(maxalign - 1);
+            ((array_type as *const u8).add(overhead), std::ptr::null())
+        };
+
         Array::<T> {
             ptr,
             array_type,
-            elements,
-            nulls,
             typoid,
+            elemtype: array_ref.elemtype,
             nelems,
-            elem_slice: std::slice::from_raw_parts(elements, nelems),
-            null_slice: std::slice::from_raw_parts(nulls, nelems),
+            ndim,
+            dims,
+            flat_shape: [0, 0],
+            layout: ElementLayout::of(array_ref.elemtype),
+            data,
+            null_bitmap,
+            owned_null_bitmap: None,
+            null_slots: false,
+            offsets: OnceCell::new(),
             _marker: PhantomData,
         }
     }
@@ -102,22 +261,30 @@ impl<'a, T: FromDatum> Array<'a, T> {
         ptr
     }
 
-    pub fn as_slice(&self) -> &[T] {
-        let sizeof_type = std::mem::size_of::<T>();
-        let sizeof_datums = std::mem::size_of_val(self.elem_slice);
-        unsafe {
-            std::slice::from_raw_parts(
-                self.elem_slice.as_ptr() as *const T,
-                sizeof_datums / sizeof_type,
-            )
+    /// Borrow the elements as a slice, without converting them
+    ///
+    /// Returns `None` unless the array's element type is `T`'s own type, as another type of the
+    /// same size, such as an `oid` for an `i32`, or a `"char"` for a `bool`, isn't a `T`.
+    ///
+    /// This function will panic when called if the array contains any SQL NULL values.
+    pub fn as_slice(&self) -> Option<&[T]>
+    where
+        T: ArraySliceable,
+    {
+        if self.elemtype != T::type_oid() {
+            return None;
+        } else if self.contains_nulls() {
+            panic!("array contains NULL");
         }
+
+        Some(unsafe { std::slice::from_raw_parts(self.data as *const T, self.nelems) })
     }
 
     /// Return an Iterator of Option<T> over the contained Datums.
     pub fn iter(&self) -> ArrayIterator<'_, T> {
         ArrayIterator {
             array: self,
-            curr: 0,
+            cursor: Cursor::default(),
         }
     }
 
@@ -125,42 +292,209 @@ impl<'a, T: FromDatum> Array<'a, T> {
     ///
     /// This function will panic when called if the array contains any SQL NULL values.
     pub fn iter_deny_null(&self) -> ArrayTypedIterator<'_, T> {
-        if self.array_type.is_null() {
-            panic!("array is NULL");
-        } else if unsafe { pg_sys::array_contains_nulls(self.array_type) } {
+        if self.contains_nulls() {
             panic!("array contains NULL");
         }
 
         ArrayTypedIterator {
             array: self,
-            curr: 0,
+            cursor: Cursor::default(),
         }
     }
 
+    /// The number of elements, across all of the array's dimensions
     #[inline]
     pub fn len(&self) -> usize {
         self.nelems
     }
 
+    /// The number of dimensions, which is zero for an empty array
+    #[inline]
+    pub fn ndim(&self) -> usize {
+        self.ndim
+    }
+
+    /// The length of each dimension, outermost first
+    #[inline]
+    pub fn dims(&self) -> &[i32] {
+        if self.dims.is_null() {
+            &self.flat_shape[..1]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.dims, self.ndim) }
+        }
+    }
+
+    /// The index of the first element in each dimension, which is 1 unless the array was made
+    /// with others, such as by `'[0:1]={1,2}'::int[]`
+    #[inline]
+    pub fn lower_bounds(&self) -> &[i32] {
+        if self.dims.is_null() {
+            &self.flat_shape[1..]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.dims.add(self.ndim), self.ndim) }
+        }
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.nelems == 0
     }
 
+    /// Does the array contain any SQL NULL values?
+    #[inline]
+    pub fn contains_nulls(&self) -> bool {
+        // Postgres can leave a bitmap on an array whose NULLs were all removed
+        !self.null_bitmap.is_null() && (0..self.nelems).any(|i| self.is_null(i))
+    }
+
+    /// Is the element at index `i` NULL?  Elements past the end of the array aren't.
+    #[inline]
+    pub fn is_null(&self, i: usize) -> bool {
+        match self.null_bitmap() {
+            Some(bitmap) if i < self.nelems => bitmap[i / 8] & (1 << (i % 8)) == 0,
+            _ => false,
+        }
+    }
+
+    /// The array's null bitmap, where bit `i % 8` of byte `i / 8` is clear if element `i` is NULL,
+    /// or `None` if the array has no bitmap as no element is NULL
+    #[inline]
+    pub fn null_bitmap(&self) -> Option<&[u8]> {
+        if self.null_bitmap.is_null() {
+            None
+        } else {
+            Some(unsafe { std::slice::from_raw_parts(self.null_bitmap, (self.nelems + 7) / 8) })
+        }
+    }
+
     #[allow(clippy::option_option)]
     #[inline]
     pub fn get(&self, i: usize) -> Option<Option<T>> {
         if i >= self.nelems {
             None
+        } else if self.is_null(i) {
+            Some(unsafe { T::from_datum(0, true, self.typoid) })
+        } else {
+            let offset = self.offset_of(i);
+            Some(unsafe { self.element_at(offset) })
+        }
+    }
+
+    /// Where element `i`, which isn't NULL, starts in the data
+    fn offset_of(&self, i: usize) -> usize {
+        if self.layout.len > 0 && (self.null_slots || self.null_bitmap.is_null()) {
+            return i * self.layout.align(self.layout.len as usize);
+        }
+
+        self.offsets.get_or_init(|| {
+            let mut cursor = Cursor::default();
+            let mut offsets = Vec::with_capacity(self.nelems);
+            while cursor.index < self.nelems {
+                offsets.push(cursor.offset);
+                unsafe { self.advance(&mut cursor) };
+            }
+            offsets
+        })[i]
+    }
+
+    /// The element starting at `offset` in the data
+    #[inline]
+    unsafe fn element_at(&self, offset: usize) -> Option<T> {
+        let datum = self.layout.fetch(self.data.add(offset));
+        T::from_datum(datum, false, self.typoid)
+    }
+
+    /// Move `cursor` past the element it's on, returning that element's offset in the data, or
+    /// `None` if it's NULL
+    #[inline]
+    unsafe fn advance(&self, cursor: &mut Cursor) -> Option<usize> {
+        let is_null = self.is_null(cursor.index);
+        cursor.index += 1;
+        if is_null && !self.null_slots {
+            return None;
+        }
+
+        let offset = cursor.offset;
+        let size = self.layout.size_of(self.data.add(offset));
+        cursor.offset = self.layout.align(offset + size);
+        if is_null {
+            None
         } else {
-            Some(unsafe { T::from_datum(self.elem_slice[i], self.null_slice[i], self.typoid) })
+            Some(offset)
+        }
+    }
+
+    /// Split the array along its outermost dimension, into arrays of one fewer dimension
+    ///
+    /// ## Panics
+    ///
+    /// If the array has only one dimension.
+    unsafe fn subarrays(&self) -> Vec<pg_sys::Datum> {
+        if self.nelems == 0 {
+            return Vec::new();
+        } else if self.ndim < 2 {
+            panic!(
+                "a {}-dimensional array can't be split into arrays",
+                self.ndim
+            );
+        }
+
+        let rows = self.dims()[0] as usize;
+        let mut dims = self.dims()[1..].to_vec();
+        let mut lower_bounds = self.lower_bounds()[1..].to_vec();
+        let row_len = self.nelems / rows;
+        let mut cursor = Cursor::default();
+        (0..rows)
+            .map(|_| {
+                let mut elements = Vec::with_capacity(row_len);
+                let mut nulls = Vec::with_capacity(row_len);
+                for _ in 0..row_len {
+                    let offset = self.advance(&mut cursor);
+                    elements
+                        .push(offset.map_or(0, |offset| self.layout.fetch(self.data.add(offset))));
+                    nulls.push(offset.is_none());
+                }
+                pg_sys::construct_md_array(
+                    elements.as_mut_ptr(),
+                    nulls.as_mut_ptr(),
+                    dims.len() as c_int,
+                    dims.as_mut_ptr(),
+                    lower_bounds.as_mut_ptr(),
+                    self.elemtype,
+                    self.layout.len as c_int,
+                    self.layout.by_val,
+                    self.layout.typalign,
+                ) as pg_sys::Datum
+            })
+            .collect()
+    }
+
+    /// The element `cursor` is on, converted to a `T`, moving past it
+    #[allow(clippy::option_option)]
+    #[inline]
+    fn next_element(&self, cursor: &mut Cursor) -> Option<Option<T>> {
+        if cursor.index >= self.nelems {
+            return None;
+        }
+        unsafe {
+            Some(match self.advance(cursor) {
+                Some(offset) => self.element_at(offset),
+                None => T::from_datum(0, true, self.typoid),
+            })
         }
     }
 }
 
+/// How far through an array an iterator is
+#[derive(Debug, Default, Clone, Copy)]
+struct Cursor {
+    index: usize,
+    offset: usize,
+}
+
 pub struct ArrayTypedIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
-    curr: usize,
+    cursor: Cursor,
 }
 
 impl<'a, T: FromDatum> Iterator for ArrayTypedIterator<'a, T> {
@@ -168,23 +502,22 @@ impl<'a, T: FromDatum> Iterator for ArrayTypedIterator<'a, T> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.curr >= self.array.nelems {
-            None
-        } else {
-            let element = self
-                .array
-                .get(self.curr)
-                .expect("array index out of bounds")
-                .expect("array element was unexpectedly NULL during iteration");
-            self.curr += 1;
-            Some(element)
-        }
+        self.array
+            .next_element(&mut self.cursor)
+            .map(|element| element.expect("array element was unexpectedly NULL during iteration"))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.array.nelems - self.cursor.index;
+        (remaining, Some(remaining))
     }
 }
 
+impl<'a, T: FromDatum> ExactSizeIterator for ArrayTypedIterator<'a, T> {}
+
 pub struct ArrayIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
-    curr: usize,
+    cursor: Cursor,
 }
 
 impl<'a, T: FromDatum> Iterator for ArrayIterator<'a, T> {
@@ -192,19 +525,20 @@ impl<'a, T: FromDatum> Iterator for ArrayIterator<'a, T> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.curr >= self.array.nelems {
-            None
-        } else {
-            let element = self.array.get(self.curr).unwrap();
-            self.curr += 1;
-            Some(element)
-        }
+        self.array.next_element(&mut self.cursor)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.array.nelems - self.cursor.index;
+        (remaining, Some(remaining))
     }
 }
 
+impl<'a, T: FromDatum> ExactSizeIterator for ArrayIterator<'a, T> {}
+
 pub struct ArrayIntoIterator<'a, T: FromDatum> {
     array: Array<'a, T>,
-    curr: usize,
+    cursor: Cursor,
 }
 
 impl<'a, T: FromDatum> IntoIterator for Array<'a, T> {
@@ -214,7 +548,7 @@ impl<'a, T: FromDatum> IntoIterator for Array<'a, T> {
     fn into_iter(self) -> Self::IntoIter {
         ArrayIntoIterator {
             array: self,
-            curr: 0,
+            cursor: Cursor::default(),
         }
     }
 }
@@ -224,45 +558,26 @@ impl<'a, T: FromDatum> Iterator for ArrayIntoIterator<'a, T> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.curr >= self.array.nelems {
-            None
-        } else {
-            let element = self.array.get(self.curr).unwrap();
-            self.curr += 1;
-            Some(element)
-        }
+        self.array.next_element(&mut self.cursor)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(self.array.nelems))
+        let remaining = self.array.nelems - self.cursor.index;
+        (remaining, Some(remaining))
     }
 
     fn count(self) -> usize
     where
         Self: Sized,
     {
-        self.array.nelems
-    }
-
-    fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.array.get(n)
+        self.array.nelems - self.cursor.index
     }
 }
 
+impl<'a, T: FromDatum> ExactSizeIterator for ArrayIntoIterator<'a, T> {}
+
 impl<'a, T: FromDatum> Drop for Array<'a, T> {
     fn drop(&mut self) {
-        if !self.elements.is_null() {
-            unsafe {
-                pg_sys::pfree(self.elements as void_mut_ptr);
-            }
-        }
-
-        if !self.nulls.is_null() {
-            unsafe {
-                pg_sys::pfree(self.nulls as void_mut_ptr);
-            }
-        }
-
         if !self.array_type.is_null() && self.array_type as *mut pg_sys::varlena != self.ptr {
             unsafe {
                 pg_sys::pfree(self.array_type as void_mut_ptr);
@@ -276,6 +591,8 @@ impl<'a, T: FromDatum> Drop for Array<'a, T> {
 }
 
 impl<'a, T: FromDatum> FromDatum for Array<'a, T> {
+    const IS_ARRAY: bool = true;
+
     #[inline]
     unsafe fn from_datum(datum: usize, is_null: bool, typoid: u32) -> Option<Array<'a, T>> {
         if is_null {
@@ -286,49 +603,15 @@ impl<'a, T: FromDatum> FromDatum for Array<'a, T> {
             let ptr = datum as *mut pg_sys::varlena;
             let array =
                 pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::ArrayType;
-            let array_ref = array.as_ref().expect("ArrayType * was NULL");
-
-            // outvals for get_typlenbyvalalign()
-            let mut typlen = 0;
-            let mut typbyval = false;
-            let mut typalign = 0;
-
-            pg_sys::get_typlenbyvalalign(
-                array_ref.elemtype,
-                &mut typlen,
-                &mut typbyval,
-                &mut typalign,
-            );
-
-            // outvals for deconstruct_array()
-            let mut elements = std::ptr::null_mut();
-            let mut nulls = std::ptr::null_mut();
-            let mut nelems = 0;
-
-            pg_sys::deconstruct_array(
-                array,
-                array_ref.elemtype,
-                typlen as i32,
-                typbyval,
-                typalign,
-                &mut elements,
-                &mut nulls,
-                &mut nelems,
-            );
 
-            Some(Array::from_pg(
-                ptr,
-                array,
-                elements,
-                nulls,
-                typoid,
-                nelems as usize,
-            ))
+            Some(Array::from_pg(ptr, array, typoid))
         }
     }
 }
 
 impl<T: FromDatum> FromDatum for Vec<T> {
+    const IS_ARRAY: bool = true;
+
     #[inline]
     unsafe fn from_datum(
         datum: pg_sys::Datum,
@@ -339,6 +622,15 @@ impl<T: FromDatum> FromDatum for Vec<T> {
             None
         } else if datum == 0 {
             panic!("array was flagged not null but datum is zero");
+        } else if T::IS_ARRAY {
+            let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
+            Some(
+                array
+                    .subarrays()
+                    .into_iter()
+                    .map(|row| T::from_datum(row, false, typoid).unwrap())
+                    .collect(),
+            )
         } else {
             let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
             let mut v = Vec::with_capacity(array.len());
@@ -352,6 +644,8 @@ impl<T: FromDatum> FromDatum for Vec<T> {
 }
 
 impl<T: FromDatum> FromDatum for Vec<Option<T>> {
+    const IS_ARRAY: bool = true;
+
     #[inline]
     unsafe fn from_datum(
         datum: pg_sys::Datum,
@@ -362,6 +656,15 @@ impl<T: FromDatum> FromDatum for Vec<Option<T>> {
             None
         } else if datum == 0 {
             panic!("array was flagged not null but datum is zero");
+        } else if T::IS_ARRAY {
+            let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
+            Some(
+                array
+                    .subarrays()
+                    .into_iter()
+                    .map(|row| T::from_datum(row, false, typoid))
+                    .collect(),
+            )
         } else {
             let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
             let mut v = Vec::with_capacity(array.len());
@@ -379,40 +682,11 @@ where
     T: IntoDatum,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
-        let mut state = unsafe {
-            pg_sys::initArrayResult(
-                T::type_oid(),
-                PgMemoryContexts::CurrentMemoryContext.value(),
-                false,
-            )
-        };
-        for s in self {
-            let datum = s.into_datum();
-            let isnull = datum.is_none();
-
-            unsafe {
-                state = pg_sys::accumArrayResult(
-                    state,
-                    datum.unwrap_or(0usize),
-                    isnull,
-                    T::type_oid(),
-                    PgMemoryContexts::CurrentMemoryContext.value(),
-                );
-            }
-        }
-
-        if state.is_null() {
-            // shoudln't happen
-            None
-        } else {
-            Some(unsafe {
-                pg_sys::makeArrayResult(state, PgMemoryContexts::CurrentMemoryContext.value())
-            })
-        }
+        accum_array(self)
     }
 
     fn type_oid() -> u32 {
-        unsafe { pg_sys::get_array_type(T::type_oid()) }
+        array_type_of(T::type_oid())
     }
 }
 
@@ -421,35 +695,195 @@ where
     T: IntoDatum + Copy,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
-        let mut state = unsafe {
-            pg_sys::initArrayResult(
-                T::type_oid(),
-                PgMemoryContexts::CurrentMemoryContext.value(),
-                false,
-            )
-        };
-        for s in self {
-            let datum = s.into_datum();
-            let isnull = datum.is_none();
+        accum_array(self.iter().copied())
+    }
 
-            unsafe {
-                state = pg_sys::accumArrayResult(
-                    state,
-                    datum.unwrap_or(0usize),
-                    isnull,
-                    T::type_oid(),
-                    PgMemoryContexts::CurrentMemoryContext.value(),
-                );
+    fn type_oid() -> u32 {
+        array_type_of(T::type_oid())
+    }
+}
+
+/// The type of an array of `typoid`, which is `typoid` itself if it's already an array type, as
+/// Postgres arrays of arrays are multidimensional arrays of their elements
+fn array_type_of(typoid: pg_sys::Oid) -> pg_sys::Oid {
+    unsafe {
+        if pg_sys::get_element_type(typoid) != pg_sys::InvalidOid {
+            typoid
+        } else {
+            pg_sys::get_array_type(typoid)
+        }
+    }
+}
+
+/// Build an array of `elements`, which has another dimension when they're arrays themselves
+fn accum_array<T: IntoDatum>(elements: impl IntoIterator<Item = T>) -> Option<pg_sys::Datum> {
+    let datums = elements
+        .into_iter()
+        .map(IntoDatum::into_datum)
+        .collect::<Vec<_>>();
+    let element_type = element_type_of(T::type_oid(), datums.iter().flatten().copied());
+
+    let context = PgMemoryContexts::CurrentMemoryContext.value();
+    let mut state = unsafe { pg_sys::initArrayResultAny(element_type, context, false) };
+    for datum in datums {
+        let isnull = datum.is_none();
+
+        unsafe {
+            state = pg_sys::accumArrayResultAny(
+                state,
+                datum.unwrap_or(0usize),
+                isnull,
+                element_type,
+                context,
+            );
+        }
+    }
+
+    if state.is_null() {
+        // shoudln't happen
+        None
+    } else {
+        Some(unsafe { pg_sys::makeArrayResultAny(state, context, false) })
+    }
+}
+
+/// The type of the elements of an array of `datums`, which were converted from a `typoid`.
+///
+/// Records, and arrays of them, are of the composite type they were built as, such as a table's
+/// row type, so that an array of them is an array of that type rather than `record[]`.
+fn element_type_of(
+    typoid: pg_sys::Oid,
+    datums: impl Iterator<Item = pg_sys::Datum>,
+) -> pg_sys::Oid {
+    if typoid != pg_sys::RECORDOID && typoid != pg_sys::RECORDARRAYOID {
+        return typoid;
+    }
+
+    let mut element_type = None;
+    for datum in datums {
+        let actual = unsafe {
+            let varlena = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena);
+            if typoid == pg_sys::RECORDOID {
+                heap_tuple_header_get_type_id(varlena as pg_sys::HeapTupleHeader)
+            } else {
+                array_type_of((*(varlena as *mut pg_sys::ArrayType)).elemtype)
+            }
+        };
+        match element_type {
+            None => element_type = Some(actual),
+            Some(element_type) if element_type != actual => {
+                panic!("the elements of an array must all be of the same composite type")
             }
+            Some(_) => (),
+        }
+    }
+    element_type.unwrap_or(typoid)
+}
+
+/// A multidimensional array to return to Postgres, built like an `ndarray` from its shape and
+/// its elements in row-major order
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn identity_matrix(n: i32) -> ArrayBuilder<i32> {
+///     let n = n as usize;
+///     let mut matrix = ArrayBuilder::new(&[n, n]);
+///     matrix.extend((0..n * n).map(|i| Some((i % (n + 1) == 0) as i32)));
+///     matrix
+/// }
+/// ```
+///
+/// Nested `Vec`s, such as a `Vec<Vec<i32>>`, also become multidimensional arrays, but they can't
+/// be empty in any dimension but the outermost, and Postgres requires them to be rectangular.
+pub struct ArrayBuilder<T> {
+    dims: Vec<c_int>,
+    lower_bounds: Vec<c_int>,
+    elements: Vec<Option<T>>,
+}
+
+impl<T: IntoDatum> ArrayBuilder<T> {
+    /// An array with dimensions of the given lengths, outermost first, each indexed from 1
+    pub fn new(dims: &[usize]) -> Self {
+        let nelems = dims.iter().product();
+        ArrayBuilder {
+            dims: dims.iter().map(|len| *len as c_int).collect(),
+            lower_bounds: vec![1; dims.len()],
+            elements: Vec::with_capacity(nelems),
         }
+    }
 
-        if state.is_null() {
-            // shoudln't happen
-            None
-        } else {
-            Some(unsafe {
-                pg_sys::makeArrayResult(state, PgMemoryContexts::CurrentMemoryContext.value())
-            })
+    /// Index each dimension from the given bound instead of 1
+    pub fn lower_bounds(mut self, lower_bounds: &[i32]) -> Self {
+        if lower_bounds.len() != self.dims.len() {
+            panic!(
+                "{} lower bounds were given for an array of {} dimensions",
+                lower_bounds.len(),
+                self.dims.len()
+            );
+        }
+        self.lower_bounds = lower_bounds.to_vec();
+        self
+    }
+
+    /// Add the next element, or a NULL, after those already added
+    pub fn push(&mut self, element: Option<T>) {
+        self.elements.push(element);
+    }
+}
+
+impl<T: IntoDatum> Extend<Option<T>> for ArrayBuilder<T> {
+    fn extend<I: IntoIterator<Item = Option<T>>>(&mut self, iter: I) {
+        self.elements.extend(iter)
+    }
+}
+
+impl<T: IntoDatum> IntoDatum for ArrayBuilder<T> {
+    /// ## Panics
+    ///
+    /// If the number of elements added doesn't fit the array's dimensions.
+    fn into_datum(mut self) -> Option<pg_sys::Datum> {
+        let nelems = self.dims.iter().map(|len| *len as usize).product::<usize>();
+        if self.elements.len() != nelems {
+            panic!(
+                "an array of dimensions {:?} has {} elements, but {} were added",
+                self.dims,
+                nelems,
+                self.elements.len()
+            );
+        }
+
+        let mut nulls = Vec::with_capacity(nelems);
+        let mut datums = Vec::with_capacity(nelems);
+        for element in self.elements {
+            let datum = element.and_then(IntoDatum::into_datum);
+            nulls.push(datum.is_none());
+            datums.push(datum.unwrap_or(0));
+        }
+        let element_type = element_type_of(
+            T::type_oid(),
+            datums
+                .iter()
+                .zip(&nulls)
+                .filter(|(_, isnull)| !**isnull)
+                .map(|(datum, _)| *datum),
+        );
+
+        unsafe {
+            let layout = ElementLayout::of(element_type);
+            let array = pg_sys::construct_md_array(
+                datums.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+                self.dims.len() as c_int,
+                self.dims.as_mut_ptr(),
+                self.lower_bounds.as_mut_ptr(),
+                element_type,
+                layout.len as c_int,
+                layout.by_val,
+                layout.typalign,
+            );
+            Some(array as pg_sys::Datum)
         }
     }
 
@@ -0,0 +1,268 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Conversions between the date/time types and `chrono`'s, with the `chrono` feature
+use crate::datum::time::DateTimeConversionError;
+use crate::{pg_sys, Date, FromDatum, Interval, IntoDatum, Time, Timestamp, TimestampWithTimeZone};
+use chrono::{Datelike, TimeZone, Timelike};
+use std::convert::TryFrom;
+
+fn to_naive_date(value: &time::Date) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(value.year(), value.month() as u8 as u32, value.day() as u32)
+        .expect("chrono can't represent the date")
+}
+
+fn from_naive_date(value: &chrono::NaiveDate) -> Result<time::Date, DateTimeConversionError> {
+    let month = time::Month::try_from(value.month() as u8)
+        .map_err(|_| DateTimeConversionError::OutOfRange)?;
+    time::Date::from_calendar_date(value.year(), month, value.day() as u8)
+        .map_err(|_| DateTimeConversionError::OutOfRange)
+}
+
+fn to_naive_time(value: &time::Time) -> chrono::NaiveTime {
+    chrono::NaiveTime::from_hms_micro_opt(
+        value.hour() as u32,
+        value.minute() as u32,
+        value.second() as u32,
+        value.microsecond(),
+    )
+    .expect("chrono can't represent the time")
+}
+
+/// Postgres times have microseconds, so any nanoseconds are truncated
+fn from_naive_time(value: &chrono::NaiveTime) -> Result<time::Time, DateTimeConversionError> {
+    time::Time::from_hms_micro(
+        value.hour() as u8,
+        value.minute() as u8,
+        value.second() as u8,
+        value.nanosecond() / 1_000,
+    )
+    .map_err(|_| DateTimeConversionError::OutOfRange)
+}
+
+impl TryFrom<Date> for chrono::NaiveDate {
+    type Error = DateTimeConversionError;
+
+    fn try_from(date: Date) -> Result<Self, Self::Error> {
+        if !date.is_finite() {
+            return Err(DateTimeConversionError::Infinite);
+        }
+        Ok(to_naive_date(&date))
+    }
+}
+
+impl TryFrom<chrono::NaiveDate> for Date {
+    type Error = DateTimeConversionError;
+
+    fn try_from(date: chrono::NaiveDate) -> Result<Self, Self::Error> {
+        Ok(Date::new(from_naive_date(&date)?))
+    }
+}
+
+impl From<Time> for chrono::NaiveTime {
+    fn from(time: Time) -> Self {
+        to_naive_time(&time)
+    }
+}
+
+impl TryFrom<chrono::NaiveTime> for Time {
+    type Error = DateTimeConversionError;
+
+    fn try_from(time: chrono::NaiveTime) -> Result<Self, Self::Error> {
+        Ok(Time::new(from_naive_time(&time)?))
+    }
+}
+
+impl TryFrom<Timestamp> for chrono::NaiveDateTime {
+    type Error = DateTimeConversionError;
+
+    fn try_from(timestamp: Timestamp) -> Result<Self, Self::Error> {
+        if !timestamp.is_finite() {
+            return Err(DateTimeConversionError::Infinite);
+        }
+        Ok(to_naive_date(&timestamp.date()).and_time(to_naive_time(&timestamp.time())))
+    }
+}
+
+impl TryFrom<chrono::NaiveDateTime> for Timestamp {
+    type Error = DateTimeConversionError;
+
+    fn try_from(timestamp: chrono::NaiveDateTime) -> Result<Self, Self::Error> {
+        let date = from_naive_date(&timestamp.date())?;
+        let time_of_day = from_naive_time(&timestamp.time())?;
+        Ok(Timestamp::new(time::PrimitiveDateTime::new(
+            date,
+            time_of_day,
+        )))
+    }
+}
+
+impl TryFrom<TimestampWithTimeZone> for chrono::DateTime<chrono::Utc> {
+    type Error = DateTimeConversionError;
+
+    fn try_from(timestamp: TimestampWithTimeZone) -> Result<Self, Self::Error> {
+        if !timestamp.is_finite() {
+            return Err(DateTimeConversionError::Infinite);
+        }
+        let utc = timestamp.to_offset(time::UtcOffset::UTC);
+        let naive = to_naive_date(&utc.date()).and_time(to_naive_time(&utc.time()));
+        Ok(chrono::Utc.from_utc_datetime(&naive))
+    }
+}
+
+impl<Tz: chrono::TimeZone> TryFrom<chrono::DateTime<Tz>> for TimestampWithTimeZone {
+    type Error = DateTimeConversionError;
+
+    fn try_from(timestamp: chrono::DateTime<Tz>) -> Result<Self, Self::Error> {
+        let naive = timestamp.naive_utc();
+        let date = from_naive_date(&naive.date())?;
+        let time_of_day = from_naive_time(&naive.time())?;
+        Ok(TimestampWithTimeZone::from(
+            time::PrimitiveDateTime::new(date, time_of_day).assume_utc(),
+        ))
+    }
+}
+
+/// Counts a day as 24 hours, as Postgres does when it compares intervals, but can't convert months
+impl TryFrom<Interval> for chrono::Duration {
+    type Error = DateTimeConversionError;
+
+    fn try_from(interval: Interval) -> Result<Self, Self::Error> {
+        if interval.months() != 0 {
+            return Err(DateTimeConversionError::IntervalHasMonths);
+        }
+        chrono::Duration::days(interval.days() as i64)
+            .checked_add(&chrono::Duration::microseconds(interval.micros()))
+            .ok_or(DateTimeConversionError::OutOfRange)
+    }
+}
+
+impl TryFrom<chrono::Duration> for Interval {
+    type Error = DateTimeConversionError;
+
+    fn try_from(duration: chrono::Duration) -> Result<Self, Self::Error> {
+        duration
+            .num_microseconds()
+            .map(Interval::from_micros)
+            .ok_or(DateTimeConversionError::OutOfRange)
+    }
+}
+
+/// Converts a Postgres value through the pgx type `P`, raising an ERROR if it's infinite or out of
+/// `chrono`'s range
+unsafe fn from_datum_via<P, C>(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<C>
+where
+    P: FromDatum,
+    C: TryFrom<P, Error = DateTimeConversionError>,
+{
+    P::from_datum(datum, is_null, typoid).map(|value| {
+        C::try_from(value).unwrap_or_else(|e| {
+            panic!(
+                "{} can't be converted to a {}: {}",
+                std::any::type_name::<P>(),
+                std::any::type_name::<C>(),
+                e
+            )
+        })
+    })
+}
+
+/// Converts a `chrono` value through the pgx type `P`, raising an ERROR if it's out of Postgres'
+/// range
+fn into_datum_via<C, P>(value: C) -> Option<pg_sys::Datum>
+where
+    P: IntoDatum + TryFrom<C, Error = DateTimeConversionError>,
+{
+    P::try_from(value)
+        .unwrap_or_else(|e| {
+            panic!(
+                "{} can't be converted to a {}: {}",
+                std::any::type_name::<C>(),
+                std::any::type_name::<P>(),
+                e
+            )
+        })
+        .into_datum()
+}
+
+impl FromDatum for chrono::NaiveDate {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Self> {
+        from_datum_via::<Date, _>(datum, is_null, typoid)
+    }
+}
+
+impl IntoDatum for chrono::NaiveDate {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        into_datum_via::<_, Date>(self)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::DATEOID
+    }
+}
+
+impl FromDatum for chrono::NaiveTime {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Self> {
+        Time::from_datum(datum, is_null, typoid).map(chrono::NaiveTime::from)
+    }
+}
+
+/// Postgres times have microseconds, so any nanoseconds are truncated
+impl IntoDatum for chrono::NaiveTime {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        into_datum_via::<_, Time>(self)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMEOID
+    }
+}
+
+impl FromDatum for chrono::NaiveDateTime {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Self> {
+        from_datum_via::<Timestamp, _>(datum, is_null, typoid)
+    }
+}
+
+/// Postgres timestamps have microseconds, so any nanoseconds are truncated
+impl IntoDatum for chrono::NaiveDateTime {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        into_datum_via::<_, Timestamp>(self)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMESTAMPOID
+    }
+}
+
+impl FromDatum for chrono::DateTime<chrono::Utc> {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Self> {
+        from_datum_via::<TimestampWithTimeZone, _>(datum, is_null, typoid)
+    }
+}
+
+/// Postgres timestamps have microseconds, so any nanoseconds are truncated
+impl IntoDatum for chrono::DateTime<chrono::Utc> {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        into_datum_via::<_, TimestampWithTimeZone>(self)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMESTAMPTZOID
+    }
+}
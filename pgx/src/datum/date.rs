@@ -7,10 +7,14 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{pg_sys, FromDatum, IntoDatum};
-use std::ops::{Deref, DerefMut};
+use crate::{direct_function_call, pg_sys, FromDatum, Interval, IntoDatum, Timestamp};
+use std::ops::{Add, Deref, DerefMut, Sub};
 use time::format_description::FormatItem;
 
+/// Postgres' `-infinity` and `infinity` dates, `DATEVAL_NOBEGIN` and `DATEVAL_NOEND`
+const DATEVAL_NOBEGIN: i32 = i32::MIN;
+const DATEVAL_NOEND: i32 = i32::MAX;
+
 #[derive(Debug)]
 pub struct Date(time::Date);
 impl FromDatum for Date {
@@ -19,6 +23,10 @@ impl FromDatum for Date {
     unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _typoid: u32) -> Option<Date> {
         if is_null {
             None
+        } else if datum as i32 == DATEVAL_NOBEGIN {
+            Some(Date::NEG_INFINITY)
+        } else if datum as i32 == DATEVAL_NOEND {
+            Some(Date::INFINITY)
         } else {
             Some(Date(
                 time::Date::from_julian_day(datum as i32 + pg_sys::POSTGRES_EPOCH_JDATE as i32)
@@ -30,7 +38,36 @@ impl FromDatum for Date {
 impl IntoDatum for Date {
     #[inline]
     fn into_datum(self) -> Option<pg_sys::Datum> {
-        Some((self.to_julian_day() as i32 - pg_sys::POSTGRES_EPOCH_JDATE as i32) as pg_sys::Datum)
+        if self.is_neg_infinity() {
+            Some(DATEVAL_NOBEGIN as pg_sys::Datum)
+        } else if self.is_infinity() {
+            Some(DATEVAL_NOEND as pg_sys::Datum)
+        } else {
+            Some(
+                (self.to_julian_day() as i32 - pg_sys::POSTGRES_EPOCH_JDATE as i32)
+                    as pg_sys::Datum,
+            )
+        }
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::DATEOID
+    }
+}
+
+/// `time::Date::MAX` and `MIN` are `infinity` and `-infinity`, as for [`Date`]
+impl FromDatum for time::Date {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<time::Date> {
+        Date::from_datum(datum, is_null, typoid).map(|date| date.0)
+    }
+}
+
+impl IntoDatum for time::Date {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Date(self).into_datum()
     }
 
     fn type_oid() -> u32 {
@@ -39,9 +76,61 @@ impl IntoDatum for Date {
 }
 
 impl Date {
+    /// Postgres' `infinity`, which is later than every other date.
+    ///
+    /// It's represented by `time::Date::MAX`, so that date is `infinity` once given to Postgres.
+    pub const INFINITY: Date = Date(time::Date::MAX);
+
+    /// Postgres' `-infinity`, which is earlier than every other date.
+    ///
+    /// It's represented by `time::Date::MIN`, so that date is `-infinity` once given to Postgres.
+    pub const NEG_INFINITY: Date = Date(time::Date::MIN);
+
     pub fn new(date: time::Date) -> Self {
         Date(date)
     }
+
+    pub fn is_infinity(&self) -> bool {
+        self.0 == Self::INFINITY.0
+    }
+
+    pub fn is_neg_infinity(&self) -> bool {
+        self.0 == Self::NEG_INFINITY.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        !self.is_infinity() && !self.is_neg_infinity()
+    }
+}
+
+/// The date at midnight plus the interval, as Postgres' `date + interval` does
+impl Add<Interval> for Date {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Interval) -> Timestamp {
+        unsafe {
+            direct_function_call(
+                pg_sys::date_pl_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("date + interval returned NULL")
+        }
+    }
+}
+
+/// The date at midnight minus the interval, as Postgres' `date - interval` does
+impl Sub<Interval> for Date {
+    type Output = Timestamp;
+
+    fn sub(self, rhs: Interval) -> Timestamp {
+        unsafe {
+            direct_function_call(
+                pg_sys::date_mi_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("date - interval returned NULL")
+        }
+    }
 }
 
 impl Deref for Date {
@@ -66,6 +155,11 @@ impl serde::Serialize for Date {
     where
         S: serde::Serializer,
     {
+        if self.is_infinity() {
+            return serializer.serialize_str("infinity");
+        } else if self.is_neg_infinity() {
+            return serializer.serialize_str("-infinity");
+        }
         serializer.serialize_str(
             &self.format(&DATE_FORMAT).map_err(|e| {
                 serde::ser::Error::custom(format!("Date formatting problem: {:?}", e))
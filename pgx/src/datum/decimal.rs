@@ -0,0 +1,53 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Conversions between `numeric` and `rust_decimal`'s `Decimal`, with the `rust_decimal` feature
+use crate::{pg_sys, FromDatum, IntoDatum, Numeric, NumericConversionError};
+use rust_decimal::Decimal;
+use std::convert::TryFrom;
+
+impl From<Decimal> for Numeric {
+    fn from(decimal: Decimal) -> Self {
+        Numeric(decimal.to_string())
+    }
+}
+
+/// Fails rather than rounds if the value has more digits than a `Decimal` can hold
+impl TryFrom<Numeric> for Decimal {
+    type Error = NumericConversionError;
+
+    fn try_from(numeric: Numeric) -> Result<Self, Self::Error> {
+        let numeric = numeric.normalize();
+        if numeric.0 == "NaN" {
+            return Err(NumericConversionError::NaN);
+        }
+        Decimal::from_str_exact(&numeric.0).map_err(|_| NumericConversionError::OutOfRange)
+    }
+}
+
+impl FromDatum for Decimal {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Decimal> {
+        Numeric::from_datum(datum, is_null, typoid).map(|numeric| {
+            Decimal::try_from(numeric)
+                .unwrap_or_else(|e| panic!("numeric can't be converted to a Decimal: {}", e))
+        })
+    }
+}
+
+impl IntoDatum for Decimal {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Numeric::from(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::NUMERICOID
+    }
+}
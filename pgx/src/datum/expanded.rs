@@ -0,0 +1,280 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Keeping `#[derive(PostgresType)]` values deserialized between function calls, as Postgres'
+//! expanded datums
+use super::serialization::{deserialize_postgres_type, serialize_postgres_type};
+use crate::{
+    pg_sys, rust_regtypein, varatt_is_external_expanded, varatt_is_external_expanded_rw, varsize,
+    FromDatum, IntoDatum, PgMemoryContexts, PostgresType,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ops::{Deref, DerefMut};
+use std::panic::AssertUnwindSafe;
+
+/// The expanded object of a `PgExpanded<T>`, in its own memory context, which Postgres deletes
+/// along with the object
+#[repr(C)]
+struct ExpandedObject<T> {
+    /// Postgres' header, which has to come first
+    header: pg_sys::ExpandedObjectHeader,
+    methods: pg_sys::ExpandedObjectMethods,
+    /// Dropped when the object's memory context is deleted
+    value: *mut T,
+    /// The flat form from `get_flat_size()`, until the value is changed
+    flat: *mut pg_sys::varlena,
+}
+
+/// A `#[derive(PostgresType)]` value kept as a Postgres expanded datum, which is passed between
+/// functions in a query as a pointer to the deserialized Rust value, rather than serialized after
+/// one function returns it only to be deserialized by the next.
+///
+/// This is how core's arrays avoid copying large values over and over, and is worth it for large
+/// types, such as matrices, which functions are called on many times in a row.  Postgres flattens
+/// the value into the type's usual format, from its `#[pgx(serialize = ..)]`, when it's stored,
+/// or passed to a function taking the type itself, so the two can be mixed freely:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PostgresType)]
+/// pub struct Samples(Vec<f64>);
+///
+/// #[pg_extern]
+/// fn samples_push(mut samples: PgExpanded<Samples>, value: f64) -> PgExpanded<Samples> {
+///     samples.0.push(value);
+///     samples
+/// }
+///
+/// #[pg_extern]
+/// fn samples_len(samples: PgExpanded<Samples>) -> i64 {
+///     samples.0.len() as i64
+/// }
+/// ```
+///
+/// An expanded value returned by one function may be changed in place by the next, but values
+/// passed read-only, such as PL/pgSQL variables, are copied the first time they're changed.
+pub struct PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    object: *mut ExpandedObject<T>,
+    read_only: bool,
+}
+
+impl<T> PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    /// Expand `value` into a new object, in a memory context under the current one
+    pub fn new(value: T) -> Self {
+        unsafe {
+            let context = pg_sys::AllocSetContextCreateExtended(
+                pg_sys::CurrentMemoryContext,
+                "expanded pgx value\0".as_ptr() as *const std::os::raw::c_char,
+                pg_sys::ALLOCSET_SMALL_MINSIZE as usize,
+                pg_sys::ALLOCSET_SMALL_INITSIZE as usize,
+                pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+            );
+            let object = PgMemoryContexts::For(context).palloc0_struct::<ExpandedObject<T>>();
+            (*object).methods.get_flat_size = Some(get_flat_size::<T>);
+            (*object).methods.flatten_into = Some(flatten_into::<T>);
+            pg_sys::EOH_init_header(&mut (*object).header, &(*object).methods, context);
+            (*object).value = PgMemoryContexts::For(context).leak_and_drop_on_delete(value);
+
+            PgExpanded {
+                object,
+                read_only: false,
+            }
+        }
+    }
+
+    /// Is this a read-only reference to another function's value, which will be copied when it's
+    /// changed?
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The object's own memory context, which Postgres deletes along with it, for any Postgres
+    /// memory the value points to, so that it's freed with the value rather than leaked into the
+    /// caller's context.
+    ///
+    /// A read-only value's context belongs to the function that passed it, so this is `None` for
+    /// one until it's been copied by changing it.
+    pub fn memory_context(&self) -> Option<PgMemoryContexts> {
+        if self.read_only {
+            None
+        } else {
+            Some(PgMemoryContexts::For(unsafe {
+                (*self.object).header.eoh_context
+            }))
+        }
+    }
+
+    /// Is the object behind the expanded `datum` one of ours, for `T`?
+    unsafe fn is_ours(datum: pg_sys::Datum) -> bool {
+        let header = pg_sys::DatumGetEOHP(datum);
+        let methods = (*header).eoh_methods;
+        !methods.is_null()
+            && (*methods).get_flat_size == Some(get_flat_size::<T> as _)
+            && (*methods).flatten_into == Some(flatten_into::<T> as _)
+    }
+}
+
+/// The flat form of the object, which is computed once until the value changes
+unsafe fn flat_of<T>(object: *mut ExpandedObject<T>) -> *mut pg_sys::varlena
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    if (*object).flat.is_null() {
+        let previous = PgMemoryContexts::For((*object).header.eoh_context).set_as_current();
+        (*object).flat = serialize_postgres_type(&*(*object).value) as *mut pg_sys::varlena;
+        previous.set_as_current();
+    }
+    (*object).flat
+}
+
+unsafe extern "C" fn get_flat_size<T>(header: *mut pg_sys::ExpandedObjectHeader) -> pg_sys::Size
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    pg_sys::guard(AssertUnwindSafe(|| {
+        varsize(flat_of(header as *mut ExpandedObject<T>))
+    }))
+}
+
+unsafe extern "C" fn flatten_into<T>(
+    header: *mut pg_sys::ExpandedObjectHeader,
+    result: *mut std::os::raw::c_void,
+    allocated_size: pg_sys::Size,
+) where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    pg_sys::guard(AssertUnwindSafe(|| {
+        let flat = flat_of(header as *mut ExpandedObject<T>);
+        assert_eq!(
+            allocated_size,
+            varsize(flat),
+            "wrong size for a flattened value"
+        );
+        std::ptr::copy_nonoverlapping(flat as *const u8, result as *mut u8, allocated_size);
+    }))
+}
+
+impl<T> Deref for PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*self.object).value }
+    }
+}
+
+/// Copies a read-only value, and forgets the flat form of the value, which is about to change
+impl<T> DerefMut for PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            if self.read_only {
+                let value = deserialize_postgres_type(flat_of(self.object));
+                *self = PgExpanded::new(value);
+            }
+
+            let object = self.object;
+            if !(*object).flat.is_null() {
+                pg_sys::pfree((*object).flat as *mut std::os::raw::c_void);
+                (*object).flat = std::ptr::null_mut();
+            }
+            &mut *(*object).value
+        }
+    }
+}
+
+impl<T> FromDatum for PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if varatt_is_external_expanded(datum as *const pg_sys::varlena)
+            && Self::is_ours(datum)
+        {
+            Some(PgExpanded {
+                object: pg_sys::DatumGetEOHP(datum) as *mut ExpandedObject<T>,
+                read_only: !varatt_is_external_expanded_rw(datum as *const pg_sys::varlena),
+            })
+        } else {
+            // flattens anything that isn't, including other expanded objects
+            Some(PgExpanded::new(deserialize_postgres_type(
+                datum as *mut pg_sys::varlena,
+            )))
+        }
+    }
+
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if varatt_is_external_expanded_rw(datum as *const pg_sys::varlena)
+            && Self::is_ours(datum)
+        {
+            // we own it, so it can be moved
+            let datum = pg_sys::TransferExpandedObject(datum, memory_context.value());
+            Self::from_datum(datum, false, typoid)
+        } else {
+            memory_context.switch_to(|_| {
+                let value = deserialize_postgres_type(datum as *mut pg_sys::varlena);
+                Some(PgExpanded::new(value))
+            })
+        }
+    }
+}
+
+impl<T> IntoDatum for PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let header = unsafe { &(*self.object).header };
+        let pointer = if self.read_only {
+            &header.eoh_ro_ptr
+        } else {
+            &header.eoh_rw_ptr
+        };
+        Some(pointer.as_ptr() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        rust_regtypein::<T>()
+    }
+}
+
+impl<T> From<T> for PgExpanded<T>
+where
+    T: PostgresType + Serialize + DeserializeOwned + 'static,
+{
+    fn from(value: T) -> Self {
+        PgExpanded::new(value)
+    }
+}
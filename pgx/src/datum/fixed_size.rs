@@ -0,0 +1,83 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The datums of `#[derive(PostgresType)]` types with `#[pgx(fixed_size)]` or
+//! `#[pgx(passedbyvalue)]`, which are the bytes of the Rust value rather than a serialized varlena
+use crate::pg_sys;
+use std::mem::{size_of, transmute_copy};
+
+/// Copy `value` into a new `Datum`, pointing to its bytes in the current memory context, as
+/// Postgres passes fixed-size types that aren't `PASSEDBYVALUE`
+///
+/// ## Safety
+///
+/// `T` must be `#[repr(C)]` or `#[repr(transparent)]`, so its layout doesn't change between builds,
+/// and mustn't have any padding, whose bytes Postgres would store and compare
+pub unsafe fn fixed_size_into_datum<T: Copy>(value: T) -> pg_sys::Datum {
+    let ptr = pg_sys::palloc0(size_of::<T>()) as *mut T;
+    ptr.write_unaligned(value);
+    ptr as pg_sys::Datum
+}
+
+/// The value a `Datum` from [`fixed_size_into_datum`] points to
+///
+/// ## Safety
+///
+/// `datum` must point to the bytes of a `T`
+pub unsafe fn fixed_size_from_datum<T: Copy>(datum: pg_sys::Datum) -> T {
+    (datum as *const T).read_unaligned()
+}
+
+/// `value` as a `Datum`, as Postgres passes `PASSEDBYVALUE` types, which it stores as an integer
+/// of the same size.
+///
+/// ## Safety
+///
+/// `T` must be `#[repr(C)]` or `#[repr(transparent)]`, so its layout doesn't change between builds,
+/// and mustn't have any padding, whose bytes Postgres would store and compare
+///
+/// ## Panics
+///
+/// If `T` isn't 1, 2, 4 or 8 bytes, which are the only sizes Postgres passes by value
+pub unsafe fn by_value_into_datum<T: Copy>(value: T) -> pg_sys::Datum {
+    match size_of::<T>() {
+        1 => transmute_copy::<T, u8>(&value) as pg_sys::Datum,
+        2 => transmute_copy::<T, u16>(&value) as pg_sys::Datum,
+        4 => transmute_copy::<T, u32>(&value) as pg_sys::Datum,
+        8 => transmute_copy::<T, u64>(&value) as pg_sys::Datum,
+        size => panic!(
+            "{} is {} bytes, but values passed by value must be 1, 2, 4 or 8",
+            std::any::type_name::<T>(),
+            size
+        ),
+    }
+}
+
+/// The value a `Datum` from [`by_value_into_datum`] holds
+///
+/// ## Safety
+///
+/// `datum` must hold the bytes of a `T`
+///
+/// ## Panics
+///
+/// If `T` isn't 1, 2, 4 or 8 bytes, which are the only sizes Postgres passes by value
+pub unsafe fn by_value_from_datum<T: Copy>(datum: pg_sys::Datum) -> T {
+    match size_of::<T>() {
+        1 => transmute_copy::<u8, T>(&(datum as u8)),
+        2 => transmute_copy::<u16, T>(&(datum as u16)),
+        4 => transmute_copy::<u32, T>(&(datum as u32)),
+        8 => transmute_copy::<u64, T>(&(datum as u64)),
+        size => panic!(
+            "{} is {} bytes, but values passed by value must be 1, 2, 4 or 8",
+            std::any::type_name::<T>(),
+            size
+        ),
+    }
+}
@@ -23,6 +23,9 @@ use std::ffi::CStr;
 /// conversion.
 pub trait FromDatum {
     const NEEDS_TYPID: bool = true;
+    /// Whether this is read from a Postgres array, so a `Vec` of it is read from each row of a
+    /// multidimensional array
+    const IS_ARRAY: bool = false;
     /// ## Safety
     ///
     /// This method is inherently unsafe as the `datum` argument can represent an arbitrary
@@ -62,6 +65,64 @@ pub trait FromDatum {
     {
         memory_context.switch_to(|_| FromDatum::from_datum(datum, is_null, typoid))
     }
+
+    /// Convert a whole column of `datums`, whose NULLs are flagged by the matching element of
+    /// `nulls`, all of type `typoid`.
+    ///
+    /// This is for code that converts many values of one type at once, such as a foreign data
+    /// wrapper filling a batch of rows.  Primitive types override it to convert without any
+    /// per-value overhead.
+    ///
+    /// ## Safety
+    ///
+    /// Same caveats as `From::from_datum(...)`, for every datum.  Panics if `datums` and `nulls`
+    /// aren't the same length.
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        typoid: pg_sys::Oid,
+    ) -> Vec<Option<Self>>
+    where
+        Self: Sized,
+    {
+        check_slice_lengths(datums, nulls);
+        datums
+            .iter()
+            .zip(nulls)
+            .map(|(datum, is_null)| Self::from_datum(*datum, *is_null, typoid))
+            .collect()
+    }
+}
+
+fn check_slice_lengths(datums: &[pg_sys::Datum], nulls: &[bool]) {
+    if datums.len() != nulls.len() {
+        panic!(
+            "{} datums were given with {} null flags",
+            datums.len(),
+            nulls.len()
+        );
+    }
+}
+
+/// `from_datum_slice()` for the pass-by-value types, which can't be NULL when they're zero
+#[inline]
+fn from_by_value_datums<T>(
+    datums: &[pg_sys::Datum],
+    nulls: &[bool],
+    convert: impl Fn(pg_sys::Datum) -> T,
+) -> Vec<Option<T>> {
+    check_slice_lengths(datums, nulls);
+    datums
+        .iter()
+        .zip(nulls)
+        .map(|(datum, is_null)| {
+            if *is_null {
+                None
+            } else {
+                Some(convert(*datum))
+            }
+        })
+        .collect()
 }
 
 /// for pg_sys::Datum
@@ -92,6 +153,15 @@ impl FromDatum for bool {
             Some(datum != 0)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<bool>> {
+        from_by_value_datums(datums, nulls, |datum| datum != 0)
+    }
 }
 
 /// for `"char"`
@@ -105,6 +175,15 @@ impl FromDatum for i8 {
             Some(datum as i8)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<i8>> {
+        from_by_value_datums(datums, nulls, |datum| datum as i8)
+    }
 }
 
 /// for smallint
@@ -118,6 +197,15 @@ impl FromDatum for i16 {
             Some(datum as i16)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<i16>> {
+        from_by_value_datums(datums, nulls, |datum| datum as i16)
+    }
 }
 
 /// for integer
@@ -131,6 +219,15 @@ impl FromDatum for i32 {
             Some(datum as i32)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<i32>> {
+        from_by_value_datums(datums, nulls, |datum| datum as i32)
+    }
 }
 
 /// for oid
@@ -144,6 +241,15 @@ impl FromDatum for u32 {
             Some(datum as u32)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<u32>> {
+        from_by_value_datums(datums, nulls, |datum| datum as u32)
+    }
 }
 
 /// for bigint
@@ -157,6 +263,15 @@ impl FromDatum for i64 {
             Some(datum as i64)
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<i64>> {
+        from_by_value_datums(datums, nulls, |datum| datum as i64)
+    }
 }
 
 /// for real
@@ -170,6 +285,15 @@ impl FromDatum for f32 {
             Some(f32::from_bits(datum as u32))
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<f32>> {
+        from_by_value_datums(datums, nulls, |datum| f32::from_bits(datum as u32))
+    }
 }
 
 /// for double precision
@@ -183,6 +307,15 @@ impl FromDatum for f64 {
             Some(f64::from_bits(datum as u64))
         }
     }
+
+    #[inline]
+    unsafe fn from_datum_slice(
+        datums: &[pg_sys::Datum],
+        nulls: &[bool],
+        _: pg_sys::Oid,
+    ) -> Vec<Option<f64>> {
+        from_by_value_datums(datums, nulls, |datum| f64::from_bits(datum as u64))
+    }
 }
 
 /// for text, varchar
@@ -7,7 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum};
+use crate::{pg_sys, set_varsize, FromDatum, IntoDatum, PgBox};
 
 impl FromDatum for pg_sys::BOX {
     const NEEDS_TYPID: bool = false;
@@ -27,14 +27,10 @@ impl FromDatum for pg_sys::BOX {
 }
 
 impl IntoDatum for pg_sys::BOX {
-    fn into_datum(mut self) -> Option<pg_sys::Datum> {
-        let the_box = &mut self;
-        unsafe {
-            direct_function_call_as_datum(
-                pg_sys::box_out,
-                vec![Some(the_box as *mut pg_sys::BOX as pg_sys::Datum)],
-            )
-        }
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut the_box = PgBox::<pg_sys::BOX>::alloc();
+        *the_box = self;
+        Some(the_box.into_pg() as pg_sys::Datum)
     }
 
     fn type_oid() -> pg_sys::Oid {
@@ -59,17 +55,204 @@ impl FromDatum for pg_sys::Point {
 }
 
 impl IntoDatum for pg_sys::Point {
-    fn into_datum(mut self) -> Option<usize> {
-        let point = &mut self;
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut point = PgBox::<pg_sys::Point>::alloc();
+        *point = self;
+        Some(point.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::POINTOID
+    }
+}
+
+impl FromDatum for pg_sys::LSEG {
+    const NEEDS_TYPID: bool = false;
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("LSEG datum declared not null, but datum is zero")
+        } else {
+            let lseg = datum as *mut pg_sys::LSEG;
+            Some(lseg.read())
+        }
+    }
+}
+
+impl IntoDatum for pg_sys::LSEG {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut lseg = PgBox::<pg_sys::LSEG>::alloc();
+        *lseg = self;
+        Some(lseg.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::LSEGOID
+    }
+}
+
+impl FromDatum for pg_sys::CIRCLE {
+    const NEEDS_TYPID: bool = false;
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("CIRCLE datum declared not null, but datum is zero")
+        } else {
+            let circle = datum as *mut pg_sys::CIRCLE;
+            Some(circle.read())
+        }
+    }
+}
+
+impl IntoDatum for pg_sys::CIRCLE {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut circle = PgBox::<pg_sys::CIRCLE>::alloc();
+        *circle = self;
+        Some(circle.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::CIRCLEOID
+    }
+}
+
+/// A Postgres `path`: a series of points, joined by line segments, which is a closed shape if the
+/// last point joins back to the first
+#[derive(Debug, Default, Clone)]
+pub struct Path {
+    pub points: Vec<pg_sys::Point>,
+    pub closed: bool,
+}
+
+impl Path {
+    pub fn open(points: Vec<pg_sys::Point>) -> Self {
+        Path {
+            points,
+            closed: false,
+        }
+    }
+
+    pub fn closed(points: Vec<pg_sys::Point>) -> Self {
+        Path {
+            points,
+            closed: true,
+        }
+    }
+}
+
+impl FromDatum for Path {
+    const NEEDS_TYPID: bool = false;
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("PATH datum declared not null, but datum is zero")
+        } else {
+            let path = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::PATH;
+            Some(Path {
+                points: (*path).p.as_slice((*path).npts as usize).to_vec(),
+                closed: (*path).closed != 0,
+            })
+        }
+    }
+}
+
+impl IntoDatum for Path {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
         unsafe {
-            direct_function_call_as_datum(
-                pg_sys::point_out,
-                vec![Some(point as *mut pg_sys::Point as pg_sys::Datum)],
-            )
+            let size = std::mem::size_of::<pg_sys::PATH>()
+                + self.points.len() * std::mem::size_of::<pg_sys::Point>();
+            let path = pg_sys::palloc0(size) as *mut pg_sys::PATH;
+            set_varsize(path as *mut pg_sys::varlena, size as i32);
+            (*path).npts = self.points.len() as i32;
+            (*path).closed = self.closed as i32;
+            std::ptr::copy_nonoverlapping(
+                self.points.as_ptr(),
+                (*path).p.as_mut_ptr(),
+                self.points.len(),
+            );
+            Some(path as pg_sys::Datum)
         }
     }
 
     fn type_oid() -> pg_sys::Oid {
-        pg_sys::POINTOID
+        pg_sys::PATHOID
+    }
+}
+
+/// A Postgres `polygon`: the closed shape made by joining each of its points to the next, and
+/// the last back to the first
+#[derive(Debug, Default, Clone)]
+pub struct Polygon {
+    pub points: Vec<pg_sys::Point>,
+}
+
+impl Polygon {
+    pub fn new(points: Vec<pg_sys::Point>) -> Self {
+        Polygon { points }
+    }
+
+    /// The smallest box containing every point, which is what Postgres stores alongside them
+    pub fn bounding_box(&self) -> pg_sys::BOX {
+        let mut points = self.points.iter();
+        let first = points.next().copied().unwrap_or_default();
+        points.fold(
+            pg_sys::BOX {
+                high: first,
+                low: first,
+            },
+            |bounds, point| pg_sys::BOX {
+                high: pg_sys::Point {
+                    x: bounds.high.x.max(point.x),
+                    y: bounds.high.y.max(point.y),
+                },
+                low: pg_sys::Point {
+                    x: bounds.low.x.min(point.x),
+                    y: bounds.low.y.min(point.y),
+                },
+            },
+        )
+    }
+}
+
+impl FromDatum for Polygon {
+    const NEEDS_TYPID: bool = false;
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("POLYGON datum declared not null, but datum is zero")
+        } else {
+            let polygon =
+                pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::POLYGON;
+            Some(Polygon {
+                points: (*polygon).p.as_slice((*polygon).npts as usize).to_vec(),
+            })
+        }
+    }
+}
+
+impl IntoDatum for Polygon {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        unsafe {
+            let size = std::mem::size_of::<pg_sys::POLYGON>()
+                + self.points.len() * std::mem::size_of::<pg_sys::Point>();
+            let polygon = pg_sys::palloc0(size) as *mut pg_sys::POLYGON;
+            set_varsize(polygon as *mut pg_sys::varlena, size as i32);
+            (*polygon).npts = self.points.len() as i32;
+            (*polygon).boundbox = self.bounding_box();
+            std::ptr::copy_nonoverlapping(
+                self.points.as_ptr(),
+                (*polygon).p.as_mut_ptr(),
+                self.points.len(),
+            );
+            Some(polygon as pg_sys::Datum)
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::POLYGONOID
     }
 }
@@ -119,3 +119,80 @@ impl Into<Inet> for String {
         Inet(self)
     }
 }
+
+/// A Postgres `cidr`: an IPv4 or IPv6 network, which unlike an [`Inet`] can't have any bits set
+/// to the right of its netmask
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Cidr(pub String);
+
+impl Deref for Cidr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = String::deserialize(deserializer)?;
+        // it's valid if Postgres can convert it into a 'cidr' Datum without raising an ERROR
+        unsafe {
+            pg_try(|| {
+                let datum = Cidr(v.clone()).into_datum().unwrap();
+                pg_sys::pfree(datum as void_mut_ptr);
+                Ok(Cidr(v.clone()))
+            })
+            .unwrap_or_else(|| Err(Error::custom(format!("invalid cidr value: {}", v))))
+        }
+    }
+}
+
+impl FromDatum for Cidr {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _typoid: u32) -> Option<Cidr> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("cidr datum is declared non-null but Datum is zero");
+        } else {
+            let cstr = direct_function_call::<&CStr>(pg_sys::cidr_out, vec![Some(datum)]);
+            Some(Cidr(
+                cstr.unwrap()
+                    .to_str()
+                    .expect("unable to convert &cstr cidr into &str")
+                    .to_owned(),
+            ))
+        }
+    }
+}
+
+impl IntoDatum for Cidr {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let cstr = std::ffi::CString::new(self.0).expect("failed to convert cidr into CString");
+        unsafe {
+            direct_function_call_as_datum(pg_sys::cidr_in, vec![cstr.as_c_str().into_datum()])
+        }
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::CIDROID
+    }
+}
+
+impl Into<Cidr> for String {
+    fn into(self) -> Cidr {
+        Cidr(self)
+    }
+}
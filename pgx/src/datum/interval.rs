@@ -0,0 +1,214 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::datum::time::{
+    DateTimeConversionError, USECS_PER_HOUR, USECS_PER_MINUTE, USECS_PER_SEC,
+};
+use crate::{pg_sys, FromDatum, IntoDatum, PgBox};
+use std::convert::TryFrom;
+use std::ops::{Add, Neg, Sub};
+
+/// A Postgres `interval`, made of months, days and microseconds.
+///
+/// They're kept apart because neither a month nor a day has a fixed length: adding `1 month` to a
+/// timestamp moves it to the same day of the next month, and adding `1 day` to a timestamp with
+/// time zone keeps its local time across a daylight saving change.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Interval {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Interval {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    pub fn from_months(months: i32) -> Self {
+        Interval::new(months, 0, 0)
+    }
+
+    pub fn from_days(days: i32) -> Self {
+        Interval::new(0, days, 0)
+    }
+
+    pub fn from_micros(micros: i64) -> Self {
+        Interval::new(0, 0, micros)
+    }
+
+    pub fn months(&self) -> i32 {
+        self.months
+    }
+
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    pub fn micros(&self) -> i64 {
+        self.micros
+    }
+}
+
+impl FromDatum for Interval {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _typoid: u32) -> Option<Interval> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("Interval datum declared not null, but datum is zero")
+        } else {
+            let interval = (datum as *const pg_sys::Interval).read();
+            Some(Interval::new(interval.month, interval.day, interval.time))
+        }
+    }
+}
+
+impl IntoDatum for Interval {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut interval = PgBox::<pg_sys::Interval>::alloc();
+        interval.month = self.months;
+        interval.day = self.days;
+        interval.time = self.micros;
+        Some(interval.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::INTERVALOID
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Interval) -> Interval {
+        Interval::new(
+            self.months
+                .checked_add(rhs.months)
+                .expect("interval out of range"),
+            self.days
+                .checked_add(rhs.days)
+                .expect("interval out of range"),
+            self.micros
+                .checked_add(rhs.micros)
+                .expect("interval out of range"),
+        )
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Interval) -> Interval {
+        self + -rhs
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval::new(
+            self.months.checked_neg().expect("interval out of range"),
+            self.days.checked_neg().expect("interval out of range"),
+            self.micros.checked_neg().expect("interval out of range"),
+        )
+    }
+}
+
+/// Counts a day as 24 hours, as Postgres does when it compares intervals, but can't convert months
+impl TryFrom<Interval> for time::Duration {
+    type Error = DateTimeConversionError;
+
+    fn try_from(interval: Interval) -> Result<Self, Self::Error> {
+        if interval.months != 0 {
+            return Err(DateTimeConversionError::IntervalHasMonths);
+        }
+        Ok(time::Duration::days(interval.days as i64)
+            + time::Duration::microseconds(interval.micros))
+    }
+}
+
+impl TryFrom<time::Duration> for Interval {
+    type Error = DateTimeConversionError;
+
+    fn try_from(duration: time::Duration) -> Result<Self, Self::Error> {
+        let micros = i64::try_from(duration.whole_microseconds())
+            .map_err(|_| DateTimeConversionError::OutOfRange)?;
+        Ok(Interval::from_micros(micros))
+    }
+}
+
+/// Formats the interval as ISO 8601, like Postgres' `IntervalStyle` of `iso_8601` does, such as
+/// `P1Y2M3DT4H5M6.5S`
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.months == 0 && self.days == 0 && self.micros == 0 {
+            return write!(f, "PT0S");
+        }
+
+        let (years, months) = (self.months / 12, self.months % 12);
+        let hours = self.micros / USECS_PER_HOUR;
+        let minutes = self.micros % USECS_PER_HOUR / USECS_PER_MINUTE;
+        let seconds = self.micros % USECS_PER_MINUTE / USECS_PER_SEC;
+        let fraction = self.micros % USECS_PER_SEC;
+
+        write!(f, "P")?;
+        if years != 0 {
+            write!(f, "{}Y", years)?;
+        }
+        if months != 0 {
+            write!(f, "{}M", months)?;
+        }
+        if self.days != 0 {
+            write!(f, "{}D", self.days)?;
+        }
+        if self.micros != 0 {
+            write!(f, "T")?;
+            if hours != 0 {
+                write!(f, "{}H", hours)?;
+            }
+            if minutes != 0 {
+                write!(f, "{}M", minutes)?;
+            }
+            if fraction != 0 {
+                let sign = if self.micros < 0 { "-" } else { "" };
+                let fraction = format!("{:06}", fraction.abs());
+                write!(
+                    f,
+                    "{}{}.{}S",
+                    sign,
+                    seconds.abs(),
+                    fraction.trim_end_matches('0')
+                )?;
+            } else if seconds != 0 {
+                write!(f, "{}S", seconds)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for Interval {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
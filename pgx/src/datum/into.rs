@@ -32,6 +32,43 @@ pub trait IntoDatum {
     fn array_type_oid() -> pg_sys::Oid {
         unsafe { pg_sys::get_array_type(Self::type_oid()) }
     }
+
+    /// Convert a whole column of `values` into their datums and NULL flags, which is the reverse
+    /// of [`FromDatum::from_datum_slice`](crate::FromDatum::from_datum_slice).
+    ///
+    /// Primitive types override it to convert without any per-value overhead.
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>)
+    where
+        Self: Sized,
+    {
+        let values = values.into_iter();
+        let mut datums = Vec::with_capacity(values.size_hint().0);
+        let mut nulls = Vec::with_capacity(values.size_hint().0);
+        for value in values {
+            match value.into_datum() {
+                Some(datum) => {
+                    datums.push(datum);
+                    nulls.push(false);
+                }
+                None => {
+                    datums.push(0);
+                    nulls.push(true);
+                }
+            }
+        }
+        (datums, nulls)
+    }
+}
+
+/// `into_datums()` for the pass-by-value types, which are never NULL
+#[inline]
+fn into_by_value_datums<T>(
+    values: impl IntoIterator<Item = T>,
+    convert: impl Fn(T) -> pg_sys::Datum,
+) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+    let datums = values.into_iter().map(convert).collect::<Vec<_>>();
+    let nulls = vec![false; datums.len()];
+    (datums, nulls)
 }
 
 /// for supporting NULL as the None value of an Option<T>
@@ -61,6 +98,11 @@ impl IntoDatum for bool {
     fn type_oid() -> u32 {
         pg_sys::BOOLOID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for "char"
@@ -72,6 +114,11 @@ impl IntoDatum for i8 {
     fn type_oid() -> u32 {
         pg_sys::CHAROID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for smallint
@@ -84,6 +131,11 @@ impl IntoDatum for i16 {
     fn type_oid() -> u32 {
         pg_sys::INT2OID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for integer
@@ -96,6 +148,11 @@ impl IntoDatum for i32 {
     fn type_oid() -> u32 {
         pg_sys::INT4OID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for oid
@@ -108,6 +165,11 @@ impl IntoDatum for u32 {
     fn type_oid() -> u32 {
         pg_sys::OIDOID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for bigint
@@ -120,6 +182,11 @@ impl IntoDatum for i64 {
     fn type_oid() -> u32 {
         pg_sys::INT8OID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value as pg_sys::Datum)
+    }
 }
 
 /// for real
@@ -132,6 +199,11 @@ impl IntoDatum for f32 {
     fn type_oid() -> u32 {
         pg_sys::FLOAT4OID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value.to_bits() as pg_sys::Datum)
+    }
 }
 
 /// for double precision
@@ -144,6 +216,11 @@ impl IntoDatum for f64 {
     fn type_oid() -> u32 {
         pg_sys::FLOAT8OID
     }
+
+    #[inline]
+    fn into_datums<I: IntoIterator<Item = Self>>(values: I) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        into_by_value_datums(values, |value| value.to_bits() as pg_sys::Datum)
+    }
 }
 
 impl IntoDatum for PgOid {
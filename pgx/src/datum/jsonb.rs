@@ -0,0 +1,641 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reading and building `jsonb` values in Postgres' own format, without going through serde
+use crate::{pg_sys, varsize, FromDatum, IntoDatum, Numeric, PgMemoryContexts};
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+
+/// A `jsonb` object, array or scalar, read in place.
+///
+/// Unlike [`JsonB`](crate::JsonB), which parses the whole document into a `serde_json::Value`,
+/// this reads only what's asked for, straight from Postgres' binary format: looking up a key is
+/// a binary search, and strings are borrowed rather than copied.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn owner_name(pet: JsonbRef) -> Option<String> {
+///     pet.get_path(&["owner", "name"])?
+///         .as_str()
+///         .map(str::to_string)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct JsonbRef<'a> {
+    container: *mut pg_sys::JsonbContainer,
+    /// The size of the container, in bytes
+    len: usize,
+    /// The whole `jsonb` this is the root of, if it is
+    jsonb: Option<*mut pg_sys::Jsonb>,
+    _marker: PhantomData<&'a pg_sys::JsonbContainer>,
+}
+
+/// A value inside a [`JsonbRef`]
+#[derive(Debug, Clone)]
+pub enum JsonbValueRef<'a> {
+    Null,
+    Bool(bool),
+    Number(Numeric),
+    String(&'a str),
+    /// An object or an array
+    Container(JsonbRef<'a>),
+}
+
+impl<'a> JsonbValueRef<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonbValueRef::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonbValueRef::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Numeric> {
+        match self {
+            JsonbValueRef::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            JsonbValueRef::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_container(&self) -> Option<JsonbRef<'a>> {
+        match self {
+            JsonbValueRef::Container(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Convert a value read from a container, whose contents point into it
+    unsafe fn from_pg(value: &pg_sys::JsonbValue) -> JsonbValueRef<'a> {
+        match value.type_ {
+            pg_sys::jbvType_jbvNull => JsonbValueRef::Null,
+            pg_sys::jbvType_jbvBool => JsonbValueRef::Bool(value.val.boolean),
+            pg_sys::jbvType_jbvNumeric => JsonbValueRef::Number(
+                Numeric::from_datum(
+                    value.val.numeric as pg_sys::Datum,
+                    false,
+                    pg_sys::NUMERICOID,
+                )
+                .expect("jsonb number was null"),
+            ),
+            pg_sys::jbvType_jbvString => {
+                let string = value.val.string;
+                JsonbValueRef::String(std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+                    string.val as *const u8,
+                    string.len as usize,
+                )))
+            }
+            pg_sys::jbvType_jbvBinary => JsonbValueRef::Container(JsonbRef {
+                container: value.val.binary.data,
+                len: value.val.binary.len as usize,
+                jsonb: None,
+                _marker: PhantomData,
+            }),
+            other => panic!("unexpected jsonb value type {}", other),
+        }
+    }
+}
+
+impl<'a> JsonbRef<'a> {
+    /// ## Safety
+    ///
+    /// `jsonb` must be a detoasted `jsonb` that lives for `'a`
+    unsafe fn from_jsonb(jsonb: *mut pg_sys::Jsonb) -> Self {
+        JsonbRef {
+            container: &mut (*jsonb).root,
+            len: varsize(jsonb as *const pg_sys::varlena) - pg_sys::VARHDRSZ,
+            jsonb: Some(jsonb),
+            _marker: PhantomData,
+        }
+    }
+
+    fn header(&self) -> u32 {
+        unsafe { (*self.container).header }
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.header() & pg_sys::JB_FOBJECT != 0
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.header() & pg_sys::JB_FARRAY != 0 && !self.is_scalar()
+    }
+
+    /// Is this a single string, number, boolean or null, rather than an object or an array?
+    pub fn is_scalar(&self) -> bool {
+        self.header() & pg_sys::JB_FSCALAR != 0
+    }
+
+    /// How many keys an object has, or how many elements an array has
+    pub fn len(&self) -> usize {
+        if self.is_scalar() {
+            0
+        } else {
+            (self.header() & pg_sys::JB_CMASK) as usize
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This as a value, which is the scalar itself if [`is_scalar`](JsonbRef::is_scalar)
+    pub fn as_value(&self) -> JsonbValueRef<'a> {
+        if self.is_scalar() {
+            // a scalar is stored as an array of just itself
+            unsafe { self.take(pg_sys::getIthJsonbValueFromContainer(self.container, 0)) }
+                .expect("jsonb scalar has no value")
+        } else {
+            JsonbValueRef::Container(*self)
+        }
+    }
+
+    /// The value of `key`, if this is an object which has it
+    pub fn get(&self, key: &str) -> Option<JsonbValueRef<'a>> {
+        if !self.is_object() {
+            return None;
+        }
+        let mut key = string_value(key.as_ptr() as *mut c_char, key.len());
+        unsafe {
+            self.take(pg_sys::findJsonbValueFromContainer(
+                self.container,
+                pg_sys::JB_FOBJECT,
+                &mut key,
+            ))
+        }
+    }
+
+    /// The element at `index`, if this is an array which has it
+    pub fn get_index(&self, index: usize) -> Option<JsonbValueRef<'a>> {
+        if !self.is_array() || index >= self.len() {
+            return None;
+        }
+        unsafe {
+            self.take(pg_sys::getIthJsonbValueFromContainer(
+                self.container,
+                index as u32,
+            ))
+        }
+    }
+
+    /// Follow `path` down through objects, by key, and arrays, by index, like the `#>` operator
+    pub fn get_path(&self, path: &[&str]) -> Option<JsonbValueRef<'a>> {
+        let mut value = self.as_value();
+        for step in path {
+            let container = value.as_container()?;
+            value = if container.is_object() {
+                container.get(step)?
+            } else {
+                container.get_index(step.parse().ok()?)?
+            };
+        }
+        Some(value)
+    }
+
+    /// An object's keys, in the order they're stored, which is shortest first
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> {
+        self.tokens(self.is_object())
+            .filter_map(|(token, value)| match token {
+                pg_sys::JsonbIteratorToken_WJB_KEY => {
+                    unsafe { JsonbValueRef::from_pg(&value) }.as_str()
+                }
+                _ => None,
+            })
+    }
+
+    /// An object's keys and their values
+    pub fn entries(&self) -> impl Iterator<Item = (&'a str, JsonbValueRef<'a>)> {
+        let mut key = None;
+        self.tokens(self.is_object())
+            .filter_map(move |(token, value)| match token {
+                pg_sys::JsonbIteratorToken_WJB_KEY => {
+                    key = unsafe { JsonbValueRef::from_pg(&value) }.as_str();
+                    None
+                }
+                pg_sys::JsonbIteratorToken_WJB_VALUE => {
+                    Some((key.take().expect("jsonb value without a key"), unsafe {
+                        JsonbValueRef::from_pg(&value)
+                    }))
+                }
+                _ => None,
+            })
+    }
+
+    /// An array's elements
+    pub fn elements(&self) -> impl Iterator<Item = JsonbValueRef<'a>> {
+        self.tokens(self.is_array())
+            .filter_map(|(token, value)| match token {
+                pg_sys::JsonbIteratorToken_WJB_ELEM => {
+                    Some(unsafe { JsonbValueRef::from_pg(&value) })
+                }
+                _ => None,
+            })
+    }
+
+    /// Every token of this container, with nested containers as values rather than their tokens,
+    /// or none if it's not `enabled`
+    fn tokens(&self, enabled: bool) -> JsonbTokens<'a> {
+        JsonbTokens {
+            iterator: if enabled {
+                unsafe { pg_sys::JsonbIteratorInit(self.container) }
+            } else {
+                std::ptr::null_mut()
+            },
+            skip_nested: true,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Convert and free a value returned by one of Postgres' lookup functions
+    unsafe fn take(&self, value: *mut pg_sys::JsonbValue) -> Option<JsonbValueRef<'a>> {
+        if value.is_null() {
+            None
+        } else {
+            let converted = JsonbValueRef::from_pg(&*value);
+            pg_sys::pfree(value as *mut std::os::raw::c_void);
+            Some(converted)
+        }
+    }
+}
+
+/// Writes the same text as casting the `jsonb` to `text` does
+impl<'a> std::fmt::Display for JsonbRef<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        unsafe {
+            let cstr =
+                pg_sys::JsonbToCString(std::ptr::null_mut(), self.container, self.len as i32);
+            let result = f.write_str(
+                std::ffi::CStr::from_ptr(cstr)
+                    .to_str()
+                    .expect("jsonb text is not valid UTF8"),
+            );
+            pg_sys::pfree(cstr as *mut std::os::raw::c_void);
+            result
+        }
+    }
+}
+
+struct JsonbTokens<'a> {
+    iterator: *mut pg_sys::JsonbIterator,
+    skip_nested: bool,
+    _marker: PhantomData<&'a pg_sys::JsonbContainer>,
+}
+
+impl<'a> Iterator for JsonbTokens<'a> {
+    /// The values are only converted when they're needed, as converting numbers isn't free
+    type Item = (pg_sys::JsonbIteratorToken, pg_sys::JsonbValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.iterator.is_null() {
+            return None;
+        }
+        unsafe {
+            let mut value = std::mem::zeroed::<pg_sys::JsonbValue>();
+            // frees the iterator, and sets it to null, when it's done
+            let token = pg_sys::JsonbIteratorNext(&mut self.iterator, &mut value, self.skip_nested);
+            match token {
+                pg_sys::JsonbIteratorToken_WJB_DONE => None,
+                _ => Some((token, value)),
+            }
+        }
+    }
+}
+
+impl<'a> FromDatum for JsonbRef<'a> {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a jsonb Datum was flagged as non-null but the datum is zero")
+        } else {
+            // the container's offsets need it to be aligned, so it can't stay packed
+            let jsonb =
+                pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::Jsonb;
+            Some(JsonbRef::from_jsonb(jsonb))
+        }
+    }
+
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a jsonb Datum was flagged as non-null but the datum is zero")
+        } else {
+            memory_context.switch_to(|_| {
+                let jsonb = pg_sys::pg_detoast_datum_copy(datum as *mut pg_sys::varlena)
+                    as *mut pg_sys::Jsonb;
+                Some(JsonbRef::from_jsonb(jsonb))
+            })
+        }
+    }
+}
+
+/// A whole `jsonb` is returned as it is, and anything inside one is copied into a new `jsonb`
+impl<'a> IntoDatum for JsonbRef<'a> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        match self.jsonb {
+            Some(jsonb) => Some(jsonb as pg_sys::Datum),
+            None => unsafe {
+                let mut value = jsonb_value(pg_sys::jbvType_jbvBinary);
+                value.val.binary.data = self.container;
+                value.val.binary.len = self.len as i32;
+                Some(pg_sys::JsonbValueToJsonb(&mut value) as pg_sys::Datum)
+            },
+        }
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::JSONBOID
+    }
+}
+
+fn jsonb_value(type_: pg_sys::jbvType) -> pg_sys::JsonbValue {
+    let mut value = unsafe { std::mem::zeroed::<pg_sys::JsonbValue>() };
+    value.type_ = type_;
+    value
+}
+
+fn string_value(string: *mut c_char, len: usize) -> pg_sys::JsonbValue {
+    let mut value = jsonb_value(pg_sys::jbvType_jbvString);
+    value.val.string.val = string;
+    value.val.string.len = len as i32;
+    value
+}
+
+enum OpenContainer {
+    Array,
+    Object { has_key: bool },
+}
+
+/// Builds a `jsonb` value a piece at a time, straight into Postgres' format.
+///
+/// Values are added to the innermost open object or array, or make up the whole `jsonb` if
+/// there isn't one.  Objects take a [`key`](JsonbBuilder::key) before each value, and like
+/// Postgres, the last value given for a key wins.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn squares(n: i32) -> JsonbRef<'static> {
+///     let mut builder = JsonbBuilder::new();
+///     builder.begin_object();
+///     for i in 1..=n {
+///         builder.key(&i.to_string()).number((i * i).into());
+///     }
+///     builder.end_object();
+///     builder.finish()
+/// }
+/// ```
+///
+/// Using it out of order, such as giving an object a value without a key, panics.
+pub struct JsonbBuilder {
+    state: *mut pg_sys::JsonbParseState,
+    open: Vec<OpenContainer>,
+    result: *mut pg_sys::JsonbValue,
+}
+
+impl Default for JsonbBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonbBuilder {
+    pub fn new() -> Self {
+        JsonbBuilder {
+            state: std::ptr::null_mut(),
+            open: Vec::new(),
+            result: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn begin_object(&mut self) -> &mut Self {
+        self.begin(
+            pg_sys::JsonbIteratorToken_WJB_BEGIN_OBJECT,
+            OpenContainer::Object { has_key: false },
+        )
+    }
+
+    pub fn end_object(&mut self) -> &mut Self {
+        match self.open.pop() {
+            Some(OpenContainer::Object { has_key: false }) => {}
+            Some(OpenContainer::Object { has_key: true }) => {
+                panic!("the last key of the object has no value")
+            }
+            _ => panic!("end_object() without a matching begin_object()"),
+        }
+        self.end(pg_sys::JsonbIteratorToken_WJB_END_OBJECT)
+    }
+
+    pub fn begin_array(&mut self) -> &mut Self {
+        self.begin(
+            pg_sys::JsonbIteratorToken_WJB_BEGIN_ARRAY,
+            OpenContainer::Array,
+        )
+    }
+
+    pub fn end_array(&mut self) -> &mut Self {
+        match self.open.pop() {
+            Some(OpenContainer::Array) => {}
+            _ => panic!("end_array() without a matching begin_array()"),
+        }
+        self.end(pg_sys::JsonbIteratorToken_WJB_END_ARRAY)
+    }
+
+    /// The key of the object's next value
+    pub fn key(&mut self, key: &str) -> &mut Self {
+        match self.open.last_mut() {
+            Some(OpenContainer::Object { has_key }) if !*has_key => *has_key = true,
+            Some(OpenContainer::Object { .. }) => panic!("the last key of the object has no value"),
+            _ => panic!("key() outside of an object"),
+        }
+        let mut key = copied_string_value(key);
+        unsafe {
+            pg_sys::pushJsonbValue(
+                &mut self.state,
+                pg_sys::JsonbIteratorToken_WJB_KEY,
+                &mut key,
+            );
+        }
+        self
+    }
+
+    pub fn null(&mut self) -> &mut Self {
+        self.scalar(jsonb_value(pg_sys::jbvType_jbvNull))
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        let mut scalar = jsonb_value(pg_sys::jbvType_jbvBool);
+        scalar.val.boolean = value;
+        self.scalar(scalar)
+    }
+
+    pub fn number(&mut self, value: Numeric) -> &mut Self {
+        let mut scalar = jsonb_value(pg_sys::jbvType_jbvNumeric);
+        scalar.val.numeric = value.into_datum().expect("numeric was null") as pg_sys::Numeric;
+        self.scalar(scalar)
+    }
+
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.scalar(copied_string_value(value))
+    }
+
+    /// Add a value read from another `jsonb`, copying any object or array it is
+    pub fn value(&mut self, value: &JsonbValueRef) -> &mut Self {
+        match value {
+            JsonbValueRef::Null => self.null(),
+            JsonbValueRef::Bool(value) => self.bool(*value),
+            JsonbValueRef::Number(value) => self.number(value.clone()),
+            JsonbValueRef::String(value) => self.string(value),
+            JsonbValueRef::Container(container) if container.is_scalar() => {
+                self.value(&container.as_value())
+            }
+            JsonbValueRef::Container(container) => {
+                let tokens = JsonbTokens {
+                    iterator: unsafe { pg_sys::JsonbIteratorInit(container.container) },
+                    skip_nested: false,
+                    _marker: PhantomData,
+                };
+                for (token, value) in tokens {
+                    match token {
+                        pg_sys::JsonbIteratorToken_WJB_BEGIN_OBJECT => self.begin_object(),
+                        pg_sys::JsonbIteratorToken_WJB_END_OBJECT => self.end_object(),
+                        pg_sys::JsonbIteratorToken_WJB_BEGIN_ARRAY => self.begin_array(),
+                        pg_sys::JsonbIteratorToken_WJB_END_ARRAY => self.end_array(),
+                        pg_sys::JsonbIteratorToken_WJB_KEY => self.key(
+                            unsafe { JsonbValueRef::from_pg(&value) }
+                                .as_str()
+                                .expect("jsonb key is not a string"),
+                        ),
+                        _ => self.value(&unsafe { JsonbValueRef::from_pg(&value) }),
+                    };
+                }
+                self
+            }
+        }
+    }
+
+    /// The finished `jsonb`, allocated in the `CurrentMemoryContext`
+    pub fn finish(self) -> JsonbRef<'static> {
+        if !self.open.is_empty() {
+            panic!(
+                "JsonbBuilder finished with {} objects or arrays still open",
+                self.open.len()
+            );
+        }
+        if self.result.is_null() {
+            panic!("JsonbBuilder finished without a value");
+        }
+        unsafe { JsonbRef::from_jsonb(pg_sys::JsonbValueToJsonb(self.result)) }
+    }
+
+    fn begin(&mut self, token: pg_sys::JsonbIteratorToken, container: OpenContainer) -> &mut Self {
+        self.before_value();
+        unsafe {
+            pg_sys::pushJsonbValue(&mut self.state, token, std::ptr::null_mut());
+        }
+        self.open.push(container);
+        self
+    }
+
+    fn end(&mut self, token: pg_sys::JsonbIteratorToken) -> &mut Self {
+        let result =
+            unsafe { pg_sys::pushJsonbValue(&mut self.state, token, std::ptr::null_mut()) };
+        if self.open.is_empty() {
+            self.result = result;
+        }
+        self
+    }
+
+    fn scalar(&mut self, mut scalar: pg_sys::JsonbValue) -> &mut Self {
+        self.before_value();
+        unsafe {
+            match self.open.last() {
+                Some(OpenContainer::Array) => {
+                    pg_sys::pushJsonbValue(
+                        &mut self.state,
+                        pg_sys::JsonbIteratorToken_WJB_ELEM,
+                        &mut scalar,
+                    );
+                }
+                Some(OpenContainer::Object { .. }) => {
+                    pg_sys::pushJsonbValue(
+                        &mut self.state,
+                        pg_sys::JsonbIteratorToken_WJB_VALUE,
+                        &mut scalar,
+                    );
+                }
+                None => {
+                    // a scalar on its own is stored as an array of just itself
+                    let mut array = jsonb_value(pg_sys::jbvType_jbvArray);
+                    array.val.array.rawScalar = true;
+                    pg_sys::pushJsonbValue(
+                        &mut self.state,
+                        pg_sys::JsonbIteratorToken_WJB_BEGIN_ARRAY,
+                        &mut array,
+                    );
+                    pg_sys::pushJsonbValue(
+                        &mut self.state,
+                        pg_sys::JsonbIteratorToken_WJB_ELEM,
+                        &mut scalar,
+                    );
+                    self.result = pg_sys::pushJsonbValue(
+                        &mut self.state,
+                        pg_sys::JsonbIteratorToken_WJB_END_ARRAY,
+                        std::ptr::null_mut(),
+                    );
+                }
+            }
+        }
+        self
+    }
+
+    /// Check a value can go where the builder is, and use up its object's key if it needs one
+    fn before_value(&mut self) {
+        match self.open.last_mut() {
+            None if !self.result.is_null() => panic!("JsonbBuilder already has a whole value"),
+            Some(OpenContainer::Object { has_key }) => {
+                if !*has_key {
+                    panic!("a value in an object needs a key() first");
+                }
+                *has_key = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Postgres keeps pointers to the strings it's given until the `jsonb` is finished, so they're
+/// copied into the `CurrentMemoryContext`
+fn copied_string_value(string: &str) -> pg_sys::JsonbValue {
+    unsafe {
+        let copy = pg_sys::palloc(string.len()) as *mut c_char;
+        std::ptr::copy_nonoverlapping(string.as_ptr() as *const c_char, copy, string.len());
+        string_value(copy, string.len())
+    }
+}
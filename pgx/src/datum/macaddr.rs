@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
+use std::fmt;
+
+/// A Postgres `macaddr`: a 6 byte MAC address, such as `08:00:2b:01:02:03`
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Debug, Default)]
+#[repr(transparent)]
+pub struct MacAddr(pub [u8; 6]);
+
+/// A Postgres `macaddr8`: an 8 byte MAC address, in EUI-64 format, such as
+/// `08:00:2b:01:02:03:04:05`
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Debug, Default)]
+#[repr(transparent)]
+pub struct MacAddr8(pub [u8; 8]);
+
+impl MacAddr8 {
+    /// Convert a 6 byte address to EUI-64 format, the same way as casting it to `macaddr8`, by
+    /// inserting `ff:fe` in the middle
+    pub fn from_macaddr(addr: MacAddr) -> Self {
+        let [a, b, c, d, e, f] = addr.0;
+        MacAddr8([a, b, c, 0xff, 0xfe, d, e, f])
+    }
+}
+
+impl From<MacAddr> for MacAddr8 {
+    fn from(addr: MacAddr) -> Self {
+        MacAddr8::from_macaddr(addr)
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex_bytes(f, &self.0)
+    }
+}
+
+impl fmt::Display for MacAddr8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex_bytes(f, &self.0)
+    }
+}
+
+/// Format `bytes` like Postgres does, such as `08:00:2b:01:02:03`
+fn write_hex_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(":")?;
+        }
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+impl FromDatum for MacAddr {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a macaddr Datum was flagged as non-null but the datum is zero");
+        } else {
+            Some(MacAddr((datum as *const [u8; 6]).read()))
+        }
+    }
+}
+
+impl IntoDatum for MacAddr {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let ptr = PgMemoryContexts::CurrentMemoryContext.palloc_slice::<u8>(self.0.len());
+        ptr.copy_from_slice(&self.0);
+        Some(ptr.as_ptr() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::MACADDROID
+    }
+}
+
+impl FromDatum for MacAddr8 {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a macaddr8 Datum was flagged as non-null but the datum is zero");
+        } else {
+            Some(MacAddr8((datum as *const [u8; 8]).read()))
+        }
+    }
+}
+
+impl IntoDatum for MacAddr8 {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let ptr = PgMemoryContexts::CurrentMemoryContext.palloc_slice::<u8>(self.0.len());
+        ptr.copy_from_slice(&self.0);
+        Some(ptr.as_ptr() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::MACADDR8OID
+    }
+}
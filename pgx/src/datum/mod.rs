@@ -10,17 +10,31 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Handing for easily converting Postgres Datum types into their corresponding Rust types
 //! and converting Rust types into their corresponding Postgres types
 mod anyarray;
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+mod anycompatible;
 mod anyelement;
+mod anynonarray;
 mod array;
+#[cfg(feature = "chrono")]
+mod chrono;
 mod date;
+#[cfg(feature = "rust_decimal")]
+mod decimal;
+mod expanded;
+mod fixed_size;
 mod from;
 mod geo;
 mod inet;
 mod internal;
+mod interval;
 mod into;
 mod item_pointer_data;
 mod json;
+mod jsonb;
+mod macaddr;
 mod numeric;
+mod serialization;
+mod text_search;
 mod time;
 mod time_stamp;
 mod time_stamp_with_timezone;
@@ -32,19 +46,29 @@ mod varlena;
 pub use self::time::*;
 pub use self::uuid::*;
 pub use anyarray::*;
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+pub use anycompatible::*;
 pub use anyelement::*;
+pub use anynonarray::*;
 pub use array::*;
 pub use date::*;
+pub use expanded::*;
+pub use fixed_size::*;
 pub use from::*;
 pub use geo::*;
 pub use inet::*;
 pub use internal::*;
+pub use interval::*;
 pub use into::*;
 pub use item_pointer_data::*;
 pub use json::*;
+pub use jsonb::*;
+pub use macaddr::*;
 pub use numeric::*;
 use once_cell::sync::Lazy;
+pub use serialization::*;
 use std::any::TypeId;
+pub use text_search::*;
 pub use time_stamp::*;
 pub use time_stamp_with_timezone::*;
 pub use time_with_timezone::*;
@@ -56,7 +80,28 @@ use pgx_utils::sql_entity_graph::RustSqlMapping;
 
 /// A tagging trait to indicate a user type is also meant to be used by Postgres
 /// Implemented automatically by `#[derive(PostgresType)]`
-pub trait PostgresType {}
+pub trait PostgresType {
+    /// The format values are stored in, from `#[pgx(serialize = ..)]`, or [`Cbor`]
+    type Format: SerializationFormat;
+
+    /// The version of the type's layout, from `#[pgx(version = ..)]`, which is stored before each
+    /// value unless it's 0
+    const VERSION: u16 = 0;
+
+    /// Read a value stored by an older `version` of the type, from `#[pgx(migrate = ..)]`.  Values
+    /// stored before the type had a version are version 0.
+    fn migrate(version: u16, _bytes: &[u8]) -> Self
+    where
+        Self: Sized,
+    {
+        panic!(
+            "{} can't read values stored by version {} of it, only version {}",
+            std::any::type_name::<Self>(),
+            version,
+            Self::VERSION
+        )
+    }
+}
 
 /// A type which can have it's [`core::any::TypeId`]s registered for Rust to SQL mapping.
 ///
@@ -133,6 +178,15 @@ pub trait WithTypeIds {
         ()
     }
 
+    fn register_expanded_with_refs(
+        _map: &mut std::collections::HashSet<RustSqlMapping>,
+        _single_sql: String,
+    ) where
+        Self: 'static,
+    {
+        ()
+    }
+
     fn register_array_with_refs(
         _map: &mut std::collections::HashSet<RustSqlMapping>,
         _single_sql: String,
@@ -223,6 +277,11 @@ impl<T: 'static> WithSizedTypeIds<T> {
         Lazy::new(|| Some(TypeId::of::<Option<Vec<T>>>()));
     pub const OPTION_VEC_OPTION_ID: Lazy<Option<TypeId>> =
         Lazy::new(|| Some(TypeId::of::<Option<Vec<Option<T>>>>()));
+    pub const VEC_VEC_ID: Lazy<Option<TypeId>> = Lazy::new(|| Some(TypeId::of::<Vec<Vec<T>>>()));
+    pub const VEC_VEC_OPTION_ID: Lazy<Option<TypeId>> =
+        Lazy::new(|| Some(TypeId::of::<Vec<Vec<Option<T>>>>()));
+    pub const ARRAY_BUILDER_ID: Lazy<Option<TypeId>> =
+        Lazy::new(|| Some(TypeId::of::<ArrayBuilder<T>>()));
 
     pub fn register_sized_with_refs(
         map: &mut std::collections::HashSet<RustSqlMapping>,
@@ -346,6 +405,45 @@ impl<T: 'static> WithSizedTypeIds<T> {
                 rust,
             );
         }
+        if let Some(id) = *WithSizedTypeIds::<T>::VEC_VEC_ID {
+            let rust = core::any::type_name::<Vec<Vec<T>>>();
+            assert_eq!(
+                map.insert(RustSqlMapping {
+                    sql: set_sql.clone(),
+                    rust: rust.to_string(),
+                    id: id,
+                }),
+                true,
+                "Cannot map `{}` twice.",
+                rust,
+            );
+        }
+        if let Some(id) = *WithSizedTypeIds::<T>::VEC_VEC_OPTION_ID {
+            let rust = core::any::type_name::<Vec<Vec<Option<T>>>>();
+            assert_eq!(
+                map.insert(RustSqlMapping {
+                    sql: set_sql.clone(),
+                    rust: rust.to_string(),
+                    id: id,
+                }),
+                true,
+                "Cannot map `{}` twice.",
+                rust,
+            );
+        }
+        if let Some(id) = *WithSizedTypeIds::<T>::ARRAY_BUILDER_ID {
+            let rust = core::any::type_name::<ArrayBuilder<T>>();
+            assert_eq!(
+                map.insert(RustSqlMapping {
+                    sql: set_sql.clone(),
+                    rust: rust.to_string(),
+                    id: id,
+                }),
+                true,
+                "Cannot map `{}` twice.",
+                rust,
+            );
+        }
     }
 }
 
@@ -504,3 +602,58 @@ impl<T: Copy + 'static> WithVarlenaTypeIds<T> {
         }
     }
 }
+
+/// A [`PgExpanded`] compatible type which can have it's [`core::any::TypeId`]s registered for Rust
+/// to SQL mapping.
+///
+/// An example use of this trait:
+///
+/// ```rust
+/// use pgx::{datum::{WithExpandedTypeIds, PgExpanded}, PostgresType};
+/// use serde::{Serialize, Deserialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, PostgresType)]
+/// pub struct Treats(Vec<String>);
+///
+/// let mut mappings = Default::default();
+/// let treats_string = stringify!(Treats).to_string();
+///
+/// pgx::datum::WithExpandedTypeIds::<Treats>::register_expanded_with_refs(
+///     &mut mappings,
+///     treats_string.clone()
+/// );
+///
+/// assert!(mappings.iter().any(|x| x.id == core::any::TypeId::of::<PgExpanded<Treats>>()));
+/// ```
+///
+/// This trait uses the fact that inherent implementations are a higher priority than trait
+/// implementations.
+pub struct WithExpandedTypeIds<T>(pub core::marker::PhantomData<T>);
+
+impl<T> WithExpandedTypeIds<T>
+where
+    T: PostgresType + serde::Serialize + serde::de::DeserializeOwned + 'static,
+{
+    pub const EXPANDED_ID: Lazy<Option<TypeId>> = Lazy::new(|| Some(TypeId::of::<PgExpanded<T>>()));
+
+    pub fn register_expanded_with_refs(
+        map: &mut std::collections::HashSet<RustSqlMapping>,
+        single_sql: String,
+    ) where
+        Self: 'static,
+    {
+        if let Some(id) = *WithExpandedTypeIds::<T>::EXPANDED_ID {
+            let rust = core::any::type_name::<PgExpanded<T>>();
+            assert_eq!(
+                map.insert(RustSqlMapping {
+                    sql: single_sql,
+                    rust: rust.to_string(),
+                    id,
+                }),
+                true,
+                "Cannot map `{}` twice.",
+                rust,
+            );
+        }
+    }
+}
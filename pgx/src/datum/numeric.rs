@@ -14,11 +14,148 @@ use pgx_pg_sys::pg_try;
 use serde::de::{Error, Visitor};
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Number;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 
-#[derive(Serialize, Debug)]
+/// A Postgres `numeric`, which can hold any number of digits exactly.
+///
+/// Its arithmetic and comparisons are done by Postgres' own `numeric` functions, so they have the
+/// same results, and errors, as the same operations in SQL:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let cents: Numeric = 1999.into();
+/// let dollars = (cents * 3.into() / 100.into()).round(1);
+/// assert_eq!(dollars, 60.into());
+/// ```
+#[derive(Serialize, Debug, Clone)]
 pub struct Numeric(pub String);
 
+/// Why a `Numeric` couldn't be converted to another type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericConversionError {
+    /// The value is `NaN`
+    NaN,
+    /// The value has a fractional part, but the other type is an integer
+    NotAnInteger,
+    /// The value is outside of the range the other type can represent
+    OutOfRange,
+}
+
+impl fmt::Display for NumericConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumericConversionError::NaN => write!(f, "value is NaN"),
+            NumericConversionError::NotAnInteger => write!(f, "value is not an integer"),
+            NumericConversionError::OutOfRange => write!(f, "value is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for NumericConversionError {}
+
+impl Numeric {
+    /// Call one of Postgres' `numeric` functions, which returns a `numeric`
+    fn call(
+        func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+        args: Vec<Option<pg_sys::Datum>>,
+    ) -> Numeric {
+        unsafe {
+            direct_function_call::<Numeric>(func, args).expect("numeric function returned null")
+        }
+    }
+
+    /// This value as Postgres would write it, such as `1.5` for `+15e-1`
+    pub fn normalize(&self) -> Numeric {
+        Numeric::call(pg_sys::numeric_uplus, vec![self.clone().into_datum()])
+    }
+
+    /// Is this value `NaN`?
+    pub fn is_nan(&self) -> bool {
+        self.normalize().0 == "NaN"
+    }
+
+    pub fn abs(&self) -> Numeric {
+        Numeric::call(pg_sys::numeric_abs, vec![self.clone().into_datum()])
+    }
+
+    /// Round this value to `scale` digits after the decimal point, or before it if `scale` is
+    /// negative, with halves rounded away from zero
+    pub fn round(&self, scale: i32) -> Numeric {
+        Numeric::call(
+            pg_sys::numeric_round,
+            vec![self.clone().into_datum(), scale.into_datum()],
+        )
+    }
+
+    /// Truncate this value to `scale` digits after the decimal point, or before it if `scale` is
+    /// negative
+    pub fn trunc(&self, scale: i32) -> Numeric {
+        Numeric::call(
+            pg_sys::numeric_trunc,
+            vec![self.clone().into_datum(), scale.into_datum()],
+        )
+    }
+}
+
+macro_rules! numeric_operator {
+    ($trait:ident, $method:ident, $func:ident) => {
+        impl $trait for Numeric {
+            type Output = Numeric;
+
+            fn $method(self, rhs: Numeric) -> Numeric {
+                Numeric::call(pg_sys::$func, vec![self.into_datum(), rhs.into_datum()])
+            }
+        }
+    };
+}
+
+numeric_operator!(Add, add, numeric_add);
+numeric_operator!(Sub, sub, numeric_sub);
+numeric_operator!(Mul, mul, numeric_mul);
+numeric_operator!(Div, div, numeric_div);
+numeric_operator!(Rem, rem, numeric_mod);
+
+impl Neg for Numeric {
+    type Output = Numeric;
+
+    fn neg(self) -> Numeric {
+        Numeric::call(pg_sys::numeric_uminus, vec![self.into_datum()])
+    }
+}
+
+/// Compares the values, not how they're written, so `1.0` equals `1`.  Like Postgres, `NaN` is
+/// equal to itself and greater than every other value.
+impl Ord for Numeric {
+    fn cmp(&self, other: &Numeric) -> Ordering {
+        let cmp = unsafe {
+            direct_function_call::<i32>(
+                pg_sys::numeric_cmp,
+                vec![self.clone().into_datum(), other.clone().into_datum()],
+            )
+            .expect("numeric_cmp returned null")
+        };
+        cmp.cmp(&0)
+    }
+}
+
+impl PartialOrd for Numeric {
+    fn partial_cmp(&self, other: &Numeric) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Numeric {
+    fn eq(&self, other: &Numeric) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Numeric {}
+
 impl std::fmt::Display for Numeric {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         fmt.write_fmt(format_args!("{}", self.0))
@@ -157,6 +294,47 @@ impl Into<Numeric> for f64 {
     }
 }
 
+impl From<i128> for Numeric {
+    fn from(value: i128) -> Numeric {
+        Numeric(value.to_string())
+    }
+}
+
+impl From<u128> for Numeric {
+    fn from(value: u128) -> Numeric {
+        Numeric(value.to_string())
+    }
+}
+
+impl TryFrom<Numeric> for i128 {
+    type Error = NumericConversionError;
+
+    fn try_from(numeric: Numeric) -> Result<Self, Self::Error> {
+        let numeric = numeric.normalize();
+        if numeric.0 == "NaN" {
+            return Err(NumericConversionError::NaN);
+        }
+        let (integer, fraction) = numeric
+            .0
+            .split_once('.')
+            .unwrap_or((numeric.0.as_str(), ""));
+        if fraction.bytes().any(|digit| digit != b'0') {
+            return Err(NumericConversionError::NotAnInteger);
+        }
+        integer
+            .parse()
+            .map_err(|_| NumericConversionError::OutOfRange)
+    }
+}
+
+impl TryFrom<Numeric> for i64 {
+    type Error = NumericConversionError;
+
+    fn try_from(numeric: Numeric) -> Result<Self, Self::Error> {
+        i64::try_from(i128::try_from(numeric)?).map_err(|_| NumericConversionError::OutOfRange)
+    }
+}
+
 impl FromDatum for Numeric {
     unsafe fn from_datum(datum: usize, is_null: bool, _typoid: u32) -> Option<Self>
     where
@@ -0,0 +1,185 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The formats `#[derive(PostgresType)]` types are stored on disk in, chosen with
+//! `#[pgx(serialize = ..)]`
+use crate::{
+    pg_sys, set_varsize, vardata_any, varsize_any_exhdr, PgMemoryContexts, PostgresType, StringInfo,
+};
+use serde::{Deserialize, Serialize};
+
+/// The byte which starts the values of a type with a `#[pgx(version = ..)]`, before the version.
+///
+/// Neither CBOR nor JSON ever start a value with it, so values they stored before the type had a
+/// version are read as version 0.  Other formats might, so their values always start with it,
+/// whether or not the type has a version (see [`SerializationFormat::MAY_START_WITH_0XFF`]).
+const VERSION_MARKER: u8 = 0xFF;
+
+/// A serde data format the values of a `#[derive(PostgresType)]` are stored in.
+///
+/// pgx has [`Cbor`], the default, [`JsonFormat`], and [`Bincode`] with the `bincode` feature.  As a
+/// value stays in that format on disk for as long as it's stored, the choice can't be changed
+/// later without a `#[pgx(version = ..)]` to tell old values from new ones.
+///
+/// Values stored before a type had a version are told apart by their first byte, which is never
+/// `0xFF` in CBOR or JSON.  In any other format a value might start with it, so unless the format
+/// sets [`MAY_START_WITH_0XFF`](SerializationFormat::MAY_START_WITH_0XFF) to `false`, every value
+/// is stored after its version, which is 0 until the type has one.
+///
+/// Another format is used by implementing this trait, and naming it:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// /// JSON which is easier to read in a dump of the table
+/// pub struct PrettyJson;
+///
+/// impl SerializationFormat for PrettyJson {
+///     fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo) {
+///         serde_json::to_writer_pretty(buffer, value).expect("failed to encode as JSON")
+///     }
+///
+///     fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T {
+///         serde_json::from_slice(bytes).expect("failed to decode JSON")
+///     }
+/// }
+///
+/// #[derive(Serialize, Deserialize, PostgresType)]
+/// #[pgx(serialize = PrettyJson)]
+/// pub struct Recipe {
+///     ingredients: Vec<String>,
+/// }
+/// ```
+pub trait SerializationFormat {
+    /// Whether a value `encode()` writes might start with the byte `0xFF`.  Only a format which
+    /// never does can store values without a version in front of them.
+    const MAY_START_WITH_0XFF: bool = true;
+
+    /// Append `value` to the `buffer`
+    fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo);
+
+    /// Read a value from all of `bytes`, which `encode()` wrote
+    fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T;
+}
+
+/// [CBOR](https://cbor.io), a compact binary format, and the default
+pub struct Cbor;
+
+impl SerializationFormat for Cbor {
+    // 0xFF is the "break" stop code, which only ends indefinite-length items
+    const MAY_START_WITH_0XFF: bool = false;
+
+    fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo) {
+        serde_cbor::to_writer(buffer, value).expect("failed to encode as CBOR")
+    }
+
+    fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T {
+        serde_cbor::from_slice(bytes).expect("failed to decode CBOR")
+    }
+}
+
+/// JSON, which is larger and slower than [`Cbor`], but can be read by anything.  [`Json`](crate::Json)
+/// is the `json` type's values.
+pub struct JsonFormat;
+
+impl SerializationFormat for JsonFormat {
+    // 0xFF is never valid UTF-8
+    const MAY_START_WITH_0XFF: bool = false;
+
+    fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo) {
+        serde_json::to_writer(buffer, value).expect("failed to encode as JSON")
+    }
+
+    fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T {
+        serde_json::from_slice(bytes).expect("failed to decode JSON")
+    }
+}
+
+/// [bincode](https://docs.rs/bincode), which is smaller and faster than [`Cbor`], but doesn't
+/// record the names of fields, so any change to them needs a new `#[pgx(version = ..)]`
+#[cfg(feature = "bincode")]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl SerializationFormat for Bincode {
+    fn encode<T: Serialize>(value: &T, buffer: &mut StringInfo) {
+        bincode::serialize_into(buffer, value).expect("failed to encode as bincode")
+    }
+
+    fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> T {
+        bincode::deserialize(bytes).expect("failed to decode bincode")
+    }
+}
+
+/// A varlena of `value` in its type's format, after its version if it has one, or if the format
+/// needs it
+pub(crate) fn serialize_postgres_type<T>(value: &T) -> *const pg_sys::varlena
+where
+    T: PostgresType + Serialize,
+{
+    let mut serialized = StringInfo::new();
+
+    serialized.push_bytes(&[0u8; pg_sys::VARHDRSZ]); // reserve space for the header
+    if T::VERSION > 0 || T::Format::MAY_START_WITH_0XFF {
+        serialized.push_bytes(&[VERSION_MARKER]);
+        serialized.push_bytes(&T::VERSION.to_le_bytes());
+    }
+    T::Format::encode(value, &mut serialized);
+
+    let size = serialized.len();
+    let varlena = serialized.into_char_ptr();
+    unsafe {
+        set_varsize(varlena as *mut pg_sys::varlena, size as i32);
+    }
+
+    varlena as *const pg_sys::varlena
+}
+
+/// Read a value of `T` from the varlena, migrating it with [`PostgresType::migrate()`] if it was
+/// stored by another version of the type
+pub(crate) unsafe fn deserialize_postgres_type<'de, T>(input: *mut pg_sys::varlena) -> T
+where
+    T: PostgresType + Deserialize<'de>,
+{
+    let varlena = pg_sys::pg_detoast_datum_packed(input);
+    let len = varsize_any_exhdr(varlena);
+    let data = vardata_any(varlena);
+    let bytes: &'de [u8] = std::slice::from_raw_parts(data as *const u8, len);
+
+    if T::VERSION == 0 && !T::Format::MAY_START_WITH_0XFF {
+        return T::Format::decode(bytes);
+    }
+
+    // values without a version were stored by CBOR or JSON, so can't start with the marker
+    let (version, bytes) = match bytes {
+        [VERSION_MARKER, low, high, rest @ ..] => (u16::from_le_bytes([*low, *high]), rest),
+        _ => (0, bytes),
+    };
+    if version == T::VERSION {
+        T::Format::decode(bytes)
+    } else {
+        T::migrate(version, bytes)
+    }
+}
+
+/// As [`deserialize_postgres_type()`], with the varlena copied into `memory_context` first
+pub(crate) unsafe fn deserialize_postgres_type_into_context<'de, T>(
+    mut memory_context: PgMemoryContexts,
+    input: *mut pg_sys::varlena,
+) -> T
+where
+    T: PostgresType + Deserialize<'de>,
+{
+    memory_context.switch_to(|_| {
+        // this gets the varlena Datum copied into this memory context
+        let varlena = pg_sys::pg_detoast_datum_copy(input);
+        deserialize_postgres_type(varlena)
+    })
+}
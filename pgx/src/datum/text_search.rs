@@ -0,0 +1,384 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The `tsvector` and `tsquery` types of Postgres' full text search
+use crate::{direct_function_call, pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
+use std::ffi::{CStr, CString};
+use std::fmt;
+
+/// The weight of one of a [`Lexeme`]'s positions, from `A`, the most important, down to `D`,
+/// which is what positions have unless `setweight()` gave them another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TsWeight {
+    D,
+    C,
+    B,
+    A,
+}
+
+/// A word of a [`TsVector`], as normalized by the text search configuration which made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lexeme<'a> {
+    word: &'a str,
+    positions: &'a [pg_sys::WordEntryPos],
+}
+
+impl<'a> Lexeme<'a> {
+    pub fn word(&self) -> &'a str {
+        self.word
+    }
+
+    /// Where the word was found in the document, counting its words from 1, along with the
+    /// weight of each.  There are none if the `tsvector` was parsed without them or stripped.
+    pub fn positions(&self) -> impl Iterator<Item = (u16, TsWeight)> + 'a {
+        self.positions.iter().map(|&position| {
+            let weight = match position >> 14 {
+                3 => TsWeight::A,
+                2 => TsWeight::B,
+                1 => TsWeight::C,
+                _ => TsWeight::D,
+            };
+            (position & 0x3fff, weight)
+        })
+    }
+}
+
+/// A Postgres `tsvector`: the distinct lexemes of a document, in sorted order, and where in the
+/// document each was found.
+///
+/// It lives in the memory context it was made or detoasted in, like the [`Datum`](pg_sys::Datum)
+/// it came from.
+pub struct TsVector {
+    tsvector: *mut pg_sys::TSVectorData,
+}
+
+impl TsVector {
+    /// Parse the text form of a `tsvector`, such as `'fat':2 'rat':3`, taking the lexemes as they
+    /// are, like casting it to `tsvector` does.  Raises an ERROR if it isn't valid.
+    pub fn parse(input: &str) -> Self {
+        let input = CString::new(input).expect("a tsvector can't contain a null byte");
+        unsafe {
+            direct_function_call::<TsVector>(
+                pg_sys::tsvectorin,
+                vec![input.as_c_str().into_datum()],
+            )
+            .expect("tsvectorin returned null")
+        }
+    }
+
+    /// Normalize `document` into lexemes with the `default_text_search_config`, like
+    /// `to_tsvector(document)`
+    pub fn from_document(document: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsVector>(pg_sys::to_tsvector, vec![document.into_datum()])
+                .expect("to_tsvector returned null")
+        }
+    }
+
+    /// Normalize `document` into lexemes with the text search configuration named `config`, like
+    /// `to_tsvector(config, document)`
+    pub fn from_document_with_config(config: &str, document: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsVector>(
+                pg_sys::to_tsvector_byid,
+                vec![regconfig(config).into_datum(), document.into_datum()],
+            )
+            .expect("to_tsvector returned null")
+        }
+    }
+
+    /// The number of lexemes, like `length()`
+    pub fn len(&self) -> usize {
+        unsafe { (*self.tsvector).size as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The lexemes, in sorted order
+    pub fn lexemes(&self) -> impl Iterator<Item = Lexeme<'_>> {
+        let entries = unsafe { (*self.tsvector).entries.as_slice(self.len()) };
+        // the lexemes are stored after the last entry
+        let strings = entries.as_ptr_range().end as *const u8;
+        entries.iter().map(move |entry| unsafe {
+            let start = entry.pos() as usize;
+            let len = entry.len() as usize;
+            let word =
+                std::str::from_utf8_unchecked(std::slice::from_raw_parts(strings.add(start), len));
+            let positions = if entry.haspos() != 0 {
+                // followed by the number of positions and then the positions, aligned to 2 bytes
+                let npos = strings.add((start + len + 1) & !1) as *const u16;
+                std::slice::from_raw_parts(npos.add(1), npos.read() as usize)
+            } else {
+                &[]
+            };
+            Lexeme { word, positions }
+        })
+    }
+
+    /// Whether `query` matches this document, like `tsvector @@ tsquery`
+    pub fn matches(&self, query: &TsQuery) -> bool {
+        query.matches(self)
+    }
+
+    /// The same lexemes without their positions and weights, like `strip()`
+    pub fn strip(&self) -> TsVector {
+        unsafe {
+            direct_function_call::<TsVector>(pg_sys::tsvector_strip, vec![self.as_datum()])
+                .expect("strip returned null")
+        }
+    }
+
+    fn as_datum(&self) -> Option<pg_sys::Datum> {
+        Some(self.tsvector as pg_sys::Datum)
+    }
+}
+
+/// The text form of the `tsvector`, such as `'fat':2 'rat':3`
+impl fmt::Display for TsVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = unsafe {
+            direct_function_call::<&CStr>(pg_sys::tsvectorout, vec![self.as_datum()])
+                .expect("tsvectorout returned null")
+        };
+        f.write_str(&text.to_string_lossy())
+    }
+}
+
+impl fmt::Debug for TsVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TsVector").field(&self.to_string()).finish()
+    }
+}
+
+impl FromDatum for TsVector {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a tsvector Datum was flagged as non-null but the datum is zero")
+        } else {
+            let tsvector = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena);
+            Some(TsVector {
+                tsvector: tsvector as *mut pg_sys::TSVectorData,
+            })
+        }
+    }
+
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a tsvector Datum was flagged as non-null but the datum is zero")
+        } else {
+            memory_context.switch_to(|_| {
+                let tsvector = pg_sys::pg_detoast_datum_copy(datum as *mut pg_sys::varlena);
+                Some(TsVector {
+                    tsvector: tsvector as *mut pg_sys::TSVectorData,
+                })
+            })
+        }
+    }
+}
+
+impl IntoDatum for TsVector {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.as_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TSVECTOROID
+    }
+}
+
+/// A Postgres `tsquery`: lexemes combined with `&`, `|`, `!` and `<->`, which a [`TsVector`]
+/// either matches or doesn't.
+///
+/// It lives in the memory context it was made or detoasted in, like the [`Datum`](pg_sys::Datum)
+/// it came from.
+pub struct TsQuery {
+    tsquery: *mut pg_sys::TSQueryData,
+}
+
+impl TsQuery {
+    /// Parse the text form of a `tsquery`, such as `'fat' & ( 'rat' | 'cat' )`, taking the
+    /// lexemes as they are, like casting it to `tsquery` does.  Raises an ERROR if it isn't valid.
+    pub fn parse(input: &str) -> Self {
+        let input = CString::new(input).expect("a tsquery can't contain a null byte");
+        unsafe {
+            direct_function_call::<TsQuery>(pg_sys::tsqueryin, vec![input.as_c_str().into_datum()])
+                .expect("tsqueryin returned null")
+        }
+    }
+
+    /// Parse `query` and normalize its words with the `default_text_search_config`, like
+    /// `to_tsquery(query)`
+    pub fn from_query(query: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(pg_sys::to_tsquery, vec![query.into_datum()])
+                .expect("to_tsquery returned null")
+        }
+    }
+
+    /// Parse `query` and normalize its words with the text search configuration named `config`,
+    /// like `to_tsquery(config, query)`
+    pub fn from_query_with_config(config: &str, query: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(
+                pg_sys::to_tsquery_byid,
+                vec![regconfig(config).into_datum(), query.into_datum()],
+            )
+            .expect("to_tsquery returned null")
+        }
+    }
+
+    /// Match all the words of `text`, normalized with the `default_text_search_config`, like
+    /// `plainto_tsquery(text)`
+    pub fn from_plain_text(text: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(pg_sys::plainto_tsquery, vec![text.into_datum()])
+                .expect("plainto_tsquery returned null")
+        }
+    }
+
+    /// Match all the words of `text`, normalized with the text search configuration named
+    /// `config`, like `plainto_tsquery(config, text)`
+    pub fn from_plain_text_with_config(config: &str, text: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(
+                pg_sys::plainto_tsquery_byid,
+                vec![regconfig(config).into_datum(), text.into_datum()],
+            )
+            .expect("plainto_tsquery returned null")
+        }
+    }
+
+    /// The lexemes the query looks for, in the order it stores them
+    pub fn lexemes(&self) -> impl Iterator<Item = &str> {
+        let items = unsafe {
+            let size = (*self.tsquery).size as usize;
+            std::slice::from_raw_parts(
+                (*self.tsquery).data.as_ptr() as *const pg_sys::QueryItem,
+                size,
+            )
+        };
+        // the lexemes are stored after the last item
+        let operands = items.as_ptr_range().end as *const u8;
+        items.iter().filter_map(move |item| unsafe {
+            if item.type_ as u32 != pg_sys::QI_VAL {
+                return None;
+            }
+            let operand = item.qoperand;
+            Some(std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+                operands.add(operand.distance() as usize),
+                operand.length() as usize,
+            )))
+        })
+    }
+
+    /// Whether `vector` matches the query, like `tsvector @@ tsquery`
+    pub fn matches(&self, vector: &TsVector) -> bool {
+        unsafe {
+            direct_function_call::<bool>(
+                pg_sys::ts_match_vq,
+                vec![vector.as_datum(), self.as_datum()],
+            )
+            .expect("ts_match_vq returned null")
+        }
+    }
+
+    fn as_datum(&self) -> Option<pg_sys::Datum> {
+        Some(self.tsquery as pg_sys::Datum)
+    }
+}
+
+/// The text form of the `tsquery`, such as `'fat' & ( 'rat' | 'cat' )`
+impl fmt::Display for TsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = unsafe {
+            direct_function_call::<&CStr>(pg_sys::tsqueryout, vec![self.as_datum()])
+                .expect("tsqueryout returned null")
+        };
+        f.write_str(&text.to_string_lossy())
+    }
+}
+
+impl fmt::Debug for TsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TsQuery").field(&self.to_string()).finish()
+    }
+}
+
+impl FromDatum for TsQuery {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a tsquery Datum was flagged as non-null but the datum is zero")
+        } else {
+            let tsquery = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena);
+            Some(TsQuery {
+                tsquery: tsquery as *mut pg_sys::TSQueryData,
+            })
+        }
+    }
+
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a tsquery Datum was flagged as non-null but the datum is zero")
+        } else {
+            memory_context.switch_to(|_| {
+                let tsquery = pg_sys::pg_detoast_datum_copy(datum as *mut pg_sys::varlena);
+                Some(TsQuery {
+                    tsquery: tsquery as *mut pg_sys::TSQueryData,
+                })
+            })
+        }
+    }
+}
+
+impl IntoDatum for TsQuery {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.as_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TSQUERYOID
+    }
+}
+
+/// The oid of the text search configuration named `config`, raising an ERROR if there's none
+fn regconfig(config: &str) -> pg_sys::Oid {
+    let config =
+        CString::new(config).expect("a text search configuration can't contain a null byte");
+    unsafe {
+        direct_function_call::<pg_sys::Oid>(
+            pg_sys::regconfigin,
+            vec![config.as_c_str().into_datum()],
+        )
+        .expect("regconfigin returned null")
+    }
+}
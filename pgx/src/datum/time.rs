@@ -17,6 +17,31 @@ pub(crate) const USECS_PER_SEC: i64 = 1_000_000;
 pub(crate) const MINS_PER_HOUR: i64 = 60;
 pub(crate) const SEC_PER_MIN: i64 = 60;
 
+/// Why a date/time value couldn't be converted to or from another crate's type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeConversionError {
+    /// The value is `infinity` or `-infinity`, which only Postgres can represent
+    Infinite,
+    /// The value is outside of the range the other type can represent
+    OutOfRange,
+    /// The `Interval` has months, whose length depends on the date they're added to
+    IntervalHasMonths,
+}
+
+impl std::fmt::Display for DateTimeConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateTimeConversionError::Infinite => write!(f, "value is infinite"),
+            DateTimeConversionError::OutOfRange => write!(f, "value is out of range"),
+            DateTimeConversionError::IntervalHasMonths => {
+                write!(f, "interval has months, which have no fixed length")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DateTimeConversionError {}
+
 #[derive(Debug)]
 pub struct Time(pub(crate) time::Time);
 impl FromDatum for Time {
@@ -62,6 +87,25 @@ impl IntoDatum for Time {
     }
 }
 
+impl FromDatum for time::Time {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<time::Time> {
+        Time::from_datum(datum, is_null, typoid).map(|time| time.0)
+    }
+}
+
+/// Postgres times have microseconds, so any nanoseconds are truncated
+impl IntoDatum for time::Time {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Time(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMEOID
+    }
+}
+
 impl Time {
     pub fn new(time: time::Time) -> Self {
         Time(time)
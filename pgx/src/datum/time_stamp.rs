@@ -8,8 +8,12 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::datum::time::USECS_PER_SEC;
-use crate::{direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum, TimestampWithTimeZone};
-use std::ops::{Deref, DerefMut};
+use crate::datum::time_stamp_with_timezone::{DT_NOBEGIN, DT_NOEND, END_OF_DAY};
+use crate::{
+    direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, Interval, IntoDatum,
+    TimestampWithTimeZone,
+};
+use std::ops::{Add, Deref, DerefMut, Sub};
 use time::{format_description::FormatItem, PrimitiveDateTime};
 
 #[derive(Debug, Copy, Clone)]
@@ -24,6 +28,12 @@ impl From<pg_sys::Timestamp> for Timestamp {
 impl FromDatum for Timestamp {
     #[inline]
     unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Timestamp> {
+        if !is_null && datum as i64 == DT_NOBEGIN {
+            return Some(Timestamp::NEG_INFINITY);
+        } else if !is_null && datum as i64 == DT_NOEND {
+            return Some(Timestamp::INFINITY);
+        }
+
         let ts: Option<TimestampWithTimeZone> =
             TimestampWithTimeZone::from_datum(datum, is_null, typoid);
         match ts {
@@ -40,6 +50,12 @@ impl FromDatum for Timestamp {
 impl IntoDatum for Timestamp {
     #[inline]
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        if self.is_neg_infinity() {
+            return Some(DT_NOBEGIN as pg_sys::Datum);
+        } else if self.is_infinity() {
+            return Some(DT_NOEND as pg_sys::Datum);
+        }
+
         let year = self.year();
         let month = self.month() as i32;
         let mday = self.day() as i32;
@@ -66,10 +82,105 @@ impl IntoDatum for Timestamp {
         pg_sys::TIMESTAMPOID
     }
 }
+
+/// The last microsecond of `time::Date::MAX` and the midnight starting `time::Date::MIN` are
+/// `infinity` and `-infinity`, as for [`Timestamp`]
+impl FromDatum for time::PrimitiveDateTime {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: u32,
+    ) -> Option<time::PrimitiveDateTime> {
+        Timestamp::from_datum(datum, is_null, typoid).map(|timestamp| timestamp.0)
+    }
+}
+
+/// Postgres timestamps have microseconds, so any nanoseconds are truncated
+impl IntoDatum for time::PrimitiveDateTime {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Timestamp(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMESTAMPOID
+    }
+}
 impl Timestamp {
+    /// Postgres' `infinity`, which is later than every other timestamp.
+    ///
+    /// It's represented by the last microsecond of `time::Date::MAX`, so that timestamp is
+    /// `infinity` once given to Postgres.
+    pub const INFINITY: Timestamp = Timestamp(time::Date::MAX.with_time(END_OF_DAY));
+
+    /// Postgres' `-infinity`, which is earlier than every other timestamp.
+    ///
+    /// It's represented by the midnight starting `time::Date::MIN`, so that timestamp is
+    /// `-infinity` once given to Postgres.
+    pub const NEG_INFINITY: Timestamp = Timestamp(time::Date::MIN.midnight());
+
     pub fn new(timestamp: time::PrimitiveDateTime) -> Self {
         Timestamp(timestamp)
     }
+
+    pub fn is_infinity(&self) -> bool {
+        self.0 == Self::INFINITY.0
+    }
+
+    pub fn is_neg_infinity(&self) -> bool {
+        self.0 == Self::NEG_INFINITY.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        !self.is_infinity() && !self.is_neg_infinity()
+    }
+}
+
+/// The timestamp plus the interval, as Postgres' `timestamp + interval` does
+impl Add<Interval> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Interval) -> Timestamp {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_pl_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamp + interval returned NULL")
+        }
+    }
+}
+
+/// The timestamp minus the interval, as Postgres' `timestamp - interval` does
+impl Sub<Interval> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, rhs: Interval) -> Timestamp {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_mi_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamp - interval returned NULL")
+        }
+    }
+}
+
+/// The interval between two timestamps, in days and microseconds, as Postgres'
+/// `timestamp - timestamp` does
+impl Sub for Timestamp {
+    type Output = Interval;
+
+    fn sub(self, rhs: Timestamp) -> Interval {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_mi,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamp - timestamp returned NULL")
+        }
+    }
 }
 
 impl Deref for Timestamp {
@@ -93,7 +204,11 @@ impl serde::Serialize for Timestamp {
     where
         S: serde::Serializer,
     {
-        if self.millisecond() > 0 {
+        if self.is_infinity() {
+            serializer.serialize_str("infinity")
+        } else if self.is_neg_infinity() {
+            serializer.serialize_str("-infinity")
+        } else if self.millisecond() > 0 {
             serializer.serialize_str(
                 &self
                     .format(
@@ -8,13 +8,22 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::datum::time::USECS_PER_SEC;
-use crate::{direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum};
+use crate::{
+    direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, Interval, IntoDatum,
+};
 use std::{
     convert::TryFrom,
-    ops::{Deref, DerefMut},
+    ops::{Add, Deref, DerefMut, Sub},
 };
 use time::{format_description::FormatItem, UtcOffset};
 
+/// Postgres' `-infinity` and `infinity` timestamps, `DT_NOBEGIN` and `DT_NOEND`
+pub(crate) const DT_NOBEGIN: i64 = i64::MIN;
+pub(crate) const DT_NOEND: i64 = i64::MAX;
+
+/// The latest time of day Postgres can represent, for `infinity`
+pub(crate) const END_OF_DAY: time::Time = time::macros::time!(23:59:59.999999);
+
 #[derive(Debug, Copy, Clone)]
 pub struct TimestampWithTimeZone(time::OffsetDateTime);
 
@@ -41,6 +50,10 @@ impl FromDatum for TimestampWithTimeZone {
     ) -> Option<TimestampWithTimeZone> {
         if is_null {
             None
+        } else if datum as i64 == DT_NOBEGIN {
+            Some(TimestampWithTimeZone::NEG_INFINITY)
+        } else if datum as i64 == DT_NOEND {
+            Some(TimestampWithTimeZone::INFINITY)
         } else {
             let mut tm = pg_sys::pg_tm {
                 tm_sec: 0,
@@ -97,6 +110,12 @@ impl FromDatum for TimestampWithTimeZone {
 impl IntoDatum for TimestampWithTimeZone {
     #[inline]
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        if self.is_neg_infinity() {
+            return Some(DT_NOBEGIN as pg_sys::Datum);
+        } else if self.is_infinity() {
+            return Some(DT_NOEND as pg_sys::Datum);
+        }
+
         let year = self.year();
         let month = self.month() as i32;
         let mday = self.day() as i32;
@@ -125,7 +144,58 @@ impl IntoDatum for TimestampWithTimeZone {
     }
 }
 
+/// The last microsecond of `time::Date::MAX` and the midnight starting `time::Date::MIN`, in UTC,
+/// are `infinity` and `-infinity`, as for [`TimestampWithTimeZone`]
+impl FromDatum for time::OffsetDateTime {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: u32,
+    ) -> Option<time::OffsetDateTime> {
+        TimestampWithTimeZone::from_datum(datum, is_null, typoid).map(|timestamp| timestamp.0)
+    }
+}
+
+/// Postgres timestamps have microseconds, so any nanoseconds are truncated
+impl IntoDatum for time::OffsetDateTime {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        TimestampWithTimeZone(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::TIMESTAMPTZOID
+    }
+}
+
 impl TimestampWithTimeZone {
+    /// Postgres' `infinity`, which is later than every other timestamp.
+    ///
+    /// It's represented by the last microsecond of `time::Date::MAX` in UTC, so that instant is
+    /// `infinity` once given to Postgres.
+    pub const INFINITY: TimestampWithTimeZone =
+        TimestampWithTimeZone(time::Date::MAX.with_time(END_OF_DAY).assume_utc());
+
+    /// Postgres' `-infinity`, which is earlier than every other timestamp.
+    ///
+    /// It's represented by the midnight starting `time::Date::MIN` in UTC, so that instant is
+    /// `-infinity` once given to Postgres.
+    pub const NEG_INFINITY: TimestampWithTimeZone =
+        TimestampWithTimeZone(time::Date::MIN.midnight().assume_utc());
+
+    pub fn is_infinity(&self) -> bool {
+        self.0 == Self::INFINITY.0
+    }
+
+    pub fn is_neg_infinity(&self) -> bool {
+        self.0 == Self::NEG_INFINITY.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        !self.is_infinity() && !self.is_neg_infinity()
+    }
+
     /// This shifts the provided `time` back to UTC
     pub fn new(time: time::PrimitiveDateTime, at_tz_offset: time::UtcOffset) -> Self {
         TimestampWithTimeZone(
@@ -138,6 +208,54 @@ impl TimestampWithTimeZone {
     }
 }
 
+/// The timestamp plus the interval, as Postgres' `timestamptz + interval` does in the session's
+/// `TimeZone`
+impl Add<Interval> for TimestampWithTimeZone {
+    type Output = TimestampWithTimeZone;
+
+    fn add(self, rhs: Interval) -> TimestampWithTimeZone {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_pl_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamptz + interval returned NULL")
+        }
+    }
+}
+
+/// The timestamp minus the interval, as Postgres' `timestamptz - interval` does in the session's
+/// `TimeZone`
+impl Sub<Interval> for TimestampWithTimeZone {
+    type Output = TimestampWithTimeZone;
+
+    fn sub(self, rhs: Interval) -> TimestampWithTimeZone {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_mi_interval,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamptz - interval returned NULL")
+        }
+    }
+}
+
+/// The interval between two timestamps, in days and microseconds, as Postgres'
+/// `timestamptz - timestamptz` does
+impl Sub for TimestampWithTimeZone {
+    type Output = Interval;
+
+    fn sub(self, rhs: TimestampWithTimeZone) -> Interval {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_mi,
+                vec![self.into_datum(), rhs.into_datum()],
+            )
+            .expect("timestamptz - timestamptz returned NULL")
+        }
+    }
+}
+
 impl Deref for TimestampWithTimeZone {
     type Target = time::OffsetDateTime;
 
@@ -159,7 +277,11 @@ impl serde::Serialize for TimestampWithTimeZone {
     where
         S: serde::Serializer,
     {
-        if self.millisecond() > 0 {
+        if self.is_infinity() {
+            serializer.serialize_str("infinity")
+        } else if self.is_neg_infinity() {
+            serializer.serialize_str("-infinity")
+        } else if self.millisecond() > 0 {
             serializer.serialize_str(
                 &self
                     .format(
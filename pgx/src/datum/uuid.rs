@@ -80,6 +80,17 @@ impl Uuid {
         Ok(Uuid::from_bytes(bytes))
     }
 
+    /// A random (version 4) UUID, from Postgres' strong random source
+    pub(crate) fn random() -> Self {
+        let mut bytes = [0; UUID_BYTES_LEN];
+        if !unsafe { pg_sys::pg_strong_random(bytes.as_mut_ptr() as _, UUID_BYTES_LEN) } {
+            panic!("could not generate random values for a uuid");
+        }
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uuid(bytes)
+    }
+
     fn format(&self, f: &mut std::fmt::Formatter<'_>, case: UuidFormatCase) -> std::fmt::Result {
         let hyphenated = f.sign_minus();
         for (i, b) in self.0.iter().enumerate() {
@@ -126,3 +137,39 @@ impl<'a> std::fmt::UpperHex for Uuid {
         self.format(f, UuidFormatCase::Uppercase)
     }
 }
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Uuid {
+    fn from(uuid: uuid::Uuid) -> Self {
+        Uuid(uuid.into_bytes())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Uuid> for uuid::Uuid {
+    fn from(uuid: Uuid) -> Self {
+        uuid::Uuid::from_bytes(uuid.0)
+    }
+}
+
+/// `uuid::Uuid` is used directly as a `uuid`, with the `uuid` feature
+#[cfg(feature = "uuid")]
+impl IntoDatum for uuid::Uuid {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Uuid::from(self).into_datum()
+    }
+
+    #[inline]
+    fn type_oid() -> u32 {
+        pg_sys::UUIDOID
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromDatum for uuid::Uuid {
+    #[inline]
+    unsafe fn from_datum(datum: usize, is_null: bool, typoid: pg_sys::Oid) -> Option<uuid::Uuid> {
+        Uuid::from_datum(datum, is_null, typoid).map(uuid::Uuid::from)
+    }
+}
@@ -7,13 +7,14 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 //! Wrapper for Postgres 'varlena' type, over Rust types of a fixed size (ie, `impl Copy`)
+use super::serialization::{
+    deserialize_postgres_type, deserialize_postgres_type_into_context, serialize_postgres_type,
+};
 use crate::pg_sys::{VARATT_SHORT_MAX, VARHDRSZ_SHORT};
 use crate::{
     pg_sys, rust_regtypein, set_varsize, set_varsize_short, vardata_any, varsize_any,
     varsize_any_exhdr, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts, PostgresType,
-    StringInfo,
 };
-use pgx_pg_sys::varlena;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::marker::PhantomData;
@@ -337,7 +338,7 @@ where
     T: PostgresType + Serialize,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
-        Some(cbor_encode(&self) as pg_sys::Datum)
+        Some(serialize_postgres_type(&self) as pg_sys::Datum)
     }
 
     fn type_oid() -> u32 {
@@ -353,7 +354,7 @@ where
         if is_null {
             None
         } else {
-            cbor_decode(datum as *mut pg_sys::varlena)
+            Some(deserialize_postgres_type(datum as *mut pg_sys::varlena))
         }
     }
 
@@ -366,29 +367,14 @@ where
         if is_null {
             None
         } else {
-            cbor_decode_into_context(memory_context, datum as *mut pg_sys::varlena)
+            Some(deserialize_postgres_type_into_context(
+                memory_context,
+                datum as *mut pg_sys::varlena,
+            ))
         }
     }
 }
 
-fn cbor_encode<T>(input: T) -> *const pg_sys::varlena
-where
-    T: Serialize,
-{
-    let mut serialized = StringInfo::new();
-
-    serialized.push_bytes(&[0u8; pg_sys::VARHDRSZ]); // reserve space fo the header
-    serde_cbor::to_writer(&mut serialized, &input).expect("failed to encode as CBOR");
-
-    let size = serialized.len() as usize;
-    let varlena = serialized.into_char_ptr();
-    unsafe {
-        set_varsize(varlena as *mut pg_sys::varlena, size as i32);
-    }
-
-    varlena as *const pg_sys::varlena
-}
-
 pub unsafe fn cbor_decode<'de, T>(input: *mut pg_sys::varlena) -> T
 where
     T: Deserialize<'de>,
@@ -413,34 +399,3 @@ where
         cbor_decode(varlena)
     })
 }
-
-#[allow(dead_code)]
-fn json_encode<T>(input: T) -> *const varlena
-where
-    T: Serialize,
-{
-    let mut serialized = StringInfo::new();
-
-    serialized.push_bytes(&[0u8; pg_sys::VARHDRSZ]); // reserve space fo the header
-    serde_json::to_writer(&mut serialized, &input).expect("failed to encode as JSON");
-
-    let size = serialized.len() as usize;
-    let varlena = serialized.into_char_ptr();
-    unsafe {
-        set_varsize(varlena as *mut pg_sys::varlena, size as i32);
-    }
-
-    varlena as *const pg_sys::varlena
-}
-
-#[allow(dead_code)]
-unsafe fn json_decode<'de, T>(input: *mut pg_sys::varlena) -> T
-where
-    T: Deserialize<'de>,
-{
-    let varlena = pg_sys::pg_detoast_datum_packed(input as *mut pg_sys::varlena);
-    let len = varsize_any_exhdr(varlena);
-    let data = vardata_any(varlena);
-    let slice = std::slice::from_raw_parts(data as *const u8, len);
-    serde_json::from_slice(slice).expect("failed to decode JSON")
-}
@@ -0,0 +1,465 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Dynamic shared memory, which backends create and attach to as they need it, instead of
+//! `shared_preload_libraries` reserving it when Postgres starts
+//!
+//! A [`DsmSegment`] is one block of memory holding one value, and a [`DsaArea`] is an allocator
+//! which grows by adding segments, such as for a [`DsHash`] table.  Each is found by other
+//! backends with its handle, which is typically passed along in fixed shared memory, or as the
+//! argument of a background worker.
+//!
+//! Everything stored must be [`PGXSharedMemory`], as it's read by other processes, where pointers
+//! into this one's memory mean nothing.
+use crate::{pg_sys, PGXSharedMemory};
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// A dynamic shared memory segment holding a `T`, which is mapped into this backend until it's
+/// dropped.
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use pgx::dsm::DsmSegment;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct Progress {
+///     rows: AtomicU64,
+/// }
+/// unsafe impl PGXSharedMemory for Progress {}
+///
+/// // the leader
+/// let progress = DsmSegment::<Progress>::create();
+/// let handle = progress.handle();
+/// // ...start workers, passing them `handle`
+///
+/// // a worker
+/// let progress = DsmSegment::<Progress>::attach(handle).expect("the leader has exited");
+/// progress.rows.fetch_add(1, Ordering::Relaxed);
+/// ```
+///
+/// The segment is destroyed once every backend has dropped theirs, unless it's
+/// [pinned](DsmSegment::pin).  The value in it is never dropped.
+pub struct DsmSegment<T: PGXSharedMemory> {
+    segment: *mut pg_sys::dsm_segment,
+    _marker: PhantomData<T>,
+}
+
+impl<T: PGXSharedMemory + Default> DsmSegment<T> {
+    /// Create a new segment holding `T::default()`
+    pub fn create() -> Self {
+        unsafe {
+            let segment = pg_sys::dsm_create(std::mem::size_of::<T>().max(1), 0);
+            // the segment is ours to detach, not the current resource owner's
+            pg_sys::dsm_pin_mapping(segment);
+            std::ptr::write(pg_sys::dsm_segment_address(segment) as *mut T, T::default());
+            DsmSegment {
+                segment,
+                _marker: PhantomData,
+            }
+        }
+    }
+}
+
+impl<T: PGXSharedMemory> DsmSegment<T> {
+    /// Attach to the segment with `handle`, or `None` if it's been destroyed.
+    ///
+    /// ## Panics
+    ///
+    /// If the segment isn't the size of a `T`, which means it holds something else.  Attaching
+    /// to a segment this backend is already attached to is an `ERROR`.
+    pub fn attach(handle: pg_sys::dsm_handle) -> Option<Self> {
+        unsafe {
+            let segment = pg_sys::dsm_attach(handle);
+            if segment.is_null() {
+                return None;
+            }
+            pg_sys::dsm_pin_mapping(segment);
+            let segment = DsmSegment {
+                segment,
+                _marker: PhantomData,
+            };
+            assert_eq!(
+                pg_sys::dsm_segment_map_length(segment.segment),
+                std::mem::size_of::<T>().max(1),
+                "dynamic shared memory segment {} does not hold a `{}`",
+                handle,
+                std::any::type_name::<T>()
+            );
+            Some(segment)
+        }
+    }
+
+    /// The handle other backends [attach](DsmSegment::attach) to this segment with
+    pub fn handle(&self) -> pg_sys::dsm_handle {
+        unsafe { pg_sys::dsm_segment_handle(self.segment) }
+    }
+
+    /// Keep the segment until the server restarts, even once no backend is attached to it
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsm_pin_segment(self.segment) }
+    }
+}
+
+impl<T: PGXSharedMemory> Deref for DsmSegment<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(pg_sys::dsm_segment_address(self.segment) as *const T) }
+    }
+}
+
+impl<T: PGXSharedMemory> Drop for DsmSegment<T> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsm_detach(self.segment) }
+    }
+}
+
+/// Register the name `pg_stat_activity` shows for the LWLocks of `tranche_id` in this backend
+fn register_tranche(tranche_id: i32, tranche_name: &str) {
+    // Postgres keeps the pointer, so the name lives as long as the backend
+    let name = CString::new(tranche_name).expect("tranche name contains a NUL");
+    unsafe { pg_sys::LWLockRegisterTranche(tranche_id, name.into_raw() as _) }
+}
+
+/// The handle of a [`DsaArea`], for other backends to attach to it with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct DsaHandle {
+    area: pg_sys::dsa_handle,
+    tranche_id: i32,
+}
+
+unsafe impl PGXSharedMemory for DsaHandle {}
+
+/// A dynamic shared memory area, which allocates shared memory for the structures in it, such as
+/// [`DsHash`] tables, as they grow.  It's mapped into this backend until it's dropped.
+///
+/// The area is destroyed once every backend has dropped theirs, unless it's
+/// [pinned](DsaArea::pin).
+pub struct DsaArea {
+    area: *mut pg_sys::dsa_area,
+    tranche_id: i32,
+}
+
+impl DsaArea {
+    /// Create a new area, whose locks `pg_stat_activity` shows waits for as `tranche_name`
+    pub fn create(tranche_name: &str) -> Self {
+        unsafe {
+            let tranche_id = pg_sys::LWLockNewTrancheId();
+            register_tranche(tranche_id, tranche_name);
+            let area = pg_sys::dsa_create(tranche_id);
+            pg_sys::dsa_pin_mapping(area);
+            DsaArea { area, tranche_id }
+        }
+    }
+
+    /// Attach to the area with `handle`, which must still exist.  `tranche_name` should be the
+    /// one it was created with.
+    pub fn attach(handle: DsaHandle, tranche_name: &str) -> Self {
+        register_tranche(handle.tranche_id, tranche_name);
+        unsafe {
+            let area = pg_sys::dsa_attach(handle.area);
+            pg_sys::dsa_pin_mapping(area);
+            DsaArea {
+                area,
+                tranche_id: handle.tranche_id,
+            }
+        }
+    }
+
+    /// The handle other backends [attach](DsaArea::attach) to this area with
+    pub fn handle(&self) -> DsaHandle {
+        DsaHandle {
+            area: unsafe { pg_sys::dsa_get_handle(self.area) },
+            tranche_id: self.tranche_id,
+        }
+    }
+
+    /// Keep the area until the server restarts, even once no backend is attached to it
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsa_pin(self.area) }
+    }
+}
+
+impl Drop for DsaArea {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsa_detach(self.area) }
+    }
+}
+
+/// The handle of a [`DsHash`], for other backends to attach to it with
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub type DsHashHandle = pg_sys::dshash_table_handle;
+
+/// A table is identified by the area it's in and its handle in that area
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+type DsHashId = (pg_sys::dsa_handle, DsHashHandle);
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+thread_local! {
+    /// The tables this backend is attached to
+    static ATTACHED_TABLES: std::cell::RefCell<std::collections::HashSet<DsHashId>> =
+        Default::default();
+}
+
+/// What a [`DsHash`] stores for each key
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[repr(C)]
+struct DsHashEntry<K, V> {
+    // dshash expects the key first
+    key: K,
+    value: V,
+}
+
+/// A concurrent hash table in a [`DsaArea`], which every backend attached to the area can read
+/// and write, with a lock on each partition of the table.
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use pgx::dsm::{DsaArea, DsHash};
+///
+/// let area = DsaArea::create("my_cache");
+/// let mut table = DsHash::<i64, i64>::create(&area);
+/// *table.get_or_insert_with(42, || 0) += 1;
+/// assert_eq!(table.get(&42).map(|value| *value), Some(1));
+/// ```
+///
+/// Entries are locked from when they're found until the reference to them is dropped, so keep
+/// them briefly.  Each reference borrows the table mutably, and a backend can only attach to a
+/// table once, so it can never hold two locks at once, which deadlocks when they're on the same
+/// partition.  Values are never dropped.
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub struct DsHash<'a, K, V> {
+    table: *mut pg_sys::dshash_table,
+    id: DsHashId,
+    _area: PhantomData<&'a DsaArea>,
+    _marker: PhantomData<(K, V)>,
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, K, V> DsHash<'a, K, V>
+where
+    K: PGXSharedMemory + Copy + Eq + std::hash::Hash,
+    V: PGXSharedMemory,
+{
+    fn parameters(area: &DsaArea) -> pg_sys::dshash_parameters {
+        pg_sys::dshash_parameters {
+            key_size: std::mem::size_of::<K>() as _,
+            entry_size: std::mem::size_of::<DsHashEntry<K, V>>() as _,
+            compare_function: Some(compare_keys::<K>),
+            hash_function: Some(hash_key::<K>),
+            tranche_id: area.tranche_id,
+        }
+    }
+
+    /// Create a new, empty table in `area`
+    pub fn create(area: &'a DsaArea) -> Self {
+        let parameters = Self::parameters(area);
+        let table = unsafe { pg_sys::dshash_create(area.area, &parameters, std::ptr::null_mut()) };
+        let id = (area.handle().area, unsafe {
+            pg_sys::dshash_get_hash_table_handle(table)
+        });
+        ATTACHED_TABLES.with(|tables| tables.borrow_mut().insert(id));
+        DsHash {
+            table,
+            id,
+            _area: PhantomData,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attach to the table in `area` with `handle`
+    ///
+    /// ## Panics
+    ///
+    /// If this backend is already attached to the table.
+    pub fn attach(area: &'a DsaArea, handle: DsHashHandle) -> Self {
+        let id = (area.handle().area, handle);
+        ATTACHED_TABLES.with(|tables| {
+            assert!(
+                tables.borrow_mut().insert(id),
+                "this backend is already attached to this dshash table"
+            )
+        });
+        let parameters = Self::parameters(area);
+        DsHash {
+            table: unsafe {
+                pg_sys::dshash_attach(area.area, &parameters, handle, std::ptr::null_mut())
+            },
+            id,
+            _area: PhantomData,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The handle other backends [attach](DsHash::attach) to this table with
+    pub fn handle(&self) -> DsHashHandle {
+        unsafe { pg_sys::dshash_get_hash_table_handle(self.table) }
+    }
+
+    /// The value for `key`, locked so it can only be read until it's dropped
+    pub fn get(&mut self, key: &K) -> Option<DsHashRef<'_, V>> {
+        self.find(key, false).map(|entry| DsHashRef {
+            table: self.table,
+            entry: entry as *mut std::ffi::c_void,
+            value: unsafe { std::ptr::addr_of_mut!((*entry).value) },
+            _marker: PhantomData,
+        })
+    }
+
+    /// The value for `key`, locked so only this backend can read or change it until it's dropped
+    pub fn get_mut(&mut self, key: &K) -> Option<DsHashRefMut<'_, V>> {
+        self.find(key, true).map(|entry| self.entry_mut(entry))
+    }
+
+    /// The value for `key`, which is `default()` if there wasn't one, locked as
+    /// [`get_mut`](DsHash::get_mut) locks it
+    pub fn get_or_insert_with(
+        &mut self,
+        key: K,
+        default: impl FnOnce() -> V,
+    ) -> DsHashRefMut<'_, V> {
+        if let Some(entry) = self.find(&key, true) {
+            return self.entry_mut(entry);
+        }
+
+        // the value is made before the entry is, and without holding its lock, so a `default`
+        // which panics or raises an ERROR leaves nothing half-inserted
+        let value = default();
+        unsafe {
+            let mut found = false;
+            let entry = pg_sys::dshash_find_or_insert(
+                self.table,
+                &key as *const K as *const std::ffi::c_void,
+                &mut found,
+            ) as *mut DsHashEntry<K, V>;
+            if !found {
+                // the key has been copied in already
+                std::ptr::write(std::ptr::addr_of_mut!((*entry).value), value);
+            }
+            // otherwise another backend inserted it first, and its value wins
+            self.entry_mut(entry)
+        }
+    }
+
+    /// Remove `key`, returning whether it was there
+    pub fn remove(&mut self, key: &K) -> bool {
+        unsafe { pg_sys::dshash_delete_key(self.table, key as *const K as *const std::ffi::c_void) }
+    }
+
+    fn find(&self, key: &K, exclusive: bool) -> Option<*mut DsHashEntry<K, V>> {
+        let entry = unsafe {
+            pg_sys::dshash_find(
+                self.table,
+                key as *const K as *const std::ffi::c_void,
+                exclusive,
+            )
+        };
+        (!entry.is_null()).then(|| entry as *mut DsHashEntry<K, V>)
+    }
+
+    /// `entry` is exclusively locked by this backend
+    fn entry_mut(&mut self, entry: *mut DsHashEntry<K, V>) -> DsHashRefMut<'_, V> {
+        DsHashRefMut {
+            table: self.table,
+            entry: entry as *mut std::ffi::c_void,
+            value: unsafe { std::ptr::addr_of_mut!((*entry).value) },
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, K, V> Drop for DsHash<'a, K, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_detach(self.table) }
+        ATTACHED_TABLES.with(|tables| tables.borrow_mut().remove(&self.id));
+    }
+}
+
+/// dshash only asks whether keys are equal
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe extern "C" fn compare_keys<K: Eq>(
+    a: *const std::ffi::c_void,
+    b: *const std::ffi::c_void,
+    _size: pg_sys::Size,
+    _arg: *mut std::ffi::c_void,
+) -> i32 {
+    (*(a as *const K) != *(b as *const K)) as i32
+}
+
+/// Every backend has to hash a key the same, so it's seahash, as `#[derive(PostgresHash)]` uses
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe extern "C" fn hash_key<K: std::hash::Hash>(
+    key: *const std::ffi::c_void,
+    _size: pg_sys::Size,
+    _arg: *mut std::ffi::c_void,
+) -> pg_sys::dshash_hash {
+    crate::misc::pgx_seahash(&*(key as *const K)) as pg_sys::dshash_hash
+}
+
+/// A value in a [`DsHash`], which other backends can read but not change until it's dropped
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub struct DsHashRef<'a, V> {
+    table: *mut pg_sys::dshash_table,
+    entry: *mut std::ffi::c_void,
+    value: *mut V,
+    _marker: PhantomData<&'a V>,
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, V> Deref for DsHashRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { &*self.value }
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, V> Drop for DsHashRef<'a, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_release_lock(self.table, self.entry) }
+    }
+}
+
+/// A value in a [`DsHash`], which other backends can't read or change until it's dropped
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub struct DsHashRefMut<'a, V> {
+    table: *mut pg_sys::dshash_table,
+    entry: *mut std::ffi::c_void,
+    value: *mut V,
+    _marker: PhantomData<&'a mut V>,
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, V> Deref for DsHashRefMut<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { &*self.value }
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, V> DerefMut for DsHashRefMut<'a, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { &mut *self.value }
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+impl<'a, V> Drop for DsHashRefMut<'a, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_release_lock(self.table, self.entry) }
+    }
+}
@@ -0,0 +1,169 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe access to the `pg_sys::EventTriggerData` of a `#[pg_event_trigger]` function
+
+use crate::{direct_function_call, is_a, pg_sys, PgBox, Spi};
+use std::ffi::CStr;
+
+/// The event that fired an event trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgEventTriggerEvent {
+    DdlCommandStart,
+    DdlCommandEnd,
+    SqlDrop,
+    TableRewrite,
+}
+
+/// An object dropped by the command that fired a `sql_drop` event trigger, as reported by
+/// `pg_event_trigger_dropped_objects()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgDroppedObject {
+    pub classid: pg_sys::Oid,
+    pub objid: pg_sys::Oid,
+    pub objsubid: i32,
+    pub original: bool,
+    pub normal: bool,
+    pub is_temporary: bool,
+    pub object_type: String,
+    pub schema_name: Option<String>,
+    pub object_name: Option<String>,
+    pub object_identity: String,
+}
+
+/// The `pg_sys::EventTriggerData` a `#[pg_event_trigger]` function was called with
+pub struct PgEventTrigger {
+    event_trigger_data: PgBox<pg_sys::EventTriggerData>,
+}
+
+impl PgEventTrigger {
+    /// Build a `PgEventTrigger` from the `fcinfo` of a function called as an event trigger.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe as we cannot validate that `fcinfo` is valid.
+    ///
+    /// ## Panics
+    ///
+    /// If the function was not called as an event trigger.
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_event_trigger(fcinfo) {
+            panic!("function was not called as an event trigger");
+        }
+
+        PgEventTrigger {
+            event_trigger_data: PgBox::from_pg(
+                fcinfo.as_ref().unwrap().context as *mut pg_sys::EventTriggerData,
+            ),
+        }
+    }
+
+    /// The event that fired this event trigger
+    pub fn event(&self) -> PgEventTriggerEvent {
+        match self.event_name() {
+            "ddl_command_start" => PgEventTriggerEvent::DdlCommandStart,
+            "ddl_command_end" => PgEventTriggerEvent::DdlCommandEnd,
+            "sql_drop" => PgEventTriggerEvent::SqlDrop,
+            "table_rewrite" => PgEventTriggerEvent::TableRewrite,
+            other => panic!("unrecognized event trigger event: {}", other),
+        }
+    }
+
+    /// The name of the event that fired this event trigger, eg `ddl_command_start`
+    pub fn event_name(&self) -> &str {
+        unsafe { CStr::from_ptr(self.event_trigger_data.event) }
+            .to_str()
+            .expect("event trigger event is not valid UTF8")
+    }
+
+    /// The command tag of the command that fired this event trigger, eg `CREATE TABLE`
+    pub fn tag(&self) -> &str {
+        #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+        let tag = self.event_trigger_data.tag;
+        #[cfg(any(feature = "pg13", feature = "pg14"))]
+        let tag = unsafe { pg_sys::GetCommandTagName(self.event_trigger_data.tag) };
+
+        unsafe { CStr::from_ptr(tag) }
+            .to_str()
+            .expect("event trigger tag is not valid UTF8")
+    }
+
+    /// The parse tree of the command that fired this event trigger.
+    ///
+    /// Its structure is not guaranteed to stay the same between Postgres versions.
+    pub fn parsetree(&self) -> *mut pg_sys::Node {
+        self.event_trigger_data.parsetree
+    }
+
+    /// The oid of the table about to be rewritten, for a `table_rewrite` event trigger
+    pub fn table_rewrite_oid(&self) -> Option<pg_sys::Oid> {
+        if self.event() != PgEventTriggerEvent::TableRewrite {
+            return None;
+        }
+        unsafe { direct_function_call(pg_sys::pg_event_trigger_table_rewrite_oid, vec![]) }
+    }
+
+    /// Why the table is about to be rewritten, for a `table_rewrite` event trigger.
+    ///
+    /// This is a bitmask of the `AT_REWRITE_*` constants.
+    pub fn table_rewrite_reason(&self) -> Option<i32> {
+        if self.event() != PgEventTriggerEvent::TableRewrite {
+            return None;
+        }
+        unsafe { direct_function_call(pg_sys::pg_event_trigger_table_rewrite_reason, vec![]) }
+    }
+
+    /// The objects dropped by the command, for a `sql_drop` event trigger.
+    ///
+    /// Empty for every other event.
+    pub fn dropped_objects(&self) -> Vec<PgDroppedObject> {
+        let mut dropped = Vec::new();
+        if self.event() != PgEventTriggerEvent::SqlDrop {
+            return dropped;
+        }
+
+        Spi::connect_read_only(|client| {
+            let table = client.select(
+                "SELECT classid, objid, objsubid, original, normal, is_temporary, object_type,
+                        schema_name, object_name, object_identity
+                   FROM pg_event_trigger_dropped_objects()",
+                None,
+                None,
+            );
+            for row in table {
+                dropped.push(PgDroppedObject {
+                    classid: row["classid"].value().unwrap(),
+                    objid: row["objid"].value().unwrap(),
+                    objsubid: row["objsubid"].value().unwrap(),
+                    original: row["original"].value().unwrap(),
+                    normal: row["normal"].value().unwrap(),
+                    is_temporary: row["is_temporary"].value().unwrap(),
+                    object_type: row["object_type"].value().unwrap(),
+                    schema_name: row["schema_name"].value(),
+                    object_name: row["object_name"].value(),
+                    object_identity: row["object_identity"].value().unwrap(),
+                });
+            }
+            Ok(Some(()))
+        });
+        dropped
+    }
+
+    /// The underlying `pg_sys::EventTriggerData`
+    pub fn as_ptr(&self) -> *mut pg_sys::EventTriggerData {
+        self.event_trigger_data.as_ptr()
+    }
+}
+
+/// Was the function behind `fcinfo` called as an event trigger?
+#[inline]
+pub unsafe fn called_as_event_trigger(fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let fcinfo = fcinfo.as_ref().expect("fcinfo was null");
+    !fcinfo.context.is_null() && is_a(fcinfo.context, pg_sys::NodeTag_T_EventTriggerData)
+}
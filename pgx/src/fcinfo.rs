@@ -328,6 +328,38 @@ pub unsafe fn direct_pg_extern_function_call<R: FromDatum>(
 pub unsafe fn direct_function_call_as_datum(
     func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
     args: Vec<Option<pg_sys::Datum>>,
+) -> Option<pg_sys::Datum> {
+    direct_function_call_with_collation_as_datum(func, pg_sys::InvalidOid, args)
+}
+
+/// Akin to [direct_function_call], but the function is called with `collation`, as the
+/// `COLLATE`-dependent functions, such as `lower()`, need
+///
+/// ## Safety
+///
+/// This function is unsafe as the underlying function being called is likely unsafe
+pub unsafe fn direct_function_call_with_collation<R: FromDatum>(
+    func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+    collation: pg_sys::Oid,
+    args: Vec<Option<pg_sys::Datum>>,
+) -> Option<R> {
+    let datum = direct_function_call_with_collation_as_datum(func, collation, args);
+    match datum {
+        Some(datum) => R::from_datum(datum, false, pg_sys::InvalidOid),
+        None => None,
+    }
+}
+
+/// Same as [direct_function_call_with_collation] but instead returns the direct
+/// `Option<pg_sys::Datum>` instead of converting it to a value
+///
+/// ## Safety
+///
+/// This function is unsafe as the function you're calling is also unsafe
+pub unsafe fn direct_function_call_with_collation_as_datum(
+    func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+    collation: pg_sys::Oid,
+    args: Vec<Option<pg_sys::Datum>>,
 ) -> Option<pg_sys::Datum> {
     let mut null_array = [false; 100usize];
     let mut arg_array = [0 as pg_sys::Datum; 100usize];
@@ -348,6 +380,7 @@ pub unsafe fn direct_function_call_as_datum(
     }
 
     let mut fcid = make_function_call_info(nargs, arg_array, null_array);
+    fcid.fncollation = collation;
     let datum = func(fcid.deref_mut());
 
     if fcid.isnull {
@@ -0,0 +1,707 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Foreign data wrappers, written in Rust
+//!
+//! Implement [`ForeignDataWrapper`] and return [`fdw_routine`] from a `#[pg_extern]` function, which
+//! pgx declares as returning `fdw_handler`.  Then create the wrapper from that handler:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct Countdown {
+//!     remaining: i32,
+//! }
+//!
+//! impl ForeignDataWrapper for Countdown {
+//!     type Row = (i32,);
+//!
+//!     fn begin(table: &PgForeignTable) -> Self {
+//!         let from = table.option("from").unwrap_or("10");
+//!         Countdown {
+//!             remaining: from.parse().expect("`from` must be an integer"),
+//!         }
+//!     }
+//!
+//!     fn iterate(&mut self) -> Option<Self::Row> {
+//!         self.remaining -= 1;
+//!         (self.remaining >= 0).then(|| (self.remaining,))
+//!     }
+//!
+//!     fn re_scan(&mut self) {
+//!         panic!("Countdown can't be rescanned");
+//!     }
+//! }
+//!
+//! #[pg_extern]
+//! fn countdown_fdw_handler() -> PgBox<pg_sys::FdwRoutine> {
+//!     fdw_routine::<Countdown>()
+//! }
+//!
+//! extension_sql!(
+//!     "CREATE FOREIGN DATA WRAPPER countdown_fdw HANDLER countdown_fdw_handler;",
+//!     name = "create_countdown_fdw",
+//!     requires = [countdown_fdw_handler]
+//! );
+//! ```
+//!
+//! ```sql
+//! CREATE SERVER countdown FOREIGN DATA WRAPPER countdown_fdw;
+//! CREATE FOREIGN TABLE liftoff (n int) SERVER countdown OPTIONS (from '3');
+//! SELECT * FROM liftoff; -- 2, 1, 0
+//! ```
+//!
+//! Each row is built in a memory context which is reset before the next one, so anything a row
+//! allocates from Postgres only lives until then.  A scan's state is dropped along with its query,
+//! even if the query is cancelled before [`ForeignDataWrapper::end`] is called.
+use crate::{
+    pg_guard, pg_sys, void_mut_ptr, AllocatedByPostgres, AllocatedByRust, IntoDatum, PgBox,
+    PgHeapTuple, PgList, PgMemoryContexts, PgRelation, PgTupleDesc, WhoAllocated,
+};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// The junk attribute which carries the old row to `UPDATE` and `DELETE`
+const WHOLEROW: &[u8] = b"wholerow\0";
+
+bitflags! {
+    /// Which kinds of modification a [`ForeignDataWrapper`] supports
+    pub struct ForeignModify: i32 {
+        const INSERT = 1 << pg_sys::CmdType_CMD_INSERT;
+        const UPDATE = 1 << pg_sys::CmdType_CMD_UPDATE;
+        const DELETE = 1 << pg_sys::CmdType_CMD_DELETE;
+    }
+}
+
+/// The planner's estimate of the cost of scanning a foreign table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForeignScanEstimate {
+    /// how many rows the scan will return
+    pub rows: f64,
+    /// the cost of starting the scan, before the first row is returned
+    pub startup_cost: f64,
+    /// the cost of returning every row
+    pub total_cost: f64,
+}
+
+impl Default for ForeignScanEstimate {
+    fn default() -> Self {
+        ForeignScanEstimate {
+            rows: 1000.0,
+            startup_cost: 10.0,
+            total_cost: 1010.0,
+        }
+    }
+}
+
+/// A foreign table which a [`ForeignDataWrapper`] is planning, scanning, or modifying
+pub struct PgForeignTable {
+    relation: PgRelation,
+    options: HashMap<String, String>,
+}
+
+impl PgForeignTable {
+    unsafe fn new(relation: PgRelation) -> Self {
+        let table = pg_sys::GetForeignTable(relation.oid());
+        let server = pg_sys::GetForeignServer((*table).serverid);
+
+        let mut options = HashMap::new();
+        // the table's options take precedence over its server's
+        for list in [(*server).options, (*table).options] {
+            for option in PgList::<pg_sys::DefElem>::from_pg(list).iter_ptr() {
+                let name = CStr::from_ptr((*option).defname);
+                let value = CStr::from_ptr(pg_sys::defGetString(option));
+                options.insert(
+                    name.to_string_lossy().into_owned(),
+                    value.to_string_lossy().into_owned(),
+                );
+            }
+        }
+
+        PgForeignTable { relation, options }
+    }
+
+    /// The foreign table itself
+    pub fn relation(&self) -> &PgRelation {
+        &self.relation
+    }
+
+    /// The columns of the foreign table
+    pub fn tuple_desc(&self) -> PgTupleDesc {
+        self.relation.tuple_desc()
+    }
+
+    /// The `OPTIONS` of the foreign table and its server
+    pub fn options(&self) -> &HashMap<String, String> {
+        &self.options
+    }
+
+    /// The option `name` of the foreign table or, failing that, its server
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+}
+
+/// A row returned by [`ForeignDataWrapper::iterate`], or by a table access method
+///
+/// This is implemented for [`PgHeapTuple`]s, and for tuples of up to 12 [`IntoDatum`]s, which fill
+/// the table's columns in order.
+pub trait IntoForeignRow {
+    /// Fill in `values` and `nulls`, which have an entry for each attribute of `tupdesc`, and are
+    /// all NULL to begin with
+    fn into_foreign_row(
+        self,
+        tupdesc: &PgTupleDesc,
+        values: &mut [pg_sys::Datum],
+        nulls: &mut [bool],
+    );
+}
+
+impl<'a, AllocatedBy: WhoAllocated<pg_sys::HeapTupleData>> IntoForeignRow
+    for PgHeapTuple<'a, AllocatedBy>
+{
+    fn into_foreign_row(
+        self,
+        tupdesc: &PgTupleDesc,
+        values: &mut [pg_sys::Datum],
+        nulls: &mut [bool],
+    ) {
+        if self.len() != tupdesc.len() {
+            panic!(
+                "a row of {} attributes can't be stored in a foreign table of {} attributes",
+                self.len(),
+                tupdesc.len()
+            );
+        }
+        unsafe {
+            pg_sys::heap_deform_tuple(
+                self.as_ptr(),
+                tupdesc.as_ptr(),
+                values.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+            );
+        }
+        // the values may point into the tuple, which the row's memory context frees with them
+        self.into_pg();
+    }
+}
+
+macro_rules! impl_into_foreign_row_for_tuple {
+    ($($t:ident $n:tt),+) => {
+        impl<$($t: IntoDatum),+> IntoForeignRow for ($($t,)+) {
+            fn into_foreign_row(
+                self,
+                tupdesc: &PgTupleDesc,
+                values: &mut [pg_sys::Datum],
+                nulls: &mut [bool],
+            ) {
+                let mut attributes = tupdesc
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, attribute)| !attribute.is_dropped());
+                $(
+                    let (i, attribute) = attributes
+                        .next()
+                        .expect("the row has more values than the foreign table has columns");
+                    if attribute.atttypid != $t::type_oid() {
+                        panic!(
+                            "column `{}` has type oid {}, but the row's value has type oid {}",
+                            attribute.name(),
+                            attribute.atttypid,
+                            $t::type_oid()
+                        );
+                    }
+                    if let Some(datum) = self.$n.into_datum() {
+                        values[i] = datum;
+                        nulls[i] = false;
+                    }
+                )+
+                if attributes.next().is_some() {
+                    panic!("the row has fewer values than the foreign table has columns");
+                }
+            }
+        }
+    };
+}
+
+impl_into_foreign_row_for_tuple!(A 0);
+impl_into_foreign_row_for_tuple!(A 0, B 1);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_into_foreign_row_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// A foreign data wrapper, whose callbacks Postgres gets from [`fdw_routine`]
+///
+/// An instance is created for each scan of a foreign table with [`ForeignDataWrapper::begin`], and
+/// for each `INSERT`, `UPDATE` or `DELETE` with [`ForeignDataWrapper::begin_modify`].  Rust panics
+/// in any of these are raised as Postgres `ERROR`s.
+pub trait ForeignDataWrapper: Sized {
+    /// What [`ForeignDataWrapper::iterate`] returns for each row of the table
+    type Row: IntoForeignRow;
+
+    /// Which modifications this wrapper supports.  Postgres raises an `ERROR` for any others,
+    /// without calling the wrapper.
+    const MODIFY: ForeignModify = ForeignModify::empty();
+
+    /// Estimate the cost of scanning `table`, for the planner
+    fn estimate(_table: &PgForeignTable) -> ForeignScanEstimate {
+        ForeignScanEstimate::default()
+    }
+
+    /// Start scanning `table`
+    fn begin(table: &PgForeignTable) -> Self;
+
+    /// The next row of the scan, or `None` when there are no more
+    fn iterate(&mut self) -> Option<Self::Row>;
+
+    /// Start the scan over from the first row
+    fn re_scan(&mut self);
+
+    /// Finish the scan
+    fn end(self) {}
+
+    /// Start an `INSERT`, `UPDATE`, or `DELETE` on `table`
+    fn begin_modify(_table: &PgForeignTable) -> Self {
+        panic!(
+            "{} does not implement `begin_modify`",
+            std::any::type_name::<Self>()
+        )
+    }
+
+    /// Insert `row`
+    fn insert(&mut self, _row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        panic!("{} does not support INSERT", std::any::type_name::<Self>())
+    }
+
+    /// Replace `old`, which is the row as it was scanned, with `new`
+    fn update(
+        &mut self,
+        _old: PgHeapTuple<'static, AllocatedByRust>,
+        _new: PgHeapTuple<'_, AllocatedByPostgres>,
+    ) {
+        panic!("{} does not support UPDATE", std::any::type_name::<Self>())
+    }
+
+    /// Delete `old`, which is the row as it was scanned.
+    ///
+    /// A `RETURNING` clause sees NULLs.
+    fn delete(&mut self, _old: PgHeapTuple<'static, AllocatedByRust>) {
+        panic!("{} does not support DELETE", std::any::type_name::<Self>())
+    }
+
+    /// Finish the `INSERT`, `UPDATE`, or `DELETE`
+    fn end_modify(self) {}
+}
+
+/// The callbacks for the foreign data wrapper `W`, which a handler function returns
+pub fn fdw_routine<W: ForeignDataWrapper>() -> PgBox<pg_sys::FdwRoutine> {
+    let mut routine = PgBox::<pg_sys::FdwRoutine>::alloc_node(pg_sys::NodeTag_T_FdwRoutine);
+
+    routine.GetForeignRelSize = Some(get_foreign_rel_size::<W>);
+    routine.GetForeignPaths = Some(get_foreign_paths::<W>);
+    routine.GetForeignPlan = Some(get_foreign_plan::<W>);
+    routine.BeginForeignScan = Some(begin_foreign_scan::<W>);
+    routine.IterateForeignScan = Some(iterate_foreign_scan::<W>);
+    routine.ReScanForeignScan = Some(re_scan_foreign_scan::<W>);
+    routine.EndForeignScan = Some(end_foreign_scan::<W>);
+
+    routine.IsForeignRelUpdatable = Some(is_foreign_rel_updatable::<W>);
+    if !W::MODIFY.is_empty() {
+        routine.AddForeignUpdateTargets = Some(add_foreign_update_targets);
+        routine.BeginForeignModify = Some(begin_foreign_modify::<W>);
+        routine.ExecForeignInsert = Some(exec_foreign_insert::<W>);
+        routine.ExecForeignUpdate = Some(exec_foreign_update::<W>);
+        routine.ExecForeignDelete = Some(exec_foreign_delete::<W>);
+        routine.EndForeignModify = Some(end_foreign_modify::<W>);
+
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        {
+            routine.BeginForeignInsert = Some(begin_foreign_insert::<W>);
+            routine.EndForeignInsert = Some(end_foreign_modify::<W>);
+        }
+    }
+
+    routine.into_pg_boxed()
+}
+
+unsafe extern "C" fn get_foreign_rel_size<W: ForeignDataWrapper>(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    foreigntableid: pg_sys::Oid,
+) {
+    crate::guard(|| {
+        // the parser already holds a lock on the table
+        let table = PgForeignTable::new(PgRelation::open(foreigntableid));
+        let estimate = PgMemoryContexts::CurrentMemoryContext.palloc_struct();
+        *estimate = W::estimate(&table);
+
+        (*baserel).rows = (*estimate).rows;
+        (*baserel).fdw_private = estimate as void_mut_ptr;
+    })
+}
+
+unsafe extern "C" fn get_foreign_paths<W: ForeignDataWrapper>(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    crate::guard(|| {
+        let estimate = *((*baserel).fdw_private as *mut ForeignScanEstimate);
+        let path = pg_sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(), // the default PathTarget
+            estimate.rows,
+            estimate.startup_cost,
+            estimate.total_cost,
+            std::ptr::null_mut(), // no pathkeys
+            std::ptr::null_mut(), // no outer rel
+            std::ptr::null_mut(), // no extra plan
+            std::ptr::null_mut(), // no fdw_private
+        );
+        pg_sys::add_path(baserel, path as *mut pg_sys::Path);
+    })
+}
+
+unsafe extern "C" fn get_foreign_plan<W: ForeignDataWrapper>(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+    _best_path: *mut pg_sys::ForeignPath,
+    tlist: *mut pg_sys::List,
+    scan_clauses: *mut pg_sys::List,
+    outer_plan: *mut pg_sys::Plan,
+) -> *mut pg_sys::ForeignScan {
+    crate::guard(|| {
+        // every clause is checked by the executor, as the wrapper can't evaluate any of them
+        let scan_clauses = pg_sys::extract_actual_clauses(scan_clauses, false);
+        pg_sys::make_foreignscan(
+            tlist,
+            scan_clauses,
+            (*baserel).relid,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            outer_plan,
+        )
+    })
+}
+
+/// What a foreign scan keeps in its `ForeignScanState`
+struct ForeignScan<W> {
+    wrapper: Option<W>,
+    rows: pg_sys::MemoryContext,
+}
+
+unsafe fn foreign_scan<'a, W>(node: *mut pg_sys::ForeignScanState) -> &'a mut ForeignScan<W> {
+    ((*node).fdw_state as *mut ForeignScan<W>)
+        .as_mut()
+        .expect("foreign scan was not started")
+}
+
+unsafe extern "C" fn begin_foreign_scan<W: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+    eflags: i32,
+) {
+    crate::guard(|| {
+        if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+            return;
+        }
+
+        let table = PgForeignTable::new(PgRelation::from_pg((*node).ss.ss_currentRelation));
+        let wrapper = W::begin(&table);
+        // a child of the query's context, which deletes it
+        let rows = pg_sys::AllocSetContextCreateExtended(
+            PgMemoryContexts::CurrentMemoryContext.value(),
+            b"pgx foreign scan rows\0".as_ptr() as *const c_char,
+            pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
+            pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
+            pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+        );
+        let scan = ForeignScan {
+            wrapper: Some(wrapper),
+            rows,
+        };
+        (*node).fdw_state =
+            PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(scan) as void_mut_ptr;
+    })
+}
+
+unsafe extern "C" fn iterate_foreign_scan<W: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    crate::guard(|| {
+        let scan = foreign_scan::<W>(node);
+        let slot = (*node).ss.ss_ScanTupleSlot;
+        pg_sys::pgx_ExecClearTuple(slot);
+        pg_sys::MemoryContextReset(scan.rows);
+
+        let wrapper = scan.wrapper.as_mut().expect("foreign scan has ended");
+        let previous = PgMemoryContexts::For(scan.rows).set_as_current();
+        if let Some(row) = wrapper.iterate() {
+            let relation = PgRelation::from_pg((*node).ss.ss_currentRelation);
+            let tupdesc = PgTupleDesc::from_relation(&relation);
+            let values = std::slice::from_raw_parts_mut((*slot).tts_values, tupdesc.len());
+            let nulls = std::slice::from_raw_parts_mut((*slot).tts_isnull, tupdesc.len());
+            nulls.fill(true);
+            row.into_foreign_row(&tupdesc, values, nulls);
+            pg_sys::ExecStoreVirtualTuple(slot);
+        }
+        previous.set_as_current();
+
+        // an empty slot ends the scan
+        slot
+    })
+}
+
+unsafe extern "C" fn re_scan_foreign_scan<W: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) {
+    crate::guard(|| {
+        foreign_scan::<W>(node)
+            .wrapper
+            .as_mut()
+            .expect("foreign scan has ended")
+            .re_scan()
+    })
+}
+
+unsafe extern "C" fn end_foreign_scan<W: ForeignDataWrapper>(node: *mut pg_sys::ForeignScanState) {
+    crate::guard(|| {
+        // there's no scan under EXPLAIN
+        if let Some(scan) = ((*node).fdw_state as *mut ForeignScan<W>).as_mut() {
+            if let Some(wrapper) = scan.wrapper.take() {
+                wrapper.end();
+            }
+        }
+    })
+}
+
+unsafe extern "C" fn is_foreign_rel_updatable<W: ForeignDataWrapper>(
+    _rel: pg_sys::Relation,
+) -> i32 {
+    W::MODIFY.bits()
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets(
+    parsetree: *mut pg_sys::Query,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    // `UPDATE` and `DELETE` get the whole old row, as we have no other way to identify it
+    let var = pg_sys::makeVar(
+        (*parsetree).resultRelation as pg_sys::Index,
+        pg_sys::InvalidAttrNumber as pg_sys::AttrNumber,
+        (*(*target_relation).rd_rel).reltype,
+        -1,
+        pg_sys::InvalidOid,
+        0,
+    );
+    let resno = PgList::<pg_sys::TargetEntry>::from_pg((*parsetree).targetList).len() + 1;
+    let entry = pg_sys::makeTargetEntry(
+        var as *mut pg_sys::Expr,
+        resno as pg_sys::AttrNumber,
+        PgMemoryContexts::CurrentMemoryContext.pstrdup("wholerow"),
+        true,
+    );
+    (*parsetree).targetList = pg_sys::lappend((*parsetree).targetList, entry as void_mut_ptr);
+}
+
+#[cfg(feature = "pg14")]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets(
+    root: *mut pg_sys::PlannerInfo,
+    rtindex: pg_sys::Index,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    // `UPDATE` and `DELETE` get the whole old row, as we have no other way to identify it
+    let var = pg_sys::makeVar(
+        rtindex,
+        pg_sys::InvalidAttrNumber as pg_sys::AttrNumber,
+        (*(*target_relation).rd_rel).reltype,
+        -1,
+        pg_sys::InvalidOid,
+        0,
+    );
+    pg_sys::add_row_identity_var(root, var, rtindex, WHOLEROW.as_ptr() as *const c_char);
+}
+
+/// What a modification keeps in its `ResultRelInfo`
+struct ForeignModification<W> {
+    wrapper: Option<W>,
+    wholerow: pg_sys::AttrNumber,
+}
+
+unsafe fn foreign_modification<'a, W>(
+    rinfo: *mut pg_sys::ResultRelInfo,
+) -> &'a mut ForeignModification<W> {
+    ((*rinfo).ri_FdwState as *mut ForeignModification<W>)
+        .as_mut()
+        .expect("foreign modification was not started")
+}
+
+unsafe fn start_foreign_modification<W: ForeignDataWrapper>(
+    rinfo: *mut pg_sys::ResultRelInfo,
+    wholerow: pg_sys::AttrNumber,
+) {
+    let table = PgForeignTable::new(PgRelation::from_pg((*rinfo).ri_RelationDesc));
+    let modification = ForeignModification {
+        wrapper: Some(W::begin_modify(&table)),
+        wholerow,
+    };
+    (*rinfo).ri_FdwState = PgMemoryContexts::CurrentMemoryContext
+        .leak_and_drop_on_delete(modification) as void_mut_ptr;
+}
+
+unsafe extern "C" fn begin_foreign_modify<W: ForeignDataWrapper>(
+    mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    _fdw_private: *mut pg_sys::List,
+    #[allow(unused_variables)] subplan_index: i32,
+    eflags: i32,
+) {
+    crate::guard(|| {
+        if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+            return;
+        }
+
+        let wholerow = if (*mtstate).operation == pg_sys::CmdType_CMD_INSERT {
+            pg_sys::InvalidAttrNumber as pg_sys::AttrNumber
+        } else {
+            #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+            let subplan = (**(*mtstate).mt_plans.add(subplan_index as usize)).plan;
+            #[cfg(feature = "pg14")]
+            let subplan = (*(*mtstate).ps.lefttree).plan;
+
+            let attno = pg_sys::ExecFindJunkAttributeInTlist(
+                (*subplan).targetlist,
+                WHOLEROW.as_ptr() as *const c_char,
+            );
+            if attno == pg_sys::InvalidAttrNumber as pg_sys::AttrNumber {
+                panic!("could not find the \"wholerow\" junk attribute");
+            }
+            attno
+        };
+        start_foreign_modification::<W>(rinfo, wholerow);
+    })
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe extern "C" fn begin_foreign_insert<W: ForeignDataWrapper>(
+    _mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+) {
+    // for `COPY` and rows routed to a foreign partition
+    crate::guard(|| {
+        start_foreign_modification::<W>(rinfo, pg_sys::InvalidAttrNumber as pg_sys::AttrNumber)
+    })
+}
+
+/// The old row of an `UPDATE` or `DELETE`, from the junk attribute that
+/// `add_foreign_update_targets()` asked for
+unsafe fn old_row(
+    plan_slot: *mut pg_sys::TupleTableSlot,
+    wholerow: pg_sys::AttrNumber,
+) -> PgHeapTuple<'static, AllocatedByRust> {
+    let mut is_null = false;
+    let datum = pg_sys::pgx_ExecGetJunkAttribute(plan_slot, wholerow, &mut is_null);
+    if is_null {
+        panic!("the \"wholerow\" junk attribute is NULL");
+    }
+    PgHeapTuple::from_composite_datum(datum)
+}
+
+unsafe extern "C" fn exec_foreign_insert<W: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    _plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    crate::guard(|| {
+        let modification = foreign_modification::<W>(rinfo);
+        let relation = PgRelation::from_pg((*rinfo).ri_RelationDesc);
+        let row = PgHeapTuple::from_heap_tuple(
+            PgTupleDesc::from_relation(&relation),
+            pg_sys::pgx_ExecCopySlotHeapTuple(slot),
+        );
+        modification
+            .wrapper
+            .as_mut()
+            .expect("foreign modification has ended")
+            .insert(row);
+        slot
+    })
+}
+
+unsafe extern "C" fn exec_foreign_update<W: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    crate::guard(|| {
+        let modification = foreign_modification::<W>(rinfo);
+        let old = old_row(plan_slot, modification.wholerow);
+        let relation = PgRelation::from_pg((*rinfo).ri_RelationDesc);
+        let new = PgHeapTuple::from_heap_tuple(
+            PgTupleDesc::from_relation(&relation),
+            pg_sys::pgx_ExecCopySlotHeapTuple(slot),
+        );
+        modification
+            .wrapper
+            .as_mut()
+            .expect("foreign modification has ended")
+            .update(old, new);
+        slot
+    })
+}
+
+unsafe extern "C" fn exec_foreign_delete<W: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    crate::guard(|| {
+        let modification = foreign_modification::<W>(rinfo);
+        let old = old_row(plan_slot, modification.wholerow);
+        modification
+            .wrapper
+            .as_mut()
+            .expect("foreign modification has ended")
+            .delete(old);
+        slot
+    })
+}
+
+unsafe extern "C" fn end_foreign_modify<W: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+) {
+    crate::guard(|| {
+        // there's no modification under EXPLAIN
+        if let Some(modification) = ((*rinfo).ri_FdwState as *mut ForeignModification<W>).as_mut() {
+            if let Some(wrapper) = modification.wrapper.take() {
+                wrapper.end_modify();
+            }
+        }
+    })
+}
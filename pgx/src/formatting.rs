@@ -0,0 +1,170 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Formatting values with templates, as SQL's `to_char()` does, and the locale conventions it uses
+use crate::{
+    direct_function_call_with_collation, pg_sys, Interval, IntoDatum, Numeric, Timestamp,
+    TimestampWithTimeZone,
+};
+use std::ffi::CStr;
+
+/// `DEFAULT_COLLATION_OID`, the database's collation, which the `TM` prefix of date and time
+/// templates translates names with
+const DEFAULT_COLLATION_OID: pg_sys::Oid = 100;
+
+/// Formatting a value as text with a template, as SQL's `to_char()` does.
+///
+/// These call the same functions as `to_char()`, so the output is identical to SQL's, including
+/// the patterns which depend on the server's locale, such as `G` for the group separator, `D` for
+/// the decimal point and `L` for the currency symbol from `lc_numeric` and `lc_monetary`, and the
+/// `TM` prefix which translates day and month names with `lc_time`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let total = Numeric("1234567.891".to_string());
+/// // such as `$ 1,234,567.89` with `lc_monetary` and `lc_numeric` of `en_US`
+/// let report = total.to_char("L9G999G999D99").unwrap();
+/// assert_eq!(42.to_char("FM000").unwrap(), "042");
+/// ```
+///
+/// Templates are parsed again for each call, as they are by SQL, though Postgres keeps the most
+/// recently used ones parsed.
+pub trait ToChar {
+    /// `to_char(self, template)`, or `None` where that's `NULL`, which it is for `infinity` and
+    /// `-infinity` timestamps
+    fn to_char(&self, template: &str) -> Option<String>;
+}
+
+fn to_char(
+    func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+    value: Option<pg_sys::Datum>,
+    template: &str,
+) -> Option<String> {
+    unsafe {
+        direct_function_call_with_collation::<String>(
+            func,
+            DEFAULT_COLLATION_OID,
+            vec![value, template.into_datum()],
+        )
+    }
+}
+
+impl ToChar for i16 {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::int4_to_char, (*self as i32).into_datum(), template)
+    }
+}
+
+impl ToChar for i32 {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::int4_to_char, (*self).into_datum(), template)
+    }
+}
+
+impl ToChar for i64 {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::int8_to_char, (*self).into_datum(), template)
+    }
+}
+
+impl ToChar for f32 {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::float4_to_char, (*self).into_datum(), template)
+    }
+}
+
+impl ToChar for f64 {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::float8_to_char, (*self).into_datum(), template)
+    }
+}
+
+impl ToChar for Numeric {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::numeric_to_char, self.clone().into_datum(), template)
+    }
+}
+
+impl ToChar for Timestamp {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::timestamp_to_char, (*self).into_datum(), template)
+    }
+}
+
+/// Formatted in the session's `TimeZone`
+impl ToChar for TimestampWithTimeZone {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::timestamptz_to_char, (*self).into_datum(), template)
+    }
+}
+
+impl ToChar for Interval {
+    fn to_char(&self, template: &str) -> Option<String> {
+        to_char(pg_sys::interval_to_char, (*self).into_datum(), template)
+    }
+}
+
+/// The conventions of the server's `lc_numeric` and `lc_monetary` locales, which `to_char()` uses
+/// for its locale-dependent patterns, as Postgres reads them with `localeconv()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleConventions {
+    /// The decimal point of numbers, `to_char()`'s `D`
+    pub decimal_point: String,
+    /// The separator between groups of digits of numbers, `to_char()`'s `G`
+    pub thousands_sep: String,
+    /// The local currency symbol, `to_char()`'s `L`
+    pub currency_symbol: String,
+    /// The international currency symbol, such as `USD `
+    pub int_curr_symbol: String,
+    /// The decimal point of amounts of money
+    pub mon_decimal_point: String,
+    /// The separator between groups of digits of amounts of money
+    pub mon_thousands_sep: String,
+    /// The sign of positive amounts of money
+    pub positive_sign: String,
+    /// The sign of negative amounts of money, `to_char()`'s `S` and `MI`
+    pub negative_sign: String,
+    /// How many fractional digits amounts of money have, if the locale says
+    pub frac_digits: Option<u8>,
+}
+
+impl LocaleConventions {
+    /// The conventions of the current `lc_numeric` and `lc_monetary`, which Postgres reads again
+    /// after either changes
+    pub fn current() -> Self {
+        unsafe {
+            let lconv = &*pg_sys::PGLC_localeconv();
+            LocaleConventions {
+                decimal_point: lconv_string(lconv.decimal_point),
+                thousands_sep: lconv_string(lconv.thousands_sep),
+                currency_symbol: lconv_string(lconv.currency_symbol),
+                int_curr_symbol: lconv_string(lconv.int_curr_symbol),
+                mon_decimal_point: lconv_string(lconv.mon_decimal_point),
+                mon_thousands_sep: lconv_string(lconv.mon_thousands_sep),
+                positive_sign: lconv_string(lconv.positive_sign),
+                negative_sign: lconv_string(lconv.negative_sign),
+                // `CHAR_MAX` when the locale doesn't say
+                frac_digits: if lconv.frac_digits == std::os::raw::c_char::MAX {
+                    None
+                } else {
+                    Some(lconv.frac_digits as u8)
+                },
+            }
+        }
+    }
+}
+
+unsafe fn lconv_string(s: *const std::os::raw::c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
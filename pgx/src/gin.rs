@@ -0,0 +1,276 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! GIN index support for custom types, through the [`GinSupport`] trait and
+//! `#[derive(PostgresGinOps)]`
+use crate::{pg_sys, FromDatum, Internal, IntoDatum};
+use std::cmp::Ordering;
+
+/// Whether an indexed value can match a query, from [`GinSupport::consistent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GinMatch {
+    /// The value doesn't match the query
+    No,
+    /// The value matches the query
+    Yes,
+    /// The value may match the query, and Postgres has to check with the operator itself
+    Recheck,
+}
+
+/// Whether an indexed key matches a partial key of a query, from [`GinSupport::compare_partial`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GinPartialMatch {
+    /// The key matches
+    Match,
+    /// The key doesn't match, but a later one may
+    NoMatch,
+    /// Neither the key nor any after it in the key's order match
+    Stop,
+}
+
+/// Which values a query can match when none of its keys are found, from [`GinQuery`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GinSearchMode {
+    /// Only values with at least one of the query's keys
+    Default,
+    /// Values with one of the query's keys, and those with no keys at all
+    IncludeEmpty,
+    /// Every value which isn't null, as for a query with no keys that have to be there
+    All,
+    /// Every value, null or not
+    Everything,
+}
+
+impl GinSearchMode {
+    fn as_i32(self) -> i32 {
+        (match self {
+            GinSearchMode::Default => pg_sys::GIN_SEARCH_MODE_DEFAULT,
+            GinSearchMode::IncludeEmpty => pg_sys::GIN_SEARCH_MODE_INCLUDE_EMPTY,
+            GinSearchMode::All => pg_sys::GIN_SEARCH_MODE_ALL,
+            GinSearchMode::Everything => pg_sys::GIN_SEARCH_MODE_EVERYTHING,
+        }) as i32
+    }
+}
+
+/// The keys of a query, from [`GinSupport::extract_query`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GinQuery<K> {
+    /// The keys to look up in the index, which are `None` to look for null keys
+    pub keys: Vec<Option<K>>,
+    /// Which of the `keys` match indexed keys by [`GinSupport::compare_partial`] rather than by
+    /// equality, such as a prefix.  Keys past its end don't.
+    pub partial_match: Vec<bool>,
+    /// Which values match when none of the keys are found
+    pub search_mode: GinSearchMode,
+}
+
+impl<K> GinQuery<K> {
+    /// A query for values with any of `keys`
+    pub fn new(keys: Vec<Option<K>>) -> Self {
+        GinQuery {
+            keys,
+            partial_match: Vec::new(),
+            search_mode: GinSearchMode::Default,
+        }
+    }
+
+    /// A query for values with keys partially matching any of `keys`
+    pub fn partial(keys: Vec<Option<K>>) -> Self {
+        GinQuery {
+            partial_match: vec![true; keys.len()],
+            ..GinQuery::new(keys)
+        }
+    }
+}
+
+/// The support functions of a GIN operator class for a type.
+///
+/// GIN indexes are inverted indexes, which map each of the keys found in the indexed values to the
+/// values they were found in, as the words of a document.  A value is split into its keys with
+/// [`GinSupport::extract_value()`], and a query into the keys to look up with
+/// [`GinSupport::extract_query()`].  The values found under those keys are then checked against
+/// the query with [`GinSupport::consistent()`].
+///
+/// `#[derive(PostgresGinOps)]` generates the functions Postgres calls, which take and return
+/// `internal` pointers to its own arrays, from the trait's, as well as the operator class:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PostgresType, PostgresGinOps)]
+/// #[gin(default, storage = "text", operators(1 = "&&"), requires = [tags_overlap])]
+/// pub struct Tags(Vec<String>);
+///
+/// #[pg_operator(immutable, parallel_safe, symbol = "&&", commutator = "&&")]
+/// fn tags_overlap(left: Tags, right: Tags) -> bool {
+///     left.0.iter().any(|tag| right.0.contains(tag))
+/// }
+///
+/// impl GinSupport for Tags {
+///     type Key = String;
+///
+///     fn extract_value(value: Tags) -> Vec<Option<String>> {
+///         value.0.into_iter().map(Some).collect()
+///     }
+///
+///     fn extract_query(query: Tags, _strategy: u16) -> GinQuery<String> {
+///         GinQuery::new(query.0.into_iter().map(Some).collect())
+///     }
+///
+///     fn consistent(check: &[bool], _query: &Tags, _strategy: u16) -> GinMatch {
+///         if check.contains(&true) {
+///             GinMatch::Yes
+///         } else {
+///             GinMatch::No
+///         }
+///     }
+/// }
+/// ```
+pub trait GinSupport: FromDatum + IntoDatum {
+    /// The keys stored in the index, which are compared by their [`Ord`].  A type other than
+    /// `Self` has to be given as the class's `storage`.
+    type Key: FromDatum + IntoDatum + Ord;
+
+    /// The keys to index `value` under, which are `None` for null keys
+    fn extract_value(value: Self) -> Vec<Option<Self::Key>>;
+
+    /// The keys to look up for `query`, with the operator of the class with the `strategy` number
+    fn extract_query(query: Self, strategy: u16) -> GinQuery<Self::Key>;
+
+    /// Does a value match `query`, when `check` is whether it has each of the keys
+    /// `extract_query()` returned, in the same order?
+    fn consistent(check: &[bool], query: &Self, strategy: u16) -> GinMatch;
+
+    /// Does `key` match the `partial` key of a query, which `extract_query()` marked as a partial
+    /// match?  Indexed keys are compared in their order, starting from the first which isn't
+    /// less than `partial`, until this returns [`GinPartialMatch::Stop`].
+    fn compare_partial(_partial: &Self::Key, _key: &Self::Key, _strategy: u16) -> GinPartialMatch {
+        panic!(
+            "{} has no partial matches, so extract_query() can't ask for them",
+            std::any::type_name::<Self>()
+        )
+    }
+}
+
+unsafe fn key_of<T: GinSupport>(key: Internal) -> T::Key {
+    let datum = key.unwrap().expect("the GIN key is null");
+    T::Key::from_datum(datum, false, pg_sys::InvalidOid).expect("the GIN key is null")
+}
+
+/// `keys` as palloc'd arrays of their datums and of whether each is null, as GIN wants them
+unsafe fn palloc_keys<K: IntoDatum>(keys: Vec<Option<K>>) -> (*mut pg_sys::Datum, *mut bool) {
+    let len = keys.len().max(1);
+    let datums = pg_sys::palloc0(len * std::mem::size_of::<pg_sys::Datum>()) as *mut pg_sys::Datum;
+    let nulls = pg_sys::palloc0(len * std::mem::size_of::<bool>()) as *mut bool;
+    for (i, key) in keys.into_iter().enumerate() {
+        match key.and_then(IntoDatum::into_datum) {
+            Some(datum) => *datums.add(i) = datum,
+            None => *nulls.add(i) = true,
+        }
+    }
+    (datums, nulls)
+}
+
+/// The `compare` support function, number 1
+#[doc(hidden)]
+pub unsafe fn gin_compare<T: GinSupport>(left: Internal, right: Internal) -> i32 {
+    match key_of::<T>(left).cmp(&key_of::<T>(right)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+/// The `extractValue` support function, number 2
+#[doc(hidden)]
+pub unsafe fn gin_extract_value<T: GinSupport>(
+    value: T,
+    nkeys: Internal,
+    null_flags: Internal,
+) -> Internal {
+    let keys = T::extract_value(value);
+    *nkeys.get_mut::<i32>().expect("the GIN key count is null") = keys.len() as i32;
+    let (datums, nulls) = palloc_keys(keys);
+    if let Some(null_flags) = null_flags.get_mut::<*mut bool>() {
+        *null_flags = nulls;
+    }
+    Internal::from(Some(datums as pg_sys::Datum))
+}
+
+/// The `extractQuery` support function, number 3
+#[doc(hidden)]
+pub unsafe fn gin_extract_query<T: GinSupport>(
+    query: T,
+    nkeys: Internal,
+    strategy: i16,
+    partial_matches: Internal,
+    null_flags: Internal,
+    search_mode: Internal,
+) -> Internal {
+    let query = T::extract_query(query, strategy as u16);
+    let len = query.keys.len();
+    *nkeys.get_mut::<i32>().expect("the GIN key count is null") = len as i32;
+    if query.partial_match.contains(&true) {
+        let partial = pg_sys::palloc0(len.max(1) * std::mem::size_of::<bool>()) as *mut bool;
+        for (i, is_partial) in query.partial_match.iter().take(len).enumerate() {
+            *partial.add(i) = *is_partial;
+        }
+        if let Some(partial_matches) = partial_matches.get_mut::<*mut bool>() {
+            *partial_matches = partial;
+        }
+    }
+    if let Some(search_mode) = search_mode.get_mut::<i32>() {
+        *search_mode = query.search_mode.as_i32();
+    }
+
+    let (datums, nulls) = palloc_keys(query.keys);
+    if let Some(null_flags) = null_flags.get_mut::<*mut bool>() {
+        *null_flags = nulls;
+    }
+    Internal::from(Some(datums as pg_sys::Datum))
+}
+
+/// The `consistent` support function, number 4
+#[doc(hidden)]
+pub unsafe fn gin_consistent<T: GinSupport>(
+    check: Internal,
+    strategy: i16,
+    query: T,
+    nkeys: i32,
+    recheck: Internal,
+) -> bool {
+    let check = match check.unwrap() {
+        Some(check) if nkeys > 0 => {
+            std::slice::from_raw_parts(check as *const bool, nkeys as usize)
+        }
+        _ => &[],
+    };
+    let found = T::consistent(check, &query, strategy as u16);
+    if let Some(recheck) = recheck.get_mut::<bool>() {
+        *recheck = found == GinMatch::Recheck;
+    }
+    found != GinMatch::No
+}
+
+/// The `comparePartial` support function, number 5
+#[doc(hidden)]
+pub unsafe fn gin_compare_partial<T: GinSupport>(
+    partial: Internal,
+    key: Internal,
+    strategy: i16,
+) -> i32 {
+    let partial = key_of::<T>(partial);
+    let key = key_of::<T>(key);
+    match T::compare_partial(&partial, &key, strategy as u16) {
+        GinPartialMatch::Match => 0,
+        GinPartialMatch::NoMatch => -1,
+        GinPartialMatch::Stop => 1,
+    }
+}
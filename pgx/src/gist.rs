@@ -0,0 +1,280 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! GiST index support for custom types, through the [`GistSupport`] trait and
+//! `#[derive(PostgresGistOps)]`
+use crate::{pg_sys, FromDatum, Internal, IntoDatum, PgBox};
+
+/// The offset of the first entry of a page, as GiST numbers them when splitting one
+const FIRST_OFFSET_NUMBER: pg_sys::OffsetNumber = 1;
+
+/// Whether a key can match a query, from [`GistSupport::consistent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GistMatch {
+    /// Nothing under the key matches the query
+    No,
+    /// The value matches the query, or for a key on an inner page, something under it may
+    Yes,
+    /// The value may match the query, and Postgres has to check with the operator itself
+    Recheck,
+}
+
+/// The support functions of a GiST operator class for a type.
+///
+/// GiST indexes are trees of keys, each of which covers every value stored under it, as a
+/// bounding box covers the points inside it.  A value is turned into the key stored in a leaf with
+/// [`GistSupport::compress()`], and the keys of a page are combined into the key of the page above
+/// it with [`GistSupport::union()`].
+///
+/// `#[derive(PostgresGistOps)]` generates the functions Postgres calls, which take and return
+/// `internal` pointers to its own structures, from the trait's, as well as the operator class:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(PartialEq, Serialize, Deserialize, PostgresType, PostgresGistOps)]
+/// #[gist(default, operators(3 = "&&"), requires = [span_overlaps])]
+/// pub struct Span {
+///     lo: i32,
+///     hi: i32,
+/// }
+///
+/// #[pg_operator(immutable, parallel_safe, symbol = "&&", commutator = "&&")]
+/// fn span_overlaps(left: Span, right: Span) -> bool {
+///     left.lo <= right.hi && right.lo <= left.hi
+/// }
+///
+/// impl GistSupport for Span {
+///     type Key = Span;
+///
+///     fn compress(value: Span) -> Span {
+///         value
+///     }
+///
+///     fn consistent(key: &Span, query: &Span, _strategy: u16, _is_leaf: bool) -> GistMatch {
+///         if key.lo <= query.hi && query.lo <= key.hi {
+///             GistMatch::Yes
+///         } else {
+///             GistMatch::No
+///         }
+///     }
+///
+///     fn union(keys: Vec<Span>) -> Span {
+///         Span {
+///             lo: keys.iter().map(|key| key.lo).min().unwrap(),
+///             hi: keys.iter().map(|key| key.hi).max().unwrap(),
+///         }
+///     }
+///
+///     fn penalty(original: &Span, new: &Span) -> f32 {
+///         let grown = (original.lo - new.lo).max(0) + (new.hi - original.hi).max(0);
+///         grown as f32
+///     }
+///
+///     fn picksplit(keys: &[Span]) -> (Vec<usize>, Vec<usize>) {
+///         let mut order = (0..keys.len()).collect::<Vec<_>>();
+///         order.sort_by_key(|i| keys[*i].lo + keys[*i].hi);
+///         let right = order.split_off(order.len() / 2);
+///         (order, right)
+///     }
+/// }
+/// ```
+pub trait GistSupport: FromDatum + IntoDatum {
+    /// The key stored in the index, which is often the type itself.  A different type has to be
+    /// given as the class's `storage` as well.
+    type Key: FromDatum + IntoDatum + PartialEq;
+
+    /// The key stored in a leaf of the index for `value`
+    fn compress(value: Self) -> Self::Key;
+
+    /// Can `query` match the values under `key`, for the operator of the class with the `strategy`
+    /// number?  A key in a leaf, `is_leaf`, is of a single value.
+    fn consistent(key: &Self::Key, query: &Self, strategy: u16, is_leaf: bool) -> GistMatch;
+
+    /// The key covering all of `keys`, of which there's at least one
+    fn union(keys: Vec<Self::Key>) -> Self::Key;
+
+    /// The cost of putting `new` under `original`, which is usually how much `original` would have
+    /// to grow to cover it
+    fn penalty(original: &Self::Key, new: &Self::Key) -> f32;
+
+    /// Split the `keys` of a full page between two pages, by their indexes in `keys`.  Each key
+    /// must be on exactly one of them.
+    fn picksplit(keys: &[Self::Key]) -> (Vec<usize>, Vec<usize>);
+
+    /// Are two keys the same?
+    fn same(left: &Self::Key, right: &Self::Key) -> bool {
+        left == right
+    }
+}
+
+unsafe fn entry_of<'a>(entry: &Internal) -> &'a mut pg_sys::GISTENTRY {
+    entry
+        .get_mut::<pg_sys::GISTENTRY>()
+        .expect("the GiST entry is null")
+}
+
+unsafe fn entries_of<'a>(entryvec: &Internal) -> &'a [pg_sys::GISTENTRY] {
+    let entryvec = entryvec
+        .get::<pg_sys::GistEntryVector>()
+        .expect("the GiST entry vector is null");
+    entryvec.vector.as_slice(entryvec.n as usize)
+}
+
+unsafe fn key_of<T: GistSupport>(datum: pg_sys::Datum) -> T::Key {
+    T::Key::from_datum(datum, false, pg_sys::InvalidOid).expect("the GiST key is null")
+}
+
+/// Is the entry on a leaf page, as `GIST_LEAF()`?
+unsafe fn is_leaf(entry: &pg_sys::GISTENTRY) -> bool {
+    let header = entry.page as *mut pg_sys::PageHeaderData;
+    let opaque = entry.page.add((*header).pd_special as usize) as *mut pg_sys::GISTPageOpaqueData;
+    (*opaque).flags as u32 & pg_sys::F_LEAF != 0
+}
+
+/// The `consistent` support function, number 1
+#[doc(hidden)]
+pub unsafe fn gist_consistent<T: GistSupport>(
+    entry: Internal,
+    query: T,
+    strategy: i16,
+    recheck: Internal,
+) -> bool {
+    let entry = entry_of(&entry);
+    let found = T::consistent(
+        &key_of::<T>(entry.key),
+        &query,
+        strategy as u16,
+        is_leaf(entry),
+    );
+    if let Some(recheck) = recheck.get_mut::<bool>() {
+        *recheck = found == GistMatch::Recheck;
+    }
+    found != GistMatch::No
+}
+
+/// The `union` support function, number 2
+#[doc(hidden)]
+pub unsafe fn gist_union<T: GistSupport>(entryvec: Internal) -> Internal {
+    let keys = entries_of(&entryvec)
+        .iter()
+        .map(|entry| key_of::<T>(entry.key))
+        .collect();
+    Internal::from(T::union(keys).into_datum())
+}
+
+/// The `compress` support function, number 3, which turns the values of new leaf entries into keys
+#[doc(hidden)]
+pub unsafe fn gist_compress<T: GistSupport>(entry: Internal) -> Internal {
+    let original = entry_of(&entry);
+    if !original.leafkey {
+        return entry;
+    }
+
+    let value =
+        T::from_datum(original.key, false, pg_sys::InvalidOid).expect("the GiST value is null");
+    let mut compressed = PgBox::<pg_sys::GISTENTRY>::alloc0();
+    compressed.key = T::compress(value)
+        .into_datum()
+        .expect("the compressed GiST key is null");
+    compressed.rel = original.rel;
+    compressed.page = original.page;
+    compressed.offset = original.offset;
+    compressed.leafkey = false;
+    Internal::from(Some(compressed.into_pg() as pg_sys::Datum))
+}
+
+/// The `decompress` support function, number 4, which leaves keys as they are, as they're read
+/// from their datums as they're used
+#[doc(hidden)]
+pub unsafe fn gist_decompress<T: GistSupport>(entry: Internal) -> Internal {
+    entry
+}
+
+/// The `penalty` support function, number 5
+#[doc(hidden)]
+pub unsafe fn gist_penalty<T: GistSupport>(
+    original: Internal,
+    new: Internal,
+    penalty: Internal,
+) -> Internal {
+    let original = key_of::<T>(entry_of(&original).key);
+    let new = key_of::<T>(entry_of(&new).key);
+    *penalty.get_mut::<f32>().expect("the GiST penalty is null") = T::penalty(&original, &new);
+    penalty
+}
+
+/// The `picksplit` support function, number 6
+#[doc(hidden)]
+pub unsafe fn gist_picksplit<T: GistSupport>(entryvec: Internal, splitvec: Internal) -> Internal {
+    let keys = entries_of(&entryvec)[FIRST_OFFSET_NUMBER as usize..]
+        .iter()
+        .map(|entry| key_of::<T>(entry.key))
+        .collect::<Vec<_>>();
+    let (left, right) = T::picksplit(&keys);
+    if left.len() + right.len() != keys.len() {
+        panic!(
+            "GistSupport::picksplit() split {} keys into {} and {}",
+            keys.len(),
+            left.len(),
+            right.len()
+        );
+    }
+
+    let mut keys = keys.into_iter().map(Some).collect::<Vec<_>>();
+    let mut take_side = |side: Vec<usize>| {
+        let offsets =
+            pg_sys::palloc(side.len().max(1) * std::mem::size_of::<pg_sys::OffsetNumber>())
+                as *mut pg_sys::OffsetNumber;
+        let mut side_keys = Vec::with_capacity(side.len());
+        for (i, index) in side.into_iter().enumerate() {
+            let key = keys
+                .get_mut(index)
+                .and_then(Option::take)
+                .unwrap_or_else(|| panic!("GistSupport::picksplit() split key {} twice", index));
+            side_keys.push(key);
+            *offsets.add(i) = index as pg_sys::OffsetNumber + FIRST_OFFSET_NUMBER;
+        }
+        let union = if side_keys.is_empty() {
+            0
+        } else {
+            T::union(side_keys)
+                .into_datum()
+                .expect("the GiST union is null")
+        };
+        (offsets, union)
+    };
+
+    let split = splitvec
+        .get_mut::<pg_sys::GIST_SPLITVEC>()
+        .expect("the GiST split vector is null");
+    split.spl_nleft = left.len() as _;
+    let (offsets, union) = take_side(left);
+    split.spl_left = offsets;
+    split.spl_ldatum = union;
+    split.spl_nright = right.len() as _;
+    let (offsets, union) = take_side(right);
+    split.spl_right = offsets;
+    split.spl_rdatum = union;
+    splitvec
+}
+
+/// The `same` support function, number 7
+#[doc(hidden)]
+pub unsafe fn gist_same<T: GistSupport>(
+    left: Internal,
+    right: Internal,
+    result: Internal,
+) -> Internal {
+    let left = key_of::<T>(left.unwrap().expect("the GiST key is null"));
+    let right = key_of::<T>(right.unwrap().expect("the GiST key is null"));
+    *result.get_mut::<bool>().expect("the GiST result is null") = T::same(&left, &right);
+    result
+}
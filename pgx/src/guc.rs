@@ -8,9 +8,46 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe interface into Postgres' Configuration System (GUC)
-use crate::{pg_sys, PgMemoryContexts};
+//!
+//! Settings are `static` [`GucSetting`]s, defined through the [`GucRegistry`] from `_PG_init()`.
+//! Postgres writes a setting's new value straight into its `GucSetting` whenever it changes, so
+//! reading one with `.get()` is as cheap as reading any other static.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static MAX_DOGS: GucSetting<i32> = GucSetting::new(8);
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     GucRegistry::define_int_guc_with_flags(
+//!         "dogs.max_dogs",
+//!         "The maximum number of dogs",
+//!         "Adopting more dogs than this raises an ERROR",
+//!         &MAX_DOGS,
+//!         0,
+//!         100,
+//!         GucContext::Userset,
+//!         GucFlags::NOT_IN_SAMPLE,
+//!     );
+//! }
+//! ```
+//!
+//! Any other setting, including Postgres' own, can be read by name with [`Guc`], which also
+//! converts memory and time settings from their units:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! let work_mem_kb: Option<i32> = Guc::get("work_mem");
+//! let work_mem_bytes: Option<u64> = Guc::get_bytes("work_mem");
+//! let timeout: Option<std::time::Duration> = Guc::get_duration("statement_timeout");
+//! ```
+use crate::callbacks::catch_error_message;
+use crate::{elog, pg_sys, PgLogLevel, PgMemoryContexts};
 use std::cell::Cell;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::time::Duration;
 
 pub enum GucContext {
     /// cannot be set by the user at all, but only through
@@ -50,6 +87,55 @@ pub enum GucContext {
     Userset = pg_sys::GucContext_PGC_USERSET as isize,
 }
 
+bitflags! {
+    /// Flags describing a GUC, such as its unit and where it may be set
+    #[derive(Default)]
+    pub struct GucFlags: i32 {
+        /// the value is a comma separated list
+        const LIST_INPUT = pg_sys::GUC_LIST_INPUT as i32;
+        /// double-quote list elements when showing the value
+        const LIST_QUOTE = pg_sys::GUC_LIST_QUOTE as i32;
+        /// exclude from `SHOW ALL`
+        const NO_SHOW_ALL = pg_sys::GUC_NO_SHOW_ALL as i32;
+        /// exclude from `RESET ALL`
+        const NO_RESET_ALL = pg_sys::GUC_NO_RESET_ALL as i32;
+        /// report changes to the client, as `ParameterStatus` messages
+        const REPORT = pg_sys::GUC_REPORT as i32;
+        /// not in `postgresql.conf.sample`
+        const NOT_IN_SAMPLE = pg_sys::GUC_NOT_IN_SAMPLE as i32;
+        /// can't be set in `postgresql.conf`
+        const DISALLOW_IN_FILE = pg_sys::GUC_DISALLOW_IN_FILE as i32;
+        /// only superusers may see the value
+        const SUPERUSER_ONLY = pg_sys::GUC_SUPERUSER_ONLY as i32;
+        /// limit the value to `NAMEDATALEN - 1` bytes
+        const IS_NAME = pg_sys::GUC_IS_NAME as i32;
+        /// can't be set while in a security restricted operation
+        const NOT_WHILE_SEC_REST = pg_sys::GUC_NOT_WHILE_SEC_REST as i32;
+        /// can't be set with `ALTER SYSTEM`
+        const DISALLOW_IN_AUTO_FILE = pg_sys::GUC_DISALLOW_IN_AUTO_FILE as i32;
+        /// the value is in kilobytes
+        const UNIT_KB = pg_sys::GUC_UNIT_KB as i32;
+        /// the value is in blocks of `BLCKSZ`
+        const UNIT_BLOCKS = pg_sys::GUC_UNIT_BLOCKS as i32;
+        /// the value is in WAL blocks of `XLOG_BLCKSZ`
+        const UNIT_XBLOCKS = pg_sys::GUC_UNIT_XBLOCKS as i32;
+        /// the value is in megabytes
+        const UNIT_MB = pg_sys::GUC_UNIT_MB as i32;
+        /// the value is in bytes
+        #[cfg(not(feature = "pg10"))]
+        const UNIT_BYTE = pg_sys::GUC_UNIT_BYTE as i32;
+        /// the value is in milliseconds
+        const UNIT_MS = pg_sys::GUC_UNIT_MS as i32;
+        /// the value is in seconds
+        const UNIT_S = pg_sys::GUC_UNIT_S as i32;
+        /// the value is in minutes
+        const UNIT_MIN = pg_sys::GUC_UNIT_MIN as i32;
+        /// include in `EXPLAIN (SETTINGS)` output when not the default
+        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+        const EXPLAIN = pg_sys::GUC_EXPLAIN as i32;
+    }
+}
+
 pub trait GucEnum<T>
 where
     T: Copy,
@@ -59,6 +145,8 @@ where
     unsafe fn config_matrix(&self) -> *const pg_sys::config_enum_entry;
 }
 
+/// The current value of a GUC, kept up to date by Postgres once it is defined through the
+/// [`GucRegistry`]
 pub struct GucSetting<T> {
     value: Cell<T>,
     char_p: Cell<*mut std::os::raw::c_char>,
@@ -143,6 +231,10 @@ where
     }
 }
 
+/// Defines custom GUCs, which can then be set like any other, such as in `postgresql.conf` or with `SET`
+///
+/// The names and descriptions are copied into the `TopMemoryContext`, which is never freed, as
+/// Postgres keeps the pointers for the life of the backend.
 pub struct GucRegistry {}
 impl GucRegistry {
     pub fn define_bool_guc(
@@ -151,6 +243,104 @@ impl GucRegistry {
         long_description: &str,
         setting: &GucSetting<bool>,
         context: GucContext,
+    ) {
+        GucRegistry::define_bool_guc_with_flags(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            GucFlags::default(),
+        )
+    }
+
+    pub fn define_int_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<i32>,
+        min_value: i32,
+        max_value: i32,
+        context: GucContext,
+    ) {
+        GucRegistry::define_int_guc_with_flags(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            GucFlags::default(),
+        )
+    }
+
+    pub fn define_string_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Option<&'static str>>,
+        context: GucContext,
+    ) {
+        GucRegistry::define_string_guc_with_flags(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            GucFlags::default(),
+        )
+    }
+
+    pub fn define_float_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<f64>,
+        min_value: f64,
+        max_value: f64,
+        context: GucContext,
+    ) {
+        GucRegistry::define_float_guc_with_flags(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            GucFlags::default(),
+        )
+    }
+
+    pub fn define_enum_guc<T>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<T>,
+        context: GucContext,
+    ) where
+        T: GucEnum<T> + Copy,
+    {
+        GucRegistry::define_enum_guc_with_flags(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            GucFlags::default(),
+        )
+    }
+
+    /// As [`define_bool_guc`](GucRegistry::define_bool_guc), with [`GucFlags`] such as where it
+    /// may be set.  The other `_with_flags` functions are the same for their types.
+    pub fn define_bool_guc_with_flags(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<bool>,
+        context: GucContext,
+        flags: GucFlags,
     ) {
         unsafe {
             pg_sys::DefineCustomBoolVariable(
@@ -160,7 +350,7 @@ impl GucRegistry {
                 setting.as_ptr(),
                 setting.get(),
                 context as isize as u32,
-                0,
+                flags.bits(),
                 None,
                 None,
                 None,
@@ -168,7 +358,8 @@ impl GucRegistry {
         }
     }
 
-    pub fn define_int_guc(
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_int_guc_with_flags(
         name: &str,
         short_description: &str,
         long_description: &str,
@@ -176,6 +367,7 @@ impl GucRegistry {
         min_value: i32,
         max_value: i32,
         context: GucContext,
+        flags: GucFlags,
     ) {
         unsafe {
             pg_sys::DefineCustomIntVariable(
@@ -187,7 +379,7 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
+                flags.bits(),
                 None,
                 None,
                 None,
@@ -195,12 +387,13 @@ impl GucRegistry {
         }
     }
 
-    pub fn define_string_guc(
+    pub fn define_string_guc_with_flags(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<Option<&'static str>>,
         context: GucContext,
+        flags: GucFlags,
     ) {
         unsafe {
             let boot_value = match setting.value.get() {
@@ -215,7 +408,7 @@ impl GucRegistry {
                 setting.as_ptr(),
                 boot_value,
                 context as isize as u32,
-                0,
+                flags.bits(),
                 None,
                 None,
                 None,
@@ -223,7 +416,8 @@ impl GucRegistry {
         }
     }
 
-    pub fn define_float_guc(
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_float_guc_with_flags(
         name: &str,
         short_description: &str,
         long_description: &str,
@@ -231,6 +425,7 @@ impl GucRegistry {
         min_value: f64,
         max_value: f64,
         context: GucContext,
+        flags: GucFlags,
     ) {
         unsafe {
             pg_sys::DefineCustomRealVariable(
@@ -242,7 +437,7 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
+                flags.bits(),
                 None,
                 None,
                 None,
@@ -250,12 +445,13 @@ impl GucRegistry {
         }
     }
 
-    pub fn define_enum_guc<T>(
+    pub fn define_enum_guc_with_flags<T>(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<T>,
         context: GucContext,
+        flags: GucFlags,
     ) where
         T: GucEnum<T> + Copy,
     {
@@ -268,7 +464,7 @@ impl GucRegistry {
                 setting.value.get().to_ordinal(),
                 setting.value.get().config_matrix(),
                 context as isize as u32,
-                0,
+                flags.bits(),
                 None,
                 None,
                 None,
@@ -276,3 +472,202 @@ impl GucRegistry {
         }
     }
 }
+
+/// A type which the current value of a GUC can be read as, with [`Guc::get`]
+pub trait GucValue: Sized {
+    /// Convert `value`, which is formatted like `current_setting()`, but without units, into `Self`
+    fn from_guc_str(value: &CStr) -> Option<Self>;
+}
+
+impl GucValue for bool {
+    fn from_guc_str(value: &CStr) -> Option<Self> {
+        let mut result = false;
+        unsafe { pg_sys::parse_bool(value.as_ptr(), &mut result) }.then(|| result)
+    }
+}
+
+impl GucValue for i32 {
+    fn from_guc_str(value: &CStr) -> Option<Self> {
+        let mut result = 0;
+        unsafe { pg_sys::parse_int(value.as_ptr(), &mut result, 0, std::ptr::null_mut()) }
+            .then(|| result)
+    }
+}
+
+impl GucValue for f64 {
+    fn from_guc_str(value: &CStr) -> Option<Self> {
+        value.to_str().ok()?.parse().ok()
+    }
+}
+
+impl GucValue for String {
+    fn from_guc_str(value: &CStr) -> Option<Self> {
+        Some(value.to_str().ok()?.to_owned())
+    }
+}
+
+/// Typed access to the current value of any GUC, by name
+///
+/// This is for settings which belong to Postgres or another extension.  Prefer `.get()` on a
+/// [`GucSetting`] for those your own extension defines.
+pub struct Guc;
+
+impl Guc {
+    /// The current value of the setting `name`, or `None` if there is no such setting or it can't
+    /// be represented as a `T`.
+    ///
+    /// Integer and floating point settings are in their base unit, such as kilobytes for
+    /// `work_mem`, as shown by the `unit` column of `pg_settings`.  See [`Guc::get_bytes`] and
+    /// [`Guc::get_duration`] to have the unit applied.
+    pub fn get<T: GucValue>(name: &str) -> Option<T> {
+        let name = CString::new(name).ok()?;
+        unsafe {
+            let value = pg_sys::GetConfigOption(name.as_ptr(), true, false);
+            if value.is_null() {
+                None
+            } else {
+                // the value is in a static buffer, so convert it before anything else can overwrite it
+                T::from_guc_str(CStr::from_ptr(value))
+            }
+        }
+    }
+
+    /// The flags of the setting `name`, including its unit, or `None` if there is no such setting
+    pub fn flags(name: &str) -> Option<GucFlags> {
+        let cname = CString::new(name).ok()?;
+        unsafe {
+            if pg_sys::GetConfigOption(cname.as_ptr(), true, false).is_null() {
+                None
+            } else {
+                Some(GucFlags::from_bits_truncate(pg_sys::GetConfigOptionFlags(
+                    cname.as_ptr(),
+                    true,
+                )))
+            }
+        }
+    }
+
+    /// The current value of the memory setting `name`, in bytes.
+    ///
+    /// `None` if there is no such setting, it doesn't have a memory unit, or it is negative, which
+    /// settings such as `maintenance_work_mem` for autovacuum use to mean "not set".
+    pub fn get_bytes(name: &str) -> Option<u64> {
+        let flags = Guc::flags(name)?.bits() & pg_sys::GUC_UNIT_MEMORY as i32;
+        let multiplier = match flags as u32 {
+            pg_sys::GUC_UNIT_KB => 1024,
+            pg_sys::GUC_UNIT_BLOCKS => pg_sys::BLCKSZ as u64,
+            pg_sys::GUC_UNIT_XBLOCKS => pg_sys::XLOG_BLCKSZ as u64,
+            pg_sys::GUC_UNIT_MB => 1024 * 1024,
+            #[cfg(not(feature = "pg10"))]
+            pg_sys::GUC_UNIT_BYTE => 1,
+            _ => return None,
+        };
+        let value = Guc::get::<i32>(name)?;
+        u64::try_from(value).ok().map(|value| value * multiplier)
+    }
+
+    /// The current value of the time setting `name`.
+    ///
+    /// `None` if there is no such setting, it doesn't have a time unit, or it is negative, which
+    /// settings such as `log_min_duration_statement` use to mean "disabled".  Note that many
+    /// settings, such as `statement_timeout`, instead use zero for that.
+    pub fn get_duration(name: &str) -> Option<Duration> {
+        let flags = Guc::flags(name)?.bits() & pg_sys::GUC_UNIT_TIME as i32;
+        let seconds = match flags as u32 {
+            pg_sys::GUC_UNIT_MS => 0.001,
+            pg_sys::GUC_UNIT_S => 1.0,
+            pg_sys::GUC_UNIT_MIN => 60.0,
+            _ => return None,
+        };
+        let value = Guc::get::<f64>(name)?;
+        if value < 0.0 || !value.is_finite() {
+            None
+        } else {
+            Some(Duration::from_secs_f64(value * seconds))
+        }
+    }
+
+    /// Call `callback` with the new value of the setting `name` whenever it changes, whether
+    /// by `SET`, `RESET`, or a configuration reload.
+    ///
+    /// Settings are compared with the last value seen each time a transaction ends, so a change
+    /// is reported at the end of the transaction it was made in, and a reload at the end of the
+    /// next transaction after it.  A `SET LOCAL` may be reported, and then its reversion reported
+    /// at the end of the following transaction.
+    ///
+    /// `callback` runs while the transaction is being committed or aborted, where it is too late
+    /// to raise an `ERROR`.  A panic or `ERROR` in it is reported as a `WARNING` instead.
+    pub fn subscribe<T, F>(name: &str, mut callback: F)
+    where
+        T: GucValue,
+        F: FnMut(Option<T>) + 'static,
+    {
+        let subscription = GucSubscription {
+            name: name.to_string(),
+            last: Guc::get::<String>(name),
+            callback: Box::new(move |name| callback(Guc::get::<T>(name))),
+        };
+
+        unsafe {
+            match GUC_SUBSCRIPTIONS.as_mut() {
+                Some(subscriptions) => subscriptions.push(subscription),
+                None => {
+                    GUC_SUBSCRIPTIONS = Some(vec![subscription]);
+                    pg_sys::RegisterXactCallback(
+                        Some(guc_subscriptions_xact_callback),
+                        std::ptr::null_mut(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+struct GucSubscription {
+    name: String,
+    last: Option<String>,
+    callback: Box<dyn FnMut(&str)>,
+}
+
+static mut GUC_SUBSCRIPTIONS: Option<Vec<GucSubscription>> = None;
+
+unsafe extern "C" fn guc_subscriptions_xact_callback(
+    event: pg_sys::XactEvent,
+    _arg: *mut std::os::raw::c_void,
+) {
+    match event {
+        pg_sys::XactEvent_XACT_EVENT_COMMIT
+        | pg_sys::XactEvent_XACT_EVENT_ABORT
+        | pg_sys::XactEvent_XACT_EVENT_PARALLEL_COMMIT
+        | pg_sys::XactEvent_XACT_EVENT_PARALLEL_ABORT
+        | pg_sys::XactEvent_XACT_EVENT_PREPARE => (),
+        _ => return,
+    }
+
+    // take the subscriptions so that a callback can subscribe to more settings
+    let mut subscriptions = match GUC_SUBSCRIPTIONS.as_mut() {
+        Some(subscriptions) => std::mem::take(subscriptions),
+        None => return,
+    };
+    for subscription in subscriptions.iter_mut() {
+        let current = Guc::get::<String>(&subscription.name);
+        if current == subscription.last {
+            continue;
+        }
+        subscription.last = current;
+
+        // the transaction is already over, so an ERROR here would be a PANIC
+        let name = subscription.name.as_str();
+        let callback = &mut subscription.callback;
+        if let Err(message) = catch_error_message(std::panic::AssertUnwindSafe(|| callback(name))) {
+            elog(
+                PgLogLevel::WARNING,
+                &format!("subscriber to setting \"{}\" failed: {}", name, message),
+            );
+        }
+    }
+    if let Some(added) = GUC_SUBSCRIPTIONS.as_mut() {
+        subscriptions.append(added);
+        *added = subscriptions;
+    }
+}
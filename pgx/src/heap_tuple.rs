@@ -0,0 +1,904 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A safe wrapper around a `pg_sys::HeapTuple` and the `pg_sys::TupleDesc` describing it
+use crate::{
+    pg_sys, AllocatedByPostgres, AllocatedByRust, FromDatum, IntoDatum, Json, JsonB, PgBox,
+    PgTupleDesc, WhoAllocated,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::cell::Cell;
+use std::ffi::{CStr, CString};
+use std::num::NonZeroUsize;
+use std::os::raw::{c_char, c_int};
+
+mod value;
+
+use self::value::{AttributeSerializer, AttributeValue};
+
+/// Errors from reading or modifying a [`PgHeapTuple`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgHeapTupleError {
+    /// The tuple has no attribute with this (1-based) number
+    NoSuchAttributeNumber(usize),
+    /// The tuple has no attribute with this name
+    NoSuchAttributeName(String),
+    /// The Rust type's SQL type is not the type of the attribute
+    IncompatibleTypes {
+        attribute: String,
+        expected: pg_sys::Oid,
+        found: pg_sys::Oid,
+    },
+    /// A Rust value could not be converted to or from the tuple's attributes
+    Serde(String),
+}
+
+impl std::fmt::Display for PgHeapTupleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgHeapTupleError::NoSuchAttributeNumber(attno) => {
+                write!(f, "no such attribute number: {}", attno)
+            }
+            PgHeapTupleError::NoSuchAttributeName(name) => {
+                write!(f, "no such attribute: `{}`", name)
+            }
+            PgHeapTupleError::IncompatibleTypes {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "attribute `{}` has type oid {}, but was given a value of type oid {}",
+                attribute, expected, found
+            ),
+            PgHeapTupleError::Serde(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PgHeapTupleError {}
+
+/// The number of an attribute of a [`PgHeapTuple`], which starts from 1.
+///
+/// A constant one is checked as it's compiled:
+///
+/// ```rust,compile_fail
+/// use pgx::AttributeNumber;
+///
+/// const NAME: AttributeNumber = AttributeNumber::new(0);
+/// assert_eq!(NAME.get(), 0);
+/// ```
+///
+/// The tuple still checks that it has the attribute, as it's read or set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AttributeNumber(NonZeroUsize);
+
+impl AttributeNumber {
+    /// The attribute numbered `attno`
+    ///
+    /// ## Panics
+    ///
+    /// If `attno` is zero, or fails to compile if it's a constant
+    pub const fn new(attno: usize) -> Self {
+        match NonZeroUsize::new(attno) {
+            Some(attno) => AttributeNumber(attno),
+            None => panic!("attribute numbers start from 1"),
+        }
+    }
+
+    /// The number, which starts from 1
+    pub const fn get(self) -> usize {
+        self.0.get()
+    }
+}
+
+impl TryFrom<usize> for AttributeNumber {
+    type Error = PgHeapTupleError;
+
+    fn try_from(attno: usize) -> Result<Self, Self::Error> {
+        NonZeroUsize::new(attno)
+            .map(AttributeNumber)
+            .ok_or(PgHeapTupleError::NoSuchAttributeNumber(attno))
+    }
+}
+
+impl From<AttributeNumber> for usize {
+    fn from(attno: AttributeNumber) -> Self {
+        attno.get()
+    }
+}
+
+impl std::fmt::Display for AttributeNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The numbers of the attributes named `names`, looked up the first time they're needed and kept
+/// for the rows after, such as every row of a composite type a function is called with:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// thread_local! {
+///     static POINT: AttributeNumbers<2> = AttributeNumbers::new(["x", "y"]);
+/// }
+///
+/// #[pg_extern]
+/// fn point_length(point: PgHeapTuple<'static, AllocatedByRust>) -> f64 {
+///     let [x, y] = POINT
+///         .with(|attnos| attnos.resolve(&point))
+///         .expect("the row isn't a point");
+///     let x = point.get_by_index::<f64>(x).unwrap().unwrap_or_default();
+///     let y = point.get_by_index::<f64>(y).unwrap().unwrap_or_default();
+///     (x * x + y * y).sqrt()
+/// }
+/// ```
+///
+/// Each time they're resolved, the attributes they were found at last are checked to still have
+/// their names, so a type that's since been altered looks them up again, as does a row of another
+/// type.
+#[derive(Debug, Clone)]
+pub struct AttributeNumbers<const N: usize> {
+    names: [&'static str; N],
+    resolved: Cell<Option<[AttributeNumber; N]>>,
+}
+
+impl<const N: usize> AttributeNumbers<N> {
+    /// The attributes named `names`, which haven't been looked up yet
+    pub const fn new(names: [&'static str; N]) -> Self {
+        AttributeNumbers {
+            names,
+            resolved: Cell::new(None),
+        }
+    }
+
+    /// The numbers of the attributes in `tuple`, which are only looked up if they've changed
+    pub fn resolve<AllocatedBy: WhoAllocated<pg_sys::HeapTupleData>>(
+        &self,
+        tuple: &PgHeapTuple<'_, AllocatedBy>,
+    ) -> Result<[AttributeNumber; N], PgHeapTupleError> {
+        if let Some(attnos) = self.resolved.get() {
+            let unchanged = attnos.iter().zip(self.names.iter()).all(|(attno, name)| {
+                matches!(tuple.attribute(*attno), Ok(attribute) if attribute.name() == *name)
+            });
+            if unchanged {
+                return Ok(attnos);
+            }
+        }
+        let attnos = tuple.attnums(self.names)?;
+        self.resolved.set(Some(attnos));
+        Ok(attnos)
+    }
+}
+
+/// A `pg_sys::HeapTuple` along with the `PgTupleDesc` that describes its attributes.
+///
+/// Tuples handed to us by Postgres, such as those of a [`PgTrigger`](crate::PgTrigger), are
+/// `AllocatedByPostgres` and read-only.  [`into_owned`](PgHeapTuple::into_owned) copies one
+/// into a tuple that can be modified with [`set_by_index`](PgHeapTuple::set_by_index) and
+/// [`set_by_name`](PgHeapTuple::set_by_name).
+///
+/// A `#[pg_extern]` can accept a `PgHeapTuple<'static, AllocatedByRust>` argument as a `record`,
+/// which takes a row of any composite type, or an anonymous `ROW(...)`.  Its tuple descriptor is
+/// looked up when the function is called, from the type the row was given as:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn column_names(row: PgHeapTuple<'static, AllocatedByRust>) -> Vec<String> {
+///     row.tupdesc()
+///         .iter()
+///         .filter(|attribute| !attribute.is_dropped())
+///         .map(|attribute| attribute.name().to_string())
+///         .collect()
+/// }
+/// ```
+///
+/// Users can `ALTER TYPE` the composite types an extension works with after it's compiled.
+/// Attributes they've dropped are skipped, as if they weren't there, and rows stored before an
+/// attribute was added read it as NULL, or as its missing default for a table column added with
+/// one.  [`has_attribute`](PgHeapTuple::has_attribute) tells if an attribute is there, and
+/// [`from_struct_ignoring_unknown`](PgHeapTuple::from_struct_ignoring_unknown) builds a tuple from
+/// a struct which has fields for attributes that have since been dropped.
+pub struct PgHeapTuple<'a, AllocatedBy: WhoAllocated<pg_sys::HeapTupleData>> {
+    tuple: PgBox<pg_sys::HeapTupleData, AllocatedBy>,
+    tupdesc: PgTupleDesc<'a>,
+}
+
+impl<'a> PgHeapTuple<'a, AllocatedByPostgres> {
+    /// Wrap a Postgres-provided `pg_sys::HeapTuple` described by `tupdesc`.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe as we cannot validate that `heap_tuple` is valid, or that `tupdesc`
+    /// actually describes it.
+    pub unsafe fn from_heap_tuple(tupdesc: PgTupleDesc<'a>, heap_tuple: pg_sys::HeapTuple) -> Self {
+        PgHeapTuple {
+            tuple: PgBox::from_pg(heap_tuple),
+            tupdesc,
+        }
+    }
+
+    /// Copy this tuple into the `CurrentMemoryContext` so that it can be modified
+    pub fn into_owned(self) -> PgHeapTuple<'a, AllocatedByRust> {
+        let copy = unsafe { pg_sys::heap_copytuple(self.tuple.as_ptr()) };
+        PgHeapTuple {
+            tuple: unsafe { PgBox::from_rust(copy) },
+            tupdesc: self.tupdesc,
+        }
+    }
+}
+
+impl PgHeapTuple<'static, AllocatedByRust> {
+    /// A tuple of the composite type with oid `type_oid`, with every attribute NULL
+    pub fn new_composite_type(type_oid: pg_sys::Oid) -> Self {
+        let tupdesc = unsafe {
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(type_oid, -1))
+        };
+        let mut values = vec![0 as pg_sys::Datum; tupdesc.len()];
+        let mut nulls = vec![true; tupdesc.len()];
+        let tuple = unsafe {
+            pg_sys::heap_form_tuple(tupdesc.as_ptr(), values.as_mut_ptr(), nulls.as_mut_ptr())
+        };
+        PgHeapTuple {
+            tuple: unsafe { PgBox::from_rust(tuple) },
+            tupdesc,
+        }
+    }
+
+    /// Wrap a `pg_sys::Datum` of a composite type, such as a function argument.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe as we cannot validate that `composite` is actually a composite type.
+    pub unsafe fn from_composite_datum(composite: pg_sys::Datum) -> Self {
+        let tuple = crate::composite_row_type_make_tuple(composite);
+        let tupdesc = PgTupleDesc::from_pg(pg_sys::lookup_rowtype_tupdesc(
+            crate::heap_tuple_header_get_type_id(tuple.t_data),
+            crate::heap_tuple_header_get_typmod(tuple.t_data),
+        ));
+        PgHeapTuple { tuple, tupdesc }
+    }
+}
+
+/// A `record`, or a row of any composite type, described by the type it was given as
+impl FromDatum for PgHeapTuple<'static, AllocatedByRust> {
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a record Datum was flagged as non-null but the datum is zero");
+        } else {
+            Some(PgHeapTuple::from_composite_datum(datum))
+        }
+    }
+}
+
+/// A `record` of the tuple's composite type, whose descriptor must be blessed if it isn't one
+/// from the catalog.  A `Vec` of tuples of a type from the catalog is an array of that type.
+impl<'a> IntoDatum for PgHeapTuple<'a, AllocatedByRust> {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.into_composite_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::RECORDOID
+    }
+}
+
+impl<'a> PgHeapTuple<'a, AllocatedByRust> {
+    /// Build a tuple described by `tupdesc` from the fields of `value`, matched by name.
+    ///
+    /// Attributes without a matching field are NULL.  Each field is converted to its attribute's
+    /// type: structs and maps become composite types, sequences become arrays, `f32`/`f64` and
+    /// bytes are stored as they are, including NaN and infinities, and anything else goes through
+    /// the type's input function.  A `bytea` is built from a `Vec<u8>` or `serde_bytes`, and a
+    /// `json`/`jsonb` from any value.
+    ///
+    /// ```rust,no_run
+    /// use pgx::{PgHeapTuple, PgRelation};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Dog {
+    ///     name: String,
+    ///     scritches: i32,
+    /// }
+    ///
+    /// let relation = PgRelation::open_with_name_and_share_lock("dogs").unwrap();
+    /// let dog = Dog { name: "Nami".into(), scritches: 10 };
+    /// let tuple = PgHeapTuple::from_struct(relation.tuple_desc(), &dog).unwrap();
+    /// assert_eq!(tuple.get_by_name::<i32>("scritches").unwrap(), Some(10));
+    /// ```
+    pub fn from_struct<T: Serialize>(
+        tupdesc: PgTupleDesc<'a>,
+        value: &T,
+    ) -> Result<Self, PgHeapTupleError> {
+        let fields = struct_to_fields(value)?;
+        let tuple = form_tuple(&tupdesc, fields, false)?;
+        Ok(PgHeapTuple {
+            tuple: unsafe { PgBox::from_rust(tuple) },
+            tupdesc,
+        })
+    }
+
+    /// Like [`from_struct`](PgHeapTuple::from_struct), but fields without a matching attribute,
+    /// at any depth, are ignored rather than an error.
+    ///
+    /// This lets a struct written for an older version of a composite type build a tuple of it
+    /// after some of its attributes have been dropped.
+    pub fn from_struct_ignoring_unknown<T: Serialize>(
+        tupdesc: PgTupleDesc<'a>,
+        value: &T,
+    ) -> Result<Self, PgHeapTupleError> {
+        let fields = struct_to_fields(value)?;
+        let tuple = form_tuple(&tupdesc, fields, true)?;
+        Ok(PgHeapTuple {
+            tuple: unsafe { PgBox::from_rust(tuple) },
+            tupdesc,
+        })
+    }
+
+    /// Set the attribute numbered `attno` to `value`.
+    ///
+    /// The SQL type of `T` must be exactly the type of the attribute.
+    pub fn set_by_index<T: IntoDatum>(
+        &mut self,
+        attno: AttributeNumber,
+        value: T,
+    ) -> Result<(), PgHeapTupleError> {
+        let attribute = self.attribute(attno)?;
+        if attribute.atttypid != T::type_oid() {
+            return Err(PgHeapTupleError::IncompatibleTypes {
+                attribute: attribute.name().to_string(),
+                expected: attribute.atttypid,
+                found: T::type_oid(),
+            });
+        }
+
+        let mut attnum = attno.get() as i32;
+        let (mut datum, mut isnull) = match value.into_datum() {
+            Some(datum) => (datum, false),
+            None => (0, true),
+        };
+
+        unsafe {
+            let modified = pg_sys::heap_modify_tuple_by_cols(
+                self.tuple.as_ptr(),
+                self.tupdesc.as_ptr(),
+                1,
+                &mut attnum,
+                &mut datum,
+                &mut isnull,
+            );
+            // dropping the previous tuple frees it
+            self.tuple = PgBox::from_rust(modified);
+        }
+        Ok(())
+    }
+
+    /// Set the attribute named `name` to `value`.
+    ///
+    /// The SQL type of `T` must be exactly the type of the attribute.
+    pub fn set_by_name<T: IntoDatum>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<(), PgHeapTupleError> {
+        let attno = self.attnum_of(name)?;
+        self.set_by_index(attno, value)
+    }
+}
+
+impl<'a, AllocatedBy: WhoAllocated<pg_sys::HeapTupleData>> PgHeapTuple<'a, AllocatedBy> {
+    /// How many attributes does this tuple have?
+    ///
+    /// This counts attributes which have been dropped, as their numbers are still used.
+    pub fn len(&self) -> usize {
+        self.tupdesc.len()
+    }
+
+    /// Does this tuple have attributes?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `PgTupleDesc` describing this tuple
+    pub fn tupdesc(&self) -> &PgTupleDesc<'a> {
+        &self.tupdesc
+    }
+
+    /// The number of the attribute named `name`, if the tuple has it and it hasn't been dropped
+    pub fn attnum(&self, name: &str) -> Option<AttributeNumber> {
+        self.tupdesc
+            .iter()
+            .position(|attribute| !attribute.is_dropped() && attribute.name() == name)
+            .map(|i| AttributeNumber::new(i + 1))
+    }
+
+    /// The numbers of the attributes named `names`, looked up all at once, for reading or setting
+    /// them by index in a loop.  [`AttributeNumbers`] keeps them from one call to the next.
+    pub fn attnums<const N: usize>(
+        &self,
+        names: [&str; N],
+    ) -> Result<[AttributeNumber; N], PgHeapTupleError> {
+        let mut attnos = [AttributeNumber::new(1); N];
+        for (attno, name) in attnos.iter_mut().zip(names.iter()) {
+            *attno = self.attnum_of(name)?;
+        }
+        Ok(attnos)
+    }
+
+    /// Does the tuple have an attribute named `name` that hasn't been dropped?
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attnum(name).is_some()
+    }
+
+    /// Get the attribute numbered `attno`.
+    ///
+    /// The SQL type of `T` must be exactly the type of the attribute.  Returns `Ok(None)` if the
+    /// attribute is NULL, or if the tuple was stored before the attribute was added and it has no
+    /// missing default.
+    pub fn get_by_index<T: FromDatum + IntoDatum>(
+        &self,
+        attno: AttributeNumber,
+    ) -> Result<Option<T>, PgHeapTupleError> {
+        let attribute = self.attribute(attno)?;
+        if attribute.atttypid != T::type_oid() {
+            return Err(PgHeapTupleError::IncompatibleTypes {
+                attribute: attribute.name().to_string(),
+                expected: attribute.atttypid,
+                found: T::type_oid(),
+            });
+        }
+
+        unsafe {
+            match crate::heap_getattr_raw(self.tuple.as_ptr(), attno.get(), self.tupdesc.as_ptr()) {
+                Some(datum) => Ok(T::from_datum(datum, false, attribute.atttypid)),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// Get the attribute named `name`.
+    ///
+    /// The SQL type of `T` must be exactly the type of the attribute.  Returns `Ok(None)` if the
+    /// attribute is NULL.
+    pub fn get_by_name<T: FromDatum + IntoDatum>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, PgHeapTupleError> {
+        let attno = self.attnum_of(name)?;
+        self.get_by_index(attno)
+    }
+
+    /// Convert this tuple into `T`, whose fields are matched to attributes by name.
+    ///
+    /// Each attribute is converted by its type: numbers, including NaN and infinite floats, and
+    /// booleans are read as they are, a `bytea` as bytes or a `Vec<u8>`, `json`/`jsonb` as the
+    /// JSON in them, arrays as sequences and composite types as structs, and everything else as
+    /// the text from the type's output function.  NULL attributes are `None`.
+    ///
+    /// Dropped attributes are skipped, so a field for one is missing, which `serde` accepts for an
+    /// `Option` or a `#[serde(default)]` field.  Attributes without a field are ignored, unless `T`
+    /// is `#[serde(deny_unknown_fields)]`.
+    pub fn into_struct<T: DeserializeOwned>(self) -> Result<T, PgHeapTupleError> {
+        T::deserialize(self.to_value()).map_err(|e| PgHeapTupleError::Serde(e.to_string()))
+    }
+
+    /// The underlying `pg_sys::HeapTuple`
+    pub fn as_ptr(&self) -> pg_sys::HeapTuple {
+        self.tuple.as_ptr()
+    }
+
+    /// Copy this tuple into a `pg_sys::Datum` of its composite type, such as a function returns
+    pub fn into_composite_datum(self) -> Option<pg_sys::Datum> {
+        Some(unsafe {
+            pg_sys::heap_copy_tuple_as_datum(self.tuple.as_ptr(), self.tupdesc.as_ptr())
+        })
+    }
+
+    /// Give the underlying `pg_sys::HeapTuple` to Postgres, as a `pg_sys::Datum`.
+    ///
+    /// This is what a trigger function returns to have Postgres store this tuple.
+    pub fn into_trigger_datum(self) -> pg_sys::Datum {
+        self.into_pg() as pg_sys::Datum
+    }
+
+    /// Give the underlying `pg_sys::HeapTuple` to Postgres, which frees it with its memory context
+    pub fn into_pg(self) -> pg_sys::HeapTuple {
+        self.tuple.into_pg()
+    }
+
+    fn attribute(
+        &self,
+        attno: AttributeNumber,
+    ) -> Result<&pg_sys::FormData_pg_attribute, PgHeapTupleError> {
+        match self.tupdesc.get(attno.get() - 1) {
+            Some(attribute) if !attribute.is_dropped() => Ok(attribute),
+            _ => Err(PgHeapTupleError::NoSuchAttributeNumber(attno.get())),
+        }
+    }
+
+    fn attnum_of(&self, name: &str) -> Result<AttributeNumber, PgHeapTupleError> {
+        self.attnum(name)
+            .ok_or_else(|| PgHeapTupleError::NoSuchAttributeName(name.to_string()))
+    }
+
+    fn to_value(&self) -> AttributeValue {
+        let mut fields = Vec::with_capacity(self.tupdesc.len());
+        for (i, attribute) in self.tupdesc.iter().enumerate() {
+            if attribute.is_dropped() {
+                continue;
+            }
+            let value = unsafe {
+                match crate::heap_getattr_raw(self.tuple.as_ptr(), i + 1, self.tupdesc.as_ptr()) {
+                    Some(datum) => datum_to_value(datum, attribute.atttypid),
+                    None => AttributeValue::Null,
+                }
+            };
+            fields.push((attribute.name().to_string(), value));
+        }
+        AttributeValue::Object(fields)
+    }
+}
+
+fn struct_to_fields<T: Serialize>(
+    value: &T,
+) -> Result<Vec<(String, AttributeValue)>, PgHeapTupleError> {
+    match value.serialize(AttributeSerializer) {
+        Ok(AttributeValue::Object(fields)) => Ok(fields),
+        Ok(_) => Err(PgHeapTupleError::Serde(
+            "only structs and maps can be converted into a tuple".to_string(),
+        )),
+        Err(e) => Err(PgHeapTupleError::Serde(e.to_string())),
+    }
+}
+
+fn form_tuple(
+    tupdesc: &PgTupleDesc,
+    mut fields: Vec<(String, AttributeValue)>,
+    ignore_unknown: bool,
+) -> Result<pg_sys::HeapTuple, PgHeapTupleError> {
+    let mut values = vec![0 as pg_sys::Datum; tupdesc.len()];
+    let mut nulls = vec![true; tupdesc.len()];
+    for (i, attribute) in tupdesc.iter().enumerate() {
+        if attribute.is_dropped() {
+            continue;
+        }
+        if let Some(position) = fields.iter().position(|(name, _)| name == attribute.name()) {
+            let (_, value) = fields.remove(position);
+            let target = Target {
+                attribute: attribute.name(),
+                typoid: attribute.atttypid,
+                typmod: attribute.atttypmod,
+            };
+            if let Some(datum) = value_to_datum(value, &target, ignore_unknown)? {
+                values[i] = datum;
+                nulls[i] = false;
+            }
+        }
+    }
+
+    // unless they're ignored, every field must have been used by an attribute
+    if !ignore_unknown {
+        if let Some((name, _)) = fields.first() {
+            return Err(PgHeapTupleError::NoSuchAttributeName(name.clone()));
+        }
+    }
+
+    Ok(unsafe {
+        pg_sys::heap_form_tuple(tupdesc.as_ptr(), values.as_mut_ptr(), nulls.as_mut_ptr())
+    })
+}
+
+/// The type a value is being converted to, for the attribute named `attribute`, or for the
+/// elements of an array attribute
+struct Target<'a> {
+    attribute: &'a str,
+    typoid: pg_sys::Oid,
+    typmod: i32,
+}
+
+impl Target<'_> {
+    fn error(&self, value: &AttributeValue) -> PgHeapTupleError {
+        PgHeapTupleError::Serde(format!(
+            "attribute `{}` of type oid {} cannot be built from {}",
+            self.attribute,
+            self.typoid,
+            value.describe()
+        ))
+    }
+}
+
+fn value_to_datum(
+    value: AttributeValue,
+    target: &Target,
+    ignore_unknown: bool,
+) -> Result<Option<pg_sys::Datum>, PgHeapTupleError> {
+    let typoid = target.typoid;
+    match value {
+        AttributeValue::Null => Ok(None),
+        value if typoid == pg_sys::JSONOID || typoid == pg_sys::JSONBOID => {
+            let json = value.into_json().map_err(|e| {
+                PgHeapTupleError::Serde(format!("attribute `{}`: {}", target.attribute, e))
+            })?;
+            Ok(if typoid == pg_sys::JSONOID {
+                Json(json).into_datum()
+            } else {
+                JsonB(json).into_datum()
+            })
+        }
+        AttributeValue::F64(f) if typoid == pg_sys::FLOAT8OID => Ok(f.into_datum()),
+        AttributeValue::F64(f) if typoid == pg_sys::FLOAT4OID => Ok((f as f32).into_datum()),
+        AttributeValue::Bytes(bytes) if typoid == pg_sys::BYTEAOID => Ok(bytes.into_datum()),
+        // a `Vec<u8>` is serialized as a sequence of numbers
+        AttributeValue::Array(values) if typoid == pg_sys::BYTEAOID && is_bytes(&values) => {
+            let bytes = values
+                .iter()
+                .map(|value| match value {
+                    AttributeValue::I64(byte) => *byte as u8,
+                    AttributeValue::U64(byte) => *byte as u8,
+                    _ => unreachable!("is_bytes() checked every value is a byte"),
+                })
+                .collect::<Vec<_>>();
+            Ok(bytes.into_datum())
+        }
+        AttributeValue::Object(fields) if unsafe { pg_sys::type_is_rowtype(typoid) } => unsafe {
+            let tupdesc = PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(
+                typoid,
+                target.typmod,
+            ));
+            let tuple = form_tuple(&tupdesc, fields, ignore_unknown)?;
+            Ok(Some(pg_sys::heap_copy_tuple_as_datum(
+                tuple,
+                tupdesc.as_ptr(),
+            )))
+        },
+        AttributeValue::Array(values)
+            if unsafe { pg_sys::get_element_type(typoid) } != pg_sys::InvalidOid =>
+        {
+            let element = Target {
+                attribute: target.attribute,
+                typoid: unsafe { pg_sys::get_element_type(typoid) },
+                typmod: target.typmod,
+            };
+            array_to_datum(values, &element, ignore_unknown).map(Some)
+        }
+        AttributeValue::String(string) => input_function_call(&string, target).map(Some),
+        AttributeValue::Bool(b) => input_function_call(&b.to_string(), target).map(Some),
+        AttributeValue::I64(i) => input_function_call(&i.to_string(), target).map(Some),
+        AttributeValue::U64(u) => input_function_call(&u.to_string(), target).map(Some),
+        AttributeValue::F64(f) => input_function_call(&float_text(f), target).map(Some),
+        value => Err(target.error(&value)),
+    }
+}
+
+fn is_bytes(values: &[AttributeValue]) -> bool {
+    values.iter().all(|value| match value {
+        AttributeValue::I64(byte) => (0..=255).contains(byte),
+        AttributeValue::U64(byte) => *byte <= 255,
+        _ => false,
+    })
+}
+
+/// A float as the input functions of `numeric` and the float types read it
+fn float_text(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f == f64::INFINITY {
+        "Infinity".to_string()
+    } else if f == f64::NEG_INFINITY {
+        "-Infinity".to_string()
+    } else {
+        f.to_string()
+    }
+}
+
+/// Build an array of `element.typoid` from `values`, whose nested sequences are its dimensions
+fn array_to_datum(
+    values: Vec<AttributeValue>,
+    element: &Target,
+    ignore_unknown: bool,
+) -> Result<pg_sys::Datum, PgHeapTupleError> {
+    let mut dims = Vec::new();
+    let mut leaves = Vec::new();
+    flatten_array(values, element, 0, &mut dims, &mut leaves)?;
+
+    let mut datums = Vec::with_capacity(leaves.len());
+    let mut nulls = Vec::with_capacity(leaves.len());
+    for leaf in leaves {
+        let datum = value_to_datum(leaf, element, ignore_unknown)?;
+        datums.push(datum.unwrap_or(0));
+        nulls.push(datum.is_none());
+    }
+
+    let mut lbs = vec![1; dims.len()];
+    let mut typlen = 0;
+    let mut typbyval = false;
+    let mut typalign: c_char = 0;
+    unsafe {
+        pg_sys::get_typlenbyvalalign(element.typoid, &mut typlen, &mut typbyval, &mut typalign);
+        Ok(pg_sys::construct_md_array(
+            datums.as_mut_ptr(),
+            nulls.as_mut_ptr(),
+            dims.len() as c_int,
+            dims.as_mut_ptr(),
+            lbs.as_mut_ptr(),
+            element.typoid,
+            typlen as c_int,
+            typbyval,
+            typalign,
+        ) as pg_sys::Datum)
+    }
+}
+
+/// Collect the elements of a (possibly multidimensional) array, in order, and the length of each
+/// of its dimensions, which must be the same for every sequence at the same depth
+fn flatten_array(
+    values: Vec<AttributeValue>,
+    element: &Target,
+    depth: usize,
+    dims: &mut Vec<c_int>,
+    leaves: &mut Vec<AttributeValue>,
+) -> Result<(), PgHeapTupleError> {
+    match dims.get(depth) {
+        None => dims.push(values.len() as c_int),
+        Some(&len) if len as usize == values.len() => {}
+        Some(_) => {
+            return Err(PgHeapTupleError::Serde(format!(
+                "attribute `{}` can't be built from sequences of different lengths",
+                element.attribute
+            )))
+        }
+    }
+
+    // a nested sequence is another dimension, unless it's an element of its own
+    let is_dimension = |value: &AttributeValue| match value {
+        AttributeValue::Array(values) => {
+            element.typoid != pg_sys::JSONOID
+                && element.typoid != pg_sys::JSONBOID
+                && !(element.typoid == pg_sys::BYTEAOID && is_bytes(values))
+        }
+        _ => false,
+    };
+    let nested = values.iter().filter(|value| is_dimension(value)).count();
+    if nested != 0 && nested != values.len() {
+        return Err(PgHeapTupleError::Serde(format!(
+            "attribute `{}` can't be built from a sequence of both sequences and elements",
+            element.attribute
+        )));
+    }
+
+    for value in values {
+        match value {
+            AttributeValue::Array(values) if nested != 0 => {
+                flatten_array(values, element, depth + 1, dims, leaves)?
+            }
+            value => leaves.push(value),
+        }
+    }
+    Ok(())
+}
+
+fn input_function_call(text: &str, target: &Target) -> Result<pg_sys::Datum, PgHeapTupleError> {
+    let cstring = CString::new(text).map_err(|e| PgHeapTupleError::Serde(e.to_string()))?;
+    let mut typinput = pg_sys::InvalidOid;
+    let mut typioparam = pg_sys::InvalidOid;
+    unsafe {
+        pg_sys::getTypeInputInfo(target.typoid, &mut typinput, &mut typioparam);
+        Ok(pg_sys::OidInputFunctionCall(
+            typinput,
+            cstring.as_ptr() as *mut c_char,
+            typioparam,
+            target.typmod,
+        ))
+    }
+}
+
+unsafe fn datum_to_value(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> AttributeValue {
+    unsafe fn from<T: FromDatum>(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> T {
+        T::from_datum(datum, false, typoid).expect("a non-NULL datum was read as NULL")
+    }
+
+    match typoid {
+        pg_sys::BOOLOID => AttributeValue::Bool(from(datum, typoid)),
+        pg_sys::INT2OID => AttributeValue::I64(from::<i16>(datum, typoid).into()),
+        pg_sys::INT4OID => AttributeValue::I64(from::<i32>(datum, typoid).into()),
+        pg_sys::INT8OID => AttributeValue::I64(from(datum, typoid)),
+        pg_sys::OIDOID => AttributeValue::U64(from::<u32>(datum, typoid).into()),
+        pg_sys::FLOAT4OID => AttributeValue::F64(from::<f32>(datum, typoid).into()),
+        pg_sys::FLOAT8OID => AttributeValue::F64(from(datum, typoid)),
+        pg_sys::BYTEAOID => AttributeValue::Bytes(from(datum, typoid)),
+        pg_sys::JSONOID => AttributeValue::from_json(from::<Json>(datum, typoid).0),
+        pg_sys::JSONBOID => AttributeValue::from_json(from::<JsonB>(datum, typoid).0),
+        pg_sys::NUMERICOID => {
+            let text = output_function_call(datum, typoid);
+            match serde_json::from_str::<serde_json::Number>(&text) {
+                Ok(number) => AttributeValue::from_json(serde_json::Value::Number(number)),
+                // NaN and the infinities have no JSON representation, but are floats
+                Err(_) => match text.parse::<f64>() {
+                    Ok(f) => AttributeValue::F64(f),
+                    Err(_) => AttributeValue::String(text),
+                },
+            }
+        }
+        _ if pg_sys::type_is_rowtype(typoid) => PgHeapTuple::from_composite_datum(datum).to_value(),
+        _ if pg_sys::get_element_type(typoid) != pg_sys::InvalidOid => {
+            array_to_value(datum, pg_sys::get_element_type(typoid))
+        }
+        _ => AttributeValue::String(output_function_call(datum, typoid)),
+    }
+}
+
+/// The elements of an array, nested in a sequence for each of its dimensions
+unsafe fn array_to_value(datum: pg_sys::Datum, elemtype: pg_sys::Oid) -> AttributeValue {
+    let array = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::ArrayType;
+    let ndim = (*array).ndim as usize;
+    // the dimensions follow the header, like `ARR_DIMS()`
+    let dims = std::slice::from_raw_parts(
+        (array as *const u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *const c_int,
+        ndim,
+    );
+
+    let mut typlen = 0;
+    let mut typbyval = false;
+    let mut typalign: c_char = 0;
+    pg_sys::get_typlenbyvalalign(elemtype, &mut typlen, &mut typbyval, &mut typalign);
+    let mut elements = std::ptr::null_mut();
+    let mut nulls = std::ptr::null_mut();
+    let mut nelems = 0;
+    pg_sys::deconstruct_array(
+        array,
+        elemtype,
+        typlen as c_int,
+        typbyval,
+        typalign,
+        &mut elements,
+        &mut nulls,
+        &mut nelems,
+    );
+
+    let mut leaves = (0..nelems as usize).map(|i| {
+        if *nulls.add(i) {
+            AttributeValue::Null
+        } else {
+            datum_to_value(*elements.add(i), elemtype)
+        }
+    });
+    nest_array(&mut leaves, dims)
+}
+
+fn nest_array(leaves: &mut impl Iterator<Item = AttributeValue>, dims: &[c_int]) -> AttributeValue {
+    match dims.split_first() {
+        None => AttributeValue::Array(Vec::new()),
+        Some((&len, [])) => AttributeValue::Array(leaves.take(len as usize).collect()),
+        Some((&len, inner)) => {
+            AttributeValue::Array((0..len).map(|_| nest_array(leaves, inner)).collect())
+        }
+    }
+}
+
+unsafe fn output_function_call(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let mut typoutput = pg_sys::InvalidOid;
+    let mut typisvarlena = false;
+    pg_sys::getTypeOutputInfo(typoid, &mut typoutput, &mut typisvarlena);
+    let cstr = pg_sys::OidOutputFunctionCall(typoutput, datum);
+    let text = CStr::from_ptr(cstr).to_string_lossy().into_owned();
+    pg_sys::pfree(cstr as *mut std::os::raw::c_void);
+    text
+}
@@ -0,0 +1,495 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The values of attributes on their way between a [`PgHeapTuple`](super::PgHeapTuple) and a
+//! Rust struct.  Unlike JSON they keep non-finite floats, and bytes apart from arrays of numbers.
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+pub(super) enum AttributeValue {
+    Null,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<AttributeValue>),
+    /// A composite type, or a struct or map, with its fields in order
+    Object(Vec<(String, AttributeValue)>),
+}
+
+impl AttributeValue {
+    pub(super) fn from_json(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => AttributeValue::Null,
+            serde_json::Value::Bool(b) => AttributeValue::Bool(b),
+            serde_json::Value::Number(number) => {
+                if let Some(i) = number.as_i64() {
+                    AttributeValue::I64(i)
+                } else if let Some(u) = number.as_u64() {
+                    AttributeValue::U64(u)
+                } else {
+                    AttributeValue::F64(number.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            serde_json::Value::String(string) => AttributeValue::String(string),
+            serde_json::Value::Array(values) => {
+                AttributeValue::Array(values.into_iter().map(AttributeValue::from_json).collect())
+            }
+            serde_json::Value::Object(object) => AttributeValue::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, AttributeValue::from_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// JSON for a `json` or `jsonb` attribute, which has no room for NaN or infinite numbers
+    pub(super) fn into_json(self) -> Result<serde_json::Value, String> {
+        Ok(match self {
+            AttributeValue::Null => serde_json::Value::Null,
+            AttributeValue::Bool(b) => serde_json::Value::Bool(b),
+            AttributeValue::I64(i) => i.into(),
+            AttributeValue::U64(u) => u.into(),
+            AttributeValue::F64(f) => serde_json::Number::from_f64(f)
+                .map(serde_json::Value::Number)
+                .ok_or_else(|| format!("{} cannot be stored as JSON", f))?,
+            AttributeValue::String(string) => serde_json::Value::String(string),
+            AttributeValue::Bytes(bytes) => bytes.into(),
+            AttributeValue::Array(values) => serde_json::Value::Array(
+                values
+                    .into_iter()
+                    .map(AttributeValue::into_json)
+                    .collect::<Result<_, _>>()?,
+            ),
+            AttributeValue::Object(fields) => serde_json::Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, value.into_json()?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+        })
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            AttributeValue::Null => "null".to_string(),
+            AttributeValue::Bool(b) => b.to_string(),
+            AttributeValue::I64(i) => i.to_string(),
+            AttributeValue::U64(u) => u.to_string(),
+            AttributeValue::F64(f) => f.to_string(),
+            AttributeValue::String(string) => format!("{:?}", string),
+            AttributeValue::Bytes(bytes) => format!("{} bytes", bytes.len()),
+            AttributeValue::Array(values) => format!("an array of {} values", values.len()),
+            AttributeValue::Object(fields) => format!("an object of {} fields", fields.len()),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for AttributeValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            AttributeValue::Null => visitor.visit_unit(),
+            AttributeValue::Bool(b) => visitor.visit_bool(b),
+            AttributeValue::I64(i) => visitor.visit_i64(i),
+            AttributeValue::U64(u) => visitor.visit_u64(u),
+            AttributeValue::F64(f) => visitor.visit_f64(f),
+            AttributeValue::String(string) => visitor.visit_string(string),
+            AttributeValue::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            AttributeValue::Array(values) => {
+                visitor.visit_seq(SeqDeserializer::new(values.into_iter()))
+            }
+            AttributeValue::Object(fields) => {
+                visitor.visit_map(MapDeserializer::new(fields.into_iter()))
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            AttributeValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    // a `Vec<u8>` asks for a sequence, even of a `bytea`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            AttributeValue::Bytes(bytes) => {
+                visitor.visit_seq(SeqDeserializer::new(bytes.into_iter()))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            AttributeValue::String(variant) => variant
+                .into_deserializer()
+                .deserialize_enum(name, variants, visitor),
+            // anything else was a JSON object of the variant and its value
+            value => de::Deserializer::deserialize_enum(
+                value.into_json().map_err(de::Error::custom)?,
+                name,
+                variants,
+                visitor,
+            )
+            .map_err(de::Error::custom),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for AttributeValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Serializes a Rust value into an [`AttributeValue`]
+pub(super) struct AttributeSerializer;
+
+impl ser::Serializer for AttributeSerializer {
+    type Ok = AttributeValue;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeVariant<SerializeObject>;
+
+    fn serialize_bool(self, v: bool) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::I64(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::I64(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::I64(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::I64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::U64(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::U64(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::U64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::U64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::F64(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<AttributeValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<AttributeValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Object(vec![(
+            variant.to_string(),
+            value.serialize(self)?,
+        )]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeArray>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject {
+            fields: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeObject>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+pub(super) struct SerializeArray(Vec<AttributeValue>);
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(AttributeSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub(super) struct SerializeObject {
+    fields: Vec<(String, AttributeValue)>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(match key.serialize(AttributeSerializer)? {
+            AttributeValue::String(key) => key,
+            AttributeValue::I64(i) => i.to_string(),
+            AttributeValue::U64(u) => u.to_string(),
+            key => {
+                return Err(ser::Error::custom(format!(
+                    "map keys must be strings or integers, not {}",
+                    key.describe()
+                )))
+            }
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value() was called before serialize_key()");
+        self.fields
+            .push((key, value.serialize(AttributeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        Ok(AttributeValue::Object(self.fields))
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.fields
+            .push((key.to_string(), value.serialize(AttributeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// An enum variant with fields, which is an object of the variant and its fields, as in JSON
+pub(super) struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, value: AttributeValue) -> AttributeValue {
+        AttributeValue::Object(vec![(variant.to_string(), value)])
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeSeq::end(self.inner)?,
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeObject> {
+    type Ok = AttributeValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<AttributeValue, Error> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeMap::end(self.inner)?,
+        ))
+    }
+}
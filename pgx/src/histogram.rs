@@ -0,0 +1,208 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Latency histograms in shared memory, which every backend records into without a lock
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Bucket 0 counts zeros, and bucket `i` counts values from `2^(i - 1)` to `2^i - 1`
+const BUCKETS: usize = 65;
+
+/// A histogram of `u64` values, such as latencies, in Postgres shared memory.
+///
+/// Values are counted in exponential buckets, each twice as wide as the one before: `0`, `1`,
+/// `2..=3`, `4..=7`, and so on up to `u64::MAX`.  Recording a value is two atomic additions, so
+/// any number of backends and background workers can record into the same histogram without
+/// waiting for each other.
+///
+/// The histogram lives in shared memory, so it must be initialized like the other shared memory
+/// types, with `#[pg_shmem]` or `pg_shmem_init!()`, and read with a set-returning function of the
+/// extension's own:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_shmem]
+/// static LOOKUP_LATENCY: PgHistogram = PgHistogram::new();
+///
+/// #[pg_extern]
+/// fn lookup(key: i64) -> Option<String> {
+///     LOOKUP_LATENCY.time(|| {
+///         Spi::get_one(&format!("SELECT value FROM cache WHERE key = {}", key))
+///     })
+/// }
+///
+/// #[pg_extern]
+/// fn lookup_latency() -> impl std::iter::Iterator<
+///     Item = (
+///         name!(lower_bound, i64),
+///         name!(upper_bound, i64),
+///         name!(count, i64),
+///     ),
+/// > {
+///     LOOKUP_LATENCY.rows()
+/// }
+/// ```
+///
+/// The histogram doesn't know what its values measure.  [`PgHistogram::time`] and
+/// [`PgHistogram::record_duration`] record microseconds, and anything else can be recorded with
+/// [`PgHistogram::record`] in whatever unit suits it, as long as a histogram only has the one.
+pub struct PgHistogram {
+    inner: OnceCell<*mut PgHistogramInner>,
+}
+
+unsafe impl Send for PgHistogram {}
+unsafe impl Sync for PgHistogram {}
+
+/// What a [`PgHistogram`] keeps in shared memory
+#[repr(C)]
+pub struct PgHistogramInner {
+    buckets: [AtomicU64; BUCKETS],
+    sum: AtomicU64,
+}
+
+impl Default for PgHistogramInner {
+    fn default() -> Self {
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        PgHistogramInner {
+            buckets: [ZERO; BUCKETS],
+            sum: AtomicU64::new(0),
+        }
+    }
+}
+
+/// One of a [`PgHistogram`]'s buckets, and how many values recorded so far fell into it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// The smallest value in the bucket
+    pub lower_bound: u64,
+    /// The largest value in the bucket, which is inclusive
+    pub upper_bound: u64,
+    pub count: u64,
+}
+
+impl PgHistogram {
+    /// Create an empty histogram which can be created as a global, and is attached to shared
+    /// memory later
+    pub const fn new() -> Self {
+        PgHistogram {
+            inner: OnceCell::new(),
+        }
+    }
+
+    /// Attach an empty PgHistogram to its shared memory
+    pub fn attach(&self, value: *mut PgHistogramInner) {
+        self.inner
+            .set(value)
+            .expect("Can't attach, PgHistogram is not in an empty state");
+    }
+
+    fn inner(&self) -> &PgHistogramInner {
+        unsafe {
+            self.inner
+                .get()
+                .expect("PgHistogram has not been initialized")
+                .as_ref()
+                .unwrap()
+        }
+    }
+
+    /// Count `value` in its bucket
+    pub fn record(&self, value: u64) {
+        let inner = self.inner();
+        inner.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        inner.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Count `duration`, in microseconds
+    pub fn record_duration(&self, duration: Duration) {
+        self.record(duration.as_micros().min(u64::MAX as u128) as u64);
+    }
+
+    /// Run `f`, and count how long it took in microseconds.  Nothing is counted if `f` raises an
+    /// `ERROR` or panics.
+    pub fn time<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.record_duration(start.elapsed());
+        result
+    }
+
+    /// The number of values recorded
+    pub fn count(&self) -> u64 {
+        self.inner()
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// The sum of the values recorded, which wraps around if it overflows
+    pub fn sum(&self) -> u64 {
+        self.inner().sum.load(Ordering::Relaxed)
+    }
+
+    /// Every bucket, from the smallest values to the largest, including the empty ones.
+    ///
+    /// Each bucket is read on its own, so values recorded while reading may be in some of them
+    /// and not others.
+    pub fn buckets(&self) -> Vec<HistogramBucket> {
+        self.inner()
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(index, count)| {
+                let (lower_bound, upper_bound) = bucket_bounds(index);
+                HistogramBucket {
+                    lower_bound,
+                    upper_bound,
+                    count: count.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    /// The `(lower_bound, upper_bound, count)` of each bucket that isn't empty, for a
+    /// set-returning function to return.  Bounds beyond `i64::MAX` are `i64::MAX`.
+    pub fn rows(&self) -> impl Iterator<Item = (i64, i64, i64)> {
+        let clamp = |value: u64| value.min(i64::MAX as u64) as i64;
+        self.buckets()
+            .into_iter()
+            .filter(|bucket| bucket.count > 0)
+            .map(move |bucket| {
+                (
+                    clamp(bucket.lower_bound),
+                    clamp(bucket.upper_bound),
+                    clamp(bucket.count),
+                )
+            })
+    }
+
+    /// Empty every bucket.  Values recorded while resetting may or may not be kept.
+    pub fn reset(&self) {
+        let inner = self.inner();
+        for bucket in inner.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        inner.sum.store(0, Ordering::Relaxed);
+    }
+}
+
+fn bucket_index(value: u64) -> usize {
+    (u64::BITS - value.leading_zeros()) as usize
+}
+
+fn bucket_bounds(index: usize) -> (u64, u64) {
+    match index {
+        0 => (0, 0),
+        64 => (1 << 63, u64::MAX),
+        _ => (1 << (index - 1), (1 << index) - 1),
+    }
+}
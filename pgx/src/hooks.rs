@@ -8,9 +8,48 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! A trait and registration system for hooking Postgres internal operations such as its planner and executor
+//!
+//! Implement [`PgHooks`], overriding the hooks you care about, and [`register_hook`] a `'static`
+//! instance of it from your extension's `_PG_init()`.  Each hook is handed the previous hook (or
+//! Postgres' standard implementation) as `prev_hook`, which it should call unless it means to
+//! replace that behavior entirely.  Rust panics in a hook are raised as Postgres `ERROR`s.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct Auditor;
+//!
+//! impl PgHooks for Auditor {
+//!     fn executor_start(
+//!         &mut self,
+//!         query_desc: PgBox<pg_sys::QueryDesc>,
+//!         eflags: i32,
+//!         prev_hook: fn(PgBox<pg_sys::QueryDesc>, i32) -> HookResult<()>,
+//!     ) -> HookResult<()> {
+//!         info!("executing a {} statement", query_desc.operation);
+//!         prev_hook(query_desc, eflags)
+//!     }
+//! }
+//!
+//! static mut AUDITOR: Auditor = Auditor;
+//!
+//! #[allow(non_snake_case)]
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     unsafe { register_hook(&mut AUDITOR) };
+//! }
+//! ```
 use crate::{pg_guard, pg_sys, void_mut_ptr, PgBox, PgList};
 use std::ops::Deref;
 
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+/// Stands in for `pg_sys::JumbleState`, which only exists on Postgres 14.  Before then
+/// [`PgHooks::post_parse_analyze`] is always given `None`.
+pub struct JumbleState {}
+
+#[cfg(feature = "pg14")]
+pub use pg_sys::JumbleState;
+
 pub struct HookResult<T> {
     pub inner: T,
 }
@@ -139,6 +178,22 @@ pub trait PgHooks {
         prev_hook(parse, query_string, cursor_options, bound_params)
     }
 
+    /// Hook for plugins to get control at the end of parse analysis, such as `pg_stat_statements`
+    /// uses to fingerprint queries.  `jumble_state` is only provided on Postgres 14 and later.
+    fn post_parse_analyze(
+        &mut self,
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        jumble_state: Option<PgBox<JumbleState>>,
+        prev_hook: fn(
+            parse_state: PgBox<pg_sys::ParseState>,
+            query: PgBox<pg_sys::Query>,
+            jumble_state: Option<PgBox<JumbleState>>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(parse_state, query, jumble_state)
+    }
+
     /// Called when the transaction aborts
     fn abort(&mut self) {}
 
@@ -155,6 +210,7 @@ struct Hooks {
     prev_executor_check_perms_hook: pg_sys::ExecutorCheckPerms_hook_type,
     prev_process_utility_hook: pg_sys::ProcessUtility_hook_type,
     prev_planner_hook: pg_sys::planner_hook_type,
+    prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook_type,
 }
 
 static mut HOOKS: Option<Hooks> = None;
@@ -187,6 +243,9 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         prev_planner_hook: pg_sys::planner_hook
             .replace(pgx_planner)
             .or(Some(pgx_standard_planner_wrapper)),
+        prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook
+            .replace(pgx_post_parse_analyze)
+            .or(Some(pgx_standard_post_parse_analyze_wrapper)),
     });
 
     unsafe extern "C" fn xact_callback(event: pg_sys::XactEvent, _: void_mut_ptr) {
@@ -486,6 +545,75 @@ unsafe extern "C" fn pgx_planner_impl(
     .inner
 }
 
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_post_parse_analyze(
+    parse_state: *mut pg_sys::ParseState,
+    query: *mut pg_sys::Query,
+) {
+    fn prev(
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        _jumble_state: Option<PgBox<JumbleState>>,
+    ) -> HookResult<()> {
+        HookResult::new(unsafe {
+            (HOOKS
+                .as_mut()
+                .unwrap()
+                .prev_post_parse_analyze_hook
+                .as_ref()
+                .unwrap())(parse_state.into_pg(), query.into_pg())
+        })
+    }
+    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    hook.post_parse_analyze(
+        PgBox::from_pg(parse_state),
+        PgBox::from_pg(query),
+        None,
+        prev,
+    )
+    .inner
+}
+
+#[cfg(feature = "pg14")]
+#[pg_guard]
+unsafe extern "C" fn pgx_post_parse_analyze(
+    parse_state: *mut pg_sys::ParseState,
+    query: *mut pg_sys::Query,
+    jumble_state: *mut JumbleState,
+) {
+    fn prev(
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        jumble_state: Option<PgBox<JumbleState>>,
+    ) -> HookResult<()> {
+        HookResult::new(unsafe {
+            (HOOKS
+                .as_mut()
+                .unwrap()
+                .prev_post_parse_analyze_hook
+                .as_ref()
+                .unwrap())(
+                parse_state.into_pg(),
+                query.into_pg(),
+                jumble_state.map_or(std::ptr::null_mut(), |js| js.into_pg()),
+            )
+        })
+    }
+    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    hook.post_parse_analyze(
+        PgBox::from_pg(parse_state),
+        PgBox::from_pg(query),
+        if jumble_state.is_null() {
+            None
+        } else {
+            Some(PgBox::from_pg(jumble_state))
+        },
+        prev,
+    )
+    .inner
+}
+
 #[pg_guard]
 unsafe extern "C" fn pgx_standard_executor_start_wrapper(
     query_desc: *mut pg_sys::QueryDesc,
@@ -588,3 +716,22 @@ unsafe extern "C" fn pgx_standard_planner_wrapper(
 ) -> *mut pg_sys::PlannedStmt {
     pg_sys::standard_planner(parse, query_string, cursor_options, bound_params)
 }
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_post_parse_analyze_wrapper(
+    _parse_state: *mut pg_sys::ParseState,
+    _query: *mut pg_sys::Query,
+) {
+    // Postgres has no standard post-parse-analysis behavior
+}
+
+#[cfg(feature = "pg14")]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_post_parse_analyze_wrapper(
+    _parse_state: *mut pg_sys::ParseState,
+    _query: *mut pg_sys::Query,
+    _jumble_state: *mut JumbleState,
+) {
+    // Postgres has no standard post-parse-analysis behavior
+}
@@ -60,3 +60,196 @@ pub trait JsonInOutFuncs<'de>: serde::de::Deserialize<'de> + serde::ser::Seriali
         serde_json::to_writer(buffer, self).expect("failed to serialize to json")
     }
 }
+
+/// `#[derive(PostgresType)]` types with the `#[sendrecvfuncs]` attribute implement this trait to
+/// provide the binary send/receive functions, which are used instead of the text ones by clients
+/// asking for the binary format, and by `COPY ... (FORMAT binary)`
+pub trait SendRecvFuncs {
+    /// Given the binary representation of `Self` a client sent, parse it into `Self`.
+    ///
+    /// It is expected that malformed input will raise an `error!()` or `panic!()`
+    fn recv(input: &[u8]) -> Self
+    where
+        Self: Sized;
+
+    /// Convert `Self` into its binary representation, to send to a client
+    fn send(&self) -> Vec<u8>;
+}
+
+/// Automatically implemented for `#[derive(Serialize, Deserialize, PostgresType)]` types with the
+/// `#[sendrecvfuncs(cbor)]` attribute, whose binary representation is their CBOR encoding
+pub trait CborSendRecvFuncs: serde::de::DeserializeOwned + serde::ser::Serialize {
+    /// Uses `serde_cbor` to deserialize the input, which is assumed to be CBOR
+    fn recv(input: &[u8]) -> Self {
+        serde_cbor::from_slice(input).expect("failed to deserialize cbor")
+    }
+
+    /// Uses `serde_cbor` to serialize `Self` into CBOR
+    fn send(&self) -> Vec<u8> {
+        serde_cbor::to_vec(self).expect("failed to serialize to cbor")
+    }
+}
+
+/// Whether a type's conversion function is working with its text or its binary representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoFormat {
+    /// The input and output functions, for SQL literals, the text format of the protocol, and
+    /// casts to and from `text`
+    Text,
+    /// The send and receive functions, for the binary format of the extended query protocol and
+    /// `COPY ... (FORMAT binary)`
+    Binary,
+}
+
+/// Whether a type's conversion function is making a value from its representation, or the other
+/// way around
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoDirection {
+    Input,
+    Output,
+}
+
+thread_local! {
+    static IO_CONTEXT: std::cell::Cell<Option<IoContext>> = std::cell::Cell::new(None);
+}
+
+/// What the `#[derive(PostgresType)]` conversion function running now is doing, for codecs which
+/// share code between the text and binary formats but want to do something different for each.
+///
+/// Postgres calls a type's input and output functions for the text format, and the send and
+/// receive functions `#[sendrecvfuncs]` adds for the binary format, so [`IoContext::format`] tells
+/// which is being serviced even when one calls the other:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PostgresType)]
+/// #[inoutfuncs]
+/// #[sendrecvfuncs]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// impl Point {
+///     fn parse(text: &str) -> Self {
+///         let (x, y) = text.trim_matches(|c| c == '(' || c == ')').split_once(',').unwrap();
+///         Point { x: x.trim().parse().unwrap(), y: y.trim().parse().unwrap() }
+///     }
+/// }
+///
+/// impl InOutFuncs for Point {
+///     fn input(input: &cstr_core::CStr) -> Self {
+///         Point::parse(input.to_str().expect("input is not valid UTF8"))
+///     }
+///
+///     fn output(&self, buffer: &mut StringInfo) {
+///         // a human reading `psql` wants it spaced out, a driver parsing it doesn't
+///         match IoContext::current() {
+///             Some(context) if context.is_binary() => {
+///                 buffer.push_str(&format!("({},{})", self.x, self.y))
+///             }
+///             _ => buffer.push_str(&format!("({}, {})", self.x, self.y)),
+///         }
+///     }
+/// }
+///
+/// impl SendRecvFuncs for Point {
+///     fn recv(input: &[u8]) -> Self {
+///         Point::parse(std::str::from_utf8(input).expect("input is not valid UTF8"))
+///     }
+///
+///     fn send(&self) -> Vec<u8> {
+///         let mut buffer = StringInfo::new();
+///         self.output(&mut buffer);
+///         buffer.as_bytes().to_vec()
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoContext {
+    format: IoFormat,
+    direction: IoDirection,
+}
+
+impl IoContext {
+    /// The conversion running now, or `None` outside a type's conversion functions
+    pub fn current() -> Option<IoContext> {
+        IO_CONTEXT.with(|context| context.get())
+    }
+
+    /// Note that a conversion is running, until the returned guard is dropped.  The functions
+    /// `#[derive(PostgresType)]` generates call this.
+    #[doc(hidden)]
+    pub fn enter(format: IoFormat, direction: IoDirection) -> IoContextGuard {
+        let previous =
+            IO_CONTEXT.with(|context| context.replace(Some(IoContext { format, direction })));
+        IoContextGuard { previous }
+    }
+
+    /// Text or binary
+    pub fn format(&self) -> IoFormat {
+        self.format
+    }
+
+    /// Input or output
+    pub fn direction(&self) -> IoDirection {
+        self.direction
+    }
+
+    /// Is this the binary format?
+    pub fn is_binary(&self) -> bool {
+        self.format == IoFormat::Binary
+    }
+
+    /// The name of the client's encoding, such as `UTF8`.
+    ///
+    /// Text is in the database's encoding, and Postgres converts it to the client's after the
+    /// output function, but a send function writing text into its binary representation is
+    /// expected to convert it itself.
+    pub fn client_encoding(&self) -> &'static str {
+        unsafe {
+            std::ffi::CStr::from_ptr(pg_sys::pg_get_client_encoding_name())
+                .to_str()
+                .expect("encoding name is not valid UTF8")
+        }
+    }
+
+    /// The name of the database's encoding, which text is in, such as `UTF8`
+    pub fn database_encoding(&self) -> &'static str {
+        unsafe {
+            std::ffi::CStr::from_ptr(pg_sys::GetDatabaseEncodingName())
+                .to_str()
+                .expect("encoding name is not valid UTF8")
+        }
+    }
+}
+
+/// Restores the [`IoContext`] from before the conversion when dropped
+#[doc(hidden)]
+pub struct IoContextGuard {
+    previous: Option<IoContext>,
+}
+
+impl Drop for IoContextGuard {
+    fn drop(&mut self) {
+        let previous = self.previous;
+        IO_CONTEXT.with(|context| context.set(previous));
+    }
+}
+
+/// The unread bytes of the message a receive function was given, which are all marked as read,
+/// as Postgres expects.  The functions `#[derive(PostgresType)]` generates call this.
+#[doc(hidden)]
+pub fn recv_message(message: &Internal) -> &[u8] {
+    unsafe {
+        let buf = message
+            .get_mut::<pg_sys::StringInfoData>()
+            .expect("receive function called without a message");
+        let start = buf.cursor as usize;
+        let len = buf.len as usize;
+        buf.cursor = buf.len;
+        std::slice::from_raw_parts((buf.data as *const u8).add(start), len - start)
+    }
+}
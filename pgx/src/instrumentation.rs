@@ -0,0 +1,128 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Custom counters and timers for finding out where an extension's functions spend their time
+use crate::pg_sys;
+use std::time::{Duration, Instant};
+
+/// The total of one of the [`Instrumentation`] counters or timers, for the last statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrumentationCounter {
+    /// The name it was recorded with
+    pub name: &'static str,
+    /// How much was counted, or how many times it was timed
+    pub count: u64,
+    /// How long was spent in total, which is zero if it was only counted
+    pub elapsed: Duration,
+}
+
+struct Counters {
+    /// The start of the statement the counters belong to
+    statement_start: pg_sys::TimestampTz,
+    counters: Vec<InstrumentationCounter>,
+}
+
+static mut COUNTERS: Counters = Counters {
+    statement_start: 0,
+    counters: Vec::new(),
+};
+
+/// Counters and timers that functions can record as they go, such as how many rows they filtered
+/// out or how long they spent parsing, to help see why a query using them is slow.
+///
+/// Everything recorded during a statement, including by queries it runs through SPI, is added
+/// together, and replaces what was recorded during earlier statements.  Recording is cheap: it
+/// doesn't allocate once a name has been used, and names are compared by address first.
+///
+/// The totals can be read by a function of the extension's own, which is called after the
+/// statement to investigate, such as an `EXPLAIN ANALYZE`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn matches(document: &str, pattern: &str) -> bool {
+///     let words = Instrumentation::time("tokenize", || document.split_whitespace().count());
+///     Instrumentation::count("words", words as u64);
+///     document.contains(pattern)
+/// }
+///
+/// /// `EXPLAIN ANALYZE SELECT ...; SELECT * FROM last_statement_counters();`
+/// #[pg_extern]
+/// fn last_statement_counters() -> impl Iterator<
+///     Item = (
+///         name!(name, &'static str),
+///         name!(count, i64),
+///         name!(milliseconds, f64),
+///     ),
+/// > {
+///     Instrumentation::counters().into_iter().map(|counter| {
+///         (
+///             counter.name,
+///             counter.count as i64,
+///             counter.elapsed.as_secs_f64() * 1000.0,
+///         )
+///     })
+/// }
+/// ```
+pub struct Instrumentation;
+
+impl Instrumentation {
+    /// Add `n` to the counter `name`
+    #[inline]
+    pub fn count(name: &'static str, n: u64) {
+        Self::record(name, n, Duration::ZERO);
+    }
+
+    /// Run `f`, adding how long it took to the timer `name`, and returning what it returned
+    #[inline]
+    pub fn time<R, F: FnOnce() -> R>(name: &'static str, f: F) -> R {
+        let start = Instant::now();
+        let result = f();
+        Self::record(name, 1, start.elapsed());
+        result
+    }
+
+    /// The totals of every counter and timer recorded during the last statement which recorded
+    /// any, in the order they were first recorded
+    pub fn counters() -> Vec<InstrumentationCounter> {
+        unsafe { COUNTERS.counters.clone() }
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset() {
+        unsafe { COUNTERS.counters.clear() }
+    }
+
+    fn record(name: &'static str, count: u64, elapsed: Duration) {
+        unsafe {
+            let statement_start = pg_sys::GetCurrentStatementStartTimestamp();
+            if COUNTERS.statement_start != statement_start {
+                COUNTERS.statement_start = statement_start;
+                COUNTERS.counters.clear();
+            }
+
+            let existing = COUNTERS
+                .counters
+                .iter_mut()
+                .find(|counter| std::ptr::eq(counter.name, name) || counter.name == name);
+            match existing {
+                Some(counter) => {
+                    counter.count += count;
+                    counter.elapsed += elapsed;
+                }
+                None => COUNTERS.counters.push(InstrumentationCounter {
+                    name,
+                    count,
+                    elapsed,
+                }),
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Responding to query cancellation and backend termination from long-running Rust code, and
+//! holding them off where they mustn't happen
+use crate::pg_sys;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Is an interrupt pending?
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+#[inline]
+pub fn interrupt_pending() -> bool {
+    unsafe { pg_sys::InterruptPending }
+}
+
+/// Is an interrupt pending?
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[inline]
+pub fn interrupt_pending() -> bool {
+    unsafe { pg_sys::InterruptPending != 0 }
+}
+
+/// If an interrupt is pending (perhaps a user-initiated "cancel query" message to this backend),
+/// this will safely abort the current transaction.
+///
+/// This is Postgres' `CHECK_FOR_INTERRUPTS()`.  The `ERROR` it raises for a cancel, or the `FATAL`
+/// for `pg_terminate_backend()`, unwinds the Rust stack as a panic, running destructors on the way,
+/// before Postgres sees it.  Call it every so often in any loop that could run for a while, so
+/// `pg_cancel_backend()` and `statement_timeout` can stop it:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn count_collatz_steps(mut n: i64) -> i64 {
+///     let mut steps = 0;
+///     while n > 1 {
+///         check_for_interrupts!();
+///         n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+///         steps += 1;
+///     }
+///     steps
+/// }
+/// ```
+///
+/// It does nothing while an [`InterruptGuard`] is held, and the interrupt stays pending until the
+/// next check after it's released.
+#[macro_export]
+macro_rules! check_for_interrupts {
+    () => {
+        if $crate::interrupt_pending() {
+            #[allow(unused_unsafe)]
+            unsafe {
+                $crate::pg_sys::ProcessInterrupts();
+            }
+        }
+    };
+}
+
+/// Holds off interrupts until it's dropped, as Postgres' `HOLD_INTERRUPTS()` and
+/// `RESUME_INTERRUPTS()` do, for work that can't be left half done, such as updating shared
+/// memory that other backends read.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn apply_all(changes: &[i32]) {
+///     let _guard = InterruptGuard::hold();
+///     for change in changes {
+///         // a cancel arriving here waits for the loop to finish
+///         check_for_interrupts!();
+///         // ...
+///     }
+/// }
+/// ```
+///
+/// A pending interrupt is processed by the first [`check_for_interrupts!()`] after the last guard is
+/// dropped.  Guards can be nested, and each only releases its own hold.  An `ERROR` raised while one
+/// is held releases every hold, as it would in C.
+pub struct InterruptGuard {
+    cancel_only: bool,
+    // the counts are per backend, so a guard belongs to the thread that took it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl InterruptGuard {
+    /// Hold off every interrupt, including `pg_terminate_backend()`, as `HOLD_INTERRUPTS()` does
+    pub fn hold() -> Self {
+        unsafe {
+            pg_sys::InterruptHoldoffCount += 1;
+        }
+        InterruptGuard {
+            cancel_only: false,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Hold off only query cancellation, such as by `pg_cancel_backend()` or `statement_timeout`,
+    /// as `HOLD_CANCEL_INTERRUPTS()` does.  The backend can still be terminated.
+    pub fn hold_cancel() -> Self {
+        unsafe {
+            pg_sys::QueryCancelHoldoffCount += 1;
+        }
+        InterruptGuard {
+            cancel_only: true,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // an ERROR zeroes the counts before it unwinds to us
+        unsafe {
+            if self.cancel_only {
+                pg_sys::QueryCancelHoldoffCount = pg_sys::QueryCancelHoldoffCount.saturating_sub(1);
+            } else {
+                pg_sys::InterruptHoldoffCount = pg_sys::InterruptHoldoffCount.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// An iterator which checks for interrupts before each item.  See
+/// [`InterruptibleIterator::check_for_interrupts`].
+pub struct Interruptible<I> {
+    iter: I,
+}
+
+impl<I: Iterator> Iterator for Interruptible<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        check_for_interrupts!();
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Checking for interrupts while iterating, for long loops over iterators
+pub trait InterruptibleIterator: Iterator + Sized {
+    /// Check for interrupts before each item, so the loop over them can be cancelled:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_extern]
+    /// fn sum_of_squares(n: i64) -> i64 {
+    ///     (1..=n).check_for_interrupts().map(|i| i * i).sum()
+    /// }
+    /// ```
+    fn check_for_interrupts(self) -> Interruptible<Self> {
+        Interruptible { iter: self }
+    }
+}
+
+impl<I: Iterator> InterruptibleIterator for I {}
+
+/// Set by pgx's background worker `SIGTERM` handler, which doesn't set `ProcDiePending`
+static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Note that the process has been asked to stop, for [`CancellationToken`]s to see.  Called from
+/// signal handlers, so it only stores to an atomic.
+pub(crate) fn request_termination() {
+    TERMINATION_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn is_set(flag: *const bool) -> bool {
+    std::ptr::read_volatile(flag)
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn is_set(flag: *const pg_sys::sig_atomic_t) -> bool {
+    std::ptr::read_volatile(flag) != 0
+}
+
+/// Would the next [`check_for_interrupts!()`] raise an `ERROR` for a cancel, or a `FATAL` for a
+/// termination?  Unlike [`interrupt_pending()`], this leaves out interrupts that are held off.
+fn cancel_or_die_pending() -> bool {
+    unsafe {
+        let holdoff = std::ptr::read_volatile(std::ptr::addr_of!(pg_sys::InterruptHoldoffCount))
+            + std::ptr::read_volatile(std::ptr::addr_of!(pg_sys::CritSectionCount));
+        if holdoff != 0 {
+            return false;
+        }
+        let cancel_holdoff =
+            std::ptr::read_volatile(std::ptr::addr_of!(pg_sys::QueryCancelHoldoffCount));
+        is_set(std::ptr::addr_of!(pg_sys::ProcDiePending))
+            || (cancel_holdoff == 0 && is_set(std::ptr::addr_of!(pg_sys::QueryCancelPending)))
+    }
+}
+
+/// The error from [`CancellationToken::check`], once the work has been cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Cooperative cancellation for Rust code that can't call [`check_for_interrupts!()`] itself, such
+/// as computations on threads of their own, which mustn't call into Postgres, and futures in
+/// [`run_async`](crate::bgworkers::run_async).
+///
+/// A token is cancelled when the query is cancelled, by `pg_cancel_backend()` or
+/// `statement_timeout`, when the backend or background worker is asked to stop, or when
+/// [`cancel`](CancellationToken::cancel) is called on it or a clone of it.  Tokens are cheap to
+/// check, and to clone and send to other threads:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn count_primes(limit: u64, token: &CancellationToken) -> Result<u64, Cancelled> {
+///     let mut count = 0;
+///     for n in 2..limit {
+///         token.check()?;
+///         if (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0) {
+///             count += 1;
+///         }
+///     }
+///     Ok(count)
+/// }
+///
+/// #[pg_extern]
+/// fn primes_below(limit: i64) -> i64 {
+///     let token = CancellationToken::new();
+///     let (result_tx, result_rx) = std::sync::mpsc::channel();
+///     {
+///         let token = token.clone();
+///         std::thread::spawn(move || result_tx.send(count_primes(limit as u64, &token)));
+///     }
+///     loop {
+///         // a cancel stops the thread, and raises its ERROR here
+///         token.check_for_interrupts();
+///         match result_rx.recv_timeout(std::time::Duration::from_millis(10)) {
+///             Ok(count) => return count.unwrap_or(0) as i64,
+///             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+///             Err(e) => panic!("the thread counting primes failed: {}", e),
+///         }
+///     }
+/// }
+/// ```
+///
+/// A token doesn't raise Postgres' `ERROR` for a cancel itself.  The backend's own thread still
+/// has to, with [`CancellationToken::check_for_interrupts`], which cancels the token first, so
+/// other threads stop too.  Interrupts held off by an [`InterruptGuard`] don't cancel tokens until
+/// it's dropped.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// A token which is cancelled along with the query or the process
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel this token and its clones, without cancelling the query
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Has the query or the process been cancelled, or this token?  Once it has, it stays so.
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return true;
+        }
+        if TERMINATION_REQUESTED.load(Ordering::SeqCst) || cancel_or_die_pending() {
+            // the backend's thread clears the flags when it processes them
+            self.cancel();
+            return true;
+        }
+        false
+    }
+
+    /// `Err(Cancelled)` if the token [is cancelled](CancellationToken::is_cancelled), for `?` in
+    /// loops which should stop
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// [`check_for_interrupts!()`], from the backend's own thread, after cancelling the token if
+    /// the interrupt it would process is a cancel or a termination, which it forgets once it's
+    /// raised the `ERROR` or `FATAL`
+    pub fn check_for_interrupts(&self) {
+        self.is_cancelled();
+        check_for_interrupts!();
+    }
+
+    /// Wait until the token is cancelled, such as in a `tokio::select!` against the work it
+    /// cancels.  This needs pgx's `async` feature, and a runtime with its timer enabled.
+    #[cfg(feature = "async")]
+    pub async fn cancelled(&self) {
+        // the flags are set by signal handlers, which can't wake a future, so they're polled
+        while !self.is_cancelled() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+}
@@ -34,33 +34,74 @@ extern crate bitflags;
 pub use pgx_macros::*;
 
 pub mod aggregate;
+pub mod backend_state;
+pub mod backend_status;
+pub mod brin;
 pub mod callbacks;
+pub mod chunked;
+pub mod copy;
 pub mod datum;
+pub mod dsm;
 pub mod enum_helper;
+pub mod event_trigger;
 pub mod fcinfo;
+pub mod fdw;
+pub mod formatting;
+pub mod gin;
+pub mod gist;
 pub mod guc;
+pub mod heap_tuple;
+pub mod histogram;
 pub mod hooks;
 pub mod htup;
 pub mod inoutfuncs;
+pub mod instrumentation;
+#[macro_use]
+pub mod interrupts;
 pub mod itemptr;
 pub mod list;
 #[macro_use]
 pub mod log;
 pub mod atomics;
 pub mod bgworkers;
+pub mod lock;
 pub mod lwlock;
 pub mod memcxt;
 pub mod misc;
 pub mod namespace;
 pub mod nodes;
+pub mod packed_args;
+pub mod page_inspect;
+pub mod panic_backtraces;
+pub mod parallel;
 pub mod pgbox;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod planner_support;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+pub mod recovery;
 pub mod rel;
+pub mod retry;
+pub mod roles;
+pub mod server_features;
+pub mod session;
 pub mod shmem;
+pub mod slow_calls;
 pub mod spi;
+pub mod spinlock;
+pub mod status_view;
 pub mod stringinfo;
+pub mod table_row;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod tableam;
+pub mod timezone;
 pub mod trigger_support;
 pub mod tupdesc;
 pub mod varlena;
+pub mod wait_event;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod window;
 pub mod wrappers;
 pub mod xid;
 
@@ -69,29 +110,60 @@ pub use once_cell;
 
 pub use aggregate::*;
 pub use atomics::*;
+pub use backend_state::*;
+pub use brin::*;
 pub use callbacks::*;
+pub use chunked::*;
+pub use copy::*;
 pub use datum::*;
 pub use enum_helper::*;
+pub use event_trigger::*;
 pub use fcinfo::*;
+pub use fdw::*;
+pub use formatting::*;
+pub use gin::*;
+pub use gist::*;
 pub use guc::*;
+pub use heap_tuple::*;
+pub use histogram::*;
 pub use hooks::*;
 pub use htup::*;
 pub use inoutfuncs::*;
+pub use instrumentation::*;
+pub use interrupts::*;
 pub use itemptr::*;
 pub use list::*;
+pub use lock::*;
 pub use log::*;
 pub use lwlock::*;
 pub use memcxt::*;
 pub use namespace::*;
 pub use nodes::*;
+pub use packed_args::*;
+pub use page_inspect::*;
+pub use panic_backtraces::*;
 pub use pgbox::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use planner_support::*;
 pub use rel::*;
+pub use retry::*;
+pub use roles::*;
+pub use server_features::*;
 pub use shmem::*;
+pub use slow_calls::*;
 pub use spi::*;
+pub use spinlock::*;
+pub use status_view::*;
 pub use stringinfo::*;
+pub use table_row::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use tableam::*;
+pub use timezone::*;
 pub use trigger_support::*;
 pub use tupdesc::*;
 pub use varlena::*;
+pub use wait_event::*;
+pub use window::*;
 pub use wrappers::*;
 pub use xid::*;
 
@@ -216,23 +288,68 @@ pub static DEFAULT_TYPEID_SQL_MAPPING: Lazy<HashSet<RustSqlMapping>> = Lazy::new
     map_type!(m, f64, "double precision");
     map_type!(m, datum::JsonB, "jsonb");
     map_type!(m, datum::Json, "json");
+    map_type!(m, datum::JsonbRef<'static>, "jsonb");
     map_type!(m, pgx_pg_sys::ItemPointerData, "tid");
     map_type!(m, pgx_pg_sys::Point, "point");
     map_type!(m, pgx_pg_sys::BOX, "box");
+    map_type!(m, pgx_pg_sys::LSEG, "lseg");
+    map_type!(m, pgx_pg_sys::CIRCLE, "circle");
+    map_type!(m, datum::Path, "path");
+    map_type!(m, datum::Polygon, "polygon");
     map_type!(m, Date, "date");
+    map_type!(m, Interval, "interval");
     map_type!(m, Time, "time");
     map_type!(m, TimeWithTimeZone, "time with time zone");
     map_type!(m, Timestamp, "timestamp");
     map_type!(m, TimestampWithTimeZone, "timestamp with time zone");
+    map_type!(m, time::Date, "date");
+    map_type!(m, time::Time, "time");
+    map_type!(m, time::PrimitiveDateTime, "timestamp");
+    map_type!(m, time::OffsetDateTime, "timestamp with time zone");
+    #[cfg(feature = "chrono")]
+    map_type!(m, chrono::NaiveDate, "date");
+    #[cfg(feature = "chrono")]
+    map_type!(m, chrono::NaiveTime, "time");
+    #[cfg(feature = "chrono")]
+    map_type!(m, chrono::NaiveDateTime, "timestamp");
+    #[cfg(feature = "chrono")]
+    map_type!(m, chrono::DateTime<chrono::Utc>, "timestamp with time zone");
     map_type!(m, pgx_pg_sys::PlannerInfo, "internal");
     map_type!(m, datum::Internal, "internal");
     map_type!(m, pgbox::PgBox<pgx_pg_sys::IndexAmRoutine>, "internal");
+    map_type!(m, pgbox::PgBox<pgx_pg_sys::FdwRoutine>, "fdw_handler");
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    map_type!(
+        m,
+        pgbox::PgBox<pgx_pg_sys::TableAmRoutine>,
+        "table_am_handler"
+    );
     map_type!(m, rel::PgRelation, "regclass");
     map_type!(m, datum::Numeric, "numeric");
+    #[cfg(feature = "rust_decimal")]
+    map_type!(m, rust_decimal::Decimal, "numeric");
     map_type!(m, datum::AnyElement, "anyelement");
     map_type!(m, datum::AnyArray, "anyarray");
+    map_type!(m, datum::AnyNonArray, "anynonarray");
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    map_type!(m, datum::AnyCompatible, "anycompatible");
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    map_type!(m, datum::AnyCompatibleArray, "anycompatiblearray");
     map_type!(m, datum::Inet, "inet");
+    map_type!(m, datum::Cidr, "cidr");
+    map_type!(m, datum::MacAddr, "macaddr");
+    map_type!(m, datum::MacAddr8, "macaddr8");
+    map_type!(m, datum::TsVector, "tsvector");
+    map_type!(m, datum::TsQuery, "tsquery");
     map_type!(m, datum::Uuid, "uuid");
+    #[cfg(feature = "uuid")]
+    map_type!(m, uuid::Uuid, "uuid");
+    map_type!(m, table_row::TableRow, "record");
+    map_type!(
+        m,
+        heap_tuple::PgHeapTuple<'static, AllocatedByRust>,
+        "record"
+    );
 
     m
 });
@@ -324,9 +441,15 @@ macro_rules! pg_magic_func {
             // calls this function first
             pgx::initialize();
 
+            // and set up the `#[pg_shmem]` statics, as this also runs before `_PG_init()`
+            pgx::PgSharedMem::initialize_registered();
+
             // return the magic
             &MY_MAGIC
         }
+
+        // an invalid `[package.metadata.pgx]` is reported once, here
+        $crate::check_library_naming!();
     };
 }
 
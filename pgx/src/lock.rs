@@ -0,0 +1,463 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Typed relation-level locks, as taken by `LOCK TABLE`
+//!
+//! See: [https://www.postgresql.org/docs/current/explicit-locking.html](https://www.postgresql.org/docs/current/explicit-locking.html)
+use crate::pg_sys;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static LOCK_UPGRADE_WARNINGS: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+thread_local! {
+    /// The relation locks taken through a [`PgRelationLockGuard`] in the current transaction
+    static HELD_LOCKS: RefCell<Vec<HeldLock>> = RefCell::new(Vec::new());
+}
+
+struct HeldLock {
+    lxid: pg_sys::LocalTransactionId,
+    relid: pg_sys::Oid,
+    mode: LockMode,
+}
+
+/// The table-level lock modes, from weakest to strongest
+///
+/// Each names the statements which usually take it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockMode {
+    /// Taken by `SELECT`
+    AccessShare = pg_sys::AccessShareLock as isize,
+    /// Taken by `SELECT FOR UPDATE` and `SELECT FOR SHARE`
+    RowShare = pg_sys::RowShareLock as isize,
+    /// Taken by `INSERT`, `UPDATE`, `DELETE`, and `MERGE`
+    RowExclusive = pg_sys::RowExclusiveLock as isize,
+    /// Taken by `VACUUM`, `ANALYZE`, and `CREATE INDEX CONCURRENTLY`
+    ShareUpdateExclusive = pg_sys::ShareUpdateExclusiveLock as isize,
+    /// Taken by `CREATE INDEX`
+    Share = pg_sys::ShareLock as isize,
+    /// Taken by `CREATE TRIGGER` and some forms of `ALTER TABLE`
+    ShareRowExclusive = pg_sys::ShareRowExclusiveLock as isize,
+    /// Taken by `REFRESH MATERIALIZED VIEW CONCURRENTLY`
+    Exclusive = pg_sys::ExclusiveLock as isize,
+    /// Taken by `DROP TABLE`, `TRUNCATE`, `VACUUM FULL`, and most forms of `ALTER TABLE`
+    AccessExclusive = pg_sys::AccessExclusiveLock as isize,
+}
+
+impl LockMode {
+    /// Every lock mode, from weakest to strongest
+    pub const ALL: [LockMode; 8] = [
+        LockMode::AccessShare,
+        LockMode::RowShare,
+        LockMode::RowExclusive,
+        LockMode::ShareUpdateExclusive,
+        LockMode::Share,
+        LockMode::ShareRowExclusive,
+        LockMode::Exclusive,
+        LockMode::AccessExclusive,
+    ];
+
+    /// The name Postgres uses for this lock mode, eg `AccessShareLock`
+    pub fn name(self) -> &'static str {
+        match self {
+            LockMode::AccessShare => "AccessShareLock",
+            LockMode::RowShare => "RowShareLock",
+            LockMode::RowExclusive => "RowExclusiveLock",
+            LockMode::ShareUpdateExclusive => "ShareUpdateExclusiveLock",
+            LockMode::Share => "ShareLock",
+            LockMode::ShareRowExclusive => "ShareRowExclusiveLock",
+            LockMode::Exclusive => "ExclusiveLock",
+            LockMode::AccessExclusive => "AccessExclusiveLock",
+        }
+    }
+
+    /// Does this mode conflict with `other`, so that another transaction can't take `other` while
+    /// this mode is held?
+    ///
+    /// This is Postgres' `DoLockModesConflict()`, which reads its `LockConflicts[]` table.  The
+    /// table is symmetric, so `a.conflicts_with(b) == b.conflicts_with(a)`, even for pairs such as
+    /// `RowExclusive` and `Share` where only one of them is exclusive.
+    pub fn conflicts_with(self, other: LockMode) -> bool {
+        unsafe { pg_sys::DoLockModesConflict(self.into(), other.into()) }
+    }
+
+    /// The lock modes that cannot be held by another transaction while this mode is held
+    pub fn conflicting_modes(self) -> Vec<LockMode> {
+        LockMode::ALL
+            .iter()
+            .copied()
+            .filter(|other| self.conflicts_with(*other))
+            .collect()
+    }
+}
+
+impl From<LockMode> for pg_sys::LOCKMODE {
+    fn from(mode: LockMode) -> Self {
+        mode as pg_sys::LOCKMODE
+    }
+}
+
+impl std::fmt::Display for LockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Another transaction holding, or waiting for, a lock that conflicts with one we might take
+///
+/// Its `Display` matches the `virtualtransaction` column of `pg_locks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConflictingTransaction {
+    pub backend_id: pg_sys::BackendId,
+    pub local_transaction_id: pg_sys::LocalTransactionId,
+}
+
+impl std::fmt::Display for ConflictingTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.backend_id, self.local_transaction_id)
+    }
+}
+
+/// A relation lock, released when this guard is dropped
+///
+/// Postgres would otherwise hold it until the end of the transaction, which is still possible
+/// with [`PgRelationLockGuard::hold_until_end_of_transaction`].
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct PgRelationLockGuard {
+    relid: pg_sys::Oid,
+    mode: LockMode,
+}
+
+impl PgRelationLockGuard {
+    /// Lock the relation with oid `relid` in `mode`, waiting for conflicting locks to be released
+    pub fn acquire(relid: pg_sys::Oid, mode: LockMode) -> Self {
+        check_lock_upgrade(relid, mode);
+        unsafe { pg_sys::LockRelationOid(relid, mode.into()) };
+        PgRelationLockGuard::held(relid, mode)
+    }
+
+    /// Lock the relation with oid `relid` in `mode`, or return `None` if a conflicting lock is held
+    pub fn try_acquire(relid: pg_sys::Oid, mode: LockMode) -> Option<Self> {
+        check_lock_upgrade(relid, mode);
+        if unsafe { pg_sys::ConditionalLockRelationOid(relid, mode.into()) } {
+            Some(PgRelationLockGuard::held(relid, mode))
+        } else {
+            None
+        }
+    }
+
+    /// The oid of the locked relation
+    pub fn relid(&self) -> pg_sys::Oid {
+        self.relid
+    }
+
+    /// The mode the relation is locked in
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Keep the lock until the end of the transaction, as Postgres itself does
+    pub fn hold_until_end_of_transaction(self) {
+        std::mem::forget(self)
+    }
+
+    fn held(relid: pg_sys::Oid, mode: LockMode) -> Self {
+        let lxid = current_lxid();
+        HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            held.retain(|lock| lock.lxid == lxid);
+            held.push(HeldLock { lxid, relid, mode });
+        });
+        PgRelationLockGuard { relid, mode }
+    }
+}
+
+impl Drop for PgRelationLockGuard {
+    fn drop(&mut self) {
+        HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(index) = held
+                .iter()
+                .rposition(|lock| lock.relid == self.relid && lock.mode == self.mode)
+            {
+                held.remove(index);
+            }
+        });
+        unsafe { pg_sys::UnlockRelationOid(self.relid, self.mode.into()) }
+    }
+}
+
+/// Locks several relations at once without deadlocking against other users of this type.
+///
+/// The relations are always locked in ascending oid order, so two transactions locking
+/// overlapping sets can't each hold a lock the other is waiting for.  Each attempt only takes
+/// locks which are immediately available: if one isn't, the locks taken so far are released and
+/// the attempt is retried after an exponentially growing delay.  This also avoids deadlocks with
+/// code that locks the same relations in some other order.
+///
+/// A relation added more than once is locked in the strongest of its modes.
+///
+/// ```rust,no_run
+/// use pgx::{pg_sys, LockMode, PgRelationLockSet};
+/// use std::time::Duration;
+///
+/// # fn example(parent: pg_sys::Oid, partitions: Vec<pg_sys::Oid>) {
+/// let locks = PgRelationLockSet::new()
+///     .add(parent, LockMode::ShareUpdateExclusive)
+///     .add_all(partitions, LockMode::AccessExclusive)
+///     .set_max_attempts(20)
+///     .set_backoff(Duration::from_millis(5), Duration::from_millis(500))
+///     .acquire()
+///     .expect("partitions are busy");
+/// // ... the relations are locked until `locks` is dropped ...
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgRelationLockSet {
+    relations: BTreeMap<pg_sys::Oid, LockMode>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+/// The error from [`PgRelationLockSet::acquire`] when the locks could not all be taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PgRelationLockSetError {
+    /// The relation whose lock was unavailable on the last attempt
+    pub relid: pg_sys::Oid,
+    /// The mode it was to be locked in
+    pub mode: LockMode,
+    /// How many attempts were made
+    pub attempts: u32,
+}
+
+impl std::fmt::Display for PgRelationLockSetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not obtain {} on relation {} after {} attempts",
+            self.mode, self.relid, self.attempts
+        )
+    }
+}
+
+impl std::error::Error for PgRelationLockSetError {}
+
+impl Default for PgRelationLockSet {
+    fn default() -> Self {
+        PgRelationLockSet {
+            relations: BTreeMap::new(),
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PgRelationLockSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock the relation with oid `relid` in `mode`
+    pub fn add(mut self, relid: pg_sys::Oid, mode: LockMode) -> Self {
+        let entry = self.relations.entry(relid).or_insert(mode);
+        *entry = (*entry).max(mode);
+        self
+    }
+
+    /// Lock each of the relations in `relids` in `mode`
+    pub fn add_all<I: IntoIterator<Item = pg_sys::Oid>>(
+        mut self,
+        relids: I,
+        mode: LockMode,
+    ) -> Self {
+        for relid in relids {
+            self = self.add(relid, mode);
+        }
+        self
+    }
+
+    /// Give up after this many attempts.  The default is 10.
+    pub fn set_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait `initial` after the first failed attempt, doubling each time up to `max`.  The
+    /// default is 10ms, up to 1s.
+    pub fn set_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Take every lock, or none of them
+    pub fn acquire(self) -> Result<PgRelationLocks, PgRelationLockSetError> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            let mut guards = Vec::with_capacity(self.relations.len());
+            let mut unavailable = None;
+            for (relid, mode) in &self.relations {
+                match PgRelationLockGuard::try_acquire(*relid, *mode) {
+                    Some(guard) => guards.push(guard),
+                    None => {
+                        unavailable = Some((*relid, *mode));
+                        break;
+                    }
+                }
+            }
+
+            let (relid, mode) = match unavailable {
+                None => return Ok(PgRelationLocks { guards }),
+                Some(unavailable) => unavailable,
+            };
+
+            // release what we hold before waiting, so we don't block anyone else meanwhile
+            drop(PgRelationLocks { guards });
+            if attempt >= self.max_attempts {
+                return Err(PgRelationLockSetError {
+                    relid,
+                    mode,
+                    attempts: attempt,
+                });
+            }
+
+            check_for_interrupts!();
+            unsafe { pg_sys::pg_usleep(backoff.as_micros() as std::os::raw::c_long) };
+            check_for_interrupts!();
+            backoff = (backoff * 2).min(self.max_backoff);
+            attempt += 1;
+        }
+    }
+}
+
+/// The locks taken by a [`PgRelationLockSet`], released in reverse order when this is dropped
+#[must_use = "the locks are released as soon as they are dropped"]
+pub struct PgRelationLocks {
+    guards: Vec<PgRelationLockGuard>,
+}
+
+impl PgRelationLocks {
+    /// The locked relations and their modes, in the order they were locked
+    pub fn iter(&self) -> impl Iterator<Item = (pg_sys::Oid, LockMode)> + '_ {
+        self.guards
+            .iter()
+            .map(|guard| (guard.relid(), guard.mode()))
+    }
+
+    /// Keep the locks until the end of the transaction, as Postgres itself does
+    pub fn hold_until_end_of_transaction(mut self) {
+        for guard in self.guards.drain(..) {
+            guard.hold_until_end_of_transaction();
+        }
+    }
+}
+
+impl Drop for PgRelationLocks {
+    fn drop(&mut self) {
+        while let Some(guard) = self.guards.pop() {
+            drop(guard);
+        }
+    }
+}
+
+/// The transactions holding, or waiting for, locks on the relation with oid `relid` which
+/// conflict with `mode`
+pub fn relation_lock_conflicts(relid: pg_sys::Oid, mode: LockMode) -> Vec<ConflictingTransaction> {
+    let is_shared = unsafe { pg_sys::IsSharedRelation(relid) };
+    let locktag = pg_sys::LOCKTAG {
+        locktag_field1: if is_shared {
+            pg_sys::InvalidOid
+        } else {
+            unsafe { pg_sys::MyDatabaseId }
+        },
+        locktag_field2: relid,
+        locktag_field3: 0,
+        locktag_field4: 0,
+        locktag_type: pg_sys::LockTagType_LOCKTAG_RELATION as u8,
+        locktag_lockmethodid: pg_sys::DEFAULT_LOCKMETHOD as u8,
+    };
+
+    #[cfg(any(feature = "pg10", feature = "pg11"))]
+    let vxids = unsafe { pg_sys::GetLockConflicts(&locktag, mode.into()) };
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    let vxids = unsafe { pg_sys::GetLockConflicts(&locktag, mode.into(), std::ptr::null_mut()) };
+
+    // the array ends with an invalid VirtualTransactionId
+    let mut conflicts = Vec::new();
+    let mut vxid = vxids;
+    while let Some(current) = unsafe { vxid.as_ref() } {
+        if current.backendId == pg_sys::InvalidBackendId
+            || current.localTransactionId == pg_sys::InvalidLocalTransactionId
+        {
+            break;
+        }
+        conflicts.push(ConflictingTransaction {
+            backend_id: current.backendId,
+            local_transaction_id: current.localTransactionId,
+        });
+        vxid = unsafe { vxid.add(1) };
+    }
+    conflicts
+}
+
+/// Enable or disable warnings when a [`PgRelationLockGuard`] upgrades a lock this transaction
+/// already holds to a stronger, conflicting mode.
+///
+/// Two transactions doing the same upgrade deadlock, as each waits for the other to release the
+/// weaker lock.  The strongest mode needed should be taken first instead.
+///
+/// The warnings are enabled by default in debug builds.
+pub fn set_lock_upgrade_warnings(enabled: bool) {
+    LOCK_UPGRADE_WARNINGS.store(enabled, Ordering::Relaxed);
+}
+
+fn check_lock_upgrade(relid: pg_sys::Oid, mode: LockMode) {
+    if !LOCK_UPGRADE_WARNINGS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let lxid = current_lxid();
+    let weaker = HELD_LOCKS.with(|held| {
+        held.borrow()
+            .iter()
+            .filter(|lock| lock.lxid == lxid && lock.relid == relid)
+            .map(|lock| lock.mode)
+            .filter(|held_mode| *held_mode < mode && mode.conflicts_with(*held_mode))
+            .max()
+    });
+
+    if let Some(weaker) = weaker {
+        let relname = unsafe { pg_sys::get_rel_name(relid) };
+        let relname = if relname.is_null() {
+            format!("oid {}", relid)
+        } else {
+            unsafe { std::ffi::CStr::from_ptr(relname) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        warning!(
+            "upgrading the lock on relation \"{}\" from {} to {} can deadlock with other transactions doing the same; take {} first instead",
+            relname,
+            weaker,
+            mode,
+            mode
+        );
+    }
+}
+
+fn current_lxid() -> pg_sys::LocalTransactionId {
+    unsafe { pg_sys::MyProc.as_ref() }
+        .map(|proc| proc.lxid)
+        .unwrap_or(pg_sys::InvalidLocalTransactionId)
+}
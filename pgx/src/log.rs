@@ -59,7 +59,7 @@ pub enum PgLogLevel {
 
 /// This list of SQL Error Codes is taken directly from Postgres 12's generated "utils/errcodes.h"
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PgSqlErrorCode {
     /// Class 00 - Successful Completion
     ERRCODE_SUCCESSFUL_COMPLETION = MAKE_SQLSTATE('0', '0', '0', '0', '0') as isize,
@@ -531,6 +531,444 @@ pub fn ereport(
     }
 }
 
+/// Take the Postgres `ERROR` a caught [`pg_sys::JumpContext`] panic unwound from, which is waiting
+/// for us in the `ErrorContext`, copying it into `memory_context` and flushing the error state so
+/// that Postgres can carry on.
+///
+/// The `ErrorData` is then the caller's to `FreeErrorData()` or `ReThrowError()`.
+pub(crate) unsafe fn take_error_data(
+    memory_context: crate::pg_sys::MemoryContext,
+) -> *mut crate::pg_sys::ErrorData {
+    crate::pg_sys::CurrentMemoryContext = memory_context;
+    let edata = crate::pg_sys::CopyErrorData();
+    crate::pg_sys::FlushErrorState();
+    edata
+}
+
+/// The message of an `ErrorData`, such as one from [`take_error_data()`]
+pub(crate) unsafe fn error_data_message(edata: *const crate::pg_sys::ErrorData) -> String {
+    if (*edata).message.is_null() {
+        String::from("unknown error")
+    } else {
+        std::ffi::CStr::from_ptr((*edata).message)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// An `ereport()` with the optional fields Postgres can give a message besides its text, which
+/// clients see as the `DETAIL`, `HINT`, and the names of the object it's about.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn set_price(price: i64) {
+///     if price < 0 {
+///         ErrorReport::new(PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, "price can't be negative")
+///             .detail(format!("The price was {}.", price))
+///             .hint("Refunds are recorded separately.")
+///             .column("price")
+///             .raise();
+///     }
+/// }
+/// ```
+///
+/// See also [`ereport!`], and [`IntoErrorReport`], for returning errors from a `#[pg_extern]`.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    sqlerrcode: PgSqlErrorCode,
+    message: String,
+    detail: Option<String>,
+    hint: Option<String>,
+    schema: Option<String>,
+    table: Option<String>,
+    column: Option<String>,
+    datatype: Option<String>,
+    constraint: Option<String>,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl ErrorReport {
+    /// A report with `sqlerrcode` and `message`, from the caller's location in the source, which
+    /// Postgres shows as its `CONTEXT`
+    #[track_caller]
+    pub fn new<S: Into<String>>(sqlerrcode: PgSqlErrorCode, message: S) -> Self {
+        ErrorReport {
+            sqlerrcode,
+            message: message.into(),
+            detail: None,
+            hint: None,
+            schema: None,
+            table: None,
+            column: None,
+            datatype: None,
+            constraint: None,
+            location: std::panic::Location::caller(),
+        }
+    }
+
+    /// A secondary message with more about the problem, `errdetail()`
+    pub fn detail<S: Into<String>>(mut self, detail: S) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// A suggestion of what to do about the problem, `errhint()`
+    pub fn hint<S: Into<String>>(mut self, hint: S) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// The schema of the object the message is about
+    pub fn schema<S: Into<String>>(mut self, schema: S) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    /// The table the message is about
+    pub fn table<S: Into<String>>(mut self, table: S) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// The column the message is about, which goes with a [`table`](ErrorReport::table)
+    pub fn column<S: Into<String>>(mut self, column: S) -> Self {
+        self.column = Some(column.into());
+        self
+    }
+
+    /// The data type the message is about
+    pub fn datatype<S: Into<String>>(mut self, datatype: S) -> Self {
+        self.datatype = Some(datatype.into());
+        self
+    }
+
+    /// The constraint the message is about
+    pub fn constraint<S: Into<String>>(mut self, constraint: S) -> Self {
+        self.constraint = Some(constraint.into());
+        self
+    }
+
+    /// The `SQLSTATE` of the report
+    pub fn sqlerrcode(&self) -> PgSqlErrorCode {
+        self.sqlerrcode
+    }
+
+    /// The primary message of the report
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Report this at `level`, which doesn't return for `ERROR` and above
+    pub fn report(self, level: PgLogLevel) {
+        use std::ffi::CString;
+        use std::os::raw::c_char;
+
+        extern "C" {
+            fn pgx_ereport_detailed(
+                level: i32,
+                code: i32,
+                message: *const c_char,
+                detail: *const c_char,
+                hint: *const c_char,
+                schema: *const c_char,
+                table: *const c_char,
+                column: *const c_char,
+                datatype: *const c_char,
+                constraint: *const c_char,
+                file: *const c_char,
+                lineno: i32,
+                colno: i32,
+            );
+        }
+
+        // the text can't have nul bytes in C, so they're replaced rather than losing the message
+        fn c_string(s: &str) -> CString {
+            CString::new(s.replace('\0', "\\0")).expect("nul bytes were replaced")
+        }
+        fn c_field(field: &Option<String>) -> Option<CString> {
+            field.as_deref().map(c_string)
+        }
+        fn as_ptr(field: &Option<CString>) -> *const c_char {
+            field
+                .as_ref()
+                .map_or(std::ptr::null(), |field| field.as_ptr())
+        }
+
+        let message = c_string(&self.message);
+        let detail = c_field(&self.detail);
+        let hint = c_field(&self.hint);
+        let schema = c_field(&self.schema);
+        let table = c_field(&self.table);
+        let column = c_field(&self.column);
+        let datatype = c_field(&self.datatype);
+        let constraint = c_field(&self.constraint);
+        let file = c_string(self.location.file());
+
+        unsafe {
+            crate::guard(|| {
+                pgx_ereport_detailed(
+                    level as i32,
+                    self.sqlerrcode as i32,
+                    message.as_ptr(),
+                    as_ptr(&detail),
+                    as_ptr(&hint),
+                    as_ptr(&schema),
+                    as_ptr(&table),
+                    as_ptr(&column),
+                    as_ptr(&datatype),
+                    as_ptr(&constraint),
+                    file.as_ptr(),
+                    self.location.line() as i32,
+                    self.location.column() as i32,
+                );
+            });
+        }
+    }
+
+    /// Raise this as an `ERROR`, aborting the transaction
+    pub fn raise(self) -> ! {
+        self.report(PgLogLevel::ERROR);
+        unreachable!("ereport(ERROR) returned")
+    }
+
+    /// A [`LazyErrorReport`] with `sqlerrcode`, whose `message` is only formatted if it's reported
+    #[track_caller]
+    pub fn lazy<S: Into<String>, F: Fn() -> S>(
+        sqlerrcode: PgSqlErrorCode,
+        message: F,
+    ) -> LazyErrorReport<F> {
+        LazyErrorReport::new(sqlerrcode, message)
+    }
+}
+
+impl std::fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ErrorReport {}
+
+/// An error a `#[pg_extern]` function can return as the `Err` of a `Result`, which raises it as
+/// an `ERROR` with the [`ErrorReport`] it converts to:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// enum TransferError {
+///     InsufficientFunds { balance: i64 },
+///     NoSuchAccount(i64),
+/// }
+///
+/// impl IntoErrorReport for TransferError {
+///     fn into_error_report(self) -> ErrorReport {
+///         match self {
+///             TransferError::InsufficientFunds { balance } => ErrorReport::new(
+///                 PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+///                 "insufficient funds",
+///             )
+///             .detail(format!("The balance is {}.", balance)),
+///             TransferError::NoSuchAccount(id) => ErrorReport::new(
+///                 PgSqlErrorCode::ERRCODE_NO_DATA_FOUND,
+///                 format!("account {} does not exist", id),
+///             ),
+///         }
+///     }
+/// }
+///
+/// #[pg_extern]
+/// fn transfer(from: i64, to: i64, amount: i64) -> Result<i64, TransferError> {
+///     Err(TransferError::NoSuchAccount(from))
+/// }
+/// ```
+///
+/// Strings and boxed `std::error::Error`s, which most other errors convert to with `?`, are
+/// reported as `ERRCODE_DATA_EXCEPTION` with their text.
+pub trait IntoErrorReport {
+    fn into_error_report(self) -> ErrorReport;
+}
+
+impl IntoErrorReport for ErrorReport {
+    fn into_error_report(self) -> ErrorReport {
+        self
+    }
+}
+
+impl IntoErrorReport for String {
+    #[track_caller]
+    fn into_error_report(self) -> ErrorReport {
+        ErrorReport::new(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, self)
+    }
+}
+
+impl IntoErrorReport for &str {
+    #[track_caller]
+    fn into_error_report(self) -> ErrorReport {
+        ErrorReport::new(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, self)
+    }
+}
+
+impl IntoErrorReport for Box<dyn std::error::Error> {
+    #[track_caller]
+    fn into_error_report(self) -> ErrorReport {
+        ErrorReport::new(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, self.to_string())
+    }
+}
+
+impl IntoErrorReport for Box<dyn std::error::Error + Send + Sync> {
+    #[track_caller]
+    fn into_error_report(self) -> ErrorReport {
+        ErrorReport::new(PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, self.to_string())
+    }
+}
+
+/// An [`ErrorReport`] whose message isn't formatted, or allocated, until it's reported, for errors
+/// that are often handled rather than reported, such as a failure to parse that the caller falls
+/// back from.
+///
+/// An `ERROR` that's raised is formatted as it's raised, even if a `pg_try()` then catches it, so
+/// a hot branch returns one of these instead, and only whatever finally reports it pays for the
+/// message:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn parse_sku(sku: &str) -> Result<i64, impl IntoErrorReport + '_> {
+///     sku.strip_prefix("SKU-")
+///         .and_then(|n| n.parse().ok())
+///         .ok_or_else(|| {
+///             ErrorReport::lazy(PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION, move || {
+///                 format!("invalid SKU: \"{}\"", sku)
+///             })
+///         })
+/// }
+///
+/// #[pg_extern]
+/// fn sku_or_default(sku: &str) -> i64 {
+///     // nothing is formatted for the SKUs that don't parse
+///     parse_sku(sku).unwrap_or(0)
+/// }
+///
+/// #[pg_extern]
+/// fn sku(sku: &str) -> i64 {
+///     parse_sku(sku).unwrap_or_else(|e| e.into_error_report().raise())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LazyErrorReport<F> {
+    sqlerrcode: PgSqlErrorCode,
+    message: F,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl<S: Into<String>, F: Fn() -> S> LazyErrorReport<F> {
+    /// A report with `sqlerrcode`, and the message `message` formats, from the caller's location in
+    /// the source
+    #[track_caller]
+    pub fn new(sqlerrcode: PgSqlErrorCode, message: F) -> Self {
+        LazyErrorReport {
+            sqlerrcode,
+            message,
+            location: std::panic::Location::caller(),
+        }
+    }
+
+    /// The `SQLSTATE` of the report, which can be known without formatting the message
+    pub fn sqlerrcode(&self) -> PgSqlErrorCode {
+        self.sqlerrcode
+    }
+
+    /// Format the message, into an [`ErrorReport`] that can be given a `detail`, `hint`, etc.
+    pub fn into_report(self) -> ErrorReport {
+        ErrorReport {
+            location: self.location,
+            ..ErrorReport::new(self.sqlerrcode, (self.message)())
+        }
+    }
+
+    /// Format the message and report it at `level`, which doesn't return for `ERROR` and above
+    pub fn report(self, level: PgLogLevel) {
+        self.into_report().report(level)
+    }
+
+    /// Format the message and raise it as an `ERROR`, aborting the transaction
+    pub fn raise(self) -> ! {
+        self.into_report().raise()
+    }
+}
+
+impl<S: Into<String>, F: Fn() -> S> IntoErrorReport for LazyErrorReport<F> {
+    fn into_error_report(self) -> ErrorReport {
+        self.into_report()
+    }
+}
+
+impl<S: Into<String>, F: Fn() -> S> std::fmt::Debug for LazyErrorReport<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyErrorReport")
+            .field("sqlerrcode", &self.sqlerrcode)
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl<S: Into<String>, F: Fn() -> S> std::fmt::Display for LazyErrorReport<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&(self.message)().into())
+    }
+}
+
+impl<S: Into<String>, F: Fn() -> S> std::error::Error for LazyErrorReport<F> {}
+
+/// Emit a Postgres `ereport` with a `SQLSTATE`, and optionally the fields of an [`ErrorReport`],
+/// such as `detail`, `hint`, `schema`, `table`, `column`, `datatype` and `constraint`.
+///
+/// For `ERROR` and above, this doesn't return.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn parse_sku(sku: &str) -> i64 {
+///     match sku.strip_prefix("SKU-").and_then(|n| n.parse().ok()) {
+///         Some(n) => n,
+///         None => ereport!(
+///             ERROR,
+///             PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+///             format!("invalid SKU: \"{}\"", sku),
+///             hint = "SKUs look like SKU-1234.",
+///         ),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! ereport {
+    (ERROR, $code:expr, $message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::log::ErrorReport::new($code, $message)
+            $(.$field($value))*
+            .raise()
+    };
+    (PANIC, $code:expr, $message:expr $(, $field:ident = $value:expr)* $(,)?) => {{
+        $crate::log::ErrorReport::new($code, $message)
+            $(.$field($value))*
+            .report($crate::log::PgLogLevel::PANIC);
+        unreachable!("ereport(PANIC) returned")
+    }};
+    (FATAL, $code:expr, $message:expr $(, $field:ident = $value:expr)* $(,)?) => {{
+        $crate::log::ErrorReport::new($code, $message)
+            $(.$field($value))*
+            .report($crate::log::PgLogLevel::FATAL);
+        unreachable!("ereport(FATAL) returned")
+    }};
+    ($level:ident, $code:expr, $message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::log::ErrorReport::new($code, $message)
+            $(.$field($value))*
+            .report($crate::log::PgLogLevel::$level)
+    };
+}
+
 /// Log to Postgres' `debug5` log level.
 ///
 /// This macro accepts arguments like the [`println`](std::println) and [`format`](std::format) macros.
@@ -746,6 +1184,9 @@ macro_rules! warning {
 /// This macro accepts arguments like the [`println`](std::println) and [`format`](std::format) macros.
 /// See [`fmt`](std::fmt) for information about options.
 ///
+/// The `ERROR` has the `SQLSTATE` `XX000`, for an internal error.  Use [`ereport!`] for another,
+/// or to give it a detail or hint.
+///
 /// Given some function:
 ///
 /// ```rust,no_run
@@ -871,40 +1312,3 @@ macro_rules! testmsg {
         eprintln!("{}", format!("TMSG: {}:{}:{}:  {}", file!(), line!(), column!(), format!($($arg)*)));
     )
 }
-
-/// Is an interrupt pending?
-#[cfg(any(feature = "pg10", feature = "pg11"))]
-#[inline]
-pub fn interrupt_pending() -> bool {
-    unsafe { crate::pg_sys::InterruptPending }
-}
-
-/// Is an interrupt pending?
-#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
-#[inline]
-pub fn interrupt_pending() -> bool {
-    unsafe { crate::pg_sys::InterruptPending != 0 }
-}
-
-/// If an interrupt is pending (perhaps a user-initiated "cancel query" message to this backend),
-/// this will safely abort the current transaction
-#[macro_export]
-macro_rules! check_for_interrupts {
-    () => {
-        #[cfg(any(feature = "pg10", feature = "pg11"))]
-        #[allow(unused_unsafe)]
-        unsafe {
-            if $crate::pg_sys::InterruptPending {
-                $crate::pg_sys::ProcessInterrupts();
-            }
-        }
-
-        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
-        #[allow(unused_unsafe)]
-        unsafe {
-            if $crate::pg_sys::InterruptPending != 0 {
-                $crate::pg_sys::ProcessInterrupts();
-            }
-        }
-    };
-}
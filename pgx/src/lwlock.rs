@@ -6,11 +6,12 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
-use crate::pg_sys;
+use crate::{ereport, pg_sys, PgLogLevel, PgSqlErrorCode};
+use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
 use once_cell::sync::OnceCell;
 use std::fmt;
-use uuid::Uuid;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// A Rust locking mechanism which uses a PostgreSQL LWLock to lock the data
 ///
@@ -27,8 +28,9 @@ use uuid::Uuid;
 /// PgLwLockExclusiveGuard, which releases the lock on drop
 ///
 /// # Poisoning
-/// This lock can not be poisoned from Rust. Panic and Abort are handled by
-/// PostgreSQL cleanly.
+/// If a Rust panic unwinds through a PgLwLockExclusiveGuard the lock is poisoned,
+/// as the data may have been left half-modified.  Every later attempt to lock it,
+/// from any backend, raises a Postgres ERROR until [`PgLwLock::clear_poison`] is called.
 pub struct PgLwLock<T> {
     inner: OnceCell<PgLwLockInner<T>>,
     name: OnceCell<&'static str>,
@@ -62,7 +64,7 @@ impl<T> PgLwLock<T> {
     pub fn get_name(&self) -> &'static str {
         match self.name.get() {
             None => {
-                let name = Box::leak(Uuid::new_v4().to_string().into_boxed_str());
+                let name = Box::leak(crate::Uuid::random().to_string().into_boxed_str());
                 self.name.set(name).unwrap();
                 name
             }
@@ -92,11 +94,32 @@ impl<T> PgLwLock<T> {
             .set(PgLwLockInner::<T>::new(self.get_name(), value))
             .expect("Can't attach, lock is not in an empty state");
     }
+
+    /// Has a panic while holding the exclusive lock poisoned this lock?
+    pub fn is_poisoned(&self) -> bool {
+        self.inner
+            .get()
+            .expect("Can't check poisoning, lock is in an empty state")
+            .poisoned()
+            .load(Ordering::SeqCst)
+    }
+
+    /// Allow this lock to be used again after it was poisoned.
+    ///
+    /// Only do this once the protected data is known to be consistent again.
+    pub fn clear_poison(&self) {
+        self.inner
+            .get()
+            .expect("Can't clear poisoning, lock is in an empty state")
+            .poisoned()
+            .store(false, Ordering::SeqCst)
+    }
 }
 
 pub struct PgLwLockInner<T> {
     lock_ptr: *mut pg_sys::LWLock,
     data: *mut T,
+    poisoned: *mut AtomicBool,
 }
 
 impl<T> fmt::Debug for PgLwLockInner<T> {
@@ -109,47 +132,81 @@ impl<'a, T> PgLwLockInner<T> {
     fn new(name: &'static str, data: *mut T) -> Self {
         unsafe {
             let lock = std::ffi::CString::new(name).expect("CString::new failed");
+            let poisoned_name =
+                std::ffi::CString::new(poisoned_shmem_name(name)).expect("CString::new failed");
+            let mut found = false;
+            let poisoned = pg_sys::ShmemInitStruct(
+                poisoned_name.into_raw(),
+                std::mem::size_of::<AtomicBool>(),
+                &mut found,
+            ) as *mut AtomicBool;
+            if !found {
+                std::ptr::write(poisoned, AtomicBool::new(false));
+            }
+
             PgLwLockInner {
                 lock_ptr: &mut (*pg_sys::GetNamedLWLockTranche(lock.as_ptr())).lock,
                 data,
+                poisoned,
             }
         }
     }
 
+    fn poisoned(&self) -> &AtomicBool {
+        unsafe { self.poisoned.as_ref().unwrap() }
+    }
+
+    fn check_poisoned(&self) {
+        if self.poisoned().load(Ordering::SeqCst) {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                "PgLwLock is poisoned, a panic occurred while it was held exclusively",
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+    }
+
     fn share(&self) -> PgLwLockShareGuard<T> {
         unsafe {
-            pg_sys::LWLockAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_SHARED);
+            let guard = LwLockShared::acquire(self.lock_ptr);
+            self.check_poisoned();
 
             PgLwLockShareGuard {
                 data: self.data.as_ref().unwrap(),
-                lock: self.lock_ptr,
+                _guard: guard,
             }
         }
     }
 
     fn exclusive(&self) -> PgLwLockExclusiveGuard<T> {
         unsafe {
-            pg_sys::LWLockAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_EXCLUSIVE);
+            let guard = LwLockExclusive::acquire(self.lock_ptr).poison_on_panic(self.poisoned());
+            self.check_poisoned();
 
             PgLwLockExclusiveGuard {
                 data: self.data.as_mut().unwrap(),
-                lock: self.lock_ptr,
+                _guard: guard,
             }
         }
     }
 }
 
-pub struct PgLwLockShareGuard<'a, T> {
-    data: &'a T,
-    lock: *mut pg_sys::LWLock,
+/// The name of the shared memory holding the poisoned flag of the PgLwLock named `name`
+fn poisoned_shmem_name(name: &str) -> String {
+    format!("{}.poisoned", name)
 }
 
-impl<T> Drop for PgLwLockShareGuard<'_, T> {
-    fn drop(&mut self) {
-        unsafe {
-            pg_sys::LWLockRelease(self.lock);
-        }
-    }
+/// The shared memory, beyond that of the value itself, a PgLwLock needs
+pub(crate) fn lwlock_shmem_size() -> usize {
+    std::mem::size_of::<AtomicBool>()
+}
+
+pub struct PgLwLockShareGuard<'a, T> {
+    data: &'a T,
+    _guard: LwLockShared<'a>,
 }
 
 impl<T> Deref for PgLwLockShareGuard<'_, T> {
@@ -162,7 +219,7 @@ impl<T> Deref for PgLwLockShareGuard<'_, T> {
 
 pub struct PgLwLockExclusiveGuard<'a, T> {
     data: &'a mut T,
-    lock: *mut pg_sys::LWLock,
+    _guard: LwLockExclusive<'a>,
 }
 
 impl<T> Deref for PgLwLockExclusiveGuard<'_, T> {
@@ -179,8 +236,110 @@ impl<T> DerefMut for PgLwLockExclusiveGuard<'_, T> {
     }
 }
 
-impl<T> Drop for PgLwLockExclusiveGuard<'_, T> {
+/// A `pg_sys::LWLock` held in `LW_SHARED` mode, which is released when dropped
+///
+/// Postgres also releases every LWLock a backend holds when its transaction aborts, so an
+/// `ERROR` while holding one doesn't leave it locked.
+pub struct LwLockShared<'a> {
+    lock: *mut pg_sys::LWLock,
+    _marker: PhantomData<&'a pg_sys::LWLock>,
+}
+
+impl<'a> LwLockShared<'a> {
+    /// Acquire `lock` in `LW_SHARED` mode, waiting until it is available
+    ///
+    /// ## Safety
+    ///
+    /// `lock` must point to an initialized `pg_sys::LWLock` which outlives the guard
+    pub unsafe fn acquire(lock: *mut pg_sys::LWLock) -> Self {
+        pg_sys::LWLockAcquire(lock, pg_sys::LWLockMode_LW_SHARED);
+        LwLockShared {
+            lock,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Acquire `lock` in `LW_SHARED` mode, or `None` if that would have to wait
+    ///
+    /// ## Safety
+    ///
+    /// `lock` must point to an initialized `pg_sys::LWLock` which outlives the guard
+    pub unsafe fn try_acquire(lock: *mut pg_sys::LWLock) -> Option<Self> {
+        if pg_sys::LWLockConditionalAcquire(lock, pg_sys::LWLockMode_LW_SHARED) {
+            Some(LwLockShared {
+                lock,
+                _marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for LwLockShared<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::LWLockRelease(self.lock);
+        }
+    }
+}
+
+/// A `pg_sys::LWLock` held in `LW_EXCLUSIVE` mode, which is released when dropped
+///
+/// Postgres also releases every LWLock a backend holds when its transaction aborts, so an
+/// `ERROR` while holding one doesn't leave it locked.
+pub struct LwLockExclusive<'a> {
+    lock: *mut pg_sys::LWLock,
+    poisoned: Option<&'a AtomicBool>,
+}
+
+impl<'a> LwLockExclusive<'a> {
+    /// Acquire `lock` in `LW_EXCLUSIVE` mode, waiting until it is available
+    ///
+    /// ## Safety
+    ///
+    /// `lock` must point to an initialized `pg_sys::LWLock` which outlives the guard
+    pub unsafe fn acquire(lock: *mut pg_sys::LWLock) -> Self {
+        pg_sys::LWLockAcquire(lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+        LwLockExclusive {
+            lock,
+            poisoned: None,
+        }
+    }
+
+    /// Acquire `lock` in `LW_EXCLUSIVE` mode, or `None` if that would have to wait
+    ///
+    /// ## Safety
+    ///
+    /// `lock` must point to an initialized `pg_sys::LWLock` which outlives the guard
+    pub unsafe fn try_acquire(lock: *mut pg_sys::LWLock) -> Option<Self> {
+        if pg_sys::LWLockConditionalAcquire(lock, pg_sys::LWLockMode_LW_EXCLUSIVE) {
+            Some(LwLockExclusive {
+                lock,
+                poisoned: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Set `poisoned` if this guard is dropped by a Rust panic unwinding.
+    ///
+    /// `poisoned` should live in shared memory next to the data this lock protects, so that
+    /// every backend sees it.
+    pub fn poison_on_panic(mut self, poisoned: &'a AtomicBool) -> Self {
+        self.poisoned = Some(poisoned);
+        self
+    }
+}
+
+impl Drop for LwLockExclusive<'_> {
     fn drop(&mut self) {
+        if std::thread::panicking() {
+            if let Some(poisoned) = self.poisoned {
+                poisoned.store(true, Ordering::SeqCst);
+            }
+        }
         unsafe {
             pg_sys::LWLockRelease(self.lock);
         }
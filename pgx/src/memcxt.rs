@@ -17,7 +17,9 @@ Use of this source code is governed by the MIT license that can be found in the
 //!
 use crate::pg_sys::AsPgCStr;
 use crate::{guard, pg_sys, PgBox};
+use std::cell::Cell;
 use std::fmt::Debug;
+use std::rc::Rc;
 
 /// A shorter type name for a `*const std::os::raw::c_void`
 #[allow(non_camel_case_types)]
@@ -181,6 +183,68 @@ impl Drop for OwnedMemoryContext {
     }
 }
 
+/// How much memory one `MemoryContext` uses, as `MemoryContextStats()` would print it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryContextStatistics {
+    /// The context's name, such as `"CacheMemoryContext"`
+    pub name: String,
+    /// What the context is for, such as the relation whose relcache entry is in it.  Always `None`
+    /// on Postgres 10.
+    pub ident: Option<String>,
+    /// How deep the context is below the one the statistics were asked of, which is at `0`
+    pub level: usize,
+    /// Bytes allocated for the context's blocks
+    pub total_bytes: usize,
+    /// Bytes of those blocks not in use
+    pub free_bytes: usize,
+    /// How many blocks the context has
+    pub blocks: usize,
+    /// How many freed chunks the context keeps for reuse
+    pub free_chunks: usize,
+}
+
+impl MemoryContextStatistics {
+    /// Bytes of the context's blocks in use
+    pub fn used_bytes(&self) -> usize {
+        self.total_bytes - self.free_bytes
+    }
+
+    unsafe fn of(context: pg_sys::MemoryContext, level: usize) -> Self {
+        let mut counters = pg_sys::MemoryContextCounters::default();
+        if let Some(stats) = (*(*context).methods).stats {
+            // none of these print anything without a print function or flag
+            #[cfg(feature = "pg10")]
+            stats(context, 0, false, &mut counters);
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+            stats(context, None, std::ptr::null_mut(), &mut counters);
+            #[cfg(feature = "pg14")]
+            stats(context, None, std::ptr::null_mut(), &mut counters, false);
+        }
+
+        let string = |s: *const std::os::raw::c_char| {
+            if s.is_null() {
+                None
+            } else {
+                Some(std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned())
+            }
+        };
+        #[cfg(feature = "pg10")]
+        let ident = None;
+        #[cfg(not(feature = "pg10"))]
+        let ident = string((*context).ident);
+
+        MemoryContextStatistics {
+            name: string((*context).name).unwrap_or_default(),
+            ident,
+            level,
+            total_bytes: counters.totalspace,
+            free_bytes: counters.freespace,
+            blocks: counters.nblocks,
+            free_chunks: counters.freechunks,
+        }
+    }
+}
+
 impl PgMemoryContexts {
     /// Create a new `PgMemoryContext::Owned`
     pub fn new(name: &str) -> PgMemoryContexts {
@@ -229,6 +293,60 @@ impl PgMemoryContexts {
         }
     }
 
+    /// How much memory this context uses, not counting the contexts below it.
+    ///
+    /// This works for every type except the `::Transient` type.
+    pub fn statistics(&self) -> MemoryContextStatistics {
+        unsafe { MemoryContextStatistics::of(self.value(), 0) }
+    }
+
+    /// How much memory this context and every context below it uses, in the depth-first order
+    /// `MemoryContextStats()` prints them in, starting with this context.
+    ///
+    /// This works for every type except the `::Transient` type.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_extern]
+    /// fn memory_contexts() -> impl Iterator<
+    ///     Item = (
+    ///         name!(name, String),
+    ///         name!(level, i32),
+    ///         name!(total_bytes, i64),
+    ///         name!(used_bytes, i64),
+    ///     ),
+    /// > {
+    ///     PgMemoryContexts::TopMemoryContext
+    ///         .statistics_tree()
+    ///         .into_iter()
+    ///         .map(|stats| {
+    ///             let used_bytes = stats.used_bytes() as i64;
+    ///             (stats.name, stats.level as i32, stats.total_bytes as i64, used_bytes)
+    ///         })
+    /// }
+    /// ```
+    pub fn statistics_tree(&self) -> Vec<MemoryContextStatistics> {
+        unsafe fn walk(
+            context: pg_sys::MemoryContext,
+            level: usize,
+            tree: &mut Vec<MemoryContextStatistics>,
+        ) {
+            tree.push(MemoryContextStatistics::of(context, level));
+            let mut child = (*context).firstchild;
+            while !child.is_null() {
+                walk(child, level + 1, tree);
+                child = (*child).nextchild;
+            }
+        }
+
+        let mut tree = Vec::new();
+        unsafe { walk(self.value(), 0, &mut tree) };
+        tree
+    }
+
     /// Release all space allocated within a context and delete all its descendant contexts (but not
     /// the context itself).
     pub fn reset(&mut self) {
@@ -374,6 +492,13 @@ impl PgMemoryContexts {
         leaked_ptr
     }
 
+    /// Like [`leak_and_drop_on_delete`](PgMemoryContexts::leak_and_drop_on_delete), but returns a
+    /// [`WeakPctx`], which knows when `v` has been dropped
+    pub fn leak_weak<T>(&mut self, v: T) -> WeakPctx<T> {
+        let ptr = self.leak_and_drop_on_delete(v);
+        unsafe { WeakPctx::new(self, ptr) }
+    }
+
     /// helper function
     fn exec_in_context<
         R,
@@ -463,3 +588,241 @@ impl PgMemoryContexts {
         //        context
     }
 }
+
+/// A `PgMemoryContexts` that only allows a fixed number of bytes to be allocated through it,
+/// raising an ERROR once the budget would be exceeded.
+///
+/// Allocations made some other way, such as by Postgres while `CurrentMemoryContext` is this
+/// context, aren't counted.  Use [`reserve`](BudgetedMemoryContext::reserve) to account for
+/// memory before building something of a size decided by the input.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn sum_squares(values: Vec<i64>) -> i64 {
+///     let mut budget = BudgetedMemoryContext::new(PgMemoryContexts::new("sum_squares"), 1 << 20);
+///     let squares = budget.palloc_slice::<i64>(values.len());
+///     for (square, value) in squares.iter_mut().zip(values) {
+///         *square = value * value;
+///     }
+///     squares.iter().sum()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BudgetedMemoryContext {
+    context: PgMemoryContexts,
+    budget: usize,
+    allocated: usize,
+}
+
+impl BudgetedMemoryContext {
+    /// Allow at most `budget` bytes to be allocated in `context` through this
+    pub fn new(context: PgMemoryContexts, budget: usize) -> Self {
+        BudgetedMemoryContext {
+            context,
+            budget,
+            allocated: 0,
+        }
+    }
+
+    /// How many bytes may be allocated in total
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// How many bytes have been allocated, or reserved, so far
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    /// How many more bytes may be allocated
+    pub fn remaining(&self) -> usize {
+        self.budget - self.allocated
+    }
+
+    /// Count `len` bytes against the budget, raising an ERROR if that exceeds it
+    pub fn reserve(&mut self, len: usize) {
+        match self.allocated.checked_add(len) {
+            Some(allocated) if allocated <= self.budget => self.allocated = allocated,
+            _ => {
+                crate::ereport(
+                    crate::PgLogLevel::ERROR,
+                    crate::PgSqlErrorCode::ERRCODE_PROGRAM_LIMIT_EXCEEDED,
+                    &format!(
+                        "memory budget of {} bytes exceeded: {} bytes are already allocated, \
+                         and {} more were requested",
+                        self.budget, self.allocated, len
+                    ),
+                    file!(),
+                    line!(),
+                    column!(),
+                );
+                unreachable!()
+            }
+        }
+    }
+
+    /// Release everything allocated in the context, and give its bytes back to the budget.
+    ///
+    /// See [`PgMemoryContexts::reset`].
+    pub fn reset(&mut self) {
+        self.context.reset();
+        self.allocated = 0;
+    }
+
+    /// The context allocations are made in
+    pub fn context(&self) -> &PgMemoryContexts {
+        &self.context
+    }
+
+    /// Allocate memory in this context, raising an ERROR if that exceeds the budget
+    pub fn palloc(&mut self, len: usize) -> *mut std::os::raw::c_void {
+        self.reserve(len);
+        self.context.palloc(len)
+    }
+
+    /// Allocate zero'd memory in this context, raising an ERROR if that exceeds the budget
+    pub fn palloc0(&mut self, len: usize) -> *mut std::os::raw::c_void {
+        self.reserve(len);
+        self.context.palloc0(len)
+    }
+
+    pub fn palloc_struct<T>(&mut self) -> *mut T {
+        self.palloc(std::mem::size_of::<T>()) as *mut T
+    }
+
+    pub fn palloc0_struct<T>(&mut self) -> *mut T {
+        self.palloc0(std::mem::size_of::<T>()) as *mut T
+    }
+
+    /// Allocate a slice in this context, raising an ERROR if that exceeds the budget
+    pub fn palloc_slice<'a, T>(&mut self, len: usize) -> &'a mut [T] {
+        self.reserve(Self::slice_size::<T>(len));
+        self.context.palloc_slice(len)
+    }
+
+    /// Allocate a zero'd slice in this context, raising an ERROR if that exceeds the budget
+    pub fn palloc0_slice<'a, T>(&mut self, len: usize) -> &'a mut [T] {
+        self.reserve(Self::slice_size::<T>(len));
+        self.context.palloc0_slice(len)
+    }
+
+    /// Duplicate a Rust `&str` into this context, raising an ERROR if that exceeds the budget
+    pub fn pstrdup(&mut self, s: &str) -> *mut std::os::raw::c_char {
+        self.reserve(s.len() + 1);
+        self.context.pstrdup(s)
+    }
+
+    fn slice_size<T>(len: usize) -> usize {
+        // an overflowing size can't fit in any budget
+        std::mem::size_of::<T>()
+            .checked_mul(len)
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// A pointer to something allocated in a memory context, which knows when the context has been
+/// reset or deleted, and so when the pointer would be dangling.
+///
+/// Something that outlives a query, such as a per-backend cache, can keep a `WeakPctx` to a
+/// per-query allocation, and check whether it's still there rather than dereferencing freed
+/// memory.
+///
+/// Nothing ties a reference to the value to the context's lifetime, so getting one is `unsafe`,
+/// and a `WeakPctx` isn't `Clone`, so it's the only way to reach the value mutably.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let mut context = PgMemoryContexts::new("per-query cache");
+/// let weak = context.leak_weak(vec![1, 2, 3]);
+/// assert_eq!(unsafe { weak.get() }.map(|v| v.len()), Some(3));
+///
+/// context.reset();
+/// assert!(!weak.is_valid());
+/// assert_eq!(unsafe { weak.get() }, None);
+/// ```
+pub struct WeakPctx<T> {
+    ptr: *mut T,
+    valid: Rc<Cell<bool>>,
+}
+
+impl<T> WeakPctx<T> {
+    /// Track `ptr`, which becomes invalid when `context` is reset or deleted.
+    ///
+    /// ## Safety
+    ///
+    /// `ptr` must stay valid until `context` is reset or deleted, such as by being allocated in it.
+    pub unsafe fn new(context: &PgMemoryContexts, ptr: *mut T) -> Self {
+        unsafe extern "C" fn invalidate(arg: void_mut_ptr) {
+            let valid = Box::from_raw(arg as *mut Rc<Cell<bool>>);
+            valid.set(false);
+        }
+
+        let valid = Rc::new(Cell::new(true));
+        let callback = pg_sys::MemoryContextAllocZero(
+            context.value(),
+            std::mem::size_of::<pg_sys::MemoryContextCallback>(),
+        ) as *mut pg_sys::MemoryContextCallback;
+        (*callback).func = Some(invalidate);
+        (*callback).arg = Box::into_raw(Box::new(valid.clone())) as void_mut_ptr;
+        pg_sys::MemoryContextRegisterResetCallback(context.value(), callback);
+
+        WeakPctx { ptr, valid }
+    }
+
+    /// Is the context the pointer was allocated in still there?
+    pub fn is_valid(&self) -> bool {
+        self.valid.get()
+    }
+
+    /// The value, if its context hasn't been reset or deleted.
+    ///
+    /// ## Safety
+    ///
+    /// The reference must not be held across anything that could reset or delete the context.
+    pub unsafe fn get(&self) -> Option<&T> {
+        if self.is_valid() {
+            Some(&*self.ptr)
+        } else {
+            None
+        }
+    }
+
+    /// The value, mutably, if its context hasn't been reset or deleted.
+    ///
+    /// ## Safety
+    ///
+    /// The reference must not be held across anything that could reset or delete the context, nor
+    /// while anything else reaches the value through [`as_ptr`](WeakPctx::as_ptr).
+    pub unsafe fn get_mut(&mut self) -> Option<&mut T> {
+        if self.is_valid() {
+            Some(&mut *self.ptr)
+        } else {
+            None
+        }
+    }
+
+    /// The pointer, if its context hasn't been reset or deleted
+    pub fn as_ptr(&self) -> Option<*mut T> {
+        if self.is_valid() {
+            Some(self.ptr)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Debug for WeakPctx<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakPctx")
+            .field("ptr", &self.ptr)
+            .field("valid", &self.is_valid())
+            .finish()
+    }
+}
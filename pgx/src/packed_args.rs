@@ -0,0 +1,83 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Unpacking the `jsonb` argument of a [`#[pg_extern_packed]`](crate::pg_extern_packed) function
+use crate::{ereport, JsonB, PgLogLevel, PgSqlErrorCode};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// The arguments of a [`#[pg_extern_packed]`](crate::pg_extern_packed) function, packed into a
+/// `jsonb` object keyed by the names of the Rust function's parameters.
+///
+/// The generated function unpacks them, raising an `ERROR` which names the argument if one is
+/// missing, has the wrong type, or isn't a parameter at all.
+pub struct PackedArgs {
+    function: &'static str,
+    args: Map<String, Value>,
+}
+
+impl PackedArgs {
+    /// Check that `args` is an object whose keys are all in `names`, the parameters of `function`
+    #[doc(hidden)]
+    pub fn unpack(function: &'static str, args: JsonB, names: &[&str]) -> Self {
+        let args = match args.0 {
+            Value::Object(args) => args,
+            value => error(
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                &format!(
+                    "{}() takes its arguments as a jsonb object, not `{}`",
+                    function, value
+                ),
+            ),
+        };
+        if let Some(unknown) = args.keys().find(|key| !names.contains(&key.as_str())) {
+            error(
+                PgSqlErrorCode::ERRCODE_UNDEFINED_PARAMETER,
+                &format!(
+                    "{}() has no argument `{}`, its arguments are: {}",
+                    function,
+                    unknown,
+                    names.join(", ")
+                ),
+            )
+        }
+        PackedArgs { function, args }
+    }
+
+    /// Take the argument `name` as a `T`.  An argument which isn't given is `null`, so it's only
+    /// optional if `T` is an `Option`.
+    #[doc(hidden)]
+    pub fn take<T: DeserializeOwned>(&mut self, name: &str) -> T {
+        let given = self.args.remove(name);
+        let missing = given.is_none();
+        match serde_json::from_value(given.unwrap_or(Value::Null)) {
+            Ok(value) => value,
+            Err(_) if missing => error(
+                PgSqlErrorCode::ERRCODE_NULL_VALUE_NOT_ALLOWED,
+                &format!("{}() is missing its argument `{}`", self.function, name),
+            ),
+            Err(e) => error(
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                &format!("{}() argument `{}` is invalid: {}", self.function, name, e),
+            ),
+        }
+    }
+}
+
+fn error(code: PgSqlErrorCode, message: &str) -> ! {
+    ereport(
+        PgLogLevel::ERROR,
+        code,
+        message,
+        file!(),
+        line!(),
+        column!(),
+    );
+    unreachable!()
+}
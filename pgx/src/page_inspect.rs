@@ -0,0 +1,362 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Looking inside the pages of a relation, as the `pageinspect` extension does, for access
+//! methods and types that store their own data in pages
+use crate::{ereport, pg_sys, PgRelation, PgSqlErrorCode};
+use std::os::raw::c_char;
+
+const PAGE_SIZE: usize = pg_sys::BLCKSZ as usize;
+
+/// The size of a page's header, before its line pointers
+const PAGE_HEADER_SIZE: usize = std::mem::size_of::<pg_sys::PageHeaderData>();
+
+/// The most line pointers that fit on a page, however corrupt its header is
+const MAX_LINE_POINTERS: usize =
+    (PAGE_SIZE - PAGE_HEADER_SIZE) / std::mem::size_of::<pg_sys::ItemIdData>();
+
+/// The bytes of a page, aligned as Postgres aligns its buffers
+#[repr(C, align(8))]
+struct PageBytes([u8; PAGE_SIZE]);
+
+/// A copy of one block of a relation, as `pageinspect`'s `get_raw_page()` takes.
+///
+/// The copy is taken while holding a share lock on the buffer, so it's consistent, and it can be
+/// looked at for as long as needed without holding up anyone else.
+pub struct RawPage {
+    bytes: Box<PageBytes>,
+}
+
+/// The fields of a [`RawPage`]'s `PageHeaderData`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawPageHeader {
+    /// The LSN of the last change to the page
+    pub lsn: u64,
+    pub checksum: u16,
+    pub flags: u16,
+    /// Where the free space starts, after the line pointers
+    pub lower: u16,
+    /// Where the free space ends, before the items
+    pub upper: u16,
+    /// Where the access method's special space starts
+    pub special: u16,
+    pub page_size: u16,
+    pub layout_version: u8,
+    /// The oldest transaction ID that might be worth pruning the page for, on heap pages
+    pub prune_xid: pg_sys::TransactionId,
+}
+
+/// A line pointer of a [`RawPage`], and the item it points to
+#[derive(Debug, Clone, Copy)]
+pub struct PageItem<'a> {
+    /// The item's offset number, counting from 1
+    pub offset: pg_sys::OffsetNumber,
+    /// `LP_UNUSED`, `LP_NORMAL`, `LP_REDIRECT`, or `LP_DEAD`
+    pub lp_flags: u32,
+    /// Where the item starts on the page, or the offset a `LP_REDIRECT` item redirects to
+    pub lp_off: u16,
+    pub lp_len: u16,
+    /// The item, which is empty unless it's a `LP_NORMAL` item that fits in the page
+    pub data: &'a [u8],
+}
+
+impl RawPage {
+    /// Copy block `blkno` of the `relation`'s main fork.
+    ///
+    /// As with `get_raw_page()`, only superusers can do this, because a page can hold anything in
+    /// the relation, including rows the user can't see.
+    pub fn read(relation: &PgRelation, blkno: pg_sys::BlockNumber) -> RawPage {
+        Self::read_fork(relation, pg_sys::ForkNumber_MAIN_FORKNUM, blkno)
+    }
+
+    /// Copy block `blkno` of one of the `relation`'s forks, such as its free space map's
+    pub fn read_fork(
+        relation: &PgRelation,
+        fork: pg_sys::ForkNumber,
+        blkno: pg_sys::BlockNumber,
+    ) -> RawPage {
+        if !unsafe { pg_sys::superuser() } {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+                "must be superuser to use raw page functions"
+            );
+        }
+
+        let rd_rel = unsafe { relation.rd_rel.as_ref().expect("rd_rel is NULL") };
+        let has_storage = [
+            pg_sys::RELKIND_RELATION,
+            pg_sys::RELKIND_INDEX,
+            pg_sys::RELKIND_SEQUENCE,
+            pg_sys::RELKIND_TOASTVALUE,
+            pg_sys::RELKIND_MATVIEW,
+        ]
+        .iter()
+        .any(|relkind| rd_rel.relkind == *relkind as c_char);
+        if !has_storage {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_WRONG_OBJECT_TYPE,
+                format!("cannot get raw page from relation \"{}\"", relation.name()),
+                detail = "The relation has no storage."
+            );
+        }
+        if rd_rel.relpersistence == pg_sys::RELPERSISTENCE_TEMP as c_char
+            && !relation.rd_islocaltemp
+        {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                "cannot access temporary tables of other sessions"
+            );
+        }
+
+        let nblocks = unsafe { pg_sys::RelationGetNumberOfBlocksInFork(relation.as_ptr(), fork) };
+        if blkno >= nblocks {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!(
+                    "block number {} is out of range for relation \"{}\"",
+                    blkno,
+                    relation.name()
+                )
+            );
+        }
+
+        let mut bytes = Box::new(PageBytes([0; PAGE_SIZE]));
+        unsafe {
+            let buffer = pg_sys::ReadBufferExtended(
+                relation.as_ptr(),
+                fork,
+                blkno,
+                pg_sys::ReadBufferMode_RBM_NORMAL,
+                std::ptr::null_mut(),
+            );
+            pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_SHARE as i32);
+            let page = pg_sys::pgx_BufferGetPage(buffer) as *const u8;
+            std::ptr::copy_nonoverlapping(page, bytes.0.as_mut_ptr(), PAGE_SIZE);
+            pg_sys::UnlockReleaseBuffer(buffer);
+        }
+        RawPage { bytes }
+    }
+
+    /// A page from its bytes, such as a `bytea` from `get_raw_page()`, if it's the size of a page
+    pub fn from_bytes(bytes: &[u8]) -> Option<RawPage> {
+        if bytes.len() != PAGE_SIZE {
+            return None;
+        }
+        let mut page = Box::new(PageBytes([0; PAGE_SIZE]));
+        page.0.copy_from_slice(bytes);
+        Some(RawPage { bytes: page })
+    }
+
+    /// The whole page
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes.0
+    }
+
+    fn header_data(&self) -> &pg_sys::PageHeaderData {
+        unsafe { &*(self.bytes.0.as_ptr() as *const pg_sys::PageHeaderData) }
+    }
+
+    /// The page's header
+    pub fn header(&self) -> RawPageHeader {
+        let header = self.header_data();
+        RawPageHeader {
+            lsn: (header.pd_lsn.xlogid as u64) << 32 | header.pd_lsn.xrecoff as u64,
+            checksum: header.pd_checksum,
+            flags: header.pd_flags,
+            lower: header.pd_lower,
+            upper: header.pd_upper,
+            special: header.pd_special,
+            page_size: header.pd_pagesize_version & 0xFF00,
+            layout_version: (header.pd_pagesize_version & 0x00FF) as u8,
+            prune_xid: header.pd_prune_xid,
+        }
+    }
+
+    /// Is the page all zeros, as a page that was never initialized is?
+    pub fn is_new(&self) -> bool {
+        self.header_data().pd_upper == 0
+    }
+
+    /// The page's line pointers, and the items they point to
+    pub fn items(&self) -> impl Iterator<Item = PageItem<'_>> {
+        let lower = self.header_data().pd_lower as usize;
+        let count =
+            lower.saturating_sub(PAGE_HEADER_SIZE) / std::mem::size_of::<pg_sys::ItemIdData>();
+        let line_pointers = unsafe {
+            std::slice::from_raw_parts(
+                self.bytes.0.as_ptr().add(PAGE_HEADER_SIZE) as *const pg_sys::ItemIdData,
+                count.min(MAX_LINE_POINTERS),
+            )
+        };
+
+        line_pointers
+            .iter()
+            .enumerate()
+            .map(move |(index, line_pointer)| {
+                let lp_flags = line_pointer.lp_flags();
+                let lp_off = line_pointer.lp_off() as u16;
+                let lp_len = line_pointer.lp_len() as u16;
+                let (start, end) = (lp_off as usize, lp_off as usize + lp_len as usize);
+                let data = if lp_flags == pg_sys::LP_NORMAL
+                    && start >= PAGE_HEADER_SIZE
+                    && end <= PAGE_SIZE
+                {
+                    &self.bytes.0[start..end]
+                } else {
+                    &[]
+                };
+                PageItem {
+                    offset: (index + 1) as pg_sys::OffsetNumber,
+                    lp_flags,
+                    lp_off,
+                    lp_len,
+                    data,
+                }
+            })
+    }
+
+    /// The access method's special space at the end of the page, which is empty for heap pages
+    pub fn special(&self) -> &[u8] {
+        let special = self.header_data().pd_special as usize;
+        if special < PAGE_HEADER_SIZE || special > PAGE_SIZE {
+            return &[];
+        }
+        &self.bytes.0[special..]
+    }
+
+    /// Describe the page for [`page_inspect_srf!`], as `(itemoffset, field, value)` rows.
+    ///
+    /// The header's fields come first, named as `pageinspect`'s `page_header()` names them, then
+    /// the fields `F` finds in the special space, both with no `itemoffset`.  Then for each line
+    /// pointer, its `lp_flags`, `lp_off`, and `lp_len`, followed by the fields `F` finds in the
+    /// item, if it's a `LP_NORMAL` one.
+    pub fn describe<F: PageFormatter>(&self) -> Vec<(Option<i32>, String, String)> {
+        let header = self.header();
+        let mut rows = vec![
+            (
+                "lsn",
+                format!("{:X}/{:X}", header.lsn >> 32, header.lsn as u32),
+            ),
+            ("checksum", header.checksum.to_string()),
+            ("flags", header.flags.to_string()),
+            ("lower", header.lower.to_string()),
+            ("upper", header.upper.to_string()),
+            ("special", header.special.to_string()),
+            ("pagesize", header.page_size.to_string()),
+            ("version", header.layout_version.to_string()),
+            ("prune_xid", header.prune_xid.to_string()),
+        ]
+        .into_iter()
+        .chain(F::special(self))
+        .map(|(field, value)| (None, field.to_string(), value))
+        .collect::<Vec<_>>();
+
+        for item in self.items() {
+            let offset = Some(item.offset as i32);
+            rows.push((offset, "lp_flags".to_string(), item.lp_flags.to_string()));
+            rows.push((offset, "lp_off".to_string(), item.lp_off.to_string()));
+            rows.push((offset, "lp_len".to_string(), item.lp_len.to_string()));
+            if item.lp_flags == pg_sys::LP_NORMAL {
+                rows.extend(
+                    F::item(self, &item)
+                        .into_iter()
+                        .map(|(field, value)| (offset, field.to_string(), value)),
+                );
+            }
+        }
+        rows
+    }
+}
+
+/// What an access method or type knows about the pages it stores, for [`page_inspect_srf!`] to
+/// describe them with.
+///
+/// Each method returns `(field, value)` pairs, with the values formatted however reads best.
+pub trait PageFormatter {
+    /// The fields of the page's special space, which is [`RawPage::special`]
+    fn special(page: &RawPage) -> Vec<(&'static str, String)> {
+        let _ = page;
+        Vec::new()
+    }
+
+    /// The fields of a `LP_NORMAL` item, which is [`PageItem::data`]
+    fn item(page: &RawPage, item: &PageItem<'_>) -> Vec<(&'static str, String)>;
+}
+
+/// Everything that's the same on every page, which only describes the header and line pointers
+pub struct RawPageFormatter;
+
+impl PageFormatter for RawPageFormatter {
+    fn item(_page: &RawPage, _item: &PageItem<'_>) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// Generate a set-returning `#[pg_extern]` function which describes a page of a relation, with a
+/// [`PageFormatter`] for what's in it.
+///
+/// The function takes a relation and a block number, as `get_raw_page()` does, and returns the
+/// rows of [`RawPage::describe()`]:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// struct BloomPage;
+///
+/// impl PageFormatter for BloomPage {
+///     fn special(page: &RawPage) -> Vec<(&'static str, String)> {
+///         let special = page.special();
+///         let max_offset = u16::from_ne_bytes([special[0], special[1]]);
+///         vec![("max_offset", max_offset.to_string())]
+///     }
+///
+///     fn item(_page: &RawPage, item: &PageItem) -> Vec<(&'static str, String)> {
+///         vec![("signature", format!("{:02x?}", item.data))]
+///     }
+/// }
+///
+/// page_inspect_srf!(bloom_page_items, BloomPage);
+/// ```
+///
+/// ```sql
+/// SELECT * FROM bloom_page_items('my_bloom_index', 1) WHERE field <> 'lp_off';
+/// ```
+///
+/// Like `pageinspect`'s functions, it can only be used by superusers.
+#[macro_export]
+macro_rules! page_inspect_srf {
+    ($name:ident, $formatter:ty) => {
+        #[$crate::pg_extern(strict)]
+        fn $name(
+            relation: $crate::PgRelation,
+            blkno: i64,
+        ) -> impl std::iter::Iterator<
+            Item = (
+                $crate::name!(itemoffset, Option<i32>),
+                $crate::name!(field, String),
+                $crate::name!(value, String),
+            ),
+        > {
+            if blkno < 0 || blkno > $crate::pg_sys::MaxBlockNumber as i64 {
+                $crate::ereport!(
+                    ERROR,
+                    $crate::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                    "invalid block number"
+                );
+            }
+            $crate::RawPage::read(&relation, blkno as $crate::pg_sys::BlockNumber)
+                .describe::<$formatter>()
+                .into_iter()
+        }
+    };
+}
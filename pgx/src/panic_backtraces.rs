@@ -0,0 +1,62 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Logging a Rust backtrace with the ERROR a panic becomes
+use crate::{pg_sys, GucContext, GucRegistry, GucSetting};
+
+/// Whether panics capture a backtrace, which stays off until the setting is defined
+static PANIC_BACKTRACES: GucSetting<bool> = GucSetting::new(false);
+
+/// Logs a Rust backtrace of where the extension panicked, along with the `ERROR` the panic
+/// becomes, when an operator turns on a setting the extension defines.
+///
+/// The extension defines the setting, such as `myext.panic_backtraces`, when it's loaded:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     PanicBacktraces::define_guc("myext.panic_backtraces");
+/// }
+/// ```
+///
+/// After `SET myext.panic_backtraces = on`, a panic is logged like so:
+///
+/// ```text
+/// ERROR:  index out of bounds: the len is 3 but the index is 7
+/// DETAIL:  Rust backtrace:
+///    0: backtrace::backtrace::libunwind::trace
+///    ...
+///   14: myext::lookup
+///              at src/lib.rs:42:5
+///    ...
+/// CONTEXT:  src/lib.rs:42:5
+/// ```
+///
+/// The backtrace is only written to the server log, as `errdetail_log()` does, so clients don't
+/// see the extension's internals.  Capturing one is slow, which is why it's off by default, but
+/// only panics pay for it.  The frames are named if the extension's shared library has its
+/// symbols, so a release build needs `debug = 1` or more, or at least not to be stripped.
+pub struct PanicBacktraces;
+
+impl PanicBacktraces {
+    /// Define the setting `name`, which is off by default, and can only be changed by
+    /// superusers.  Call it from `_PG_init()`.
+    pub fn define_guc(name: &str) {
+        GucRegistry::define_bool_guc(
+            name,
+            "Logs a Rust backtrace when the extension panics",
+            "The backtrace is written to the server log with the ERROR the panic becomes.",
+            &PANIC_BACKTRACES,
+            GucContext::Suset,
+        );
+        pg_sys::capture_panic_backtraces(|| PANIC_BACKTRACES.get());
+    }
+}
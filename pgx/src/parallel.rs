@@ -0,0 +1,414 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Parallel workers for a function's own work, such as a CPU-heavy search, within a single query
+//!
+//! The leader, the backend running the query, [launches](ParallelQuery::launch) workers with a
+//! state they share, and reads what they [send](ParallelWorker::send) back as they send it:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use pgx::parallel::*;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! struct Search {
+//!     next_block: AtomicUsize,
+//!     blocks: usize,
+//! }
+//! unsafe impl PGXSharedMemory for Search {}
+//!
+//! // each worker, and the leader, claims blocks until there are none left
+//! fn search_blocks(search: &Search, mut found: impl FnMut(usize)) {
+//!     loop {
+//!         let block = search.next_block.fetch_add(1, Ordering::Relaxed);
+//!         if block >= search.blocks {
+//!             break;
+//!         }
+//!         // ...search the block
+//!         found(block);
+//!     }
+//! }
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn search_worker(seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) {
+//!     let worker = unsafe { ParallelWorker::<Search>::attach(seg, toc) };
+//!     search_blocks(worker.shared(), |block| {
+//!         worker.send(&block);
+//!     });
+//! }
+//!
+//! #[pg_extern]
+//! fn parallel_search(blocks: i64) -> Vec<i64> {
+//!     let search = Search {
+//!         next_block: AtomicUsize::new(0),
+//!         blocks: blocks as usize,
+//!     };
+//!     let mut results = ParallelQuery::new("my_extension", "search_worker")
+//!         .set_workers(4)
+//!         .launch::<_, usize>(search);
+//!     let mut found = results.by_ref().map(|block| block as i64).collect::<Vec<_>>();
+//!     // the leader takes what's left, which is everything if no workers could be launched
+//!     search_blocks(results.shared(), |block| found.push(block as i64));
+//!     found
+//! }
+//! ```
+//!
+//! Workers are Postgres' own parallel workers, so they count towards `max_parallel_workers`, run
+//! with the leader's snapshot and settings, and can only read the database.  An `ERROR` in one is
+//! raised in the leader, the next time it checks for interrupts.
+use crate::{check_for_interrupts, pg_sys, PGXSharedMemory};
+use serde::{de::DeserializeOwned, Serialize};
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+// Postgres' own keys, for the parallel context's internals, count down from u64::MAX
+const KEY_HEADER: u64 = 0x5047_5800_0000_0001;
+const KEY_SHARED: u64 = 0x5047_5800_0000_0002;
+const KEY_QUEUES: u64 = 0x5047_5800_0000_0003;
+
+/// The size of the queue each worker sends through, as Postgres' own tuple queues are
+const DEFAULT_QUEUE_SIZE: usize = 65536;
+
+/// How the leader laid out the dynamic shared memory, for workers to find their way around it
+#[repr(C)]
+struct Header {
+    workers: usize,
+    queue_size: usize,
+    shared_size: usize,
+}
+
+/// `BUFFERALIGN()`, which the shared memory table of contents aligns its chunks to
+fn buffer_align(size: usize) -> usize {
+    let align = pg_sys::ALIGNOF_BUFFER as usize;
+    (size + align - 1) & !(align - 1)
+}
+
+/// `shm_toc_estimate_chunk()` and `shm_toc_estimate_keys()`, which are macros
+fn estimate(estimator: &mut pg_sys::shm_toc_estimator, sizes: &[usize]) {
+    for size in sizes {
+        estimator.space_for_chunks += buffer_align(*size);
+    }
+    estimator.number_of_keys += sizes.len();
+}
+
+#[cfg(feature = "pg11")]
+unsafe fn create_parallel_context(
+    library: &CString,
+    function: &CString,
+    workers: i32,
+) -> *mut pg_sys::ParallelContext {
+    pg_sys::CreateParallelContext(library.as_ptr(), function.as_ptr(), workers, false)
+}
+
+#[cfg(not(feature = "pg11"))]
+unsafe fn create_parallel_context(
+    library: &CString,
+    function: &CString,
+    workers: i32,
+) -> *mut pg_sys::ParallelContext {
+    pg_sys::CreateParallelContext(library.as_ptr(), function.as_ptr(), workers)
+}
+
+/// Launches parallel workers which run the function `function` from the library `library`, such
+/// as the extension's own.
+///
+/// The function is declared as:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn my_worker(seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) {
+///     // ...
+/// }
+/// ```
+///
+/// and starts by [attaching](ParallelWorker::attach) to what the leader shared.
+pub struct ParallelQuery {
+    library: String,
+    function: String,
+    workers: usize,
+    queue_size: usize,
+}
+
+impl ParallelQuery {
+    /// Workers which run `function` from `library`, as many as `max_parallel_workers_per_gather`
+    /// allows by default
+    pub fn new(library: &str, function: &str) -> Self {
+        ParallelQuery {
+            library: library.to_string(),
+            function: function.to_string(),
+            workers: unsafe { pg_sys::max_parallel_workers_per_gather }.max(0) as usize,
+            queue_size: DEFAULT_QUEUE_SIZE,
+        }
+    }
+
+    /// How many workers to ask for, which Postgres launches as many of as it has to spare
+    pub fn set_workers(mut self: Self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// The size in bytes of the queue each worker sends through, which it waits on while it's full
+    pub fn set_queue_size(mut self: Self, queue_size: usize) -> Self {
+        self.queue_size = buffer_align(queue_size);
+        self
+    }
+
+    /// Launch the workers, which share `shared` with the leader, and send back `T`s.  The state is
+    /// moved into dynamic shared memory, where it's never dropped.
+    ///
+    /// ## Panics
+    ///
+    /// If `S` needs more alignment than shared memory has, which is 32 bytes
+    pub fn launch<S, T>(self, shared: S) -> ParallelResults<S, T>
+    where
+        S: PGXSharedMemory,
+        T: DeserializeOwned,
+    {
+        assert!(
+            std::mem::align_of::<S>() <= pg_sys::ALIGNOF_BUFFER as usize,
+            "`{}` needs more alignment than shared memory has",
+            std::any::type_name::<S>()
+        );
+        let library = CString::new(self.library).expect("library name contains a NUL");
+        let function = CString::new(self.function).expect("function name contains a NUL");
+        let shared_size = std::mem::size_of::<S>().max(1);
+
+        unsafe {
+            pg_sys::EnterParallelMode();
+            let context = create_parallel_context(&library, &function, self.workers as i32);
+            estimate(
+                &mut (*context).estimator,
+                &[
+                    std::mem::size_of::<Header>(),
+                    shared_size,
+                    self.queue_size * self.workers,
+                ],
+            );
+            pg_sys::InitializeParallelDSM(context);
+
+            // Postgres may have decided it can't have any workers after all
+            let workers = (*context).nworkers as usize;
+            let toc = (*context).toc;
+            let header =
+                pg_sys::shm_toc_allocate(toc, std::mem::size_of::<Header>()) as *mut Header;
+            header.write(Header {
+                workers,
+                queue_size: self.queue_size,
+                shared_size,
+            });
+            pg_sys::shm_toc_insert(toc, KEY_HEADER, header as *mut std::ffi::c_void);
+
+            let state = pg_sys::shm_toc_allocate(toc, shared_size) as *mut S;
+            state.write(shared);
+            pg_sys::shm_toc_insert(toc, KEY_SHARED, state as *mut std::ffi::c_void);
+
+            let queue_space = pg_sys::shm_toc_allocate(toc, self.queue_size * workers) as *mut u8;
+            pg_sys::shm_toc_insert(toc, KEY_QUEUES, queue_space as *mut std::ffi::c_void);
+            let mut queues = (0..workers)
+                .map(|i| {
+                    let queue = pg_sys::shm_mq_create(
+                        queue_space.add(i * self.queue_size) as *mut std::ffi::c_void,
+                        self.queue_size,
+                    );
+                    pg_sys::shm_mq_set_receiver(queue, pg_sys::MyProc);
+                    pg_sys::shm_mq_attach(queue, (*context).seg, std::ptr::null_mut())
+                })
+                .collect::<Vec<_>>();
+
+            pg_sys::LaunchParallelWorkers(context);
+            // workers are numbered in the order they're launched, and launching stops at the first
+            // that can't be
+            queues.truncate((*context).nworkers_launched as usize);
+            for (i, queue) in queues.iter().enumerate() {
+                // so a worker which fails to start detaches its queue
+                pg_sys::shm_mq_set_handle(*queue, (*(*context).worker.add(i)).bgwhandle);
+            }
+
+            ParallelResults {
+                context,
+                shared: state,
+                launched: queues.len(),
+                queues,
+                next_queue: 0,
+                _marker: PhantomData,
+            }
+        }
+    }
+}
+
+/// What the workers of a [`ParallelQuery`] send, in the order it arrives, which ends once every
+/// worker has finished.
+///
+/// Dropping it before then stops the workers.
+pub struct ParallelResults<S, T> {
+    context: *mut pg_sys::ParallelContext,
+    shared: *const S,
+    launched: usize,
+    queues: Vec<*mut pg_sys::shm_mq_handle>,
+    next_queue: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<S: PGXSharedMemory, T> ParallelResults<S, T> {
+    /// How many workers were launched, which may be fewer than were asked for, or none at all, so
+    /// the leader should be able to do the work itself
+    pub fn workers_launched(&self) -> usize {
+        self.launched
+    }
+
+    /// The state shared with the workers
+    pub fn shared(&self) -> &S {
+        unsafe { &*self.shared }
+    }
+}
+
+impl<S, T: DeserializeOwned> Iterator for ParallelResults<S, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            // round-robin, so one busy worker doesn't starve the rest
+            let mut tried = 0;
+            while tried < self.queues.len() {
+                let i = self.next_queue % self.queues.len();
+                let mut size = 0;
+                let mut data = std::ptr::null_mut();
+                let result =
+                    unsafe { pg_sys::shm_mq_receive(self.queues[i], &mut size, &mut data, true) };
+                match result {
+                    pg_sys::shm_mq_result_SHM_MQ_SUCCESS => {
+                        self.next_queue = i + 1;
+                        // the message is only valid until the next receive from the queue
+                        let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, size) };
+                        return Some(serde_cbor::from_slice(bytes).expect("failed to decode CBOR"));
+                    }
+                    pg_sys::shm_mq_result_SHM_MQ_DETACHED => {
+                        // the worker has exited
+                        self.queues.swap_remove(i);
+                    }
+                    _ => {
+                        self.next_queue = i + 1;
+                        tried += 1;
+                    }
+                }
+            }
+            if self.queues.is_empty() {
+                return None;
+            }
+
+            unsafe {
+                let events = pg_sys::WaitLatch(
+                    pg_sys::MyLatch,
+                    (pg_sys::WL_LATCH_SET | pg_sys::WL_POSTMASTER_DEATH) as i32,
+                    -1,
+                    pg_sys::WaitEventIPC_WAIT_EVENT_EXECUTE_GATHER,
+                );
+                if events & pg_sys::WL_POSTMASTER_DEATH as i32 != 0 {
+                    pg_sys::proc_exit(1);
+                }
+                pg_sys::ResetLatch(pg_sys::MyLatch);
+            }
+            // which is also where errors from the workers are raised
+            check_for_interrupts!();
+        }
+    }
+}
+
+impl<S, T> Drop for ParallelResults<S, T> {
+    fn drop(&mut self) {
+        // aborting the transaction cleans up after an ERROR
+        if std::thread::panicking() {
+            return;
+        }
+        unsafe {
+            if self.queues.is_empty() {
+                pg_sys::WaitForParallelWorkersToFinish(self.context);
+            }
+            pg_sys::DestroyParallelContext(self.context);
+            pg_sys::ExitParallelMode();
+        }
+    }
+}
+
+/// A parallel worker launched by a [`ParallelQuery`], with the state shared with it
+pub struct ParallelWorker<S> {
+    shared: *const S,
+    queue: *mut pg_sys::shm_mq_handle,
+    number: usize,
+    workers: usize,
+}
+
+impl<S: PGXSharedMemory> ParallelWorker<S> {
+    /// Attach to the leader's dynamic shared memory, from the worker's function.
+    ///
+    /// ## Safety
+    ///
+    /// `seg` and `toc` must be the arguments Postgres called the worker's function with, and the
+    /// leader's state must be a `S`.
+    ///
+    /// ## Panics
+    ///
+    /// If the leader's state isn't the size of a `S`, which means it's something else
+    pub unsafe fn attach(seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) -> Self {
+        let header = &*(pg_sys::shm_toc_lookup(toc, KEY_HEADER, false) as *const Header);
+        assert_eq!(
+            header.shared_size,
+            std::mem::size_of::<S>().max(1),
+            "the parallel leader's state is not a `{}`",
+            std::any::type_name::<S>()
+        );
+        let shared = pg_sys::shm_toc_lookup(toc, KEY_SHARED, false) as *const S;
+        let queue_space = pg_sys::shm_toc_lookup(toc, KEY_QUEUES, false) as *mut u8;
+        let number = pg_sys::ParallelWorkerNumber as usize;
+        let queue = queue_space.add(number * header.queue_size) as *mut pg_sys::shm_mq;
+        pg_sys::shm_mq_set_sender(queue, pg_sys::MyProc);
+        ParallelWorker {
+            shared,
+            queue: pg_sys::shm_mq_attach(queue, seg, std::ptr::null_mut()),
+            number,
+            workers: header.workers,
+        }
+    }
+
+    /// The state shared with the leader and the other workers
+    pub fn shared(&self) -> &S {
+        unsafe { &*self.shared }
+    }
+
+    /// Which worker this is, from 0
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// How many workers the leader planned for, which may be more than were launched, so dividing
+    /// work up by [`number`](ParallelWorker::number) can leave some undone.  Claiming it from the
+    /// shared state can't.
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Send `value` to the leader, waiting while the queue is full.  Returns `false` if the leader
+    /// has stopped reading, so the worker can stop too.
+    pub fn send<T: Serialize>(&self, value: &T) -> bool {
+        let bytes = serde_cbor::to_vec(value).expect("failed to encode as CBOR");
+        let result = unsafe {
+            pg_sys::shm_mq_send(
+                self.queue,
+                bytes.len(),
+                bytes.as_ptr() as *const std::ffi::c_void,
+                false,
+            )
+        };
+        result == pg_sys::shm_mq_result_SHM_MQ_SUCCESS
+    }
+}
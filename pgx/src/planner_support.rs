@@ -0,0 +1,271 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Planner support functions, which tell the planner more about a function than its `pg_proc`
+//! entry can (Postgres 12+)
+use crate::{is_a, pg_sys, FromDatum, Internal, IntoDatum, PgList};
+use std::marker::PhantomData;
+
+/// Answer the planner's request to a support function, which is a `#[pg_extern]` taking and
+/// returning `Internal` and named by the `support` option of the functions it supports.
+///
+/// The planner asks with a node describing a call to a supported function, which `handler` sees as
+/// a [`SupportRequest`].  It answers with a [`SupportResponse`] from the request, or
+/// [`SupportResponse::declined()`] when it can't help, which is always a correct answer:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern(immutable)]
+/// fn add_support(request: Internal) -> Internal {
+///     planner_support(request, |request| match request {
+///         // `add(x, 0)` is just `x`
+///         SupportRequest::Simplify(call) if call.args().const_value::<i32>(1) == Some(0) => {
+///             call.replace_with_arg(0)
+///         }
+///         _ => SupportResponse::declined(),
+///     })
+/// }
+///
+/// #[pg_extern(immutable, support = add_support)]
+/// fn add(a: i32, b: i32) -> i32 {
+///     a + b
+/// }
+/// ```
+///
+/// Postgres only passes `internal` arguments from C, so SQL can't call a support function with
+/// anything but the planner's request.
+pub fn planner_support<F>(request: Internal, handler: F) -> Internal
+where
+    F: FnOnce(SupportRequest<'_>) -> SupportResponse,
+{
+    match request.unwrap() {
+        Some(datum) if datum != 0 => {
+            let request = unsafe { SupportRequest::from_node(datum as *mut pg_sys::Node) };
+            Internal::from(Some(handler(request).0))
+        }
+        _ => Internal::from(Some(SupportResponse::declined().0)),
+    }
+}
+
+/// What the planner is asking a support function.  See [`planner_support`].
+pub enum SupportRequest<'a> {
+    /// Can a call be replaced with something simpler, for any arguments that are constant?
+    Simplify(SimplifyRequest<'a>),
+    /// What fraction of rows does a call to a function returning `bool` select?
+    Selectivity(SelectivityRequest<'a>),
+    /// How many rows does a call to a set-returning function return?
+    Rows(RowsRequest<'a>),
+    /// A request pgx doesn't have a view of, such as `SupportRequestCost`, as its node
+    Other(*mut pg_sys::Node),
+}
+
+impl<'a> SupportRequest<'a> {
+    unsafe fn from_node(node: *mut pg_sys::Node) -> Self {
+        if is_a(node, pg_sys::NodeTag_T_SupportRequestSimplify) {
+            SupportRequest::Simplify(SimplifyRequest(
+                &mut *(node as *mut pg_sys::SupportRequestSimplify),
+            ))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestSelectivity) {
+            SupportRequest::Selectivity(SelectivityRequest(
+                &mut *(node as *mut pg_sys::SupportRequestSelectivity),
+            ))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestRows) {
+            SupportRequest::Rows(RowsRequest(&mut *(node as *mut pg_sys::SupportRequestRows)))
+        } else {
+            SupportRequest::Other(node)
+        }
+    }
+}
+
+/// A support function's answer to the planner, made by the request it answers
+pub struct SupportResponse(pg_sys::Datum);
+
+impl SupportResponse {
+    /// The support function can't help, and the planner carries on as it would without one
+    pub fn declined() -> Self {
+        SupportResponse(0)
+    }
+}
+
+/// The arguments of a call the planner is asking about, which are expressions that are only
+/// sometimes constants
+pub struct SupportArgs<'a> {
+    args: PgList<pg_sys::Node>,
+    _marker: PhantomData<&'a pg_sys::List>,
+}
+
+impl<'a> SupportArgs<'a> {
+    unsafe fn from_pg(args: *mut pg_sys::List) -> Self {
+        SupportArgs {
+            args: PgList::from_pg(args),
+            _marker: PhantomData,
+        }
+    }
+
+    /// How many arguments the call has
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Does the call have no arguments?
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// The expression of the argument at `index`
+    pub fn get(&self, index: usize) -> Option<*mut pg_sys::Node> {
+        self.args.get_ptr(index)
+    }
+
+    /// Is the argument at `index` a constant, including a constant `NULL`?
+    pub fn is_const(&self, index: usize) -> bool {
+        match self.get(index) {
+            Some(arg) => unsafe { is_a(arg, pg_sys::NodeTag_T_Const) },
+            None => false,
+        }
+    }
+
+    /// The value of the argument at `index`, if it's a constant `T` that isn't `NULL`
+    pub fn const_value<T: FromDatum + IntoDatum>(&self, index: usize) -> Option<T> {
+        if !self.is_const(index) {
+            return None;
+        }
+        let arg = unsafe { &*(self.get(index)? as *mut pg_sys::Const) };
+        if arg.consttype != T::type_oid() {
+            return None;
+        }
+        unsafe { T::from_datum(arg.constvalue, arg.constisnull, arg.consttype) }
+    }
+}
+
+/// A `SupportRequestSimplify`, which the planner makes of each call to a supported function while
+/// simplifying a query's expressions
+pub struct SimplifyRequest<'a>(&'a mut pg_sys::SupportRequestSimplify);
+
+impl<'a> SimplifyRequest<'a> {
+    fn call(&self) -> &pg_sys::FuncExpr {
+        unsafe { &*self.0.fcall }
+    }
+
+    /// The function being called, which may be a different one with the same support function
+    pub fn function_oid(&self) -> pg_sys::Oid {
+        self.call().funcid
+    }
+
+    /// The arguments of the call
+    pub fn args(&self) -> SupportArgs<'_> {
+        unsafe { SupportArgs::from_pg(self.call().args) }
+    }
+
+    /// Replace the call with the constant `value`, which must be of the function's return type
+    pub fn replace_with_const<T: IntoDatum>(self, value: Option<T>) -> SupportResponse {
+        let call = self.call();
+        if T::type_oid() != call.funcresulttype {
+            panic!(
+                "can't replace a call returning type {} with a `{}`",
+                call.funcresulttype,
+                std::any::type_name::<T>()
+            );
+        }
+
+        let datum = value.and_then(|value| value.into_datum());
+        let mut typlen = 0;
+        let mut typbyval = false;
+        unsafe {
+            pg_sys::get_typlenbyval(call.funcresulttype, &mut typlen, &mut typbyval);
+            let replacement = pg_sys::makeConst(
+                call.funcresulttype,
+                -1,
+                call.funccollid,
+                typlen as i32,
+                datum.unwrap_or(0),
+                datum.is_none(),
+                typbyval,
+            );
+            SupportResponse(replacement as pg_sys::Datum)
+        }
+    }
+
+    /// Replace the call with its argument at `index`, which must be of the function's return type
+    pub fn replace_with_arg(self, index: usize) -> SupportResponse {
+        let call = self.call();
+        let arg = self
+            .args()
+            .get(index)
+            .unwrap_or_else(|| panic!("the call has no argument {}", index));
+        let arg_type = unsafe { pg_sys::exprType(arg) };
+        if arg_type != call.funcresulttype {
+            panic!(
+                "can't replace a call returning type {} with argument {} of type {}",
+                call.funcresulttype, index, arg_type
+            );
+        }
+        SupportResponse(arg as pg_sys::Datum)
+    }
+}
+
+/// A `SupportRequestSelectivity`, which the planner makes of a supported function returning
+/// `bool` when it's a condition of a `WHERE` or `JOIN`, or the function of an operator that is
+pub struct SelectivityRequest<'a>(&'a mut pg_sys::SupportRequestSelectivity);
+
+impl<'a> SelectivityRequest<'a> {
+    /// The function being called
+    pub fn function_oid(&self) -> pg_sys::Oid {
+        self.0.funcid
+    }
+
+    /// The arguments of the call
+    pub fn args(&self) -> SupportArgs<'_> {
+        unsafe { SupportArgs::from_pg(self.0.args) }
+    }
+
+    /// Is the call a condition joining two relations, rather than one restricting a single one?
+    pub fn is_join(&self) -> bool {
+        self.0.is_join
+    }
+
+    /// The call selects `selectivity` of the rows, from `0.0` for none to `1.0` for all of them
+    pub fn selectivity(self, selectivity: f64) -> SupportResponse {
+        self.0.selectivity = selectivity.clamp(0.0, 1.0);
+        SupportResponse(self.0 as *mut pg_sys::SupportRequestSelectivity as pg_sys::Datum)
+    }
+}
+
+/// A `SupportRequestRows`, which the planner makes of a supported set-returning function called
+/// in a query's `FROM`
+pub struct RowsRequest<'a>(&'a mut pg_sys::SupportRequestRows);
+
+impl<'a> RowsRequest<'a> {
+    /// The function being called
+    pub fn function_oid(&self) -> pg_sys::Oid {
+        self.0.funcid
+    }
+
+    /// The arguments of the call, which is a function's or an operator's
+    pub fn args(&self) -> SupportArgs<'_> {
+        unsafe {
+            let node = self.0.node;
+            let args = if is_a(node, pg_sys::NodeTag_T_FuncExpr) {
+                (*(node as *mut pg_sys::FuncExpr)).args
+            } else if is_a(node, pg_sys::NodeTag_T_OpExpr) {
+                (*(node as *mut pg_sys::OpExpr)).args
+            } else {
+                std::ptr::null_mut()
+            };
+            SupportArgs::from_pg(args)
+        }
+    }
+
+    /// The call returns about `rows` rows
+    pub fn rows(self, rows: f64) -> SupportResponse {
+        self.0.rows = rows.max(0.0);
+        SupportResponse(self.0 as *mut pg_sys::SupportRequestRows as pg_sys::Datum)
+    }
+}
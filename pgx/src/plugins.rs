@@ -0,0 +1,368 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rust dynamic libraries an extension loads at runtime, so others can extend it without
+//! recompiling it
+//!
+//! A plugin is a `cdylib` crate which depends on `pgx` and declares the functions it provides
+//! with [`declare_plugin!`](crate::declare_plugin):
+//!
+//! ```rust,no_run
+//! use pgx::plugins::{PluginCall, PluginRegistrar};
+//! use pgx::*;
+//!
+//! pgx::declare_plugin!(register);
+//!
+//! fn register(registrar: &mut PluginRegistrar) {
+//!     registrar.function("score", score);
+//! }
+//!
+//! #[pg_guard]
+//! unsafe extern "C" fn score(call: &mut PluginCall) -> pg_sys::Datum {
+//!     let hits = call.arg::<i32>(0).unwrap_or(0);
+//!     let boost = call.arg::<f64>(1).unwrap_or(1.0);
+//!     call.result(Some(hits as f64 * boost))
+//! }
+//! ```
+//!
+//! The extension, built with the `plugins` feature of `pgx`, then loads it and calls its
+//! functions by name:
+//!
+//! ```rust,no_run
+//! use pgx::plugins::Plugin;
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn plugin_score(plugin: &str, hits: i32, boost: f64) -> Option<f64> {
+//!     let plugin = Plugin::load(format!("$libdir/{}", plugin)).unwrap();
+//!     plugin
+//!         .call::<f64>("score", &[hits.into_datum(), boost.into_datum()])
+//!         .unwrap()
+//! }
+//! ```
+//!
+//! The two are only compatible when they were built for the same major version of Postgres and
+//! with the same [`PLUGIN_ABI_VERSION`], which [`Plugin::load()`] checks, but may otherwise be
+//! built with different versions of Rust and `pgx`.  Everything which passes between them is
+//! `#[repr(C)]`, and functions are only called through `extern "C"` pointers.
+//!
+//! A plugin runs with all the privileges of the backend which loads it, so an extension should
+//! only load them from trusted locations, such as `$libdir`, and not from paths given by
+//! unprivileged users.
+use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
+use std::collections::HashMap;
+use std::ffi::{CStr, OsString};
+use std::os::raw::c_void;
+
+/// The version of the interface between extensions and their plugins, which changes whenever
+/// the layout or meaning of what passes between them does
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The name of the [`PluginDeclaration`] a plugin exports
+pub const PLUGIN_DECLARATION_SYMBOL: &[u8] = b"PGX_PLUGIN_DECLARATION\0";
+
+/// What a plugin exports, for an extension to check that it's compatible, then have it register
+/// its functions
+///
+/// This is made by [`declare_plugin!`](crate::declare_plugin).
+#[repr(C)]
+pub struct PluginDeclaration {
+    pub abi_version: u32,
+    /// The major version of Postgres the plugin was built for, as in `PG_VERSION_NUM / 100`
+    pub pg_version: u32,
+    pub register: unsafe extern "C" fn(registrar: &mut PluginRegistrar),
+}
+
+/// A function provided by a plugin
+///
+/// It's called in a memory context of its own, which is deleted once it returns, so it needn't
+/// free what it allocates.  Its result must be made with [`PluginCall::result()`] so that it's
+/// allocated in the caller's context instead.  It should be `#[pg_guard]`, so that it raises an
+/// `ERROR` rather than unwinding into the extension if it panics.
+pub type PluginFunction = unsafe extern "C" fn(call: &mut PluginCall) -> pg_sys::Datum;
+
+/// The arguments of a call to a [`PluginFunction`], and where its result goes
+#[repr(C)]
+pub struct PluginCall {
+    args: *const pg_sys::Datum,
+    nulls: *const bool,
+    nargs: usize,
+    result_context: pg_sys::MemoryContext,
+    result_is_null: bool,
+}
+
+impl PluginCall {
+    /// The number of arguments the function was called with
+    pub fn nargs(&self) -> usize {
+        self.nargs
+    }
+
+    /// Argument `i` converted to a `T`, or `None` if it's NULL
+    ///
+    /// ## Safety
+    ///
+    /// The argument must be a Datum of the type `T` is converted from.  Pass-by-reference
+    /// arguments belong to the caller, and are only valid until the function returns.
+    ///
+    /// ## Panics
+    ///
+    /// If there are only `i` arguments or fewer.
+    pub unsafe fn arg<T: FromDatum>(&self, i: usize) -> Option<T> {
+        if i >= self.nargs {
+            panic!(
+                "plugin function has {} arguments, so there is no argument {}",
+                self.nargs, i
+            );
+        }
+        T::from_datum(*self.args.add(i), *self.nulls.add(i), pg_sys::InvalidOid)
+    }
+
+    /// The Datum to return for `value`, allocated in the caller's memory context
+    pub fn result<T: IntoDatum>(&mut self, value: Option<T>) -> pg_sys::Datum {
+        let previous_context = PgMemoryContexts::For(self.result_context).set_as_current();
+        let datum = value.and_then(IntoDatum::into_datum);
+        previous_context.set_as_current();
+        self.result_is_null = datum.is_none();
+        datum.unwrap_or(0)
+    }
+}
+
+/// What a plugin registers its functions with, when it's loaded
+#[repr(C)]
+pub struct PluginRegistrar {
+    functions: *mut c_void,
+    add_function: unsafe extern "C" fn(
+        functions: *mut c_void,
+        name: *const u8,
+        name_len: usize,
+        function: PluginFunction,
+    ),
+}
+
+impl PluginRegistrar {
+    /// Provide `function` under `name`, replacing any function already registered with it
+    pub fn function(&mut self, name: &str, function: PluginFunction) {
+        unsafe { (self.add_function)(self.functions, name.as_ptr(), name.len(), function) }
+    }
+}
+
+unsafe extern "C" fn add_function(
+    functions: *mut c_void,
+    name: *const u8,
+    name_len: usize,
+    function: PluginFunction,
+) {
+    let functions = &mut *(functions as *mut HashMap<String, PluginFunction>);
+    let name = std::slice::from_raw_parts(name, name_len);
+    functions.insert(String::from_utf8_lossy(name).into_owned(), function);
+}
+
+/// Declare the crate to be a plugin, whose functions are registered by `$register`, a
+/// `fn(&mut PluginRegistrar)`
+///
+/// See the [`plugins`](crate::plugins) module for an example.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        #[doc(hidden)]
+        pub static PGX_PLUGIN_DECLARATION: $crate::plugins::PluginDeclaration =
+            $crate::plugins::PluginDeclaration {
+                abi_version: $crate::plugins::PLUGIN_ABI_VERSION,
+                pg_version: $crate::pg_sys::PG_VERSION_NUM / 100,
+                register: {
+                    #[$crate::pg_guard]
+                    unsafe extern "C" fn register(
+                        registrar: &mut $crate::plugins::PluginRegistrar,
+                    ) {
+                        $register(registrar)
+                    }
+                    register
+                },
+            };
+    };
+}
+
+/// Why a [`Plugin`] could not be loaded, or one of its functions called
+#[derive(Debug)]
+pub enum PluginError {
+    /// The library could not be opened
+    Load(libloading::Error),
+    /// The library doesn't export a [`PluginDeclaration`]
+    NotAPlugin { path: OsString },
+    /// The plugin was built for a different [`PLUGIN_ABI_VERSION`]
+    AbiVersion { expected: u32, found: u32 },
+    /// The plugin was built for a different major version of Postgres
+    PgVersion { expected: u32, found: u32 },
+    /// The plugin has no function of this name
+    NoSuchFunction(String),
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Load(e) => write!(f, "could not load plugin: {}", e),
+            PluginError::NotAPlugin { path } => {
+                write!(f, "`{}` is not a pgx plugin", path.to_string_lossy())
+            }
+            PluginError::AbiVersion { expected, found } => write!(
+                f,
+                "plugin has ABI version {}, but version {} is required",
+                found, expected
+            ),
+            PluginError::PgVersion { expected, found } => write!(
+                f,
+                "plugin was built for Postgres {}, but this is Postgres {}",
+                found / 100,
+                expected / 100
+            ),
+            PluginError::NoSuchFunction(name) => write!(f, "plugin has no function `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PluginError::Load(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A loaded plugin, whose functions can be called by name
+///
+/// As with the libraries Postgres loads itself, the library is never unloaded, as what it leaves
+/// behind, such as callbacks it registered or memory contexts it created, can outlive the
+/// `Plugin`.  Loading it again is cheap, but an extension which calls a plugin often should still
+/// keep it, such as in its [backend state](crate::backend_state), rather than load it for each
+/// call.
+pub struct Plugin {
+    functions: HashMap<String, PluginFunction>,
+}
+
+impl Plugin {
+    /// Load the plugin at `path`, where a leading `$libdir` is the directory Postgres installs
+    /// extensions' libraries in
+    pub fn load(path: impl Into<OsString>) -> Result<Plugin, PluginError> {
+        let path = expand_libdir(path.into());
+        unsafe {
+            let library = libloading::Library::new(&path).map_err(PluginError::Load)?;
+            let declaration = library
+                .get::<*const PluginDeclaration>(PLUGIN_DECLARATION_SYMBOL)
+                .map_err(|_| PluginError::NotAPlugin { path: path.clone() })?;
+            let declaration = &**declaration;
+
+            if declaration.abi_version != PLUGIN_ABI_VERSION {
+                return Err(PluginError::AbiVersion {
+                    expected: PLUGIN_ABI_VERSION,
+                    found: declaration.abi_version,
+                });
+            } else if declaration.pg_version != pg_sys::PG_VERSION_NUM / 100 {
+                return Err(PluginError::PgVersion {
+                    expected: pg_sys::PG_VERSION_NUM / 100,
+                    found: declaration.pg_version,
+                });
+            }
+
+            let mut functions = HashMap::new();
+            let mut registrar = PluginRegistrar {
+                functions: &mut functions as *mut HashMap<String, PluginFunction> as *mut c_void,
+                add_function,
+            };
+            let register = declaration.register;
+            guarded(|| register(&mut registrar));
+
+            // the library is leaked, as Postgres never unloads libraries either
+            std::mem::forget(library);
+            Ok(Plugin { functions })
+        }
+    }
+
+    /// The names of the functions the plugin provides
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    /// Does the plugin provide a function named `name`?
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Call the plugin's function `name` with `args`, converting its result to an `R`
+    ///
+    /// An `ERROR` the function raises is raised here too, as with any Postgres function.
+    pub fn call<R: FromDatum>(
+        &self,
+        name: &str,
+        args: &[Option<pg_sys::Datum>],
+    ) -> Result<Option<R>, PluginError> {
+        let function = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| PluginError::NoSuchFunction(name.to_string()))?;
+
+        let datums = args.iter().map(|arg| arg.unwrap_or(0)).collect::<Vec<_>>();
+        let nulls = args.iter().map(Option::is_none).collect::<Vec<_>>();
+        let mut call = PluginCall {
+            args: datums.as_ptr(),
+            nulls: nulls.as_ptr(),
+            nargs: args.len(),
+            result_context: PgMemoryContexts::CurrentMemoryContext.value(),
+            result_is_null: true,
+        };
+        let call_ptr: *mut PluginCall = &mut call;
+
+        let datum = PgMemoryContexts::Transient {
+            parent: PgMemoryContexts::CurrentMemoryContext.value(),
+            name: "pgx plugin call",
+            min_context_size: pg_sys::ALLOCSET_SMALL_MINSIZE,
+            initial_block_size: pg_sys::ALLOCSET_SMALL_INITSIZE,
+            max_block_size: pg_sys::ALLOCSET_SMALL_MAXSIZE,
+        }
+        .switch_to(|_| unsafe { guarded(|| function(&mut *call_ptr)) });
+        Ok(unsafe { R::from_datum(datum, call.result_is_null, pg_sys::InvalidOid) })
+    }
+}
+
+/// Call into a plugin, raising any `ERROR` it raises here instead
+///
+/// `f` is called by Postgres, as a function given to `DirectFunctionCall1Coll()`, so the ERROR is
+/// caught by the guard every call into Postgres has.  Nothing `f` owns is dropped if it's raised.
+unsafe fn guarded<T, F: FnOnce() -> T>(f: F) -> T {
+    unsafe extern "C" fn trampoline<T, F: FnOnce() -> T>(
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> pg_sys::Datum {
+        let state = &mut *(crate::pg_getarg_datum_raw(fcinfo, 0) as *mut (Option<F>, Option<T>));
+        let f = state.0.take().expect("plugin call made twice");
+        state.1 = Some(f());
+        0
+    }
+
+    let mut state: (Option<F>, Option<T>) = (Some(f), None);
+    pg_sys::DirectFunctionCall1Coll(
+        Some(trampoline::<T, F>),
+        pg_sys::InvalidOid,
+        &mut state as *mut (Option<F>, Option<T>) as pg_sys::Datum,
+    );
+    state.1.expect("plugin call didn't return")
+}
+
+/// Replace a leading `$libdir` in `path`, as Postgres does for `LOAD` and `CREATE FUNCTION`
+fn expand_libdir(path: OsString) -> OsString {
+    match path.to_str().and_then(|path| path.strip_prefix("$libdir")) {
+        Some(rest) => {
+            let libdir = unsafe { CStr::from_ptr(pg_sys::pkglib_path.as_ptr()) };
+            let mut expanded = OsString::from(libdir.to_string_lossy().into_owned());
+            expanded.push(rest);
+            expanded
+        }
+        None => path,
+    }
+}
@@ -0,0 +1,50 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Whether the server is a standby, which can only read, for functions that write
+//!
+//! A write on a standby fails deep inside Postgres, with an error that says little about why, so
+//! functions that write should check first:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern(primary_only)]
+//! fn record_visit() {
+//!     Spi::run("INSERT INTO visits DEFAULT VALUES");
+//! }
+//! ```
+//!
+//! Called on a standby, that's `ERROR: cannot execute record_visit() during recovery`, as Postgres
+//! says for its own commands.  Functions without `primary_only` run on a standby as usual, which
+//! is right for those that only read.
+use crate::{ereport, pg_sys, PgSqlErrorCode};
+
+/// Is the server recovering, as a standby or after a crash, so it can't write?
+pub fn in_recovery() -> bool {
+    unsafe { pg_sys::RecoveryInProgress() }
+}
+
+/// Is the server a hot standby, which runs read-only queries while it recovers?
+pub fn is_hot_standby() -> bool {
+    in_recovery() && unsafe { pg_sys::HotStandbyActive() }
+}
+
+/// Raise the `ERROR` Postgres does for its own commands which write, `cannot execute <what> during
+/// recovery`, if the server is [recovering](in_recovery).  `#[pg_extern(primary_only)]` functions
+/// call this with their name first.
+pub fn prevent_during_recovery(what: &str) {
+    if in_recovery() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_READ_ONLY_SQL_TRANSACTION,
+            format!("cannot execute {} during recovery", what),
+        );
+    }
+}
@@ -9,7 +9,8 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides a safe wrapper around Postgres' `pg_sys::RelationData` struct
 use crate::{
-    direct_function_call, name_data_to_str, pg_sys, FromDatum, IntoDatum, PgBox, PgList,
+    direct_function_call, name_data_to_str, pg_sys, relation_lock_conflicts,
+    ConflictingTransaction, FromDatum, IntoDatum, LockMode, PgBox, PgList, PgRelationLockGuard,
     PgTupleDesc,
 };
 use std::ops::Deref;
@@ -239,6 +240,30 @@ impl PgRelation {
         }
     }
 
+    /// Lock this relation in `mode`, as `LOCK TABLE` does, until the returned guard is dropped
+    ///
+    /// ```rust,no_run
+    /// use pgx::{LockMode, PgRelation};
+    /// # fn example(relation: &PgRelation) {
+    /// let guard = relation.lock(LockMode::ShareRowExclusive);
+    /// // ... no other transaction can modify the relation here ...
+    /// drop(guard);
+    /// # }
+    /// ```
+    pub fn lock(&self, mode: LockMode) -> PgRelationLockGuard {
+        PgRelationLockGuard::acquire(self.oid(), mode)
+    }
+
+    /// Lock this relation in `mode` if that can be done without waiting
+    pub fn try_lock(&self, mode: LockMode) -> Option<PgRelationLockGuard> {
+        PgRelationLockGuard::try_acquire(self.oid(), mode)
+    }
+
+    /// The other transactions that would block us from locking this relation in `mode`
+    pub fn lock_conflicts(&self, mode: LockMode) -> Vec<ConflictingTransaction> {
+        relation_lock_conflicts(self.oid(), mode)
+    }
+
     pub fn is_table(&self) -> bool {
         let rd_rel: &pg_sys::FormData_pg_class =
             unsafe { self.boxed.rd_rel.as_ref().expect("rd_rel is NULL") };
@@ -0,0 +1,163 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Retrying work that failed on a serialization failure or a deadlock, in a subtransaction
+use crate::log::{error_data_message, take_error_data};
+use crate::{check_for_interrupts, debug1, pg_sys, PgSqlErrorCode};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+/// The errors worth another attempt: `40001` and `40P01`
+const RETRYABLE: [PgSqlErrorCode; 2] = [
+    PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE,
+    PgSqlErrorCode::ERRCODE_T_R_DEADLOCK_DETECTED,
+];
+
+/// Runs a closure in a subtransaction, and runs it again in a new one if it fails with a
+/// serialization failure (`SQLSTATE 40001`) or a deadlock (`40P01`), waiting longer before each
+/// attempt.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn transfer(from: i64, to: i64, amount: i64) {
+///     PgRetry::new().set_max_attempts(5).run(|| {
+///         Spi::run(&format!(
+///             "UPDATE accounts SET balance = balance - {} WHERE id = {}",
+///             amount, from
+///         ));
+///         Spi::run(&format!(
+///             "UPDATE accounts SET balance = balance + {} WHERE id = {}",
+///             amount, to
+///         ));
+///     })
+/// }
+/// ```
+///
+/// A failed attempt's subtransaction is rolled back, undoing its changes and releasing its locks,
+/// before the next.  Any other error, a panic, or the last attempt's failure is raised as usual.
+///
+/// That helps with deadlocks in any isolation level, and with serialization failures in
+/// `READ COMMITTED`, where each statement sees the latest committed data.  In `REPEATABLE READ`
+/// and `SERIALIZABLE`, the whole transaction keeps one snapshot, so another attempt usually fails
+/// the same way, and only the client can retry the transaction.
+///
+/// The closure can run more than once, so whatever it does outside the database has to be safe to
+/// do again.
+#[derive(Debug, Clone)]
+pub struct PgRetry {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for PgRetry {
+    fn default() -> Self {
+        PgRetry {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PgRetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up after this many attempts.  The default is 3.
+    pub fn set_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait `initial` after the first failed attempt, doubling each time up to `max`.  The
+    /// default is 10ms, up to 1s.
+    pub fn set_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Run `f` until an attempt succeeds, fails with an error that isn't worth retrying, or
+    /// there have been the maximum number of attempts
+    pub fn run<R, F: FnMut() -> R>(&self, mut f: F) -> R {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            let edata = match unsafe { attempt_in_subtransaction(&mut f) } {
+                Ok(result) => return result,
+                Err(edata) => edata,
+            };
+            unsafe {
+                let retryable = RETRYABLE
+                    .iter()
+                    .any(|code| *code as i32 == (*edata).sqlerrcode);
+                if !retryable || attempt >= self.max_attempts {
+                    pg_sys::ReThrowError(edata);
+                    unreachable!("ReThrowError() returned");
+                }
+
+                let message = error_data_message(edata);
+                debug1!(
+                    "retrying after attempt {} of {} failed: {}",
+                    attempt,
+                    self.max_attempts,
+                    message
+                );
+                pg_sys::FreeErrorData(edata);
+            }
+
+            check_for_interrupts!();
+            unsafe { pg_sys::pg_usleep(backoff.as_micros() as std::os::raw::c_long) };
+            check_for_interrupts!();
+            backoff = (backoff * 2).min(self.max_backoff);
+            attempt += 1;
+        }
+    }
+}
+
+/// Run `f` in a subtransaction, committing it if `f` returns, and rolling it back if `f` raises
+/// an `ERROR`, which is returned, or panics, which continues
+unsafe fn attempt_in_subtransaction<R, F: FnMut() -> R>(
+    f: &mut F,
+) -> Result<R, *mut pg_sys::ErrorData> {
+    let memory_context = pg_sys::CurrentMemoryContext;
+    let resource_owner = pg_sys::CurrentResourceOwner;
+
+    pg_sys::BeginInternalSubTransaction(std::ptr::null());
+    // so whatever `f` returns outlives the subtransaction
+    pg_sys::CurrentMemoryContext = memory_context;
+
+    let result = catch_unwind(AssertUnwindSafe(f));
+    pg_sys::CurrentMemoryContext = memory_context;
+    let result = match result {
+        Ok(result) => {
+            pg_sys::ReleaseCurrentSubTransaction();
+            Ok(result)
+        }
+        Err(e) => {
+            // a Postgres ERROR, rather than a Rust panic
+            let edata = e
+                .downcast_ref::<pg_sys::JumpContext>()
+                .map(|_| take_error_data(memory_context));
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            Err((edata, e))
+        }
+    };
+    pg_sys::CurrentMemoryContext = memory_context;
+    pg_sys::CurrentResourceOwner = resource_owner;
+    match result {
+        Ok(result) => Ok(result),
+        Err((Some(edata), _)) => Err(edata),
+        Err((None, panic)) => resume_unwind(panic),
+    }
+}
@@ -0,0 +1,124 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checking who is calling a function, for functions which only some roles should be able to use
+use crate::{ereport, pg_sys, PgSqlErrorCode};
+use std::ffi::CString;
+
+/// The OID of the role privileges are checked against, which is the role a `SECURITY DEFINER`
+/// function was defined by while it runs, and the current user otherwise
+pub fn current_role_oid() -> pg_sys::Oid {
+    unsafe { pg_sys::GetUserId() }
+}
+
+/// The OID of the role named `name`, or `None` if there's no such role
+pub fn role_oid(name: &str) -> Option<pg_sys::Oid> {
+    let name = CString::new(name).ok()?;
+    match unsafe { pg_sys::pgx_get_role_oid(name.as_ptr()) } {
+        pg_sys::InvalidOid => None,
+        oid => Some(oid),
+    }
+}
+
+/// Does the current role have the privileges of the role named `name`, by being it, being a member
+/// of it that inherits its privileges, or being a superuser?  A role that doesn't exist is no
+/// one's.
+pub fn has_role(name: &str) -> bool {
+    match role_oid(name) {
+        Some(role) => unsafe { pg_sys::pgx_has_privs_of_role(current_role_oid(), role) },
+        None => false,
+    }
+}
+
+/// Is the current role a superuser?
+pub fn is_superuser() -> bool {
+    unsafe { pg_sys::superuser() }
+}
+
+/// Raise an `ERROR` unless the current role has the privileges of the role named `name`.  See
+/// [`require_role!`].
+pub fn require_role(name: &str) {
+    let role = match role_oid(name) {
+        Some(role) => role,
+        None => ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!("role \"{}\" does not exist", name),
+        ),
+    };
+    if !unsafe { pg_sys::pgx_has_privs_of_role(current_role_oid(), role) } {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            "permission denied",
+            detail = format!(
+                "Only roles with the privileges of role \"{}\" may do this.",
+                name
+            ),
+        );
+    }
+}
+
+/// Raise an `ERROR` unless the current role is a superuser.  See [`require_superuser!`].
+pub fn require_superuser() {
+    if !is_superuser() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            "permission denied",
+            detail = "Only superusers may do this.",
+        );
+    }
+}
+
+/// Raise an `ERROR` unless the current role has the privileges of a role, so a function can be
+/// executable by `PUBLIC`, as functions are by default, and still only be used by some roles:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn reset_cache() {
+///     require_role!("myext_admin");
+///     // ...
+/// }
+/// ```
+///
+/// Members of the role who inherit its privileges and superusers pass, and a role that doesn't
+/// exist is an `ERROR` too.
+///
+/// When it's the first statement of a `#[pg_extern]` function, the function's `CREATE FUNCTION`
+/// in the schema notes the role it requires.
+#[macro_export]
+macro_rules! require_role {
+    ($role:expr) => {
+        $crate::require_role($role)
+    };
+}
+
+/// Raise an `ERROR` unless the current role is a superuser:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn drop_all_caches() {
+///     require_superuser!();
+///     // ...
+/// }
+/// ```
+///
+/// When it's the first statement of a `#[pg_extern]` function, the function's `CREATE FUNCTION`
+/// in the schema notes that it requires a superuser.
+#[macro_export]
+macro_rules! require_superuser {
+    () => {
+        $crate::require_superuser()
+    };
+}
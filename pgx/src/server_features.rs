@@ -0,0 +1,146 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checking for facilities which only newer servers have, so functions relying on them can fail
+//! cleanly, or do without, on older ones
+use crate::{ereport, Guc, PgLogLevel, PgSqlErrorCode};
+use once_cell::sync::OnceCell;
+use std::fmt;
+
+/// A facility which only some Postgres servers have.
+///
+/// An extension is compiled for one major version of Postgres, but may be installed on a server
+/// of an older minor version than it was compiled against, and may be built from code which
+/// supports several major versions.  Rather than calling into something the server doesn't have,
+/// check with [`require_feature!`] or [`if_feature!`] first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerFeature {
+    /// Procedures, which are run with `CALL` and can commit transactions, from Postgres 11
+    Procedures,
+    /// Generated columns, from Postgres 12
+    GeneratedColumns,
+    /// Table access methods, from Postgres 12
+    TableAccessMethods,
+    /// Incremental sorts, from Postgres 13
+    IncrementalSort,
+    /// Multirange types, from Postgres 14
+    Multiranges,
+    /// Anything from a server of at least this `server_version_num`, such as `140002` for 14.2,
+    /// for facilities added in a minor release
+    Version(i32),
+}
+
+impl ServerFeature {
+    /// The `server_version_num` of the first version of Postgres with the feature
+    pub fn version_num(&self) -> i32 {
+        match self {
+            ServerFeature::Procedures => 110000,
+            ServerFeature::GeneratedColumns | ServerFeature::TableAccessMethods => 120000,
+            ServerFeature::IncrementalSort => 130000,
+            ServerFeature::Multiranges => 140000,
+            ServerFeature::Version(version_num) => *version_num,
+        }
+    }
+
+    /// Does the server have the feature?
+    pub fn is_available(&self) -> bool {
+        server_version_num() >= self.version_num()
+    }
+
+    /// Raise a `FEATURE_NOT_SUPPORTED` ERROR, starting with `message`, if the server doesn't have
+    /// the feature
+    pub fn require(&self, message: &str) {
+        if !self.is_available() {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                &format!("{} (requires {})", message, self),
+                file!(),
+                line!(),
+                column!(),
+            );
+            unreachable!()
+        }
+    }
+}
+
+/// As in `Postgres 14 or newer`, or `Postgres 14.2 or newer` for a minor release
+impl fmt::Display for ServerFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version_num = self.version_num();
+        let (major, minor) = (version_num / 10000, version_num % 10000);
+        if minor == 0 {
+            write!(f, "Postgres {} or newer", major)
+        } else {
+            write!(f, "Postgres {}.{} or newer", major, minor)
+        }
+    }
+}
+
+/// The `server_version_num` of the server the extension is running in, such as `140002` for 14.2
+pub fn server_version_num() -> i32 {
+    static SERVER_VERSION_NUM: OnceCell<i32> = OnceCell::new();
+    *SERVER_VERSION_NUM.get_or_init(|| {
+        Guc::get::<i32>("server_version_num").expect("server_version_num is not set")
+    })
+}
+
+/// Raise a `FEATURE_NOT_SUPPORTED` ERROR, with `message`, unless the server has the
+/// [`ServerFeature`]
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn merge_ranges(ranges: &str) -> String {
+///     require_feature!(Multiranges, "merge_ranges() needs multiranges");
+///     Spi::get_one_with_args(
+///         "SELECT range_agg(r)::text FROM unnest($1::int4range[]) r",
+///         vec![(PgBuiltInOids::TEXTOID.oid(), ranges.into_datum())],
+///     )
+///     .unwrap()
+/// }
+/// ```
+///
+/// Running it on Postgres 13 raises `merge_ranges() needs multiranges (requires Postgres 14 or
+/// newer)`.  A minor version is required with `require_feature!(Version(140002), "...")`.
+#[macro_export]
+macro_rules! require_feature {
+    ($feature:ident $(($version_num:expr))?, $message:expr) => {
+        $crate::ServerFeature::$feature$(($version_num))?.require($message)
+    };
+}
+
+/// Run the first block if the server has the [`ServerFeature`], and the `else` block, if any,
+/// if it doesn't
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn sort_method() -> &'static str {
+///     if_feature!(IncrementalSort, {
+///         "incremental"
+///     } else {
+///         "full"
+///     })
+/// }
+/// ```
+///
+/// Code which only compiles for some versions of Postgres, such as calls to functions `pg_sys`
+/// only has for them, still needs `#[cfg(feature = "pg14")]` or similar.
+#[macro_export]
+macro_rules! if_feature {
+    ($feature:ident $(($version_num:expr))?, $then:block else $otherwise:block) => {
+        if $crate::ServerFeature::$feature$(($version_num))?.is_available() $then else $otherwise
+    };
+    ($feature:ident $(($version_num:expr))?, $then:block) => {
+        if $crate::ServerFeature::$feature$(($version_num))?.is_available() $then
+    };
+}
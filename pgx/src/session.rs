@@ -0,0 +1,73 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! What's known about the current session and the query it's running, such as for audit logs
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! fn audit(action: &str) {
+//!     log!(
+//!         "{} by pid {} ({}) from {:?}: {}",
+//!         action,
+//!         session::backend_pid(),
+//!         session::application_name().unwrap_or_default(),
+//!         session::client_addr(),
+//!         session::query_string().unwrap_or_default(),
+//!     );
+//! }
+//! ```
+//!
+//! Strings are copied out of Postgres, which may change or free them when the next query starts.
+use crate::pg_sys;
+use std::ffi::CStr;
+use std::net::{IpAddr, SocketAddr};
+use std::os::raw::c_char;
+
+/// Copy a string Postgres owns, which isn't necessarily UTF-8, or set at all
+unsafe fn copy_string(string: *const c_char) -> Option<String> {
+    if string.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(string).to_string_lossy().into_owned())
+    }
+}
+
+/// The process ID of this backend, as `pg_backend_pid()` returns
+pub fn backend_pid() -> i32 {
+    unsafe { pg_sys::MyProcPid }
+}
+
+/// The text of the query the client sent, which is all of it when it's several statements, or
+/// `None` outside of one, such as in a background worker.  Anything invalid in UTF-8 is replaced.
+pub fn query_string() -> Option<String> {
+    unsafe { copy_string(pg_sys::debug_query_string) }
+}
+
+/// The `application_name` the client set, or `None` if it didn't set one
+pub fn application_name() -> Option<String> {
+    unsafe { copy_string(pg_sys::application_name) }.filter(|name| !name.is_empty())
+}
+
+/// The IP address and port the client is connected from, as `inet_client_addr()` and
+/// `inet_client_port()` return, or `None` for clients connected by a Unix-domain socket and
+/// for background workers
+pub fn client_addr() -> Option<SocketAddr> {
+    // NI_MAXHOST
+    let mut host = [0 as c_char; 1025];
+    let mut port = 0;
+    let found = unsafe { pg_sys::pgx_client_addr(host.as_mut_ptr(), host.len() as i32, &mut port) };
+    if !found {
+        return None;
+    }
+    let host = unsafe { CStr::from_ptr(host.as_ptr()) }.to_str().ok()?;
+    // without the zone of a link-local IPv6 address, as Postgres leaves it out too
+    let ip = host.split('%').next()?.parse::<IpAddr>().ok()?;
+    Some(SocketAddr::new(ip, port as u16))
+}
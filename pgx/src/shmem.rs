@@ -6,10 +6,21 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use crate::histogram::{PgHistogram, PgHistogramInner};
 use crate::lwlock::*;
-use crate::{pg_sys, PgAtomic};
+use crate::spinlock::{PgSpinLock, PgSpinLockInner};
+use crate::{pg_guard, pg_sys, PgAtomic};
+use once_cell::sync::Lazy;
 use std::hash::Hash;
-use uuid::Uuid;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+type RegisteredSharedMemory = &'static (dyn PgSharedMemoryInitialization + Sync);
+
+/// The statics declared with `#[pg_shmem]`, in the order their registration ran
+static REGISTERED: Lazy<Mutex<Vec<RegisteredSharedMemory>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static REGISTERED_INITIALIZED: AtomicBool = AtomicBool::new(false);
+static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
 
 /// Custom types that want to participate in shared memory must implement this marker trait
 pub unsafe trait PGXSharedMemory {}
@@ -29,6 +40,9 @@ pub unsafe trait PGXSharedMemory {}
 /// > Extensions that use shared memory **must** be loaded via `postgresql.conf`'s
 /// `shared_preload_libraries` configuration setting.  
 ///
+/// Statics declared with `#[pg_shmem]` are initialized automatically by [`pg_module_magic!`] and
+/// must not also be passed to `pg_shmem_init!()`.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -92,6 +106,19 @@ where
     }
 }
 
+impl<T> PgSharedMemoryInitialization for PgSpinLock<T>
+where
+    T: Default + PGXSharedMemory + 'static,
+{
+    fn pg_init(&'static self) {
+        PgSharedMem::pg_init_spinlock(self);
+    }
+
+    fn shmem_init(&'static self) {
+        PgSharedMem::shmem_init_spinlock(self);
+    }
+}
+
 impl<T> PgSharedMemoryInitialization for PgAtomic<T>
 where
     T: atomic_traits::Atomic + Default,
@@ -105,15 +132,71 @@ where
     }
 }
 
+impl PgSharedMemoryInitialization for PgHistogram {
+    fn pg_init(&'static self) {
+        PgSharedMem::pg_init_histogram(self);
+    }
+
+    fn shmem_init(&'static self) {
+        PgSharedMem::shmem_init_histogram(self);
+    }
+}
+
 /// This struct contains methods to drive creation of types in shared memory
 pub struct PgSharedMem {}
 
 impl PgSharedMem {
+    /// Have `value` initialized along with the other `#[pg_shmem]` statics.
+    ///
+    /// `#[pg_shmem]` calls this when the extension's shared library is loaded.
+    #[doc(hidden)]
+    pub fn register(value: RegisteredSharedMemory) {
+        REGISTERED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(value);
+    }
+
+    /// Request the shared memory for every `#[pg_shmem]` static, and install the
+    /// `shmem_startup_hook` which will create them.
+    ///
+    /// `pg_module_magic!()` calls this when Postgres loads the extension, which must be through
+    /// `shared_preload_libraries` for the shared memory to be available.  Otherwise this does
+    /// nothing, and using the statics will panic.
+    #[doc(hidden)]
+    pub fn initialize_registered() {
+        unsafe {
+            if !pg_sys::process_shared_preload_libraries_in_progress {
+                return;
+            }
+        }
+        if REGISTERED_INITIALIZED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let registered = REGISTERED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if registered.is_empty() {
+            return;
+        }
+        for value in registered.iter() {
+            value.pg_init();
+        }
+
+        unsafe {
+            PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
+            pg_sys::shmem_startup_hook = Some(registered_shmem_startup_hook);
+        }
+    }
+
     /// Must be run from PG_init, use for types which are guarded by a LWLock
     pub fn pg_init_locked<T: Default + PGXSharedMemory>(lock: &PgLwLock<T>) {
         unsafe {
             let lock = std::ffi::CString::new(lock.get_name()).expect("CString::new failed");
-            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<T>());
+            pg_sys::RequestAddinShmemSpace(
+                std::mem::size_of::<T>() + crate::lwlock::lwlock_shmem_size(),
+            );
             pg_sys::RequestNamedLWLockTranche(lock.as_ptr(), 1);
         }
     }
@@ -145,11 +228,73 @@ impl PgSharedMem {
         }
     }
 
+    /// Must be run from PG_init, use for types which are guarded by a spinlock
+    pub fn pg_init_spinlock<T: Default + PGXSharedMemory>(_lock: &PgSpinLock<T>) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<PgSpinLockInner<T>>());
+        }
+    }
+
+    /// Must be run from the shared memory init hook, use for types which are guarded by a spinlock
+    pub fn shmem_init_spinlock<T: Default + PGXSharedMemory>(lock: &PgSpinLock<T>) {
+        unsafe {
+            let shm_name = std::ffi::CString::new(crate::Uuid::random().to_string())
+                .expect("CString::new() failed");
+
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            let _guard = LwLockExclusive::acquire(addin_shmem_init_lock);
+
+            let mut found = false;
+            let fv_shmem = pg_sys::ShmemInitStruct(
+                shm_name.into_raw(),
+                std::mem::size_of::<PgSpinLockInner<T>>(),
+                &mut found,
+            ) as *mut PgSpinLockInner<T>;
+            if !found {
+                std::ptr::write(fv_shmem, PgSpinLockInner::<T>::default());
+            }
+
+            lock.attach(fv_shmem);
+        }
+    }
+
+    /// Must be run from _PG_init for histograms
+    pub fn pg_init_histogram(_histogram: &PgHistogram) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<PgHistogramInner>());
+        }
+    }
+
+    /// Must be run from the shared memory init hook, use for `PgHistogram`
+    pub fn shmem_init_histogram(histogram: &PgHistogram) {
+        unsafe {
+            let shm_name = std::ffi::CString::new(crate::Uuid::random().to_string())
+                .expect("CString::new() failed");
+
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            let _guard = LwLockExclusive::acquire(addin_shmem_init_lock);
+
+            let mut found = false;
+            let fv_shmem = pg_sys::ShmemInitStruct(
+                shm_name.into_raw(),
+                std::mem::size_of::<PgHistogramInner>(),
+                &mut found,
+            ) as *mut PgHistogramInner;
+            if !found {
+                std::ptr::write(fv_shmem, PgHistogramInner::default());
+            }
+
+            histogram.attach(fv_shmem);
+        }
+    }
+
     /// Must be run from the shared memory init hook, use for rust atomics behind `PgAtomic`
     pub fn shmem_init_atomic<T: atomic_traits::Atomic + Default>(atomic: &PgAtomic<T>) {
         unsafe {
-            let shm_name =
-                std::ffi::CString::new(Uuid::new_v4().to_string()).expect("CString::new() failed");
+            let shm_name = std::ffi::CString::new(crate::Uuid::random().to_string())
+                .expect("CString::new() failed");
 
             let addin_shmem_init_lock: *mut pg_sys::LWLock =
                 &mut (*pg_sys::MainLWLockArray.add(21)).lock;
@@ -168,6 +313,22 @@ impl PgSharedMem {
     }
 }
 
+#[pg_guard]
+extern "C" fn registered_shmem_startup_hook() {
+    unsafe {
+        if let Some(prev) = PREV_SHMEM_STARTUP_HOOK {
+            prev();
+        }
+    }
+
+    let registered = REGISTERED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for value in registered.iter() {
+        value.shmem_init();
+    }
+}
+
 unsafe impl PGXSharedMemory for bool {}
 unsafe impl PGXSharedMemory for char {}
 unsafe impl PGXSharedMemory for str {}
@@ -0,0 +1,141 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Logging calls to `#[pg_extern(log_slow)]` functions which take longer than a threshold
+use crate::{
+    direct_function_call, elog, pg_arg_is_null, pg_getarg_datum_raw, pg_sys, GucContext, GucFlags,
+    GucRegistry, GucSetting, IntoDatum, PgLogLevel,
+};
+use std::ffi::CStr;
+use std::time::Duration;
+
+/// The threshold in milliseconds, which stays at -1, disabled, until the setting is defined
+static SLOW_MS: GucSetting<i32> = GucSetting::new(-1);
+
+/// Logs calls to the extension's `#[pg_extern(log_slow)]` functions which take longer than the
+/// threshold set by an operator, as Postgres' `log_min_duration_statement` does for statements.
+///
+/// The extension defines the setting, such as `myext.slow_ms`, when it's loaded:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     SlowCalls::define_guc("myext.slow_ms");
+/// }
+///
+/// #[pg_extern(log_slow)]
+/// fn expensive(document: &str) -> i64 {
+///     document.split_whitespace().count() as i64
+/// }
+/// ```
+///
+/// After `SET myext.slow_ms = 100`, a call taking longer than 100ms is logged like so:
+///
+/// ```text
+/// LOG:  duration: 153.208 ms  function: expensive(text)  arguments: $1 = 'the quick brown fox...'
+/// ```
+///
+/// Each argument is rendered by its type's output function, and cut short after
+/// [`SlowCalls::MAX_ARGUMENT_LEN`] bytes.  For set-returning functions, only the call which
+/// creates the iterator is timed.
+pub struct SlowCalls;
+
+impl SlowCalls {
+    /// How much of each argument is logged, in bytes
+    pub const MAX_ARGUMENT_LEN: usize = 64;
+
+    /// Define the setting `name`, in milliseconds, which is -1 to log nothing by default, and can
+    /// only be changed by superusers.  Call it from `_PG_init()`.
+    pub fn define_guc(name: &str) {
+        GucRegistry::define_int_guc_with_flags(
+            name,
+            "Logs calls to the extension's functions which take at least this long",
+            "Only the functions the extension chooses to time are logged.  Zero logs every \
+             call, and -1 disables logging.",
+            &SLOW_MS,
+            -1,
+            i32::MAX,
+            GucContext::Suset,
+            GucFlags::UNIT_MS,
+        );
+    }
+
+    /// Log the call in `fcinfo` if it took longer than the threshold.  The wrappers generated for
+    /// `#[pg_extern(log_slow)]` functions call this.
+    #[doc(hidden)]
+    pub unsafe fn check(fcinfo: pg_sys::FunctionCallInfo, elapsed: Duration) {
+        let threshold = SLOW_MS.get();
+        if threshold < 0 || elapsed < Duration::from_millis(threshold as u64) {
+            return;
+        }
+
+        let fn_oid = (*(*fcinfo).flinfo).fn_oid;
+        let function =
+            direct_function_call::<&CStr>(pg_sys::regprocedureout, vec![fn_oid.into_datum()])
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+        elog(
+            PgLogLevel::LOG,
+            &format!(
+                "duration: {:.3} ms  function: {}  arguments: {}",
+                elapsed.as_secs_f64() * 1000.0,
+                function,
+                describe_arguments(fcinfo, fn_oid)
+            ),
+        );
+    }
+}
+
+/// The arguments as Postgres logs the parameters of a statement, as in `$1 = 'a', $2 = NULL`
+unsafe fn describe_arguments(fcinfo: pg_sys::FunctionCallInfo, fn_oid: pg_sys::Oid) -> String {
+    let mut declared_types = std::ptr::null_mut();
+    let mut declared_nargs = 0;
+    pg_sys::get_func_signature(fn_oid, &mut declared_types, &mut declared_nargs);
+
+    let nargs = (*fcinfo).nargs as usize;
+    let mut arguments = Vec::with_capacity(nargs);
+    for i in 0..nargs {
+        let value = if pg_arg_is_null(fcinfo, i) {
+            "NULL".to_string()
+        } else {
+            // polymorphic arguments have the type they were resolved to in the calling expression
+            let typoid = match pg_sys::get_fn_expr_argtype((*fcinfo).flinfo, i as i32) {
+                pg_sys::InvalidOid if (i as i32) < declared_nargs => *declared_types.add(i),
+                typoid => typoid,
+            };
+            describe_value(pg_getarg_datum_raw(fcinfo, i), typoid)
+        };
+        arguments.push(format!("${} = {}", i + 1, value));
+    }
+    arguments.join(", ")
+}
+
+/// The value rendered by its type's output function, quoted, and cut short after
+/// [`SlowCalls::MAX_ARGUMENT_LEN`] bytes
+unsafe fn describe_value(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    // pseudo-types such as `internal` can't be output, and unresolved ones have no type at all
+    if typoid == pg_sys::InvalidOid || pg_sys::get_typtype(typoid) == pg_sys::TYPTYPE_PSEUDO as _ {
+        return "?".to_string();
+    }
+
+    let mut output_func = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    pg_sys::getTypeOutputInfo(typoid, &mut output_func, &mut is_varlena);
+    let output = CStr::from_ptr(pg_sys::OidOutputFunctionCall(output_func, datum));
+    let output = output.to_string_lossy();
+
+    let mut end = output.len().min(SlowCalls::MAX_ARGUMENT_LEN);
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    let ellipsis = if end < output.len() { "..." } else { "" };
+    format!("'{}{}'", output[..end].replace('\'', "''"), ellipsis)
+}
@@ -14,7 +14,15 @@ use enum_primitive_derive::*;
 use num_traits::FromPrimitive;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// The number of [`Spi::connect`] calls made by this extension currently active in this backend.
+static SPI_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the `#[pg_extern]` function running in this backend is declared `spi_read_only`
+static IN_READ_ONLY_FUNCTION: AtomicBool = AtomicBool::new(false);
 
 #[derive(Debug, Primitive)]
 pub enum SpiOk {
@@ -60,7 +68,71 @@ pub enum SpiError {
 
 pub struct Spi;
 
-pub struct SpiClient;
+/// A client for executing queries through SPI, handed to the closure given to [`Spi::connect`]
+/// or [`Spi::connect_read_only`].
+///
+/// The `Mode` decides the `read_only` flag every query is executed with.  Only a
+/// `SpiClient<ReadWrite>` can [`update`](SpiClient::update), and inside a `#[pg_extern]`
+/// function declared `spi_read_only` every query runs read-only whatever the `Mode`
+/// (see [`Spi::in_read_only_function`]).
+///
+/// The `'conn` lifetime ties a client to the closure it was given to, so it can't outlive its
+/// SPI connection:
+///
+/// ```rust,compile_fail
+/// use pgx::*;
+///
+/// let mut leaked = None;
+/// Spi::connect(|client| {
+///     leaked = Some(client);
+///     Ok(Some(()))
+/// });
+/// ```
+pub struct SpiClient<'conn, Mode: SpiMode = ReadWrite> {
+    mode: PhantomData<Mode>,
+    connection: PhantomData<&'conn ()>,
+}
+
+/// The execution mode of a [`SpiClient`], either [`ReadOnly`] or [`ReadWrite`]
+pub trait SpiMode: private::Sealed {
+    /// The `read_only` flag passed to `SPI_execute()`
+    const READ_ONLY: bool;
+}
+
+/// Queries run with SPI's `read_only` flag set.
+///
+/// Postgres rejects any command that isn't read-only, and queries see the snapshot of the
+/// calling query rather than taking a new one, as `IMMUTABLE` and `STABLE` functions should.
+pub struct ReadOnly;
+
+/// Queries run with SPI's `read_only` flag unset, and can modify the database
+pub struct ReadWrite;
+
+impl SpiMode for ReadOnly {
+    const READ_ONLY: bool = true;
+}
+
+impl SpiMode for ReadWrite {
+    const READ_ONLY: bool = false;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::ReadOnly {}
+    impl Sealed for super::ReadWrite {}
+}
+
+/// Restores whether SPI runs read-only when the `#[pg_extern]` function it was created for returns
+#[doc(hidden)]
+pub struct SpiFunctionGuard {
+    previous: bool,
+}
+
+impl Drop for SpiFunctionGuard {
+    fn drop(&mut self) {
+        IN_READ_ONLY_FUNCTION.store(self.previous, Ordering::Relaxed);
+    }
+}
 
 #[derive(Debug)]
 pub struct SpiTupleTable {
@@ -72,6 +144,18 @@ pub struct SpiTupleTable {
     current: isize,
 }
 
+/// A value that can be borrowed from a row of a [`SpiTupleTable`] without being copied, by
+/// [`SpiTupleTable::get_borrowed`].
+///
+/// The borrowed value points into memory owned by SPI, so it can only live as long as the
+/// `SpiTupleTable` it was borrowed from, which itself can't outlive `SPI_finish()`.
+pub trait SpiBorrowable<'a>: FromDatum + 'a {}
+
+impl<'a> SpiBorrowable<'a> for &'a str {}
+impl<'a> SpiBorrowable<'a> for &'a [u8] {}
+impl<'a> SpiBorrowable<'a> for &'a std::ffi::CStr {}
+impl<'a> SpiBorrowable<'a> for &'a crate::cstr_core::CStr {}
+
 /// Represents a single `pg_sys::Datum` inside a `SpiHeapTupleData`
 pub struct SpiHeapTupleDataEntry {
     datum: Option<pg_sys::Datum>,
@@ -84,7 +168,103 @@ pub struct SpiHeapTupleData {
     entries: HashMap<usize, SpiHeapTupleDataEntry>,
 }
 
+/// A query with parameters bound to it, made by [`Spi::query`].
+///
+/// Each parameter's type is the SQL type of the Rust value bound to it, so the types and values
+/// can't disagree, and binding `None` binds a NULL of its type:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let names = Spi::query("SELECT name FROM dogs WHERE age > $1 AND owner IS NOT DISTINCT FROM $2")
+///     .bind(3)
+///     .bind(Option::<String>::None)
+///     .map(|row| row.by_ordinal(1).unwrap().value::<String>());
+/// ```
+pub struct SpiQuery<'a> {
+    query: &'a str,
+    args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    limit: Option<i64>,
+    read_only: bool,
+    #[cfg(feature = "pg14")]
+    cursor_options: CursorOptions,
+    #[cfg(feature = "pg14")]
+    must_return_tuples: bool,
+}
+
+#[cfg(feature = "pg14")]
+bitflags! {
+    /// How a [`SpiQuery`] is planned, as Postgres' `CURSOR_OPT_*` flags
+    pub struct CursorOptions: i32 {
+        /// Prefer a plan which returns its first rows quickly
+        const FAST_PLAN = pg_sys::CURSOR_OPT_FAST_PLAN as i32;
+        /// Plan without looking at the parameters' values, as a prepared statement's cached
+        /// generic plan is
+        const GENERIC_PLAN = pg_sys::CURSOR_OPT_GENERIC_PLAN as i32;
+        /// Plan for the parameters' values, as if they were written into the query
+        const CUSTOM_PLAN = pg_sys::CURSOR_OPT_CUSTOM_PLAN as i32;
+        /// Allow a parallel plan
+        const PARALLEL_OK = pg_sys::CURSOR_OPT_PARALLEL_OK as i32;
+    }
+}
+
 impl Spi {
+    /// Start building a query whose `$1`, `$2`, ... parameters are bound with
+    /// [`SpiQuery::bind`].
+    pub fn query(query: &str) -> SpiQuery {
+        SpiQuery {
+            query,
+            args: Vec::new(),
+            limit: None,
+            read_only: false,
+            #[cfg(feature = "pg14")]
+            cursor_options: CursorOptions::empty(),
+            #[cfg(feature = "pg14")]
+            must_return_tuples: false,
+        }
+    }
+
+    /// Is this backend currently inside a [`Spi::connect`] (or [`Spi::execute`]) call?
+    pub fn is_connected() -> bool {
+        Spi::depth() > 0
+    }
+
+    /// The number of [`Spi::connect`] calls made by this extension currently active in this
+    /// backend.
+    ///
+    /// This is `0` outside of SPI, `1` inside a [`Spi::connect`] closure, and grows by one
+    /// for every nested connection, including those made by this extension's functions called
+    /// from SPI queries.  Connections made by other extensions, or by PL/pgSQL, aren't counted.
+    pub fn depth() -> usize {
+        SPI_DEPTH.load(Ordering::Relaxed)
+    }
+
+    /// Is the `#[pg_extern]` function running in this backend declared `spi_read_only`?
+    ///
+    /// If so, every query run through SPI, including by [`Spi::run`] and [`Spi::get_one`], is
+    /// executed with SPI's `read_only` flag set, as Postgres expects of non-volatile functions.
+    pub fn in_read_only_function() -> bool {
+        IN_READ_ONLY_FUNCTION.load(Ordering::Relaxed)
+    }
+
+    /// Called by the wrapper `#[pg_extern]` generates around every function, with whether the
+    /// function is declared `spi_read_only`.  The returned guard restores the caller's setting.
+    #[doc(hidden)]
+    pub fn enter_function(read_only: bool) -> SpiFunctionGuard {
+        SpiFunctionGuard {
+            previous: IN_READ_ONLY_FUNCTION.swap(read_only, Ordering::Relaxed),
+        }
+    }
+
+    /// Returns `Err(SpiError::Unconnected)` unless this backend is connected to SPI.
+    pub fn check_connection() -> std::result::Result<(), SpiError> {
+        if Spi::is_connected() {
+            Ok(())
+        } else {
+            Err(SpiError::Unconnected)
+        }
+    }
+
     pub fn get_one<A: FromDatum + IntoDatum>(query: &str) -> Option<A> {
         Spi::connect(|client| {
             let result = client.select(query, Some(1), None).first().get_one();
@@ -165,7 +345,8 @@ impl Spi {
     ///
     /// ## Safety
     ///
-    /// The statement runs in read/write mode
+    /// The statement runs in read/write mode, unless called from a `#[pg_extern]` function
+    /// declared `spi_read_only`
     pub fn run(query: &str) {
         Spi::execute(|mut client| {
             client.update(query, None, None);
@@ -188,18 +369,50 @@ impl Spi {
     }
 
     /// execute SPI commands via the provided `SpiClient`
-    pub fn execute<F: FnOnce(SpiClient) + std::panic::UnwindSafe>(f: F) {
+    pub fn execute<F: FnOnce(SpiClient<'_>) + std::panic::UnwindSafe>(f: F) {
         Spi::connect(|client| {
             f(client);
             Ok(Some(()))
         });
     }
 
+    /// execute SPI commands via the provided read-only `SpiClient`
+    pub fn execute_read_only<F: FnOnce(SpiClient<'_, ReadOnly>) + std::panic::UnwindSafe>(f: F) {
+        Spi::connect_read_only(|client| {
+            f(client);
+            Ok(Some(()))
+        });
+    }
+
     /// execute SPI commands via the provided `SpiClient` and return a value from SPI which is
     /// automatically copied into the `CurrentMemoryContext` at the time of this function call
     pub fn connect<
         R: FromDatum + IntoDatum,
-        F: FnOnce(SpiClient) -> std::result::Result<Option<R>, SpiError>,
+        F: FnOnce(SpiClient<'_, ReadWrite>) -> std::result::Result<Option<R>, SpiError>,
+    >(
+        f: F,
+    ) -> Option<R> {
+        Spi::connect_with_mode(f)
+    }
+
+    /// Like [`Spi::connect`], but the provided `SpiClient` runs every query read-only and
+    /// cannot [`update`](SpiClient::update).
+    ///
+    /// `#[pg_extern(spi_read_only)]` functions run every query read-only anyway, so this is for
+    /// code that isn't called from one, such as hooks, or to rule out `update` entirely.
+    pub fn connect_read_only<
+        R: FromDatum + IntoDatum,
+        F: FnOnce(SpiClient<'_, ReadOnly>) -> std::result::Result<Option<R>, SpiError>,
+    >(
+        f: F,
+    ) -> Option<R> {
+        Spi::connect_with_mode(f)
+    }
+
+    fn connect_with_mode<
+        Mode: SpiMode,
+        R: FromDatum + IntoDatum,
+        F: FnOnce(SpiClient<'_, Mode>) -> std::result::Result<Option<R>, SpiError>,
     >(
         f: F,
     ) -> Option<R> {
@@ -211,8 +424,25 @@ impl Spi {
         impl SpiConnection {
             /// Connect to Postgres' SPI system
             fn connect() -> Self {
+                // SPI_connect() itself will happily succeed outside of a transaction, but every
+                // query made afterwards fails with an unhelpful error, so we catch it here.
+                if !unsafe { pg_sys::IsTransactionState() } {
+                    panic!(
+                        "Spi::connect() requires a transaction, but was called outside of one (SPI depth {})",
+                        Spi::depth()
+                    );
+                }
+
                 // connect to SPI
-                Spi::check_status(unsafe { pg_sys::SPI_connect() });
+                let status_code = unsafe { pg_sys::SPI_connect() };
+                if status_code == pg_sys::SPI_ERROR_CONNECT {
+                    panic!(
+                        "Spi::connect() could not connect to SPI at depth {}; SPI cannot be used from this context",
+                        Spi::depth()
+                    );
+                }
+                Spi::check_status(status_code);
+                SPI_DEPTH.fetch_add(1, Ordering::Relaxed);
                 SpiConnection
             }
         }
@@ -220,6 +450,7 @@ impl Spi {
         impl Drop for SpiConnection {
             /// when SpiConnection is dropped, we make sure to disconnect from SPI
             fn drop(&mut self) {
+                SPI_DEPTH.fetch_sub(1, Ordering::Relaxed);
                 // disconnect from SPI
                 Spi::check_status(unsafe { pg_sys::SPI_finish() });
             }
@@ -232,7 +463,10 @@ impl Spi {
         // just put us un.  We'll disconnect from SPI when the closure is finished.
         // If there's a panic or elog(ERROR), we don't care about also disconnecting from
         // SPI b/c Postgres will do that for us automatically
-        match f(SpiClient) {
+        match f(SpiClient {
+            mode: PhantomData,
+            connection: PhantomData,
+        }) {
             // copy the result to the outer memory context we saved above
             Ok(result) => {
                 // we need to copy the resulting Datum into the outer memory context
@@ -283,7 +517,7 @@ impl Spi {
     }
 }
 
-impl SpiClient {
+impl<Mode: SpiMode> SpiClient<'_, Mode> {
     /// perform a SELECT statement
     pub fn select(
         &self,
@@ -297,22 +531,17 @@ impl SpiClient {
         //    using SPI; that could result in very confusing behavior, since the read-only queries
         //    would not see the results of any database updates done by the read-write queries.
         //
-        // As such, we don't actually set read-only to true here
-
-        // TODO:  can we detect if the command counter (or something?) has incremented and if yes
-        //        then we set read_only=false, else we can set it to true?
-        //        Is this even a good idea?
-        SpiClient::execute(query, false, limit, args)
+        // As such, a SELECT is only read-only when the whole client is
+        Self::execute(query, Mode::READ_ONLY, limit, args)
     }
 
-    /// perform any query (including utility statements) that modify the database in some way
-    pub fn update(
-        &mut self,
-        query: &str,
-        limit: Option<i64>,
-        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> SpiTupleTable {
-        SpiClient::execute(query, false, limit, args)
+    /// Panic if there's no SPI connection to run a query on
+    fn check_connected() {
+        if Spi::check_connection().is_err() {
+            panic!(
+                "SpiClient used outside of Spi::connect(); it is only valid inside the closure it was given to"
+            );
+        }
     }
 
     fn execute(
@@ -321,6 +550,9 @@ impl SpiClient {
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
     ) -> SpiTupleTable {
+        Self::check_connected();
+        let read_only = read_only || Spi::in_read_only_function();
+
         unsafe {
             pg_sys::SPI_tuptable = std::ptr::null_mut();
         }
@@ -366,6 +598,66 @@ impl SpiClient {
             None => unsafe { pg_sys::SPI_execute(src.as_ptr(), read_only, limit.unwrap_or(0)) },
         };
 
+        Self::tuple_table(status_code)
+    }
+
+    /// Prepare `query` with `cursor_options`, and run it with `SPI_execute_plan_extended()`
+    #[cfg(feature = "pg14")]
+    fn execute_extended(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+        read_only: bool,
+        limit: Option<i64>,
+        cursor_options: CursorOptions,
+        must_return_tuples: bool,
+    ) -> SpiTupleTable {
+        Self::check_connected();
+        let read_only = read_only || Spi::in_read_only_function();
+
+        unsafe {
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+        }
+
+        let src = std::ffi::CString::new(query).expect("query contained a null byte");
+        let mut argtypes = args
+            .iter()
+            .map(|(argtype, _)| argtype.value())
+            .collect::<Vec<_>>();
+        unsafe {
+            let plan = pg_sys::SPI_prepare_cursor(
+                src.as_ptr(),
+                argtypes.len() as i32,
+                argtypes.as_mut_ptr(),
+                cursor_options.bits(),
+            );
+            if plan.is_null() {
+                Spi::check_status(pg_sys::SPI_result);
+            }
+
+            let params = pg_sys::makeParamList(args.len() as i32);
+            let slots = (*params).params.as_mut_slice(args.len());
+            for (slot, (argtype, datum)) in slots.iter_mut().zip(args) {
+                slot.ptype = argtype.value();
+                slot.pflags = pg_sys::PARAM_FLAG_CONST as u16;
+                slot.value = datum.unwrap_or(0);
+                slot.isnull = datum.is_none();
+            }
+
+            let options = pg_sys::SPIExecuteOptions {
+                params,
+                read_only,
+                must_return_tuples,
+                tcount: limit.unwrap_or(0) as u64,
+                ..Default::default()
+            };
+            let status_code = pg_sys::SPI_execute_plan_extended(plan, &options);
+            pg_sys::SPI_freeplan(plan);
+            Self::tuple_table(status_code)
+        }
+    }
+
+    /// The rows SPI returned from the query it just ran, which ended with `status_code`
+    fn tuple_table(status_code: i32) -> SpiTupleTable {
         SpiTupleTable {
             status_code: Spi::check_status(status_code),
             table: unsafe { pg_sys::SPI_tuptable },
@@ -380,6 +672,130 @@ impl SpiClient {
     }
 }
 
+impl SpiClient<'_, ReadWrite> {
+    /// perform any query (including utility statements) that modify the database in some way
+    pub fn update(
+        &mut self,
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiTupleTable {
+        Self::execute(query, false, limit, args)
+    }
+}
+
+impl<'a> SpiQuery<'a> {
+    /// Bind `value` to the next parameter, with the SQL type of `T`.  A `None` is bound as NULL.
+    pub fn bind<T: IntoDatum>(mut self, value: T) -> Self {
+        let type_oid = T::type_oid();
+        if type_oid == pg_sys::InvalidOid {
+            panic!(
+                "parameter ${} is a `{}`, which has no SQL type",
+                self.args.len() + 1,
+                std::any::type_name::<T>()
+            );
+        }
+        self.args.push((PgOid::from(type_oid), value.into_datum()));
+        self
+    }
+
+    /// Return at most `limit` rows
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Run the query with SPI's `read_only` flag set, as [`Spi::connect_read_only`] does
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Plan the query with `options`, rather than as SPI usually does, such as to always use a
+    /// [generic plan](CursorOptions::GENERIC_PLAN), which isn't planned again for the
+    /// parameters' values:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    /// use pgx::spi::CursorOptions;
+    ///
+    /// let count = Spi::query("SELECT count(*) FROM events WHERE kind = $1")
+    ///     .bind("click")
+    ///     .cursor_options(CursorOptions::GENERIC_PLAN)
+    ///     .get_one::<i64>();
+    /// ```
+    ///
+    /// This needs Postgres 14, for `SPI_execute_plan_extended()`.
+    #[cfg(feature = "pg14")]
+    pub fn cursor_options(mut self, options: CursorOptions) -> Self {
+        self.cursor_options = options;
+        self
+    }
+
+    /// Raise an `ERROR` if the query can't return rows, such as an `INSERT` without a `RETURNING`
+    /// clause, rather than running it.  This needs Postgres 14, for `SPI_execute_plan_extended()`.
+    #[cfg(feature = "pg14")]
+    pub fn must_return_tuples(mut self) -> Self {
+        self.must_return_tuples = true;
+        self
+    }
+
+    /// Run the query, ignoring any rows it returns
+    pub fn run(self) {
+        self.execute(|_| ())
+    }
+
+    /// The first column of the first row, copied out of SPI's memory
+    pub fn get_one<A: FromDatum + IntoDatum>(self) -> Option<A> {
+        let query = self.limit(1);
+        if query.read_only {
+            Spi::connect_read_only(|_| Ok(query.table().first().get_one()))
+        } else {
+            Spi::connect(|_| Ok(query.table().first().get_one()))
+        }
+    }
+
+    /// Run the query and map each of its rows with `f`.
+    ///
+    /// The rows are only valid while the query is, so anything borrowed from them, such as a
+    /// `&str`, needs to be made into an owned value by `f`.
+    pub fn map<R, F: FnMut(&SpiHeapTupleData) -> R>(self, mut f: F) -> Vec<R> {
+        self.execute(|table| table.map(|row| f(&row)).collect())
+    }
+
+    fn execute<R, F: FnOnce(SpiTupleTable) -> R>(self, f: F) -> R {
+        let mut result = None;
+        if self.read_only {
+            Spi::connect_read_only(|_| {
+                result = Some(f(self.table()));
+                Ok(Some(()))
+            });
+        } else {
+            Spi::connect(|_| {
+                result = Some(f(self.table()));
+                Ok(Some(()))
+            });
+        }
+        result.expect("SPI query was not run")
+    }
+
+    /// Run the query on the SPI connection it's inside
+    fn table(self) -> SpiTupleTable {
+        #[cfg(feature = "pg14")]
+        if !self.cursor_options.is_empty() || self.must_return_tuples {
+            return SpiClient::<ReadWrite>::execute_extended(
+                self.query,
+                self.args,
+                self.read_only,
+                self.limit,
+                self.cursor_options,
+                self.must_return_tuples,
+            );
+        }
+        SpiClient::<ReadWrite>::execute(self.query, self.read_only, self.limit, Some(self.args))
+    }
+}
+
 impl SpiTupleTable {
     /// `SpiTupleTable`s are positioned before the start, for iteration purposes.
     ///
@@ -418,6 +834,34 @@ impl SpiTupleTable {
         (a, b, c)
     }
 
+    /// Like [`get_one`](SpiTupleTable::get_one), but borrows the value instead of copying it.
+    pub fn get_one_borrowed<'a, A: SpiBorrowable<'a>>(&'a self) -> Option<A> {
+        self.get_borrowed(1)
+    }
+
+    /// Borrow the value of the column at `ordinal` in the current row, without copying it.
+    ///
+    /// This is useful for inspecting large `text` or `bytea` values, which [`get_datum`] would
+    /// copy out, but the value can't outlive this `SpiTupleTable`:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let length = Spi::connect(|client| {
+    ///     let table = client.select("SELECT repeat('x', 1000000)", None, None).first();
+    ///     let text = table.get_borrowed::<&str>(1);
+    ///     Ok(text.map(|text| text.len() as i64))
+    /// });
+    /// assert_eq!(length, Some(1000000));
+    /// ```
+    ///
+    /// [`get_datum`]: SpiTupleTable::get_datum
+    pub fn get_borrowed<'a, T: SpiBorrowable<'a>>(&'a self, ordinal: i32) -> Option<T> {
+        // a borrowed value's data lives in the tuple, or in a detoasted copy made in SPI's memory
+        // context, both of which are freed no sooner than SPI_finish()
+        self.get_datum(ordinal)
+    }
+
     pub fn get_heap_tuple(&self) -> Option<SpiHeapTupleData> {
         if self.current < 0 {
             panic!("SpiTupleTable positioned before start")
@@ -0,0 +1,156 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::pg_sys::{pgx_SpinLockAcquire, pgx_SpinLockInit, pgx_SpinLockRelease};
+use crate::{ereport, pg_sys, PgLogLevel, PgSqlErrorCode};
+use core::ops::{Deref, DerefMut};
+use once_cell::sync::OnceCell;
+
+/// A Rust locking mechanism which uses a PostgreSQL spinlock (`slock_t`) to lock the data
+///
+/// Spinlocks are meant for critical sections of a few instructions, such as updating a
+/// counter or copying a small struct.  Waiting backends busy-loop, and Postgres considers
+/// a spinlock held for more than about a minute to be stuck, and PANICs.  Don't do
+/// anything that could block, allocate, or raise an error while holding one.  For
+/// anything longer use a [`PgLwLock`](crate::PgLwLock).
+///
+/// The lock lives in shared memory next to the data, so it must be initialized like
+/// the other shared memory types, with `#[pg_shmem]` or `pg_shmem_init!()`.
+///
+/// # Poisoning
+/// If a Rust panic unwinds through a [`SpinLockGuard`] the lock is poisoned, as the
+/// data may have been left half-modified.  Every later attempt to lock it, from any
+/// backend, raises a Postgres ERROR until [`PgSpinLock::clear_poison`] is called.
+pub struct PgSpinLock<T> {
+    inner: OnceCell<*mut PgSpinLockInner<T>>,
+}
+
+unsafe impl<T> Send for PgSpinLock<T> {}
+unsafe impl<T> Sync for PgSpinLock<T> {}
+
+/// What a [`PgSpinLock`] keeps in shared memory
+#[repr(C)]
+pub struct PgSpinLockInner<T> {
+    mutex: pg_sys::slock_t,
+    poisoned: bool,
+    data: T,
+}
+
+impl<T: Default> Default for PgSpinLockInner<T> {
+    fn default() -> Self {
+        let mut inner = PgSpinLockInner {
+            mutex: Default::default(),
+            poisoned: false,
+            data: T::default(),
+        };
+        unsafe {
+            pgx_SpinLockInit(&mut inner.mutex);
+        }
+        inner
+    }
+}
+
+impl<T> PgSpinLock<T> {
+    /// Create an empty lock which can be created as a global, and is attached to
+    /// shared memory later
+    pub const fn new() -> Self {
+        PgSpinLock {
+            inner: OnceCell::new(),
+        }
+    }
+
+    /// Attach an empty PgSpinLock to its shared memory
+    pub fn attach(&self, value: *mut PgSpinLockInner<T>) {
+        self.inner
+            .set(value)
+            .expect("Can't attach, lock is not in an empty state");
+    }
+
+    fn inner(&self) -> *mut PgSpinLockInner<T> {
+        *self
+            .inner
+            .get()
+            .expect("Can't lock, PgSpinLock is in an empty state")
+    }
+
+    /// Obtain the lock (which comes with `&mut T` access), spinning until it is available
+    pub fn lock(&self) -> SpinLockGuard<T> {
+        let inner = self.inner();
+        unsafe {
+            pgx_SpinLockAcquire(&mut (*inner).mutex);
+            if (*inner).poisoned {
+                pgx_SpinLockRelease(&mut (*inner).mutex);
+                ereport(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                    "PgSpinLock is poisoned, a panic occurred while it was held",
+                    file!(),
+                    line!(),
+                    column!(),
+                );
+            }
+
+            SpinLockGuard {
+                inner: inner.as_mut().unwrap(),
+            }
+        }
+    }
+
+    /// Has a panic while holding this lock poisoned it?
+    pub fn is_poisoned(&self) -> bool {
+        let inner = self.inner();
+        unsafe {
+            pgx_SpinLockAcquire(&mut (*inner).mutex);
+            let poisoned = (*inner).poisoned;
+            pgx_SpinLockRelease(&mut (*inner).mutex);
+            poisoned
+        }
+    }
+
+    /// Allow this lock to be used again after it was poisoned.
+    ///
+    /// Only do this once the protected data is known to be consistent again.
+    pub fn clear_poison(&self) {
+        let inner = self.inner();
+        unsafe {
+            pgx_SpinLockAcquire(&mut (*inner).mutex);
+            (*inner).poisoned = false;
+            pgx_SpinLockRelease(&mut (*inner).mutex);
+        }
+    }
+}
+
+/// A held [`PgSpinLock`], which is released when dropped
+pub struct SpinLockGuard<'a, T> {
+    inner: &'a mut PgSpinLockInner<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.data
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.data
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.inner.poisoned = true;
+        }
+        unsafe {
+            pgx_SpinLockRelease(&mut self.inner.mutex);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reading an extension's shared state from SQL, for `#[derive(PostgresStatusView)]`
+use crate::{PgLwLock, PgSpinLock};
+
+/// Somewhere a consistent copy of a [`PostgresStatusView`](crate::PostgresStatusView) struct can
+/// be taken from, such as the lock it's kept in, in shared memory.
+///
+/// The copy is taken while holding the lock for as short a time as possible, and the lock is
+/// released before any of it is returned to Postgres, so a slow client reading the view never
+/// holds up the backends and workers updating it.
+pub trait StatusViewSource<T> {
+    fn snapshot(&self) -> T;
+}
+
+/// Copies the value while holding the lock in share mode
+impl<T: Clone> StatusViewSource<T> for PgLwLock<T> {
+    fn snapshot(&self) -> T {
+        (*self.share()).clone()
+    }
+}
+
+impl<T: Clone> StatusViewSource<T> for PgSpinLock<T> {
+    fn snapshot(&self) -> T {
+        (*self.lock()).clone()
+    }
+}
@@ -0,0 +1,196 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rows of a set-returning function whose columns are decided at runtime
+use crate::{
+    ereport, pg_sys, IntoDatum, PgHeapTupleError, PgLogLevel, PgSqlErrorCode, PgTupleDesc,
+};
+
+/// A row of a function returning `SETOF record`, whose columns are given by the caller's column
+/// definition list rather than a tuple of `name!()`s.
+///
+/// A `#[pg_extern]` returning `impl Iterator<Item = TableRow>` has to be called with a column
+/// definition list, and each row is matched up with it as it's returned: values given by
+/// [`with_value`](TableRow::with_value) fill the columns in order, and values given by
+/// [`with`](TableRow::with) fill the column of that name.  Columns without a value are NULL, and
+/// each value's SQL type must be exactly its column's type.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn key_values(
+///     keys: Vec<String>,
+///     fcinfo: pg_sys::FunctionCallInfo,
+/// ) -> impl Iterator<Item = TableRow> {
+///     // the columns the caller asked for, such as `AS t(key text, length integer)`
+///     let columns = TableRow::expected_columns(fcinfo).expect("no column definition list");
+///     let wants_length = columns.iter().any(|column| column.name() == "length");
+///
+///     keys.into_iter().map(move |key| {
+///         let length = key.len() as i32;
+///         let row = TableRow::new().with("key", key);
+///         if wants_length {
+///             row.with("length", length)
+///         } else {
+///             row
+///         }
+///     })
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TableRow {
+    positional: Vec<TableRowValue>,
+    named: Vec<(String, TableRowValue)>,
+}
+
+#[derive(Debug)]
+struct TableRowValue {
+    datum: Option<pg_sys::Datum>,
+    type_oid: pg_sys::Oid,
+}
+
+impl TableRowValue {
+    fn new<T: IntoDatum>(value: T) -> Self {
+        TableRowValue {
+            datum: value.into_datum(),
+            type_oid: T::type_oid(),
+        }
+    }
+}
+
+impl TableRow {
+    /// A row without any values, which is all NULLs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give the next column, in the order of the column definition list, the value `value`
+    pub fn with_value<T: IntoDatum>(mut self, value: T) -> Self {
+        self.push(value);
+        self
+    }
+
+    /// Give the column named `name` the value `value`
+    pub fn with<T: IntoDatum>(mut self, name: &str, value: T) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Give the next column, in the order of the column definition list, the value `value`
+    pub fn push<T: IntoDatum>(&mut self, value: T) {
+        self.positional.push(TableRowValue::new(value));
+    }
+
+    /// Give the column named `name` the value `value`
+    pub fn set<T: IntoDatum>(&mut self, name: &str, value: T) {
+        self.named
+            .push((name.to_string(), TableRowValue::new(value)));
+    }
+
+    /// How many values have been given to this row?
+    pub fn len(&self) -> usize {
+        self.positional.len() + self.named.len()
+    }
+
+    /// Have no values been given to this row?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The columns the function with `fcinfo` was asked to return, by the caller's column
+    /// definition list, or `None` if it wasn't called with one.
+    pub fn expected_columns(fcinfo: pg_sys::FunctionCallInfo) -> Option<PgTupleDesc<'static>> {
+        let mut tupdesc: pg_sys::TupleDesc = std::ptr::null_mut();
+        unsafe {
+            if pg_sys::get_call_result_type(fcinfo, std::ptr::null_mut(), &mut tupdesc)
+                != pg_sys::TypeFuncClass_TYPEFUNC_COMPOSITE
+                || tupdesc.is_null()
+            {
+                return None;
+            }
+            Some(PgTupleDesc::from_pg_copy(tupdesc))
+        }
+    }
+
+    fn values_and_nulls(
+        self,
+        tupdesc: &PgTupleDesc,
+    ) -> Result<(Vec<pg_sys::Datum>, Vec<bool>), PgHeapTupleError> {
+        let mut values = vec![0 as pg_sys::Datum; tupdesc.len()];
+        let mut nulls = vec![true; tupdesc.len()];
+
+        let mut columns = tupdesc
+            .iter()
+            .enumerate()
+            .filter(|(_, attribute)| !attribute.is_dropped());
+        let mut assigned = Vec::with_capacity(self.len());
+        for (i, value) in self.positional.into_iter().enumerate() {
+            match columns.next() {
+                Some((index, attribute)) => assigned.push((index, attribute, value)),
+                None => return Err(PgHeapTupleError::NoSuchAttributeNumber(i + 1)),
+            }
+        }
+        for (name, value) in self.named {
+            match tupdesc
+                .iter()
+                .enumerate()
+                .find(|(_, attribute)| !attribute.is_dropped() && attribute.name() == name)
+            {
+                Some((index, attribute)) => assigned.push((index, attribute, value)),
+                None => return Err(PgHeapTupleError::NoSuchAttributeName(name)),
+            }
+        }
+
+        for (index, attribute, value) in assigned {
+            if attribute.atttypid != value.type_oid {
+                return Err(PgHeapTupleError::IncompatibleTypes {
+                    attribute: attribute.name().to_string(),
+                    expected: attribute.atttypid,
+                    found: value.type_oid,
+                });
+            }
+            if let Some(datum) = value.datum {
+                values[index] = datum;
+                nulls[index] = false;
+            }
+        }
+        Ok((values, nulls))
+    }
+
+    /// Form this row into a tuple described by `tupdesc`, raising an ERROR if it doesn't fit.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe as we cannot validate that `tupdesc` is valid.
+    #[doc(hidden)]
+    pub unsafe fn into_heap_tuple(self, tupdesc: pg_sys::TupleDesc) -> pg_sys::HeapTuple {
+        // not reference counted, so it's left alone when dropped
+        let descriptor = PgTupleDesc::from_pg(tupdesc);
+        match self.values_and_nulls(&descriptor) {
+            Ok((mut values, mut nulls)) => {
+                pg_sys::heap_form_tuple(tupdesc, values.as_mut_ptr(), nulls.as_mut_ptr())
+            }
+            Err(e) => {
+                ereport(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                    &format!(
+                        "returned row does not match the column definition list: {}",
+                        e
+                    ),
+                    file!(),
+                    line!(),
+                    column!(),
+                );
+                unreachable!()
+            }
+        }
+    }
+}
@@ -0,0 +1,679 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Table access methods, for prototyping storage engines in Rust (Postgres 12 and later)
+//!
+//! Implement [`TableAccessMethod`] and return [`table_am_routine`] from a handler function.
+//! Postgres requires handlers to be declared as taking an `internal` argument, which it never
+//! actually passes, so the handler's SQL is written out:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct Squares {
+//!     next: u16,
+//! }
+//!
+//! impl TableAccessMethod for Squares {
+//!     type Row = (i32,);
+//!
+//!     fn begin_scan(_relation: &PgRelation) -> Self {
+//!         Squares { next: 1 }
+//!     }
+//!
+//!     fn next(&mut self) -> Option<(pg_sys::ItemPointerData, Self::Row)> {
+//!         let n = self.next;
+//!         self.next += 1;
+//!         (n <= 10).then(|| {
+//!             let mut tid = pg_sys::ItemPointerData::default();
+//!             item_pointer_set_all(&mut tid, 0, n);
+//!             (tid, (n as i32 * n as i32,))
+//!         })
+//!     }
+//!
+//!     fn rescan(&mut self) {
+//!         self.next = 1;
+//!     }
+//! }
+//!
+//! #[pg_extern(sql = r#"
+//!     CREATE FUNCTION squares_am_handler(internal) RETURNS table_am_handler
+//!     LANGUAGE c AS '@MODULE_PATHNAME@', '@FUNCTION_NAME@';
+//!     CREATE ACCESS METHOD squares TYPE TABLE HANDLER squares_am_handler;
+//! "#)]
+//! fn squares_am_handler(_fcinfo: pg_sys::FunctionCallInfo) -> PgBox<pg_sys::TableAmRoutine> {
+//!     table_am_routine::<Squares>()
+//! }
+//! ```
+//!
+//! ```sql
+//! CREATE TABLE squares (n int) USING squares;
+//! SELECT * FROM squares; -- 1, 4, 9, ..., 100
+//! ```
+//!
+//! This is a prototyping tool rather than a complete storage engine: pgx creates no files for the
+//! table, there's no MVCC, so every row is visible to everyone, and anything beyond sequential
+//! scans, fetching rows by `ctid`, and `INSERT` (including `COPY`) raises an `ERROR`.  That
+//! includes indexes, `UPDATE`, `DELETE`, and row locks.
+//!
+//! As with foreign data wrappers, each row is built in a memory context which is reset before the
+//! next one, and a scan is dropped along with its query if it's cancelled before
+//! [`TableAccessMethod::end_scan`] is called.
+use crate::{
+    pg_sys, AllocatedByPostgres, IntoForeignRow, PgBox, PgHeapTuple, PgMemoryContexts, PgRelation,
+    PgTupleDesc,
+};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_int};
+
+/// A table access method, whose callbacks Postgres gets from [`table_am_routine`]
+///
+/// An instance is created for each scan of a table using the access method.  The other callbacks
+/// only get the table.  Rust panics in any of them are raised as Postgres `ERROR`s.
+pub trait TableAccessMethod: Sized + 'static {
+    /// What each row of the table is returned as
+    type Row: IntoForeignRow;
+
+    /// Estimate how many rows `relation` has, for the planner
+    fn estimate_rows(_relation: &PgRelation) -> f64 {
+        1000.0
+    }
+
+    /// `relation` has been created, or `TRUNCATE`d, so should be empty from now on.
+    ///
+    /// A `TRUNCATE` which is rolled back must leave the old rows in place.
+    fn create_storage(_relation: &PgRelation) {}
+
+    /// Start a sequential scan of `relation`
+    fn begin_scan(relation: &PgRelation) -> Self;
+
+    /// The next row of the scan and its `ctid`, or `None` when there are no more.
+    ///
+    /// The `ctid` must be valid, with an offset of at least 1, and [`TableAccessMethod::fetch`]
+    /// should find the row by it.
+    fn next(&mut self) -> Option<(pg_sys::ItemPointerData, Self::Row)>;
+
+    /// Start the scan over from the first row
+    fn rescan(&mut self);
+
+    /// Finish the scan
+    fn end_scan(self) {}
+
+    /// The row of `relation` with `ctid`, if there is one
+    fn fetch(_relation: &PgRelation, _ctid: pg_sys::ItemPointerData) -> Option<Self::Row> {
+        None
+    }
+
+    /// Insert `row` into `relation`, returning its new `ctid`
+    fn insert(
+        _relation: &PgRelation,
+        _row: PgHeapTuple<'_, AllocatedByPostgres>,
+    ) -> pg_sys::ItemPointerData {
+        panic!("{} does not support INSERT", std::any::type_name::<Self>())
+    }
+}
+
+/// Postgres keeps the routine a handler returns without copying it, so there's one for each
+/// access method, which lives as long as the backend
+static mut ROUTINES: Option<HashMap<TypeId, usize>> = None;
+
+/// The callbacks for the table access method `T`, which a handler function returns
+pub fn table_am_routine<T: TableAccessMethod>() -> PgBox<pg_sys::TableAmRoutine> {
+    let routine = unsafe {
+        *ROUTINES
+            .get_or_insert_with(HashMap::new)
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                PgMemoryContexts::TopMemoryContext.switch_to(|_| new_routine::<T>().into_pg())
+                    as usize
+            })
+    };
+    unsafe { PgBox::from_pg(routine as *mut pg_sys::TableAmRoutine) }
+}
+
+fn new_routine<T: TableAccessMethod>() -> PgBox<pg_sys::TableAmRoutine> {
+    let mut routine = PgBox::<pg_sys::TableAmRoutine>::alloc_node(pg_sys::NodeTag_T_TableAmRoutine);
+
+    routine.slot_callbacks = Some(slot_callbacks);
+
+    routine.scan_begin = Some(scan_begin::<T>);
+    routine.scan_end = Some(scan_end::<T>);
+    routine.scan_rescan = Some(scan_rescan::<T>);
+    routine.scan_getnextslot = Some(scan_getnextslot::<T>);
+
+    routine.parallelscan_estimate = Some(parallelscan_estimate);
+    routine.parallelscan_initialize = Some(parallelscan_initialize);
+    routine.parallelscan_reinitialize = Some(parallelscan_reinitialize);
+
+    routine.index_fetch_begin = Some(index_fetch_begin::<T>);
+    routine.index_fetch_reset = Some(index_fetch_reset);
+    routine.index_fetch_end = Some(index_fetch_reset);
+    routine.index_fetch_tuple = Some(index_fetch_tuple::<T>);
+
+    routine.tuple_fetch_row_version = Some(tuple_fetch_row_version::<T>);
+    routine.tuple_tid_valid = Some(tuple_tid_valid);
+    routine.tuple_get_latest_tid = Some(tuple_get_latest_tid);
+    routine.tuple_satisfies_snapshot = Some(tuple_satisfies_snapshot);
+    #[cfg(any(feature = "pg12", feature = "pg13"))]
+    {
+        routine.compute_xid_horizon_for_tuples = Some(compute_xid_horizon_for_tuples::<T>);
+    }
+    #[cfg(feature = "pg14")]
+    {
+        routine.index_delete_tuples = Some(index_delete_tuples::<T>);
+    }
+
+    routine.tuple_insert = Some(tuple_insert::<T>);
+    routine.tuple_insert_speculative = Some(tuple_insert_speculative::<T>);
+    routine.tuple_complete_speculative = Some(tuple_complete_speculative::<T>);
+    routine.multi_insert = Some(multi_insert::<T>);
+    routine.tuple_delete = Some(tuple_delete::<T>);
+    routine.tuple_update = Some(tuple_update::<T>);
+    routine.tuple_lock = Some(tuple_lock::<T>);
+
+    routine.relation_set_new_filenode = Some(relation_set_new_filenode::<T>);
+    routine.relation_nontransactional_truncate = Some(relation_nontransactional_truncate::<T>);
+    routine.relation_copy_data = Some(relation_copy_data::<T>);
+    routine.relation_copy_for_cluster = Some(relation_copy_for_cluster::<T>);
+    routine.relation_vacuum = Some(relation_vacuum);
+    routine.scan_analyze_next_block = Some(scan_analyze_next_block);
+    routine.scan_analyze_next_tuple = Some(scan_analyze_next_tuple);
+    routine.index_build_range_scan = Some(index_build_range_scan::<T>);
+    routine.index_validate_scan = Some(index_validate_scan::<T>);
+
+    routine.relation_size = Some(relation_size);
+    routine.relation_needs_toast_table = Some(relation_needs_toast_table);
+    routine.relation_estimate_size = Some(relation_estimate_size::<T>);
+
+    routine.scan_sample_next_block = Some(scan_sample_next_block::<T>);
+    routine.scan_sample_next_tuple = Some(scan_sample_next_tuple::<T>);
+
+    routine.into_pg_boxed()
+}
+
+fn unsupported<T>(what: &str) -> ! {
+    panic!("{} does not support {}", std::any::type_name::<T>(), what)
+}
+
+/// Fill the cleared `slot` with `row`, whose datums are allocated in `rows`
+unsafe fn store_row<R: IntoForeignRow>(
+    relation: &PgRelation,
+    rows: pg_sys::MemoryContext,
+    slot: *mut pg_sys::TupleTableSlot,
+    ctid: pg_sys::ItemPointerData,
+    row: R,
+) {
+    let previous = PgMemoryContexts::For(rows).set_as_current();
+    let tupdesc = PgTupleDesc::from_relation(relation);
+    let values = std::slice::from_raw_parts_mut((*slot).tts_values, tupdesc.len());
+    let nulls = std::slice::from_raw_parts_mut((*slot).tts_isnull, tupdesc.len());
+    nulls.fill(true);
+    row.into_foreign_row(&tupdesc, values, nulls);
+    previous.set_as_current();
+
+    pg_sys::ExecStoreVirtualTuple(slot);
+    (*slot).tts_tid = ctid;
+    (*slot).tts_tableOid = relation.oid();
+}
+
+/// A new memory context, which is deleted along with `parent`
+unsafe fn child_context(parent: pg_sys::MemoryContext, name: &[u8]) -> pg_sys::MemoryContext {
+    pg_sys::AllocSetContextCreateExtended(
+        parent,
+        name.as_ptr() as *const c_char,
+        pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
+        pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
+        pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+    )
+}
+
+unsafe extern "C" fn slot_callbacks(_rel: pg_sys::Relation) -> *const pg_sys::TupleTableSlotOps {
+    &pg_sys::TTSOpsVirtual
+}
+
+/// The `TableScanDesc` of a scan, which Postgres only knows the `base` of
+#[repr(C)]
+struct TableScan<T> {
+    base: pg_sys::TableScanDescData,
+    access_method: Option<T>,
+    /// everything the scan allocates, including itself
+    context: pg_sys::MemoryContext,
+    rows: pg_sys::MemoryContext,
+}
+
+unsafe fn table_scan<'a, T>(scan: pg_sys::TableScanDesc) -> &'a mut TableScan<T> {
+    (scan as *mut TableScan<T>)
+        .as_mut()
+        .expect("table scan was not started")
+}
+
+unsafe extern "C" fn scan_begin<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    snapshot: pg_sys::Snapshot,
+    nkeys: c_int,
+    key: *mut pg_sys::ScanKeyData,
+    pscan: pg_sys::ParallelTableScanDesc,
+    flags: u32,
+) -> pg_sys::TableScanDesc {
+    crate::guard(|| {
+        let access_method = T::begin_scan(&PgRelation::from_pg(rel));
+        // a child of the query's context, so that cancelling the query drops the scan
+        let context = child_context(
+            PgMemoryContexts::CurrentMemoryContext.value(),
+            b"pgx table scan\0",
+        );
+        let scan = TableScan {
+            base: pg_sys::TableScanDescData {
+                rs_rd: rel,
+                rs_snapshot: snapshot,
+                rs_nkeys: nkeys,
+                rs_key: key,
+                rs_flags: flags,
+                rs_parallel: pscan,
+                #[cfg(feature = "pg14")]
+                rs_mintid: Default::default(),
+                #[cfg(feature = "pg14")]
+                rs_maxtid: Default::default(),
+            },
+            access_method: Some(access_method),
+            context,
+            rows: child_context(context, b"pgx table scan rows\0"),
+        };
+        PgMemoryContexts::For(context).leak_and_drop_on_delete(scan) as pg_sys::TableScanDesc
+    })
+}
+
+unsafe extern "C" fn scan_end<T: TableAccessMethod>(sscan: pg_sys::TableScanDesc) {
+    crate::guard(|| {
+        let scan = table_scan::<T>(sscan);
+        if let Some(access_method) = scan.access_method.take() {
+            access_method.end_scan();
+        }
+        if scan.base.rs_flags & pg_sys::ScanOptions_SO_TEMP_SNAPSHOT != 0 {
+            pg_sys::UnregisterSnapshot(scan.base.rs_snapshot);
+        }
+        // drops the scan
+        pg_sys::MemoryContextDelete(scan.context);
+    })
+}
+
+unsafe extern "C" fn scan_rescan<T: TableAccessMethod>(
+    sscan: pg_sys::TableScanDesc,
+    _key: *mut pg_sys::ScanKeyData,
+    _set_params: bool,
+    _allow_strat: bool,
+    _allow_sync: bool,
+    _allow_pagemode: bool,
+) {
+    crate::guard(|| {
+        table_scan::<T>(sscan)
+            .access_method
+            .as_mut()
+            .expect("table scan has ended")
+            .rescan()
+    })
+}
+
+unsafe extern "C" fn scan_getnextslot<T: TableAccessMethod>(
+    sscan: pg_sys::TableScanDesc,
+    direction: pg_sys::ScanDirection,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    crate::guard(|| {
+        if direction == pg_sys::ScanDirection_BackwardScanDirection {
+            unsupported::<T>("backward scans");
+        }
+
+        let scan = table_scan::<T>(sscan);
+        pg_sys::pgx_ExecClearTuple(slot);
+        pg_sys::MemoryContextReset(scan.rows);
+
+        let access_method = scan.access_method.as_mut().expect("table scan has ended");
+        let previous = PgMemoryContexts::For(scan.rows).set_as_current();
+        let next = access_method.next();
+        previous.set_as_current();
+
+        match next {
+            Some((ctid, row)) => {
+                let relation = PgRelation::from_pg(scan.base.rs_rd);
+                store_row(&relation, scan.rows, slot, ctid, row);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+unsafe extern "C" fn parallelscan_estimate(rel: pg_sys::Relation) -> pg_sys::Size {
+    crate::guard(|| pg_sys::table_block_parallelscan_estimate(rel))
+}
+
+unsafe extern "C" fn parallelscan_initialize(
+    rel: pg_sys::Relation,
+    pscan: pg_sys::ParallelTableScanDesc,
+) -> pg_sys::Size {
+    crate::guard(|| pg_sys::table_block_parallelscan_initialize(rel, pscan))
+}
+
+unsafe extern "C" fn parallelscan_reinitialize(
+    rel: pg_sys::Relation,
+    pscan: pg_sys::ParallelTableScanDesc,
+) {
+    crate::guard(|| pg_sys::table_block_parallelscan_reinitialize(rel, pscan))
+}
+
+unsafe extern "C" fn index_fetch_begin<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+) -> *mut pg_sys::IndexFetchTableData {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+unsafe extern "C" fn index_fetch_reset(_data: *mut pg_sys::IndexFetchTableData) {}
+
+unsafe extern "C" fn index_fetch_tuple<T: TableAccessMethod>(
+    _scan: *mut pg_sys::IndexFetchTableData,
+    _tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _call_again: *mut bool,
+    _all_dead: *mut bool,
+) -> bool {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+unsafe extern "C" fn tuple_fetch_row_version<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    crate::guard(|| {
+        let relation = PgRelation::from_pg(rel);
+        match T::fetch(&relation, *tid) {
+            Some(row) => {
+                pg_sys::pgx_ExecClearTuple(slot);
+                // there's no scan to own the row, so it lives as long as Postgres' current context
+                let rows = PgMemoryContexts::CurrentMemoryContext.value();
+                store_row(&relation, rows, slot, *tid, row);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+unsafe extern "C" fn tuple_tid_valid(
+    _scan: pg_sys::TableScanDesc,
+    tid: pg_sys::ItemPointer,
+) -> bool {
+    // the access method decides when it's asked to fetch the row
+    crate::item_pointer_is_valid(tid)
+}
+
+unsafe extern "C" fn tuple_get_latest_tid(_scan: pg_sys::TableScanDesc, _tid: pg_sys::ItemPointer) {
+    // rows are never updated, so every row is the latest version of itself
+}
+
+unsafe extern "C" fn tuple_satisfies_snapshot(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _snapshot: pg_sys::Snapshot,
+) -> bool {
+    // there's no MVCC
+    true
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13"))]
+unsafe extern "C" fn compute_xid_horizon_for_tuples<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _items: *mut pg_sys::ItemPointerData,
+    _nitems: c_int,
+) -> pg_sys::TransactionId {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+#[cfg(feature = "pg14")]
+unsafe extern "C" fn index_delete_tuples<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _delstate: *mut pg_sys::TM_IndexDeleteOp,
+) -> pg_sys::TransactionId {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+unsafe extern "C" fn tuple_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _options: c_int,
+    _bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    crate::guard(|| {
+        let relation = PgRelation::from_pg(rel);
+        let row = PgHeapTuple::from_heap_tuple(
+            PgTupleDesc::from_relation(&relation),
+            pg_sys::pgx_ExecCopySlotHeapTuple(slot),
+        );
+        (*slot).tts_tid = T::insert(&relation, row);
+        (*slot).tts_tableOid = relation.oid();
+    })
+}
+
+unsafe extern "C" fn tuple_insert_speculative<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _options: c_int,
+    _bistate: *mut pg_sys::BulkInsertStateData,
+    _spec_token: u32,
+) {
+    crate::guard(|| unsupported::<T>("INSERT ... ON CONFLICT"))
+}
+
+unsafe extern "C" fn tuple_complete_speculative<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _spec_token: u32,
+    _succeeded: bool,
+) {
+    crate::guard(|| unsupported::<T>("INSERT ... ON CONFLICT"))
+}
+
+unsafe extern "C" fn multi_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slots: *mut *mut pg_sys::TupleTableSlot,
+    nslots: c_int,
+    cid: pg_sys::CommandId,
+    options: c_int,
+    bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    // `COPY` inserts in batches
+    for i in 0..nslots as usize {
+        tuple_insert::<T>(rel, *slots.add(i), cid, options, bistate);
+    }
+}
+
+unsafe extern "C" fn tuple_delete<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _tid: pg_sys::ItemPointer,
+    _cid: pg_sys::CommandId,
+    _snapshot: pg_sys::Snapshot,
+    _crosscheck: pg_sys::Snapshot,
+    _wait: bool,
+    _tmfd: *mut pg_sys::TM_FailureData,
+    _changing_part: bool,
+) -> pg_sys::TM_Result {
+    crate::guard(|| unsupported::<T>("DELETE"))
+}
+
+unsafe extern "C" fn tuple_update<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _otid: pg_sys::ItemPointer,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _snapshot: pg_sys::Snapshot,
+    _crosscheck: pg_sys::Snapshot,
+    _wait: bool,
+    _tmfd: *mut pg_sys::TM_FailureData,
+    _lockmode: *mut pg_sys::LockTupleMode,
+    _update_indexes: *mut bool,
+) -> pg_sys::TM_Result {
+    crate::guard(|| unsupported::<T>("UPDATE"))
+}
+
+unsafe extern "C" fn tuple_lock<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _mode: pg_sys::LockTupleMode,
+    _wait_policy: pg_sys::LockWaitPolicy,
+    _flags: u8,
+    _tmfd: *mut pg_sys::TM_FailureData,
+) -> pg_sys::TM_Result {
+    crate::guard(|| unsupported::<T>("row locks"))
+}
+
+unsafe extern "C" fn relation_set_new_filenode<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _newrnode: *const pg_sys::RelFileNode,
+    _persistence: c_char,
+    freeze_xid: *mut pg_sys::TransactionId,
+    minmulti: *mut pg_sys::MultiXactId,
+) {
+    crate::guard(|| {
+        // nothing needs freezing, as there are no transaction ids in the table
+        *freeze_xid = pg_sys::InvalidTransactionId;
+        *minmulti = pg_sys::InvalidMultiXactId;
+        T::create_storage(&PgRelation::from_pg(rel));
+    })
+}
+
+unsafe extern "C" fn relation_nontransactional_truncate<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+) {
+    crate::guard(|| T::create_storage(&PgRelation::from_pg(rel)))
+}
+
+unsafe extern "C" fn relation_copy_data<T: TableAccessMethod>(
+    _rel: pg_sys::Relation,
+    _newrnode: *const pg_sys::RelFileNode,
+) {
+    crate::guard(|| unsupported::<T>("moving to another tablespace"))
+}
+
+unsafe extern "C" fn relation_copy_for_cluster<T: TableAccessMethod>(
+    _new_table: pg_sys::Relation,
+    _old_table: pg_sys::Relation,
+    _old_index: pg_sys::Relation,
+    _use_sort: bool,
+    _oldest_xmin: pg_sys::TransactionId,
+    _xid_cutoff: *mut pg_sys::TransactionId,
+    _multi_cutoff: *mut pg_sys::MultiXactId,
+    _num_tuples: *mut f64,
+    _tups_vacuumed: *mut f64,
+    _tups_recently_dead: *mut f64,
+) {
+    crate::guard(|| unsupported::<T>("CLUSTER and VACUUM FULL"))
+}
+
+unsafe extern "C" fn relation_vacuum(
+    _rel: pg_sys::Relation,
+    _params: *mut pg_sys::VacuumParams,
+    _bstrategy: pg_sys::BufferAccessStrategy,
+) {
+    // there are no dead rows
+}
+
+unsafe extern "C" fn scan_analyze_next_block(
+    _scan: pg_sys::TableScanDesc,
+    _blockno: pg_sys::BlockNumber,
+    _bstrategy: pg_sys::BufferAccessStrategy,
+) -> bool {
+    // `ANALYZE` samples blocks, and the table doesn't have any
+    false
+}
+
+unsafe extern "C" fn scan_analyze_next_tuple(
+    _scan: pg_sys::TableScanDesc,
+    _oldest_xmin: pg_sys::TransactionId,
+    _liverows: *mut f64,
+    _deadrows: *mut f64,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn index_build_range_scan<T: TableAccessMethod>(
+    _table_rel: pg_sys::Relation,
+    _index_rel: pg_sys::Relation,
+    _index_info: *mut pg_sys::IndexInfo,
+    _allow_sync: bool,
+    _anyvisible: bool,
+    _progress: bool,
+    _start_blockno: pg_sys::BlockNumber,
+    _numblocks: pg_sys::BlockNumber,
+    _callback: pg_sys::IndexBuildCallback,
+    _callback_state: *mut std::os::raw::c_void,
+    _scan: pg_sys::TableScanDesc,
+) -> f64 {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+unsafe extern "C" fn index_validate_scan<T: TableAccessMethod>(
+    _table_rel: pg_sys::Relation,
+    _index_rel: pg_sys::Relation,
+    _index_info: *mut pg_sys::IndexInfo,
+    _snapshot: pg_sys::Snapshot,
+    _state: *mut pg_sys::ValidateIndexState,
+) {
+    crate::guard(|| unsupported::<T>("indexes"))
+}
+
+unsafe extern "C" fn relation_size(
+    _rel: pg_sys::Relation,
+    _fork_number: pg_sys::ForkNumber,
+) -> u64 {
+    // the table has no blocks of its own
+    0
+}
+
+unsafe extern "C" fn relation_needs_toast_table(_rel: pg_sys::Relation) -> bool {
+    false
+}
+
+unsafe extern "C" fn relation_estimate_size<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _attr_widths: *mut i32,
+    pages: *mut pg_sys::BlockNumber,
+    tuples: *mut f64,
+    allvisfrac: *mut f64,
+) {
+    crate::guard(|| {
+        *pages = 1;
+        *tuples = T::estimate_rows(&PgRelation::from_pg(rel));
+        *allvisfrac = 0.0;
+    })
+}
+
+unsafe extern "C" fn scan_sample_next_block<T: TableAccessMethod>(
+    _scan: pg_sys::TableScanDesc,
+    _scanstate: *mut pg_sys::SampleScanState,
+) -> bool {
+    crate::guard(|| unsupported::<T>("TABLESAMPLE"))
+}
+
+unsafe extern "C" fn scan_sample_next_tuple<T: TableAccessMethod>(
+    _scan: pg_sys::TableScanDesc,
+    _scanstate: *mut pg_sys::SampleScanState,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    crate::guard(|| unsupported::<T>("TABLESAMPLE"))
+}
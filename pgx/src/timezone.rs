@@ -0,0 +1,162 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The server's time zone database, as Postgres itself uses it
+use crate::{pg_sys, Timestamp, TimestampWithTimeZone};
+use std::ffi::{CStr, CString};
+use time::UtcOffset;
+
+/// A time zone from the server's time zone database, such as `America/New_York`.
+///
+/// These are the zones `SET TIME ZONE` and `AT TIME ZONE` accept, with the same rules, so an
+/// extension which schedules things in a user's zone agrees with SQL about what time it is there,
+/// without bundling time zone data of its own that may be older or newer than the server's.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let tokyo = PgTimeZone::by_name("Asia/Tokyo").expect("no such time zone");
+/// let now = TimestampWithTimeZone::from(time::OffsetDateTime::now_utc());
+/// let local = tokyo.local_time(now).unwrap();
+/// println!("it's {} in Tokyo ({})", local.time, local.abbreviation);
+/// ```
+///
+/// Postgres keeps every zone it loads for the life of the backend, so a `PgTimeZone` can be copied
+/// and kept around freely.
+#[derive(Debug, Copy, Clone)]
+pub struct PgTimeZone(*mut pg_sys::pg_tz);
+
+/// An instant in a particular time zone.  See [`PgTimeZone::local_time`].
+#[derive(Debug, Clone)]
+pub struct ZonedTime {
+    /// The instant, at the zone's offset from UTC then
+    pub time: time::OffsetDateTime,
+    /// The zone's abbreviation then, such as `EST` or `EDT`
+    pub abbreviation: String,
+    /// Was daylight saving time in effect?
+    pub is_dst: bool,
+}
+
+impl PgTimeZone {
+    /// The time zone named `name`, as `SET TIME ZONE` would find it, if the database has it
+    pub fn by_name(name: &str) -> Option<PgTimeZone> {
+        let name = CString::new(name).ok()?;
+        let tz = unsafe { pg_sys::pg_tzset(name.as_ptr()) };
+        if tz.is_null() {
+            None
+        } else {
+            Some(PgTimeZone(tz))
+        }
+    }
+
+    /// The session's `TimeZone`
+    pub fn session() -> PgTimeZone {
+        PgTimeZone(unsafe { pg_sys::session_timezone })
+    }
+
+    /// The names of every zone in the database, as the `pg_timezone_names` view lists them
+    pub fn names() -> Vec<String> {
+        let mut names = Vec::new();
+        unsafe {
+            let tzenum = pg_sys::pg_tzenumerate_start();
+            loop {
+                // points into `tzenum`, and is overwritten by the next zone
+                let tz = pg_sys::pg_tzenumerate_next(tzenum);
+                if tz.is_null() {
+                    break;
+                }
+                let name = CStr::from_ptr(pg_sys::pg_get_timezone_name(tz))
+                    .to_string_lossy()
+                    .into_owned();
+                // as `pg_timezone_names`, skip zic's "Factory" zone, which isn't a real place
+                if name != "Factory" {
+                    names.push(name);
+                }
+            }
+            pg_sys::pg_tzenumerate_end(tzenum);
+        }
+        names.sort();
+        names
+    }
+
+    /// The name of this zone, as it was looked up
+    pub fn name(&self) -> String {
+        unsafe {
+            CStr::from_ptr(pg_sys::pg_get_timezone_name(self.0))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// Can this zone be the session's `TimeZone`?  Zones which count leap seconds can't be.
+    pub fn is_acceptable(&self) -> bool {
+        unsafe { pg_sys::pg_tz_acceptable(self.0) }
+    }
+
+    /// What `instant` is in this zone, or `None` for `infinity`, `-infinity`, and instants too
+    /// far in the past or future for the time zone database
+    pub fn local_time(&self, instant: TimestampWithTimeZone) -> Option<ZonedTime> {
+        if !instant.is_finite() {
+            return None;
+        }
+
+        let seconds: pg_sys::pg_time_t = instant.unix_timestamp();
+        let tm = unsafe { pg_sys::pg_localtime(&seconds, self.0).as_ref()? };
+        let offset = UtcOffset::from_whole_seconds(tm.tm_gmtoff as i32).ok()?;
+        let abbreviation = if tm.tm_zone.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(tm.tm_zone) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        Some(ZonedTime {
+            time: instant.to_offset(offset),
+            abbreviation,
+            is_dst: tm.tm_isdst > 0,
+        })
+    }
+
+    /// The instant it's `local` time in this zone.
+    ///
+    /// As for `timestamp AT TIME ZONE`, a time skipped by a change to daylight saving time has the
+    /// offset from UTC in effect just before the change, and a time repeated by a change back has
+    /// the offset in effect just after it, making it the later of the two instants.
+    pub fn to_instant(&self, local: Timestamp) -> TimestampWithTimeZone {
+        if local.is_infinity() {
+            return TimestampWithTimeZone::INFINITY;
+        } else if local.is_neg_infinity() {
+            return TimestampWithTimeZone::NEG_INFINITY;
+        }
+
+        let mut tm = pg_sys::pg_tm {
+            tm_sec: local.second() as i32,
+            tm_min: local.minute() as i32,
+            tm_hour: local.hour() as i32,
+            tm_mday: local.day() as i32,
+            tm_mon: local.month() as i32,
+            tm_year: local.year(),
+            tm_wday: 0,
+            tm_yday: 0,
+            tm_isdst: -1,
+            tm_gmtoff: 0,
+            tm_zone: std::ptr::null(),
+        };
+        // in seconds west of UTC, as Postgres counts them
+        let west = unsafe { pg_sys::DetermineTimeZoneOffset(&mut tm, self.0) };
+        let offset = UtcOffset::from_whole_seconds(-west)
+            .expect("the time zone's offset from UTC is out of range");
+        TimestampWithTimeZone::from(local.assume_offset(offset))
+    }
+
+    /// The underlying `pg_sys::pg_tz`
+    pub fn as_ptr(&self) -> *mut pg_sys::pg_tz {
+        self.0
+    }
+}
@@ -9,7 +9,10 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Helper functions for working with custom Rust trigger functions
 
-use crate::{is_a, pg_sys};
+use crate::{
+    is_a, pg_sys, AllocatedByPostgres, PgBox, PgHeapTuple, PgRelation, PgTupleDesc, WhoAllocated,
+};
+use std::ffi::CStr;
 
 #[inline]
 pub unsafe fn called_as_trigger(fcinfo: pg_sys::FunctionCallInfo) -> bool {
@@ -61,3 +64,206 @@ pub fn trigger_fired_after(event: u32) -> bool {
 pub fn trigger_fired_instead(event: u32) -> bool {
     event & pg_sys::TRIGGER_EVENT_TIMINGMASK == pg_sys::TRIGGER_EVENT_INSTEAD
 }
+
+/// The operation that fired a trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgTriggerEvent {
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+}
+
+/// When a trigger fired, relative to its operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgTriggerWhen {
+    Before,
+    After,
+    InsteadOf,
+}
+
+/// Whether a trigger fired once per row, or once per statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgTriggerLevel {
+    Row,
+    Statement,
+}
+
+/// The `pg_sys::TriggerData` a `#[pg_trigger]` function was called with
+pub struct PgTrigger {
+    trigger_data: PgBox<pg_sys::TriggerData>,
+    relation: PgRelation,
+}
+
+impl PgTrigger {
+    /// Build a `PgTrigger` from the `fcinfo` of a function called as a trigger.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe as we cannot validate that `fcinfo` is valid.
+    ///
+    /// ## Panics
+    ///
+    /// If the function was not called as a trigger.
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_trigger(fcinfo) {
+            panic!("function was not called as a trigger");
+        }
+
+        let trigger_data =
+            PgBox::from_pg(fcinfo.as_ref().unwrap().context as *mut pg_sys::TriggerData);
+        let relation = PgRelation::from_pg(trigger_data.tg_relation);
+        PgTrigger {
+            trigger_data,
+            relation,
+        }
+    }
+
+    /// The operation that fired this trigger
+    pub fn event(&self) -> PgTriggerEvent {
+        let event = self.trigger_data.tg_event;
+        if trigger_fired_by_insert(event) {
+            PgTriggerEvent::Insert
+        } else if trigger_fired_by_update(event) {
+            PgTriggerEvent::Update
+        } else if trigger_fired_by_delete(event) {
+            PgTriggerEvent::Delete
+        } else if trigger_fired_by_truncate(event) {
+            PgTriggerEvent::Truncate
+        } else {
+            panic!("unrecognized trigger event: {}", event)
+        }
+    }
+
+    /// When this trigger fired
+    pub fn when(&self) -> PgTriggerWhen {
+        let event = self.trigger_data.tg_event;
+        if trigger_fired_before(event) {
+            PgTriggerWhen::Before
+        } else if trigger_fired_after(event) {
+            PgTriggerWhen::After
+        } else if trigger_fired_instead(event) {
+            PgTriggerWhen::InsteadOf
+        } else {
+            panic!("unrecognized trigger timing: {}", event)
+        }
+    }
+
+    /// Whether this trigger fired for a row, or a statement
+    pub fn level(&self) -> PgTriggerLevel {
+        if trigger_fired_for_row(self.trigger_data.tg_event) {
+            PgTriggerLevel::Row
+        } else {
+            PgTriggerLevel::Statement
+        }
+    }
+
+    /// The name of the trigger, as given to `CREATE TRIGGER`
+    pub fn name(&self) -> &str {
+        unsafe {
+            let trigger = self.trigger_data.tg_trigger.as_ref().unwrap();
+            CStr::from_ptr(trigger.tgname)
+                .to_str()
+                .expect("trigger name is not valid UTF8")
+        }
+    }
+
+    /// The relation the trigger fired on
+    pub fn relation(&self) -> &PgRelation {
+        &self.relation
+    }
+
+    /// The name of the table the trigger fired on
+    pub fn table_name(&self) -> &str {
+        self.relation.name()
+    }
+
+    /// The schema of the table the trigger fired on
+    pub fn table_schema(&self) -> &str {
+        self.relation.namespace()
+    }
+
+    /// The arguments given to the function in `CREATE TRIGGER`
+    pub fn extra_args(&self) -> Vec<&str> {
+        unsafe {
+            let trigger = self.trigger_data.tg_trigger.as_ref().unwrap();
+            if trigger.tgargs.is_null() {
+                return Vec::new();
+            }
+            std::slice::from_raw_parts(trigger.tgargs, trigger.tgnargs as usize)
+                .iter()
+                .map(|arg| {
+                    CStr::from_ptr(*arg)
+                        .to_str()
+                        .expect("trigger argument is not valid UTF8")
+                })
+                .collect()
+        }
+    }
+
+    /// The row being inserted, or the new version of the row being updated.
+    ///
+    /// `None` for `DELETE`, `TRUNCATE`, and statement-level triggers.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(&self) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+        let tuple = match self.event() {
+            PgTriggerEvent::Insert => self.trigger_data.tg_trigtuple,
+            PgTriggerEvent::Update => self.trigger_data.tg_newtuple,
+            PgTriggerEvent::Delete | PgTriggerEvent::Truncate => return None,
+        };
+        self.heap_tuple(tuple)
+    }
+
+    /// The row being deleted, or the old version of the row being updated.
+    ///
+    /// `None` for `INSERT`, `TRUNCATE`, and statement-level triggers.
+    pub fn old(&self) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+        let tuple = match self.event() {
+            PgTriggerEvent::Update | PgTriggerEvent::Delete => self.trigger_data.tg_trigtuple,
+            PgTriggerEvent::Insert | PgTriggerEvent::Truncate => return None,
+        };
+        self.heap_tuple(tuple)
+    }
+
+    /// The underlying `pg_sys::TriggerData`
+    pub fn as_ptr(&self) -> *mut pg_sys::TriggerData {
+        self.trigger_data.as_ptr()
+    }
+
+    fn heap_tuple(&self, tuple: pg_sys::HeapTuple) -> Option<PgHeapTuple<'_, AllocatedByPostgres>> {
+        if tuple.is_null() || self.level() == PgTriggerLevel::Statement {
+            return None;
+        }
+        let tupdesc = PgTupleDesc::from_relation(&self.relation);
+        Some(unsafe { PgHeapTuple::from_heap_tuple(tupdesc, tuple) })
+    }
+}
+
+/// What a `#[pg_trigger]` function can return
+///
+/// `None` tells Postgres to skip the operation for the current row.  A tuple tells it to use that
+/// tuple for the row.  Statement-level and `AFTER` triggers may return either, as Postgres ignores
+/// the result.
+pub trait IntoTriggerDatum {
+    fn into_trigger_datum(self) -> pg_sys::Datum;
+}
+
+impl<'a, AllocatedBy: WhoAllocated<pg_sys::HeapTupleData>> IntoTriggerDatum
+    for Option<PgHeapTuple<'a, AllocatedBy>>
+{
+    fn into_trigger_datum(self) -> pg_sys::Datum {
+        match self {
+            Some(tuple) => tuple.into_trigger_datum(),
+            None => 0,
+        }
+    }
+}
+
+impl<T: IntoTriggerDatum, E: std::fmt::Display> IntoTriggerDatum for Result<T, E> {
+    fn into_trigger_datum(self) -> pg_sys::Datum {
+        match self {
+            Ok(result) => result.into_trigger_datum(),
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
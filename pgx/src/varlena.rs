@@ -161,6 +161,26 @@ pub unsafe fn varatt_is_1b_e(ptr: *const pg_sys::varlena) -> bool {
     (*va1b).va_header == 0x01
 }
 
+/// ```c
+/// #define VARATT_IS_EXTERNAL_EXPANDED(PTR) \
+///     (VARATT_IS_EXTERNAL(PTR) && VARTAG_IS_EXPANDED(VARTAG_EXTERNAL(PTR)))
+/// ```
+#[inline]
+pub unsafe fn varatt_is_external_expanded(ptr: *const pg_sys::varlena) -> bool {
+    varatt_is_1b_e(ptr) && vartag_is_expanded(vartag_external(ptr) as pg_sys::vartag_external)
+}
+
+/// ```c
+/// #define VARATT_IS_EXTERNAL_EXPANDED_RW(PTR) \
+///     (VARATT_IS_EXTERNAL(PTR) && VARTAG_EXTERNAL(PTR) == VARTAG_EXPANDED_RW)
+/// ```
+#[inline]
+pub unsafe fn varatt_is_external_expanded_rw(ptr: *const pg_sys::varlena) -> bool {
+    varatt_is_1b_e(ptr)
+        && vartag_external(ptr) as pg_sys::vartag_external
+            == pg_sys::vartag_external_VARTAG_EXPANDED_RW
+}
+
 /// ```c
 /// #define VARATT_NOT_PAD_BYTE(PTR) \
 /// (*((uint8 *) (PTR)) != 0)
@@ -316,6 +336,86 @@ pub unsafe fn varlena_to_byte_slice<'a>(varlena: *const pg_sys::varlena) -> &'a
     std::slice::from_raw_parts(data as *const u8, len)
 }
 
+/// ```c
+/// #define VARATT_IS_EXTERNAL(PTR) VARATT_IS_1B_E(PTR)
+/// ```
+///
+/// The varlena points to its data elsewhere, such as in the TOAST table.
+#[inline]
+pub unsafe fn varatt_is_external(ptr: *const pg_sys::varlena) -> bool {
+    varatt_is_1b_e(ptr)
+}
+
+/// ```c
+/// #define VARATT_IS_EXTENDED(PTR) (!VARATT_IS_4B_U(PTR))
+/// ```
+///
+/// The varlena is anything but plain data behind a 4-byte header, so it's compressed, stored
+/// elsewhere, or has a short header.
+#[inline]
+pub unsafe fn varatt_is_extended(ptr: *const pg_sys::varlena) -> bool {
+    !varatt_is_4b_u(ptr)
+}
+
+/// Fetch a varlena's data from the TOAST table and decompress it, but leave it with a short header
+/// if it has one, as Postgres' `pg_detoast_datum_packed()` does.  That's the cheapest way to read
+/// all of a value, with [`vardata_any`] and [`varsize_any_exhdr`].
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly dereferences the varlena pointer argument.
+///
+/// The result is `varlena` itself if it didn't need detoasting, and a copy allocated in
+/// `CurrentMemoryContext` if it did.
+#[inline]
+pub unsafe fn detoast_packed(varlena: *mut pg_sys::varlena) -> *mut pg_sys::varlena {
+    pg_sys::pg_detoast_datum_packed(varlena)
+}
+
+/// Read `length` bytes of a varlena's data from `offset`, or as many as it has, as Postgres'
+/// `pg_detoast_datum_slice()` does.
+///
+/// For a value stored in the TOAST table uncompressed, as the values of a type with
+/// `#[pgx(storage = "external")]` are once they're large, only the chunks holding those bytes are
+/// read, so a partial read of a large value doesn't cost as much as the whole.  A compressed value
+/// is decompressed first.
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly dereferences the varlena pointer argument.
+///
+/// The result is a copy with a 4-byte header, allocated in `CurrentMemoryContext`.
+#[inline]
+pub unsafe fn detoast_slice(
+    varlena: *mut pg_sys::varlena,
+    offset: usize,
+    length: usize,
+) -> *mut pg_sys::varlena {
+    pg_sys::pg_detoast_datum_slice(
+        varlena,
+        offset.min(i32::MAX as usize) as i32,
+        length.min(i32::MAX as usize) as i32,
+    )
+}
+
+/// Read `length` bytes of a varlena's data from `offset`, or as many as it has, into a Rust
+/// `&[u8]`, with [`detoast_slice`].
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly dereferences the varlena pointer argument.
+///
+/// The slice is backed by a copy allocated in `CurrentMemoryContext`, and becomes invalid when
+/// that's reset.
+#[inline]
+pub unsafe fn varlena_slice_to_byte_slice<'a>(
+    varlena: *mut pg_sys::varlena,
+    offset: usize,
+    length: usize,
+) -> &'a [u8] {
+    varlena_to_byte_slice(detoast_slice(varlena, offset, length))
+}
+
 /// Convert a Rust `&str` into a Postgres `text *`.
 ///
 /// This allocates the returned Postgres `text *` in `CurrentMemoryContext`.
@@ -0,0 +1,144 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reporting what a backend is waiting on, as `pg_stat_activity` shows it
+use crate::pg_sys;
+
+/// Something a backend can wait on, as its `wait_event_type` and `wait_event` in
+/// `pg_stat_activity`.
+///
+/// A backend which is busy waiting on something outside of Postgres, such as a network call
+/// from a background worker, otherwise looks like it's using the CPU.  Report the wait around it
+/// with [`WaitEvent::start`]:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// const FETCH: WaitEvent = WaitEvent::extension(1);
+///
+/// fn fetch(url: &str) -> String {
+///     let _waiting = FETCH.start();
+///     // make the request, while `pg_stat_activity` shows the backend waiting on `Extension`
+///     format!("the body of {}", url)
+/// }
+/// ```
+///
+/// The Postgres versions pgx supports name every wait event of the `Extension` class `Extension`,
+/// whatever its number.  An extension can still give each of its waits a different number, for
+/// tools that read the raw `wait_event_info` from shared memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WaitEvent(u32);
+
+impl WaitEvent {
+    /// The `Extension` wait event that Postgres itself reports for extensions, such as while a
+    /// background worker is waiting on its latch
+    pub const EXTENSION: WaitEvent = WaitEvent(pg_sys::PG_WAIT_EXTENSION);
+
+    /// The wait event of the `Extension` class numbered `id`
+    pub const fn extension(id: u16) -> Self {
+        WaitEvent(pg_sys::PG_WAIT_EXTENSION | id as u32)
+    }
+
+    /// A wait event from its raw `wait_event_info`, such as one of Postgres' own
+    pub const fn from_info(wait_event_info: u32) -> Self {
+        WaitEvent(wait_event_info)
+    }
+
+    /// The raw `wait_event_info` Postgres stores for this wait event
+    pub const fn info(self) -> u32 {
+        self.0
+    }
+
+    /// The wait event this backend is currently reporting, if any
+    pub fn current() -> Option<WaitEvent> {
+        match unsafe { current_wait_event_info() } {
+            0 => None,
+            wait_event_info => Some(WaitEvent(wait_event_info)),
+        }
+    }
+
+    /// The name of this wait event, as `pg_stat_activity` shows it in `wait_event`
+    pub fn name(self) -> String {
+        unsafe {
+            let name = pg_sys::pgstat_get_wait_event(self.0);
+            if name.is_null() {
+                return String::new();
+            }
+            std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// Report that this backend is waiting on this wait event until the returned guard is
+    /// dropped, which happens on an `ERROR` too.
+    ///
+    /// Postgres reports its own waits the same way, so a guard shouldn't be held across calls back
+    /// into Postgres that may wait themselves, such as on a lock.  Those end the wait event early.
+    pub fn start(self) -> WaitEventGuard {
+        pgstat_report_wait_start(self.0);
+        WaitEventGuard(())
+    }
+}
+
+/// Reports that the backend is no longer waiting when dropped.  See [`WaitEvent::start`].
+#[must_use = "the wait ends as soon as the guard is dropped"]
+pub struct WaitEventGuard(());
+
+impl Drop for WaitEventGuard {
+    fn drop(&mut self) {
+        pgstat_report_wait_end();
+    }
+}
+
+/// Report that this backend is waiting on `wait_event_info`, until
+/// [`pgstat_report_wait_end`] is called.
+///
+/// This is Postgres' inline function of the same name, which bindgen can't bind.  Prefer
+/// [`WaitEvent::start`], which can't forget to end the wait.
+#[inline]
+pub fn pgstat_report_wait_start(wait_event_info: u32) {
+    unsafe { set_wait_event_info(wait_event_info) }
+}
+
+/// Report that this backend is no longer waiting.
+///
+/// This is Postgres' inline function of the same name, which bindgen can't bind.
+#[inline]
+pub fn pgstat_report_wait_end() {
+    unsafe { set_wait_event_info(0) }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe fn set_wait_event_info(wait_event_info: u32) {
+    if !pg_sys::pgstat_track_activities || pg_sys::MyProc.is_null() {
+        return;
+    }
+    // a four-byte field that's always read and written whole, so updates are atomic
+    std::ptr::write_volatile(&mut (*pg_sys::MyProc).wait_event_info, wait_event_info);
+}
+
+#[cfg(feature = "pg14")]
+unsafe fn set_wait_event_info(wait_event_info: u32) {
+    // points at a local variable until the backend has a PGPROC
+    std::ptr::write_volatile(pg_sys::my_wait_event_info, wait_event_info);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe fn current_wait_event_info() -> u32 {
+    if pg_sys::MyProc.is_null() {
+        return 0;
+    }
+    std::ptr::read_volatile(&(*pg_sys::MyProc).wait_event_info)
+}
+
+#[cfg(feature = "pg14")]
+unsafe fn current_wait_event_info() -> u32 {
+    std::ptr::read_volatile(pg_sys::my_wait_event_info)
+}
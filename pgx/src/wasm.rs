@@ -0,0 +1,398 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Running untrusted, user-supplied WebAssembly functions, with limits on how long they run and
+//! how much memory they use
+//!
+//! Unlike [`plugins`](crate::plugins), which run with all the privileges of the backend, a WASM
+//! function can only compute its result from its arguments.  It can't call into Postgres, the
+//! extension, or the operating system, as modules aren't given any imports.
+//!
+//! ```rust,no_run
+//! use pgx::wasm::{WasmLimits, WasmSandbox, WasmValue};
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn run_wasm(module: &[u8], name: &str, x: i32, y: i32) -> Option<i32> {
+//!     let sandbox = WasmSandbox::new(module, WasmLimits::default()).unwrap();
+//!     match sandbox.call(name, &[WasmValue::Int4(x), WasmValue::Int4(y)], WasmType::Int4) {
+//!         Ok(WasmValue::Int4(result)) => Some(result),
+//!         Ok(_) => unreachable!(),
+//!         Err(e) => error!("{}", e),
+//!     }
+//! }
+//! # use pgx::wasm::WasmType;
+//! ```
+//!
+//! Values are passed to and from WASM functions as WASM values:
+//!
+//! * `bool` as an `i32` which is 0 or 1, `smallint` and `integer` as `i32`, and `bigint` as `i64`
+//! * `real` and `double precision` as `f32` and `f64`
+//! * `text`, `varchar`, and `bytea` as two `i32`s, a pointer to the value in the module's memory
+//!   and its length in bytes.  The module must export its memory as `memory`, and an
+//!   `alloc(len: i32) -> i32` function the value is copied into space from.  To return one of
+//!   these, the function returns an `i64` whose upper 32 bits are the pointer and lower are the
+//!   length.
+//!
+//! Each call gets a new instance of the module, so calls can't affect each other.  Execution is
+//! limited by [fuel](https://docs.wasmtime.dev/api/wasmtime/struct.Config.html#method.consume_fuel),
+//! so a function that runs too long fails rather than blocking the backend, as it can't be
+//! interrupted by a query cancel or `statement_timeout`.
+use crate::{pg_sys, FromDatum, IntoDatum};
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Val,
+};
+
+/// How much a call of a WASM function can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// Units of fuel a call can consume, where most instructions consume one
+    pub fuel: u64,
+    /// Bytes of linear memory the module's instance can have
+    pub memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        WasmLimits {
+            fuel: 10_000_000,
+            memory_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// The Postgres types whose values can be passed to and returned from WASM functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmType {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Text,
+    Bytea,
+}
+
+impl WasmType {
+    /// The type for values of the type with `typoid`, if it can be passed to WASM
+    pub fn from_oid(typoid: pg_sys::Oid) -> Option<WasmType> {
+        match typoid {
+            pg_sys::BOOLOID => Some(WasmType::Bool),
+            pg_sys::INT2OID => Some(WasmType::Int2),
+            pg_sys::INT4OID => Some(WasmType::Int4),
+            pg_sys::INT8OID => Some(WasmType::Int8),
+            pg_sys::FLOAT4OID => Some(WasmType::Float4),
+            pg_sys::FLOAT8OID => Some(WasmType::Float8),
+            pg_sys::TEXTOID | pg_sys::VARCHAROID => Some(WasmType::Text),
+            pg_sys::BYTEAOID => Some(WasmType::Bytea),
+            _ => None,
+        }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        match self {
+            WasmType::Bool => pg_sys::BOOLOID,
+            WasmType::Int2 => pg_sys::INT2OID,
+            WasmType::Int4 => pg_sys::INT4OID,
+            WasmType::Int8 => pg_sys::INT8OID,
+            WasmType::Float4 => pg_sys::FLOAT4OID,
+            WasmType::Float8 => pg_sys::FLOAT8OID,
+            WasmType::Text => pg_sys::TEXTOID,
+            WasmType::Bytea => pg_sys::BYTEAOID,
+        }
+    }
+}
+
+/// A value passed to or returned from a WASM function
+#[derive(Debug, Clone, PartialEq)]
+pub enum WasmValue {
+    Bool(bool),
+    Int2(i16),
+    Int4(i32),
+    Int8(i64),
+    Float4(f32),
+    Float8(f64),
+    Text(String),
+    Bytea(Vec<u8>),
+}
+
+impl WasmValue {
+    /// The Postgres type of the value
+    pub fn wasm_type(&self) -> WasmType {
+        match self {
+            WasmValue::Bool(_) => WasmType::Bool,
+            WasmValue::Int2(_) => WasmType::Int2,
+            WasmValue::Int4(_) => WasmType::Int4,
+            WasmValue::Int8(_) => WasmType::Int8,
+            WasmValue::Float4(_) => WasmType::Float4,
+            WasmValue::Float8(_) => WasmType::Float8,
+            WasmValue::Text(_) => WasmType::Text,
+            WasmValue::Bytea(_) => WasmType::Bytea,
+        }
+    }
+
+    /// Convert the value to a Datum of its Postgres type
+    pub fn into_datum(self) -> Option<pg_sys::Datum> {
+        match self {
+            WasmValue::Bool(value) => value.into_datum(),
+            WasmValue::Int2(value) => value.into_datum(),
+            WasmValue::Int4(value) => value.into_datum(),
+            WasmValue::Int8(value) => value.into_datum(),
+            WasmValue::Float4(value) => value.into_datum(),
+            WasmValue::Float8(value) => value.into_datum(),
+            WasmValue::Text(value) => value.into_datum(),
+            WasmValue::Bytea(value) => value.into_datum(),
+        }
+    }
+}
+
+impl FromDatum for WasmValue {
+    /// ## Panics
+    ///
+    /// If `typoid` is not a type [`WasmType`] supports.
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<WasmValue> {
+        let wasm_type = WasmType::from_oid(typoid)
+            .unwrap_or_else(|| panic!("type oid {} can't be passed to WASM", typoid));
+        match wasm_type {
+            WasmType::Bool => bool::from_datum(datum, is_null, typoid).map(WasmValue::Bool),
+            WasmType::Int2 => i16::from_datum(datum, is_null, typoid).map(WasmValue::Int2),
+            WasmType::Int4 => i32::from_datum(datum, is_null, typoid).map(WasmValue::Int4),
+            WasmType::Int8 => i64::from_datum(datum, is_null, typoid).map(WasmValue::Int8),
+            WasmType::Float4 => f32::from_datum(datum, is_null, typoid).map(WasmValue::Float4),
+            WasmType::Float8 => f64::from_datum(datum, is_null, typoid).map(WasmValue::Float8),
+            WasmType::Text => String::from_datum(datum, is_null, typoid).map(WasmValue::Text),
+            WasmType::Bytea => Vec::<u8>::from_datum(datum, is_null, typoid).map(WasmValue::Bytea),
+        }
+    }
+}
+
+/// Why a WASM function could not be compiled or called
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WasmError {
+    /// The module is not valid WASM, or uses features which aren't supported
+    Compile(String),
+    /// The module doesn't export something it needs to
+    MissingExport(String),
+    /// The function was called with or returned values of the wrong types
+    Type(String),
+    /// The function ran out of fuel
+    OutOfFuel { fuel: u64 },
+    /// The function trapped, such as by accessing memory out of bounds or exceeding the memory
+    /// limit
+    Trap(String),
+}
+
+impl std::fmt::Display for WasmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WasmError::Compile(message) => write!(f, "invalid WASM module: {}", message),
+            WasmError::MissingExport(name) => {
+                write!(f, "WASM module does not export `{}`", name)
+            }
+            WasmError::Type(message) => write!(f, "WASM type mismatch: {}", message),
+            WasmError::OutOfFuel { fuel } => {
+                write!(f, "WASM function exceeded its limit of {} fuel", fuel)
+            }
+            WasmError::Trap(message) => write!(f, "WASM function failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for WasmError {}
+
+/// A compiled WASM module, whose functions can be called with [`WasmLimits`]
+///
+/// Compiling is much slower than calling, so an extension which calls the same module often
+/// should keep the sandbox, such as in its [backend state](crate::backend_state).
+pub struct WasmSandbox {
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+}
+
+impl WasmSandbox {
+    /// Compile `wasm`, which is a binary module or its text format
+    pub fn new(wasm: &[u8], limits: WasmLimits) -> Result<WasmSandbox, WasmError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        // backends must stay single threaded
+        config.parallel_compilation(false);
+        let engine = Engine::new(&config).map_err(|e| WasmError::Compile(format!("{:#}", e)))?;
+        let module =
+            Module::new(&engine, wasm).map_err(|e| WasmError::Compile(format!("{:#}", e)))?;
+        Ok(WasmSandbox {
+            engine,
+            module,
+            limits,
+        })
+    }
+
+    /// Call the function the module exports as `name` with `args`, returning a value of
+    /// `result_type`
+    pub fn call(
+        &self,
+        name: &str,
+        args: &[WasmValue],
+        result_type: WasmType,
+    ) -> Result<WasmValue, WasmError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .add_fuel(self.limits.fuel)
+            .map_err(|e| WasmError::Trap(format!("{:#}", e)))?;
+
+        let instance = Instance::new(&mut store, &self.module, &[])
+            .map_err(|e| self.trap(&store, format!("{:#}", e)))?;
+        let function = instance
+            .get_func(&mut store, name)
+            .ok_or_else(|| WasmError::MissingExport(name.to_string()))?;
+
+        let mut params = Vec::with_capacity(args.len());
+        for arg in args {
+            match arg {
+                WasmValue::Bool(value) => params.push(Val::I32(*value as i32)),
+                WasmValue::Int2(value) => params.push(Val::I32(*value as i32)),
+                WasmValue::Int4(value) => params.push(Val::I32(*value)),
+                WasmValue::Int8(value) => params.push(Val::I64(*value)),
+                WasmValue::Float4(value) => params.push(Val::F32(value.to_bits())),
+                WasmValue::Float8(value) => params.push(Val::F64(value.to_bits())),
+                WasmValue::Text(value) => {
+                    let ptr = self.copy_in(&mut store, &instance, value.as_bytes())?;
+                    params.push(Val::I32(ptr));
+                    params.push(Val::I32(value.len() as i32));
+                }
+                WasmValue::Bytea(value) => {
+                    let ptr = self.copy_in(&mut store, &instance, value)?;
+                    params.push(Val::I32(ptr));
+                    params.push(Val::I32(value.len() as i32));
+                }
+            }
+        }
+
+        let mut results = vec![Val::I32(0); function.ty(&store).results().len()];
+        if results.len() != 1 {
+            return Err(WasmError::Type(format!(
+                "`{}` returns {} values, rather than one",
+                name,
+                results.len()
+            )));
+        }
+        function
+            .call(&mut store, &params, &mut results)
+            .map_err(|e| self.trap(&store, format!("{:#}", e)))?;
+
+        let result = &results[0];
+        let mismatch = || {
+            WasmError::Type(format!(
+                "`{}` returned {:?}, which is not a {:?}",
+                name,
+                result.ty(),
+                result_type
+            ))
+        };
+        Ok(match result_type {
+            WasmType::Bool => WasmValue::Bool(result.i32().ok_or_else(mismatch)? != 0),
+            WasmType::Int2 => WasmValue::Int2(result.i32().ok_or_else(mismatch)? as i16),
+            WasmType::Int4 => WasmValue::Int4(result.i32().ok_or_else(mismatch)?),
+            WasmType::Int8 => WasmValue::Int8(result.i64().ok_or_else(mismatch)?),
+            WasmType::Float4 => WasmValue::Float4(result.f32().ok_or_else(mismatch)?),
+            WasmType::Float8 => WasmValue::Float8(result.f64().ok_or_else(mismatch)?),
+            WasmType::Text => {
+                let bytes =
+                    self.copy_out(&mut store, &instance, result.i64().ok_or_else(mismatch)?)?;
+                WasmValue::Text(String::from_utf8(bytes).map_err(|_| {
+                    WasmError::Type(format!("`{}` returned text which is not UTF-8", name))
+                })?)
+            }
+            WasmType::Bytea => WasmValue::Bytea(self.copy_out(
+                &mut store,
+                &instance,
+                result.i64().ok_or_else(mismatch)?,
+            )?),
+        })
+    }
+
+    /// The error for a failed call, which is because the fuel ran out if it all was consumed
+    fn trap(&self, store: &Store<StoreLimits>, message: String) -> WasmError {
+        match store.fuel_consumed() {
+            Some(consumed) if consumed >= self.limits.fuel => WasmError::OutOfFuel {
+                fuel: self.limits.fuel,
+            },
+            _ => WasmError::Trap(message),
+        }
+    }
+
+    fn memory(
+        &self,
+        store: &mut Store<StoreLimits>,
+        instance: &Instance,
+    ) -> Result<Memory, WasmError> {
+        instance
+            .get_memory(store, "memory")
+            .ok_or_else(|| WasmError::MissingExport("memory".to_string()))
+    }
+
+    /// Copy `bytes` into space the module allocates for them, returning where they are
+    fn copy_in(
+        &self,
+        store: &mut Store<StoreLimits>,
+        instance: &Instance,
+        bytes: &[u8],
+    ) -> Result<i32, WasmError> {
+        let alloc = instance
+            .get_typed_func::<i32, i32, _>(&mut *store, "alloc")
+            .map_err(|_| WasmError::MissingExport("alloc".to_string()))?;
+        let ptr = alloc
+            .call(&mut *store, bytes.len() as i32)
+            .map_err(|e| self.trap(store, format!("{:#}", e)))?;
+        self.memory(store, instance)?
+            .write(&mut *store, ptr as u32 as usize, bytes)
+            .map_err(|e| WasmError::Trap(format!("`alloc` returned an invalid pointer: {}", e)))?;
+        Ok(ptr)
+    }
+
+    /// Copy out the bytes a function returned the location of, as its pointer and length packed
+    /// into an `i64`
+    fn copy_out(
+        &self,
+        store: &mut Store<StoreLimits>,
+        instance: &Instance,
+        packed: i64,
+    ) -> Result<Vec<u8>, WasmError> {
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xffff_ffff) as usize;
+        let memory = self.memory(store, instance)?;
+
+        // checked before allocating, as the module could claim to have returned up to 4GiB
+        match ptr.checked_add(len) {
+            Some(end) if end <= memory.data_size(&*store) => (),
+            _ => {
+                return Err(WasmError::Trap(format!(
+                    "returned {} bytes at {}, outside of its memory",
+                    len, ptr
+                )))
+            }
+        }
+
+        let mut bytes = vec![0; len];
+        memory
+            .read(&*store, ptr, &mut bytes)
+            .map_err(|e| WasmError::Trap(format!("returned an invalid pointer: {}", e)))?;
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,211 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe access to the Postgres `WindowObject` API for `#[pg_extern(window)]` functions
+//!
+//! Postgres calls a window function once per row of a partition, and passes all of its
+//! arguments as NULL.  The argument values, along with information about the current
+//! partition and frame, are instead read through a [`WindowFunctionContext`].
+//!
+//! Add a `WindowFunctionContext` argument to the function and `#[pg_extern]` will construct it
+//! from the `fcinfo`.  It is not part of the function's SQL signature.  The SQL arguments must
+//! all be declared as `Option<T>`:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// Returns the value of `value` from the first row of the partition
+//! #[pg_extern(window)]
+//! fn first_in_partition(_value: Option<i32>, window: WindowFunctionContext) -> Option<i32> {
+//!     window
+//!         .get_func_arg_in_partition(0, 0, WindowSeek::Head, false)
+//!         .flatten()
+//! }
+//! ```
+//!
+//! ```sql
+//! SELECT x, first_in_partition(x) OVER (ORDER BY x) FROM generate_series(1, 3) x;
+//! ```
+
+use crate::{is_a, pg_sys, FromDatum};
+
+/// Where a relative row position passed to [`WindowFunctionContext`] is measured from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSeek {
+    /// Relative to the current row
+    Current,
+    /// Relative to the first row of the partition (or frame)
+    Head,
+    /// Relative to the last row of the partition (or frame)
+    Tail,
+}
+
+impl WindowSeek {
+    fn as_seektype(self) -> i32 {
+        (match self {
+            WindowSeek::Current => pg_sys::WINDOW_SEEK_CURRENT,
+            WindowSeek::Head => pg_sys::WINDOW_SEEK_HEAD,
+            WindowSeek::Tail => pg_sys::WINDOW_SEEK_TAIL,
+        }) as i32
+    }
+}
+
+/// The window state of a `#[pg_extern(window)]` function, wrapping a `pg_sys::WindowObject`
+///
+/// It is only valid for the duration of the function call it was given to.
+pub struct WindowFunctionContext {
+    fcinfo: pg_sys::FunctionCallInfo,
+    winobj: pg_sys::WindowObject,
+}
+
+impl WindowFunctionContext {
+    /// Build the context from the `fcinfo` of a function called as a window function.
+    ///
+    /// ## Panics
+    ///
+    /// If the function was not called as a window function.
+    pub fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if unsafe { !called_as_window_function(fcinfo) } {
+            panic!("function was not called as a window function; was it created with #[pg_extern(window)]?");
+        }
+
+        let winobj = unsafe { fcinfo.as_ref().unwrap().context } as pg_sys::WindowObject;
+        WindowFunctionContext { fcinfo, winobj }
+    }
+
+    /// The number of rows in the current partition
+    pub fn partition_row_count(&self) -> i64 {
+        unsafe { pg_sys::WinGetPartitionRowCount(self.winobj) }
+    }
+
+    /// The position of the current row within its partition, starting at zero
+    pub fn current_position(&self) -> i64 {
+        unsafe { pg_sys::WinGetCurrentPosition(self.winobj) }
+    }
+
+    /// Tell Postgres that rows before `markpos` will no longer be fetched, so it can discard them
+    pub fn set_mark_position(&self, markpos: i64) {
+        unsafe { pg_sys::WinSetMarkPosition(self.winobj, markpos) }
+    }
+
+    /// Are the rows at partition positions `pos1` and `pos2` peers under the window's `ORDER BY`?
+    pub fn rows_are_peers(&self, pos1: i64, pos2: i64) -> bool {
+        unsafe { pg_sys::WinRowsArePeers(self.winobj, pos1, pos2) }
+    }
+
+    /// Evaluate argument `argno` (starting at zero) on the row `relpos` rows away from `seek`
+    /// within the current partition.
+    ///
+    /// Returns `None` if that row is outside the partition, and `Some(None)` if the argument
+    /// was NULL on that row.
+    pub fn get_func_arg_in_partition<T: FromDatum>(
+        &self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let mut isnull = false;
+        let mut isout = false;
+        let datum = unsafe {
+            pg_sys::WinGetFuncArgInPartition(
+                self.winobj,
+                argno as i32,
+                relpos,
+                seek.as_seektype(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            )
+        };
+
+        if isout {
+            None
+        } else {
+            Some(unsafe { self.from_datum(argno, datum, isnull) })
+        }
+    }
+
+    /// Evaluate argument `argno` (starting at zero) on the row `relpos` rows away from `seek`
+    /// within the current window frame.
+    ///
+    /// Returns `None` if that row is outside the frame, and `Some(None)` if the argument was
+    /// NULL on that row.
+    pub fn get_func_arg_in_frame<T: FromDatum>(
+        &self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let mut isnull = false;
+        let mut isout = false;
+        let datum = unsafe {
+            pg_sys::WinGetFuncArgInFrame(
+                self.winobj,
+                argno as i32,
+                relpos,
+                seek.as_seektype(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            )
+        };
+
+        if isout {
+            None
+        } else {
+            Some(unsafe { self.from_datum(argno, datum, isnull) })
+        }
+    }
+
+    /// Evaluate argument `argno` (starting at zero) on the current row
+    pub fn get_func_arg_current<T: FromDatum>(&self, argno: usize) -> Option<T> {
+        let mut isnull = false;
+        let datum = unsafe { pg_sys::WinGetFuncArgCurrent(self.winobj, argno as i32, &mut isnull) };
+
+        unsafe { self.from_datum(argno, datum, isnull) }
+    }
+
+    /// Memory that lives as long as the current partition, zeroed when the partition starts.
+    ///
+    /// Window functions use it to carry state from one row of a partition to the next.
+    ///
+    /// ## Safety
+    ///
+    /// All-zero bytes must be a valid `T`, and every call made during the same partition must
+    /// use the same `T`.
+    pub unsafe fn partition_local_memory<T: Copy>(&mut self) -> &mut T {
+        let ptr = pg_sys::WinGetPartitionLocalMemory(self.winobj, std::mem::size_of::<T>());
+        (ptr as *mut T)
+            .as_mut()
+            .expect("WinGetPartitionLocalMemory returned null")
+    }
+
+    unsafe fn from_datum<T: FromDatum>(
+        &self,
+        argno: usize,
+        datum: pg_sys::Datum,
+        isnull: bool,
+    ) -> Option<T> {
+        let typid = if T::NEEDS_TYPID {
+            crate::get_getarg_type(self.fcinfo, argno)
+        } else {
+            pg_sys::InvalidOid
+        };
+        T::from_datum(datum, isnull, typid)
+    }
+}
+
+/// Was the function behind `fcinfo` called as a window function?
+#[inline]
+pub unsafe fn called_as_window_function(fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let fcinfo = fcinfo.as_ref().expect("fcinfo was null");
+    !fcinfo.context.is_null() && is_a(fcinfo.context, pg_sys::NodeTag_T_WindowObjectData)
+}
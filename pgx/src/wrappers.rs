@@ -7,7 +7,10 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 //! Provides safe wrapper functions around some of Postgres' useful functions.
-use crate::{direct_function_call, pg_sys, IntoDatum};
+use crate::{direct_function_call, pg_sys, IntoDatum, PgHeapTuple, PgRelation};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 /// A helper function for Postgres' `regtypein` function to lookup a type by a specific name
 ///
@@ -21,6 +24,107 @@ pub fn regtypein(type_name: &str) -> pg_sys::Oid {
     }
 }
 
+/// Look up the type named `type_name` in `schema`, rather than whichever the `search_path` finds
+/// first
+///
+/// `type_name` is the name as Postgres stores it, so lowercase unless it was quoted.  Will panic
+/// if Postgres can't find the type
+pub fn schema_regtypein(schema: &str, type_name: &str) -> pg_sys::Oid {
+    let schema = CString::new(schema).expect("specified schema has embedded NULL byte");
+    let type_name = CString::new(type_name).expect("specified type_name has embedded NULL byte");
+    let qualified = unsafe {
+        CStr::from_ptr(pg_sys::quote_qualified_identifier(
+            schema.as_ptr(),
+            type_name.as_ptr(),
+        ))
+    };
+    regtypein(
+        qualified
+            .to_str()
+            .expect("qualified type name is not valid UTF8"),
+    )
+}
+
+/// The name of the schema the extension `extname` was created in.  Will panic if it isn't
+/// installed
+pub fn extension_schema(extname: &str) -> String {
+    // the bindings don't include `commands/extension.h`, and `get_extension_schema()` is static,
+    // so this reads `pg_extension` the way it does
+    const EXTENSION_RELATION_ID: pg_sys::Oid = 3079;
+    extern "C" {
+        fn get_extension_oid(extname: *const c_char, missing_ok: bool) -> pg_sys::Oid;
+    }
+
+    let name = CString::new(extname).expect("specified extname has embedded NULL byte");
+    let extension = unsafe { get_extension_oid(name.as_ptr(), true) };
+    if extension == pg_sys::InvalidOid {
+        panic!("extension `{}` is not installed", extname);
+    }
+
+    let catalog = PgRelation::with_lock(EXTENSION_RELATION_ID, pg_sys::AccessShareLock as _);
+    let namespace = unsafe {
+        #[cfg(any(feature = "pg10", feature = "pg11"))]
+        let tuple = pg_sys::get_catalog_object_by_oid(catalog.as_ptr(), extension);
+        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+        let tuple = pg_sys::get_catalog_object_by_oid(catalog.as_ptr(), 1, extension);
+
+        PgHeapTuple::from_heap_tuple(catalog.tuple_desc(), tuple)
+            .get_by_name::<pg_sys::Oid>("extnamespace")
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("pg_extension.extnamespace is NULL")
+    };
+    unsafe { CStr::from_ptr(pg_sys::get_namespace_name(namespace)) }
+        .to_str()
+        .expect("schema name is not valid UTF8")
+        .to_string()
+}
+
+/// The oid of a type the extension created, which `#[pg_composite]` looks up by its
+/// schema-qualified name the first time it's needed in each transaction, as the extension may
+/// have been dropped and created again since the last.
+#[doc(hidden)]
+pub struct ExtensionTypeOid {
+    extname: &'static str,
+    schema: Option<&'static str>,
+    type_name: &'static str,
+    lxid: AtomicU32,
+    oid: AtomicU32,
+}
+
+impl ExtensionTypeOid {
+    /// The type named `type_name` in `schema`, or in the schema of the extension `extname`
+    pub const fn new(
+        extname: &'static str,
+        schema: Option<&'static str>,
+        type_name: &'static str,
+    ) -> Self {
+        ExtensionTypeOid {
+            extname,
+            schema,
+            type_name,
+            lxid: AtomicU32::new(pg_sys::InvalidLocalTransactionId),
+            oid: AtomicU32::new(pg_sys::InvalidOid),
+        }
+    }
+
+    pub fn get(&self) -> pg_sys::Oid {
+        let lxid = unsafe { pg_sys::MyProc.as_ref() }
+            .map(|proc| proc.lxid)
+            .unwrap_or(pg_sys::InvalidLocalTransactionId);
+        if lxid != pg_sys::InvalidLocalTransactionId && self.lxid.load(Ordering::Relaxed) == lxid {
+            return self.oid.load(Ordering::Relaxed);
+        }
+
+        let oid = match self.schema {
+            Some(schema) => schema_regtypein(schema, self.type_name),
+            None => schema_regtypein(&extension_schema(self.extname), self.type_name),
+        };
+        self.oid.store(oid, Ordering::Relaxed);
+        self.lxid.store(lxid, Ordering::Relaxed);
+        oid
+    }
+}
+
 /// A helper function for Postgres' `regtypein` function to lookup a type using the name of a Rust type
 ///
 /// We truncate the type name to its last value, unless its a primitive type.